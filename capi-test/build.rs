@@ -0,0 +1,24 @@
+//! Compiles the C programs that the tests call into, against the headers of
+//! the crates, which keeps the C compiler out of the crates' own builds
+
+fn main() {
+    for (src, include, header, lib) in [
+        (
+            "c/crash_context.c",
+            "../crash-context/include",
+            "crash_context.h",
+            "crash_context_layout_test",
+        ),
+        (
+            "c/crash_handler.c",
+            "../crash-handler/include",
+            "crash_handler.h",
+            "crash_handler_capi_test",
+        ),
+    ] {
+        println!("cargo:rerun-if-changed={src}");
+        println!("cargo:rerun-if-changed={include}/{header}");
+
+        cc::Build::new().file(src).include(include).compile(lib);
+    }
+}
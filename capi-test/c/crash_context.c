@@ -0,0 +1,47 @@
+// The C side of tests/crash_context.rs, which reports the layout of the structs in
+// the header so that it can be compared with the Rust layout
+
+#include "crash_context.h"
+
+#if defined(__APPLE__)
+typedef cc_RawCrashContext context_t;
+#else
+typedef cc_CrashContext context_t;
+#endif
+
+// Writes the offsets of the key fields, in the order documented by
+// tests/layout.rs, returning the size of the context
+size_t crash_context_c_layout(size_t offsets[9]) {
+    size_t i = 0;
+
+#if defined(__linux__) || defined(__ANDROID__)
+    offsets[i++] = offsetof(context_t, context);
+    offsets[i++] = offsetof(context_t, context.uc_mcontext);
+#if !defined(__arm__)
+    offsets[i++] = offsetof(context_t, float_state);
+#endif
+    offsets[i++] = offsetof(context_t, siginfo);
+    offsets[i++] = offsetof(context_t, pid);
+    offsets[i++] = offsetof(context_t, tid);
+#elif defined(_WIN32)
+    offsets[i++] = offsetof(context_t, exception_pointers);
+    offsets[i++] = offsetof(context_t, exception_code);
+    offsets[i++] = offsetof(context_t, process_id);
+    offsets[i++] = offsetof(context_t, thread_id);
+    offsets[i++] = offsetof(context_t, main_thread);
+#elif defined(__APPLE__)
+    offsets[i++] = offsetof(context_t, task);
+    offsets[i++] = offsetof(context_t, handler_thread);
+    offsets[i++] = offsetof(context_t, main_thread);
+    offsets[i++] = offsetof(context_t, exception);
+    offsets[i++] = offsetof(context_t, exception.subcode);
+#endif
+
+    offsets[i++] = offsetof(context_t, timestamps);
+    offsets[i++] = offsetof(context_t, thread_name);
+#if defined(__linux__) || defined(__ANDROID__)
+    offsets[i++] = offsetof(context_t, tls);
+#endif
+
+    return sizeof(context_t);
+}
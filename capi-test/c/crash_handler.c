@@ -0,0 +1,63 @@
+// The C program for tests/crash_handler.rs, which attaches the handler via the C API
+// and then crashes
+
+#include "crash_handler.h"
+
+#include <signal.h>
+
+#ifdef _WIN32
+#include <process.h>
+#define getpid _getpid
+#define EXPECTED_CODE ((int64_t)(int32_t)0xc0000005) // EXCEPTION_ACCESS_VIOLATION
+#elif defined(__APPLE__)
+#define EXPECTED_CODE 1 // EXC_BAD_ACCESS
+#else
+#define EXPECTED_CODE SIGSEGV
+#endif
+
+#ifndef _WIN32
+#include <unistd.h>
+#endif
+
+static int on_crash(const CrashContextC *context, void *user_data) {
+    int *crashes = (int *)user_data;
+    *crashes += 1;
+
+    int ok = context->context != NULL &&
+             context->process_id == (uint32_t)getpid() &&
+             context->code == EXPECTED_CODE &&
+             *crashes == 1;
+
+    // Exit with success rather than returning, as the process would otherwise
+    // still be killed by the crash
+    _exit(ok ? 0 : 1);
+}
+
+int crash_handler_capi_main(void) {
+    static int crashes = 0;
+
+    if (crash_handler_attach(NULL, NULL) != CRASH_HANDLER_ERR_INVALID_ARGUMENT) {
+        return 2;
+    }
+
+    if (crash_handler_attach(on_crash, &crashes) != CRASH_HANDLER_OK) {
+        return 3;
+    }
+
+    if (crash_handler_attach(on_crash, &crashes) != CRASH_HANDLER_ERR_ALREADY_ATTACHED) {
+        return 4;
+    }
+
+    // Reattaching after detaching works
+    crash_handler_detach();
+    crash_handler_detach();
+
+    if (crash_handler_attach(on_crash, &crashes) != CRASH_HANDLER_OK) {
+        return 5;
+    }
+
+    volatile int *null = NULL;
+    *null = 42;
+
+    return 6;
+}
@@ -0,0 +1,70 @@
+//! Calls `c/crash_context.c`, which is compiled against the C header, and
+//! verifies that its layout of the crash context matches the Rust one
+
+#![allow(unsafe_code)]
+
+use crash_context as cc;
+
+extern "C" {
+    fn crash_context_c_layout(offsets: *mut [usize; 9]) -> usize;
+}
+
+/// The offset of the field from the start of the context
+macro_rules! offset {
+    ($ctx:expr, $($field:tt)+) => {
+        // SAFETY: only the address of the field is taken, it is never read
+        unsafe { std::ptr::addr_of!((*$ctx).$($field)+) as usize - $ctx as usize }
+    };
+}
+
+#[test]
+fn header_matches_layout() {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "macos")] {
+            type Context = cc::RawCrashContext;
+        } else {
+            type Context = cc::CrashContext;
+        }
+    }
+
+    let ctx = std::mem::MaybeUninit::<Context>::uninit();
+    let ctx = ctx.as_ptr();
+
+    let mut expected = Vec::new();
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            expected.push(offset!(ctx, context));
+            expected.push(offset!(ctx, context.uc_mcontext));
+            #[cfg(not(target_arch = "arm"))]
+            expected.push(offset!(ctx, float_state));
+            expected.push(offset!(ctx, siginfo));
+            expected.push(offset!(ctx, pid));
+            expected.push(offset!(ctx, tid));
+        } else if #[cfg(target_os = "windows")] {
+            expected.push(offset!(ctx, exception_pointers));
+            expected.push(offset!(ctx, exception_code));
+            expected.push(offset!(ctx, process_id));
+            expected.push(offset!(ctx, thread_id));
+            expected.push(offset!(ctx, main_thread));
+        } else if #[cfg(target_os = "macos")] {
+            expected.push(offset!(ctx, task));
+            expected.push(offset!(ctx, handler_thread));
+            expected.push(offset!(ctx, main_thread));
+            expected.push(offset!(ctx, exception));
+            expected.push(offset!(ctx, exception.subcode));
+        }
+    }
+
+    expected.push(offset!(ctx, timestamps));
+    expected.push(offset!(ctx, thread_name));
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    expected.push(offset!(ctx, tls));
+
+    let mut offsets = [0; 9];
+    let size = unsafe { crash_context_c_layout(&mut offsets) };
+
+    assert_eq!(size, std::mem::size_of::<Context>());
+    assert_eq!(&offsets[..expected.len()], expected.as_slice());
+    assert!(cc::C_HEADER.contains("cc_CrashContext"));
+}
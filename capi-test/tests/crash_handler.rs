@@ -0,0 +1,19 @@
+//! Runs the C program in `c/crash_handler.c`, which attaches the handler via the
+//! C API and then crashes
+
+#![allow(unsafe_code)]
+
+// Links the crate, whose C API the C program calls
+use crash_handler as _;
+
+extern "C" {
+    fn crash_handler_capi_main() -> i32;
+}
+
+#[test]
+fn handles_crash_via_capi() {
+    // The program exits the process with 0 from its crash callback if all of
+    // its checks pass, so returning at all is a failure
+    let code = unsafe { crash_handler_capi_main() };
+    panic!("the C program returned {code} rather than crashing");
+}
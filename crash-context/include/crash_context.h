@@ -0,0 +1,359 @@
+/* SPDX-License-Identifier: MIT OR Apache-2.0 */
+
+#ifndef CRASH_CONTEXT_H
+#define CRASH_CONTEXT_H
+
+/* Generated by cbindgen from src, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#if defined(__linux__) || defined(__ANDROID__)
+#include <sys/signalfd.h>
+typedef struct signalfd_siginfo signalfd_siginfo;
+#elif defined(_WIN32)
+typedef struct _EXCEPTION_POINTERS EXCEPTION_POINTERS;
+#elif defined(__APPLE__)
+#include <mach/mach_types.h>
+#endif
+
+#if defined(__UINTPTR_MAX__) && __UINTPTR_MAX__ > 0xffffffff
+#define CRASH_CONTEXT_64BIT
+#elif defined(_WIN64)
+#define CRASH_CONTEXT_64BIT
+#else
+#define CRASH_CONTEXT_32BIT
+#endif
+
+// Clock readings captured at the time of a crash
+typedef struct cc_CrashTimestamps {
+  // A monotonic clock reading, in nanoseconds, taken when the crash was
+  // caught. This is only meaningful relative to other readings of the same
+  // clock on the same machine, eg. to order crashes.
+  //
+  // * Linux/Android - `CLOCK_MONOTONIC`
+  // * Windows - `QueryPerformanceCounter`
+  // * Macos - `mach_absolute_time`
+  uint64_t monotonic_ns;
+  // The wall clock time, in nanoseconds since the Unix epoch, taken when the
+  // crash was caught
+  uint64_t realtime_ns;
+  // The wall clock time, in nanoseconds since the Unix epoch, at which the
+  // crashed process started, or 0 if it is unknown
+  uint64_t process_start_ns;
+} cc_CrashTimestamps;
+
+// The name of a thread, captured at the time of a crash.
+//
+// This is a fixed size, nul padded, UTF-8 buffer so that it can be captured
+// without allocating and sent as-is to another process. The largest thread
+// name supported by the OS is 64 bytes on Macos, Linux only supports 16, and
+// names on Windows are truncated to fit.
+typedef struct cc_ThreadName {
+  uint8_t _0[64];
+} cc_ThreadName;
+
+// Whether the crashing thread is the main thread of the process, in a form
+// that can be embedded in the `#[repr(C)]` crash contexts, and sent as-is to
+// another process.
+//
+// Any value other than [`Self::MAIN`] or [`Self::OTHER`], eg. one from a
+// context written by an older version, means it is unknown.
+typedef struct cc_MainThread {
+  uint8_t _0;
+} cc_MainThread;
+
+#if (defined(__linux__) || defined(__ANDROID__))
+// The registers locating the thread local storage of the crashing thread,
+// which is useful when debugging crashes caused by TLS corruption
+typedef struct cc_TlsRegisters {
+  // The thread pointer, ie. the base address of the thread's TLS block, the
+  // same value as `__builtin_thread_pointer()`
+  //
+  // * `x86_64` - `fs_base`
+  // * `x86` - The base of the `gs` segment
+  // * `aarch64` - `TPIDR_EL0`
+  // * `arm` - `TPIDRURO`
+  uint64_t thread_pointer;
+  // The base of the `gs` segment on `x86_64`, which isn't used for TLS by
+  // Linux userspace, but can be set by eg. runtimes via `arch_prctl`.
+  //
+  // Always 0 on other architectures.
+  uint64_t gs_base;
+} cc_TlsRegisters;
+#endif
+
+#if (defined(__linux__) || defined(__ANDROID__))
+typedef struct cc_stack_t {
+  void *ss_sp;
+  int32_t ss_flags;
+  size_t ss_size;
+} cc_stack_t;
+#endif
+
+#if (defined(__linux__) || defined(__ANDROID__))
+typedef struct cc_sigset_t {
+#if defined(CRASH_CONTEXT_32BIT)
+  uint32_t __val[32];
+#endif
+#if defined(CRASH_CONTEXT_64BIT)
+  uint64_t __val[16];
+#endif
+} cc_sigset_t;
+#endif
+
+#if (defined(__linux__) || defined(__ANDROID__))
+#if defined(__x86_64__)
+typedef struct cc_fpregset_t {
+  uint16_t cwd;
+  uint16_t swd;
+  uint16_t ftw;
+  uint16_t fop;
+  uint64_t rip;
+  uint64_t rdp;
+  uint32_t mxcsr;
+  uint32_t mxcr_mask;
+  uint32_t st_space[32];
+  uint32_t xmm_space[64];
+  uint64_t __padding[12];
+} cc_fpregset_t;
+
+typedef struct cc_mcontext_t {
+  int64_t gregs[23];
+  struct cc_fpregset_t *fpregs;
+  uint64_t __reserved[8];
+} cc_mcontext_t;
+
+typedef struct cc_ucontext_t {
+  uint64_t uc_flags;
+  struct cc_ucontext_t *uc_link;
+  struct cc_stack_t uc_stack;
+  struct cc_mcontext_t uc_mcontext;
+  struct cc_sigset_t uc_sigmask;
+  uint8_t __private[512];
+} cc_ucontext_t;
+#elif defined(__i386__)
+typedef struct cc_fpreg_t {
+  uint16_t significand[4];
+  uint16_t exponent;
+} cc_fpreg_t;
+
+typedef struct cc_fpregset_t {
+  uint32_t cw;
+  uint32_t sw;
+  uint32_t tag;
+  uint32_t ipoff;
+  uint32_t cssel;
+  uint32_t dataoff;
+  uint32_t datasel;
+  struct cc_fpreg_t _st[8];
+  uint32_t status;
+} cc_fpregset_t;
+
+typedef struct cc_mcontext_t {
+  int32_t gregs[23];
+  struct cc_fpregset_t *fpregs;
+  uint32_t oldmask;
+  uint32_t cr2;
+} cc_mcontext_t;
+
+typedef struct cc_ucontext_t {
+  uint32_t uc_flags;
+  struct cc_ucontext_t *uc_link;
+  struct cc_stack_t uc_stack;
+  struct cc_mcontext_t uc_mcontext;
+  struct cc_sigset_t uc_sigmask;
+  uint32_t __fpregs_mem[28];
+} cc_ucontext_t;
+#elif defined(__aarch64__)
+// Magic value written by the kernel and our custom getcontext
+#define cc_FPSIMD_MAGIC 1179680769
+
+typedef struct cc_mcontext_t {
+  uint64_t fault_address;
+  uint64_t regs[31];
+  uint64_t sp;
+  uint64_t pc;
+  uint64_t pstate;
+  __uint128_t __reserved[256];
+} cc_mcontext_t;
+
+typedef struct cc_ucontext_t {
+  uint64_t uc_flags;
+  struct cc_ucontext_t *uc_link;
+  struct cc_stack_t uc_stack;
+  struct cc_sigset_t uc_sigmask;
+  struct cc_mcontext_t uc_mcontext;
+} cc_ucontext_t;
+
+typedef struct cc__aarch64_ctx {
+  uint32_t magic;
+  uint32_t size;
+} cc__aarch64_ctx;
+
+typedef struct cc_fpsimd_context {
+  struct cc__aarch64_ctx head;
+  uint32_t fpsr;
+  uint32_t fpcr;
+  __uint128_t vregs[32];
+} cc_fpsimd_context;
+
+typedef struct cc_fpsimd_context cc_fpregset_t;
+#elif defined(__arm__)
+typedef struct cc_mcontext_t {
+  uint32_t trap_no;
+  uint32_t error_code;
+  uint32_t oldmask;
+  uint32_t arm_r0;
+  uint32_t arm_r1;
+  uint32_t arm_r2;
+  uint32_t arm_r3;
+  uint32_t arm_r4;
+  uint32_t arm_r5;
+  uint32_t arm_r6;
+  uint32_t arm_r7;
+  uint32_t arm_r8;
+  uint32_t arm_r9;
+  uint32_t arm_r10;
+  uint32_t arm_fp;
+  uint32_t arm_ip;
+  uint32_t arm_sp;
+  uint32_t arm_lr;
+  uint32_t arm_pc;
+  uint32_t arm_cpsr;
+  uint32_t fault_address;
+} cc_mcontext_t;
+
+typedef struct cc_ucontext_t {
+  uint32_t uc_flags;
+  struct cc_ucontext_t *uc_link;
+  struct cc_stack_t uc_stack;
+  struct cc_mcontext_t uc_mcontext;
+  struct cc_sigset_t uc_sigmask;
+  uint64_t uc_regspace[64];
+} cc_ucontext_t;
+#endif
+#endif
+
+#if (defined(__linux__) || defined(__ANDROID__))
+// The full context for a Linux/Android crash
+//
+// The layout is stable, ie. the fields are laid out in declaration order per
+// `#[repr(C)]`, and is described for C by `include/crash_context.h`
+typedef struct cc_CrashContext {
+  // Crashing thread context.
+  //
+  // Note that we use [`crate::ucontext_t`] instead of [`libc::ucontext_t`]
+  // as libc's differs between glibc and musl <https://github.com/rust-lang/libc/pull/1646>
+  // even though the `ucontext_t` received from a signal will be the same
+  // regardless of the libc implementation used as it is only arch specific
+  // and not libc specific
+  //
+  // Note that we hide `ucontext_t::uc_link` as it is a pointer and thus can't
+  // be accessed in a process other than the one the `CrashContext` was created
+  // in. This is a just a self-reference so is not useful in practice.
+  //
+  // Note that the same applies to [`mcontext_t::fpregs`], but since that points
+  // to floating point registers and _is_ interesting to read in another process,
+  // those registers available as [`Self::float_state`], except on the `arm`
+  // architecture since they aren't part of `mcontext_t` at all.
+  struct cc_ucontext_t context;
+#if !defined(__arm__)
+  // State of floating point registers.
+  //
+  // This isn't part of the user ABI for Linux arm
+  cc_fpregset_t float_state;
+#endif
+  // The signal info for the crash
+  signalfd_siginfo siginfo;
+  // The id of the crashing process
+  int32_t pid;
+  // The id of the crashing thread
+  int32_t tid;
+  // The clock readings when the crash was caught
+  struct cc_CrashTimestamps timestamps;
+  // The name of the crashing thread, via `PR_GET_NAME`
+  struct cc_ThreadName thread_name;
+  // The thread local storage registers of the crashing thread, which
+  // aren't captured by [`Self::context`] on every architecture
+  struct cc_TlsRegisters tls;
+} cc_CrashContext;
+#endif
+
+#if defined(_WIN32)
+// Full Windows crash context
+//
+// The layout is stable, ie. the fields are laid out in declaration order per
+// `#[repr(C)]`, and is described for C by `include/crash_context.h`
+typedef struct cc_CrashContext {
+  // The information on the exception.
+  //
+  // Note that this is a pointer into the actual memory of the crashed process,
+  // and is a pointer to an [EXCEPTION_POINTERS](https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-exception_pointers)
+  const EXCEPTION_POINTERS *exception_pointers;
+  // The top level exception code from the `exception_pointers`. This is provided
+  // so that external processes don't need to use `ReadProcessMemory` to inspect
+  // the exception code
+  int32_t exception_code;
+  // The pid of the process that crashed
+  uint32_t process_id;
+  // The thread id on which the exception occurred
+  uint32_t thread_id;
+  // Whether [`Self::thread_id`] is the main thread, see
+  // [`Self::is_main_thread`]
+  struct cc_MainThread main_thread;
+  // The clock readings when the exception was caught
+  struct cc_CrashTimestamps timestamps;
+  // The description of the thread on which the exception occurred, via
+  // `GetThreadDescription`
+  struct cc_ThreadName thread_name;
+} cc_CrashContext;
+#endif
+
+#if defined(__APPLE__)
+// There is an exception
+#define cc_RawExceptionInfo_HAS_EXCEPTION 1
+
+// The exception has a subcode
+#define cc_RawExceptionInfo_HAS_SUBCODE 2
+
+// A `#[repr(C)]` equivalent of an `Option<ExceptionInfo>`, where the
+// presence of the exception and its subcode are indicated by [`Self::flags`]
+typedef struct cc_RawExceptionInfo {
+  // Combination of [`Self::HAS_EXCEPTION`] and [`Self::HAS_SUBCODE`]
+  uint32_t flags;
+  // [`ExceptionInfo::kind`]
+  uint32_t kind;
+  // [`ExceptionInfo::code`]
+  uint64_t code;
+  // [`ExceptionInfo::subcode`], 0 if not present
+  uint64_t subcode;
+} cc_RawExceptionInfo;
+
+// A `#[repr(C)]` equivalent of a [`CrashContext`], for use from other
+// languages, see `include/crash_context.h`.
+//
+// The fields are in the same order as [`CrashContext`], except that the
+// [`CrashContext::thread_state`] is not included.
+typedef struct cc_RawCrashContext {
+  // [`CrashContext::task`]
+  task_t task;
+  // [`CrashContext::thread`]
+  thread_t thread;
+  // [`CrashContext::handler_thread`]
+  thread_t handler_thread;
+  // [`CrashContext::main_thread`]
+  struct cc_MainThread main_thread;
+  // [`CrashContext::exception`]
+  struct cc_RawExceptionInfo exception;
+  // [`CrashContext::timestamps`]
+  struct cc_CrashTimestamps timestamps;
+  // [`CrashContext::thread_name`]
+  struct cc_ThreadName thread_name;
+} cc_RawCrashContext;
+#endif
+
+#endif /* CRASH_CONTEXT_H */
@@ -20,9 +20,39 @@
 //! provides a `Client` and `Server` that can be used to send and receive a
 //! [`CrashContext`] across processes so that you don't have to suffer like I
 //! did.
+//!
+//! ## Other targets
+//!
+//! On every other target, eg. `wasm32-unknown-unknown`, the [`CrashContext`]
+//! is a placeholder that only contains the timestamps and thread name, so that
+//! crates passing it around compile for those targets as well.
+//!
+//! ## `no_std`
+//!
+//! The types are plain data, so, with the default `std` feature disabled, the
+//! crate only depends on `core` and `alloc`, eg. so that a serialized
+//! [`CrashContext`] can be parsed by a static tool without a full libc. The
+//! pieces that need `std`, ie. the Macos IPC, and reading the state of other
+//! threads or processes on Windows, are only available with the feature.
 
 // crate-specific exceptions:
 #![allow(unsafe_code, nonstandard_style)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+/// The C header describing the layout of the [`CrashContext`] for each
+/// platform, generated by running `cbindgen --config cbindgen.toml --crate crash-context --output include/crash_context.h`
+/// in the crate root
+#[cfg(feature = "capi")]
+pub const C_HEADER: &str = include_str!("../include/crash_context.h");
+
+mod main_thread;
+mod thread_name;
+mod timestamps;
+pub use main_thread::MainThread;
+pub use thread_name::ThreadName;
+pub use timestamps::CrashTimestamps;
 
 cfg_if::cfg_if! {
     if #[cfg(any(target_os = "linux", target_os = "android"))] {
@@ -34,5 +64,8 @@ cfg_if::cfg_if! {
     } else if #[cfg(target_os = "macos")] {
         mod mac;
         pub use mac::*;
+    } else {
+        mod stub;
+        pub use stub::*;
     }
 }
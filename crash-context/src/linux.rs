@@ -1,8 +1,13 @@
 mod getcontext;
+mod tls;
 
 pub use getcontext::crash_context_getcontext;
+pub use tls::TlsRegisters;
 
 /// The full context for a Linux/Android crash
+///
+/// The layout is stable, ie. the fields are laid out in declaration order per
+/// `#[repr(C)]`, and is described for C by `include/crash_context.h`
 #[repr(C)]
 #[derive(Clone)]
 pub struct CrashContext {
@@ -34,28 +39,107 @@ pub struct CrashContext {
     pub pid: libc::pid_t,
     /// The id of the crashing thread
     pub tid: libc::pid_t,
+    /// The clock readings when the crash was caught
+    pub timestamps: crate::CrashTimestamps,
+    /// The name of the crashing thread, via `PR_GET_NAME`
+    pub thread_name: crate::ThreadName,
+    /// The thread local storage registers of the crashing thread, which
+    /// aren't captured by [`Self::context`] on every architecture
+    pub tls: TlsRegisters,
 }
 
 unsafe impl Send for CrashContext {}
 
+// The layout is part of the C API, see `include/crash_context.h`
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        const _: () = assert!(core::mem::size_of::<CrashContext>() == 1688);
+    } else if #[cfg(target_arch = "x86")] {
+        const _: () = assert!(core::mem::size_of::<CrashContext>() == 716);
+    } else if #[cfg(target_arch = "aarch64")] {
+        const _: () = assert!(core::mem::size_of::<CrashContext>() == 5328);
+    } else if #[cfg(target_arch = "arm")] {
+        const _: () = assert!(core::mem::size_of::<CrashContext>() == 984);
+    }
+}
+
 impl CrashContext {
     pub fn as_bytes(&self) -> &[u8] {
         unsafe {
-            let size = std::mem::size_of_val(self);
+            let size = core::mem::size_of_val(self);
             let ptr = (self as *const Self).cast();
-            std::slice::from_raw_parts(ptr, size)
+            core::slice::from_raw_parts(ptr, size)
         }
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        if bytes.len() != std::mem::size_of::<Self>() {
+        if bytes.len() != core::mem::size_of::<Self>() {
+            return None;
+        }
+
+        // The buffer can come from anywhere, eg. an IPC message, so we can't
+        // assume it is aligned
+        unsafe { Some(bytes.as_ptr().cast::<Self>().read_unaligned()) }
+    }
+
+    /// The OS id of the crashing thread
+    #[inline]
+    pub fn crashing_thread_id(&self) -> u64 {
+        self.tid as u64
+    }
+
+    /// Whether the crash occurred on the main thread, ie. the thread whose
+    /// id is the same as the process's, which is always known
+    #[inline]
+    pub fn is_main_thread(&self) -> Option<bool> {
+        Some(self.tid == self.pid)
+    }
+
+    /// Whether the crash was caused by the kernel detecting a hardware memory
+    /// failure, eg. an uncorrectable ECC error, which indicates a problem with
+    /// the machine rather than the software that crashed
+    #[inline]
+    pub fn is_memory_failure(&self) -> bool {
+        self.memory_failure().is_some()
+    }
+
+    /// Retrieves the details of a hardware memory failure, if that is what
+    /// caused the crash, ie. a `SIGBUS` with a `si_code` of `BUS_MCEERR_AR` or
+    /// `BUS_MCEERR_AO`
+    pub fn memory_failure(&self) -> Option<MemoryFailure> {
+        if self.siginfo.ssi_signo != libc::SIGBUS as u32 {
             return None;
         }
 
-        unsafe { Some((*bytes.as_ptr().cast::<Self>()).clone()) }
+        let action_required = match self.siginfo.ssi_code {
+            libc::BUS_MCEERR_AR => true,
+            libc::BUS_MCEERR_AO => false,
+            _ => return None,
+        };
+
+        Some(MemoryFailure {
+            address: self.siginfo.ssi_addr,
+            addr_lsb: self.siginfo.ssi_addr_lsb,
+            action_required,
+        })
     }
 }
 
+/// The details of a hardware memory failure reported by the kernel, see
+/// [`CrashContext::memory_failure`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MemoryFailure {
+    /// The address of the corrupted memory
+    pub address: u64,
+    /// The least significant bit of the address, ie. the corrupted memory is
+    /// the `1 << addr_lsb` bytes that contain [`Self::address`], typically a
+    /// whole page
+    pub addr_lsb: u16,
+    /// True if the corrupted memory was consumed by the process (`BUS_MCEERR_AR`),
+    /// false if the failure was only detected (`BUS_MCEERR_AO`)
+    pub action_required: bool,
+}
+
 #[repr(C)]
 #[derive(Clone)]
 #[doc(hidden)]
@@ -70,7 +154,7 @@ pub struct sigset_t {
 #[derive(Clone)]
 #[doc(hidden)]
 pub struct stack_t {
-    pub ss_sp: *mut std::ffi::c_void,
+    pub ss_sp: *mut core::ffi::c_void,
     pub ss_flags: i32,
     pub ss_size: usize,
 }
@@ -270,4 +354,42 @@ mod test {
             std::mem::size_of::<super::ucontext_t>()
         );
     }
+
+    #[test]
+    #[allow(unsafe_code)]
+    fn memory_failure_round_trips() {
+        let mut cc: super::CrashContext = unsafe { std::mem::zeroed() };
+        cc.siginfo.ssi_signo = libc::SIGBUS as u32;
+        cc.siginfo.ssi_code = libc::BUS_MCEERR_AR;
+        cc.siginfo.ssi_addr = 0xdead_b000;
+        cc.siginfo.ssi_addr_lsb = 12;
+
+        // Offset the bytes so that they aren't aligned, like they might not be
+        // when received over IPC
+        let mut buf = vec![0u8; 1];
+        buf.extend_from_slice(cc.as_bytes());
+        let cc = super::CrashContext::from_bytes(&buf[1..]).unwrap();
+
+        assert_eq!(
+            cc.memory_failure(),
+            Some(super::MemoryFailure {
+                address: 0xdead_b000,
+                addr_lsb: 12,
+                action_required: true,
+            })
+        );
+
+        let mut cc = cc;
+        cc.siginfo.ssi_code = libc::BUS_MCEERR_AO;
+        assert!(!cc.memory_failure().unwrap().action_required);
+
+        // A plain bus error is a software problem
+        cc.siginfo.ssi_code = libc::BUS_ADRERR;
+        assert!(!cc.is_memory_failure());
+
+        // As is any other signal that happens to share the code
+        cc.siginfo.ssi_signo = libc::SIGSEGV as u32;
+        cc.siginfo.ssi_code = libc::BUS_MCEERR_AR;
+        assert!(!cc.is_memory_failure());
+    }
 }
@@ -2,7 +2,7 @@
 // REGISTER_SIZE = 8
 // SIMD_REGISTER_SIZE = 16
 
-std::arch::global_asm! {
+core::arch::global_asm! {
     ".text",
     ".global crash_context_getcontext",
     ".hidden crash_context_getcontext",
@@ -47,12 +47,13 @@ std::arch::global_asm! {
     "mov     w3, #528", // FPSIMD_CONTEXT_SIZE
     "str     w3, [x2, #4]", // FPSIMD_CONTEXT_SIZE_OFFSET
 
-    // Fill in the FP SIMD context.
+    // Fill in the FP SIMD context. Each vreg is a full 128-bit q register, of
+    // which the callee-saved d8-d15 are the low halves
     "add     x3, x2, #144", // VREGS_OFFSET + 8 * SIMD_REGISTER_SIZE
-    "stp     d8,  d9, [x3], #32",
-    "stp     d10, d11, [x3], #32",
-    "stp     d12, d13, [x3], #32",
-    "stp     d14, d15, [x3], #32",
+    "stp     q8,  q9, [x3], #32",
+    "stp     q10, q11, [x3], #32",
+    "stp     q12, q13, [x3], #32",
+    "stp     q14, q15, [x3], #32",
 
     "add     x3, x2, 8", // FPSR_OFFSET
 
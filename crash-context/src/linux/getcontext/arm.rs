@@ -1,4 +1,4 @@
-std::arch::global_asm! {
+core::arch::global_asm! {
     ".text",
     ".global crash_context_getcontext",
     ".hidden crash_context_getcontext",
@@ -9,7 +9,7 @@
 // or not :(
 macro_rules! asm_func {
     ($offset:expr) => {
-        std::arch::global_asm! {
+        core::arch::global_asm! {
             ".text",
             ".global crash_context_getcontext",
             ".hidden crash_context_getcontext",
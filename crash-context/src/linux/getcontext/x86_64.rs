@@ -45,7 +45,7 @@ WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.  */
 // or not :(
 macro_rules! asm_func {
     ($offset:expr) => {
-        std::arch::global_asm! {
+        core::arch::global_asm! {
             ".text",
             ".global crash_context_getcontext",
             ".hidden crash_context_getcontext",
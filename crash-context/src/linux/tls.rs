@@ -0,0 +1,109 @@
+//! Reads the registers that locate the thread local storage of a thread, which
+//! aren't reliably part of the `ucontext_t` the kernel passes to a signal
+//! handler, eg. `fs_base` isn't part of `mcontext_t` on `x86_64`, and
+//! `TPIDR_EL0` isn't in the signal frame at all on `aarch64`
+
+/// The registers locating the thread local storage of the crashing thread,
+/// which is useful when debugging crashes caused by TLS corruption
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsRegisters {
+    /// The thread pointer, ie. the base address of the thread's TLS block, the
+    /// same value as `__builtin_thread_pointer()`
+    ///
+    /// * `x86_64` - `fs_base`
+    /// * `x86` - The base of the `gs` segment
+    /// * `aarch64` - `TPIDR_EL0`
+    /// * `arm` - `TPIDRURO`
+    pub thread_pointer: u64,
+    /// The base of the `gs` segment on `x86_64`, which isn't used for TLS by
+    /// Linux userspace, but can be set by eg. runtimes via `arch_prctl`.
+    ///
+    /// Always 0 on other architectures.
+    pub gs_base: u64,
+}
+
+impl TlsRegisters {
+    /// Reads the registers of the calling thread.
+    ///
+    /// This is async signal safe, so can be called in a signal handler, where
+    /// the calling thread is the crashing one for synchronous signals. Any
+    /// register that can't be read is 0.
+    pub fn current() -> Self {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "x86_64")] {
+                // `asm/prctl.h`, which libc doesn't bind
+                const ARCH_GET_FS: i32 = 0x1003;
+                const ARCH_GET_GS: i32 = 0x1004;
+
+                let mut regs = Self::default();
+
+                // `rdfsbase` would avoid the syscalls, but is only usable if
+                // both the CPU and kernel support `FSGSBASE`
+                // SAFETY: syscalls, which only write the u64s they are given
+                unsafe {
+                    libc::syscall(libc::SYS_arch_prctl, ARCH_GET_FS, &mut regs.thread_pointer as *mut u64);
+                    libc::syscall(libc::SYS_arch_prctl, ARCH_GET_GS, &mut regs.gs_base as *mut u64);
+                }
+
+                regs
+            } else if #[cfg(target_arch = "x86")] {
+                /// `struct user_desc` from `asm/ldt.h`
+                #[repr(C)]
+                struct UserDesc {
+                    entry_number: u32,
+                    base_addr: u32,
+                    limit: u32,
+                    flags: u32,
+                }
+
+                let selector: u16;
+                // SAFETY: only reads the segment selector
+                unsafe {
+                    core::arch::asm!("mov {0:x}, gs", out(reg) selector, options(nomem, nostack, preserves_flags));
+                }
+
+                // The TLS segment is a GDT entry installed via
+                // `set_thread_area`, whose index is the top 13 bits of the
+                // selector
+                let mut desc = UserDesc {
+                    entry_number: u32::from(selector >> 3),
+                    base_addr: 0,
+                    limit: 0,
+                    flags: 0,
+                };
+
+                // SAFETY: syscall, which only writes to the descriptor
+                let res = unsafe { libc::syscall(libc::SYS_get_thread_area, &mut desc as *mut UserDesc) };
+
+                Self {
+                    thread_pointer: if res == 0 { u64::from(desc.base_addr) } else { 0 },
+                    gs_base: 0,
+                }
+            } else if #[cfg(target_arch = "aarch64")] {
+                let thread_pointer: u64;
+                // SAFETY: only reads the register
+                unsafe {
+                    core::arch::asm!("mrs {}, tpidr_el0", out(reg) thread_pointer, options(nomem, nostack, preserves_flags));
+                }
+
+                Self {
+                    thread_pointer,
+                    gs_base: 0,
+                }
+            } else if #[cfg(target_arch = "arm")] {
+                let thread_pointer: u32;
+                // SAFETY: only reads the register, which is available from
+                // ARMv6K onwards
+                unsafe {
+                    core::arch::asm!("mrc p15, 0, {}, c13, c0, 3", out(reg) thread_pointer, options(nomem, nostack, preserves_flags));
+                }
+
+                Self {
+                    thread_pointer: u64::from(thread_pointer),
+                    gs_base: 0,
+                }
+            }
+        }
+    }
+}
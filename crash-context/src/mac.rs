@@ -1,11 +1,13 @@
 pub mod guard;
+#[cfg(feature = "std")]
 pub mod ipc;
 pub mod resource;
+pub mod thread_state;
 
 use mach2::mach_types as mt;
 
 /// Information on the exception that caused the crash
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct ExceptionInfo {
     /// The exception kind
     pub kind: u32,
@@ -18,6 +20,113 @@ pub struct ExceptionInfo {
     pub subcode: Option<u64>,
 }
 
+/// A `#[repr(C)]` equivalent of an `Option<ExceptionInfo>`, where the
+/// presence of the exception and its subcode are indicated by [`Self::flags`]
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RawExceptionInfo {
+    /// Combination of [`Self::HAS_EXCEPTION`] and [`Self::HAS_SUBCODE`]
+    pub flags: u32,
+    /// [`ExceptionInfo::kind`]
+    pub kind: u32,
+    /// [`ExceptionInfo::code`]
+    pub code: u64,
+    /// [`ExceptionInfo::subcode`], 0 if not present
+    pub subcode: u64,
+}
+
+impl RawExceptionInfo {
+    /// There is an exception
+    pub const HAS_EXCEPTION: u32 = 0x1;
+    /// The exception has a subcode
+    pub const HAS_SUBCODE: u32 = 0x2;
+}
+
+impl From<Option<ExceptionInfo>> for RawExceptionInfo {
+    fn from(exc: Option<ExceptionInfo>) -> Self {
+        match exc {
+            Some(exc) => Self {
+                flags: Self::HAS_EXCEPTION
+                    | if exc.subcode.is_some() {
+                        Self::HAS_SUBCODE
+                    } else {
+                        0
+                    },
+                kind: exc.kind,
+                code: exc.code,
+                subcode: exc.subcode.unwrap_or_default(),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+impl From<RawExceptionInfo> for Option<ExceptionInfo> {
+    fn from(raw: RawExceptionInfo) -> Self {
+        (raw.flags & RawExceptionInfo::HAS_EXCEPTION != 0).then_some(ExceptionInfo {
+            kind: raw.kind,
+            code: raw.code,
+            subcode: (raw.flags & RawExceptionInfo::HAS_SUBCODE != 0).then_some(raw.subcode),
+        })
+    }
+}
+
+/// A `#[repr(C)]` equivalent of a [`CrashContext`], for use from other
+/// languages, see `include/crash_context.h`.
+///
+/// The fields are in the same order as [`CrashContext`], except that the
+/// [`CrashContext::thread_state`] is not included.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct RawCrashContext {
+    /// [`CrashContext::task`]
+    pub task: mt::task_t,
+    /// [`CrashContext::thread`]
+    pub thread: mt::thread_t,
+    /// [`CrashContext::handler_thread`]
+    pub handler_thread: mt::thread_t,
+    /// [`CrashContext::main_thread`]
+    pub main_thread: crate::MainThread,
+    /// [`CrashContext::exception`]
+    pub exception: RawExceptionInfo,
+    /// [`CrashContext::timestamps`]
+    pub timestamps: crate::CrashTimestamps,
+    /// [`CrashContext::thread_name`]
+    pub thread_name: crate::ThreadName,
+}
+
+// The layout is part of the C API
+const _: () = assert!(core::mem::size_of::<RawCrashContext>() == 128);
+
+impl From<&CrashContext> for RawCrashContext {
+    fn from(cc: &CrashContext) -> Self {
+        Self {
+            task: cc.task,
+            thread: cc.thread,
+            handler_thread: cc.handler_thread,
+            main_thread: cc.main_thread,
+            exception: cc.exception.into(),
+            timestamps: cc.timestamps,
+            thread_name: cc.thread_name,
+        }
+    }
+}
+
+impl From<RawCrashContext> for CrashContext {
+    fn from(raw: RawCrashContext) -> Self {
+        Self {
+            task: raw.task,
+            thread: raw.thread,
+            handler_thread: raw.handler_thread,
+            main_thread: raw.main_thread,
+            exception: raw.exception.into(),
+            timestamps: raw.timestamps,
+            thread_name: raw.thread_name,
+            thread_state: None,
+        }
+    }
+}
+
 /// Full Macos crash context
 #[derive(Debug)]
 pub struct CrashContext {
@@ -27,6 +136,76 @@ pub struct CrashContext {
     pub thread: mt::thread_t,
     /// The thread that handled the exception. This may be useful to ignore.
     pub handler_thread: mt::thread_t,
+    /// Whether [`Self::thread`] is the main thread, see
+    /// [`Self::is_main_thread`]
+    pub main_thread: crate::MainThread,
     /// Optional exception information
     pub exception: Option<ExceptionInfo>,
+    /// The clock readings when the exception was caught
+    pub timestamps: crate::CrashTimestamps,
+    /// The name of the thread that crashed, via `pthread_getname_np`
+    pub thread_name: crate::ThreadName,
+    /// The CPU state of the crashed thread at the time of the exception.
+    ///
+    /// This is only captured by the exception handler if requested when it is
+    /// attached, and is never sent over IPC, as it can be retrieved directly
+    /// from the crashed thread by the receiving process.
+    pub thread_state: Option<thread_state::ThreadState>,
+}
+
+extern "C" {
+    /// From `<mach/thread_act.h>`, there is no binding for this in mach2
+    fn thread_info(
+        target_act: mt::thread_t,
+        flavor: u32,
+        thread_info_out: *mut u32,
+        thread_info_out_count: *mut u32,
+    ) -> mach2::kern_return::kern_return_t;
+}
+
+impl CrashContext {
+    /// The OS id of the crashing thread, ie. the one returned by
+    /// `pthread_threadid_np`, rather than the thread's port, which is only
+    /// meaningful to the task that holds it
+    ///
+    /// Returns 0, which is never a valid thread id, if the thread has already
+    /// died.
+    pub fn crashing_thread_id(&self) -> u64 {
+        /// `THREAD_IDENTIFIER_INFO`
+        const THREAD_IDENTIFIER_INFO: u32 = 4;
+
+        /// `thread_identifier_info_data_t`
+        #[repr(C)]
+        struct ThreadIdentifierInfo {
+            thread_id: u64,
+            thread_handle: u64,
+            dispatch_qaddr: u64,
+        }
+
+        // SAFETY: syscall
+        unsafe {
+            let mut info: ThreadIdentifierInfo = core::mem::zeroed();
+            let mut count = (core::mem::size_of::<ThreadIdentifierInfo>() / 4) as u32;
+
+            if thread_info(
+                self.thread,
+                THREAD_IDENTIFIER_INFO,
+                (&mut info as *mut ThreadIdentifierInfo).cast(),
+                &mut count,
+            ) == mach2::kern_return::KERN_SUCCESS
+            {
+                info.thread_id
+            } else {
+                0
+            }
+        }
+    }
+
+    /// Whether the crash occurred on the main thread, if known, which is
+    /// the thread that the crash handler was attached from, if it was the
+    /// main thread, unless another thread was set as the main one
+    #[inline]
+    pub fn is_main_thread(&self) -> Option<bool> {
+        self.main_thread.get()
+    }
 }
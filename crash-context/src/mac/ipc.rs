@@ -23,8 +23,20 @@ use std::{ffi::CStr, time::Duration};
 extern "C" {
     /// From `<usr/include/mach/mach_traps.h>`, there is no binding for this in mach2
     pub fn pid_for_task(task: port::mach_port_name_t, pid: *mut i32) -> kern_return_t;
+    /// From `<usr/include/mach/mach_error.h>`, there is no binding for this in mach2
+    fn mach_error_string(error_value: i32) -> *const std::os::raw::c_char;
 }
 
+/// The name was not registered with the bootstrap server, eg. because the
+/// [`Server`] has not been created (yet), see [`Error::is_unknown_service`]
+pub const BOOTSTRAP_UNKNOWN_SERVICE: kern_return_t = bootstrap::BOOTSTRAP_UNKNOWN_SERVICE as _;
+/// A send timed out before the message could be queued, see
+/// [`Error::is_send_timed_out`]
+pub const MACH_SEND_TIMED_OUT: mach_msg_return_t = msg::MACH_SEND_TIMED_OUT;
+/// A received message was larger than the receive buffer, see
+/// [`Error::is_receive_too_large`]
+pub const MACH_RCV_TOO_LARGE: mach_msg_return_t = msg::MACH_RCV_TOO_LARGE;
+
 /// <https://github.com/apple-oss-distributions/xnu/blob/e6231be02a03711ca404e5121a151b24afbff733/osfmk/mach/message.h#L379-L391>
 #[repr(C, packed(4))]
 struct MachMsgPortDescriptor {
@@ -90,7 +102,7 @@ struct CrashContextMessage {
     handler_thread: MachMsgPortDescriptor,
     // Port opened by the client to receive an ack from the server
     ack_port: MachMsgPortDescriptor,
-    /// Combination of the FLAG_* constants
+    /// [`crate::RawExceptionInfo::flags`]
     flags: u32,
     /// The exception type
     exception_kind: u32,
@@ -98,13 +110,20 @@ struct CrashContextMessage {
     exception_code: u64,
     /// The optional exception subcode
     exception_subcode: u64,
+    /// [`crate::CrashTimestamps::monotonic_ns`]
+    monotonic_ns: u64,
+    /// [`crate::CrashTimestamps::realtime_ns`]
+    realtime_ns: u64,
+    /// [`crate::CrashTimestamps::process_start_ns`]
+    process_start_ns: u64,
+    /// [`CrashContext::thread_name`]
+    thread_name: [u8; 64],
+    /// [`CrashContext::main_thread`]
+    main_thread: u32,
     /// We don't actually send this, but it's tacked on by the kernel :(
     trailer: MachMsgTrailer,
 }
 
-const FLAG_HAS_EXCEPTION: u32 = 0x1;
-const FLAG_HAS_SUBCODE: u32 = 0x2;
-
 /// Message sent from the [`Receiver`] upon receiving and handling a [`CrashContextMessage`]
 #[repr(C, packed(4))]
 struct AcknowledgementMessage {
@@ -123,14 +142,67 @@ pub enum Error {
     Message(mach_msg_return_t),
 }
 
+impl Error {
+    /// The raw `kern_return_t` or `mach_msg_return_t`
+    #[inline]
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Kernel(code) | Self::Message(code) => *code,
+        }
+    }
+
+    /// The description of the error code, as provided by the system
+    pub fn description(&self) -> std::borrow::Cow<'static, str> {
+        // SAFETY: both return a pointer to a static string, even for unknown
+        // codes, bootstrap_strerror also knows about the bootstrap specific
+        // codes, and otherwise defers to mach_error_string
+        let desc = unsafe {
+            match self {
+                Self::Kernel(code) => bootstrap::bootstrap_strerror(*code),
+                Self::Message(code) => mach_error_string(*code),
+            }
+        };
+
+        if desc.is_null() {
+            "unknown error".into()
+        } else {
+            // SAFETY: checked for null above
+            unsafe { CStr::from_ptr(desc) }.to_string_lossy()
+        }
+    }
+
+    /// The name was not registered with the bootstrap server, so is likely to
+    /// succeed if retried once the [`Server`] has been created
+    #[inline]
+    pub fn is_unknown_service(&self) -> bool {
+        matches!(self, Self::Kernel(BOOTSTRAP_UNKNOWN_SERVICE))
+    }
+
+    /// A send timed out, eg. because the receiver's queue was full
+    #[inline]
+    pub fn is_send_timed_out(&self) -> bool {
+        matches!(self, Self::Message(MACH_SEND_TIMED_OUT))
+    }
+
+    /// A received message was larger than expected
+    #[inline]
+    pub fn is_receive_too_large(&self) -> bool {
+        matches!(self, Self::Message(MACH_RCV_TOO_LARGE))
+    }
+}
+
 impl std::error::Error for Error {}
 
 use std::fmt;
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // TODO: use a good string for the error codes
-        write!(f, "{:?}", self)
+        match self {
+            Self::Kernel(code) => write!(f, "{} (kern_return_t {code})", self.description()),
+            Self::Message(code) => {
+                write!(f, "{} (mach_msg_return_t {code:#x})", self.description())
+            }
+        }
     }
 }
 
@@ -214,22 +286,7 @@ impl Client {
             // just return immediately
             let mut ack_port = AckReceiver::new()?;
 
-            let (flags, exception_kind, exception_code, exception_subcode) =
-                if let Some(exc) = ctx.exception {
-                    (
-                        FLAG_HAS_EXCEPTION
-                            | if exc.subcode.is_some() {
-                                FLAG_HAS_SUBCODE
-                            } else {
-                                0
-                            },
-                        exc.kind,
-                        exc.code,
-                        exc.subcode.unwrap_or_default(),
-                    )
-                } else {
-                    (0, 0, 0, 0)
-                };
+            let exception = crate::RawExceptionInfo::from(ctx.exception);
 
             let mut msg = CrashContextMessage {
                 head: MachMsgHeader {
@@ -251,10 +308,15 @@ impl Client {
                     msg::MACH_MSG_TYPE_COPY_SEND,
                 ),
                 ack_port: MachMsgPortDescriptor::new(ack_port.port, msg::MACH_MSG_TYPE_COPY_SEND),
-                flags,
-                exception_kind,
-                exception_code,
-                exception_subcode,
+                flags: exception.flags,
+                exception_kind: exception.kind,
+                exception_code: exception.code,
+                exception_subcode: exception.subcode,
+                monotonic_ns: ctx.timestamps.monotonic_ns,
+                realtime_ns: ctx.timestamps.realtime_ns,
+                process_start_ns: ctx.timestamps.process_start_ns,
+                thread_name: ctx.thread_name.0,
+                main_thread: ctx.main_thread.0.into(),
                 // We don't actually send this but I didn't feel like making
                 // two types
                 trailer: MachMsgTrailer { kind: 0, size: 8 },
@@ -370,22 +432,29 @@ impl Server {
             }
 
             // Reconstruct a crash context from the message we received
-            let exception = if crash_ctx_msg.flags & FLAG_HAS_EXCEPTION != 0 {
-                Some(crate::ExceptionInfo {
-                    kind: crash_ctx_msg.exception_kind,
-                    code: crash_ctx_msg.exception_code,
-                    subcode: (crash_ctx_msg.flags & FLAG_HAS_SUBCODE != 0)
-                        .then_some(crash_ctx_msg.exception_subcode),
-                })
-            } else {
-                None
-            };
+            let exception = crate::RawExceptionInfo {
+                flags: crash_ctx_msg.flags,
+                kind: crash_ctx_msg.exception_kind,
+                code: crash_ctx_msg.exception_code,
+                subcode: crash_ctx_msg.exception_subcode,
+            }
+            .into();
 
             let crash_context = CrashContext {
                 task: crash_ctx_msg.task.name,
                 thread: crash_ctx_msg.crash_thread.name,
                 handler_thread: crash_ctx_msg.handler_thread.name,
+                main_thread: crate::MainThread(
+                    u8::try_from(crash_ctx_msg.main_thread).unwrap_or_default(),
+                ),
                 exception,
+                timestamps: crate::CrashTimestamps {
+                    monotonic_ns: crash_ctx_msg.monotonic_ns,
+                    realtime_ns: crash_ctx_msg.realtime_ns,
+                    process_start_ns: crash_ctx_msg.process_start_ns,
+                },
+                thread_name: crate::ThreadName(crash_ctx_msg.thread_name),
+                thread_state: None,
             };
 
             // Translate the task to a pid so the user doesn't have to do it
@@ -551,3 +620,33 @@ impl Drop for AckReceiver {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_strings() {
+        let err = Error::Kernel(BOOTSTRAP_UNKNOWN_SERVICE);
+        assert!(err.is_unknown_service());
+        assert_eq!(err.to_string(), "Unknown service name (kern_return_t 1102)");
+
+        assert_eq!(
+            Error::Kernel(mach2::kern_return::KERN_INVALID_ARGUMENT).to_string(),
+            "(os/kern) invalid argument (kern_return_t 4)"
+        );
+
+        let err = Error::Message(MACH_SEND_TIMED_OUT);
+        assert!(err.is_send_timed_out());
+        assert_eq!(err.code(), 0x1000_0004);
+        assert_eq!(
+            err.to_string(),
+            "(ipc/send) timed out (mach_msg_return_t 0x10000004)"
+        );
+
+        let err = Error::Message(MACH_RCV_TOO_LARGE);
+        assert!(err.is_receive_too_large());
+        assert!(!err.is_send_timed_out());
+        assert_eq!(err.description(), "(ipc/rcv) message too large");
+    }
+}
@@ -7,8 +7,8 @@
 //! for the various constants and decoding of exception information wrapped in
 //! this module.
 
+use core::time::Duration;
 use mach2::exception_types::EXC_RESOURCE;
-use std::time::Duration;
 
 /// The details for an `EXC_RESOURCE` exception as retrieved from the exception's
 /// code and subcode
@@ -35,12 +35,12 @@ pub enum ResourceException {
 /// and while these most likely don't change often, we try to be forward
 /// compatible by not failing if a particular flavor is unknown
 #[derive(Copy, Clone, Debug)]
-pub enum Flavor<T: Copy + Clone + std::fmt::Debug> {
+pub enum Flavor<T: Copy + Clone + core::fmt::Debug> {
     Known(T),
     Unknown(u8),
 }
 
-impl<T: TryFrom<u8> + Copy + Clone + std::fmt::Debug> From<u64> for Flavor<T> {
+impl<T: TryFrom<u8> + Copy + Clone + core::fmt::Debug> From<u64> for Flavor<T> {
     #[inline]
     fn from(code: u64) -> Self {
         let flavor = resource_exc_flavor(code);
@@ -52,7 +52,7 @@ impl<T: TryFrom<u8> + Copy + Clone + std::fmt::Debug> From<u64> for Flavor<T> {
     }
 }
 
-impl<T: PartialEq + Copy + Clone + std::fmt::Debug> PartialEq<T> for Flavor<T> {
+impl<T: PartialEq + Copy + Clone + core::fmt::Debug> PartialEq<T> for Flavor<T> {
     fn eq(&self, o: &T) -> bool {
         match self {
             Self::Known(flavor) => flavor == o,
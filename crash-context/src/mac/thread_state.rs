@@ -0,0 +1,114 @@
+//! Contains types and helpers for capturing the CPU state of a thread.
+//!
+//! `mach2` only provides the general purpose register states, so the float and
+//! exception states are defined here, matching `mach/i386/_structs.h` and
+//! `mach/arm/_structs.h`
+
+use mach2::{
+    kern_return::KERN_SUCCESS, mach_types as mt, message::mach_msg_type_number_t,
+    thread_act::thread_get_state, thread_status as ts,
+};
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        /// The general purpose registers
+        pub type GeneralState = mach2::structs::x86_thread_state64_t;
+
+        /// `x86_float_state64_t`
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug)]
+        pub struct FloatState {
+            pub __fpu_reserved: [i32; 2],
+            /// The legacy `FXSAVE` area, containing the x87, MMX, and SSE
+            /// control/status words and registers
+            pub __fpu_fxsave: [u8; 512],
+            pub __fpu_reserved1: i32,
+        }
+
+        /// `x86_exception_state64_t`
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug)]
+        pub struct ExceptionState {
+            pub __trapno: u16,
+            pub __cpu: u16,
+            pub __err: u32,
+            pub __faultvaddr: u64,
+        }
+
+        const THREAD_STATE_FLAVOR: ts::thread_state_flavor_t = 4; // x86_THREAD_STATE64
+        const FLOAT_STATE_FLAVOR: ts::thread_state_flavor_t = 5; // x86_FLOAT_STATE64
+        const EXCEPTION_STATE_FLAVOR: ts::thread_state_flavor_t = 6; // x86_EXCEPTION_STATE64
+    } else if #[cfg(target_arch = "aarch64")] {
+        /// The general purpose registers
+        pub type GeneralState = mach2::structs::arm_thread_state64_t;
+
+        /// `arm_neon_state64_t`
+        #[repr(C, align(16))]
+        #[derive(Copy, Clone, Debug)]
+        pub struct FloatState {
+            pub __v: [u128; 32],
+            pub __fpsr: u32,
+            pub __fpcr: u32,
+        }
+
+        /// `arm_exception_state64_t`
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug)]
+        pub struct ExceptionState {
+            /// The fault address
+            pub __far: u64,
+            /// The exception syndrome
+            pub __esr: u32,
+            /// The number of the exception
+            pub __exception: u32,
+        }
+
+        const THREAD_STATE_FLAVOR: ts::thread_state_flavor_t = 6; // ARM_THREAD_STATE64
+        const FLOAT_STATE_FLAVOR: ts::thread_state_flavor_t = 17; // ARM_NEON_STATE64
+        const EXCEPTION_STATE_FLAVOR: ts::thread_state_flavor_t = 7; // ARM_EXCEPTION_STATE64
+    }
+}
+
+/// The CPU state of a thread
+#[derive(Copy, Clone, Debug)]
+pub struct ThreadState {
+    /// The general purpose registers
+    pub general: GeneralState,
+    /// The floating point/vector registers
+    pub float: Option<FloatState>,
+    /// The state of the last exception on the thread
+    pub exception: Option<ExceptionState>,
+}
+
+/// Retrieves the specified state flavor for the thread
+///
+/// # Safety
+///
+/// `T` must be the state type that corresponds to the flavor
+unsafe fn get_state<T>(thread: mt::thread_t, flavor: ts::thread_state_flavor_t) -> Option<T> {
+    let mut state = core::mem::MaybeUninit::<T>::zeroed();
+    let mut count =
+        (core::mem::size_of::<T>() / core::mem::size_of::<u32>()) as mach_msg_type_number_t;
+
+    (thread_get_state(thread, flavor, state.as_mut_ptr().cast(), &mut count) == KERN_SUCCESS)
+        .then(|| state.assume_init())
+}
+
+impl ThreadState {
+    /// Captures the CPU state of the specified thread.
+    ///
+    /// The thread should be suspended, otherwise the state may be stale by the
+    /// time this returns. Returns `None` if the general purpose registers could
+    /// not be retrieved, the float and exception states are optional as they
+    /// are not available for every thread.
+    pub fn capture(thread: mt::thread_t) -> Option<Self> {
+        // SAFETY: syscalls, the types match the flavors
+        unsafe {
+            Some(Self {
+                general: get_state(thread, THREAD_STATE_FLAVOR)?,
+                float: get_state(thread, FLOAT_STATE_FLAVOR),
+                exception: get_state(thread, EXCEPTION_STATE_FLAVOR),
+            })
+        }
+    }
+}
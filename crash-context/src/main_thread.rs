@@ -0,0 +1,39 @@
+/// Whether the crashing thread is the main thread of the process, in a form
+/// that can be embedded in the `#[repr(C)]` crash contexts, and sent as-is to
+/// another process.
+///
+/// Any value other than [`Self::MAIN`] or [`Self::OTHER`], eg. one from a
+/// context written by an older version, means it is unknown.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MainThread(pub u8);
+
+impl MainThread {
+    /// It isn't known which thread is the main thread
+    pub const UNKNOWN: Self = Self(0);
+    /// The crashing thread is the main thread
+    pub const MAIN: Self = Self(1);
+    /// The crashing thread is not the main thread
+    pub const OTHER: Self = Self(2);
+
+    /// Whether the crashing thread is the main thread, if known
+    #[inline]
+    pub fn get(self) -> Option<bool> {
+        match self {
+            Self::MAIN => Some(true),
+            Self::OTHER => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl From<Option<bool>> for MainThread {
+    #[inline]
+    fn from(is_main: Option<bool>) -> Self {
+        match is_main {
+            Some(true) => Self::MAIN,
+            Some(false) => Self::OTHER,
+            None => Self::UNKNOWN,
+        }
+    }
+}
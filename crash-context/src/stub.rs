@@ -0,0 +1,26 @@
+/// The details of a crash, on targets where crashes can't be caught.
+///
+/// This only exists so that code which passes a [`CrashContext`] around, eg.
+/// via `crash-handler` or `minidumper`, which are stubbed out on such targets
+/// as well, compiles unchanged. It is never actually produced by a crash.
+#[derive(Clone)]
+pub struct CrashContext {
+    /// The clock readings when the crash was caught
+    pub timestamps: crate::CrashTimestamps,
+    /// The name of the thread that crashed
+    pub thread_name: crate::ThreadName,
+}
+
+impl CrashContext {
+    /// The OS id of the crashing thread, which is always 0, as there are no
+    /// crashing threads on this target
+    pub fn crashing_thread_id(&self) -> u64 {
+        0
+    }
+
+    /// Whether the crash occurred on the main thread, which is never known on
+    /// this target
+    pub fn is_main_thread(&self) -> Option<bool> {
+        None
+    }
+}
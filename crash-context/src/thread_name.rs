@@ -0,0 +1,112 @@
+/// The name of a thread, captured at the time of a crash.
+///
+/// This is a fixed size, nul padded, UTF-8 buffer so that it can be captured
+/// without allocating and sent as-is to another process. The largest thread
+/// name supported by the OS is 64 bytes on Macos, Linux only supports 16, and
+/// names on Windows are truncated to fit.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct ThreadName(pub [u8; 64]);
+
+impl ThreadName {
+    /// A thread without a name
+    pub const EMPTY: Self = Self([0; 64]);
+
+    /// Creates a name from the specified bytes, truncating at the first nul,
+    /// or to fit in the buffer, whichever comes first
+    pub fn new(name: &[u8]) -> Self {
+        let mut buf = [0; 64];
+        let len = name
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(name.len())
+            .min(buf.len());
+
+        buf[..len].copy_from_slice(&name[..len]);
+        Self(buf)
+    }
+
+    /// Creates a name from UTF-16, eg. from Windows, transcoding it without
+    /// allocating. Invalid code units are replaced with U+FFFD, and the name
+    /// is truncated to the last character that fits in the buffer
+    pub fn from_utf16(name: impl IntoIterator<Item = u16>) -> Self {
+        let mut buf = [0; 64];
+        let mut len = 0;
+
+        for c in char::decode_utf16(name.into_iter().take_while(|c| *c != 0)) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            if len + c.len_utf8() > buf.len() {
+                break;
+            }
+
+            len += c.encode_utf8(&mut buf[len..]).len();
+        }
+
+        Self(buf)
+    }
+
+    /// The bytes of the name, without the nul padding
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        let len = self.0.iter().position(|b| *b == 0).unwrap_or(self.0.len());
+        &self.0[..len]
+    }
+
+    /// The name, if the thread had one
+    ///
+    /// Truncation can split a multi-byte character, so the name is converted
+    /// lossily
+    #[inline]
+    pub fn to_str(&self) -> Option<alloc::borrow::Cow<'_, str>> {
+        (!self.is_empty()).then(|| alloc::string::String::from_utf8_lossy(self.as_bytes()))
+    }
+
+    /// True if the thread did not have a name
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0[0] == 0
+    }
+}
+
+impl Default for ThreadName {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl core::fmt::Debug for ThreadName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("ThreadName")
+            .field(&alloc::string::String::from_utf8_lossy(self.as_bytes()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ThreadName;
+
+    #[test]
+    fn truncates() {
+        assert!(ThreadName::EMPTY.is_empty());
+        assert!(ThreadName::EMPTY.to_str().is_none());
+
+        let name = ThreadName::new(b"render-worker-3\0garbage");
+        assert_eq!(name.to_str().unwrap(), "render-worker-3");
+
+        let long = [b'a'; 100];
+        assert_eq!(ThreadName::new(&long).as_bytes(), &long[..64]);
+    }
+
+    #[test]
+    fn transcodes_utf16() {
+        let name = ThreadName::from_utf16("render-worker-3 🦀".encode_utf16());
+        assert_eq!(name.to_str().unwrap(), "render-worker-3 🦀");
+
+        // The crab is 4 bytes and doesn't fit after 62 bytes, so is dropped
+        // entirely rather than split
+        let long: String = std::iter::repeat('a').take(62).chain(Some('🦀')).collect();
+        let name = ThreadName::from_utf16(long.encode_utf16());
+        assert_eq!(name.as_bytes(), &[b'a'; 62]);
+    }
+}
@@ -0,0 +1,176 @@
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+/// Clock readings captured at the time of a crash
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrashTimestamps {
+    /// A monotonic clock reading, in nanoseconds, taken when the crash was
+    /// caught. This is only meaningful relative to other readings of the same
+    /// clock on the same machine, eg. to order crashes.
+    ///
+    /// * Linux/Android - `CLOCK_MONOTONIC`
+    /// * Windows - `QueryPerformanceCounter`
+    /// * Macos - `mach_absolute_time`
+    ///
+    /// On other targets, neither this nor [`Self::realtime_ns`] is read, and
+    /// both are always 0.
+    pub monotonic_ns: u64,
+    /// The wall clock time, in nanoseconds since the Unix epoch, taken when the
+    /// crash was caught
+    pub realtime_ns: u64,
+    /// The wall clock time, in nanoseconds since the Unix epoch, at which the
+    /// crashed process started, or 0 if it is unknown
+    pub process_start_ns: u64,
+}
+
+impl CrashTimestamps {
+    /// Reads the clocks for the current time.
+    ///
+    /// This only uses functions that are async signal safe, so can be called
+    /// from within a signal handler.
+    pub fn now(process_start_ns: u64) -> Self {
+        let (monotonic_ns, realtime_ns) = read_clocks();
+
+        Self {
+            monotonic_ns,
+            realtime_ns,
+            process_start_ns,
+        }
+    }
+
+    /// The time at which the crash occurred
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn crash_time(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_nanos(self.realtime_ns)
+    }
+
+    /// The time at which the crashed process started, if known
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn process_start_time(&self) -> Option<SystemTime> {
+        (self.process_start_ns != 0)
+            .then(|| SystemTime::UNIX_EPOCH + Duration::from_nanos(self.process_start_ns))
+    }
+
+    /// How long the crashed process had been running when it crashed, if known
+    #[inline]
+    pub fn process_uptime(&self) -> Option<Duration> {
+        (self.process_start_ns != 0 && self.realtime_ns >= self.process_start_ns)
+            .then(|| Duration::from_nanos(self.realtime_ns - self.process_start_ns))
+    }
+}
+
+/// Retrieves the (monotonic, realtime) clocks in nanoseconds
+#[inline]
+fn read_clocks() -> (u64, u64) {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            fn read(clock: libc::clockid_t) -> u64 {
+                // SAFETY: syscall
+                unsafe {
+                    let mut ts: libc::timespec = core::mem::zeroed();
+                    libc::clock_gettime(clock, &mut ts);
+                    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+                }
+            }
+
+            (read(libc::CLOCK_MONOTONIC), read(libc::CLOCK_REALTIME))
+        } else if #[cfg(target_os = "windows")] {
+            #[link(name = "kernel32")]
+            extern "system" {
+                fn QueryPerformanceCounter(count: *mut i64) -> i32;
+                fn QueryPerformanceFrequency(frequency: *mut i64) -> i32;
+                /// Fills out a `FILETIME`, ie. the low and high parts of the
+                /// number of 100ns intervals since 1601-01-01
+                fn GetSystemTimePreciseAsFileTime(file_time: *mut [u32; 2]);
+            }
+
+            /// The number of 100ns intervals between 1601-01-01 and the Unix epoch
+            const EPOCH_DIFFERENCE: u64 = 116_444_736_000_000_000;
+
+            // SAFETY: syscalls
+            unsafe {
+                let mut counter = 0;
+                let mut frequency = 0;
+                QueryPerformanceCounter(&mut counter);
+                QueryPerformanceFrequency(&mut frequency);
+
+                let monotonic = if frequency > 0 {
+                    (counter as u128 * 1_000_000_000 / frequency as u128) as u64
+                } else {
+                    0
+                };
+
+                let mut ft = [0u32; 2];
+                GetSystemTimePreciseAsFileTime(&mut ft);
+
+                let intervals = (u64::from(ft[1]) << 32) | u64::from(ft[0]);
+
+                (monotonic, intervals.saturating_sub(EPOCH_DIFFERENCE) * 100)
+            }
+        } else if #[cfg(target_os = "macos")] {
+            // SAFETY: syscalls
+            let monotonic = unsafe {
+                let mut timebase = mach2::mach_time::mach_timebase_info { numer: 0, denom: 0 };
+                mach2::mach_time::mach_timebase_info(&mut timebase);
+
+                let ticks = mach2::mach_time::mach_absolute_time();
+
+                if timebase.denom > 0 {
+                    (ticks as u128 * timebase.numer as u128 / timebase.denom as u128) as u64
+                } else {
+                    ticks
+                }
+            };
+
+            // The exception handler runs on a normal thread rather than in a
+            // signal handler, so this is safe
+            #[cfg(feature = "std")]
+            let realtime = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64);
+
+            #[cfg(not(feature = "std"))]
+            // SAFETY: syscall
+            let realtime = unsafe {
+                let mut ts: libc::timespec = core::mem::zeroed();
+                libc::clock_gettime(libc::CLOCK_REALTIME, &mut ts);
+                ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+            };
+
+            (monotonic, realtime)
+        } else {
+            // There are no clocks we can read without potentially panicking,
+            // eg. `SystemTime::now` on `wasm32-unknown-unknown`
+            (0, 0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CrashTimestamps;
+
+    #[test]
+    fn reads_clocks() {
+        let start = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+
+        let first = CrashTimestamps::now(start - 1_000_000_000);
+        let second = CrashTimestamps::now(0);
+
+        assert!(first.monotonic_ns > 0);
+        assert!(second.monotonic_ns >= first.monotonic_ns);
+        assert!(first.realtime_ns >= start);
+        assert!(first.process_uptime().unwrap() >= std::time::Duration::from_secs(1));
+
+        #[cfg(feature = "std")]
+        assert!(second.process_start_time().is_none());
+        assert!(second.process_uptime().is_none());
+    }
+}
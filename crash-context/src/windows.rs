@@ -1,4 +1,18 @@
+mod exception_record;
+#[cfg(feature = "std")]
+mod thread_context;
+
+pub use exception_record::{OwnedExceptionRecord, MAX_EXCEPTION_RECORD_DEPTH};
+#[cfg(feature = "std")]
+pub use thread_context::{capture_thread_context, CONTEXT_ALL};
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+pub use thread_context::{capture_thread_context_extended, ExtendedContext};
+
 /// Full Windows crash context
+///
+/// The layout is stable, ie. the fields are laid out in declaration order per
+/// `#[repr(C)]`, and is described for C by `include/crash_context.h`
+#[repr(C)]
 pub struct CrashContext {
     /// The information on the exception.
     ///
@@ -13,6 +27,34 @@ pub struct CrashContext {
     pub process_id: u32,
     /// The thread id on which the exception occurred
     pub thread_id: u32,
+    /// Whether [`Self::thread_id`] is the main thread, see
+    /// [`Self::is_main_thread`]
+    pub main_thread: crate::MainThread,
+    /// The clock readings when the exception was caught
+    pub timestamps: crate::CrashTimestamps,
+    /// The description of the thread on which the exception occurred, via
+    /// `GetThreadDescription`
+    pub thread_name: crate::ThreadName,
+}
+
+// The layout is part of the C API, see `include/crash_context.h`. The main
+// thread flag fills what is otherwise padding before the timestamps on 64-bit
+const _: () = assert!(core::mem::size_of::<CrashContext>() == 112);
+
+impl CrashContext {
+    /// The OS id of the crashing thread
+    #[inline]
+    pub fn crashing_thread_id(&self) -> u64 {
+        self.thread_id.into()
+    }
+
+    /// Whether the exception occurred on the main thread, if known, which is
+    /// the thread the crash handler was attached from, unless another thread
+    /// was set as the main one
+    #[inline]
+    pub fn is_main_thread(&self) -> Option<bool> {
+        self.main_thread.get()
+    }
 }
 
 #[link(name = "kernel32")]
@@ -73,8 +115,8 @@ cfg_if::cfg_if! {
 
         #[repr(C)]
         pub union CONTEXT_0 {
-            pub FltSave: std::mem::ManuallyDrop<XSAVE_FORMAT>,
-            pub Anonymous: std::mem::ManuallyDrop<CONTEXT_0_0>,
+            pub FltSave: core::mem::ManuallyDrop<XSAVE_FORMAT>,
+            pub Anonymous: core::mem::ManuallyDrop<CONTEXT_0_0>,
         }
 
         #[repr(C, align(16))]
@@ -176,7 +218,7 @@ cfg_if::cfg_if! {
         }
         #[repr(C)]
         pub union ARM64_NT_NEON128 {
-            pub Anonymous: std::mem::ManuallyDrop<ARM64_NT_NEON128_0>,
+            pub Anonymous: core::mem::ManuallyDrop<ARM64_NT_NEON128_0>,
             pub D: [f64; 2],
             pub S: [f32; 4],
             pub H: [u16; 8],
@@ -220,7 +262,7 @@ cfg_if::cfg_if! {
 
         #[repr(C)]
         pub union CONTEXT_0 {
-            pub Anonymous: std::mem::ManuallyDrop<CONTEXT_0_0>,
+            pub Anonymous: core::mem::ManuallyDrop<CONTEXT_0_0>,
             pub X: [u64; 31],
         }
 
@@ -244,13 +286,14 @@ cfg_if::cfg_if! {
 
 pub type NTSTATUS = i32;
 pub type BOOL = i32;
+pub type HANDLE = isize;
 
 #[repr(C)]
 pub struct EXCEPTION_RECORD {
     pub ExceptionCode: NTSTATUS,
     pub ExceptionFlags: u32,
     pub ExceptionRecord: *mut EXCEPTION_RECORD,
-    pub ExceptionAddress: *mut std::ffi::c_void,
+    pub ExceptionAddress: *mut core::ffi::c_void,
     pub NumberParameters: u32,
     pub ExceptionInformation: [usize; 15],
 }
@@ -0,0 +1,247 @@
+use super::{CrashContext, EXCEPTION_RECORD};
+use alloc::vec::Vec;
+use core::ptr;
+#[cfg(feature = "std")]
+use {
+    super::{BOOL, EXCEPTION_POINTERS},
+    core::mem,
+    std::io,
+};
+
+/// The default maximum number of records walked in an exception record chain.
+///
+/// Nested exceptions are rare, and this guards against walking a corrupted,
+/// eg. cyclic, chain forever
+pub const MAX_EXCEPTION_RECORD_DEPTH: usize = 16;
+
+/// An owned copy of an [`EXCEPTION_RECORD`](https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-exception_record),
+/// without the pointer to the next record in the chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnedExceptionRecord {
+    /// The exception code, eg. `EXCEPTION_ACCESS_VIOLATION`
+    pub code: i32,
+    /// The exception flags, eg. `EXCEPTION_NONCONTINUABLE`
+    pub flags: u32,
+    /// The address where the exception occurred
+    pub address: u64,
+    /// The additional parameters of the exception, the meaning of which
+    /// depends on the exception code
+    pub parameters: Vec<u64>,
+}
+
+impl OwnedExceptionRecord {
+    fn new(record: &EXCEPTION_RECORD) -> Self {
+        let count = (record.NumberParameters as usize).min(record.ExceptionInformation.len());
+
+        Self {
+            code: record.ExceptionCode,
+            flags: record.ExceptionFlags,
+            address: record.ExceptionAddress as u64,
+            parameters: record.ExceptionInformation[..count]
+                .iter()
+                .map(|p| *p as u64)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[link(name = "kernel32")]
+extern "system" {
+    fn OpenProcess(desired_access: u32, inherit_handle: BOOL, process_id: u32) -> isize;
+    fn ReadProcessMemory(
+        process: isize,
+        base_address: *const core::ffi::c_void,
+        buffer: *mut core::ffi::c_void,
+        size: usize,
+        number_of_bytes_read: *mut usize,
+    ) -> BOOL;
+    fn CloseHandle(handle: isize) -> BOOL;
+}
+
+#[cfg(feature = "std")]
+const PROCESS_VM_READ: u32 = 0x0010;
+
+/// Walks the chain starting at `first`, copying out each record read by `read`
+fn walk<E>(
+    first: *const EXCEPTION_RECORD,
+    max_depth: usize,
+    mut read: impl FnMut(*const EXCEPTION_RECORD) -> Result<EXCEPTION_RECORD, E>,
+) -> Result<Vec<OwnedExceptionRecord>, E> {
+    let mut records = Vec::new();
+    let mut next = first;
+
+    while !next.is_null() && records.len() < max_depth {
+        let record = read(next)?;
+        records.push(OwnedExceptionRecord::new(&record));
+        next = record.ExceptionRecord;
+    }
+
+    Ok(records)
+}
+
+impl CrashContext {
+    /// Walks the chain of nested exception records, starting with the top
+    /// level record, in the current process.
+    ///
+    /// At most `max_depth` records are returned, see [`MAX_EXCEPTION_RECORD_DEPTH`].
+    ///
+    /// # Safety
+    ///
+    /// The [`Self::exception_pointers`] must point to valid memory in this
+    /// process, ie. this must be called in the process that crashed, and
+    /// before the exception handler returns.
+    pub unsafe fn exception_records(&self, max_depth: usize) -> Vec<OwnedExceptionRecord> {
+        if self.exception_pointers.is_null() {
+            return Vec::new();
+        }
+
+        let first = (*self.exception_pointers).ExceptionRecord;
+
+        match walk(first, max_depth, |record| {
+            Ok::<_, core::convert::Infallible>(ptr::read(record))
+        }) {
+            Ok(records) => records,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Walks the chain of nested exception records, starting with the top
+    /// level record, in the process that crashed via `ReadProcessMemory`.
+    ///
+    /// At most `max_depth` records are returned, see [`MAX_EXCEPTION_RECORD_DEPTH`].
+    ///
+    /// # Errors
+    ///
+    /// The crashed process can't be opened, eg. because it has already exited,
+    /// or any of the records in the chain can't be read
+    #[cfg(feature = "std")]
+    pub fn read_exception_records(
+        &self,
+        max_depth: usize,
+    ) -> io::Result<Vec<OwnedExceptionRecord>> {
+        if self.exception_pointers.is_null() {
+            return Ok(Vec::new());
+        }
+
+        /// Closes the process handle on drop
+        struct Process(isize);
+
+        impl Drop for Process {
+            fn drop(&mut self) {
+                // SAFETY: syscall
+                unsafe { CloseHandle(self.0) };
+            }
+        }
+
+        // SAFETY: syscall
+        let process = unsafe { OpenProcess(PROCESS_VM_READ, 0, self.process_id) };
+        if process == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let process = Process(process);
+
+        /// Reads a `T` from the crashed process
+        fn read<T>(process: &Process, addr: *const T) -> io::Result<T> {
+            let mut val = mem::MaybeUninit::<T>::uninit();
+            let mut read = 0;
+
+            // SAFETY: syscall, the buffer is valid for the size of `T`, and
+            // is only assumed to be initialized if fully written
+            unsafe {
+                if ReadProcessMemory(
+                    process.0,
+                    addr.cast(),
+                    val.as_mut_ptr().cast(),
+                    mem::size_of::<T>(),
+                    &mut read,
+                ) == 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+
+                if read != mem::size_of::<T>() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "partial read of exception record",
+                    ));
+                }
+
+                Ok(val.assume_init())
+            }
+        }
+
+        let pointers: EXCEPTION_POINTERS = read(&process, self.exception_pointers)?;
+
+        walk(pointers.ExceptionRecord, max_depth, |record| {
+            read(&process, record)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EXCEPTION_POINTERS;
+    use std::mem;
+
+    #[test]
+    fn walks_chain() {
+        unsafe {
+            let mut inner: EXCEPTION_RECORD = mem::zeroed();
+            inner.ExceptionCode = 0xc000_0005_u32 as i32;
+            inner.ExceptionAddress = 0x1234 as _;
+            inner.NumberParameters = 2;
+            inner.ExceptionInformation[0] = 1;
+            inner.ExceptionInformation[1] = 0xdead;
+
+            let mut outer: EXCEPTION_RECORD = mem::zeroed();
+            outer.ExceptionCode = 0xc000_0409_u32 as i32;
+            outer.ExceptionFlags = 1;
+            // Out of range counts are clamped
+            outer.NumberParameters = 100;
+            outer.ExceptionRecord = &mut inner;
+
+            let mut pointers = EXCEPTION_POINTERS {
+                ExceptionRecord: &mut outer,
+                ContextRecord: ptr::null_mut(),
+            };
+
+            let cc = CrashContext {
+                exception_pointers: &mut pointers,
+                exception_code: outer.ExceptionCode,
+                process_id: std::process::id(),
+                thread_id: 0,
+                main_thread: Default::default(),
+                timestamps: Default::default(),
+                thread_name: Default::default(),
+            };
+
+            let local = cc.exception_records(MAX_EXCEPTION_RECORD_DEPTH);
+            assert_eq!(local.len(), 2);
+            assert_eq!(local[0].flags, 1);
+            assert_eq!(local[0].parameters.len(), 15);
+            assert_eq!(
+                local[1],
+                OwnedExceptionRecord {
+                    code: 0xc000_0005_u32 as i32,
+                    flags: 0,
+                    address: 0x1234,
+                    parameters: vec![1, 0xdead],
+                }
+            );
+
+            // Reading our own process via ReadProcessMemory must agree
+            #[cfg(feature = "std")]
+            assert_eq!(
+                cc.read_exception_records(MAX_EXCEPTION_RECORD_DEPTH)
+                    .unwrap(),
+                local
+            );
+
+            // The depth is bounded, even for a cyclic chain
+            (*outer.ExceptionRecord).ExceptionRecord = &mut outer;
+            assert_eq!(cc.exception_records(5).len(), 5);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use super::{BOOL, CONTEXT, HANDLE};
+use std::{io, mem};
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        /// `CONTEXT_AMD64 | CONTEXT_CONTROL | CONTEXT_INTEGER | CONTEXT_SEGMENTS
+        /// | CONTEXT_FLOATING_POINT | CONTEXT_DEBUG_REGISTERS`
+        pub const CONTEXT_ALL: u32 = 0x0010_001f;
+        /// `CONTEXT_AMD64 | 0x40`
+        const CONTEXT_XSTATE: u32 = 0x0010_0040;
+    } else if #[cfg(target_arch = "x86")] {
+        /// `CONTEXT_i386 | CONTEXT_CONTROL | CONTEXT_INTEGER | CONTEXT_SEGMENTS
+        /// | CONTEXT_FLOATING_POINT | CONTEXT_DEBUG_REGISTERS | CONTEXT_EXTENDED_REGISTERS`
+        pub const CONTEXT_ALL: u32 = 0x0001_003f;
+        /// `CONTEXT_i386 | 0x40`
+        const CONTEXT_XSTATE: u32 = 0x0001_0040;
+    } else if #[cfg(target_arch = "aarch64")] {
+        /// `CONTEXT_ARM64 | CONTEXT_CONTROL | CONTEXT_INTEGER
+        /// | CONTEXT_FLOATING_POINT | CONTEXT_DEBUG_REGISTERS | CONTEXT_X18`
+        pub const CONTEXT_ALL: u32 = 0x0040_001f;
+    }
+}
+
+/// The exit code of a thread that hasn't exited
+const STILL_ACTIVE: u32 = 259;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn SuspendThread(thread: HANDLE) -> u32;
+    fn ResumeThread(thread: HANDLE) -> u32;
+    fn GetThreadContext(thread: HANDLE, context: *mut CONTEXT) -> BOOL;
+    fn GetThreadId(thread: HANDLE) -> u32;
+    fn GetCurrentThreadId() -> u32;
+    fn GetExitCodeThread(thread: HANDLE, exit_code: *mut u32) -> BOOL;
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[link(name = "kernel32")]
+extern "system" {
+    fn InitializeContext(
+        buffer: *mut std::ffi::c_void,
+        context_flags: u32,
+        context: *mut *mut CONTEXT,
+        context_length: *mut u32,
+    ) -> BOOL;
+    fn GetEnabledXStateFeatures() -> u64;
+    fn SetXStateFeaturesMask(context: *mut CONTEXT, feature_mask: u64) -> BOOL;
+}
+
+/// The error for a failed call on the thread, which is reported as
+/// [`io::ErrorKind::NotFound`] if the thread has exited, as that is a race
+/// inherent to inspecting another thread, rather than a usage error
+fn thread_error(thread: HANDLE) -> io::Error {
+    let err = io::Error::last_os_error();
+    let mut exit_code = 0;
+
+    // SAFETY: syscall
+    if unsafe { GetExitCodeThread(thread, &mut exit_code) } != 0 && exit_code != STILL_ACTIVE {
+        io::Error::new(io::ErrorKind::NotFound, "the thread has exited")
+    } else {
+        err
+    }
+}
+
+/// Suspends a thread, resuming it on drop
+struct Suspended(HANDLE);
+
+impl Suspended {
+    fn new(thread: HANDLE) -> io::Result<Self> {
+        // SAFETY: syscalls
+        unsafe {
+            // The suspension would never be lifted
+            if GetThreadId(thread) == GetCurrentThreadId() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the calling thread can't capture its own context this way, use `capture_context`",
+                ));
+            }
+
+            if SuspendThread(thread) == u32::MAX {
+                return Err(thread_error(thread));
+            }
+        }
+
+        Ok(Self(thread))
+    }
+}
+
+impl Drop for Suspended {
+    fn drop(&mut self) {
+        // SAFETY: syscall, the thread was suspended by us. A suspended thread
+        // can only exit if it is terminated, in which case this fails, which
+        // is fine
+        unsafe { ResumeThread(self.0) };
+    }
+}
+
+/// Captures the full context, ie. [`CONTEXT_ALL`], of another thread in the
+/// current process, or in another process.
+///
+/// The thread is suspended while its context is retrieved, and resumed
+/// afterwards. `SuspendThread` is asynchronous, but `GetThreadContext` waits
+/// for the suspension to take effect, so the context is that of the thread
+/// while it is suspended, not while it is running.
+///
+/// The handle must have the `THREAD_SUSPEND_RESUME`, `THREAD_GET_CONTEXT`,
+/// and `THREAD_QUERY_LIMITED_INFORMATION` access rights.
+///
+/// # Errors
+///
+/// The thread is the calling thread, whose context can be captured with
+/// [`crate::capture_context`] instead, the handle lacks the required rights, or the
+/// thread exited before its context was retrieved, which is reported as
+/// [`io::ErrorKind::NotFound`]
+pub fn capture_thread_context(thread: HANDLE) -> io::Result<CONTEXT> {
+    let _suspended = Suspended::new(thread)?;
+
+    // SAFETY: the context is POD, and only assumed initialized once filled
+    unsafe {
+        let mut context = mem::MaybeUninit::<CONTEXT>::zeroed();
+        (*context.as_mut_ptr()).ContextFlags = CONTEXT_ALL;
+
+        if GetThreadContext(thread, context.as_mut_ptr()) == 0 {
+            return Err(thread_error(thread));
+        }
+
+        Ok(context.assume_init())
+    }
+}
+
+/// A [`CONTEXT`] followed by the extended processor state, eg. the upper
+/// halves of the AVX registers, which doesn't fit in a plain [`CONTEXT`], see
+/// [`capture_thread_context_extended`]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub struct ExtendedContext {
+    /// The buffer initialized by `InitializeContext`, which the context is
+    /// placed at some aligned offset in
+    buffer: Vec<u8>,
+    offset: usize,
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl ExtendedContext {
+    /// The context, which also has the [`CONTEXT_ALL`] registers
+    #[inline]
+    pub fn context(&self) -> &CONTEXT {
+        // SAFETY: the offset was returned by InitializeContext, which aligns it
+        unsafe { &*self.as_ptr() }
+    }
+
+    /// A pointer to the context, which can be passed to `LocateXStateFeature`
+    /// to find each feature in the extended state that follows it
+    #[inline]
+    pub fn as_ptr(&self) -> *const CONTEXT {
+        self.buffer[self.offset..].as_ptr().cast()
+    }
+}
+
+/// Captures the context of another thread as [`capture_thread_context`] does,
+/// along with every extended processor state feature that is enabled by the
+/// OS, eg. AVX.
+///
+/// # Errors
+///
+/// The same as [`capture_thread_context`], or the context buffer can't be
+/// initialized, eg. because the OS doesn't support extended state
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub fn capture_thread_context_extended(thread: HANDLE) -> io::Result<ExtendedContext> {
+    let flags = CONTEXT_ALL | CONTEXT_XSTATE;
+
+    // SAFETY: syscalls, the buffer is sized as requested by InitializeContext
+    let mut extended = unsafe {
+        let mut context = std::ptr::null_mut();
+        let mut len = 0;
+
+        // The first call fails, and reports the required length
+        InitializeContext(std::ptr::null_mut(), flags, &mut context, &mut len);
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut buffer = vec![0u8; len as usize];
+        if InitializeContext(buffer.as_mut_ptr().cast(), flags, &mut context, &mut len) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if SetXStateFeaturesMask(context, GetEnabledXStateFeatures()) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let offset = context as usize - buffer.as_ptr() as usize;
+        ExtendedContext { buffer, offset }
+    };
+
+    let _suspended = Suspended::new(thread)?;
+
+    // SAFETY: syscall, the context was initialized above
+    unsafe {
+        let context = extended.buffer[extended.offset..].as_mut_ptr().cast();
+        if GetThreadContext(thread, context) == 0 {
+            return Err(thread_error(thread));
+        }
+    }
+
+    Ok(extended)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        os::windows::io::AsRawHandle,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    #[test]
+    fn captures_other_thread() {
+        static STOP: AtomicBool = AtomicBool::new(false);
+
+        let spinner = std::thread::spawn(|| {
+            while !STOP.load(Ordering::Relaxed) {
+                std::hint::spin_loop();
+            }
+        });
+
+        let handle = spinner.as_raw_handle() as HANDLE;
+
+        let context = capture_thread_context(handle).unwrap();
+        assert_eq!(context.ContextFlags & CONTEXT_ALL, CONTEXT_ALL);
+
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "x86_64")] {
+                assert_ne!(context.Rip, 0);
+                assert_ne!(context.Rsp, 0);
+            } else if #[cfg(target_arch = "x86")] {
+                let (eip, esp) = (context.Eip, context.Esp);
+                assert_ne!(eip, 0);
+                assert_ne!(esp, 0);
+            } else if #[cfg(target_arch = "aarch64")] {
+                assert_ne!(context.Pc, 0);
+                assert_ne!(context.Sp, 0);
+            }
+        }
+
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            let extended = capture_thread_context_extended(handle).unwrap();
+            let flags = extended.context().ContextFlags;
+            assert_eq!(flags & CONTEXT_ALL, CONTEXT_ALL);
+        }
+
+        // The calling thread can't suspend itself
+        let current = unsafe { GetCurrentThread() };
+        assert!(matches!(
+            capture_thread_context(current),
+            Err(err) if err.kind() == io::ErrorKind::InvalidInput
+        ));
+
+        // The thread has only exited once its handle is signaled, it can
+        // still be running after its closure has returned
+        STOP.store(true, Ordering::Relaxed);
+        assert_eq!(unsafe { WaitForSingleObject(handle, u32::MAX) }, 0);
+
+        // The handle is still open, but the thread is gone
+        assert!(matches!(
+            capture_thread_context(handle),
+            Err(err) if err.kind() == io::ErrorKind::NotFound
+        ));
+
+        spinner.join().unwrap();
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThread() -> HANDLE;
+        fn WaitForSingleObject(handle: HANDLE, milliseconds: u32) -> u32;
+    }
+}
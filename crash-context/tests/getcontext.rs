@@ -0,0 +1,360 @@
+//! Verifies that `crash_context_getcontext` captures the actual values of the
+//! callee-saved registers, as well as the stack pointer and return address, by
+//! loading known sentinel values into them before calling it, and that it
+//! agrees with glibc's `getcontext` where that is available.
+//!
+//! Every architecture `crash_context_getcontext` is implemented for needs its
+//! own `arch` module below, anything else fails to compile.
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_context::ucontext_t;
+use std::mem::{ManuallyDrop, MaybeUninit};
+
+type GetContext = unsafe extern "C" fn(*mut ucontext_t) -> i32;
+
+/// The registers captured in a `ucontext_t`
+#[derive(Debug, PartialEq, Eq)]
+struct Registers<const N: usize> {
+    /// The callee-saved registers, in the same order as [`arch::SENTINELS`]
+    callee_saved: [usize; N],
+    sp: usize,
+    pc: usize,
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        mod arch {
+            use super::*;
+
+            /// rbx, rbp, r12, r13, r14, r15
+            pub const SENTINELS: [usize; 6] = [
+                0x5e47_1e10_0000_00b0,
+                0x5e47_1e10_0000_00b9,
+                0x5e47_1e10_0000_0012,
+                0x5e47_1e10_0000_0013,
+                0x5e47_1e10_0000_0014,
+                0x5e47_1e10_0000_0015,
+            ];
+
+            /// Calls `getcontext` with the callee-saved registers set to the
+            /// [`SENTINELS`], restoring them afterwards
+            #[inline(never)]
+            pub unsafe fn call_with_sentinels(getcontext: GetContext, ctx: *mut ucontext_t) -> i32 {
+                let ret: usize;
+                // rbx and rbp can't be used as operands, so every callee-saved
+                // register is saved and restored manually
+                std::arch::asm!(
+                    "push rbx",
+                    "push rbp",
+                    "push r12",
+                    "push r13",
+                    "push r14",
+                    "push r15",
+                    "mov rbx, [rsi]",
+                    "mov rbp, [rsi + 8]",
+                    "mov r12, [rsi + 16]",
+                    "mov r13, [rsi + 24]",
+                    "mov r14, [rsi + 32]",
+                    "mov r15, [rsi + 40]",
+                    "call rax",
+                    "pop r15",
+                    "pop r14",
+                    "pop r13",
+                    "pop r12",
+                    "pop rbp",
+                    "pop rbx",
+                    inout("rax") getcontext as usize => ret,
+                    in("rdi") ctx,
+                    in("rsi") SENTINELS.as_ptr(),
+                    clobber_abi("C"),
+                );
+                ret as i32
+            }
+
+            pub fn registers(ctx: &ucontext_t) -> Registers<6> {
+                let gregs = &ctx.uc_mcontext.gregs;
+                let reg = |i: libc::c_int| gregs[i as usize] as usize;
+
+                Registers {
+                    callee_saved: [
+                        reg(libc::REG_RBX),
+                        reg(libc::REG_RBP),
+                        reg(libc::REG_R12),
+                        reg(libc::REG_R13),
+                        reg(libc::REG_R14),
+                        reg(libc::REG_R15),
+                    ],
+                    sp: reg(libc::REG_RSP),
+                    pc: reg(libc::REG_RIP),
+                }
+            }
+        }
+    } else if #[cfg(target_arch = "x86")] {
+        mod arch {
+            use super::*;
+
+            // libc doesn't define the REG_* indices for every x86 target
+            const REG_EDI: usize = 4;
+            const REG_ESI: usize = 5;
+            const REG_EBP: usize = 6;
+            const REG_ESP: usize = 7;
+            const REG_EIP: usize = 14;
+
+            /// esi, edi, ebp
+            ///
+            /// ebx is left alone, as `crash_context_getcontext` calls
+            /// `sigprocmask` through the PLT, which relies on it holding the
+            /// address of the GOT in position independent code
+            pub const SENTINELS: [usize; 3] = [0x5e47_0e51, 0x5e47_0ed1, 0x5e47_0eb9];
+
+            /// Calls `getcontext` with the callee-saved registers set to the
+            /// [`SENTINELS`], restoring them afterwards
+            #[inline(never)]
+            pub unsafe fn call_with_sentinels(getcontext: GetContext, ctx: *mut ucontext_t) -> i32 {
+                let ret: usize;
+                // esi and ebp can't be used as operands, so every callee-saved
+                // register is saved and restored manually
+                std::arch::asm!(
+                    "push ebx",
+                    "push esi",
+                    "push edi",
+                    "push ebp",
+                    // Keeps the stack 16-byte aligned at the call
+                    "sub esp, 12",
+                    "push eax",
+                    "mov esi, [ecx]",
+                    "mov edi, [ecx + 4]",
+                    "mov ebp, [ecx + 8]",
+                    "call edx",
+                    "add esp, 16",
+                    "pop ebp",
+                    "pop edi",
+                    "pop esi",
+                    "pop ebx",
+                    inout("eax") ctx => ret,
+                    in("ecx") SENTINELS.as_ptr(),
+                    in("edx") getcontext as usize,
+                    clobber_abi("C"),
+                );
+                ret as i32
+            }
+
+            pub fn registers(ctx: &ucontext_t) -> Registers<3> {
+                let gregs = &ctx.uc_mcontext.gregs;
+                let reg = |i: usize| gregs[i] as usize;
+
+                Registers {
+                    callee_saved: [reg(REG_ESI), reg(REG_EDI), reg(REG_EBP)],
+                    sp: reg(REG_ESP),
+                    pc: reg(REG_EIP),
+                }
+            }
+        }
+    } else if #[cfg(target_arch = "aarch64")] {
+        mod arch {
+            use super::*;
+
+            /// x19-x29, then d8-d15
+            pub const SENTINELS: [usize; 19] = [
+                0x5e47_1e10_0000_0019,
+                0x5e47_1e10_0000_0020,
+                0x5e47_1e10_0000_0021,
+                0x5e47_1e10_0000_0022,
+                0x5e47_1e10_0000_0023,
+                0x5e47_1e10_0000_0024,
+                0x5e47_1e10_0000_0025,
+                0x5e47_1e10_0000_0026,
+                0x5e47_1e10_0000_0027,
+                0x5e47_1e10_0000_0028,
+                0x5e47_1e10_0000_0029,
+                0x5e47_1e10_0000_d008,
+                0x5e47_1e10_0000_d009,
+                0x5e47_1e10_0000_d010,
+                0x5e47_1e10_0000_d011,
+                0x5e47_1e10_0000_d012,
+                0x5e47_1e10_0000_d013,
+                0x5e47_1e10_0000_d014,
+                0x5e47_1e10_0000_d015,
+            ];
+
+            /// Calls `getcontext` with the callee-saved registers set to the
+            /// [`SENTINELS`], restoring them afterwards
+            #[inline(never)]
+            pub unsafe fn call_with_sentinels(getcontext: GetContext, ctx: *mut ucontext_t) -> i32 {
+                let ret: usize;
+                // x19 and x29 can't be used as operands, so every callee-saved
+                // register is saved and restored manually
+                std::arch::asm!(
+                    "stp x19, x20, [sp, #-160]!",
+                    "stp x21, x22, [sp, #16]",
+                    "stp x23, x24, [sp, #32]",
+                    "stp x25, x26, [sp, #48]",
+                    "stp x27, x28, [sp, #64]",
+                    "stp x29, x30, [sp, #80]",
+                    "stp d8, d9, [sp, #96]",
+                    "stp d10, d11, [sp, #112]",
+                    "stp d12, d13, [sp, #128]",
+                    "stp d14, d15, [sp, #144]",
+                    "ldp x19, x20, [x1]",
+                    "ldp x21, x22, [x1, #16]",
+                    "ldp x23, x24, [x1, #32]",
+                    "ldp x25, x26, [x1, #48]",
+                    "ldp x27, x28, [x1, #64]",
+                    "ldr x29, [x1, #80]",
+                    "ldp d8, d9, [x1, #88]",
+                    "ldp d10, d11, [x1, #104]",
+                    "ldp d12, d13, [x1, #120]",
+                    "ldp d14, d15, [x1, #136]",
+                    "blr x9",
+                    "ldp d14, d15, [sp, #144]",
+                    "ldp d12, d13, [sp, #128]",
+                    "ldp d10, d11, [sp, #112]",
+                    "ldp d8, d9, [sp, #96]",
+                    "ldp x29, x30, [sp, #80]",
+                    "ldp x27, x28, [sp, #64]",
+                    "ldp x25, x26, [sp, #48]",
+                    "ldp x23, x24, [sp, #32]",
+                    "ldp x21, x22, [sp, #16]",
+                    "ldp x19, x20, [sp], #160",
+                    inout("x0") ctx => ret,
+                    in("x1") SENTINELS.as_ptr(),
+                    in("x9") getcontext as usize,
+                    clobber_abi("C"),
+                );
+                ret as i32
+            }
+
+            pub fn registers(ctx: &ucontext_t) -> Registers<19> {
+                let mc = &ctx.uc_mcontext;
+                // SAFETY: the fpsimd context is the first extension block
+                let fpsimd = unsafe {
+                    &*mc.__reserved.as_ptr().cast::<crash_context::fpsimd_context>()
+                };
+                assert_eq!(fpsimd.head.magic, crash_context::FPSIMD_MAGIC);
+
+                let mut callee_saved = [0; 19];
+                for (i, reg) in mc.regs[19..=29].iter().enumerate() {
+                    callee_saved[i] = *reg as usize;
+                }
+                // Only the low 64 bits, ie. d8-d15, of v8-v15 are callee-saved
+                for (i, vreg) in fpsimd.vregs[8..16].iter().enumerate() {
+                    callee_saved[11 + i] = *vreg as u64 as usize;
+                }
+
+                Registers {
+                    callee_saved,
+                    sp: mc.sp as usize,
+                    pc: mc.pc as usize,
+                }
+            }
+        }
+    } else if #[cfg(target_arch = "arm")] {
+        mod arch {
+            use super::*;
+
+            /// r4-r11
+            pub const SENTINELS: [usize; 8] = [
+                0x5e47_0004,
+                0x5e47_0005,
+                0x5e47_0006,
+                0x5e47_0007,
+                0x5e47_0008,
+                0x5e47_0009,
+                0x5e47_0010,
+                0x5e47_0011,
+            ];
+
+            /// Calls `getcontext` with the callee-saved registers set to the
+            /// [`SENTINELS`], restoring them afterwards
+            #[inline(never)]
+            pub unsafe fn call_with_sentinels(getcontext: GetContext, ctx: *mut ucontext_t) -> i32 {
+                let ret: usize;
+                // r6, r7 and r11 can't be used as operands, so every
+                // callee-saved register is saved and restored manually
+                std::arch::asm!(
+                    "push {{r4-r11}}",
+                    "ldm r1, {{r4-r11}}",
+                    "blx r2",
+                    "pop {{r4-r11}}",
+                    inout("r0") ctx => ret,
+                    in("r1") SENTINELS.as_ptr(),
+                    in("r2") getcontext as usize,
+                    clobber_abi("C"),
+                );
+                ret as i32
+            }
+
+            pub fn registers(ctx: &ucontext_t) -> Registers<8> {
+                let mc = &ctx.uc_mcontext;
+
+                Registers {
+                    callee_saved: [
+                        mc.arm_r4 as usize,
+                        mc.arm_r5 as usize,
+                        mc.arm_r6 as usize,
+                        mc.arm_r7 as usize,
+                        mc.arm_r8 as usize,
+                        mc.arm_r9 as usize,
+                        mc.arm_r10 as usize,
+                        mc.arm_fp as usize,
+                    ],
+                    sp: mc.arm_sp as usize,
+                    pc: mc.arm_pc as usize,
+                }
+            }
+        }
+    } else {
+        compile_error!("add a sentinel test for crash_context_getcontext on this architecture");
+    }
+}
+
+/// Storage for a `ucontext_t` that is large enough for glibc's, which extends
+/// the kernel's layout that ours follows
+#[repr(C)]
+union Context {
+    ours: ManuallyDrop<ucontext_t>,
+    #[cfg(target_env = "gnu")]
+    glibc: libc::ucontext_t,
+}
+
+#[inline(never)]
+fn capture(getcontext: GetContext) -> Registers<{ arch::SENTINELS.len() }> {
+    let mut ctx = MaybeUninit::<Context>::zeroed();
+
+    unsafe {
+        assert_eq!(
+            arch::call_with_sentinels(getcontext, ctx.as_mut_ptr().cast()),
+            0
+        );
+        arch::registers(&ctx.assume_init_ref().ours)
+    }
+}
+
+#[test]
+fn captures_sentinels() {
+    let regs = capture(crash_context::crash_context_getcontext);
+
+    assert_eq!(regs.callee_saved, arch::SENTINELS);
+    assert_ne!(regs.sp, 0);
+    assert_ne!(regs.pc, 0);
+}
+
+/// libc doesn't bind `getcontext` for arm
+#[cfg(all(target_env = "gnu", not(target_arch = "arm")))]
+#[test]
+fn matches_glibc() {
+    let glibc_getcontext = unsafe {
+        std::mem::transmute::<unsafe extern "C" fn(*mut libc::ucontext_t) -> i32, GetContext>(
+            libc::getcontext,
+        )
+    };
+
+    // Both are called from the same place, with the same stack, so even the
+    // stack pointer and return address should match
+    assert_eq!(
+        capture(crash_context::crash_context_getcontext),
+        capture(glibc_getcontext)
+    );
+}
@@ -0,0 +1,87 @@
+//! Measures the overhead of the crash handler on the process when no crash
+//! occurs.
+//!
+//! Criterion writes the results of each benchmark to
+//! `target/criterion/<group>/<bench>/new/estimates.json`, which can be used to
+//! track regressions over time.
+
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+fn attach_detach(c: &mut Criterion) {
+    c.bench_function("attach_detach", |b| {
+        b.iter(|| {
+            let handler = ch::CrashHandler::attach(unsafe {
+                ch::make_crash_event(|_cc: &ch::CrashContext| ch::CrashEventResult::Handled(false))
+            })
+            .expect("failed to attach crash handler");
+
+            handler.detach().unwrap();
+        });
+    });
+}
+
+/// Spawns a thread directly via libc's `pthread_create`, bypassing the hook
+/// that installs an alternate signal stack for every thread
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn spawn_unhooked() {
+    type PthreadCreate = unsafe extern "C" fn(
+        *mut libc::pthread_t,
+        *const libc::pthread_attr_t,
+        extern "C" fn(*mut libc::c_void) -> *mut libc::c_void,
+        *mut libc::c_void,
+    ) -> i32;
+
+    extern "C" fn thread_main(_arg: *mut libc::c_void) -> *mut libc::c_void {
+        std::ptr::null_mut()
+    }
+
+    static REAL_PTHREAD_CREATE: std::sync::OnceLock<PthreadCreate> = std::sync::OnceLock::new();
+
+    let pthread_create = REAL_PTHREAD_CREATE.get_or_init(|| unsafe {
+        let sym = libc::dlsym(libc::RTLD_NEXT, c"pthread_create".as_ptr());
+        assert!(!sym.is_null(), "failed to find libc's pthread_create");
+        std::mem::transmute::<*mut libc::c_void, PthreadCreate>(sym)
+    });
+
+    unsafe {
+        let mut thread = std::mem::zeroed();
+        assert_eq!(
+            pthread_create(
+                &mut thread,
+                std::ptr::null(),
+                thread_main,
+                std::ptr::null_mut()
+            ),
+            0
+        );
+        assert_eq!(libc::pthread_join(thread, std::ptr::null_mut()), 0);
+    }
+}
+
+fn thread_spawn(c: &mut Criterion) {
+    let mut group = c.benchmark_group("thread_spawn");
+
+    group.bench_function("detached", |b| {
+        b.iter(|| std::thread::spawn(|| {}).join().unwrap());
+    });
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    group.bench_function("unhooked", |b| b.iter(spawn_unhooked));
+
+    let _handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|_cc: &ch::CrashContext| ch::CrashEventResult::Handled(false))
+    })
+    .expect("failed to attach crash handler");
+
+    group.bench_function("attached", |b| {
+        b.iter(|| std::thread::spawn(|| {}).join().unwrap());
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, attach_detach, thread_spawn);
+criterion_main!(benches);
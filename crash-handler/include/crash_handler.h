@@ -0,0 +1,91 @@
+/* SPDX-License-Identifier: MIT OR Apache-2.0 */
+
+#ifndef CRASH_HANDLER_H
+#define CRASH_HANDLER_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call succeeded
+#define CRASH_HANDLER_OK 0
+
+// The callback was null
+#define CRASH_HANDLER_ERR_INVALID_ARGUMENT -1
+
+// A handler is already attached, ie. [`Error::HandlerAlreadyInstalled`]
+#define CRASH_HANDLER_ERR_ALREADY_ATTACHED -2
+
+// Memory for the handler could not be allocated, ie. [`Error::OutOfMemory`]
+#define CRASH_HANDLER_ERR_OUT_OF_MEMORY -3
+
+// A syscall failed, ie. [`Error::Io`]
+#define CRASH_HANDLER_ERR_IO -4
+
+// Any other error, including a panic, which is caught rather than unwinding
+// into the caller
+#define CRASH_HANDLER_ERR_OTHER -5
+
+typedef struct CrashHandlerCrashContext CrashHandlerCrashContext;
+
+// A stable view of the [`CrashContext`] for the current platform, passed to
+// the [`CrashHandlerCallback`]
+typedef struct CrashContextC {
+  // The full platform [`CrashContext`], only valid for the duration of the
+  // callback
+  const CrashHandlerCrashContext *context;
+  // The id of the crashing process
+  uint32_t process_id;
+  // The OS id of the crashing thread
+  uint64_t thread_id;
+  // The signal number on Linux/Android, the exception code on Windows, or
+  // the exception type on Macos, which is 0 if there is no exception, eg.
+  // for a simulated exception
+  int64_t code;
+} CrashContextC;
+
+// The callback invoked when a crash occurs.
+//
+// Returning non-zero indicates the crash was handled, ie. the same as
+// returning `CrashEventResult::Handled(true)` from a [`crate::CrashEvent`].
+//
+// The callback runs in the same compromised context as a
+// [`crate::CrashEvent`], see its documentation for the restrictions this
+// imposes, notably that it must be async signal safe on Linux/Android. The
+// callback can be invoked from any thread, but is never invoked for more
+// than one crash at a time. It must not call [`crash_handler_attach`] or
+// [`crash_handler_detach`].
+typedef int (*CrashHandlerCallback)(const CrashContextC *context, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Attaches the crash handler, see [`CrashHandler::attach`], which invokes
+// `callback` with `user_data` when a crash occurs.
+//
+// Returns [`CRASH_HANDLER_OK`] on success, or one of the `CRASH_HANDLER_ERR_*`
+// codes. This can be called before any Rust code has run, and from any
+// thread.
+//
+// # Safety
+//
+// `user_data` is not read or freed by this crate, only passed to `callback`,
+// and so is owned by the caller. It must remain valid, and usable from any
+// thread, until [`crash_handler_detach`] returns.
+int crash_handler_attach(CrashHandlerCallback callback, void *user_data);
+
+// Detaches the crash handler attached via [`crash_handler_attach`], after
+// which the `user_data` passed to it is no longer used. Does nothing if no
+// handler is attached.
+void crash_handler_detach(void);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* CRASH_HANDLER_H */
@@ -0,0 +1,170 @@
+//! A C API for attaching the [`CrashHandler`], so that a non-Rust host can
+//! install the handler, eg. at the start of `main`, and receive the crash
+//! callback in its own code.
+//!
+//! The header for this API is `include/crash_handler.h`, which is generated by
+//! running `cbindgen --config cbindgen.toml --crate crash-handler --output include/crash_handler.h`
+//! in the crate root.
+
+use crate::{CrashContext, CrashEventResult, CrashHandler, Error};
+use std::os::raw::{c_int, c_void};
+
+/// The call succeeded
+pub const CRASH_HANDLER_OK: c_int = 0;
+/// The callback was null
+pub const CRASH_HANDLER_ERR_INVALID_ARGUMENT: c_int = -1;
+/// A handler is already attached, ie. [`Error::HandlerAlreadyInstalled`]
+pub const CRASH_HANDLER_ERR_ALREADY_ATTACHED: c_int = -2;
+/// Memory for the handler could not be allocated, ie. [`Error::OutOfMemory`]
+pub const CRASH_HANDLER_ERR_OUT_OF_MEMORY: c_int = -3;
+/// A syscall failed, eg. [`Error::Io`] or [`Error::Signal`]
+pub const CRASH_HANDLER_ERR_IO: c_int = -4;
+/// Any other error, including a panic, which is caught rather than unwinding
+/// into the caller
+pub const CRASH_HANDLER_ERR_OTHER: c_int = -5;
+
+/// A stable view of the [`CrashContext`] for the current platform, passed to
+/// the [`CrashHandlerCallback`]
+#[repr(C)]
+pub struct CrashContextC {
+    /// The full platform [`CrashContext`], only valid for the duration of the
+    /// callback
+    pub context: *const CrashContext,
+    /// The id of the crashing process
+    pub process_id: u32,
+    /// The OS id of the crashing thread
+    pub thread_id: u64,
+    /// The signal number on Linux/Android, the exception code on Windows, or
+    /// the exception type on Macos, which is 0 if there is no exception, eg.
+    /// for a simulated exception
+    pub code: i64,
+}
+
+impl CrashContextC {
+    fn new(cc: &CrashContext) -> Self {
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                let (process_id, code) = (cc.pid as u32, cc.siginfo.ssi_signo.into());
+            } else if #[cfg(target_os = "windows")] {
+                let (process_id, code) = (cc.process_id, cc.exception_code.into());
+            } else if #[cfg(target_os = "macos")] {
+                let (process_id, code) = (
+                    std::process::id(),
+                    cc.exception.map_or(0, |exc| exc.kind.into()),
+                );
+            } else {
+                let (process_id, code) = (0, 0);
+            }
+        }
+
+        Self {
+            context: cc,
+            process_id,
+            thread_id: cc.crashing_thread_id(),
+            code,
+        }
+    }
+}
+
+/// The callback invoked when a crash occurs.
+///
+/// Returning non-zero indicates the crash was handled, ie. the same as
+/// returning `CrashEventResult::Handled(true)` from a [`crate::CrashEvent`].
+///
+/// The callback runs in the same compromised context as a
+/// [`crate::CrashEvent`], see its documentation for the restrictions this
+/// imposes, notably that it must be async signal safe on Linux/Android. The
+/// callback can be invoked from any thread, but is never invoked for more
+/// than one crash at a time. It must not call [`crash_handler_attach`] or
+/// [`crash_handler_detach`].
+pub type CrashHandlerCallback =
+    unsafe extern "C" fn(context: *const CrashContextC, user_data: *mut c_void) -> c_int;
+
+struct Callback {
+    callback: CrashHandlerCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: the caller of `crash_handler_attach` guarantees that `user_data`
+// can be used from any thread
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+unsafe impl crate::CrashEvent for Callback {
+    fn on_crash(&self, context: &CrashContext) -> CrashEventResult {
+        let context = CrashContextC::new(context);
+        // SAFETY: the caller of `crash_handler_attach` guarantees the callback
+        // is valid until it is detached
+        let handled = unsafe { (self.callback)(&context, self.user_data) };
+        (handled != 0).into()
+    }
+}
+
+/// The handler attached via [`crash_handler_attach`]
+static HANDLER: parking_lot::Mutex<Option<CrashHandler>> = parking_lot::const_mutex(None);
+
+/// Attaches the crash handler, see [`CrashHandler::attach`], which invokes
+/// `callback` with `user_data` when a crash occurs.
+///
+/// Returns [`CRASH_HANDLER_OK`] on success, or one of the `CRASH_HANDLER_ERR_*`
+/// codes. This can be called before any Rust code has run, and from any
+/// thread.
+///
+/// # Safety
+///
+/// `user_data` is not read or freed by this crate, only passed to `callback`,
+/// and so is owned by the caller. It must remain valid, and usable from any
+/// thread, until [`crash_handler_detach`] returns.
+#[no_mangle]
+pub unsafe extern "C" fn crash_handler_attach(
+    callback: Option<CrashHandlerCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    let callback = match callback {
+        Some(callback) => callback,
+        None => return CRASH_HANDLER_ERR_INVALID_ARGUMENT,
+    };
+
+    let attached = std::panic::catch_unwind(|| {
+        let mut handler = HANDLER.lock();
+        if handler.is_some() {
+            return Err(Error::HandlerAlreadyInstalled);
+        }
+
+        *handler = Some(CrashHandler::attach(Box::new(Callback {
+            callback,
+            user_data,
+        }))?);
+        Ok(())
+    });
+
+    match attached {
+        Ok(Ok(())) => CRASH_HANDLER_OK,
+        Ok(Err(Error::HandlerAlreadyInstalled)) => CRASH_HANDLER_ERR_ALREADY_ATTACHED,
+        Ok(Err(Error::OutOfMemory)) => CRASH_HANDLER_ERR_OUT_OF_MEMORY,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Ok(Err(Error::AltStack(err))) if err.raw_os_error() == Some(libc::ENOMEM) => {
+            CRASH_HANDLER_ERR_OUT_OF_MEMORY
+        }
+        Ok(Err(Error::Io(_) | Error::Signal { .. })) => CRASH_HANDLER_ERR_IO,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Ok(Err(Error::AltStack(_))) => CRASH_HANDLER_ERR_IO,
+        #[cfg(target_os = "windows")]
+        Ok(Err(Error::StackGuarantee(_))) => CRASH_HANDLER_ERR_IO,
+        #[cfg(target_os = "macos")]
+        Ok(Err(Error::Kernel(_) | Error::ThreadSpawn(_))) => CRASH_HANDLER_ERR_IO,
+        Ok(Err(_)) | Err(_) => CRASH_HANDLER_ERR_OTHER,
+    }
+}
+
+/// Detaches the crash handler attached via [`crash_handler_attach`], after
+/// which the `user_data` passed to it is no longer used. Does nothing if no
+/// handler is attached.
+#[no_mangle]
+pub extern "C" fn crash_handler_detach() {
+    let _detached = std::panic::catch_unwind(|| {
+        if let Some(handler) = HANDLER.lock().take() {
+            let _ = handler.detach();
+        }
+    });
+}
@@ -0,0 +1,85 @@
+//! Saving and restoring of the calling thread's error code(s) around the
+//! handling of a crash, so that the syscalls made while handling it don't
+//! clobber the error code seen by the interrupted code if it resumes, eg. after
+//! a [`crate::CrashEventResult::Jump`]
+
+cfg_if::cfg_if! {
+    if #[cfg(target_os = "windows")] {
+        extern "system" {
+            fn GetLastError() -> u32;
+            fn SetLastError(code: u32);
+        }
+
+        extern "C" {
+            fn _errno() -> *mut i32;
+        }
+
+        /// Retrieves the pointer to the calling thread's `errno`
+        #[inline]
+        pub(crate) unsafe fn errno_location() -> *mut i32 {
+            _errno()
+        }
+    } else {
+        /// Retrieves the pointer to the calling thread's `errno`
+        #[inline]
+        pub(crate) unsafe fn errno_location() -> *mut i32 {
+            cfg_if::cfg_if! {
+                if #[cfg(target_os = "android")] {
+                    libc::__errno()
+                } else if #[cfg(target_os = "macos")] {
+                    libc::__error()
+                } else {
+                    libc::__errno_location()
+                }
+            }
+        }
+    }
+}
+
+/// The error code(s) of the calling thread when it was created, which are
+/// restored when it is dropped.
+///
+/// Since jumping out of a handler doesn't run destructors,
+/// [`SavedErrno::restore`] must be called explicitly before doing so.
+pub(crate) struct SavedErrno {
+    errno: i32,
+    /// The value of `GetLastError`, which is also the value of
+    /// `WSAGetLastError`, as Winsock stores its errors in the same place
+    #[cfg(target_os = "windows")]
+    last_error: u32,
+}
+
+impl SavedErrno {
+    /// Saves the calling thread's error code(s)
+    ///
+    /// # Safety
+    ///
+    /// Must be dropped, or restored, on the same thread
+    #[inline]
+    pub(crate) unsafe fn save() -> Self {
+        Self {
+            errno: *errno_location(),
+            #[cfg(target_os = "windows")]
+            last_error: GetLastError(),
+        }
+    }
+
+    /// Restores the calling thread's error code(s) to when they were saved
+    #[inline]
+    pub(crate) fn restore(&self) {
+        // SAFETY: the location is always valid for the calling thread
+        unsafe {
+            *errno_location() = self.errno;
+
+            #[cfg(target_os = "windows")]
+            SetLastError(self.last_error);
+        }
+    }
+}
+
+impl Drop for SavedErrno {
+    #[inline]
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
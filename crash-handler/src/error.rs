@@ -2,20 +2,58 @@ use std::fmt;
 
 /// An error that can occur when attaching or detaching a [`crate::CrashHandler`]
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
-    /// Unable to `mmap` memory
+    /// Unable to allocate memory
     OutOfMemory,
     /// For simplicity sake, only one [`crate::CrashHandler`] can be registered
     /// at any one time.
     HandlerAlreadyInstalled,
     /// An I/O or other syscall failed
     Io(std::io::Error),
+    /// The signal number can't be handled, eg. because it is already handled
+    /// as a crash, or can't be caught at all
+    InvalidSignal(i32),
+    /// Changing the action for the signal failed
+    Signal {
+        /// The signal whose action couldn't be changed
+        signal: i32,
+        /// The reason the action couldn't be changed
+        source: std::io::Error,
+    },
+    /// Mapping, or installing, the alternate signal stack for the thread that
+    /// attached the handler failed
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    AltStack(std::io::Error),
+    /// Setting the stack guarantee of a thread failed, see
+    /// [`crate::AttachOptions::stack_guarantee`]
+    #[cfg(target_os = "windows")]
+    StackGuarantee(std::io::Error),
+    /// A mach call failed with the specified `kern_return_t`
+    #[cfg(target_os = "macos")]
+    Kernel(mach2::kern_return::kern_return_t),
+    /// Restoring the previous exception port for the exception mask failed
+    /// with the specified `kern_return_t`
+    #[cfg(target_os = "macos")]
+    ExceptionPort(u32, mach2::kern_return::kern_return_t),
+    /// Spawning the thread that receives exceptions failed
+    #[cfg(target_os = "macos")]
+    ThreadSpawn(std::io::Error),
+    /// Crashes can't be caught on the target, eg. `wasm32-unknown-unknown`, so
+    /// the handler can't be attached
+    Unsupported,
 }
 
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::Io(inner) => Some(inner),
+            Self::Io(inner) | Self::Signal { source: inner, .. } => Some(inner),
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Self::AltStack(inner) => Some(inner),
+            #[cfg(target_os = "windows")]
+            Self::StackGuarantee(inner) => Some(inner),
+            #[cfg(target_os = "macos")]
+            Self::ThreadSpawn(inner) => Some(inner),
             _ => None,
         }
     }
@@ -29,6 +67,30 @@ impl fmt::Display for Error {
                 f.write_str("an exception handler is already installed")
             }
             Self::Io(e) => write!(f, "{}", e),
+            Self::InvalidSignal(sig) => write!(f, "signal {sig} can't be handled"),
+            Self::Signal { signal, source } => {
+                write!(
+                    f,
+                    "failed to change the action for signal {signal}: {source}"
+                )
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Self::AltStack(e) => write!(f, "failed to install the alternate signal stack: {e}"),
+            #[cfg(target_os = "windows")]
+            Self::StackGuarantee(e) => write!(f, "failed to set the thread stack guarantee: {e}"),
+            #[cfg(target_os = "macos")]
+            Self::Kernel(code) => write!(f, "{}", crash_context::ipc::Error::Kernel(*code)),
+            #[cfg(target_os = "macos")]
+            Self::ExceptionPort(mask, code) => {
+                write!(
+                    f,
+                    "failed to restore the exception port for mask {mask:#x}: {}",
+                    crash_context::ipc::Error::Kernel(*code)
+                )
+            }
+            #[cfg(target_os = "macos")]
+            Self::ThreadSpawn(e) => write!(f, "failed to spawn the exception handler thread: {e}"),
+            Self::Unsupported => f.write_str("crash handling is not supported on this target"),
         }
     }
 }
@@ -38,3 +100,17 @@ impl From<std::io::Error> for Error {
         Self::Io(e)
     }
 }
+
+/// The error returned when an integer can't be converted into one of the
+/// platform's signal or exception enums, as it doesn't correspond to any of
+/// their variants
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnknownCodeError(pub i64);
+
+impl std::error::Error for UnknownCodeError {}
+
+impl fmt::Display for UnknownCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown signal or exception code {:#x}", self.0)
+    }
+}
@@ -0,0 +1,218 @@
+//! A safer wrapper around [`crate::jmp`] for recovering from crashes
+
+use crate::{jmp, CrashEventResult};
+use std::{
+    cell::UnsafeCell,
+    mem::{ManuallyDrop, MaybeUninit},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// How [`JumpPoint::set`] returned
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Landing<T> {
+    /// The closure returned normally with the specified value
+    Completed(T),
+    /// A crash occurred while the closure was executing, and the crash handler
+    /// jumped back with the specified value, see [`JumpPoint::jump`]
+    Resumed(i32),
+}
+
+struct Inner {
+    buf: UnsafeCell<MaybeUninit<jmp::JmpBuf>>,
+    /// The thread the jump point is currently set on, or 0 if it isn't set
+    owner: AtomicUsize,
+}
+
+// SAFETY: the buffer is only written by the thread that owns the jump point,
+// and is only ever jumped to on that same thread
+unsafe impl Sync for Inner {}
+unsafe impl Send for Inner {}
+
+/// Uniquely identifies the current thread, without allocating, as this is
+/// called from within the signal handler
+#[inline]
+fn current_thread() -> usize {
+    thread_local! {
+        static MARKER: u8 = const { 0 };
+    }
+
+    MARKER.with(|marker| marker as *const u8 as usize)
+}
+
+/// Disarms the jump point however [`JumpPoint::set`] is exited
+struct Disarm<'jp>(&'jp AtomicUsize);
+
+impl Drop for Disarm<'_> {
+    fn drop(&mut self) {
+        self.0.store(0, Ordering::Release);
+    }
+}
+
+/// A point that a crashed thread can jump back to.
+///
+/// Unlike using [`crate::jmp`] directly, the jump buffer is only jumped to by
+/// [`Self::jump`] while the frame that set it is still executing, on the thread
+/// that set it, as jumping anywhere else is undefined behavior.
+///
+/// The buffer is heap allocated, so clones of the jump point, eg. one captured
+/// by the [`crate::CrashEvent`], all refer to the same buffer.
+///
+/// ```no_run
+/// use crash_handler::{CrashContext, CrashHandler, JumpPoint, Landing};
+///
+/// thread_local! {
+///     static JUMP_POINT: JumpPoint = JumpPoint::new();
+/// }
+///
+/// let _handler = CrashHandler::attach(unsafe {
+///     crash_handler::make_crash_event(|_cc: &CrashContext| {
+///         JUMP_POINT
+///             .with(|jp| jp.jump(1))
+///             .unwrap_or(crash_handler::CrashEventResult::Handled(false))
+///     })
+/// })
+/// .unwrap();
+///
+/// let landing = JUMP_POINT.with(|jp| {
+///     jp.set(|| unsafe { sadness_generator::raise_segfault() })
+/// });
+/// assert_eq!(landing, Landing::Resumed(1));
+/// ```
+#[derive(Clone)]
+pub struct JumpPoint {
+    inner: Arc<Inner>,
+}
+
+impl JumpPoint {
+    /// Allocates a jump point, which isn't set until [`Self::set`] is called
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                buf: UnsafeCell::new(MaybeUninit::uninit()),
+                owner: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Sets the jump point on the current thread, then executes the closure.
+    ///
+    /// If a crash occurs on this thread while the closure is executing, and
+    /// the [`crate::CrashEvent`] returns the result of [`Self::jump`], this
+    /// returns [`Landing::Resumed`] with the value passed to [`Self::jump`].
+    /// The jump skips every frame between the crash and this one, so nothing
+    /// owned by those frames, including the closure itself, is dropped.
+    ///
+    /// On Linux, the signal mask is saved and restored by the jump, so signals
+    /// blocked while the signal handler was running don't remain blocked.
+    ///
+    /// # Panics
+    ///
+    /// The jump point is already set, eg. by a call to this method within the
+    /// closure.
+    #[inline(never)]
+    pub fn set<T>(&self, f: impl FnOnce() -> T) -> Landing<T> {
+        let inner = &*self.inner;
+
+        assert!(
+            inner
+                .owner
+                .compare_exchange(0, current_thread(), Ordering::AcqRel, Ordering::Acquire)
+                .is_ok(),
+            "the jump point is already set"
+        );
+        let _disarm = Disarm(&inner.owner);
+
+        // Returning from `sigsetjmp` a second time means the closure was
+        // consumed and then abandoned by the jump, so it must not be dropped
+        let mut f = ManuallyDrop::new(f);
+
+        // SAFETY: the buffer is owned by this thread until `_disarm` is dropped,
+        // which is when this frame is exited
+        let value = unsafe {
+            cfg_if::cfg_if! {
+                if #[cfg(target_os = "windows")] {
+                    jmp::setjmp(inner.buf.get().cast())
+                } else {
+                    jmp::sigsetjmp(inner.buf.get().cast(), 1)
+                }
+            }
+        };
+
+        if value != 0 {
+            return Landing::Resumed(value);
+        }
+
+        // SAFETY: this is the only place the closure is taken, and it can only
+        // be reached once
+        Landing::Completed((unsafe { ManuallyDrop::take(&mut f) })())
+    }
+
+    /// True if the jump point is set on the current thread
+    #[inline]
+    pub fn is_set(&self) -> bool {
+        self.inner.owner.load(Ordering::Acquire) == current_thread()
+    }
+
+    /// Creates the [`CrashEventResult::Jump`] that jumps back to where
+    /// [`Self::set`] was called, with the specified value. Note that if the
+    /// value is 0 it will be corrected to 1.
+    ///
+    /// Returns `None` if the jump point isn't set on the current thread, ie.
+    /// the crash occurred after [`Self::set`] returned, or on another thread,
+    /// in which case the crash needs to be handled in some other way.
+    #[inline]
+    pub fn jump(&self, value: i32) -> Option<CrashEventResult> {
+        self.is_set().then(|| CrashEventResult::Jump {
+            jmp_buf: self.inner.buf.get().cast(),
+            value,
+        })
+    }
+}
+
+impl Default for JumpPoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_set_while_executing() {
+        let jp = JumpPoint::new();
+        assert!(jp.jump(1).is_none());
+
+        let landing = jp.set(|| {
+            assert!(jp.is_set());
+            assert!(jp.jump(1).is_some());
+
+            // Other threads can't jump to this thread's jump point
+            let other = jp.clone();
+            std::thread::spawn(move || other.jump(1).is_none())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(landing, Landing::Completed(true));
+
+        // A late jump is detected rather than jumping into a dead frame
+        assert!(jp.jump(1).is_none());
+    }
+
+    #[test]
+    fn disarmed_by_panic() {
+        let jp = JumpPoint::new();
+
+        let panicked =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| jp.set(|| panic!("oh no"))));
+        assert!(panicked.is_err());
+        assert!(!jp.is_set());
+
+        // It can be set again
+        assert_eq!(jp.set(|| 5), Landing::Completed(5));
+    }
+}
@@ -1,9 +1,32 @@
 #![doc = include_str!("../README.md")]
 #![allow(unsafe_code)]
 
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "windows",
+    target_os = "macos",
+))]
+mod errno;
 mod error;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    all(target_os = "windows", target_arch = "x86_64"),
+))]
+mod jump_point;
+mod main_thread;
 
-pub use error::Error;
+pub use error::{Error, UnknownCodeError};
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    all(target_os = "windows", target_arch = "x86_64"),
+))]
+pub use jump_point::{JumpPoint, Landing};
+pub use main_thread::set_main_thread;
 
 #[cfg(feature = "debug-print")]
 #[macro_export]
@@ -12,12 +35,18 @@ macro_rules! debug_print {
         let cstr = concat!($s, "\n");
         $crate::write_stderr(cstr);
     };
+    ($s:literal, $name:expr) => {
+        $crate::write_stderr($s);
+        $crate::write_stderr($name);
+        $crate::write_stderr("\n");
+    };
 }
 
 #[cfg(not(feature = "debug-print"))]
 #[macro_export]
 macro_rules! debug_print {
     ($s:literal) => {};
+    ($s:literal, $name:expr) => {};
 }
 
 /// Writes the specified string directly to stderr.
@@ -25,17 +54,24 @@ macro_rules! debug_print {
 /// This is safe to be called from within a compromised context.
 #[inline]
 pub fn write_stderr(s: &'static str) {
-    unsafe {
-        #[cfg(target_os = "windows")]
-        libc::write(2, s.as_ptr().cast(), s.len() as u32);
-
-        #[cfg(not(target_os = "windows"))]
-        libc::write(2, s.as_ptr().cast(), s.len());
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "windows")] {
+            unsafe {
+                libc::write(2, s.as_ptr().cast(), s.len() as u32);
+            }
+        } else if #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))] {
+            unsafe {
+                libc::write(2, s.as_ptr().cast(), s.len());
+            }
+        } else {
+            // There is no stderr to write to on other targets
+            let _ = s;
+        }
     }
 }
 
 cfg_if::cfg_if! {
-    if #[cfg(all(unix, not(target_os = "macos")))] {
+    if #[cfg(any(target_os = "linux", target_os = "android"))] {
         /// The sole purpose of the unix module is to hook pthread_create to ensure
         /// an alternate stack is installed for every native thread in case of a
         /// stack overflow. This doesn't apply to MacOS as it uses exception ports,
@@ -46,6 +82,7 @@ cfg_if::cfg_if! {
 }
 
 pub use crash_context::CrashContext;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// The result of the user code executed during a crash event
 pub enum CrashEventResult {
@@ -57,7 +94,8 @@ pub enum CrashEventResult {
         all(target_os = "windows", target_arch = "x86_64"),
     ))]
     /// The handler wishes to jump somewhere else, presumably to return
-    /// execution and skip the code that caused the exception
+    /// execution and skip the code that caused the exception. See
+    /// [`JumpPoint::jump`] for a safer way to create this
     Jump {
         /// The location to jump back to, retrieved via sig/setjmp
         jmp_buf: *mut jmp::JmpBuf,
@@ -103,6 +141,14 @@ impl From<bool> for CrashEventResult {
 /// documentation is terrible) which are handled by a thread owned by the
 /// exception handler which makes them slightly safer to handle than UNIX signals,
 /// but it is again recommended to do as little work as possible.
+///
+/// ## Panics
+///
+/// A panic that escapes [`Self::on_crash`] is caught and treated as if it had
+/// returned `Handled(false)`, since unwinding out of a signal or exception
+/// handler is undefined behavior. Note that this is not possible if built with
+/// `panic = "abort"`, in which case a panic will abort the process without
+/// any further crash handling.
 pub unsafe trait CrashEvent: Send + Sync {
     /// Method invoked when a crash occurs.
     ///
@@ -111,6 +157,139 @@ pub unsafe trait CrashEvent: Send + Sync {
     fn on_crash(&self, context: &CrashContext) -> CrashEventResult;
 }
 
+/// The number of times a [`CrashEvent`] has been invoked, see
+/// [`CrashHandler::crash_count`]
+static CRASH_COUNT: AtomicUsize = AtomicUsize::new(0);
+/// The number of times a [`CrashEvent`] has returned [`CrashEventResult::Jump`],
+/// see [`CrashHandler::recovered_count`]
+static RECOVERED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A hook invoked before the [`CrashEvent`], see
+/// [`CrashHandler::set_pre_crash_hook`]
+pub(crate) type PreCrashHook = Box<dyn Fn(&CrashContext) + Send + Sync>;
+
+/// Invokes the pre-crash hook, if there is one, then the [`CrashEvent`],
+/// treating a panic in the event as `Handled(false)` rather than letting it
+/// unwind out of the signal/exception handler
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "windows",
+    target_os = "macos",
+))]
+#[inline]
+pub(crate) fn call_crash_event(
+    event: &dyn CrashEvent,
+    hook: Option<&PreCrashHook>,
+    context: &CrashContext,
+) -> CrashEventResult {
+    if let Some(hook) = hook {
+        call_pre_crash_hook(hook, context);
+    }
+
+    CRASH_COUNT.fetch_add(1, Ordering::Relaxed);
+
+    let result =
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| event.on_crash(context))) {
+            Ok(result) => result,
+            Err(_payload) => {
+                debug_print!("crash event panicked");
+                CrashEventResult::Handled(false)
+            }
+        };
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        all(target_os = "windows", target_arch = "x86_64"),
+    ))]
+    if let CrashEventResult::Jump { .. } = result {
+        RECOVERED_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    result
+}
+
+/// Invokes the pre-crash hook, ignoring a panic, or on Linux/Android a crash,
+/// so that it doesn't prevent the [`CrashEvent`] from running
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "windows",
+    target_os = "macos",
+))]
+#[inline]
+fn call_pre_crash_hook(hook: &PreCrashHook, context: &CrashContext) {
+    let call = || {
+        if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| hook(context))).is_err() {
+            debug_print!("pre-crash hook panicked");
+        }
+    };
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            // SAFETY: we're always called while handling a crash
+            unsafe { linux::call_recoverable(&call) };
+        } else {
+            call();
+        }
+    }
+}
+
+impl CrashHandler {
+    /// Sets a hook that is invoked with the [`CrashContext`] before the
+    /// [`CrashEvent`] for every crash, including simulated ones, replacing the
+    /// previous hook if there was one.
+    ///
+    /// This allows work that needs to happen before a dump is taken, eg.
+    /// flushing an in-memory log to a file descriptor that was opened up front,
+    /// to be kept separate from the [`CrashEvent`] that requests the dump.
+    ///
+    /// A panic in the hook is caught and ignored, and the [`CrashEvent`] is
+    /// invoked as normal. On Linux/Android, the same is true if the hook itself
+    /// crashes, on other platforms this is treated the same as a crash inside
+    /// the [`CrashEvent`].
+    ///
+    /// The hook is dropped when the handler is detached.
+    ///
+    /// # Safety
+    ///
+    /// The hook runs in the same compromised context as the [`CrashEvent`],
+    /// see its Safety section.
+    pub unsafe fn set_pre_crash_hook(&self, hook: Box<dyn Fn(&CrashContext) + Send + Sync>) {
+        replace_pre_crash_hook(hook);
+    }
+
+    /// The number of crashes the [`CrashEvent`] has been invoked for, including
+    /// simulated ones, since the process started or [`Self::reset_counts`] was
+    /// last called.
+    ///
+    /// The count is kept across handlers, ie. it is not reset when a handler is
+    /// detached and another is attached.
+    #[inline]
+    pub fn crash_count(&self) -> usize {
+        CRASH_COUNT.load(Ordering::Relaxed)
+    }
+
+    /// The number of crashes for which the [`CrashEvent`] returned
+    /// [`CrashEventResult::Jump`], ie. the process recovered from them, since
+    /// the process started or [`Self::reset_counts`] was last called.
+    ///
+    /// This can be used to eg. disable a feature whose crashes are being
+    /// recovered from after a certain number of them.
+    #[inline]
+    pub fn recovered_count(&self) -> usize {
+        RECOVERED_COUNT.load(Ordering::Relaxed)
+    }
+
+    /// Resets both [`Self::crash_count`] and [`Self::recovered_count`] to 0
+    #[inline]
+    pub fn reset_counts(&self) {
+        CRASH_COUNT.store(0, Ordering::Relaxed);
+        RECOVERED_COUNT.store(0, Ordering::Relaxed);
+    }
+}
+
 /// Creates a [`CrashEvent`] using the supplied closure as the implementation.
 ///
 /// The supplied closure will be called for both real crash events as well as
@@ -141,6 +320,73 @@ where
     Box::new(Wrapper { inner: closure })
 }
 
+/// Creates a [`CrashEvent`] using the supplied closure as the implementation.
+///
+/// This uses an `FnMut` closure instead of `Fn` like [`make_crash_event`], so
+/// that the closure can mutate its own state, eg. a "have we already dumped"
+/// flag, without needing to use atomics or locks itself.
+///
+/// Crashes can occur on several threads at the same time, and the closure can
+/// itself crash or simulate a crash, so the closure is guarded by an atomic flag
+/// (not a lock, so this is safe in a compromised context) that ensures it only
+/// ever runs once at a time. If the closure is already running when a crash
+/// occurs, it is not invoked and `Handled(false)` is returned for that crash,
+/// which will typically result in the previous or default handler being run.
+///
+/// # Safety
+///
+/// See the [`CrashEvent`] Safety section for information on why this is `unsafe`.
+#[inline]
+pub unsafe fn make_crash_event_mut<F>(closure: F) -> Box<dyn CrashEvent>
+where
+    F: Send + FnMut(&CrashContext) -> CrashEventResult + 'static,
+{
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct Wrapper<F> {
+        inner: std::cell::UnsafeCell<F>,
+        running: AtomicBool,
+    }
+
+    // SAFETY: the closure is only ever accessed by the thread that sets `running`
+    unsafe impl<F: Send> Sync for Wrapper<F> {}
+
+    unsafe impl<F> CrashEvent for Wrapper<F>
+    where
+        F: Send + FnMut(&CrashContext) -> CrashEventResult,
+    {
+        fn on_crash(&self, context: &CrashContext) -> CrashEventResult {
+            /// Clears the flag even if the closure panics
+            struct Running<'a>(&'a AtomicBool);
+
+            impl Drop for Running<'_> {
+                fn drop(&mut self) {
+                    self.0.store(false, Ordering::Release);
+                }
+            }
+
+            if self
+                .running
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                return false.into();
+            }
+
+            let _running = Running(&self.running);
+
+            // SAFETY: we have exclusive access until `running` is cleared
+            let inner = unsafe { &mut *self.inner.get() };
+            (inner)(context)
+        }
+    }
+
+    Box::new(Wrapper {
+        inner: std::cell::UnsafeCell::new(closure),
+        running: AtomicBool::new(false),
+    })
+}
+
 /// Creates a [`CrashEvent`] using the supplied closure as the implementation.
 ///
 /// This uses an `FnOnce` closure instead of `Fn` like `[make_crash_event]`, but
@@ -185,17 +431,77 @@ cfg_if::cfg_if! {
     if #[cfg(any(target_os = "linux", target_os = "android"))] {
         mod linux;
 
-        pub use linux::{CrashHandler, Signal, jmp};
+        use linux::replace_pre_crash_hook;
+        pub use linux::{
+            AttachOptions, CrashHandler, DumpableGuard, MapRegion, MemoryMaps, Signal,
+            is_dump_request, jmp, memory_maps,
+        };
     } else if #[cfg(target_os = "windows")] {
         mod windows;
 
+        use windows::replace_pre_crash_hook;
         #[cfg(target_arch = "x86_64")]
         pub use windows::jmp;
 
-        pub use windows::{CrashHandler, ExceptionCode};
+        pub use windows::{
+            AttachOptions, CrashHandler, DEFAULT_STACK_GUARANTEE, ExceptionCode, VectoredHandler,
+            register_thread,
+        };
     } else if #[cfg(target_os = "macos")] {
         mod mac;
 
-        pub use mac::{CrashHandler, ExceptionType};
+        use mac::replace_pre_crash_hook;
+        pub use mac::{AttachOptions, CrashHandler, ExceptionType, jmp};
+    } else {
+        mod stub;
+
+        use stub::replace_pre_crash_hook;
+        pub use stub::{AttachOptions, CrashHandler};
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicPtr;
+
+    /// The event, so that the closure can call back into it as if it crashed
+    static EVENT: AtomicPtr<Box<dyn CrashEvent>> = AtomicPtr::new(std::ptr::null_mut());
+
+    #[test]
+    fn mut_event_is_not_reentrant() {
+        let mut crashes = 0;
+        let event = Box::new(unsafe {
+            make_crash_event_mut(move |cc: &CrashContext| {
+                crashes += 1;
+
+                // Crash while handling the crash
+                let nested = (*EVENT.load(Ordering::Relaxed)).on_crash(cc);
+                assert!(matches!(nested, CrashEventResult::Handled(false)));
+
+                (crashes == 2).into()
+            })
+        });
+        EVENT.store(Box::into_raw(event), Ordering::Relaxed);
+
+        let event = unsafe { &*EVENT.load(Ordering::Relaxed) };
+        let cc: CrashContext = unsafe { std::mem::zeroed() };
+
+        // The state is kept across crashes, and the guard is released after
+        // each one
+        assert!(matches!(
+            event.on_crash(&cc),
+            CrashEventResult::Handled(false)
+        ));
+        assert!(matches!(
+            event.on_crash(&cc),
+            CrashEventResult::Handled(true)
+        ));
+        assert!(matches!(
+            event.on_crash(&cc),
+            CrashEventResult::Handled(false)
+        ));
+
+        drop(unsafe { Box::from_raw(EVENT.swap(std::ptr::null_mut(), Ordering::Relaxed)) });
     }
 }
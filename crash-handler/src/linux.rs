@@ -1,10 +1,15 @@
+mod dumpable;
 pub mod jmp;
+mod maps;
 mod state;
 
+pub use dumpable::DumpableGuard;
+pub use maps::{memory_maps, MapRegion, MemoryMaps};
+
 use crate::Error;
 
 /// The signals that we support catching and raising
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(i32)]
 pub enum Signal {
     Abort = libc::SIGABRT,
@@ -16,14 +21,192 @@ pub enum Signal {
 }
 
 impl Signal {
+    /// Every signal, all of which are handled by the [`CrashHandler`]
+    pub const ALL: &'static [Self] = &state::EXCEPTION_SIGNALS;
+
+    /// Sets the action for the signal to `SIG_IGN`
     #[inline]
-    pub fn ignore(self) {
-        unsafe {
-            state::ignore_signal(self);
+    pub fn ignore(self) -> Result<(), Error> {
+        unsafe { state::ignore_signal(self) }
+    }
+
+    /// The conventional name of the signal, eg. `SIGSEGV`
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Abort => "SIGABRT",
+            Self::Bus => "SIGBUS",
+            Self::Fpe => "SIGFPE",
+            Self::Illegal => "SIGILL",
+            Self::Segv => "SIGSEGV",
+            Self::Trap => "SIGTRAP",
         }
     }
 }
 
+impl std::fmt::Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl TryFrom<i32> for Signal {
+    type Error = crate::UnknownCodeError;
+
+    fn try_from(signo: i32) -> Result<Self, Self::Error> {
+        Self::ALL
+            .iter()
+            .find(|sig| **sig as i32 == signo)
+            .copied()
+            .ok_or(crate::UnknownCodeError(signo.into()))
+    }
+}
+
+impl TryFrom<u32> for Signal {
+    type Error = crate::UnknownCodeError;
+
+    /// Converts eg. [`libc::signalfd_siginfo::ssi_signo`] into a [`Signal`]
+    fn try_from(signo: u32) -> Result<Self, Self::Error> {
+        i32::try_from(signo)
+            .ok()
+            .and_then(|signo| Self::try_from(signo).ok())
+            .ok_or(crate::UnknownCodeError(signo.into()))
+    }
+}
+
+/// Options for [`CrashHandler::attach_with_options`]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct AttachOptions {
+    pub(crate) dump_request_signal: Option<i32>,
+    pub(crate) exit_code_on_handled: Option<i32>,
+    pub(crate) chain_previous_handler: bool,
+    pub(crate) reinstall_after_handled: bool,
+    pub(crate) memory_maps_capacity: Option<usize>,
+    pub(crate) one_shot: bool,
+}
+
+impl AttachOptions {
+    /// Registers an additional signal, eg. `SIGRTMIN + 3`, which another
+    /// process can send to ask this process to dump itself.
+    ///
+    /// When the signal is received, the [`crate::CrashEvent`] is invoked with
+    /// a [`crate::CrashContext`] captured via `getcontext` in the signal
+    /// handler, and [`is_dump_request`] will return true for that context.
+    ///
+    /// Unlike crash signals, the signal is never re-raised, so the process
+    /// continues running regardless of the result of the callback. If the
+    /// callback returns `Handled(false)` and a handler was already installed
+    /// for the signal, it is invoked instead.
+    ///
+    /// The signal can't be one of the signals in [`Signal::ALL`], nor one that
+    /// can't be caught, otherwise [`Error::InvalidSignal`] is returned when
+    /// attaching.
+    #[inline]
+    pub fn dump_request_signal(mut self, signal: i32) -> Self {
+        self.dump_request_signal = Some(signal);
+        self
+    }
+
+    /// Exits the process with the specified code via `_exit` after the
+    /// [`crate::CrashEvent`] returns `Handled(true)` for a crash, rather than
+    /// restoring the default handler and re-raising the signal.
+    ///
+    /// This allows a parent process to distinguish between a crash that was
+    /// handled, eg. a minidump was written, and one that wasn't, but note that
+    /// the process will no longer be killed by the original signal, so eg. a
+    /// core dump will not be generated.
+    ///
+    /// This does not apply to [`CrashHandler::simulate_signal`] or dump requests.
+    #[inline]
+    pub fn exit_code_on_handled(mut self, code: i32) -> Self {
+        self.exit_code_on_handled = Some(code);
+        self
+    }
+
+    /// When the [`crate::CrashEvent`] returns `Handled(false)` for a crash,
+    /// directly invokes the handler that was installed before ours with the
+    /// original `siginfo_t` and `ucontext_t`, rather than restoring it and
+    /// relying on the signal being raised again.
+    ///
+    /// This is useful when the previous handler needs to see the exact state
+    /// of the crash, eg. Android's debuggerd writing a tombstone. Its
+    /// `sa_mask`, `SA_NODEFER`, and `SA_RESETHAND` are honored as if the
+    /// kernel had invoked it, but it runs on our alternate signal stack. The
+    /// previous handler is then responsible for what happens to the process. If it was
+    /// the default or ignore action, the signal is re-raised as usual.
+    #[inline]
+    pub fn chain_previous_handler(mut self, chain: bool) -> Self {
+        self.chain_previous_handler = chain;
+        self
+    }
+
+    /// Keeps our handler installed after the [`crate::CrashEvent`] returns
+    /// `Handled(true)` for a crash, rather than restoring the default handler
+    /// and re-raising the signal, so that later crashes are still caught, eg.
+    /// in a long-lived process that recovers from a crash by other means.
+    ///
+    /// **This is a footgun.** Returning from the signal handler for a fault
+    /// resumes execution at the faulting instruction, which will fault again
+    /// unless the callback has fixed its cause, eg. by making the faulting
+    /// page accessible, invoking the callback over and over forever. Only use
+    /// this if the callback never returns `Handled(true)` for a crash it
+    /// hasn't fixed, eg. it always returns [`crate::CrashEventResult::Jump`],
+    /// or exits the crashing thread itself.
+    ///
+    /// Signals sent to the process, eg. via `kill` or `abort`, are not raised
+    /// again, so the process continues running. [`Self::exit_code_on_handled`]
+    /// takes precedence over this.
+    #[inline]
+    pub fn reinstall_after_handled(mut self, reinstall: bool) -> Self {
+        self.reinstall_after_handled = reinstall;
+        self
+    }
+
+    /// Takes a snapshot of `/proc/self/maps` when attaching, holding at most
+    /// `capacity` mappings, which can then be queried from within
+    /// [`crate::CrashEvent::on_crash`] via [`memory_maps`], eg. to determine
+    /// if the fault address is in a loaded module or near a stack guard page.
+    ///
+    /// The snapshot is not updated automatically, so call
+    /// [`CrashHandler::refresh_memory_maps`] after eg. loading plugins. See
+    /// [`MemoryMaps`] for what happens if there are more than `capacity`
+    /// mappings.
+    #[inline]
+    pub fn cache_memory_maps(mut self, capacity: usize) -> Self {
+        self.memory_maps_capacity = Some(capacity);
+        self
+    }
+
+    /// Installs the handlers with `SA_RESETHAND | SA_NODEFER`, so that the
+    /// kernel resets the action for a signal to the default as it delivers it
+    /// to us, rather than us restoring the default handler from within the
+    /// signal handler after the [`crate::CrashEvent`] returns `Handled(true)`,
+    /// which can fail, eg. if a seccomp filter disallows `sigaction`.
+    ///
+    /// This means only the first crash of each signal is ever seen by the
+    /// callback, even if it returns [`crate::CrashEventResult::Jump`], any
+    /// later crash with the same signal is handled by the default action, ie.
+    /// terminates the process. Signals that haven't been delivered yet are
+    /// still handled as usual, and `Handled(false)` still restores the
+    /// previous handlers.
+    ///
+    /// [`Self::reinstall_after_handled`] is ignored in this mode.
+    #[inline]
+    pub fn one_shot(mut self, one_shot: bool) -> Self {
+        self.one_shot = one_shot;
+        self
+    }
+}
+
+/// Returns true if the context was created in response to the signal registered
+/// via [`AttachOptions::dump_request_signal`], rather than due to a crash.
+///
+/// This is safe to call from within [`crate::CrashEvent::on_crash`].
+#[inline]
+pub fn is_dump_request(context: &crate::CrashContext) -> bool {
+    state::dump_request_signal() == Some(context.siginfo.ssi_signo as i32)
+}
+
 /// A Linux/Android signal handler
 pub struct CrashHandler;
 
@@ -39,17 +222,40 @@ impl CrashHandler {
     /// to not perform actions that may fail due to corrupted state that caused
     /// or is a symptom of the original signal. This includes doing heap
     /// allocations from the same allocator as the crashing code.
+    ///
+    /// If the callback itself crashes, the default action is restored for
+    /// every signal and the nested signal is re-raised immediately, so that
+    /// the process is terminated by it, rather than it being deferred until
+    /// the callback returns, or delivered to the previously installed
+    /// handlers.
     pub fn attach(on_crash: Box<dyn crate::CrashEvent>) -> Result<Self, Error> {
-        state::attach(on_crash)?;
+        Self::attach_with_options(on_crash, AttachOptions::default())
+    }
+
+    /// Attaches the signal handler with the specified options.
+    ///
+    /// See [`Self::attach`] for details.
+    pub fn attach_with_options(
+        on_crash: Box<dyn crate::CrashEvent>,
+        options: AttachOptions,
+    ) -> Result<Self, Error> {
+        state::attach(on_crash, options)?;
         Ok(Self)
     }
 
     /// Detaches the handler.
     ///
     /// This is done automatically when this [`CrashHandler`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// The handler is always detached, but if the handler that was installed
+    /// for a signal before we attached can't be restored, the default action
+    /// is installed for it instead, and [`Error::Signal`] is returned for
+    /// the first such signal.
     #[inline]
-    pub fn detach(self) {
-        state::detach();
+    pub fn detach(self) -> Result<(), Error> {
+        state::detach()
     }
 
     /// Set the process that is allowed to perform `ptrace` operations on the
@@ -70,6 +276,9 @@ impl CrashHandler {
     /// is 1 "restricted ptrace", but there is no harm in setting this if it is
     /// in another mode.
     ///
+    /// The process is only allowed to `ptrace` this process for the duration
+    /// of the [`crate::CrashEvent`], see [`DumpableGuard`] to extend it.
+    ///
     /// See <https://www.kernel.org/doc/Documentation/security/Yama.txt> for
     /// the full documentation.
     #[inline]
@@ -81,6 +290,16 @@ impl CrashHandler {
         }
     }
 
+    /// Retakes the snapshot of the memory map, if it was enabled via
+    /// [`AttachOptions::cache_memory_maps`].
+    ///
+    /// This should be called after the memory map changes in ways that are
+    /// interesting to the [`crate::CrashEvent`], eg. after loading plugins.
+    #[inline]
+    pub fn refresh_memory_maps(&self) -> Result<(), Error> {
+        Ok(maps::refresh()?)
+    }
+
     /// Sends the specified user signal.
     pub fn simulate_signal(&self, signal: u32) -> crate::CrashEventResult {
         // Normally this would be an unsafe function, since this unsafe encompasses
@@ -99,7 +318,7 @@ impl CrashHandler {
             let lock = state::HANDLER.lock();
             if let Some(handler) = &*lock {
                 handler.handle_signal(
-                    &mut *(&mut siginfo as *mut libc::signalfd_siginfo).cast::<libc::siginfo_t>(),
+                    &siginfo,
                     &mut *(&mut context as *mut crash_context::ucontext_t).cast::<libc::c_void>(),
                 )
             } else {
@@ -109,8 +328,111 @@ impl CrashHandler {
     }
 }
 
+/// See [`crate::CrashHandler::set_pre_crash_hook`]
+pub(crate) fn replace_pre_crash_hook(hook: crate::PreCrashHook) {
+    if let Some(handler) = &mut *state::HANDLER.lock() {
+        handler.pre_crash_hook = Some(hook);
+    }
+}
+
+pub(crate) use state::call_recoverable;
+
 impl Drop for CrashHandler {
     fn drop(&mut self) {
-        state::detach();
+        let _ = state::detach();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Signal;
+
+    #[test]
+    fn round_trips() {
+        for &sig in Signal::ALL {
+            // Ensures this test is updated if a variant is added
+            match sig {
+                Signal::Abort
+                | Signal::Bus
+                | Signal::Fpe
+                | Signal::Illegal
+                | Signal::Segv
+                | Signal::Trap => {}
+            }
+
+            assert_eq!(Signal::try_from(sig as i32), Ok(sig));
+            assert_eq!(Signal::try_from(sig as u32), Ok(sig));
+            assert_eq!(sig.to_string(), sig.name());
+        }
+
+        assert_eq!(Signal::ALL.len(), 6);
+        assert_eq!(Signal::Segv.to_string(), "SIGSEGV");
+    }
+
+    /// Retrieves the action currently installed for every exception signal
+    fn current_actions() -> Vec<usize> {
+        Signal::ALL
+            .iter()
+            .map(|sig| unsafe {
+                let mut sa: libc::sigaction = std::mem::zeroed();
+                assert_eq!(libc::sigaction(*sig as i32, std::ptr::null(), &mut sa), 0);
+                sa.sa_sigaction
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_sigaction_errors() {
+        use std::sync::atomic::Ordering;
+
+        fn event() -> Box<dyn crate::CrashEvent> {
+            unsafe { crate::make_crash_event(|_cc: &crate::CrashContext| false.into()) }
+        }
+
+        let before = current_actions();
+
+        // The handlers installed before the failing signal are backed out
+        super::state::UNMODIFIABLE_SIGNAL.store(libc::SIGILL, Ordering::Relaxed);
+        match super::CrashHandler::attach(event()) {
+            Err(crate::Error::Signal { signal, source }) => {
+                assert_eq!(signal, libc::SIGILL);
+                assert_eq!(source.raw_os_error(), Some(libc::EPERM));
+            }
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(_) => panic!("attached despite SIGILL being unmodifiable"),
+        }
+        assert_eq!(current_actions(), before);
+
+        super::state::UNMODIFIABLE_SIGNAL.store(0, Ordering::Relaxed);
+        let handler = super::CrashHandler::attach(event()).unwrap();
+        assert_ne!(current_actions(), before);
+
+        // The handler is still detached, with the default action installed for
+        // the signal that couldn't be restored, which was also the previous one
+        super::state::UNMODIFIABLE_SIGNAL.store(libc::SIGFPE, Ordering::Relaxed);
+        match handler.detach() {
+            Err(crate::Error::Signal { signal, .. }) => assert_eq!(signal, libc::SIGFPE),
+            other => panic!("unexpected result: {other:?}"),
+        }
+        super::state::UNMODIFIABLE_SIGNAL.store(0, Ordering::Relaxed);
+
+        assert_eq!(current_actions(), before);
+
+        // Since the handler was detached, it can be attached again
+        super::CrashHandler::attach(event())
+            .unwrap()
+            .detach()
+            .unwrap();
+        assert_eq!(current_actions(), before);
+    }
+
+    #[test]
+    fn rejects_unknown() {
+        assert_eq!(
+            Signal::try_from(libc::SIGKILL),
+            Err(crate::UnknownCodeError(libc::SIGKILL.into()))
+        );
+        assert!(Signal::try_from(0i32).is_err());
+        assert!(Signal::try_from(u32::MAX).is_err());
     }
 }
@@ -0,0 +1,85 @@
+//! Allows another process, eg. the one writing a minidump, to `ptrace` the
+//! current process, see [`DumpableGuard`]
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// We define these constants ourselves rather than use libc as they are missing
+/// from eg. Android
+const PR_GET_DUMPABLE: i32 = 3;
+const PR_SET_DUMPABLE: i32 = 4;
+const PR_SET_PTRACER: i32 = 0x59616d61;
+const PR_SET_PTRACER_ANY: i32 = -1;
+
+/// The number of [`DumpableGuard`]s that are alive
+static GUARDS: AtomicUsize = AtomicUsize::new(0);
+/// Whether the process was dumpable before the outermost guard was created
+static WAS_DUMPABLE: AtomicBool = AtomicBool::new(true);
+
+/// Sets the process as dumpable, and the process that is allowed to `ptrace`
+/// it, for as long as the guard is alive, returning both to their original
+/// state when the last guard is dropped.
+///
+/// The [`crate::CrashHandler`] holds one of these, with the process set via
+/// [`crate::CrashHandler::set_ptracer`], for the duration of the
+/// [`crate::CrashEvent`], which is enough when the event blocks until the
+/// minidump has been written, eg. `minidumper::Client::request_dump`, which
+/// waits on the server to acknowledge it. If the minidump is written after the
+/// event returns, or outside of a crash, eg. for an on demand dump, hold a
+/// guard until it has been written instead, otherwise the writer fails to
+/// attach with `EPERM` partway through.
+///
+/// Guards can be nested, but only the outermost one sets the state, so the
+/// ptracer of an inner guard is ignored.
+///
+/// This only needs to be done if `/proc/sys/kernel/yama/ptrace_scope` is 1, or
+/// if the process is not dumpable, eg. because it changed its credentials, but
+/// there is no harm in doing so in other cases.
+pub struct DumpableGuard {
+    _private: (),
+}
+
+impl DumpableGuard {
+    /// Allows the specified process to `ptrace` the current one, or any process
+    /// if `None`, which _somewhat_ defeats the purpose of the Yama security
+    /// module that this is needed for.
+    ///
+    /// This only performs syscalls, so it is safe to call from within a signal
+    /// handler.
+    pub fn new(ptracer: Option<u32>) -> Self {
+        if GUARDS.fetch_add(1, Ordering::AcqRel) == 0 {
+            // SAFETY: syscalls
+            unsafe {
+                let was_dumpable = libc::syscall(libc::SYS_prctl, PR_GET_DUMPABLE, 0, 0, 0, 0) > 0;
+                WAS_DUMPABLE.store(was_dumpable, Ordering::Relaxed);
+
+                if !was_dumpable {
+                    libc::syscall(libc::SYS_prctl, PR_SET_DUMPABLE, 1, 0, 0, 0);
+                }
+
+                // Note that this will fail with EINVAL if the pid does not
+                // exist, but that would be on the user
+                let ptracer = ptracer.map_or(PR_SET_PTRACER_ANY, |pid| pid as i32);
+                libc::syscall(libc::SYS_prctl, PR_SET_PTRACER, ptracer, 0, 0, 0);
+            }
+        }
+
+        Self { _private: () }
+    }
+}
+
+impl Drop for DumpableGuard {
+    fn drop(&mut self) {
+        if GUARDS.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+
+        // SAFETY: syscalls
+        unsafe {
+            libc::syscall(libc::SYS_prctl, PR_SET_PTRACER, 0, 0, 0, 0);
+
+            if !WAS_DUMPABLE.load(Ordering::Relaxed) {
+                libc::syscall(libc::SYS_prctl, PR_SET_DUMPABLE, 0, 0, 0, 0);
+            }
+        }
+    }
+}
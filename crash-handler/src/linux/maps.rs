@@ -0,0 +1,246 @@
+//! A snapshot of the memory map of the current process, taken ahead of time so
+//! that it can be queried from within a signal handler.
+
+use std::{io, path::PathBuf};
+
+/// A single mapping from `/proc/self/maps`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapRegion {
+    /// The first address of the mapping
+    pub start: usize,
+    /// The address after the last address of the mapping
+    pub end: usize,
+    /// The offset into the mapped file, 0 for anonymous mappings
+    pub offset: u64,
+    /// The mapping can be read
+    pub readable: bool,
+    /// The mapping can be written
+    pub writable: bool,
+    /// The mapping can be executed
+    pub executable: bool,
+    /// The mapping is shared rather than private (copy on write)
+    pub shared: bool,
+    /// The inode of the mapped file, 0 for anonymous mappings
+    pub inode: u64,
+    /// The path of the mapped file, or a pseudo-path such as `[stack]` or
+    /// `[vdso]`. `None` for anonymous mappings.
+    pub pathname: Option<PathBuf>,
+}
+
+impl MapRegion {
+    /// True if the address lies within this mapping
+    #[inline]
+    pub fn contains(&self, addr: usize) -> bool {
+        addr >= self.start && addr < self.end
+    }
+
+    /// Parses a single line of `/proc/<pid>/maps`, eg.
+    ///
+    /// `7f0c2a1e2000-7f0c2a1e4000 r-xp 00002000 08:01 1234   /usr/lib/libfoo.so`
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(6, ' ');
+
+        let (start, end) = fields.next()?.split_once('-')?;
+        let perms = fields.next()?.as_bytes();
+        let offset = fields.next()?;
+        let _device = fields.next()?;
+        let inode = fields.next()?;
+        // The pathname is padded with spaces, but can also contain them
+        let pathname = fields.next().map(str::trim_start).unwrap_or_default();
+
+        if perms.len() < 4 {
+            return None;
+        }
+
+        Some(Self {
+            start: usize::from_str_radix(start, 16).ok()?,
+            end: usize::from_str_radix(end, 16).ok()?,
+            offset: u64::from_str_radix(offset, 16).ok()?,
+            readable: perms[0] == b'r',
+            writable: perms[1] == b'w',
+            executable: perms[2] == b'x',
+            shared: perms[3] == b's',
+            inode: inode.parse().ok()?,
+            pathname: (!pathname.is_empty()).then(|| PathBuf::from(pathname)),
+        })
+    }
+}
+
+/// A fixed capacity, sorted snapshot of the memory map of the current process.
+///
+/// All allocation is done when the snapshot is taken, so [`Self::region_for`]
+/// is safe to call from within a signal handler, eg. in
+/// [`crate::CrashEvent::on_crash`].
+///
+/// If the process has more mappings than the capacity, only the mappings with
+/// the lowest addresses are kept, and [`Self::is_truncated`] returns true.
+/// Lookups of addresses above the last kept mapping will then return `None`,
+/// notably including the main thread's `[stack]`, which is usually mapped near
+/// the top of the address space.
+#[derive(Debug)]
+pub struct MemoryMaps {
+    regions: Vec<MapRegion>,
+    capacity: usize,
+    truncated: bool,
+}
+
+impl MemoryMaps {
+    /// Takes a snapshot of `/proc/self/maps`, keeping at most `capacity`
+    /// mappings.
+    ///
+    /// Most processes have between a few hundred and a few thousand mappings.
+    pub fn snapshot(capacity: usize) -> io::Result<Self> {
+        let mut maps = Self {
+            regions: Vec::with_capacity(capacity),
+            capacity,
+            truncated: false,
+        };
+
+        maps.refresh()?;
+        Ok(maps)
+    }
+
+    /// Retakes the snapshot, eg. after loading or unloading shared libraries,
+    /// reusing the existing buffer.
+    ///
+    /// If reading the maps fails, the previous snapshot is kept.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let maps = std::fs::read_to_string("/proc/self/maps")?;
+
+        self.regions.clear();
+        self.truncated = false;
+
+        for region in maps.lines().filter_map(MapRegion::parse) {
+            if self.regions.len() == self.capacity {
+                self.truncated = true;
+                break;
+            }
+
+            self.regions.push(region);
+        }
+
+        // The kernel already sorts the maps by address, but binary searching
+        // relies on that, so don't take it on faith
+        self.regions.sort_unstable_by_key(|region| region.start);
+        Ok(())
+    }
+
+    /// Retrieves the mapping that contains the address, if any.
+    ///
+    /// This does not allocate or take any locks, so is safe to call from
+    /// within a signal handler.
+    #[inline]
+    pub fn region_for(&self, addr: usize) -> Option<&MapRegion> {
+        let index = self.regions.partition_point(|region| region.end <= addr);
+        self.regions
+            .get(index)
+            .filter(|region| region.contains(addr))
+    }
+
+    /// All of the mappings in the snapshot, sorted by address
+    #[inline]
+    pub fn regions(&self) -> &[MapRegion] {
+        &self.regions
+    }
+
+    /// True if the process had more mappings than the capacity when the
+    /// snapshot was taken
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// The snapshot taken if [`super::AttachOptions::cache_memory_maps`] was set.
+///
+/// This is only ever written outside of the signal handler, and only ever read
+/// via `try_read`, so a crash while it is being refreshed can't deadlock.
+static MEMORY_MAPS: parking_lot::RwLock<Option<MemoryMaps>> = parking_lot::const_rwlock(None);
+
+pub(super) fn cache(capacity: usize) -> io::Result<()> {
+    let maps = MemoryMaps::snapshot(capacity)?;
+    *MEMORY_MAPS.write() = Some(maps);
+    Ok(())
+}
+
+pub(super) fn refresh() -> io::Result<()> {
+    match &mut *MEMORY_MAPS.write() {
+        Some(maps) => maps.refresh(),
+        None => Ok(()),
+    }
+}
+
+pub(super) fn clear() {
+    MEMORY_MAPS.write().take();
+}
+
+/// Retrieves the memory map snapshot taken when the [`super::CrashHandler`]
+/// was attached with [`super::AttachOptions::cache_memory_maps`], or last
+/// refreshed via [`super::CrashHandler::refresh_memory_maps`].
+///
+/// Returns `None` if the maps are not cached, or if they are currently being
+/// refreshed, eg. because another thread crashed in the middle of a refresh.
+///
+/// This is safe to call from within [`crate::CrashEvent::on_crash`].
+#[inline]
+pub fn memory_maps() -> Option<parking_lot::MappedRwLockReadGuard<'static, MemoryMaps>> {
+    let guard = MEMORY_MAPS.try_read()?;
+    parking_lot::RwLockReadGuard::try_map(guard, Option::as_ref).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MapRegion, MemoryMaps};
+
+    #[test]
+    fn parses_lines() {
+        let region = MapRegion::parse(
+            "7f0c2a1e2000-7f0c2a1e4000 r-xp 00002000 08:01 1234                       /usr/lib/lib foo.so",
+        )
+        .unwrap();
+
+        assert_eq!(
+            region,
+            MapRegion {
+                start: 0x7f0c2a1e2000,
+                end: 0x7f0c2a1e4000,
+                offset: 0x2000,
+                readable: true,
+                writable: false,
+                executable: true,
+                shared: false,
+                inode: 1234,
+                pathname: Some("/usr/lib/lib foo.so".into()),
+            }
+        );
+
+        let anon = MapRegion::parse("7f0c2a1e4000-7f0c2a1e6000 rw-s 00000000 00:00 0 ").unwrap();
+        assert!(anon.writable && anon.shared && anon.pathname.is_none());
+
+        assert!(MapRegion::parse("").is_none());
+        assert!(MapRegion::parse("nonsense r-xp 0 0 0").is_none());
+    }
+
+    #[test]
+    fn finds_regions() {
+        let maps = MemoryMaps::snapshot(8 * 1024).unwrap();
+        assert!(!maps.is_truncated());
+
+        let code = maps
+            .region_for(finds_regions as *const () as usize)
+            .expect("test code is mapped");
+        assert!(code.executable);
+
+        let local = 0u64;
+        let stack = maps
+            .region_for(&local as *const u64 as usize)
+            .expect("the stack is mapped");
+        assert!(stack.readable && stack.writable && !stack.executable);
+
+        assert!(maps.region_for(0).is_none());
+
+        let truncated = MemoryMaps::snapshot(1).unwrap();
+        assert!(truncated.is_truncated());
+        assert_eq!(truncated.regions(), &maps.regions()[..1]);
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{Error, Signal};
-use std::{mem, ptr};
+use std::{
+    mem, ptr,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+};
 
 // std::cmp::max is not const :(
 const fn get_stack_size() -> usize {
@@ -23,6 +26,10 @@ pub(crate) const SI_USER: i32 = 0;
 struct StackSave {
     old: Option<libc::stack_t>,
     new: libc::stack_t,
+    /// The start of the mapping, ie. the guard page that precedes the stack
+    map_base: *mut libc::c_void,
+    /// The length of the entire mapping, including the guard page
+    map_len: usize,
 }
 
 unsafe impl Send for StackSave {}
@@ -35,13 +42,9 @@ pub unsafe fn install_sigaltstack() -> Result<(), Error> {
     // Check to see if the existing sigaltstack, and if it exists, is it big
     // enough. If so we don't need to allocate our own.
     let mut old_stack = mem::zeroed();
-    let r = libc::sigaltstack(ptr::null(), &mut old_stack);
-    assert_eq!(
-        r,
-        0,
-        "learning about sigaltstack failed: {}",
-        std::io::Error::last_os_error()
-    );
+    if libc::sigaltstack(ptr::null(), &mut old_stack) != 0 {
+        return Err(Error::AltStack(std::io::Error::last_os_error()));
+    }
 
     if old_stack.ss_flags & libc::SS_DISABLE == 0 && old_stack.ss_size >= SIG_STACK_SIZE {
         return Ok(());
@@ -52,16 +55,18 @@ pub unsafe fn install_sigaltstack() -> Result<(), Error> {
     let guard_size = libc::sysconf(libc::_SC_PAGESIZE) as usize;
     let alloc_size = guard_size + SIG_STACK_SIZE;
 
+    // MAP_STACK is a no-op on most kernels, but hardened ones may require
+    // it, and it lets eg. MTE treat the mapping as a stack
     let ptr = libc::mmap(
         ptr::null_mut(),
         alloc_size,
         libc::PROT_NONE,
-        libc::MAP_PRIVATE | libc::MAP_ANON,
+        libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_STACK,
         -1,
         0,
     );
     if ptr == libc::MAP_FAILED {
-        return Err(Error::OutOfMemory);
+        return Err(Error::AltStack(std::io::Error::last_os_error()));
     }
 
     // Prepare the stack with readable/writable memory and then register it
@@ -72,28 +77,28 @@ pub unsafe fn install_sigaltstack() -> Result<(), Error> {
         SIG_STACK_SIZE,
         libc::PROT_READ | libc::PROT_WRITE,
     );
-    assert_eq!(
-        r,
-        0,
-        "mprotect to configure memory for sigaltstack failed: {}",
-        std::io::Error::last_os_error()
-    );
+    if r != 0 {
+        let err = std::io::Error::last_os_error();
+        libc::munmap(ptr, alloc_size);
+        return Err(Error::AltStack(err));
+    }
     let new_stack = libc::stack_t {
         ss_sp: stack_ptr,
         ss_flags: 0,
         ss_size: SIG_STACK_SIZE,
     };
     let r = libc::sigaltstack(&new_stack, ptr::null_mut());
-    assert_eq!(
-        r,
-        0,
-        "registering new sigaltstack failed: {}",
-        std::io::Error::last_os_error()
-    );
+    if r != 0 {
+        let err = std::io::Error::last_os_error();
+        libc::munmap(ptr, alloc_size);
+        return Err(Error::AltStack(err));
+    }
 
     *STACK_SAVE.lock() = Some(StackSave {
         old: (old_stack.ss_flags & libc::SS_DISABLE != 0).then_some(old_stack),
         new: new_stack,
+        map_base: ptr,
+        map_len: alloc_size,
     });
 
     Ok(())
@@ -126,7 +131,9 @@ pub unsafe fn restore_sigaltstack() {
             }
         }
 
-        let r = libc::munmap(ss.new.ss_sp, ss.new.ss_size);
+        // The guard page that precedes the stack was mapped along with it, and
+        // must be unmapped with it, otherwise every attach leaks a mapping
+        let r = libc::munmap(ss.map_base, ss.map_len);
         debug_assert_eq!(r, 0, "munmap failed during thread shutdown");
         *ssl = None;
     }
@@ -135,43 +142,281 @@ pub unsafe fn restore_sigaltstack() {
 /// Restores the signal handler for the specified signal back to its default
 /// handler, which _should_ perform the default signal action as seen in
 /// <https://man7.org/linux/man-pages/man7/signal.7.html>
+///
+/// This is called from within the signal handler, where failing to restore the
+/// default action would mean the signal is delivered to us again in a loop, so
+/// if the libc wrapper fails, the `rt_sigaction` syscall is made directly.
 #[inline]
 unsafe fn install_default_handler(sig: Signal) {
-    set_handler(sig, libc::SIG_DFL);
+    if set_handler(sig, libc::SIG_DFL).is_err() {
+        let dfl = KernelSigaction {
+            handler: libc::SIG_DFL,
+            flags: 0,
+            restorer: 0,
+            mask: 0,
+        };
+
+        libc::syscall(
+            libc::SYS_rt_sigaction,
+            sig as i32,
+            &dfl,
+            ptr::null_mut::<KernelSigaction>(),
+            mem::size_of::<u64>(),
+        );
+    }
+}
+
+#[inline]
+pub(crate) unsafe fn ignore_signal(sig: Signal) -> Result<(), Error> {
+    set_handler(sig, libc::SIG_IGN)
+}
+
+/// Sets the action for the signal to the specified disposition, ie. `SIG_DFL`
+/// or `SIG_IGN`.
+///
+/// Unlike `signal`, this always uses `sigaction`, like every other change to
+/// the handlers, with flags that don't depend on the libc, ie. interrupted
+/// syscalls are restarted, and the disposition isn't reset upon delivery.
+unsafe fn set_handler(sig: Signal, action: usize) -> Result<(), Error> {
+    let mut sa: libc::sigaction = mem::zeroed();
+    libc::sigemptyset(&mut sa.sa_mask);
+    sa.sa_sigaction = action;
+    sa.sa_flags = libc::SA_RESTART;
+
+    if sys_sigaction(sig as i32, Some(&sa), None) {
+        Ok(())
+    } else {
+        Err(sigaction_error(sig as i32))
+    }
 }
 
+/// Creates the error for a failure to change the action for the signal, from
+/// the current `errno`
 #[inline]
-pub(crate) unsafe fn ignore_signal(sig: Signal) {
-    set_handler(sig, libc::SIG_IGN);
+fn sigaction_error(sig: i32) -> Error {
+    Error::Signal {
+        signal: sig,
+        source: std::io::Error::last_os_error(),
+    }
+}
+
+/// The `struct sigaction` used by the `rt_sigaction` syscall, which differs
+/// from the one exposed by bionic on every architecture, and from the one
+/// exposed by glibc and musl on most
+#[repr(C)]
+struct KernelSigaction {
+    handler: usize,
+    flags: libc::c_ulong,
+    restorer: usize,
+    mask: u64,
+}
+
+// The function that returns from a signal handler via `rt_sigreturn`, which
+// the kernel requires userspace to provide on x86, for actions that are set
+// without one, see `sys_sigaction`
+#[cfg(all(target_os = "android", target_arch = "x86_64"))]
+std::arch::global_asm! {
+    ".text",
+    ".global crash_handler_restore_rt",
+    ".hidden crash_handler_restore_rt",
+    ".align 16",
+    ".type crash_handler_restore_rt, @function",
+"crash_handler_restore_rt:",
+    "mov $15, %rax", // __NR_rt_sigreturn
+    "syscall",
+    ".size crash_handler_restore_rt, . - crash_handler_restore_rt",
+    options(att_syntax)
+}
+
+#[cfg(all(target_os = "android", target_arch = "x86"))]
+std::arch::global_asm! {
+    ".text",
+    ".global crash_handler_restore_rt",
+    ".hidden crash_handler_restore_rt",
+    ".align 16",
+    ".type crash_handler_restore_rt, @function",
+"crash_handler_restore_rt:",
+    "mov $173, %eax", // __NR_rt_sigreturn
+    "int $0x80",
+    ".size crash_handler_restore_rt, . - crash_handler_restore_rt",
+    options(att_syntax)
+}
+
+#[cfg(all(
+    target_os = "android",
+    any(target_arch = "x86", target_arch = "x86_64")
+))]
+extern "C" {
+    fn crash_handler_restore_rt();
 }
 
-unsafe fn set_handler(sig: Signal, action: usize) {
-    // Android L+ expose signal and sigaction symbols that override the system
-    // ones. There is a bug in these functions where a request to set the handler
-    // to SIG_DFL is ignored. In that case, an infinite loop is entered as the
-    // signal is repeatedly sent to breakpad's signal handler.
-    // To work around this, directly call the system's sigaction.
+/// Retrieves and/or sets the action for the specified signal, returning false
+/// on failure.
+///
+/// Android L+ expose signal and sigaction symbols that override the system
+/// ones, eg. ART's sigchain. There is a bug in these functions where a request
+/// to set the handler to `SIG_DFL` is ignored, in which case an infinite loop
+/// is entered as the signal is repeatedly sent to our signal handler, and the
+/// action they report is not the one the kernel will actually invoke, eg. for
+/// debuggerd. To work around this, the `rt_sigaction` syscall is always made
+/// directly, so that the complete action, including its flags and restorer, is
+/// saved and restored exactly.
+unsafe fn sys_sigaction(
+    sig: i32,
+    new: Option<&libc::sigaction>,
+    old: Option<&mut libc::sigaction>,
+) -> bool {
+    #[cfg(test)]
+    if new.is_some() && sig == UNMODIFIABLE_SIGNAL.load(Ordering::Relaxed) {
+        *crate::errno::errno_location() = libc::EPERM;
+        return false;
+    }
+
     cfg_if::cfg_if! {
         if #[cfg(target_os = "android")] {
-            let mut sa: libc::sigaction = mem::zeroed();
-            libc::sigemptyset(&mut sa.sa_mask);
-            sa.sa_sigaction = action;
-            sa.sa_flags = libc::SA_RESTART;
-            libc::syscall(
+            /// `SA_RESTORER`, which is not exposed by libc for every target
+            const SA_RESTORER: libc::c_ulong = 0x0400_0000;
+
+            #[allow(unused_mut)]
+            let mut new = new.map(|new| {
+                let mut ksa = KernelSigaction {
+                    handler: new.sa_sigaction,
+                    flags: new.sa_flags as libc::c_ulong,
+                    restorer: mem::transmute(new.sa_restorer),
+                    mask: 0,
+                };
+
+                ptr::copy_nonoverlapping(
+                    (&new.sa_mask as *const libc::sigset_t).cast::<u8>(),
+                    (&mut ksa.mask as *mut u64).cast::<u8>(),
+                    mem::size_of::<libc::sigset_t>().min(mem::size_of::<u64>()),
+                );
+
+                ksa
+            });
+
+            // On x86 the kernel requires userspace to provide the function
+            // that returns from a signal handler. Bionic's is private, so the
+            // one of the action being replaced is reused, as it is the one
+            // bionic installed if the action was set via sigaction, otherwise
+            // our own is used
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            if let Some(ksa) = new.as_mut().filter(|ksa| {
+                ksa.handler != libc::SIG_DFL
+                    && ksa.handler != libc::SIG_IGN
+                    && ksa.flags & SA_RESTORER == 0
+            }) {
+                let mut cur: KernelSigaction = mem::zeroed();
+                if libc::syscall(
+                    libc::SYS_rt_sigaction,
+                    sig,
+                    ptr::null::<KernelSigaction>(),
+                    &mut cur,
+                    mem::size_of::<u64>(),
+                ) != 0
+                {
+                    return false;
+                }
+
+                ksa.restorer = if cur.flags & SA_RESTORER != 0 && cur.restorer != 0 {
+                    cur.restorer
+                } else {
+                    crash_handler_restore_rt as *const () as usize
+                };
+                ksa.flags |= SA_RESTORER;
+            }
+
+            let mut kold: KernelSigaction = mem::zeroed();
+
+            if libc::syscall(
                 libc::SYS_rt_sigaction,
-                sig as i32,
-                &sa,
-                ptr::null::<libc::sigaction>(),
-                mem::size_of::<libc::sigset_t>(),
-            );
+                sig,
+                new.as_ref().map_or(ptr::null(), |new| new as *const KernelSigaction),
+                &mut kold,
+                mem::size_of::<u64>(),
+            ) != 0
+            {
+                return false;
+            }
+
+            if let Some(old) = old {
+                *old = mem::zeroed();
+                old.sa_sigaction = kold.handler;
+                old.sa_flags = kold.flags as _;
+                old.sa_restorer = mem::transmute(kold.restorer);
+
+                ptr::copy_nonoverlapping(
+                    (&kold.mask as *const u64).cast::<u8>(),
+                    (&mut old.sa_mask as *mut libc::sigset_t).cast::<u8>(),
+                    mem::size_of::<libc::sigset_t>().min(mem::size_of::<u64>()),
+                );
+            }
+
+            true
         } else {
-            libc::signal(sig as i32, action);
+            libc::sigaction(
+                sig,
+                new.map_or(ptr::null(), |new| new as *const _),
+                old.map_or(ptr::null_mut(), |old| old as *mut _),
+            ) == 0
         }
     }
 }
 
+/// A signal whose action [`sys_sigaction`] fails to change, as if eg. a seccomp
+/// filter disallowed it, so that the handling of the failure can be tested
+#[cfg(test)]
+pub(super) static UNMODIFIABLE_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// Directly invokes the specified action, if it is a function, as if the kernel
+/// had delivered the signal to it, returning false if it isn't
+///
+/// Like the kernel, the action's mask, and the signal itself unless it has
+/// `SA_NODEFER`, are blocked while it runs, and the action is reset to the
+/// default before it runs if it has `SA_RESETHAND`. The action always runs on
+/// our alternate stack however, regardless of whether it has `SA_ONSTACK`, as
+/// the stack the signal was raised on may be the cause of it.
+unsafe fn call_action(
+    action: &libc::sigaction,
+    sig: i32,
+    info: *mut libc::siginfo_t,
+    uc: *mut libc::c_void,
+) -> bool {
+    if action.sa_sigaction == libc::SIG_DFL || action.sa_sigaction == libc::SIG_IGN {
+        return false;
+    }
+
+    if action.sa_flags & libc::SA_RESETHAND != 0 {
+        let mut dfl: libc::sigaction = mem::zeroed();
+        dfl.sa_sigaction = libc::SIG_DFL;
+        sys_sigaction(sig, Some(&dfl), None);
+    }
+
+    let mut mask = action.sa_mask;
+    if action.sa_flags & libc::SA_NODEFER == 0 {
+        libc::sigaddset(&mut mask, sig);
+    }
+
+    // If the action jumps out rather than returning, restoring the mask is up
+    // to it, just as if the kernel had invoked it
+    let mut old_mask = mem::zeroed();
+    libc::pthread_sigmask(libc::SIG_BLOCK, &mask, &mut old_mask);
+
+    if action.sa_flags & libc::SA_SIGINFO != 0 {
+        let action: unsafe extern "C" fn(i32, *mut libc::siginfo_t, *mut libc::c_void) =
+            mem::transmute(action.sa_sigaction);
+        action(sig, info, uc);
+    } else {
+        let action: unsafe extern "C" fn(i32) = mem::transmute(action.sa_sigaction);
+        action(sig);
+    }
+
+    libc::pthread_sigmask(libc::SIG_SETMASK, &old_mask, ptr::null_mut());
+    true
+}
+
 /// The various signals we attempt to handle
-const EXCEPTION_SIGNALS: [Signal; 6] = [
+pub(super) const EXCEPTION_SIGNALS: [Signal; 6] = [
     Signal::Abort,
     Signal::Bus,
     Signal::Fpe,
@@ -183,29 +428,67 @@ const EXCEPTION_SIGNALS: [Signal; 6] = [
 static OLD_HANDLERS: parking_lot::Mutex<Option<[libc::sigaction; 6]>> =
     parking_lot::const_mutex(None);
 
+/// Whether the handlers were installed with `SA_RESETHAND | SA_NODEFER`, see
+/// [`super::AttachOptions::one_shot`]
+static ONE_SHOT: AtomicBool = AtomicBool::new(false);
+
+/// The flags [`signal_handler`] is installed with
+#[inline]
+fn handler_flags() -> i32 {
+    if ONE_SHOT.load(Ordering::Relaxed) {
+        libc::SA_ONSTACK | libc::SA_SIGINFO | libc::SA_RESETHAND | libc::SA_NODEFER
+    } else {
+        libc::SA_ONSTACK | libc::SA_SIGINFO
+    }
+}
+
 /// Restores all of the signal handlers back to their previous values, or the
-/// default if the previous value cannot be restored
-pub unsafe fn restore_handlers() {
+/// default if the previous value cannot be restored, in which case the error
+/// for the first signal that couldn't be restored is returned
+pub unsafe fn restore_handlers() -> Result<(), Error> {
     let mut ohl = OLD_HANDLERS.lock();
+    let mut result = Ok(());
 
     if let Some(old) = &*ohl {
         for (sig, action) in EXCEPTION_SIGNALS.into_iter().zip(old.iter()) {
-            if libc::sigaction(sig as i32, action, ptr::null_mut()) == -1 {
+            if !sys_sigaction(sig as i32, Some(action), None) {
+                if result.is_ok() {
+                    result = Err(sigaction_error(sig as i32));
+                }
+
                 install_default_handler(sig);
             }
         }
     }
 
     ohl.take();
+    result
+}
+
+/// Retrieves the action that was installed for the signal before ours
+fn previous_action(sig: Signal) -> Option<libc::sigaction> {
+    let ohl = OLD_HANDLERS.lock();
+    let old = ohl.as_ref()?;
+
+    EXCEPTION_SIGNALS
+        .iter()
+        .position(|es| *es == sig)
+        .map(|i| old[i])
 }
 
-pub unsafe fn install_handlers() {
+/// Installs our signal handler for every signal in [`EXCEPTION_SIGNALS`].
+///
+/// If any of them can't be installed, the previous handlers are restored for
+/// the signals that were, and the error for the signal is returned
+pub unsafe fn install_handlers(one_shot: bool) -> Result<(), Error> {
     let mut ohl = OLD_HANDLERS.lock();
 
     if ohl.is_some() {
-        return;
+        return Ok(());
     }
 
+    ONE_SHOT.store(one_shot, Ordering::Relaxed);
+
     // Attempt store all of the current handlers so we can restore them later
     let mut old_handlers: [mem::MaybeUninit<libc::sigaction>; 6] =
         mem::MaybeUninit::uninit().assume_init();
@@ -216,8 +499,8 @@ pub unsafe fn install_handlers() {
         .zip(old_handlers.iter_mut())
     {
         let mut old = mem::zeroed();
-        if libc::sigaction(sig as i32, ptr::null(), &mut old) == -1 {
-            return;
+        if !sys_sigaction(sig as i32, None, Some(&mut old)) {
+            return Err(sigaction_error(sig as i32));
         }
         *handler = mem::MaybeUninit::new(old);
     }
@@ -225,19 +508,30 @@ pub unsafe fn install_handlers() {
     let mut sa: libc::sigaction = mem::zeroed();
     libc::sigemptyset(&mut sa.sa_mask);
 
-    // Mask all exception signals when we're handling one of them.
-    for sig in EXCEPTION_SIGNALS {
-        libc::sigaddset(&mut sa.sa_mask, sig as i32);
+    // Other exception signals are deliberately not masked while we're handling
+    // one of them, so that a crash inside the user callback is delivered
+    // immediately, see `terminate_nested`. Dump requests are masked however,
+    // as they would otherwise deadlock on the handler
+    if let Some(sig) = dump_request_signal() {
+        libc::sigaddset(&mut sa.sa_mask, sig);
     }
 
     sa.sa_sigaction = signal_handler as usize;
-    sa.sa_flags = libc::SA_ONSTACK | libc::SA_SIGINFO;
+    sa.sa_flags = handler_flags();
 
     // Use our signal_handler for all of the signals we wish to catch
-    for sig in EXCEPTION_SIGNALS {
-        // At this point it is impractical to back out changes, and so failure to
-        // install a signal is intentionally ignored.
-        let _ = libc::sigaction(sig as i32, &sa, ptr::null_mut());
+    for (i, sig) in EXCEPTION_SIGNALS.into_iter().enumerate() {
+        if !sys_sigaction(sig as i32, Some(&sa), None) {
+            let err = sigaction_error(sig as i32);
+
+            for (sig, old) in EXCEPTION_SIGNALS.into_iter().zip(&old_handlers).take(i) {
+                if !sys_sigaction(sig as i32, Some(old.assume_init_ref()), None) {
+                    install_default_handler(sig);
+                }
+            }
+
+            return Err(err);
+        }
     }
 
     // Everything is initialized. Transmute the array to the
@@ -246,38 +540,143 @@ pub unsafe fn install_handlers() {
         [std::mem::MaybeUninit<libc::sigaction>; 6],
         [libc::sigaction; 6],
     >(old_handlers));
+
+    Ok(())
+}
+
+/// The signal registered via [`super::AttachOptions::dump_request_signal`], or
+/// 0 if there isn't one
+static DUMP_REQUEST_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+static OLD_REQUEST_HANDLER: parking_lot::Mutex<Option<libc::sigaction>> =
+    parking_lot::const_mutex(None);
+
+#[inline]
+pub(super) fn dump_request_signal() -> Option<i32> {
+    let sig = DUMP_REQUEST_SIGNAL.load(Ordering::Relaxed);
+    (sig != 0).then_some(sig)
 }
 
-pub(super) fn attach(on_crash: Box<dyn crate::CrashEvent>) -> Result<(), Error> {
+/// Installs [`dump_request_handler`] for the specified signal
+unsafe fn install_request_handler(sig: i32) -> Result<(), Error> {
+    let mut sa: libc::sigaction = mem::zeroed();
+    libc::sigemptyset(&mut sa.sa_mask);
+
+    // Mask all exception signals while handling the request, a crash during
+    // the request will then terminate the process rather than deadlock
+    for sig in EXCEPTION_SIGNALS {
+        libc::sigaddset(&mut sa.sa_mask, sig as i32);
+    }
+
+    sa.sa_sigaction = dump_request_handler as *const () as usize;
+    sa.sa_flags = libc::SA_ONSTACK | libc::SA_SIGINFO | libc::SA_RESTART;
+
+    let mut old = mem::zeroed();
+    if !sys_sigaction(sig, Some(&sa), Some(&mut old)) {
+        return Err(sigaction_error(sig));
+    }
+
+    *OLD_REQUEST_HANDLER.lock() = Some(old);
+    Ok(())
+}
+
+/// Restores the handler that was installed for the dump request signal before
+/// we attached, if any
+unsafe fn restore_request_handler() -> Result<(), Error> {
+    let sig = DUMP_REQUEST_SIGNAL.swap(0, Ordering::Relaxed);
+
+    if let Some(old) = OLD_REQUEST_HANDLER.lock().take() {
+        if !sys_sigaction(sig, Some(&old), None) {
+            return Err(sigaction_error(sig));
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) fn attach(
+    on_crash: Box<dyn crate::CrashEvent>,
+    options: super::AttachOptions,
+) -> Result<(), Error> {
     let mut lock = HANDLER.lock();
 
     if lock.is_some() {
         return Err(Error::HandlerAlreadyInstalled);
     }
 
+    if let Some(sig) = options.dump_request_signal {
+        if sig <= 0
+            || sig > libc::SIGRTMAX()
+            || sig == libc::SIGKILL
+            || sig == libc::SIGSTOP
+            || Signal::try_from(sig).is_ok()
+        {
+            return Err(Error::InvalidSignal(sig));
+        }
+
+        DUMP_REQUEST_SIGNAL.store(sig, Ordering::Relaxed);
+    }
+
+    if let Some(capacity) = options.memory_maps_capacity {
+        if let Err(err) = super::maps::cache(capacity) {
+            DUMP_REQUEST_SIGNAL.store(0, Ordering::Relaxed);
+            return Err(err.into());
+        }
+    }
+
     // SAFETY: syscalls
     unsafe {
-        install_sigaltstack()?;
-        install_handlers();
+        if let Err(err) = install_sigaltstack() {
+            DUMP_REQUEST_SIGNAL.store(0, Ordering::Relaxed);
+            super::maps::clear();
+            return Err(err);
+        }
+
+        if let Err(err) = install_handlers(options.one_shot) {
+            restore_sigaltstack();
+            DUMP_REQUEST_SIGNAL.store(0, Ordering::Relaxed);
+            super::maps::clear();
+            return Err(err);
+        }
+
+        if let Some(sig) = options.dump_request_signal {
+            if let Err(err) = install_request_handler(sig) {
+                let _ = restore_handlers();
+                restore_sigaltstack();
+                DUMP_REQUEST_SIGNAL.store(0, Ordering::Relaxed);
+                super::maps::clear();
+                return Err(err);
+            }
+        }
     }
 
-    *lock = Some(HandlerInner::new(on_crash));
+    *lock = Some(HandlerInner::new(on_crash, &options));
 
     Ok(())
 }
 
 /// Detaches our signal handle, restoring the previously installed or default
-/// handlers
-pub(super) fn detach() {
+/// handlers.
+///
+/// The handler is detached even if a previous handler couldn't be restored, in
+/// which case the error for the first signal that failed is returned
+pub(super) fn detach() -> Result<(), Error> {
     let mut lock = HANDLER.lock();
+    let mut result = Ok(());
+
     if lock.is_some() {
         // SAFETY: syscalls
         unsafe {
             restore_sigaltstack();
-            restore_handlers();
+            let handlers = restore_handlers();
+            let request = restore_request_handler();
+            result = handlers.and(request);
         }
+        super::maps::clear();
         lock.take();
     }
+
+    result
 }
 
 pub(super) static HANDLER: parking_lot::Mutex<Option<HandlerInner>> =
@@ -290,12 +689,29 @@ unsafe extern "C" fn signal_handler(
     info: *mut libc::siginfo_t,
     uc: *mut libc::c_void,
 ) {
+    // The syscalls made while handling the signal can clobber errno, which
+    // the interrupted code may still be relying on if it is resumed
+    let errno = crate::errno::SavedErrno::save();
+
+    // The user callback crashed, see `Handling`
+    if HANDLING_THREAD.load(Ordering::Relaxed) == current_tid() {
+        if RECOVERABLE.load(Ordering::Relaxed) {
+            super::jmp::siglongjmp(RECOVER_JMP_BUF.0.get().cast(), 1);
+        }
+
+        terminate_nested(sig);
+    }
+
     let info = &mut *info;
     let uc = &mut *uc;
 
     enum Action {
+        Exit(i32),
+        KeepInstalled,
         RestoreDefault,
+        DefaultRestored,
         RestorePrevious,
+        ChainPrevious,
         Jump((*mut super::jmp::JmpBuf, i32)),
     }
 
@@ -310,9 +726,14 @@ unsafe extern "C" fn signal_handler(
         // resets the signal handlers with `sigaction` & `SA_SIGINFO` and returns.
         // This forces the signal to be thrown again, but this time the kernel
         // will call the function with the right arguments.
+        //
+        // In one shot mode, the kernel has already reset the action to the
+        // default if it was ours, so this only triggers if the buggy code
+        // reinstalled us without `SA_RESETHAND`, in which case we are
+        // reinstalled with it, and the retriggered signal is then the one shot.
         {
             let mut cur_handler = mem::zeroed();
-            if libc::sigaction(sig as i32, ptr::null_mut(), &mut cur_handler) == 0
+            if sys_sigaction(sig as i32, None, Some(&mut cur_handler))
                 && cur_handler.sa_sigaction == signal_handler as usize
                 && cur_handler.sa_flags & libc::SA_SIGINFO == 0
             {
@@ -321,9 +742,9 @@ unsafe extern "C" fn signal_handler(
                 libc::sigaddset(&mut cur_handler.sa_mask, sig as i32);
 
                 cur_handler.sa_sigaction = signal_handler as usize;
-                cur_handler.sa_flags = libc::SA_ONSTACK | libc::SA_SIGINFO;
+                cur_handler.sa_flags = handler_flags();
 
-                if libc::sigaction(sig as i32, &cur_handler, ptr::null_mut()) == -1 {
+                if !sys_sigaction(sig as i32, Some(&cur_handler), None) {
                     // When resetting the handler fails, try to reset the
                     // default one to avoid an infinite loop here.
                     install_default_handler(sig);
@@ -337,9 +758,25 @@ unsafe extern "C" fn signal_handler(
         let handler = HANDLER.lock();
 
         if let Some(handler) = &*handler {
-            match handler.handle_signal(info, uc) {
-                crate::CrashEventResult::Handled(true) => Action::RestoreDefault,
-                crate::CrashEventResult::Handled(false) => Action::RestorePrevious,
+            match handler.handle_signal(&to_signalfd_siginfo(info), uc) {
+                crate::CrashEventResult::Handled(true) => {
+                    if let Some(code) = handler.exit_code_on_handled {
+                        Action::Exit(code)
+                    } else if ONE_SHOT.load(Ordering::Relaxed) {
+                        Action::DefaultRestored
+                    } else if handler.reinstall_after_handled {
+                        Action::KeepInstalled
+                    } else {
+                        Action::RestoreDefault
+                    }
+                }
+                crate::CrashEventResult::Handled(false) => {
+                    if handler.chain_previous_handler {
+                        Action::ChainPrevious
+                    } else {
+                        Action::RestorePrevious
+                    }
+                }
                 crate::CrashEventResult::Jump { jmp_buf, value } => Action::Jump((jmp_buf, value)),
             }
         } else {
@@ -353,16 +790,49 @@ unsafe extern "C" fn signal_handler(
     // previously installed handler. Then, when the signal is retriggered,
     // it will be delivered to the appropriate handler.
     match action {
+        Action::Exit(code) => {
+            debug_print!("exiting");
+            libc::_exit(code);
+        }
+        Action::KeepInstalled => {
+            // The crash was handled, so a signal that was sent to us isn't
+            // raised again, and a fault is retried once we return, see
+            // `AttachOptions::reinstall_after_handled`
+            debug_print!("keeping handler installed");
+            return;
+        }
         Action::RestoreDefault => {
             debug_print!("installing default handler");
             install_default_handler(sig);
         }
+        Action::DefaultRestored => {
+            // The kernel reset the action to the default when it delivered
+            // the signal, see `AttachOptions::one_shot`
+            debug_print!("default handler already restored");
+        }
         Action::RestorePrevious => {
             debug_print!("restoring handlers");
-            restore_handlers();
+            // Any handler that couldn't be restored has its default action
+            // installed instead, which is all we can do here
+            let _ = restore_handlers();
+        }
+        Action::ChainPrevious => {
+            let previous = previous_action(sig);
+            let _ = restore_handlers();
+
+            if let Some(previous) = previous {
+                // The previous handler is invoked with the original info and
+                // context, so it is responsible for what happens next, eg.
+                // debuggerd re-raises the signal itself after writing a tombstone
+                if call_action(&previous, sig as i32, info, uc) {
+                    debug_print!("chained previous handler");
+                    return;
+                }
+            }
         }
         Action::Jump((jmp_buf, value)) => {
             debug_print!("jumping");
+            errno.restore();
             super::jmp::siglongjmp(jmp_buf, value);
         }
     }
@@ -374,8 +844,7 @@ unsafe extern "C" fn signal_handler(
         // In order to retrigger it, we have to queue a new signal by calling
         // kill() ourselves.  The special case (si_pid == 0 && sig == SIGABRT) is
         // due to the kernel sending a SIGABRT from a user request via SysRQ.
-        let tid = libc::syscall(libc::SYS_gettid) as i32;
-        if libc::syscall(libc::SYS_tgkill, std::process::id(), tid, sig) < 0 {
+        if libc::syscall(libc::SYS_tgkill, std::process::id(), current_tid(), sig) < 0 {
             // If we failed to kill ourselves (e.g. because a sandbox disallows us
             // to do so), we instead resort to terminating our process. This will
             // result in an incorrect exit code.
@@ -388,6 +857,236 @@ unsafe extern "C" fn signal_handler(
     }
 }
 
+/// The thread currently running the user callback, or 0 if it isn't running
+static HANDLING_THREAD: AtomicI32 = AtomicI32::new(0);
+
+/// Marks the current thread as running the user callback for as long as it
+/// is alive.
+///
+/// A signal received on the thread while it is running the callback means
+/// the callback itself has crashed, in which case the process is terminated
+/// immediately by the nested signal, see [`terminate_nested`]. Crashes on
+/// other threads are unaffected, and are handled once the callback returns.
+struct Handling;
+
+impl Handling {
+    #[inline]
+    fn enter() -> Self {
+        HANDLING_THREAD.store(current_tid(), Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for Handling {
+    #[inline]
+    fn drop(&mut self) {
+        HANDLING_THREAD.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Whether the [`HANDLING_THREAD`] is inside [`call_recoverable`]
+static RECOVERABLE: AtomicBool = AtomicBool::new(false);
+
+struct RecoverJmpBuf(std::cell::UnsafeCell<mem::MaybeUninit<super::jmp::JmpBuf>>);
+
+// SAFETY: only accessed by the handling thread, while the `HANDLER` lock is held
+unsafe impl Sync for RecoverJmpBuf {}
+
+/// Where a crash inside [`call_recoverable`] jumps back to
+static RECOVER_JMP_BUF: RecoverJmpBuf =
+    RecoverJmpBuf(std::cell::UnsafeCell::new(mem::MaybeUninit::uninit()));
+
+/// Calls the function such that if it crashes, the crash is ignored and this
+/// returns as if the function had, rather than the process being terminated
+/// as for other crashes inside the user callback.
+///
+/// The exception signals are unblocked while the function runs, as a crash
+/// with the signal currently being handled would otherwise kill the process.
+///
+/// # Safety
+///
+/// Must only be called by the [`HANDLING_THREAD`], while the `HANDLER` lock is
+/// held.
+#[inline(never)]
+pub(crate) unsafe fn call_recoverable(f: &dyn Fn()) {
+    let mut unblock: libc::sigset_t = mem::zeroed();
+    libc::sigemptyset(&mut unblock);
+    for sig in EXCEPTION_SIGNALS {
+        libc::sigaddset(&mut unblock, sig as i32);
+    }
+
+    // The signal mask is also restored by jumping back here
+    if super::jmp::sigsetjmp(RECOVER_JMP_BUF.0.get().cast(), 1) == 0 {
+        let mut previous: libc::sigset_t = mem::zeroed();
+        libc::pthread_sigmask(libc::SIG_UNBLOCK, &unblock, &mut previous);
+
+        RECOVERABLE.store(true, Ordering::Relaxed);
+        f();
+        RECOVERABLE.store(false, Ordering::Relaxed);
+
+        libc::pthread_sigmask(libc::SIG_SETMASK, &previous, ptr::null_mut());
+    } else {
+        RECOVERABLE.store(false, Ordering::Relaxed);
+        debug_print!("crashed inside a recoverable call");
+    }
+}
+
+#[inline]
+fn current_tid() -> i32 {
+    // SAFETY: syscall
+    unsafe { libc::syscall(libc::SYS_gettid) as i32 }
+}
+
+/// Terminates the process with a signal received while the user callback was
+/// handling a previous crash on the same thread.
+///
+/// The default action is restored for every exception signal before the
+/// signal is re-raised, so that the process dies with the nested signal
+/// recorded as the cause, rather than it being delivered to the previous
+/// handlers once the callback returns, by which point they may no longer be
+/// in a state to handle it.
+unsafe fn terminate_nested(sig: Signal) -> ! {
+    debug_print!("crashed while handling a crash");
+
+    for sig in EXCEPTION_SIGNALS {
+        install_default_handler(sig);
+    }
+
+    let mut unblock: libc::sigset_t = mem::zeroed();
+    libc::sigemptyset(&mut unblock);
+    libc::sigaddset(&mut unblock, sig as i32);
+    libc::pthread_sigmask(libc::SIG_UNBLOCK, &unblock, ptr::null_mut());
+
+    libc::syscall(libc::SYS_tgkill, std::process::id(), current_tid(), sig);
+
+    // The signal should have terminated the process, but if not, eg. it was
+    // blocked by a sandbox, there's nothing left to do but exit
+    libc::_exit(1);
+}
+
+/// The function installed for the signal registered via
+/// [`super::AttachOptions::dump_request_signal`]
+///
+/// Unlike [`signal_handler`], the signal is never re-raised, as the process is
+/// not expected to die after a dump is requested
+unsafe extern "C" fn dump_request_handler(
+    sig: i32,
+    info: *mut libc::siginfo_t,
+    uc: *mut libc::c_void,
+) {
+    let errno = crate::errno::SavedErrno::save();
+
+    let result = {
+        let handler = HANDLER.lock();
+
+        if let Some(handler) = &*handler {
+            // The request was not caused by the code that was interrupted, so
+            // capture the context here rather than use the one from the kernel
+            let mut context: crash_context::ucontext_t = mem::zeroed();
+            crash_context::crash_context_getcontext(&mut context);
+
+            handler.handle_signal(
+                &to_signalfd_siginfo(&*info),
+                &mut *(&mut context as *mut crash_context::ucontext_t).cast::<libc::c_void>(),
+            )
+        } else {
+            crate::CrashEventResult::Handled(false)
+        }
+    };
+
+    match result {
+        crate::CrashEventResult::Handled(true) => {
+            debug_print!("dump request handled");
+        }
+        crate::CrashEventResult::Handled(false) => {
+            // Forward the request to the handler that was installed before us,
+            // if there was one, but never perform the default action, as that
+            // would terminate the process
+            let old = *OLD_REQUEST_HANDLER.lock();
+
+            if let Some(old) = old {
+                if call_action(&old, sig, info, uc) {
+                    debug_print!("forwarded dump request");
+                }
+            }
+        }
+        crate::CrashEventResult::Jump { jmp_buf, value } => {
+            debug_print!("jumping");
+            errno.restore();
+            super::jmp::siglongjmp(jmp_buf, value);
+        }
+    }
+}
+
+/// The start of the kernel's `siginfo_t`, which libc doesn't expose in full
+#[repr(C)]
+struct SigInfo {
+    si_signo: i32,
+    si_errno: i32,
+    si_code: i32,
+    fields: SigFields,
+}
+
+/// The subset of the `siginfo_t` union relevant to the signals we handle. Note
+/// that this also ensures the union has the same alignment as the kernel's,
+/// which contains pointers
+#[repr(C)]
+union SigFields {
+    kill: SigKill,
+    fault: SigFault,
+}
+
+/// Fields for signals sent by a process, eg. via `kill` or `abort`
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct SigKill {
+    pid: libc::pid_t,
+    uid: libc::uid_t,
+}
+
+/// Fields for signals generated by the kernel due to a fault
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct SigFault {
+    addr: *mut libc::c_void,
+    /// Only valid for `BUS_MCEERR_AR` and `BUS_MCEERR_AO`
+    addr_lsb: i16,
+}
+
+/// Converts the `siginfo_t` received by the signal handler into the
+/// `signalfd_siginfo` stored in the [`crash_context::CrashContext`].
+///
+/// The two have different layouts, so this is done the same way the kernel
+/// does for signalfd, only copying the fields that are valid for the kind of
+/// signal that was received.
+unsafe fn to_signalfd_siginfo(info: &libc::siginfo_t) -> libc::signalfd_siginfo {
+    let info = &*(info as *const libc::siginfo_t).cast::<SigInfo>();
+
+    let mut sfd: libc::signalfd_siginfo = mem::zeroed();
+    sfd.ssi_signo = info.si_signo as u32;
+    sfd.ssi_errno = info.si_errno;
+    sfd.ssi_code = info.si_code;
+
+    if info.si_code > 0 {
+        // The signal was generated by the kernel, and every signal we handle
+        // shares the same layout for faults
+        let fault = info.fields.fault;
+        sfd.ssi_addr = fault.addr as u64;
+
+        if info.si_signo == libc::SIGBUS
+            && matches!(info.si_code, libc::BUS_MCEERR_AR | libc::BUS_MCEERR_AO)
+        {
+            sfd.ssi_addr_lsb = fault.addr_lsb as u16;
+        }
+    } else {
+        let kill = info.fields.kill;
+        sfd.ssi_pid = kill.pid as u32;
+        sfd.ssi_uid = kill.uid;
+    }
+
+    sfd
+}
+
 /// The size of `CrashContext` can be too big w.r.t the size of alternatate stack
 /// for `signal_handler`. Keep the crash context as a .bss field.
 static CRASH_CONTEXT: parking_lot::Mutex<mem::MaybeUninit<crash_context::CrashContext>> =
@@ -395,37 +1094,46 @@ static CRASH_CONTEXT: parking_lot::Mutex<mem::MaybeUninit<crash_context::CrashCo
 
 pub(super) struct HandlerInner {
     handler: Box<dyn crate::CrashEvent>,
+    pub(super) pre_crash_hook: Option<crate::PreCrashHook>,
     pub(super) dump_process: Option<u32>,
+    exit_code_on_handled: Option<i32>,
+    chain_previous_handler: bool,
+    reinstall_after_handled: bool,
+    /// When the process was started, see [`crash_context::CrashTimestamps`]
+    process_start_ns: u64,
 }
 
 impl HandlerInner {
     #[inline]
-    pub(super) fn new(handler: Box<dyn crate::CrashEvent>) -> Self {
+    pub(super) fn new(handler: Box<dyn crate::CrashEvent>, options: &super::AttachOptions) -> Self {
         Self {
             handler,
+            pre_crash_hook: None,
             dump_process: None,
+            exit_code_on_handled: options.exit_code_on_handled,
+            chain_previous_handler: options.chain_previous_handler,
+            reinstall_after_handled: options.reinstall_after_handled,
+            process_start_ns: process_start_ns().unwrap_or_default(),
         }
     }
 
     pub(super) unsafe fn handle_signal(
         &self,
-        info: &mut libc::siginfo_t,
+        info: &libc::signalfd_siginfo,
         uc: &mut libc::c_void,
     ) -> crate::CrashEventResult {
-        // The siginfo_t in libc is lowest common denominator, but this code is
-        // specifically targeting linux/android, which contains the si_pid field
-        // that we require
-        let nix_info = &*((info as *const libc::siginfo_t).cast::<libc::signalfd_siginfo>());
+        let timestamps = crash_context::CrashTimestamps::now(self.process_start_ns);
 
         // Allow ourselves to be dumped, if that is what the user handler wishes to do
-        let _set_dumpable = SetDumpable::new(self.dump_process);
+        let _dumpable = super::DumpableGuard::new(self.dump_process);
         let mut crash_ctx = CRASH_CONTEXT.lock();
+        let _handling = Handling::enter();
 
         {
             *crash_ctx = mem::MaybeUninit::zeroed();
             let cc = &mut *crash_ctx.as_mut_ptr();
 
-            ptr::copy_nonoverlapping(nix_info, &mut cc.siginfo, 1);
+            ptr::copy_nonoverlapping(info, &mut cc.siginfo, 1);
 
             let uc_ptr = &*(uc as *const libc::c_void).cast::<crash_context::ucontext_t>();
             ptr::copy_nonoverlapping(uc_ptr, &mut cc.context, 1);
@@ -446,59 +1154,173 @@ impl HandlerInner {
             }
 
             cc.pid = std::process::id() as i32;
-            cc.tid = libc::syscall(libc::SYS_gettid) as i32;
+            cc.tid = current_tid();
+            cc.timestamps = timestamps;
+            cc.thread_name = current_thread_name();
+            cc.tls = crash_context::TlsRegisters::current();
         }
 
-        self.handler.on_crash(&*crash_ctx.as_ptr())
+        crate::call_crash_event(
+            &*self.handler,
+            self.pre_crash_hook.as_ref(),
+            &*crash_ctx.as_ptr(),
+        )
+    }
+}
+
+/// Retrieves the time the current process was started, in nanoseconds since
+/// the Unix epoch.
+///
+/// The kernel only exposes the start time in clock ticks since boot, so this
+/// is only accurate to the tick, usually 10ms
+fn process_start_ns() -> Option<u64> {
+    // The boot time, in seconds since the Unix epoch
+    let boot_time: u64 = std::fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+
+    // The command name can contain spaces and parentheses, so skip past it
+    // before splitting the fields, the start time is the 22nd field
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    let start_ticks: u64 = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()?;
+
+    // SAFETY: syscall
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_second <= 0 {
+        return None;
     }
+
+    Some(boot_time * 1_000_000_000 + start_ticks * 1_000_000_000 / ticks_per_second as u64)
 }
 
-/// We define these constans ourselves rather than use libc as they are missing
-/// from eg. Android
-const PR_GET_DUMPABLE: i32 = 3;
-const PR_SET_DUMPABLE: i32 = 4;
-const PR_SET_PTRACER: i32 = 0x59616d61;
-const PR_SET_PTRACER_ANY: i32 = -1;
+/// We define this ourselves rather than use libc as it is missing from eg.
+/// Android
+const PR_GET_NAME: i32 = 16;
 
-/// Helper that sets the process as dumpable if it is not, and when dropped
-/// returns it back to the original state if needed
-struct SetDumpable {
-    was_dumpable: bool,
+/// Retrieves the name of the calling thread
+unsafe fn current_thread_name() -> crash_context::ThreadName {
+    // The name is at most 16 bytes, including the nul terminator
+    let mut name = [0u8; 16];
+    libc::syscall(libc::SYS_prctl, PR_GET_NAME, name.as_mut_ptr(), 0, 0, 0);
+    crash_context::ThreadName::new(&name)
 }
 
-impl SetDumpable {
-    unsafe fn new(dump_process: Option<u32>) -> Self {
-        let is_dumpable = libc::syscall(libc::SYS_prctl, PR_GET_DUMPABLE, 0, 0, 0, 0);
-        let was_dumpable = is_dumpable > 0;
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Synthesizes a `siginfo_t`, as we can't eg. provoke real hardware memory
+    /// failures
+    unsafe fn siginfo(signo: i32, code: i32, fields: SigFields) -> libc::siginfo_t {
+        let mut info: libc::siginfo_t = mem::zeroed();
+        let si = &mut *(&mut info as *mut libc::siginfo_t).cast::<SigInfo>();
+        si.si_signo = signo;
+        si.si_code = code;
+        si.fields = fields;
+        info
+    }
 
-        if !was_dumpable {
-            libc::syscall(libc::SYS_prctl, PR_SET_DUMPABLE, 1, 0, 0, 0);
+    #[test]
+    fn converts_fault() {
+        unsafe {
+            let info = siginfo(
+                libc::SIGSEGV,
+                2, // SEGV_ACCERR
+                SigFields {
+                    fault: SigFault {
+                        addr: 0x1234_5678 as _,
+                        addr_lsb: 0,
+                    },
+                },
+            );
+
+            // The accessor libc _does_ expose must agree with our layout
+            assert_eq!(info.si_addr() as usize, 0x1234_5678);
+
+            let sfd = to_signalfd_siginfo(&info);
+            assert_eq!(sfd.ssi_signo, libc::SIGSEGV as u32);
+            assert_eq!(sfd.ssi_code, 2);
+            assert_eq!(sfd.ssi_addr, 0x1234_5678);
+            assert_eq!(sfd.ssi_pid, 0);
         }
+    }
 
-        // Set the process that is allowed to do ptrace operations on this process,
-        // we either set it to the process that the user specified, or allow
-        // any process, which _somewhat_ defeats the purpose of the yama security
-        // that this call is needed for
-        let ptracer = dump_process.map_or(PR_SET_PTRACER_ANY, |dp| dp as i32);
+    #[test]
+    fn converts_memory_failure() {
+        unsafe {
+            let info = siginfo(
+                libc::SIGBUS,
+                libc::BUS_MCEERR_AR,
+                SigFields {
+                    fault: SigFault {
+                        addr: 0xdead_b000usize as _,
+                        addr_lsb: 12,
+                    },
+                },
+            );
 
-        // Note that this will fail with EINVAL if the pid does not exist, but
-        // that would be on the user. We only need to do this if
-        // `/proc/sys/kernel/yama/ptrace_scope` = 1, but should not have a negative
-        // impact if it is in any other mode
-        libc::syscall(libc::SYS_prctl, PR_SET_PTRACER, ptracer, 0, 0, 0);
+            let sfd = to_signalfd_siginfo(&info);
+            assert_eq!(sfd.ssi_signo, libc::SIGBUS as u32);
+            assert_eq!(sfd.ssi_code, libc::BUS_MCEERR_AR);
+            assert_eq!(sfd.ssi_addr, 0xdead_b000);
+            assert_eq!(sfd.ssi_addr_lsb, 12);
+
+            // The lsb is only valid for memory failures
+            let info = siginfo(
+                libc::SIGBUS,
+                libc::BUS_ADRERR,
+                SigFields {
+                    fault: SigFault {
+                        addr: 0xdead_b000usize as _,
+                        addr_lsb: 12,
+                    },
+                },
+            );
 
-        Self { was_dumpable }
+            let sfd = to_signalfd_siginfo(&info);
+            assert_eq!(sfd.ssi_addr_lsb, 0);
+        }
     }
-}
 
-impl Drop for SetDumpable {
-    fn drop(&mut self) {
+    #[test]
+    fn reads_process_start() {
+        let start = process_start_ns().expect("failed to read process start");
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64;
+
+        // Allow for the tick granularity
+        assert!(start <= now + 1_000_000_000);
+        assert!(now - start < 60 * 60 * 1_000_000_000);
+    }
+
+    #[test]
+    fn converts_kill() {
         unsafe {
-            libc::syscall(libc::SYS_prctl, PR_SET_PTRACER, 0, 0, 0, 0);
+            let info = siginfo(
+                libc::SIGABRT,
+                -6, // SI_TKILL
+                SigFields {
+                    kill: SigKill { pid: 42, uid: 1000 },
+                },
+            );
 
-            if !self.was_dumpable {
-                libc::syscall(libc::SYS_prctl, PR_SET_DUMPABLE, 0, 0, 0, 0);
-            }
+            assert_eq!(info.si_pid(), 42);
+
+            let sfd = to_signalfd_siginfo(&info);
+            assert_eq!(sfd.ssi_pid, 42);
+            assert_eq!(sfd.ssi_uid, 1000);
+            assert_eq!(sfd.ssi_addr, 0);
         }
     }
 }
@@ -1,10 +1,12 @@
 mod ffi;
+pub mod jmp;
 mod signal;
 mod state;
 
 /// High level exception types
 ///
 /// `exception_types.h`
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(i32)]
 pub enum ExceptionType {
     /// Could not access memory. (SIGSEGV/SIGBUS)
@@ -53,6 +55,113 @@ pub enum ExceptionType {
     CorpseNotify = 13,
 }
 
+impl ExceptionType {
+    /// Every exception type
+    pub const ALL: &'static [Self] = &[
+        Self::BadAccess,
+        Self::BadInstruction,
+        Self::Arithmetic,
+        Self::Emulation,
+        Self::Software,
+        Self::Breakpoint,
+        Self::SysCall,
+        Self::MachSysCall,
+        Self::RpcAlert,
+        Self::Crash,
+        Self::Resource,
+        Self::Guard,
+        Self::CorpseNotify,
+    ];
+
+    /// The conventional name of the exception type, eg. `EXC_BAD_ACCESS`
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::BadAccess => "EXC_BAD_ACCESS",
+            Self::BadInstruction => "EXC_BAD_INSTRUCTION",
+            Self::Arithmetic => "EXC_ARITHMETIC",
+            Self::Emulation => "EXC_EMULATION",
+            Self::Software => "EXC_SOFTWARE",
+            Self::Breakpoint => "EXC_BREAKPOINT",
+            Self::SysCall => "EXC_SYSCALL",
+            Self::MachSysCall => "EXC_MACH_SYSCALL",
+            Self::RpcAlert => "EXC_RPC_ALERT",
+            Self::Crash => "EXC_CRASH",
+            Self::Resource => "EXC_RESOURCE",
+            Self::Guard => "EXC_GUARD",
+            Self::CorpseNotify => "EXC_CORPSE_NOTIFY",
+        }
+    }
+}
+
+impl std::fmt::Display for ExceptionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl TryFrom<i32> for ExceptionType {
+    type Error = crate::UnknownCodeError;
+
+    fn try_from(kind: i32) -> Result<Self, Self::Error> {
+        Self::ALL
+            .iter()
+            .find(|et| **et as i32 == kind)
+            .copied()
+            .ok_or(crate::UnknownCodeError(kind.into()))
+    }
+}
+
+impl TryFrom<u32> for ExceptionType {
+    type Error = crate::UnknownCodeError;
+
+    /// Converts eg. [`crash_context::ExceptionInfo::kind`] into an [`ExceptionType`]
+    fn try_from(kind: u32) -> Result<Self, Self::Error> {
+        i32::try_from(kind)
+            .ok()
+            .and_then(|kind| Self::try_from(kind).ok())
+            .ok_or(crate::UnknownCodeError(kind.into()))
+    }
+}
+
+/// Options for [`CrashHandler::attach_with_options`]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct AttachOptions {
+    pub(crate) exit_code_on_handled: Option<i32>,
+    pub(crate) capture_thread_state: bool,
+}
+
+impl AttachOptions {
+    /// Exits the process with the specified code via `_exit` after the
+    /// [`crate::CrashEvent`] returns `Handled(true)` for a crash, rather than
+    /// replying to the kernel and letting the previous exception port, usually
+    /// the OS, terminate the process.
+    ///
+    /// This allows a parent process to distinguish between a crash that was
+    /// handled, eg. a minidump was written, and one that wasn't, but note that
+    /// the OS crash reporter will no longer see the crash.
+    ///
+    /// This does not apply to [`CrashHandler::simulate_exception`].
+    #[inline]
+    pub fn exit_code_on_handled(mut self, code: i32) -> Self {
+        self.exit_code_on_handled = Some(code);
+        self
+    }
+
+    /// Captures the CPU state of the crashed thread, while it is suspended,
+    /// into [`crate::CrashContext::thread_state`] before the
+    /// [`crate::CrashEvent`] is invoked.
+    ///
+    /// This is off by default, as the state can also be retrieved from the
+    /// crashed thread by an out of process minidump writer, but is useful when
+    /// the crash is handled in process.
+    #[inline]
+    pub fn capture_thread_state(mut self, capture: bool) -> Self {
+        self.capture_thread_state = capture;
+        self
+    }
+}
+
 /// A Macos exception handler
 pub struct CrashHandler;
 
@@ -63,17 +172,39 @@ impl CrashHandler {
     /// The provided callback will be invoked if an exception is caught,
     /// providing a [`crate::CrashContext`] with the details of the thread where
     /// the exception was thrown.
+    ///
+    /// If this is called from the main thread, crashes report whether they
+    /// occurred on it, see `CrashContext::is_main_thread`, otherwise that is
+    /// unknown until [`crate::set_main_thread`] is called from it.
     pub fn attach(on_crash: Box<dyn crate::CrashEvent>) -> Result<Self, crate::Error> {
-        state::attach(on_crash)?;
+        Self::attach_with_options(on_crash, AttachOptions::default())
+    }
+
+    /// Attaches the exception handler with the specified options.
+    ///
+    /// See [`Self::attach`] for details.
+    pub fn attach_with_options(
+        on_crash: Box<dyn crate::CrashEvent>,
+        options: AttachOptions,
+    ) -> Result<Self, crate::Error> {
+        state::attach(on_crash, options)?;
         Ok(Self)
     }
 
     /// Detaches the handler.
     ///
     /// This is done automatically when [`CrashHandler`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// The handler is always detached, and every previous exception port, as
+    /// well as the previous `SIGABRT` handler, is restored if possible. The
+    /// exception port for any mask whose previous port couldn't be restored
+    /// is cleared, and the error for the first thing that couldn't be restored
+    /// is returned.
     #[inline]
-    pub fn detach(self) {
-        state::detach(false);
+    pub fn detach(self) -> Result<(), crate::Error> {
+        state::detach(false)
     }
 
     // Raises the specified user exception
@@ -83,8 +214,101 @@ impl CrashHandler {
     }
 }
 
+pub(crate) use state::replace_pre_crash_hook;
+
 impl Drop for CrashHandler {
     fn drop(&mut self) {
-        state::detach(false);
+        let _ = state::detach(false);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ExceptionType;
+
+    #[test]
+    fn round_trips() {
+        for &et in ExceptionType::ALL {
+            // Ensures this test is updated if a variant is added
+            match et {
+                ExceptionType::BadAccess
+                | ExceptionType::BadInstruction
+                | ExceptionType::Arithmetic
+                | ExceptionType::Emulation
+                | ExceptionType::Software
+                | ExceptionType::Breakpoint
+                | ExceptionType::SysCall
+                | ExceptionType::MachSysCall
+                | ExceptionType::RpcAlert
+                | ExceptionType::Crash
+                | ExceptionType::Resource
+                | ExceptionType::Guard
+                | ExceptionType::CorpseNotify => {}
+            }
+
+            assert_eq!(ExceptionType::try_from(et as i32), Ok(et));
+            assert_eq!(ExceptionType::try_from(et as u32), Ok(et));
+            assert_eq!(et.to_string(), et.name());
+        }
+
+        assert_eq!(ExceptionType::ALL.len(), 13);
+        assert_eq!(ExceptionType::BadAccess.to_string(), "EXC_BAD_ACCESS");
+    }
+
+    #[test]
+    fn detach_failures() {
+        use super::{state, CrashHandler};
+
+        fn event() -> Box<dyn crate::CrashEvent> {
+            unsafe { crate::make_crash_event(|_cc: &crate::CrashContext| false.into()) }
+        }
+
+        // The handler thread having already exited doesn't prevent, nor hang,
+        // detaching
+        let handler = CrashHandler::attach(event()).unwrap();
+        state::stop_handler_thread();
+        handler.detach().unwrap();
+
+        // A previous port that is no longer valid is reported, and the port
+        // for its mask is cleared, rather than left as our now dead port
+        let mask = mach2::exception_types::EXC_MASK_BREAKPOINT;
+        let handler = CrashHandler::attach(event()).unwrap();
+        state::set_previous_port(mask, 0xdead_beef);
+        match handler.detach() {
+            Err(crate::Error::ExceptionPort(m, code)) => {
+                assert_eq!(m, mask);
+                assert_ne!(code, mach2::kern_return::KERN_SUCCESS);
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+        assert_eq!(state::current_port(mask), mach2::port::MACH_PORT_NULL);
+
+        // The handler was still fully detached
+        CrashHandler::attach(event()).unwrap().detach().unwrap();
+    }
+
+    #[test]
+    fn kernel_errors() {
+        let err = super::state::kern_ret(|| unsafe {
+            mach2::mach_port::mach_port_deallocate(mach2::traps::mach_task_self(), 0xdead_beef)
+        })
+        .unwrap_err();
+
+        match &err {
+            crate::Error::Kernel(code) => {
+                assert_ne!(*code, mach2::kern_return::KERN_SUCCESS);
+                assert!(err.to_string().contains(&format!("(kern_return_t {code})")));
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown() {
+        assert_eq!(
+            ExceptionType::try_from(0u32),
+            Err(crate::UnknownCodeError(0))
+        );
+        assert!(ExceptionType::try_from(14i32).is_err());
     }
 }
@@ -152,6 +152,21 @@ extern "C" {
         new_flavor: ts::thread_state_flavor_t, // What CPU context info to send with the exception
     ) -> kern_return_t;
 
+    /// Retrieves the exception ports registered for a task, in the same
+    /// structure of arrays as [`task_swap_exception_ports`]
+    ///
+    /// <https://github.com/apple-oss-distributions/xnu/blob/e7776783b89a353188416a9a346c6cdb4928faad/osfmk/mach/task.defs#L262-L279>
+    #[cfg(test)]
+    pub fn task_get_exception_ports(
+        task: mt::task_t,
+        exception_mask: et::exception_mask_t,
+        masks: *mut et::exception_mask_t,
+        masks_count: *mut u32,
+        old_handlers: *mut mach_port_t,
+        old_behaviors: *mut et::exception_behavior_t,
+        old_flavors: *mut ts::thread_state_flavor_t,
+    ) -> kern_return_t;
+
     /// The host? NDR
     ///
     /// <https://github.com/apple-oss-distributions/xnu/blob/e7776783b89a353188416a9a346c6cdb4928faad/osfmk/mach/arm/ndr_def.h#L36-L45>
@@ -0,0 +1,63 @@
+//! FFI bindings for non-local goto
+//!
+//! Unlike Linux and Windows, exceptions on Macos are handled on a separate
+//! thread rather than the thread that crashed, so [`crate::CrashEventResult`]
+//! can't be used to jump back to the crashing thread. These are still useful
+//! to recover a thread that cooperates with the exception handler, eg. one
+//! that waits to be told to jump after calling
+//! [`crate::CrashHandler::simulate_exception`].
+//!
+//! ```
+//! use crash_handler::jmp;
+//!
+//! unsafe {
+//!     let mut jmp_buf = std::mem::MaybeUninit::uninit();
+//!
+//!     let val = jmp::sigsetjmp(jmp_buf.as_mut_ptr(), 1);
+//!
+//!     if val == 0 {
+//!         jmp::siglongjmp(jmp_buf.as_mut_ptr(), 22);
+//!     } else {
+//!         assert_eq!(val, 22);
+//!     }
+//! }
+//! ```
+
+/// `_JBLEN + 1` from `<machine/setjmp.h>`, the extra slot holds whether the
+/// signal mask was saved
+#[cfg(target_arch = "x86_64")]
+const SIGJMP_BUF_LEN: usize = (9 * 2) + 3 + 16 + 1;
+#[cfg(target_arch = "aarch64")]
+const SIGJMP_BUF_LEN: usize = ((14 + 8 + 2) * 2) + 1;
+
+/// A jump buffer.
+///
+/// This is essentially the register state of a point in execution at the time
+/// of a [`sigsetjmp`] call that can be returned to by passing this buffer to
+/// [`siglongjmp`].
+#[repr(C)]
+pub struct JmpBuf {
+    /// CPU context, signal mask, and whether the mask was saved
+    __jmp_buf: [i32; SIGJMP_BUF_LEN],
+}
+
+extern "C" {
+    /// Set jump point for a non-local goto.
+    ///
+    /// The return value will be 0 if this is a direct invocation (ie the "first
+    /// time" `sigsetjmp` is executed), and will be the value passed to `siglongjmp`
+    /// otherwise.
+    ///
+    /// See [sigsetjmp](https://man7.org/linux/man-pages/man3/sigsetjmp.3p.html)
+    /// for more information.
+    pub fn sigsetjmp(jb: *mut JmpBuf, save_mask: i32) -> i32;
+    /// Non-local goto with signal handling
+    ///
+    /// The value passed here will be returned by `sigsetjmp` when returning
+    /// to that callsite. Note that passing a value of 0 here will be changed
+    /// to a 1.
+    ///
+    /// See [siglongjmp](https://man7.org/linux/man-pages/man3/siglongjmp.3p.html)
+    /// for more information.
+    pub fn siglongjmp(jb: *mut JmpBuf, val: i32) -> !;
+}
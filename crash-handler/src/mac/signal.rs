@@ -7,7 +7,7 @@ use std::mem;
 /// # Safety
 ///
 /// Performs syscalls
-pub(crate) unsafe fn install_abort_handler() -> Result<libc::sigaction, std::io::Error> {
+pub(crate) unsafe fn install_abort_handler() -> Result<libc::sigaction, crate::Error> {
     let mut sa: libc::sigaction = mem::zeroed();
     libc::sigemptyset(&mut sa.sa_mask);
     libc::sigaddset(&mut sa.sa_mask, libc::SIGABRT);
@@ -19,7 +19,10 @@ pub(crate) unsafe fn install_abort_handler() -> Result<libc::sigaction, std::io:
     if libc::sigaction(libc::SIGABRT, &sa, old_action.as_mut_ptr()) != -1 {
         Ok(old_action.assume_init())
     } else {
-        Err(std::io::Error::last_os_error())
+        Err(crate::Error::Signal {
+            signal: libc::SIGABRT,
+            source: std::io::Error::last_os_error(),
+        })
     }
 }
 
@@ -29,8 +32,15 @@ pub(crate) unsafe fn install_abort_handler() -> Result<libc::sigaction, std::io:
 ///
 /// Performs syscalls
 #[inline]
-pub(crate) unsafe fn restore_abort_handler(handler: libc::sigaction) {
-    libc::sigaction(libc::SIGABRT, &handler, std::ptr::null_mut());
+pub(crate) unsafe fn restore_abort_handler(handler: libc::sigaction) -> Result<(), crate::Error> {
+    if libc::sigaction(libc::SIGABRT, &handler, std::ptr::null_mut()) == -1 {
+        Err(crate::Error::Signal {
+            signal: libc::SIGABRT,
+            source: std::io::Error::last_os_error(),
+        })
+    } else {
+        Ok(())
+    }
 }
 
 /// Our signal handler, transforms the signal into a [`crash_context::ExceptionInfo`]
@@ -45,9 +55,11 @@ unsafe extern "C" fn signal_handler(
     // Sanity check
     assert_eq!(signal, libc::SIGABRT);
 
-    super::state::simulate_exception(Some(crash_context::ExceptionInfo {
+    if super::state::simulate_exception(Some(crash_context::ExceptionInfo {
         kind: ffi::et::EXC_SOFTWARE,
         code: ffi::EXC_SOFT_SIGNAL as u64, // Unix signal
         subcode: Some(signal as _),
-    }));
+    })) {
+        super::state::exit_if_requested();
+    }
 }
@@ -47,7 +47,7 @@ pub(crate) fn kern_ret(func: impl FnOnce() -> kern_return_t) -> Result<(), Error
     if res == KERN_SUCCESS {
         Ok(())
     } else {
-        Err(std::io::Error::from_raw_os_error(res).into())
+        Err(Error::Kernel(res))
     }
 }
 
@@ -67,6 +67,40 @@ struct PreviousPorts {
     ports: [PreviousPort; EXC_TYPES_COUNT],
 }
 
+/// Restores the previously registered signal handler and exception ports, see
+/// [`HandlerInner::uninstall`]
+///
+/// SAFETY: syscalls
+unsafe fn restore_previous(
+    previous_abort_action: libc::sigaction,
+    previous: &PreviousPorts,
+) -> Result<(), Error> {
+    let mut result = super::signal::restore_abort_handler(previous_abort_action);
+
+    let current_task = mach_task_self();
+
+    // Restore the previous ports
+    for pp in &previous.ports[..previous.count] {
+        let res = task_set_exception_ports(current_task, pp.mask, pp.port, pp.behavior, pp.flavor);
+
+        if res != KERN_SUCCESS {
+            if result.is_ok() {
+                result = Err(Error::ExceptionPort(pp.mask, res));
+            }
+
+            let _cleared = task_set_exception_ports(
+                current_task,
+                pp.mask,
+                MACH_PORT_NULL,
+                et::EXCEPTION_DEFAULT as _,
+                THREAD_STATE_NONE,
+            );
+        }
+    }
+
+    result
+}
+
 type UserSignal = std::sync::Arc<(parking_lot::Mutex<Option<bool>>, parking_lot::Condvar)>;
 
 struct AllocatedPort {
@@ -83,35 +117,47 @@ impl Drop for AllocatedPort {
 
 pub(super) struct HandlerInner {
     pub(super) crash_event: Box<dyn crate::CrashEvent>,
+    /// Invoked before the crash event, see [`crate::CrashHandler::set_pre_crash_hook`]
+    pub(super) pre_crash_hook: Option<crate::PreCrashHook>,
     handler_port: AllocatedPort,
     user_signal: UserSignal,
     handler_thread: std::thread::JoinHandle<()>,
     previous_abort_action: libc::sigaction,
     previous: PreviousPorts,
+    /// The code to exit with after a crash is handled
+    exit_code_on_handled: Option<i32>,
+    /// Whether the CPU state of the crashed thread is captured
+    capture_thread_state: bool,
+    /// When the process was started, see [`crash_context::CrashTimestamps`]
+    process_start_ns: u64,
 }
 
 impl HandlerInner {
-    /// Restores the previously registered signal handler and exception ports
+    /// Restores the previously registered signal handler and exception ports.
+    ///
+    /// Every restore is attempted even if an earlier one fails, in which case
+    /// the first error is returned. If a previous port can't be restored, eg.
+    /// because it is no longer valid, the exception port for its mask is
+    /// cleared instead, as otherwise the exception would be sent to our port,
+    /// which no longer has a thread receiving on it, hanging the process.
     ///
     /// SAFETY: syscalls
     unsafe fn uninstall(&self) -> Result<(), Error> {
-        super::signal::restore_abort_handler(self.previous_abort_action);
-
-        let current_task = mach_task_self();
-
-        // Restore the previous ports
-        for pp in &self.previous.ports[..self.previous.count] {
-            kern_ret(|| {
-                task_set_exception_ports(current_task, pp.mask, pp.port, pp.behavior, pp.flavor)
-            })?;
-        }
-
-        Ok(())
+        restore_previous(self.previous_abort_action, &self.previous)
     }
 
+    /// Uninstalls the handler, then stops the handler thread, which is done
+    /// even if the uninstall fails, as the handler is no longer usable
+    ///
     /// SAFETY: syscalls
     unsafe fn shutdown(self, is_handler_thread: bool) -> Result<(), Error> {
-        self.uninstall()?;
+        let result = self.uninstall();
+
+        // If the thread has already exited, there's no one to receive the
+        // message, which would block forever once the port's queue is full
+        if self.handler_thread.is_finished() {
+            return result;
+        }
 
         let mut exc_msg: UserException = mem::zeroed();
         exc_msg.header.msgh_id = MessageIds::Shutdown as i32;
@@ -125,7 +171,7 @@ impl HandlerInner {
             }
         }
 
-        Ok(())
+        result
     }
 
     /// SAFETY: syscalls
@@ -182,7 +228,10 @@ static HANDLER_THREAD: parking_lot::Mutex<Option<mach_port_t>> = parking_lot::co
 ///
 /// - A handler has already been installed, we only allow one
 /// - Any of the various syscalls that are made fail
-pub(super) fn attach(crash_event: Box<dyn crate::CrashEvent>) -> Result<(), Error> {
+pub(super) fn attach(
+    crash_event: Box<dyn crate::CrashEvent>,
+    options: super::AttachOptions,
+) -> Result<(), Error> {
     let mut lock = HANDLER.write();
 
     if lock.is_some() {
@@ -240,7 +289,7 @@ pub(super) fn attach(crash_event: Box<dyn crate::CrashEvent>) -> Result<(), Erro
             et::MACH_EXCEPTION_CODES;
 
         // Swap the exception ports so that we use our own
-        kern_ret(|| {
+        if let Err(err) = kern_ret(|| {
             task_swap_exception_ports(
                 current_task,
                 EXCEPTION_MASK,
@@ -253,7 +302,10 @@ pub(super) fn attach(crash_event: Box<dyn crate::CrashEvent>) -> Result<(), Erro
                 behaviors.as_mut_ptr(),
                 flavors.as_mut_ptr(),
             )
-        })?;
+        }) {
+            let _ = super::signal::restore_abort_handler(previous_abort_action);
+            return Err(err);
+        }
 
         let mut previous: PreviousPorts = std::mem::zeroed();
         previous.count = count as usize;
@@ -274,37 +326,120 @@ pub(super) fn attach(crash_event: Box<dyn crate::CrashEvent>) -> Result<(), Erro
 
         // Spawn a thread that will handle the actual exception/user messages sent
         // to the exception port we've just created
-        let handler_thread = std::thread::spawn(move || {
+        let handler_thread = match std::thread::Builder::new().spawn(move || {
             *HANDLER_THREAD.lock() = Some(mach_thread_self());
 
             exception_handler(port, us);
 
             *HANDLER_THREAD.lock() = None;
-        });
+        }) {
+            Ok(thread) => thread,
+            Err(err) => {
+                let _ = restore_previous(previous_abort_action, &previous);
+                return Err(Error::ThreadSpawn(err));
+            }
+        };
 
         *lock = Some(HandlerInner {
             crash_event,
+            pre_crash_hook: None,
             handler_port,
             user_signal,
             handler_thread,
             previous_abort_action,
             previous,
+            exit_code_on_handled: options.exit_code_on_handled,
+            capture_thread_state: options.capture_thread_state,
+            process_start_ns: process_start_ns(),
         });
     }
 
+    crate::main_thread::attaching();
+
     Ok(())
 }
 
-pub(super) fn detach(is_handler_thread: bool) {
+/// Detaches the handler, restoring the previous exception ports and abort
+/// handler, and stopping the handler thread.
+///
+/// The handler is detached even if something fails to be restored, in which
+/// case the first error is returned
+pub(super) fn detach(is_handler_thread: bool) -> Result<(), Error> {
     let mut lock = HANDLER.write();
     if let Some(handler) = lock.take() {
-        // user can't really do anything if something fails at this point, but
-        // should have a clean way of surfacing the error happened
         // SAFETY: syscalls
-        let _result = unsafe { handler.shutdown(is_handler_thread) };
+        unsafe { handler.shutdown(is_handler_thread) }
+    } else {
+        Ok(())
     }
 }
 
+/// Stops the handler thread without detaching the handler, as if it had
+/// exited unexpectedly, waiting until it has
+#[cfg(test)]
+pub(super) fn stop_handler_thread() {
+    let lock = HANDLER.read();
+    let handler = lock.as_ref().expect("handler is not attached");
+
+    // SAFETY: UserException is POD and send_message is syscalls
+    unsafe {
+        let mut exc_msg: UserException = mem::zeroed();
+        exc_msg.header.msgh_id = MessageIds::Shutdown as i32;
+        assert!(handler.send_message(exc_msg));
+    }
+
+    while !handler.handler_thread.is_finished() {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+}
+
+/// Replaces the first previous exception port with the specified port, or adds
+/// it if there weren't any, so that it is restored when detaching
+#[cfg(test)]
+pub(super) fn set_previous_port(mask: et::exception_mask_t, port: mach_port_t) {
+    let mut lock = HANDLER.write();
+    let handler = lock.as_mut().expect("handler is not attached");
+
+    handler.previous.ports[0] = PreviousPort {
+        mask,
+        port,
+        behavior: et::EXCEPTION_DEFAULT as _,
+        flavor: THREAD_STATE_NONE,
+    };
+    handler.previous.count = handler.previous.count.max(1);
+}
+
+/// Retrieves the exception port currently registered for the mask
+#[cfg(test)]
+pub(super) fn current_port(mask: et::exception_mask_t) -> mach_port_t {
+    let mut count = EXC_TYPES_COUNT as u32;
+    let mut masks = [0; EXC_TYPES_COUNT];
+    let mut ports = [0; EXC_TYPES_COUNT];
+    let mut behaviors = [0; EXC_TYPES_COUNT];
+    let mut flavors = [0; EXC_TYPES_COUNT];
+
+    // SAFETY: syscall
+    unsafe {
+        kern_ret(|| {
+            task_get_exception_ports(
+                mach_task_self(),
+                mask,
+                masks.as_mut_ptr(),
+                &mut count,
+                ports.as_mut_ptr(),
+                behaviors.as_mut_ptr(),
+                flavors.as_mut_ptr(),
+            )
+        })
+        .expect("failed to get exception ports");
+    }
+
+    masks[..count as usize]
+        .iter()
+        .position(|m| m & mask != 0)
+        .map_or(MACH_PORT_NULL, |i| ports[i])
+}
+
 #[repr(C)]
 struct UserException {
     header: msg::mach_msg_header_t,
@@ -370,11 +505,94 @@ pub(super) fn simulate_exception(info: Option<crash_context::ExceptionInfo>) ->
     }
 }
 
+/// See [`crate::CrashHandler::set_pre_crash_hook`]
+pub(crate) fn replace_pre_crash_hook(hook: crate::PreCrashHook) {
+    if let Some(handler) = &mut *HANDLER.write() {
+        handler.pre_crash_hook = Some(hook);
+    }
+}
+
+/// Exits the process if an exit code was specified for handled crashes
+pub(super) fn exit_if_requested() {
+    let code = HANDLER
+        .read()
+        .as_ref()
+        .and_then(|hi| hi.exit_code_on_handled);
+
+    if let Some(code) = code {
+        // SAFETY: syscall
+        unsafe { libc::_exit(code) };
+    }
+}
+
+/// Retrieves the time the current process was started, in nanoseconds since
+/// the Unix epoch, or 0 if it can't be retrieved
+fn process_start_ns() -> u64 {
+    // SAFETY: syscall
+    unsafe {
+        let mut info: libc::proc_bsdinfo = mem::zeroed();
+        let size = mem::size_of::<libc::proc_bsdinfo>() as i32;
+
+        if libc::proc_pidinfo(
+            std::process::id() as i32,
+            libc::PROC_PIDTBSDINFO,
+            0,
+            (&mut info as *mut libc::proc_bsdinfo).cast(),
+            size,
+        ) != size
+        {
+            return 0;
+        }
+
+        info.pbi_start_tvsec * 1_000_000_000 + info.pbi_start_tvusec * 1_000
+    }
+}
+
+/// Retrieves the name of the specified thread in this process
+fn thread_name(thread: mach_port_t) -> crash_context::ThreadName {
+    // SAFETY: syscalls
+    unsafe {
+        let pthread = libc::pthread_from_mach_thread_np(thread);
+        if pthread == 0 {
+            return crash_context::ThreadName::EMPTY;
+        }
+
+        // MAXTHREADNAMESIZE
+        let mut name = [0u8; 64];
+        if libc::pthread_getname_np(pthread, name.as_mut_ptr().cast(), name.len()) != 0 {
+            return crash_context::ThreadName::EMPTY;
+        }
+
+        crash_context::ThreadName::new(&name)
+    }
+}
+
+/// Reads the clocks for the crash that is being handled
+#[inline]
+fn crash_timestamps() -> crash_context::CrashTimestamps {
+    let process_start_ns = HANDLER.read().as_ref().map_or(0, |hi| hi.process_start_ns);
+    crash_context::CrashTimestamps::now(process_start_ns)
+}
+
+/// Captures the CPU state of the crashed thread, if requested when attaching
+#[inline]
+fn capture_thread_state(
+    thread: mach2::mach_types::thread_t,
+) -> Option<crash_context::thread_state::ThreadState> {
+    let capture = matches!(&*HANDLER.read(), Some(hi) if hi.capture_thread_state);
+
+    if capture {
+        crash_context::thread_state::ThreadState::capture(thread)
+    } else {
+        None
+    }
+}
+
 #[inline]
 fn call_user_callback(cc: &crash_context::CrashContext) -> CrashEventResult {
     let lock = HANDLER.read();
     if let Some(handler) = &*lock {
-        handler.crash_event.on_crash(cc)
+        crate::call_crash_event(&*handler.crash_event, handler.pre_crash_hook.as_ref(), cc)
     } else {
         CrashEventResult::Handled(false)
     }
@@ -430,15 +648,21 @@ unsafe fn exception_handler(port: mach_port_t, us: UserSignal) {
                     // and importantly _don't_ detach the exception handler like we
                     // do for fatal exceptions
                     if !is_exception_non_fatal(exc_info, request.task.name) {
-                        let cc = crash_context::CrashContext {
+                        let mut cc = crash_context::CrashContext {
                             thread: request.thread.name,
                             task: request.task.name,
                             handler_thread: mach_thread_self(),
+                            main_thread: crash_context::MainThread::UNKNOWN,
                             exception: Some(exc_info),
+                            timestamps: crash_timestamps(),
+                            thread_name: thread_name(request.thread.name),
+                            thread_state: capture_thread_state(request.thread.name),
                         };
+                        cc.main_thread = crate::main_thread::main_thread(cc.crashing_thread_id());
 
                         let ret_code =
                             if let CrashEventResult::Handled(true) = call_user_callback(&cc) {
+                                exit_if_requested();
                                 KERN_SUCCESS
                             } else {
                                 mach2::kern_return::KERN_FAILURE
@@ -446,8 +670,9 @@ unsafe fn exception_handler(port: mach_port_t, us: UserSignal) {
 
                         // Restores the previous exception ports, in most cases
                         // this will be the default for the OS, which will kill this
-                        // process when we reply that we've handled the exception
-                        detach(true);
+                        // process when we reply that we've handled the exception.
+                        // There's no one to report a failure to at this point
+                        let _ = detach(true);
 
                         ret_code
                     } else {
@@ -500,12 +725,17 @@ unsafe fn exception_handler(port: mach_port_t, us: UserSignal) {
                     };
 
                     // Reconstruct a crash context from the message we received
-                    let cc = crash_context::CrashContext {
+                    let mut cc = crash_context::CrashContext {
                         task: mach_task_self(),
                         thread: user_exception.crash_thread.name,
                         handler_thread: mach_thread_self(),
+                        main_thread: crash_context::MainThread::UNKNOWN,
                         exception,
+                        timestamps: crash_timestamps(),
+                        thread_name: thread_name(user_exception.crash_thread.name),
+                        thread_state: capture_thread_state(user_exception.crash_thread.name),
                     };
+                    cc.main_thread = crate::main_thread::main_thread(cc.crashing_thread_id());
 
                     call_user_callback(&cc)
                 };
@@ -0,0 +1,81 @@
+//! Tracks which thread is the main thread of the process, so that the crash
+//! handler can report whether a crash occurred on it, see [`set_main_thread`]
+
+/// Sets the calling thread as the main thread of the process, as reported by
+/// `CrashContext::is_main_thread`.
+///
+/// * Windows - The thread that the handler is attached from is assumed to be
+///   the main thread, as there is no way to determine it afterwards, so this
+///   only needs to be called, from the main thread, if the handler is
+///   attached from another thread.
+/// * Macos - The thread that the handler is attached from is only used if it
+///   is the main thread, otherwise whether a crash occurred on the main thread
+///   is unknown until this is called from it.
+/// * Linux/Android - This has no effect, as the main thread is always the one
+///   whose id is the same as the process's.
+#[inline]
+pub fn set_main_thread() {
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    imp::MAIN_THREAD.store(
+        imp::current_thread_id(),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub(crate) use imp::{attaching, main_thread};
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+mod imp {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// The OS id of the main thread, or 0 if it isn't known
+    pub(super) static MAIN_THREAD: AtomicU64 = AtomicU64::new(0);
+
+    /// The OS id of the calling thread, as reported by the `CrashContext`
+    #[inline]
+    pub(super) fn current_thread_id() -> u64 {
+        cfg_if::cfg_if! {
+            if #[cfg(target_os = "windows")] {
+                extern "system" {
+                    fn GetCurrentThreadId() -> u32;
+                }
+
+                // SAFETY: syscall
+                unsafe { GetCurrentThreadId() }.into()
+            } else {
+                let mut id = 0;
+                // SAFETY: syscall
+                unsafe { libc::pthread_threadid_np(0, &mut id) };
+                id
+            }
+        }
+    }
+
+    /// Sets the thread attaching the handler as the main thread, unless it
+    /// has already been set, see [`super::set_main_thread`]
+    pub(crate) fn attaching() {
+        // SAFETY: syscall
+        #[cfg(target_os = "macos")]
+        if unsafe { libc::pthread_main_np() } == 0 {
+            return;
+        }
+
+        let _ = MAIN_THREAD.compare_exchange(
+            0,
+            current_thread_id(),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+
+    /// Whether the thread with the specified OS id is the main thread, where
+    /// an id of 0 is an unknown thread
+    pub(crate) fn main_thread(thread_id: u64) -> crash_context::MainThread {
+        match MAIN_THREAD.load(Ordering::Relaxed) {
+            0 => crash_context::MainThread::UNKNOWN,
+            _ if thread_id == 0 => crash_context::MainThread::UNKNOWN,
+            main => Some(main == thread_id).into(),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! The handler for targets that crashes can't be caught on, eg.
+//! `wasm32-unknown-unknown`, so that code attaching it compiles unchanged, but
+//! always fails to attach with [`crate::Error::Unsupported`]
+
+/// Options for [`CrashHandler::attach_with_options`], of which there are none
+/// on this target
+#[derive(Copy, Clone, Default, Debug)]
+pub struct AttachOptions {
+    _private: (),
+}
+
+/// A handler that can't be attached on this target
+pub struct CrashHandler;
+
+#[allow(clippy::unused_self)]
+impl CrashHandler {
+    /// Always fails with [`crate::Error::Unsupported`], as crashes can't be
+    /// caught on this target
+    pub fn attach(on_crash: Box<dyn crate::CrashEvent>) -> Result<Self, crate::Error> {
+        Self::attach_with_options(on_crash, AttachOptions::default())
+    }
+
+    /// Always fails with [`crate::Error::Unsupported`], as crashes can't be
+    /// caught on this target
+    pub fn attach_with_options(
+        _on_crash: Box<dyn crate::CrashEvent>,
+        _options: AttachOptions,
+    ) -> Result<Self, crate::Error> {
+        Err(crate::Error::Unsupported)
+    }
+
+    /// Detaches the handler, which is a no-op as it can never be attached
+    #[inline]
+    pub fn detach(self) -> Result<(), crate::Error> {
+        Ok(())
+    }
+}
+
+/// The pre-crash hook is never invoked, as there are never any crashes to
+/// invoke it for, so it is just dropped
+pub(crate) fn replace_pre_crash_hook(_hook: crate::PreCrashHook) {}
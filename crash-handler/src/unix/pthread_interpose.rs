@@ -163,7 +163,7 @@ unsafe fn install_sig_alt_stack() -> *mut libc::c_void {
         ptr::null_mut(),
         SIG_STACK_SIZE,
         libc::PROT_READ | libc::PROT_WRITE,
-        libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+        libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_STACK,
         -1,
         0,
     );
@@ -4,29 +4,449 @@ mod state;
 
 use crate::Error;
 
-/// Possible exception codes values for the the `exception_code` field
-/// in the crash context.
+/// Exception codes that can be seen in the `exception_code` field of the crash
+/// context, ie. the `NTSTATUS` codes of the exceptions that are crashes, or
+/// that are commonly seen by an unhandled exception filter.
 ///
-/// This is mainly for testing purposes, and is not exhaustive nor really accurate,
-/// as eg. a distinction is made between a divide by zero between integers and
-/// floats.
-#[derive(Copy, Clone)]
+/// Exceptions can be raised with any code, eg. by language runtimes, so codes
+/// that aren't known are returned as `None` by [`Self::from_code`].
+///
+/// | Code | Name | Variant |
+/// |------|------|---------|
+/// | `0x40000015` | `STATUS_FATAL_APP_EXIT` | [`Self::Abort`] |
+/// | `0x40010005` | `DBG_CONTROL_C` | [`Self::DebugControlC`] |
+/// | `0x40010006` | `DBG_PRINTEXCEPTION_C` | [`Self::DebugPrint`] |
+/// | `0x4001000a` | `DBG_PRINTEXCEPTION_WIDE_C` | [`Self::DebugPrintWide`] |
+/// | `0x80000001` | `EXCEPTION_GUARD_PAGE` | [`Self::GuardPage`] |
+/// | `0x80000002` | `EXCEPTION_DATATYPE_MISALIGNMENT` | [`Self::DatatypeMisalignment`] |
+/// | `0x80000003` | `EXCEPTION_BREAKPOINT` | [`Self::Trap`] |
+/// | `0x80000004` | `EXCEPTION_SINGLE_STEP` | [`Self::SingleStep`] |
+/// | `0xc0000005` | `EXCEPTION_ACCESS_VIOLATION` | [`Self::Segv`] |
+/// | `0xc0000006` | `EXCEPTION_IN_PAGE_ERROR` | [`Self::InPageError`] |
+/// | `0xc0000008` | `EXCEPTION_INVALID_HANDLE` | [`Self::InvalidHandle`] |
+/// | `0xc000000d` | `STATUS_INVALID_PARAMETER` | [`Self::InvalidParameter`] |
+/// | `0xc0000017` | `STATUS_NO_MEMORY` | [`Self::NoMemory`] |
+/// | `0xc000001d` | `EXCEPTION_ILLEGAL_INSTRUCTION` | [`Self::Illegal`] |
+/// | `0xc0000025` | `STATUS_NONCONTINUABLE_EXCEPTION` | [`Self::Purecall`] |
+/// | `0xc0000026` | `EXCEPTION_INVALID_DISPOSITION` | [`Self::InvalidDisposition`] |
+/// | `0xc0000028` | `STATUS_BAD_STACK` | [`Self::BadStack`] |
+/// | `0xc0000029` | `STATUS_INVALID_UNWIND_TARGET` | [`Self::InvalidUnwindTarget`] |
+/// | `0xc000008c` | `EXCEPTION_ARRAY_BOUNDS_EXCEEDED` | [`Self::ArrayBoundsExceeded`] |
+/// | `0xc000008d` | `EXCEPTION_FLT_DENORMAL_OPERAND` | [`Self::FloatDenormalOperand`] |
+/// | `0xc000008e` | `EXCEPTION_FLT_DIVIDE_BY_ZERO` | [`Self::FloatDivideByZero`] |
+/// | `0xc000008f` | `EXCEPTION_FLT_INEXACT_RESULT` | [`Self::FloatInexactResult`] |
+/// | `0xc0000090` | `EXCEPTION_FLT_INVALID_OPERATION` | [`Self::FloatInvalidOperation`] |
+/// | `0xc0000091` | `EXCEPTION_FLT_OVERFLOW` | [`Self::FloatOverflow`] |
+/// | `0xc0000092` | `EXCEPTION_FLT_STACK_CHECK` | [`Self::FloatStackCheck`] |
+/// | `0xc0000093` | `EXCEPTION_FLT_UNDERFLOW` | [`Self::FloatUnderflow`] |
+/// | `0xc0000094` | `EXCEPTION_INT_DIVIDE_BY_ZERO` | [`Self::Fpe`] |
+/// | `0xc0000095` | `EXCEPTION_INT_OVERFLOW` | [`Self::IntOverflow`] |
+/// | `0xc0000096` | `EXCEPTION_PRIV_INSTRUCTION` | [`Self::PrivilegedInstruction`] |
+/// | `0xc00000fd` | `EXCEPTION_STACK_OVERFLOW` | [`Self::StackOverflow`] |
+/// | `0xc0000194` | `EXCEPTION_POSSIBLE_DEADLOCK` | [`Self::PossibleDeadlock`] |
+/// | `0xc00002b4` | `STATUS_FLOAT_MULTIPLE_FAULTS` | [`Self::FloatMultipleFaults`] |
+/// | `0xc00002b5` | `STATUS_FLOAT_MULTIPLE_TRAPS` | [`Self::FloatMultipleTraps`] |
+/// | `0xc0000374` | `STATUS_HEAP_CORRUPTION` | [`Self::HeapCorruption`] |
+/// | `0xc0000409` | `STATUS_STACK_BUFFER_OVERRUN` | [`Self::StackBufferOverrun`] |
+/// | `0xc0000417` | `STATUS_INVALID_CRUNTIME_PARAMETER` | [`Self::InvalidCrtParameter`] |
+/// | `0xc000041d` | `STATUS_FATAL_USER_CALLBACK_EXCEPTION` | [`Self::FatalUserCallback`] |
+/// | `0xc0000420` | `STATUS_ASSERTION_FAILURE` | [`Self::AssertionFailure`] |
+/// | `0xc0000602` | `STATUS_FAIL_FAST_EXCEPTION` | [`Self::FailFast`] |
+/// | `0xe06d7363` | `EH_EXCEPTION_NUMBER` | [`Self::CppException`] |
+/// | `0xe0434352` | `EXCEPTION_COMPLUS` | [`Self::ClrException`] |
+/// | `0x0cca11ed` | `EXCEPTION_SIMULATED` | [`Self::User`] |
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[repr(i32)]
 #[allow(overflowing_literals)]
+#[non_exhaustive]
 pub enum ExceptionCode {
-    Abort = 0x40000015,             // STATUS_FATAL_APP_EXIT
-    Fpe = -1073741676,              // EXCEPTION_INT_DIVIDE_BY_ZERO
-    Illegal = -1073741795,          // EXCEPTION_ILLEGAL_INSTRUCTION
-    Segv = -1073741819,             // EXCEPTION_ACCESS_VIOLATION
-    StackOverflow = -1073741571,    // EXCEPTION_STACK_OVERFLOW
-    Trap = -2147483645,             // EXCEPTION_BREAKPOINT
-    InvalidParameter = -1073741811, // STATUS_INVALID_PARAMETER
-    Purecall = -1073741787,         // STATUS_NONCONTINUABLE_EXCEPTION
-    User = 0xcca11ed, // https://github.com/chromium/crashpad/blob/fca8871ca3fb721d3afab370ca790122f9333bfd/util/win/exception_codes.h#L32
-    HeapCorruption = 0xc0000374, // STATUS_HEAP_CORRUPTION
+    /// The process aborted, eg. via `abort()`
+    Abort = 0x40000015,
+    /// Ctrl+C was pressed in a console process being debugged
+    DebugControlC = 0x40010005,
+    /// Raised by `OutputDebugStringA` to pass the string to a debugger
+    DebugPrint = 0x40010006,
+    /// Raised by `OutputDebugStringW` to pass the string to a debugger
+    DebugPrintWide = 0x4001000a,
+    /// A page with `PAGE_GUARD` was accessed, after which it is an ordinary page again
+    GuardPage = 0x80000001,
+    /// Misaligned data was accessed on hardware that doesn't allow it
+    DatatypeMisalignment = 0x80000002,
+    /// A breakpoint was hit, eg. `int3` or `__debugbreak`
+    Trap = 0x80000003,
+    /// A single instruction was executed with the trap flag set, eg. by a debugger
+    SingleStep = 0x80000004,
+    /// Memory was read, written, or executed without the access to do so
+    Segv = 0xc0000005,
+    /// A page couldn't be loaded, eg. the file it maps is on a network drive that was disconnected
+    InPageError = 0xc0000006,
+    /// An invalid handle was used, only raised when eg. a debugger or Application Verifier asks for it
+    InvalidHandle = 0xc0000008,
+    /// A CRT function was passed an invalid parameter
+    InvalidParameter = 0xc000000d,
+    /// A heap allocation failed, for heaps created with `HEAP_GENERATE_EXCEPTIONS`
+    NoMemory = 0xc0000017,
+    /// An invalid instruction was executed
+    Illegal = 0xc000001d,
+    /// Execution was continued after a non-continuable exception, also used for pure virtual calls
+    Purecall = 0xc0000025,
+    /// An exception handler returned an invalid disposition
+    InvalidDisposition = 0xc0000026,
+    /// The stack was found to be invalid or misaligned while unwinding
+    BadStack = 0xc0000028,
+    /// An invalid unwind target was found while unwinding
+    InvalidUnwindTarget = 0xc0000029,
+    /// An array was accessed out of bounds on hardware that checks bounds
+    ArrayBoundsExceeded = 0xc000008c,
+    /// A floating point operand was denormal, if the exception is unmasked
+    FloatDenormalOperand = 0xc000008d,
+    /// A floating point value was divided by zero, if the exception is unmasked
+    FloatDivideByZero = 0xc000008e,
+    /// A floating point result can't be represented exactly, if the exception is unmasked
+    FloatInexactResult = 0xc000008f,
+    /// Any other floating point exception, if the exception is unmasked
+    FloatInvalidOperation = 0xc0000090,
+    /// A floating point result overflowed, if the exception is unmasked
+    FloatOverflow = 0xc0000091,
+    /// The x87 register stack overflowed or underflowed
+    FloatStackCheck = 0xc0000092,
+    /// A floating point result underflowed, if the exception is unmasked
+    FloatUnderflow = 0xc0000093,
+    /// An integer was divided by zero
+    Fpe = 0xc0000094,
+    /// An integer operation overflowed, eg. `i32::MIN / -1`
+    IntOverflow = 0xc0000095,
+    /// An instruction that isn't allowed in user mode was executed
+    PrivilegedInstruction = 0xc0000096,
+    /// The thread exhausted its stack
+    StackOverflow = 0xc00000fd,
+    /// Waiting on a critical section timed out
+    PossibleDeadlock = 0xc0000194,
+    /// Multiple floating point faults occurred, in code using SSE
+    FloatMultipleFaults = 0xc00002b4,
+    /// Multiple floating point traps occurred, in code using SSE
+    FloatMultipleTraps = 0xc00002b5,
+    /// The heap detected that it was corrupted, see [`VectoredHandler`]
+    HeapCorruption = 0xc0000374,
+    /// The process terminated itself via `__fastfail`, eg. due to a stack cookie, or Control Flow Guard, check failing
+    StackBufferOverrun = 0xc0000409,
+    /// The CRT terminated the process after being passed an invalid parameter
+    InvalidCrtParameter = 0xc0000417,
+    /// An exception was unhandled in a callback from the kernel, eg. a window procedure
+    FatalUserCallback = 0xc000041d,
+    /// An assertion failed, eg. via `int 0x2c`
+    AssertionFailure = 0xc0000420,
+    /// The process terminated itself via `RaiseFailFastException`
+    FailFast = 0xc0000602,
+    /// An MSVC C++ exception was thrown
+    CppException = 0xe06d7363,
+    /// A .NET exception was thrown
+    ClrException = 0xe0434352,
+    /// Used for crashes simulated via [`CrashHandler::simulate_exception`], see [crashpad](https://github.com/chromium/crashpad/blob/fca8871ca3fb721d3afab370ca790122f9333bfd/util/win/exception_codes.h#L32)
+    User = 0x0cca11ed,
+}
+
+impl ExceptionCode {
+    /// Every exception code
+    pub const ALL: &'static [Self] = &[
+        Self::Abort,
+        Self::DebugControlC,
+        Self::DebugPrint,
+        Self::DebugPrintWide,
+        Self::GuardPage,
+        Self::DatatypeMisalignment,
+        Self::Trap,
+        Self::SingleStep,
+        Self::Segv,
+        Self::InPageError,
+        Self::InvalidHandle,
+        Self::InvalidParameter,
+        Self::NoMemory,
+        Self::Illegal,
+        Self::Purecall,
+        Self::InvalidDisposition,
+        Self::BadStack,
+        Self::InvalidUnwindTarget,
+        Self::ArrayBoundsExceeded,
+        Self::FloatDenormalOperand,
+        Self::FloatDivideByZero,
+        Self::FloatInexactResult,
+        Self::FloatInvalidOperation,
+        Self::FloatOverflow,
+        Self::FloatStackCheck,
+        Self::FloatUnderflow,
+        Self::Fpe,
+        Self::IntOverflow,
+        Self::PrivilegedInstruction,
+        Self::StackOverflow,
+        Self::PossibleDeadlock,
+        Self::FloatMultipleFaults,
+        Self::FloatMultipleTraps,
+        Self::HeapCorruption,
+        Self::StackBufferOverrun,
+        Self::InvalidCrtParameter,
+        Self::FatalUserCallback,
+        Self::AssertionFailure,
+        Self::FailFast,
+        Self::CppException,
+        Self::ClrException,
+        Self::User,
+    ];
+
+    /// Converts eg. [`crate::CrashContext::exception_code`] into an
+    /// [`ExceptionCode`], if it is known
+    #[inline]
+    pub fn from_code(code: i32) -> Option<Self> {
+        Self::ALL.iter().find(|ec| **ec as i32 == code).copied()
+    }
+
+    /// The conventional name of the exception code, eg. `EXCEPTION_ACCESS_VIOLATION`
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Abort => "STATUS_FATAL_APP_EXIT",
+            Self::DebugControlC => "DBG_CONTROL_C",
+            Self::DebugPrint => "DBG_PRINTEXCEPTION_C",
+            Self::DebugPrintWide => "DBG_PRINTEXCEPTION_WIDE_C",
+            Self::GuardPage => "EXCEPTION_GUARD_PAGE",
+            Self::DatatypeMisalignment => "EXCEPTION_DATATYPE_MISALIGNMENT",
+            Self::Trap => "EXCEPTION_BREAKPOINT",
+            Self::SingleStep => "EXCEPTION_SINGLE_STEP",
+            Self::Segv => "EXCEPTION_ACCESS_VIOLATION",
+            Self::InPageError => "EXCEPTION_IN_PAGE_ERROR",
+            Self::InvalidHandle => "EXCEPTION_INVALID_HANDLE",
+            Self::InvalidParameter => "STATUS_INVALID_PARAMETER",
+            Self::NoMemory => "STATUS_NO_MEMORY",
+            Self::Illegal => "EXCEPTION_ILLEGAL_INSTRUCTION",
+            Self::Purecall => "STATUS_NONCONTINUABLE_EXCEPTION",
+            Self::InvalidDisposition => "EXCEPTION_INVALID_DISPOSITION",
+            Self::BadStack => "STATUS_BAD_STACK",
+            Self::InvalidUnwindTarget => "STATUS_INVALID_UNWIND_TARGET",
+            Self::ArrayBoundsExceeded => "EXCEPTION_ARRAY_BOUNDS_EXCEEDED",
+            Self::FloatDenormalOperand => "EXCEPTION_FLT_DENORMAL_OPERAND",
+            Self::FloatDivideByZero => "EXCEPTION_FLT_DIVIDE_BY_ZERO",
+            Self::FloatInexactResult => "EXCEPTION_FLT_INEXACT_RESULT",
+            Self::FloatInvalidOperation => "EXCEPTION_FLT_INVALID_OPERATION",
+            Self::FloatOverflow => "EXCEPTION_FLT_OVERFLOW",
+            Self::FloatStackCheck => "EXCEPTION_FLT_STACK_CHECK",
+            Self::FloatUnderflow => "EXCEPTION_FLT_UNDERFLOW",
+            Self::Fpe => "EXCEPTION_INT_DIVIDE_BY_ZERO",
+            Self::IntOverflow => "EXCEPTION_INT_OVERFLOW",
+            Self::PrivilegedInstruction => "EXCEPTION_PRIV_INSTRUCTION",
+            Self::StackOverflow => "EXCEPTION_STACK_OVERFLOW",
+            Self::PossibleDeadlock => "EXCEPTION_POSSIBLE_DEADLOCK",
+            Self::FloatMultipleFaults => "STATUS_FLOAT_MULTIPLE_FAULTS",
+            Self::FloatMultipleTraps => "STATUS_FLOAT_MULTIPLE_TRAPS",
+            Self::HeapCorruption => "STATUS_HEAP_CORRUPTION",
+            Self::StackBufferOverrun => "STATUS_STACK_BUFFER_OVERRUN",
+            Self::InvalidCrtParameter => "STATUS_INVALID_CRUNTIME_PARAMETER",
+            Self::FatalUserCallback => "STATUS_FATAL_USER_CALLBACK_EXCEPTION",
+            Self::AssertionFailure => "STATUS_ASSERTION_FAILURE",
+            Self::FailFast => "STATUS_FAIL_FAST_EXCEPTION",
+            Self::CppException => "EH_EXCEPTION_NUMBER",
+            Self::ClrException => "EXCEPTION_COMPLUS",
+            Self::User => "EXCEPTION_SIMULATED",
+        }
+    }
+
+    /// A hint for whether the exception is a crash the process can't continue
+    /// from, as opposed to exceptions that are routinely raised and handled,
+    /// or continued from, eg. C++ exceptions, debugger output, and guard page
+    /// accesses.
+    ///
+    /// Any exception that isn't handled terminates the process, so this is
+    /// mostly useful for deciding whether an exception seen by eg. a vectored
+    /// handler is worth reporting.
+    #[inline]
+    pub fn is_fatal_by_default(self) -> bool {
+        !matches!(
+            self,
+            Self::DebugControlC
+                | Self::DebugPrint
+                | Self::DebugPrintWide
+                | Self::GuardPage
+                | Self::SingleStep
+                | Self::CppException
+                | Self::ClrException
+                | Self::User
+        )
+    }
+}
+
+impl std::fmt::Display for ExceptionCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
+impl TryFrom<i32> for ExceptionCode {
+    type Error = crate::UnknownCodeError;
+
+    /// Converts eg. [`crate::CrashContext::exception_code`] into an [`ExceptionCode`]
+    fn try_from(code: i32) -> Result<Self, Self::Error> {
+        // Exception codes are conventionally treated as unsigned
+        Self::from_code(code).ok_or(crate::UnknownCodeError((code as u32).into()))
+    }
+}
+
+impl TryFrom<u32> for ExceptionCode {
+    type Error = crate::UnknownCodeError;
+
+    /// Converts an exception code in its (common) unsigned form, eg.
+    /// `0xc0000005`, into an [`ExceptionCode`]
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        Self::try_from(code as i32)
+    }
+}
+
+/// Whether, and where, the [`CrashHandler`] installs the vectored exception
+/// handler it uses to catch `STATUS_HEAP_CORRUPTION`, which is otherwise not
+/// delivered to the unhandled exception filter, see
+/// [`AttachOptions::vectored_handler`]
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub enum VectoredHandler {
+    /// The handler is called before any vectored handlers that are already
+    /// registered
+    #[default]
+    First,
+    /// The handler is called after any vectored handlers that are already
+    /// registered
+    Last,
+    /// The handler is not installed, only the unhandled exception filter is
+    Disabled,
+}
+
+/// The default for [`AttachOptions::stack_guarantee`]
+pub const DEFAULT_STACK_GUARANTEE: u32 = 64 * 1024;
+
 /// A Windows exception handler
+/// Options for [`CrashHandler::attach_with_options`]
+#[derive(Copy, Clone, Default, Debug)]
+pub struct AttachOptions {
+    pub(crate) exit_code_on_handled: Option<i32>,
+    pub(crate) always_chain_previous_filter: bool,
+    pub(crate) terminate_on_nested_crash: bool,
+    pub(crate) vectored_handler: VectoredHandler,
+    pub(crate) handle_debug_exceptions: bool,
+    pub(crate) stack_guarantee: Option<u32>,
+}
+
+impl AttachOptions {
+    /// Terminates the process with the specified exit code via
+    /// `TerminateProcess` after the [`crate::CrashEvent`] returns
+    /// `Handled(true)` for a crash, rather than letting the exception
+    /// terminate the process with the exception code.
+    ///
+    /// This allows a parent process to distinguish between a crash that was
+    /// handled, eg. a minidump was written, and one that wasn't, but note that
+    /// as the exception is no longer unhandled, WER will not see it.
+    ///
+    /// This does not apply to [`CrashHandler::simulate_exception`].
+    #[inline]
+    pub fn exit_code_on_handled(mut self, code: i32) -> Self {
+        self.exit_code_on_handled = Some(code);
+        self
+    }
+
+    /// By default, the unhandled exception filter, invalid parameter handler,
+    /// and purecall handler that were installed before the [`CrashHandler`]
+    /// are only invoked if the [`crate::CrashEvent`] returns `Handled(false)`.
+    ///
+    /// If enabled, the previous handler is also invoked after the callback
+    /// returns `Handled(true)`, so that eg. WER `LocalDumps` or an endpoint
+    /// agent still see every crash. The return value of a previous exception
+    /// filter is ignored in this case, `EXCEPTION_EXECUTE_HANDLER` is always
+    /// returned as the crash was handled. Note that the previous handler may
+    /// itself terminate the process, in which case [`Self::exit_code_on_handled`]
+    /// will not be applied.
+    #[inline]
+    pub fn always_chain_previous_filter(mut self, chain: bool) -> Self {
+        self.always_chain_previous_filter = chain;
+        self
+    }
+
+    /// By default, the handlers that were installed before the [`CrashHandler`]
+    /// are restored while the [`crate::CrashEvent`] is running, so that a crash
+    /// that occurs inside the callback is delivered to them.
+    ///
+    /// If enabled, handlers that immediately terminate the process with the
+    /// code of the nested exception are installed instead, which avoids eg. WER
+    /// showing a modal dialog and blocking the process indefinitely when the
+    /// callback crashes.
+    #[inline]
+    pub fn terminate_on_nested_crash(mut self, terminate: bool) -> Self {
+        self.terminate_on_nested_crash = terminate;
+        self
+    }
+
+    /// Sets where the vectored exception handler is registered, relative to
+    /// vectored handlers that are already registered, eg. by another library
+    /// or an anti-cheat driver, or disables it entirely.
+    ///
+    /// Defaults to [`VectoredHandler::First`]. Note that heap corruption is not
+    /// reported to the [`crate::CrashEvent`] if the handler is
+    /// [`VectoredHandler::Disabled`], nor if it is [`VectoredHandler::Last`]
+    /// and one of the handlers before it continues execution.
+    #[inline]
+    pub fn vectored_handler(mut self, position: VectoredHandler) -> Self {
+        self.vectored_handler = position;
+        self
+    }
+
+    /// By default, the exceptions raised by `OutputDebugString` when no
+    /// debugger is attached (`DBG_PRINTEXCEPTION_C` and
+    /// `DBG_PRINTEXCEPTION_WIDE_C`), as well as `EXCEPTION_SINGLE_STEP`, are
+    /// not crashes, and are passed to the previous exception filter without
+    /// invoking the [`crate::CrashEvent`].
+    ///
+    /// If enabled, they are passed to the [`crate::CrashEvent`] like any other
+    /// exception, eg. for tooling that works with debuggers.
+    #[inline]
+    pub fn handle_debug_exceptions(mut self, handle: bool) -> Self {
+        self.handle_debug_exceptions = handle;
+        self
+    }
+
+    /// Sets the number of bytes of stack, via `SetThreadStackGuarantee`, that
+    /// are kept available to the exception handler when a thread overflows
+    /// its stack, on the thread that attaches the [`CrashHandler`], and on
+    /// every thread that calls [`register_thread`].
+    ///
+    /// Without it, only the few pages Windows reserves by default are left
+    /// when `EXCEPTION_STACK_OVERFLOW` is raised, which is often not enough
+    /// for the [`crate::CrashEvent`] to run, in which case the process is
+    /// terminated without it ever being called.
+    ///
+    /// Defaults to [`DEFAULT_STACK_GUARANTEE`], `0` disables it. Note that
+    /// the guarantee can only be increased, and is taken from the stack the
+    /// thread has available for normal use.
+    #[inline]
+    pub fn stack_guarantee(mut self, bytes: u32) -> Self {
+        self.stack_guarantee = Some(bytes);
+        self
+    }
+}
+
+/// Applies [`AttachOptions::stack_guarantee`] to the calling thread, so that
+/// the [`crate::CrashEvent`] has enough stack to run if the thread overflows
+/// its stack.
+///
+/// This is the Windows counterpart to the `pthread_create` interposition done
+/// on Linux, but as there is no equivalent way to intercept thread creation,
+/// it needs to be called at the start of every thread that might overflow its
+/// stack, the thread that attached the [`CrashHandler`] already has it
+/// applied. If called before a [`CrashHandler`] is attached, the guarantee is
+/// [`DEFAULT_STACK_GUARANTEE`].
+///
+/// # Errors
+///
+/// [`Error::StackGuarantee`] if `SetThreadStackGuarantee` fails, eg. because
+/// the guarantee is larger than the thread's stack
+#[inline]
+pub fn register_thread() -> Result<(), Error> {
+    state::guarantee_stack()
+}
+
 pub struct CrashHandler;
 
 #[allow(clippy::unused_self)]
@@ -36,17 +456,38 @@ impl CrashHandler {
     /// The provided callback will be invoked if an exception is caught,
     /// providing a [`crate::CrashContext`] with the details of the thread where
     /// the exception was thrown.
+    ///
+    /// The thread this is first called from is taken to be the main thread,
+    /// as reported by `CrashContext::is_main_thread`, unless
+    /// [`crate::set_main_thread`] is called, so the handler should be attached
+    /// from the main thread.
     pub fn attach(on_crash: Box<dyn crate::CrashEvent>) -> Result<Self, Error> {
-        state::attach(on_crash)?;
+        Self::attach_with_options(on_crash, AttachOptions::default())
+    }
+
+    /// Attaches the crash handler with the specified options.
+    ///
+    /// See [`Self::attach`] for details.
+    pub fn attach_with_options(
+        on_crash: Box<dyn crate::CrashEvent>,
+        options: AttachOptions,
+    ) -> Result<Self, Error> {
+        state::attach(on_crash, options)?;
         Ok(Self)
     }
 
     /// Detaches this handler, removing it from the handler stack.
     ///
     /// This is done automatically when this [`CrashHandler`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// This currently always succeeds on Windows, the `Result` is for parity
+    /// with the other platforms
     #[inline]
-    pub fn detach(self) {
+    pub fn detach(self) -> Result<(), Error> {
         state::detach();
+        Ok(())
     }
 
     /// Creates an exception with the specified exception code that is passed
@@ -60,8 +501,133 @@ impl CrashHandler {
     }
 }
 
+/// See [`crate::CrashHandler::set_pre_crash_hook`]
+pub(crate) fn replace_pre_crash_hook(hook: crate::PreCrashHook) {
+    if let Some(handler) = &mut *state::HANDLER.lock() {
+        handler.pre_crash_hook = Some(hook);
+    }
+}
+
 impl Drop for CrashHandler {
     fn drop(&mut self) {
         state::detach();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ExceptionCode;
+
+    #[test]
+    fn stack_guarantee_failure() {
+        // No thread has a stack anywhere near this large
+        let result = super::CrashHandler::attach_with_options(
+            unsafe { crate::make_crash_event(|_cc: &crate::CrashContext| false.into()) },
+            super::AttachOptions::default().stack_guarantee(u32::MAX),
+        );
+        assert!(matches!(result, Err(crate::Error::StackGuarantee(_))));
+
+        // Nothing was left attached by the failure
+        super::CrashHandler::attach(unsafe {
+            crate::make_crash_event(|_cc: &crate::CrashContext| false.into())
+        })
+        .unwrap()
+        .detach()
+        .unwrap();
+    }
+
+    #[test]
+    fn round_trips() {
+        for &ec in ExceptionCode::ALL {
+            // Ensures this test is updated if a variant is added
+            match ec {
+                ExceptionCode::Abort
+                | ExceptionCode::DebugControlC
+                | ExceptionCode::DebugPrint
+                | ExceptionCode::DebugPrintWide
+                | ExceptionCode::GuardPage
+                | ExceptionCode::DatatypeMisalignment
+                | ExceptionCode::Trap
+                | ExceptionCode::SingleStep
+                | ExceptionCode::Segv
+                | ExceptionCode::InPageError
+                | ExceptionCode::InvalidHandle
+                | ExceptionCode::InvalidParameter
+                | ExceptionCode::NoMemory
+                | ExceptionCode::Illegal
+                | ExceptionCode::Purecall
+                | ExceptionCode::InvalidDisposition
+                | ExceptionCode::BadStack
+                | ExceptionCode::InvalidUnwindTarget
+                | ExceptionCode::ArrayBoundsExceeded
+                | ExceptionCode::FloatDenormalOperand
+                | ExceptionCode::FloatDivideByZero
+                | ExceptionCode::FloatInexactResult
+                | ExceptionCode::FloatInvalidOperation
+                | ExceptionCode::FloatOverflow
+                | ExceptionCode::FloatStackCheck
+                | ExceptionCode::FloatUnderflow
+                | ExceptionCode::Fpe
+                | ExceptionCode::IntOverflow
+                | ExceptionCode::PrivilegedInstruction
+                | ExceptionCode::StackOverflow
+                | ExceptionCode::PossibleDeadlock
+                | ExceptionCode::FloatMultipleFaults
+                | ExceptionCode::FloatMultipleTraps
+                | ExceptionCode::HeapCorruption
+                | ExceptionCode::StackBufferOverrun
+                | ExceptionCode::InvalidCrtParameter
+                | ExceptionCode::FatalUserCallback
+                | ExceptionCode::AssertionFailure
+                | ExceptionCode::FailFast
+                | ExceptionCode::CppException
+                | ExceptionCode::ClrException
+                | ExceptionCode::User => {}
+            }
+
+            assert_eq!(ExceptionCode::from_code(ec as i32), Some(ec));
+            assert_eq!(ExceptionCode::try_from(ec as i32), Ok(ec));
+            assert_eq!(ExceptionCode::try_from(ec as u32), Ok(ec));
+            assert_eq!(ec.to_string(), ec.name());
+        }
+
+        assert_eq!(ExceptionCode::ALL.len(), 42);
+        assert_eq!(
+            ExceptionCode::try_from(0xc0000005u32),
+            Ok(ExceptionCode::Segv)
+        );
+        assert_eq!(
+            ExceptionCode::Segv.to_string(),
+            "EXCEPTION_ACCESS_VIOLATION"
+        );
+    }
+
+    #[test]
+    fn codes_and_names_are_unique() {
+        for (i, a) in ExceptionCode::ALL.iter().enumerate() {
+            for b in &ExceptionCode::ALL[i + 1..] {
+                assert_ne!(*a as i32, *b as i32, "{a:?} and {b:?} have the same code");
+                assert_ne!(a.name(), b.name());
+            }
+        }
+    }
+
+    #[test]
+    fn fatal_by_default() {
+        assert!(ExceptionCode::Segv.is_fatal_by_default());
+        assert!(ExceptionCode::HeapCorruption.is_fatal_by_default());
+        assert!(ExceptionCode::StackBufferOverrun.is_fatal_by_default());
+        assert!(!ExceptionCode::CppException.is_fatal_by_default());
+        assert!(!ExceptionCode::DebugPrint.is_fatal_by_default());
+        assert!(!ExceptionCode::GuardPage.is_fatal_by_default());
+    }
+
+    #[test]
+    fn rejects_unknown() {
+        assert_eq!(ExceptionCode::from_code(0xdeadbeefu32 as i32), None);
+        assert_eq!(
+            ExceptionCode::try_from(0xdeadbeefu32),
+            Err(crate::UnknownCodeError(0xdeadbeef))
+        );
+    }
+}
@@ -27,10 +27,30 @@ pub(crate) unsafe fn restore_abort_handler(handler: libc::sighandler_t) {
     libc::signal(libc::SIGABRT, handler);
 }
 
+/// Installs a `SIGABRT` handler that immediately terminates the process, used
+/// while the user callback is running if
+/// [`super::AttachOptions::terminate_on_nested_crash`] is enabled
+///
+/// # Safety
+///
+/// Performs syscalls
+#[inline]
+pub(crate) unsafe fn install_terminate_abort_handler() {
+    libc::signal(libc::SIGABRT, terminate_handler as *const () as usize);
+}
+
+unsafe extern "C" fn terminate_handler(_signal: i32, _subcode: i32) {
+    super::state::terminate(super::ExceptionCode::Abort);
+}
+
 unsafe extern "C" fn signal_handler(signal: i32, _subcode: i32) {
     // Sanity check
     assert_eq!(signal, libc::SIGABRT);
 
     // https://github.com/chromium/crashpad/blob/fca8871ca3fb721d3afab370ca790122f9333bfd/client/crashpad_client_win.cc#L197
-    super::state::simulate_exception(Some(super::ExceptionCode::Abort as _));
+    if let crate::CrashEventResult::Handled(true) =
+        super::state::simulate_exception(Some(super::ExceptionCode::Abort as _))
+    {
+        super::state::exit_if_requested();
+    }
 }
@@ -21,6 +21,83 @@ extern "system" {
         handler: PVECTORED_EXCEPTION_HANDLER,
     ) -> *mut core::ffi::c_void;
     fn RemoveVectoredExceptionHandler(handle: *mut core::ffi::c_void) -> u32;
+    fn GetCurrentProcess() -> isize;
+    fn TerminateProcess(process: isize, exit_code: u32) -> i32;
+    fn GetCurrentThread() -> isize;
+    fn GetModuleHandleA(module_name: *const u8) -> isize;
+    fn GetProcAddress(module: isize, proc_name: *const u8) -> Option<unsafe extern "system" fn()>;
+    fn LocalFree(mem: *mut core::ffi::c_void) -> *mut core::ffi::c_void;
+    fn GetProcessTimes(
+        process: isize,
+        creation_time: *mut u64,
+        exit_time: *mut u64,
+        kernel_time: *mut u64,
+        user_time: *mut u64,
+    ) -> i32;
+    fn SetThreadStackGuarantee(stack_size_in_bytes: *mut u32) -> i32;
+}
+
+/// Retrieves the description of the calling thread, if it has one
+unsafe fn current_thread_name() -> crash_context::ThreadName {
+    type GetThreadDescription =
+        unsafe extern "system" fn(thread: isize, description: *mut *mut u16) -> i32;
+
+    // GetThreadDescription was only added in Windows 10 1607, so it needs to
+    // be looked up rather than linked
+    let kernel32 = GetModuleHandleA(b"kernel32.dll\0".as_ptr());
+    if kernel32 == 0 {
+        return crash_context::ThreadName::EMPTY;
+    }
+
+    let get_thread_description: GetThreadDescription =
+        match GetProcAddress(kernel32, b"GetThreadDescription\0".as_ptr()) {
+            Some(func) => std::mem::transmute(func),
+            None => return crash_context::ThreadName::EMPTY,
+        };
+
+    let mut description = std::ptr::null_mut();
+    if get_thread_description(GetCurrentThread(), &mut description) < 0 || description.is_null() {
+        return crash_context::ThreadName::EMPTY;
+    }
+
+    let mut len = 0;
+    while *description.add(len) != 0 {
+        len += 1;
+    }
+
+    let name = crash_context::ThreadName::from_utf16(
+        std::slice::from_raw_parts(description, len).iter().copied(),
+    );
+    LocalFree(description.cast());
+    name
+}
+
+/// Retrieves the time the current process was created, in nanoseconds since
+/// the Unix epoch, or 0 if it can't be retrieved
+fn process_start_ns() -> u64 {
+    /// The number of 100ns intervals between 1601-01-01 and the Unix epoch
+    const EPOCH_DIFFERENCE: u64 = 116_444_736_000_000_000;
+
+    // SAFETY: syscall, the `FILETIME`s are just the halves of a u64
+    unsafe {
+        let mut creation = 0;
+        let mut exit = 0;
+        let mut kernel = 0;
+        let mut user = 0;
+
+        if GetProcessTimes(
+            GetCurrentProcess(),
+            &mut creation,
+            &mut exit,
+            &mut kernel,
+            &mut user,
+        ) == 0
+        {
+            return 0;
+        }
+
+        creation.saturating_sub(EPOCH_DIFFERENCE) * 100
+    }
 }
 
 struct VehHandler(std::ptr::NonNull<libc::c_void>);
@@ -68,6 +145,8 @@ pub(super) static HANDLER: parking_lot::Mutex<Option<HandlerInner>> =
 
 pub(super) struct HandlerInner {
     pub(super) user_handler: Box<dyn crate::CrashEvent>,
+    /// Invoked before the user handler, see [`crate::CrashHandler::set_pre_crash_hook`]
+    pub(super) pre_crash_hook: Option<crate::PreCrashHook>,
     /// The previously installed filter before this handler installed its own
     previous_filter: LPTOP_LEVEL_EXCEPTION_FILTER,
     /// The previously installed invalid parameter handler
@@ -78,10 +157,24 @@ pub(super) struct HandlerInner {
     previous_abort_handler: Option<libc::sighandler_t>,
     /// The handle of our own vectored exception handler
     veh_handle: Option<VehHandler>,
+    /// The code to exit with after a crash is handled
+    exit_code_on_handled: Option<i32>,
+    /// Whether the previous handlers are invoked even if the crash is handled
+    always_chain_previous_filter: bool,
+    /// Whether crashes inside the user callback terminate the process rather
+    /// than being delivered to the previous handlers
+    terminate_on_nested_crash: bool,
+    /// Whether debug exceptions are passed to the user callback
+    handle_debug_exceptions: bool,
+    /// When the process was created, see [`crash_context::CrashTimestamps`]
+    process_start_ns: u64,
 }
 
 impl HandlerInner {
-    pub(crate) fn new(user_handler: Box<dyn crate::CrashEvent>) -> Self {
+    pub(crate) fn new(
+        user_handler: Box<dyn crate::CrashEvent>,
+        options: &super::AttachOptions,
+    ) -> Result<Self, Error> {
         // Note that breakpad has flags so the user can choose which error handlers
         // to install, but for now we just install all of them
 
@@ -90,18 +183,67 @@ impl HandlerInner {
             let previous_filter = SetUnhandledExceptionFilter(Some(handle_exception));
             let previous_iph = _set_invalid_parameter_handler(Some(handle_invalid_parameter));
             let previous_pch = _set_purecall_handler(Some(handle_pure_virtual_call));
-            let previous_abort_handler = super::signal::install_abort_handler().ok();
-            let veh_handle = AddVectoredExceptionHandler(1, Some(vectored_handle_exception));
-            let veh_handle = std::ptr::NonNull::new(veh_handle).map(VehHandler);
 
-            Self {
+            // Constructed before the remaining handlers are installed, so that
+            // if one of them fails, dropping it restores the ones already
+            // installed
+            let mut inner = Self {
                 user_handler,
+                pre_crash_hook: None,
                 previous_filter,
                 previous_iph,
                 previous_pch,
-                previous_abort_handler,
-                veh_handle,
-            }
+                previous_abort_handler: None,
+                veh_handle: None,
+                exit_code_on_handled: options.exit_code_on_handled,
+                always_chain_previous_filter: options.always_chain_previous_filter,
+                terminate_on_nested_crash: options.terminate_on_nested_crash,
+                handle_debug_exceptions: options.handle_debug_exceptions,
+                process_start_ns: process_start_ns(),
+            };
+
+            inner.previous_abort_handler = Some(super::signal::install_abort_handler().map_err(
+                |source| Error::Signal {
+                    signal: libc::SIGABRT,
+                    source,
+                },
+            )?);
+
+            let veh_handle = match options.vectored_handler {
+                super::VectoredHandler::First => {
+                    AddVectoredExceptionHandler(1, Some(vectored_handle_exception))
+                }
+                super::VectoredHandler::Last => {
+                    AddVectoredExceptionHandler(0, Some(vectored_handle_exception))
+                }
+                super::VectoredHandler::Disabled => return Ok(inner),
+            };
+            // The only reason for registration to fail is the allocation of
+            // the handler's entry
+            inner.veh_handle = Some(VehHandler(
+                std::ptr::NonNull::new(veh_handle).ok_or(Error::OutOfMemory)?,
+            ));
+
+            Ok(inner)
+        }
+    }
+
+    /// Passes the exception to the previous filter, if there was one
+    unsafe fn call_previous_filter(
+        &self,
+        except_info: *const crash_context::EXCEPTION_POINTERS,
+    ) -> i32 {
+        if let Some(previous) = self.previous_filter {
+            previous(except_info)
+        } else {
+            EXCEPTION_CONTINUE_SEARCH
+        }
+    }
+
+    /// Terminates the process if an exit code was specified for handled crashes
+    unsafe fn exit_if_requested(&self) {
+        if let Some(code) = self.exit_code_on_handled {
+            TerminateProcess(GetCurrentProcess(), code as u32);
         }
     }
 
@@ -121,6 +263,23 @@ impl HandlerInner {
             }
         }
     }
+
+    /// Sets handlers that immediately terminate the process, rather than the
+    /// previous handlers, for crashes that occur while the user callback runs
+    fn set_terminate_handlers(&mut self) {
+        // SAFETY: syscalls
+        unsafe {
+            if self.previous_abort_handler.is_some() {
+                super::signal::install_terminate_abort_handler();
+            }
+            SetUnhandledExceptionFilter(Some(terminate_on_exception));
+            _set_invalid_parameter_handler(Some(terminate_on_invalid_parameter));
+            _set_purecall_handler(Some(terminate_on_pure_virtual_call));
+            if let Some(handler) = self.veh_handle.take() {
+                RemoveVectoredExceptionHandler(handler.0.as_ptr());
+            }
+        }
+    }
 }
 
 impl Drop for HandlerInner {
@@ -129,14 +288,46 @@ impl Drop for HandlerInner {
     }
 }
 
-pub(super) fn attach(on_crash: Box<dyn crate::CrashEvent>) -> Result<(), Error> {
+pub(super) fn attach(
+    on_crash: Box<dyn crate::CrashEvent>,
+    options: super::AttachOptions,
+) -> Result<(), Error> {
     let mut lock = HANDLER.lock();
 
     if lock.is_some() {
         return Err(Error::HandlerAlreadyInstalled);
     }
 
-    *lock = Some(HandlerInner::new(on_crash));
+    STACK_GUARANTEE.store(
+        options
+            .stack_guarantee
+            .unwrap_or(super::DEFAULT_STACK_GUARANTEE),
+        std::sync::atomic::Ordering::Relaxed,
+    );
+    guarantee_stack()?;
+
+    *lock = Some(HandlerInner::new(on_crash, &options)?);
+    crate::main_thread::attaching();
+    Ok(())
+}
+
+/// The size applied by [`guarantee_stack`], see
+/// [`super::AttachOptions::stack_guarantee`]
+static STACK_GUARANTEE: std::sync::atomic::AtomicU32 =
+    std::sync::atomic::AtomicU32::new(super::DEFAULT_STACK_GUARANTEE);
+
+/// Sets the stack guarantee of the calling thread, see [`super::register_thread`]
+pub(super) fn guarantee_stack() -> Result<(), Error> {
+    let mut size = STACK_GUARANTEE.load(std::sync::atomic::Ordering::Relaxed);
+    if size == 0 {
+        return Ok(());
+    }
+
+    // SAFETY: syscall, a guarantee smaller than the current one is ignored
+    if unsafe { SetThreadStackGuarantee(&mut size) } == 0 {
+        return Err(Error::StackGuarantee(std::io::Error::last_os_error()));
+    }
+
     Ok(())
 }
 
@@ -146,6 +337,13 @@ pub(super) fn detach() {
     lock.take();
 }
 
+/// Called after a `SIGABRT`, which is not an exception, has been handled
+pub(super) unsafe fn exit_if_requested() {
+    if let Some(handler) = &*HANDLER.lock() {
+        handler.exit_if_requested();
+    }
+}
+
 pub(super) unsafe fn simulate_exception(exception_code: Option<i32>) -> crate::CrashEventResult {
     let lock = HANDLER.lock();
     if let Some(handler) = &*lock {
@@ -170,17 +368,21 @@ pub(super) unsafe fn simulate_exception(exception_code: Option<i32>) -> crate::C
                 .cast(),
             process_id: std::process::id(),
             thread_id: GetCurrentThreadId(),
+            main_thread: crate::main_thread::main_thread(GetCurrentThreadId().into()),
             exception_code,
+            timestamps: crash_context::CrashTimestamps::now(handler.process_start_ns),
+            thread_name: current_thread_name(),
         };
 
-        handler.user_handler.on_crash(&cc)
+        crate::call_crash_event(&*handler.user_handler, handler.pre_crash_hook.as_ref(), &cc)
     } else {
         crate::CrashEventResult::Handled(false)
     }
 }
 
 /// While handling any exceptions, especially when calling user code, we restore
-/// and previously registered handlers
+/// and previously registered handlers, or handlers that just terminate the
+/// process if [`super::AttachOptions::terminate_on_nested_crash`] is enabled
 /// Note this keeps the `HANDLER` lock for the duration of the scope
 struct AutoHandler<'scope> {
     lock: parking_lot::MutexGuard<'scope, Option<HandlerInner>>,
@@ -190,8 +392,13 @@ impl<'scope> AutoHandler<'scope> {
     fn new(mut lock: parking_lot::MutexGuard<'scope, Option<HandlerInner>>) -> Option<Self> {
         if let Some(hi) = &mut *lock {
             // In case another exception occurs while this handler is doing its thing,
-            // it should be delivered to the previous filter.
-            hi.restore_previous_handlers();
+            // it should be delivered to the previous filter, unless the user
+            // wants it to terminate the process instead
+            if hi.terminate_on_nested_crash {
+                hi.set_terminate_handlers();
+            } else {
+                hi.restore_previous_handlers();
+            }
         }
 
         if lock.is_some() {
@@ -238,18 +445,65 @@ use crate::CrashEventResult;
 pub(super) unsafe extern "system" fn handle_exception(
     except_info: *const crash_context::EXCEPTION_POINTERS,
 ) -> i32 {
+    // The calls made while handling the exception can clobber the last
+    // error, which the interrupted code may still be relying on if it is
+    // resumed
+    let _errno = crate::errno::SavedErrno::save();
+
     let _jump = {
         let lock = HANDLER.lock();
+        let code = (*(*except_info).ExceptionRecord).ExceptionCode;
+        let known = ExceptionCode::from_code(code);
+
+        debug_print!(
+            "handling exception ",
+            known.map_or("<unknown>", ExceptionCode::name)
+        );
+
+        // Debug exceptions are not crashes, so ignore them unless the user
+        // has asked for them
+        if let Some(handler) = &*lock {
+            if !handler.handle_debug_exceptions
+                && matches!(
+                    known,
+                    Some(
+                        ExceptionCode::DebugPrint
+                            | ExceptionCode::DebugPrintWide
+                            | ExceptionCode::SingleStep
+                    )
+                )
+            {
+                return handler.call_previous_filter(except_info);
+            }
+        }
+
         if let Some(current_handler) = AutoHandler::new(lock) {
-            let code = (*(*except_info).ExceptionRecord).ExceptionCode;
-
-            match current_handler.user_handler.on_crash(&crate::CrashContext {
-                exception_pointers: except_info.cast(),
-                process_id: std::process::id(),
-                thread_id: GetCurrentThreadId(),
-                exception_code: code as _,
-            }) {
+            match crate::call_crash_event(
+                &*current_handler.user_handler,
+                current_handler.pre_crash_hook.as_ref(),
+                &crate::CrashContext {
+                    exception_pointers: except_info.cast(),
+                    process_id: std::process::id(),
+                    thread_id: GetCurrentThreadId(),
+                    main_thread: crate::main_thread::main_thread(GetCurrentThreadId().into()),
+                    exception_code: code as _,
+                    timestamps: crash_context::CrashTimestamps::now(
+                        current_handler.process_start_ns,
+                    ),
+                    thread_name: current_thread_name(),
+                },
+            ) {
                 CrashEventResult::Handled(true) => {
+                    if current_handler.always_chain_previous_filter {
+                        if let Some(previous) = current_handler.previous_filter {
+                            // The exception is handled regardless of what the
+                            // previous filter thinks
+                            previous(except_info);
+                        }
+                    }
+
+                    current_handler.exit_if_requested();
+
                     // The handler fully handled the exception.  Returning
                     // EXCEPTION_EXECUTE_HANDLER indicates this to the system, and usually
                     // results in the application being terminated.
@@ -266,11 +520,7 @@ pub(super) unsafe extern "system" fn handle_exception(
                     // If there is no previous handler, return EXCEPTION_CONTINUE_SEARCH,
                     // which will allow a debugger or native "crashed" dialog to handle the
                     // exception.
-                    return if let Some(previous) = current_handler.previous_filter {
-                        previous(except_info)
-                    } else {
-                        EXCEPTION_CONTINUE_SEARCH
-                    };
+                    return current_handler.call_previous_filter(except_info);
                 }
                 #[cfg(target_arch = "x86_64")]
                 CrashEventResult::Jump { jmp_buf, value } => (jmp_buf, value),
@@ -281,18 +531,52 @@ pub(super) unsafe extern "system" fn handle_exception(
     };
 
     #[cfg(target_arch = "x86_64")]
-    super::jmp::longjmp(_jump.0, _jump.1);
+    {
+        _errno.restore();
+        super::jmp::longjmp(_jump.0, _jump.1);
+    }
 }
 
-const STATUS_HEAP_CORRUPTION: u32 = 0xc0000374;
+/// Installed while the user callback is running if
+/// [`super::AttachOptions::terminate_on_nested_crash`] is enabled
+unsafe extern "system" fn terminate_on_exception(
+    except_info: *const crash_context::EXCEPTION_POINTERS,
+) -> i32 {
+    let code = (*(*except_info).ExceptionRecord).ExceptionCode;
+    TerminateProcess(GetCurrentProcess(), code as u32);
+    EXCEPTION_EXECUTE_HANDLER
+}
+
+/// Installed while the user callback is running if
+/// [`super::AttachOptions::terminate_on_nested_crash`] is enabled
+unsafe extern "C" fn terminate_on_invalid_parameter(
+    _expression: *const u16,
+    _function: *const u16,
+    _file: *const u16,
+    _line: u32,
+    _reserved: usize,
+) {
+    terminate(ExceptionCode::InvalidParameter);
+}
+
+/// Installed while the user callback is running if
+/// [`super::AttachOptions::terminate_on_nested_crash`] is enabled
+unsafe extern "C" fn terminate_on_pure_virtual_call() {
+    terminate(ExceptionCode::Purecall);
+}
+
+/// Terminates the process with the specified exception code
+pub(super) unsafe fn terminate(code: ExceptionCode) {
+    TerminateProcess(GetCurrentProcess(), code as u32);
+}
 
 /// Called on the exception thread when an exception occurs.
 /// Gets to act before other exception handlers.
 pub(super) unsafe extern "system" fn vectored_handle_exception(
     except_info: *const crash_context::EXCEPTION_POINTERS,
 ) -> i32 {
-    let exception_code = (*(*except_info).ExceptionRecord).ExceptionCode as u32;
-    if exception_code == STATUS_HEAP_CORRUPTION {
+    let exception_code = (*(*except_info).ExceptionRecord).ExceptionCode;
+    if exception_code == ExceptionCode::HeapCorruption as i32 {
         handle_exception(except_info)
     } else {
         EXCEPTION_CONTINUE_SEARCH
@@ -316,6 +600,8 @@ unsafe extern "C" fn handle_invalid_parameter(
     line: u32,
     reserved: usize,
 ) {
+    let _errno = crate::errno::SavedErrno::save();
+
     let _jump = {
         let lock = HANDLER.lock();
         if let Some(current_handler) = AutoHandler::new(lock) {
@@ -338,14 +624,33 @@ unsafe extern "C" fn handle_invalid_parameter(
             let exception_code = ExceptionCode::InvalidParameter as i32;
             exception_record.ExceptionCode = exception_code;
 
-            match current_handler.user_handler.on_crash(&crate::CrashContext {
-                exception_pointers: (&exception_ptrs as *const crash_context::EXCEPTION_POINTERS)
-                    .cast(),
-                process_id: std::process::id(),
-                thread_id: GetCurrentThreadId(),
-                exception_code,
-            }) {
-                CrashEventResult::Handled(true) => return,
+            match crate::call_crash_event(
+                &*current_handler.user_handler,
+                current_handler.pre_crash_hook.as_ref(),
+                &crate::CrashContext {
+                    exception_pointers: (&exception_ptrs
+                        as *const crash_context::EXCEPTION_POINTERS)
+                        .cast(),
+                    process_id: std::process::id(),
+                    thread_id: GetCurrentThreadId(),
+                    main_thread: crate::main_thread::main_thread(GetCurrentThreadId().into()),
+                    exception_code,
+                    timestamps: crash_context::CrashTimestamps::now(
+                        current_handler.process_start_ns,
+                    ),
+                    thread_name: current_thread_name(),
+                },
+            ) {
+                CrashEventResult::Handled(true) => {
+                    if current_handler.always_chain_previous_filter {
+                        if let Some(prev_iph) = current_handler.previous_iph {
+                            prev_iph(expression, function, file, line, reserved);
+                        }
+                    }
+
+                    current_handler.exit_if_requested();
+                    return;
+                }
                 CrashEventResult::Handled(false) => {
                     if let Some(prev_iph) = current_handler.previous_iph {
                         prev_iph(expression, function, file, line, reserved);
@@ -383,13 +688,18 @@ unsafe extern "C" fn handle_invalid_parameter(
     };
 
     #[cfg(target_arch = "x86_64")]
-    super::jmp::longjmp(_jump.0, _jump.1);
+    {
+        _errno.restore();
+        super::jmp::longjmp(_jump.0, _jump.1);
+    }
 }
 
 /// Handler for pure virtual function calls, this is not an exception so the
 /// context (shouldn't be) isn't compromised
 #[no_mangle]
 unsafe extern "C" fn handle_pure_virtual_call() {
+    let _errno = crate::errno::SavedErrno::save();
+
     let _jump = {
         let lock = HANDLER.lock();
         if let Some(current_handler) = AutoHandler::new(lock) {
@@ -412,14 +722,32 @@ unsafe extern "C" fn handle_pure_virtual_call() {
             let exception_code = ExceptionCode::Purecall as i32;
             exception_record.ExceptionCode = exception_code;
 
-            match current_handler.user_handler.on_crash(&crate::CrashContext {
-                exception_pointers: (&exception_ptrs as *const crash_context::EXCEPTION_POINTERS)
-                    .cast(),
-                process_id: std::process::id(),
-                thread_id: GetCurrentThreadId(),
-                exception_code,
-            }) {
+            match crate::call_crash_event(
+                &*current_handler.user_handler,
+                current_handler.pre_crash_hook.as_ref(),
+                &crate::CrashContext {
+                    exception_pointers: (&exception_ptrs
+                        as *const crash_context::EXCEPTION_POINTERS)
+                        .cast(),
+                    process_id: std::process::id(),
+                    thread_id: GetCurrentThreadId(),
+                    main_thread: crate::main_thread::main_thread(GetCurrentThreadId().into()),
+                    exception_code,
+                    timestamps: crash_context::CrashTimestamps::now(
+                        current_handler.process_start_ns,
+                    ),
+                    thread_name: current_thread_name(),
+                },
+            ) {
                 CrashEventResult::Handled(true) => {
+                    if current_handler.always_chain_previous_filter {
+                        if let Some(pch) = current_handler.previous_pch {
+                            pch();
+                        }
+                    }
+
+                    current_handler.exit_if_requested();
+
                     // The handler either took care of the invalid parameter problem itself,
                     // or passed it on to another handler. "Swallow" it by exiting, paralleling
                     // the behavior of "swallowing" exceptions.
@@ -445,5 +773,8 @@ unsafe extern "C" fn handle_pure_virtual_call() {
     };
 
     #[cfg(target_arch = "x86_64")]
-    super::jmp::longjmp(_jump.0, _jump.1);
+    {
+        _errno.restore();
+        super::jmp::longjmp(_jump.0, _jump.1);
+    }
 }
@@ -0,0 +1,71 @@
+//! Verifies that failing to install the alternate signal stack is reported as
+//! [`crash_handler::Error::AltStack`] rather than panicking, by attaching from
+//! a signal handler that is already running on an alternate stack, which the
+//! kernel refuses to replace with `EPERM`
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// The OS error of the attach attempt, or one of the values below
+static RESULT: AtomicI32 = AtomicI32::new(NOT_RUN);
+const NOT_RUN: i32 = -1;
+const ATTACHED: i32 = -2;
+const OTHER_ERROR: i32 = -3;
+
+extern "C" fn on_usr1(_sig: libc::c_int) {
+    let result = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|_cc: &ch::CrashContext| false.into())
+    });
+
+    let code = match result {
+        Ok(_handler) => ATTACHED,
+        Err(ch::Error::AltStack(err)) => err.raw_os_error().unwrap_or(OTHER_ERROR),
+        Err(_) => OTHER_ERROR,
+    };
+    RESULT.store(code, Ordering::Relaxed);
+}
+
+#[test]
+fn reports_alt_stack_failure() {
+    unsafe {
+        // Smaller than the stack the handler requires, so that it doesn't
+        // just reuse ours
+        let mut stack = vec![0u8; 12 * 1024];
+        let alt_stack = libc::stack_t {
+            ss_sp: stack.as_mut_ptr().cast(),
+            ss_flags: 0,
+            ss_size: stack.len(),
+        };
+        assert_eq!(libc::sigaltstack(&alt_stack, std::ptr::null_mut()), 0);
+
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_usr1 as *const () as usize;
+        action.sa_flags = libc::SA_ONSTACK;
+        assert_eq!(
+            libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()),
+            0
+        );
+
+        assert_eq!(libc::raise(libc::SIGUSR1), 0);
+
+        let disable = libc::stack_t {
+            ss_sp: std::ptr::null_mut(),
+            ss_flags: libc::SS_DISABLE,
+            ss_size: 0,
+        };
+        assert_eq!(libc::sigaltstack(&disable, std::ptr::null_mut()), 0);
+    }
+
+    assert_eq!(RESULT.load(Ordering::Relaxed), libc::EPERM);
+
+    // Nothing was left installed by the failed attach
+    ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|_cc: &ch::CrashContext| false.into())
+    })
+    .unwrap()
+    .detach()
+    .unwrap();
+}
@@ -0,0 +1,6 @@
+mod shared;
+
+#[test]
+fn handles_at_exit() {
+    shared::handles_crash(shared::SadnessFlavor::AtExitCrash);
+}
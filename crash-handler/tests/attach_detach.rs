@@ -0,0 +1,102 @@
+//! The sigaltstack for the thread that attaches the handler is only mapped by
+//! the signal handler implementation
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+
+/// Only one handler can be attached at a time
+static SERIAL: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// The address range and permissions of each of the current process' mappings
+fn mappings() -> Vec<(std::ops::Range<usize>, String)> {
+    std::fs::read_to_string("/proc/self/maps")
+        .expect("failed to read mappings")
+        .lines()
+        .map(|line| {
+            let mut fields = line.split(' ');
+            let range = fields.next().unwrap();
+            let perms = fields.next().unwrap();
+            let (start, end) = range.split_once('-').unwrap();
+            let start = usize::from_str_radix(start, 16).unwrap();
+            let end = usize::from_str_radix(end, 16).unwrap();
+
+            (start..end, perms.to_owned())
+        })
+        .collect()
+}
+
+/// The permissions of the mapping containing the address, if there is one
+fn mapping_perms(addr: usize) -> Option<String> {
+    mappings()
+        .into_iter()
+        .find_map(|(range, perms)| range.contains(&addr).then_some(perms))
+}
+
+/// Checks if the address is in any of the current process' mappings
+fn is_mapped(addr: usize) -> bool {
+    mapping_perms(addr).is_some()
+}
+
+/// Ensures the thread doesn't already have a sigaltstack installed, otherwise
+/// the handler will use it rather than mapping its own
+fn disable_sigaltstack() {
+    unsafe {
+        let mut disable: libc::stack_t = std::mem::zeroed();
+        disable.ss_flags = libc::SS_DISABLE;
+        assert_eq!(libc::sigaltstack(&disable, std::ptr::null_mut()), 0);
+    }
+}
+
+fn attach() -> ch::CrashHandler {
+    ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|_cc: &ch::CrashContext| ch::CrashEventResult::Handled(false))
+    })
+    .unwrap()
+}
+
+/// Ensures that detaching unmaps all of the memory mapped for the alternate
+/// signal stack when attaching, including its guard page
+#[test]
+fn unmaps_sigaltstack() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    disable_sigaltstack();
+
+    let handler = attach();
+
+    let stack = unsafe {
+        let mut ss: libc::stack_t = std::mem::zeroed();
+        assert_eq!(libc::sigaltstack(std::ptr::null(), &mut ss), 0);
+        ss.ss_sp as usize
+    };
+
+    let guard = stack - unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+
+    assert_eq!(mapping_perms(stack).as_deref(), Some("rw-p"));
+    assert_eq!(mapping_perms(guard).as_deref(), Some("---p"));
+
+    handler.detach().unwrap();
+
+    assert!(!is_mapped(stack), "the stack was not unmapped");
+    assert!(!is_mapped(guard), "the guard page was not unmapped");
+}
+
+/// Ensures that repeatedly attaching and detaching doesn't leave any mappings
+/// behind
+#[test]
+fn no_stray_mappings() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    disable_sigaltstack();
+
+    // Anything mapped lazily on first use, eg. by the allocator, is mapped
+    // before the mappings are compared
+    attach().detach().unwrap();
+    let before = mappings();
+
+    for _ in 0..10 {
+        attach().detach().unwrap();
+    }
+
+    assert_eq!(before, mappings());
+}
@@ -4,5 +4,7 @@ mod shared;
 
 #[test]
 fn handles_bus() {
-    shared::handles_crash(shared::SadnessFlavor::Bus);
+    shared::handles_crash(shared::SadnessFlavor::Bus {
+        native_thread: false,
+    });
 }
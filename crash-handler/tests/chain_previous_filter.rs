@@ -0,0 +1,50 @@
+//! Verifies that the filter installed before the crash handler is still invoked
+//! after the user callback handles the crash, when requested
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+type Filter = Option<unsafe extern "system" fn(*const std::ffi::c_void) -> i32>;
+
+extern "system" {
+    fn SetUnhandledExceptionFilter(filter: Filter) -> Filter;
+}
+
+static HANDLED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "system" fn previous_filter(_exception_info: *const std::ffi::c_void) -> i32 {
+    // We should only be called after the crash has already been handled
+    #[allow(clippy::exit)]
+    std::process::exit(if HANDLED.load(Ordering::Relaxed) {
+        0
+    } else {
+        1
+    });
+}
+
+#[test]
+fn chains_previous_filter() {
+    unsafe {
+        SetUnhandledExceptionFilter(Some(previous_filter));
+    }
+
+    let _handler = ch::CrashHandler::attach_with_options(
+        unsafe {
+            ch::make_crash_event(|_cc: &ch::CrashContext| {
+                HANDLED.store(true, Ordering::Relaxed);
+                true.into()
+            })
+        },
+        ch::AttachOptions::default().always_chain_previous_filter(true),
+    )
+    .unwrap();
+
+    unsafe {
+        sadness_generator::raise_segfault();
+    }
+
+    panic!("this should be impossible");
+}
@@ -0,0 +1,133 @@
+//! Verifies that the handler installed before the crash handler is invoked
+//! directly with the original signal information, and the signal mask it was
+//! installed with, when the crash isn't handled.
+//! The test runs itself as a child process that does the actual crashing, since
+//! the test itself needs to check how the child exited
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+mod shared;
+
+use crash_handler as ch;
+
+const EXIT_CODE: i32 = 29;
+
+/// The pid and value the signal is queued with, which only the original
+/// siginfo has, as a signal that is raised again is sent with `tgkill`
+const SENTINEL_PID: libc::pid_t = 0x7e57;
+const SENTINEL_VALUE: usize = 0x5e47_1ae1;
+/// The fault address the signal is queued with, as if it were a fault, which
+/// isn't raised again at all, so is only seen if the siginfo is passed along
+const SENTINEL_ADDRESS: usize = 0xdead_0000;
+/// `sigqueue`, which is not exposed by libc for every target
+const SI_QUEUE: i32 = -1;
+/// An invalid address, see `SENTINEL_ADDRESS`
+const SEGV_MAPERR: i32 = 1;
+
+/// The start of `siginfo_t`, as libc only exposes accessors for its fields
+#[repr(C)]
+struct SigInfo {
+    signo: i32,
+    errno: i32,
+    code: i32,
+    fields: SigInfoFields,
+}
+
+#[repr(C)]
+union SigInfoFields {
+    queue: Queue,
+    addr: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Queue {
+    pid: libc::pid_t,
+    uid: libc::uid_t,
+    value: usize,
+}
+
+unsafe extern "C" fn previous_handler(sig: i32, info: *mut libc::siginfo_t, uc: *mut libc::c_void) {
+    if sig != libc::SIGSEGV || (*info).si_signo != libc::SIGSEGV || uc.is_null() {
+        libc::_exit(1);
+    }
+
+    let expected = match (*info).si_code {
+        SI_QUEUE => {
+            (*info).si_pid() == SENTINEL_PID
+                && (*info).si_value().sival_ptr as usize == SENTINEL_VALUE
+        }
+        SEGV_MAPERR => (*info).si_addr() as usize == SENTINEL_ADDRESS,
+        _ => false,
+    };
+
+    if !expected {
+        libc::_exit(2);
+    }
+
+    let mut mask = std::mem::zeroed();
+    libc::pthread_sigmask(libc::SIG_BLOCK, std::ptr::null(), &mut mask);
+    if libc::sigismember(&mask, libc::SIGUSR1) != 1 || libc::sigismember(&mask, libc::SIGSEGV) != 1
+    {
+        libc::_exit(3);
+    }
+
+    libc::_exit(EXIT_CODE);
+}
+
+#[test]
+fn chains_previous_handler() {
+    if let Some(code) = shared::child_value() {
+        unsafe {
+            let mut sa: libc::sigaction = std::mem::zeroed();
+            libc::sigemptyset(&mut sa.sa_mask);
+            libc::sigaddset(&mut sa.sa_mask, libc::SIGUSR1);
+            sa.sa_sigaction = previous_handler as *const () as usize;
+            sa.sa_flags = libc::SA_SIGINFO;
+            assert_eq!(libc::sigaction(libc::SIGSEGV, &sa, std::ptr::null_mut()), 0);
+        }
+
+        let _handler = ch::CrashHandler::attach_with_options(
+            unsafe { ch::make_crash_event(|_cc: &ch::CrashContext| false.into()) },
+            ch::AttachOptions::default().chain_previous_handler(true),
+        )
+        .unwrap();
+
+        // Queue the signal to ourselves, which is the only way to send it
+        // with an arbitrary siginfo
+        unsafe {
+            let mut info: libc::siginfo_t = std::mem::zeroed();
+            let raw = (&mut info as *mut libc::siginfo_t).cast::<SigInfo>();
+            (*raw).signo = libc::SIGSEGV;
+            (*raw).code = code.parse().unwrap();
+
+            if (*raw).code == SI_QUEUE {
+                (*raw).fields.queue = Queue {
+                    pid: SENTINEL_PID,
+                    uid: libc::getuid(),
+                    value: SENTINEL_VALUE,
+                };
+            } else {
+                (*raw).fields.addr = SENTINEL_ADDRESS;
+            }
+
+            libc::syscall(
+                libc::SYS_rt_tgsigqueueinfo,
+                libc::getpid(),
+                libc::syscall(libc::SYS_gettid),
+                libc::SIGSEGV,
+                &info,
+            );
+        }
+
+        // The signal wasn't delivered to the previous handler
+        #[allow(clippy::exit)]
+        std::process::exit(4);
+    }
+
+    for code in [SI_QUEUE, SEGV_MAPERR] {
+        let (status, stderr) = shared::run_in_child("chains_previous_handler", &code.to_string());
+        assert_eq!(status.code(), Some(EXIT_CODE), "si_code {code}: {stderr}");
+    }
+}
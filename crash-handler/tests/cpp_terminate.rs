@@ -0,0 +1,6 @@
+mod shared;
+
+#[test]
+fn handles_cpp_terminate() {
+    shared::handles_crash(shared::SadnessFlavor::CppTerminate);
+}
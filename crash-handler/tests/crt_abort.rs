@@ -0,0 +1,8 @@
+#![cfg(windows)]
+
+mod shared;
+
+#[test]
+fn handles_crt_abort() {
+    shared::handles_crash(shared::SadnessFlavor::CrtAbort);
+}
@@ -0,0 +1,47 @@
+//! Verifies that the exceptions raised by `OutputDebugString` are passed to the
+//! filter installed before the crash handler without invoking the user callback
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+type Filter = Option<unsafe extern "system" fn(*const std::ffi::c_void) -> i32>;
+
+extern "system" {
+    fn SetUnhandledExceptionFilter(filter: Filter) -> Filter;
+    fn RaiseException(code: u32, flags: u32, num_args: u32, args: *const usize);
+}
+
+/// `DBG_PRINTEXCEPTION_C`
+const DBG_PRINTEXCEPTION_C: u32 = 0x40010006;
+
+static CALLED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "system" fn previous_filter(_exception_info: *const std::ffi::c_void) -> i32 {
+    // The callback should never have seen the exception
+    #[allow(clippy::exit)]
+    std::process::exit(if CALLED.load(Ordering::Relaxed) { 1 } else { 0 });
+}
+
+#[test]
+fn ignores_debug_exceptions() {
+    unsafe {
+        SetUnhandledExceptionFilter(Some(previous_filter));
+    }
+
+    let _handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|_cc: &ch::CrashContext| {
+            CALLED.store(true, Ordering::Relaxed);
+            true.into()
+        })
+    })
+    .unwrap();
+
+    unsafe {
+        RaiseException(DBG_PRINTEXCEPTION_C, 0, 0, std::ptr::null());
+    }
+
+    panic!("this should be impossible");
+}
@@ -0,0 +1,8 @@
+#![cfg(windows)]
+
+mod shared;
+
+#[test]
+fn handles_debug_service() {
+    shared::handles_crash(shared::SadnessFlavor::DebugService { code: 0x5ad });
+}
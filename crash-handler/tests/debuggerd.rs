@@ -0,0 +1,42 @@
+//! Verifies that a crash that isn't handled is chained to debuggerd's handler,
+//! so that the process still dies of the signal, and a tombstone is written for
+//! it, which is checked by the Android emulator job in CI.
+//! Each test runs itself as a child process that does the actual crashing, since
+//! the test itself needs to check how the child exited
+
+#![cfg(target_os = "android")]
+#![allow(unsafe_code)]
+
+mod shared;
+
+use crash_handler as ch;
+use std::os::unix::process::ExitStatusExt;
+
+fn chains_to_debuggerd(name: &str, sig: i32, raise: unsafe fn() -> !) {
+    if shared::child_value().is_some() {
+        let _handler = ch::CrashHandler::attach_with_options(
+            unsafe { ch::make_crash_event(|_cc: &ch::CrashContext| false.into()) },
+            ch::AttachOptions::default().chain_previous_handler(true),
+        )
+        .unwrap();
+
+        unsafe {
+            raise();
+        }
+    }
+
+    let (status, stderr) = shared::run_in_child(name, "1");
+
+    // debuggerd raises the signal again once the tombstone is written
+    assert_eq!(status.signal(), Some(sig), "{stderr}");
+}
+
+#[test]
+fn abort() {
+    chains_to_debuggerd("abort", libc::SIGABRT, sadness_generator::raise_abort);
+}
+
+#[test]
+fn segv() {
+    chains_to_debuggerd("segv", libc::SIGSEGV, sadness_generator::raise_segfault);
+}
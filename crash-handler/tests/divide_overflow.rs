@@ -0,0 +1,10 @@
+//! This is ignored on mac when targeting arm for the same reason as `fpe.rs`,
+//! the signal is explicitly raised as the hardware doesn't trap
+#![cfg(not(all(target_os = "macos", any(target_arch = "arm", target_arch = "aarch64"))))]
+
+mod shared;
+
+#[test]
+fn handles_divide_overflow() {
+    shared::handles_crash(shared::SadnessFlavor::DivideOverflow);
+}
@@ -0,0 +1,80 @@
+//! Dump requests are delivered via a user specified signal, and unlike crashes
+//! must not terminate the process
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+static CRASHES: AtomicUsize = AtomicUsize::new(0);
+static HANDLED: AtomicBool = AtomicBool::new(true);
+
+#[test]
+fn handles_dump_request() {
+    let request_signal = libc::SIGRTMIN() + 3;
+
+    assert!(matches!(
+        ch::CrashHandler::attach_with_options(
+            unsafe { ch::make_crash_event(|_cc: &ch::CrashContext| false.into()) },
+            ch::AttachOptions::default().dump_request_signal(libc::SIGSEGV),
+        ),
+        Err(ch::Error::InvalidSignal(libc::SIGSEGV))
+    ));
+
+    let handler = ch::CrashHandler::attach_with_options(
+        unsafe {
+            ch::make_crash_event(move |cc: &ch::CrashContext| {
+                if !ch::is_dump_request(cc) {
+                    CRASHES.fetch_add(1, Ordering::Relaxed);
+                    return false.into();
+                }
+
+                assert_eq!(cc.siginfo.ssi_signo, request_signal as u32);
+                assert_eq!(cc.siginfo.ssi_pid, std::process::id());
+                assert_eq!(cc.pid, std::process::id() as i32);
+                assert_eq!(cc.tid, libc::syscall(libc::SYS_gettid) as i32);
+
+                REQUESTS.fetch_add(1, Ordering::Relaxed);
+                HANDLED.load(Ordering::Relaxed).into()
+            })
+        },
+        ch::AttachOptions::default().dump_request_signal(request_signal),
+    )
+    .unwrap();
+
+    // The signal is delivered to the calling thread before raise returns
+    unsafe {
+        assert_eq!(libc::raise(request_signal), 0);
+        assert_eq!(REQUESTS.load(Ordering::Relaxed), 1);
+
+        // The handler remains installed after a handled request
+        assert_eq!(libc::raise(request_signal), 0);
+        assert_eq!(REQUESTS.load(Ordering::Relaxed), 2);
+
+        // There was no previous handler, and the default action for a
+        // real-time signal is to terminate, which must not happen even if the
+        // request wasn't handled
+        HANDLED.store(false, Ordering::Relaxed);
+        assert_eq!(libc::raise(request_signal), 0);
+        assert_eq!(REQUESTS.load(Ordering::Relaxed), 3);
+    }
+
+    // Crashes are not dump requests
+    handler.simulate_signal(libc::SIGSEGV as u32);
+    assert_eq!(REQUESTS.load(Ordering::Relaxed), 3);
+    assert_eq!(CRASHES.load(Ordering::Relaxed), 1);
+
+    handler.detach().unwrap();
+
+    // The default disposition is restored on detach
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        assert_eq!(
+            libc::sigaction(request_signal, std::ptr::null(), &mut sa),
+            0
+        );
+        assert_eq!(sa.sa_sigaction, libc::SIG_DFL);
+    }
+}
@@ -0,0 +1,52 @@
+//! Verifies that the process is kept dumpable until the outermost guard, eg.
+//! the one held by the handler during the crash callback, is dropped
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+fn is_dumpable() -> bool {
+    // PR_GET_DUMPABLE
+    unsafe { libc::prctl(3, 0, 0, 0, 0) > 0 }
+}
+
+fn set_dumpable(dumpable: bool) {
+    // PR_SET_DUMPABLE
+    unsafe { libc::prctl(4, libc::c_ulong::from(dumpable), 0, 0, 0) };
+}
+
+static DUMPABLE_IN_CALLBACK: AtomicBool = AtomicBool::new(false);
+
+#[test]
+fn dumpable_guard() {
+    set_dumpable(false);
+
+    {
+        let _outer = ch::DumpableGuard::new(None);
+        assert!(is_dumpable());
+
+        // Dropping an inner guard doesn't end the window
+        drop(ch::DumpableGuard::new(Some(std::process::id())));
+        assert!(is_dumpable());
+    }
+    assert!(!is_dumpable());
+
+    let handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|_cc: &ch::CrashContext| {
+            // The minidump would be requested while holding this, which the
+            // handler's own guard outlasts
+            let _guard = ch::DumpableGuard::new(None);
+            DUMPABLE_IN_CALLBACK.store(is_dumpable(), Ordering::Relaxed);
+            ch::CrashEventResult::Handled(true)
+        })
+    })
+    .unwrap();
+
+    handler.simulate_signal(libc::SIGUSR2 as u32);
+    assert!(DUMPABLE_IN_CALLBACK.load(Ordering::Relaxed));
+    assert!(!is_dumpable());
+
+    set_dumpable(true);
+}
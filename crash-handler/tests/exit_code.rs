@@ -0,0 +1,29 @@
+//! Verifies that the process exits with the requested code after a crash is
+//! handled. The test runs itself as a child process that does the actual
+//! crashing, since the test itself needs to check the exit code
+
+#![allow(unsafe_code)]
+
+mod shared;
+
+use crash_handler as ch;
+
+const EXIT_CODE: i32 = 23;
+
+#[test]
+fn exits_with_code() {
+    if shared::child_value().is_some() {
+        let _handler = ch::CrashHandler::attach_with_options(
+            unsafe { ch::make_crash_event(|_cc: &ch::CrashContext| true.into()) },
+            ch::AttachOptions::default().exit_code_on_handled(EXIT_CODE),
+        )
+        .unwrap();
+
+        unsafe {
+            sadness_generator::raise_segfault();
+        }
+    }
+
+    let (status, stderr) = shared::run_in_child("exits_with_code", "1");
+    assert_eq!(status.code(), Some(EXIT_CODE), "{stderr}");
+}
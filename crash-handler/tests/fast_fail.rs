@@ -0,0 +1,41 @@
+//! Verifies that `__fastfail`, used by [`std::process::abort`] on Windows,
+//! bypasses the crash handler entirely. The test runs itself as a child process
+//! that does the actual aborting, since the test needs to check how the child
+//! was terminated
+
+#![cfg(windows)]
+#![allow(unsafe_code)]
+
+mod shared;
+
+use crash_handler as ch;
+
+/// The code the child exits with if the crash event is invoked
+const HANDLED_EXIT_CODE: i32 = 23;
+/// `STATUS_STACK_BUFFER_OVERRUN`, which every `__fastfail` terminates the
+/// process with, regardless of its failure code
+const STATUS_STACK_BUFFER_OVERRUN: u32 = 0xc0000409;
+
+#[test]
+fn bypasses_fast_fail() {
+    if shared::child_value().is_some() {
+        let _handler = ch::CrashHandler::attach(unsafe {
+            ch::make_crash_event(|_cc: &ch::CrashContext| {
+                #[allow(clippy::exit)]
+                std::process::exit(HANDLED_EXIT_CODE);
+            })
+        })
+        .unwrap();
+
+        unsafe {
+            sadness_generator::raise_fast_fail();
+        }
+    }
+
+    let (status, stderr) = shared::run_in_child("bypasses_fast_fail", "1");
+    assert_eq!(
+        status.code(),
+        Some(STATUS_STACK_BUFFER_OVERRUN as i32),
+        "{stderr}"
+    );
+}
@@ -7,5 +7,7 @@ mod shared;
 
 #[test]
 fn handles_fpe() {
-    shared::handles_crash(shared::SadnessFlavor::DivideByZero);
+    shared::handles_crash(shared::SadnessFlavor::DivideByZero {
+        native_thread: false,
+    });
 }
@@ -2,5 +2,7 @@ mod shared;
 
 #[test]
 fn handles_illegal_instruction() {
-    shared::handles_crash(shared::SadnessFlavor::Illegal);
+    shared::handles_crash(shared::SadnessFlavor::Illegal {
+        native_thread: false,
+    });
 }
@@ -0,0 +1,58 @@
+//! Verifies that errno is the same after recovering from a crash as it was
+//! when the crash occurred, even if it was clobbered while handling the crash
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::{cell::UnsafeCell, mem::MaybeUninit};
+
+/// The errno the crashing code had set before it crashed
+const SENTINEL: i32 = libc::EAGAIN;
+
+struct JmpBuf(UnsafeCell<MaybeUninit<ch::jmp::JmpBuf>>);
+
+// SAFETY: only accessed by the test thread, and the signal handler running on it
+unsafe impl Sync for JmpBuf {}
+
+static JMP_BUF: JmpBuf = JmpBuf(UnsafeCell::new(MaybeUninit::uninit()));
+
+unsafe fn errno_location() -> *mut i32 {
+    #[cfg(target_os = "android")]
+    {
+        libc::__errno()
+    }
+    #[cfg(not(target_os = "android"))]
+    {
+        libc::__errno_location()
+    }
+}
+
+#[test]
+fn preserves_errno() {
+    let _handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|_cc: &ch::CrashContext| {
+            // Fails with EBADF, as eg. a handler writing a dump to a file that
+            // couldn't be opened would
+            libc::close(-1);
+
+            ch::CrashEventResult::Jump {
+                jmp_buf: JMP_BUF.0.get().cast(),
+                value: 1,
+            }
+        })
+    })
+    .unwrap();
+
+    let value = unsafe { ch::jmp::sigsetjmp(JMP_BUF.0.get().cast(), 1) };
+
+    if value == 0 {
+        unsafe {
+            *errno_location() = SENTINEL;
+            sadness_generator::raise_segfault();
+        }
+    }
+
+    assert_eq!(value, 1);
+    assert_eq!(unsafe { *errno_location() }, SENTINEL);
+}
@@ -0,0 +1,54 @@
+//! Exceptions on Macos are handled on a separate thread, so the handler can't
+//! jump back to the thread that raised the exception itself. Instead, it sends
+//! the value to jump with to that thread, which then performs the jump
+
+#![cfg(target_os = "macos")]
+#![allow(unsafe_code)]
+
+use crash_handler::{self as ch, jmp};
+
+/// The code of the simulated exception, which is also the value jumped with
+const CODE: u64 = 0x5ad;
+
+#[test]
+fn recovers_from_simulated_exception() {
+    let (tx, rx) = std::sync::mpsc::sync_channel(1);
+
+    let handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(move |cc: &ch::CrashContext| {
+            let value = cc.exception.map_or(0, |exc| exc.code as i32);
+            tx.send(value).is_ok().into()
+        })
+    })
+    .unwrap();
+
+    let handler = &handler;
+    let value = std::thread::scope(|s| {
+        s.spawn(move || unsafe {
+            let mut jmp_buf = std::mem::MaybeUninit::uninit();
+
+            let value = jmp::sigsetjmp(jmp_buf.as_mut_ptr(), 1);
+
+            if value == 0 {
+                assert!(
+                    handler.simulate_exception(Some(crash_context::ExceptionInfo {
+                        kind: ch::ExceptionType::Software as u32,
+                        code: CODE,
+                        subcode: None,
+                    }))
+                );
+
+                // The handler has finished with the exception by the time
+                // `simulate_exception` returns, so the value is already sent
+                let value = rx.try_recv().expect("the handler didn't send a value");
+                jmp::siglongjmp(jmp_buf.as_mut_ptr(), value);
+            }
+
+            value
+        })
+        .join()
+        .unwrap()
+    });
+
+    assert_eq!(value, CODE as i32);
+}
@@ -0,0 +1,78 @@
+//! Jumping is only supported by the signal handler, as mac exceptions are
+//! handled on a separate thread
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+#[path = "shared/jump.rs"]
+mod jump;
+
+use jump::SadnessFlavor;
+
+/// Each thread establishes its own jump point and repeatedly crashes and
+/// recovers, then the handler is checked to still be installed and working
+#[test]
+fn recovers_on_threads() {
+    const THREADS: u64 = 8;
+    const ITERATIONS: u64 = 10;
+
+    let handler = jump::attach();
+
+    let segv_action = jump::current_action(crash_handler::Signal::Segv);
+    let fpe_action = jump::current_action(crash_handler::Signal::Fpe);
+
+    let threads: Vec<_> = (0..THREADS)
+        .map(|thread| {
+            std::thread::spawn(move || {
+                for i in 0..ITERATIONS {
+                    let flavor = if (thread + i) % 2 == 0 {
+                        SadnessFlavor::Segfault {
+                            native_thread: false,
+                        }
+                    } else {
+                        SadnessFlavor::DivideByZero {
+                            native_thread: false,
+                        }
+                    };
+
+                    jump::crash_and_recover(flavor, (thread << 32) | i, || {});
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().expect("thread failed to recover");
+    }
+
+    assert_eq!(jump::crashes(), (THREADS * ITERATIONS) as usize);
+    jump::assert_no_mismatches();
+
+    // Every crash was recovered from
+    assert_eq!(handler.crash_count(), (THREADS * ITERATIONS) as usize);
+    assert_eq!(handler.recovered_count(), (THREADS * ITERATIONS) as usize);
+    handler.reset_counts();
+    assert_eq!(handler.crash_count(), 0);
+    assert_eq!(handler.recovered_count(), 0);
+
+    // The handler must still be installed, and still function, for threads that
+    // haven't crashed before
+    assert_eq!(
+        jump::current_action(crash_handler::Signal::Segv),
+        segv_action
+    );
+    assert_eq!(jump::current_action(crash_handler::Signal::Fpe), fpe_action);
+
+    jump::crash_and_recover(
+        SadnessFlavor::Segfault {
+            native_thread: false,
+        },
+        u64::MAX,
+        || {},
+    );
+
+    assert_eq!(jump::crashes(), (THREADS * ITERATIONS) as usize + 1);
+    jump::assert_no_mismatches();
+
+    assert_eq!(handler.crash_count(), 1);
+    assert_eq!(handler.recovered_count(), 1);
+}
@@ -0,0 +1,60 @@
+//! Jumping is only supported by the signal handler, as mac exceptions are
+//! handled on a separate thread
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+#[path = "shared/jump.rs"]
+mod jump;
+
+use jump::SadnessFlavor;
+use std::sync::{Arc, Barrier};
+
+/// Two threads crash at (nearly) the same time, so that one crash is delivered
+/// while the handler is still processing the other, ensuring neither crash
+/// causes the handler to be uninstalled before the other is handled
+#[test]
+fn recovers_simultaneous_crashes() {
+    const ITERATIONS: u64 = 100;
+
+    let _handler = jump::attach();
+
+    let segv_action = jump::current_action(crash_handler::Signal::Segv);
+    let fpe_action = jump::current_action(crash_handler::Signal::Fpe);
+
+    let barrier = Arc::new(Barrier::new(2));
+
+    let threads: Vec<_> = [
+        SadnessFlavor::Segfault {
+            native_thread: false,
+        },
+        SadnessFlavor::DivideByZero {
+            native_thread: false,
+        },
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(thread, flavor)| {
+        let barrier = barrier.clone();
+        std::thread::spawn(move || {
+            for i in 0..ITERATIONS {
+                jump::crash_and_recover(flavor, ((thread as u64) << 32) | i, || {
+                    barrier.wait();
+                });
+            }
+        })
+    })
+    .collect();
+
+    for thread in threads {
+        thread.join().expect("thread failed to recover");
+    }
+
+    assert_eq!(jump::crashes(), 2 * ITERATIONS as usize);
+    jump::assert_no_mismatches();
+
+    assert_eq!(
+        jump::current_action(crash_handler::Signal::Segv),
+        segv_action
+    );
+    assert_eq!(jump::current_action(crash_handler::Signal::Fpe), fpe_action);
+}
@@ -0,0 +1,52 @@
+//! Verifies that whether the crash occurred on the main thread is reported,
+//! which is why this runs without the test harness, as it would otherwise run
+//! on a thread spawned by it
+
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use parking_lot::Mutex;
+
+/// What each invocation of the crash callback reported
+static IS_MAIN_THREAD: Mutex<Vec<Option<bool>>> = parking_lot::const_mutex(Vec::new());
+
+fn simulate(handler: &ch::CrashHandler) -> Option<bool> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            handler.simulate_signal(libc::SIGUSR2 as u32);
+        } else if #[cfg(target_os = "windows")] {
+            handler.simulate_exception(None);
+        } else if #[cfg(target_os = "macos")] {
+            handler.simulate_exception(None);
+        }
+    }
+
+    IS_MAIN_THREAD
+        .lock()
+        .pop()
+        .expect("the crash callback was not invoked")
+}
+
+fn main() {
+    // Attaching from the main thread is enough for the main thread to be known
+    let handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|cc: &ch::CrashContext| {
+            IS_MAIN_THREAD.lock().push(cc.is_main_thread());
+            ch::CrashEventResult::Handled(true)
+        })
+    })
+    .unwrap();
+
+    assert_eq!(simulate(&handler), Some(true));
+
+    let on_worker = std::thread::scope(|s| {
+        std::thread::Builder::new()
+            .name("worker".to_owned())
+            .spawn_scoped(s, || simulate(&handler))
+            .unwrap()
+            .join()
+            .unwrap()
+    });
+
+    assert_eq!(on_worker, Some(false));
+}
@@ -0,0 +1,45 @@
+//! Verifies that the memory map snapshot can be queried from within the crash
+//! callback
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+
+#[inline(never)]
+fn plugin_entry() -> u32 {
+    42
+}
+
+#[test]
+fn queries_memory_maps() {
+    assert!(ch::memory_maps().is_none());
+
+    let handler = ch::CrashHandler::attach_with_options(
+        unsafe {
+            ch::make_crash_event(|_cc: &ch::CrashContext| {
+                ch::memory_maps()
+                    .and_then(|maps| {
+                        maps.region_for(plugin_entry as *const () as usize)
+                            .map(|region| region.executable)
+                    })
+                    .unwrap_or(false)
+                    .into()
+            })
+        },
+        ch::AttachOptions::default().cache_memory_maps(8 * 1024),
+    )
+    .unwrap();
+
+    assert!(!ch::memory_maps().unwrap().regions().is_empty());
+    handler.refresh_memory_maps().unwrap();
+
+    assert!(matches!(
+        handler.simulate_signal(libc::SIGUSR2 as u32),
+        ch::CrashEventResult::Handled(true)
+    ));
+    assert_eq!(plugin_entry(), 42);
+
+    handler.detach().unwrap();
+    assert!(ch::memory_maps().is_none());
+}
@@ -0,0 +1,55 @@
+//! Verifies that a crash inside the user callback terminates the process
+//! immediately, rather than being delivered to eg. WER which could block on a
+//! modal dialog, or the previous signal handlers. The test runs itself as a
+//! child process that does the actual crashing, since the test itself needs to
+//! check how the child exited
+
+#![cfg(any(target_os = "windows", target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+mod shared;
+
+use crash_handler as ch;
+
+#[test]
+fn terminates_on_nested_crash() {
+    if shared::child_value().is_some() {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let _handler = ch::CrashHandler::attach(unsafe {
+            ch::make_crash_event(|_cc: &ch::CrashContext| {
+                // Crash again, with a different signal, while handling the crash
+                sadness_generator::raise_abort()
+            })
+        })
+        .unwrap();
+
+        #[cfg(target_os = "windows")]
+        let _handler = ch::CrashHandler::attach_with_options(
+            unsafe {
+                ch::make_crash_event(|_cc: &ch::CrashContext| {
+                    // Crash again while handling the crash
+                    sadness_generator::raise_segfault()
+                })
+            },
+            ch::AttachOptions::default().terminate_on_nested_crash(true),
+        )
+        .unwrap();
+
+        unsafe {
+            sadness_generator::raise_illegal_instruction();
+        }
+    }
+
+    // The child is killed if it doesn't terminate promptly
+    let (status, stderr) = shared::run_in_child("terminates_on_nested_crash", "1");
+
+    // The process is terminated with the code of the nested exception
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "windows")] {
+            assert_eq!(status.code(), Some(ch::ExceptionCode::Segv as i32), "{stderr}");
+        } else {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(status.signal(), Some(libc::SIGABRT), "{stderr}");
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Verifies that in one shot mode only the first crash is delivered to our
+//! handler, and that the second is handled by the default action, ie. kills
+//! the process.
+//!
+//! As the second crash kills the process, the crashes happen in a forked
+//! child, which reports each invocation of the callback over a pipe
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+struct JmpBuf(UnsafeCell<MaybeUninit<ch::jmp::JmpBuf>>);
+
+// SAFETY: only accessed by the child's only thread, and the signal handler
+// running on it
+unsafe impl Sync for JmpBuf {}
+
+static JMP_BUF: JmpBuf = JmpBuf(UnsafeCell::new(MaybeUninit::uninit()));
+
+/// The write end of the pipe to the parent
+static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Returns the current action for the signal
+unsafe fn current_action(sig: i32) -> usize {
+    let mut action: libc::sigaction = std::mem::zeroed();
+    assert_eq!(libc::sigaction(sig, std::ptr::null(), &mut action), 0);
+    action.sa_sigaction
+}
+
+unsafe fn child() -> ! {
+    let _handler = ch::CrashHandler::attach_with_options(
+        ch::make_crash_event(|_cc: &ch::CrashContext| {
+            libc::write(PIPE.load(Ordering::Relaxed), b"c".as_ptr().cast(), 1);
+
+            ch::CrashEventResult::Jump {
+                jmp_buf: JMP_BUF.0.get().cast(),
+                value: 1,
+            }
+        }),
+        ch::AttachOptions::default().one_shot(true),
+    )
+    .unwrap();
+
+    let handler = current_action(libc::SIGSEGV);
+
+    if ch::jmp::sigsetjmp(JMP_BUF.0.get().cast(), 1) == 0 {
+        sadness_generator::raise_segfault();
+    }
+
+    // The kernel reset the action for the signal we received, but not for
+    // the signals we haven't
+    if current_action(libc::SIGSEGV) != libc::SIG_DFL {
+        libc::_exit(2);
+    }
+    if current_action(libc::SIGBUS) != handler {
+        libc::_exit(3);
+    }
+
+    // If our handler were to see this, it would jump back into the check
+    // above and exit with 2
+    sadness_generator::raise_segfault();
+}
+
+#[test]
+fn second_crash_bypasses_handler() {
+    unsafe {
+        let mut fds = [0; 2];
+        assert_eq!(libc::pipe(fds.as_mut_ptr()), 0);
+
+        let pid = libc::fork();
+        assert!(pid >= 0);
+
+        if pid == 0 {
+            libc::close(fds[0]);
+            PIPE.store(fds[1], Ordering::Relaxed);
+            child();
+        }
+
+        libc::close(fds[1]);
+
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+
+        let mut calls = [0u8; 8];
+        let read = libc::read(fds[0], calls.as_mut_ptr().cast(), calls.len());
+        libc::close(fds[0]);
+
+        assert!(
+            libc::WIFSIGNALED(status),
+            "child exited with {}",
+            libc::WEXITSTATUS(status)
+        );
+        assert_eq!(libc::WTERMSIG(status), libc::SIGSEGV);
+        assert_eq!(read, 1, "the callback should only be invoked once");
+    }
+}
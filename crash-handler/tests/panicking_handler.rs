@@ -0,0 +1,41 @@
+//! Verifies that a panic in the user callback is treated as an unhandled crash,
+//! rather than unwinding out of the signal/exception handler. The test runs
+//! itself as a child process that does the actual crashing, since the test
+//! itself needs to check how the child exited
+
+#![allow(unsafe_code)]
+
+mod shared;
+
+use crash_handler as ch;
+
+#[test]
+fn panic_is_unhandled() {
+    if shared::child_value().is_some() {
+        let _handler = ch::CrashHandler::attach(unsafe {
+            ch::make_crash_event(|_cc: &ch::CrashContext| panic!("the crash handler panicked"))
+        })
+        .unwrap();
+
+        unsafe {
+            sadness_generator::raise_segfault();
+        }
+    }
+
+    let (status, stderr) = shared::run_in_child("panic_is_unhandled", "1");
+    assert!(stderr.contains("the crash handler panicked"), "{stderr}");
+
+    // The default handling of the crash still occurs
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(status.signal(), Some(libc::SIGSEGV), "{stderr}");
+        } else if #[cfg(target_os = "windows")] {
+            assert_eq!(
+                status.code(),
+                Some(ch::ExceptionCode::Segv as i32),
+                "{stderr}"
+            );
+        }
+    }
+}
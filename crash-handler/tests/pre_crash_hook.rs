@@ -0,0 +1,72 @@
+//! Verifies that the pre-crash hook is invoked before the crash event, for both
+//! simulated and real crashes, and that neither a panic nor a crash inside the
+//! hook prevents the event from running
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct JmpBuf(UnsafeCell<MaybeUninit<ch::jmp::JmpBuf>>);
+
+// SAFETY: only accessed by the test thread, and the signal handler running on it
+unsafe impl Sync for JmpBuf {}
+
+static JMP_BUF: JmpBuf = JmpBuf(UnsafeCell::new(MaybeUninit::uninit()));
+
+/// The number of times the hook has been invoked
+static HOOK_CALLS: AtomicUsize = AtomicUsize::new(0);
+/// The value of [`HOOK_CALLS`] each time the event was invoked
+static EVENT_SAW: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+fn runs_hook_before_event() {
+    let handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|_cc: &ch::CrashContext| {
+            EVENT_SAW.store(HOOK_CALLS.load(Ordering::Relaxed), Ordering::Relaxed);
+
+            ch::CrashEventResult::Jump {
+                jmp_buf: JMP_BUF.0.get().cast(),
+                value: 1,
+            }
+        })
+    })
+    .unwrap();
+
+    unsafe {
+        handler.set_pre_crash_hook(Box::new(|_cc: &ch::CrashContext| {
+            HOOK_CALLS.fetch_add(1, Ordering::Relaxed);
+            panic!("the hook panicked");
+        }));
+    }
+
+    // The jump isn't taken for simulated crashes, it is just returned
+    assert!(matches!(
+        handler.simulate_signal(libc::SIGUSR1 as u32),
+        ch::CrashEventResult::Jump { .. }
+    ));
+    assert_eq!(EVENT_SAW.load(Ordering::Relaxed), 1);
+
+    unsafe {
+        handler.set_pre_crash_hook(Box::new(|_cc: &ch::CrashContext| {
+            HOOK_CALLS.fetch_add(1, Ordering::Relaxed);
+            sadness_generator::raise_segfault();
+        }));
+    }
+
+    let value = unsafe { ch::jmp::sigsetjmp(JMP_BUF.0.get().cast(), 1) };
+
+    if value == 0 {
+        unsafe {
+            sadness_generator::raise_segfault();
+        }
+    }
+
+    assert_eq!(value, 1);
+    assert_eq!(EVENT_SAW.load(Ordering::Relaxed), 2);
+}
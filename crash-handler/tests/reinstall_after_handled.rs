@@ -0,0 +1,101 @@
+//! Verifies that our handler is still installed after a crash is handled, so
+//! that a second crash is caught as well.
+//!
+//! The first crash is a write to a read only page, which the callback fixes by
+//! making the page writable, so that the write succeeds when it is retried,
+//! and the second is recovered from by jumping out of the handler, which would
+//! otherwise be caught by the default handler and kill the process
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct JmpBuf(UnsafeCell<MaybeUninit<ch::jmp::JmpBuf>>);
+
+// SAFETY: only accessed by the test thread, and the signal handler running on it
+unsafe impl Sync for JmpBuf {}
+
+static JMP_BUF: JmpBuf = JmpBuf(UnsafeCell::new(MaybeUninit::uninit()));
+
+static PAGE: AtomicUsize = AtomicUsize::new(0);
+static CRASHES: AtomicUsize = AtomicUsize::new(0);
+
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[test]
+fn catches_second_crash() {
+    let _handler = ch::CrashHandler::attach_with_options(
+        unsafe {
+            ch::make_crash_event(|cc: &ch::CrashContext| {
+                let page = PAGE.load(Ordering::Relaxed);
+                assert_eq!(cc.siginfo.ssi_signo, libc::SIGSEGV as u32);
+                assert_eq!(cc.siginfo.ssi_addr as usize, page);
+
+                if CRASHES.fetch_add(1, Ordering::Relaxed) == 0 {
+                    libc::mprotect(
+                        page as *mut _,
+                        page_size(),
+                        libc::PROT_READ | libc::PROT_WRITE,
+                    );
+                    ch::CrashEventResult::Handled(true)
+                } else {
+                    ch::CrashEventResult::Jump {
+                        jmp_buf: JMP_BUF.0.get().cast(),
+                        value: 1,
+                    }
+                }
+            })
+        },
+        ch::AttachOptions::default().reinstall_after_handled(true),
+    )
+    .unwrap();
+
+    let page = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            page_size(),
+            libc::PROT_READ,
+            libc::MAP_PRIVATE | libc::MAP_ANON,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(page, libc::MAP_FAILED);
+    PAGE.store(page as usize, Ordering::Relaxed);
+
+    let page = page.cast::<u8>();
+
+    unsafe {
+        page.write_volatile(0x5a);
+        assert_eq!(page.read_volatile(), 0x5a);
+    }
+    assert_eq!(CRASHES.load(Ordering::Relaxed), 1);
+
+    unsafe {
+        assert_eq!(libc::mprotect(page.cast(), page_size(), libc::PROT_READ), 0);
+    }
+
+    let value = unsafe { ch::jmp::sigsetjmp(JMP_BUF.0.get().cast(), 1) };
+
+    if value == 0 {
+        unsafe {
+            page.write_volatile(0xa5);
+        }
+    }
+
+    assert_eq!(value, 1);
+    assert_eq!(CRASHES.load(Ordering::Relaxed), 2);
+
+    unsafe {
+        assert_eq!(page.read_volatile(), 0x5a);
+        libc::munmap(page.cast(), page_size());
+    }
+}
@@ -2,5 +2,7 @@ mod shared;
 
 #[test]
 fn handles_segv() {
-    shared::handles_crash(shared::SadnessFlavor::Segfault);
+    shared::handles_crash(shared::SadnessFlavor::Segfault {
+        native_thread: false,
+    });
 }
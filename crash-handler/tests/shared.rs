@@ -1,9 +1,62 @@
 #![allow(unsafe_code)]
+// Each test only uses some of the helpers
+#![allow(dead_code)]
 
 use crash_handler as ch;
 
 pub use sadness_generator::SadnessFlavor;
 
+/// Set for the child process spawned by [`run_in_child`], to the value passed
+/// to it
+const CHILD_ENV: &str = "CRASH_HANDLER_TEST_CHILD";
+
+/// Returns the value passed to [`run_in_child`] if this is the child process,
+/// which should do the actual crashing
+pub fn child_value() -> Option<String> {
+    std::env::var(CHILD_ENV).ok()
+}
+
+/// Runs the test `name` again in a child process, where [`child_value`]
+/// returns `value`, for tests that need to check how the process exits after
+/// a crash. Returns the exit status and stderr of the child.
+///
+/// The test fails if the child doesn't exit within 30 seconds, eg. because it
+/// is blocked on a modal error dialog.
+pub fn run_in_child(name: &str, value: &str) -> (std::process::ExitStatus, String) {
+    use std::io::Read;
+
+    let start = std::time::Instant::now();
+    let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", name, "--nocapture"])
+        .env(CHILD_ENV, value)
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run child");
+
+    // Drained as the child runs, so it can't block on a full pipe
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        let _res = stderr.read_to_end(&mut output);
+        String::from_utf8_lossy(&output).into_owned()
+    });
+
+    let status = loop {
+        if let Some(status) = child.try_wait().expect("failed to wait on child") {
+            break status;
+        }
+
+        if start.elapsed() > std::time::Duration::from_secs(30) {
+            let _res = child.kill();
+            panic!("child did not exit");
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+
+    (status, stderr.join().unwrap())
+}
+
 pub fn handles_crash(flavor: SadnessFlavor) {
     let mut _handler = None;
 
@@ -14,25 +67,69 @@ pub fn handles_crash(flavor: SadnessFlavor) {
                     if #[cfg(any(target_os = "linux", target_os = "android"))] {
                         use ch::Signal;
 
+                        /// `SEGV_ACCERR`, which is not exposed by libc for
+                        /// Linux/Android
+                        const SEGV_ACCERR: i32 = 2;
+
                         assert_eq!(
                             cc.siginfo.ssi_signo,
                             match flavor {
-                                SadnessFlavor::Abort => Signal::Abort,
-                                SadnessFlavor::Bus => Signal::Bus,
-                                SadnessFlavor::DivideByZero => Signal::Fpe,
-                                SadnessFlavor::Illegal => Signal::Illegal,
-                                SadnessFlavor::Segfault | SadnessFlavor::StackOverflow { .. } => {
+                                SadnessFlavor::Abort => {
+                                    // abort sends the signal to ourselves
+                                    assert_eq!(cc.siginfo.ssi_pid, std::process::id());
+
+                                    Signal::Abort
+                                }
+                                // std::terminate calls abort
+                                #[cfg(feature = "cpp")]
+                                SadnessFlavor::CppTerminate => {
+                                    assert_eq!(cc.siginfo.ssi_pid, std::process::id());
+
+                                    Signal::Abort
+                                }
+                                SadnessFlavor::Bus { .. } => Signal::Bus,
+                                SadnessFlavor::DivideByZero { .. } => Signal::Fpe,
+                                SadnessFlavor::DivideOverflow => {
+                                    // x86 reports every divide error the same
+                                    // way, while ARM raises it explicitly
+                                    let expected = if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+                                        1 // FPE_INTDIV
+                                    } else {
+                                        2 // FPE_INTOVF
+                                    };
+                                    assert_eq!(cc.siginfo.ssi_code, expected);
+
+                                    Signal::Fpe
+                                }
+                                SadnessFlavor::Illegal { .. } => Signal::Illegal,
+                                SadnessFlavor::Segfault { .. } | SadnessFlavor::AtExitCrash => {
+                                    assert_eq!(cc.siginfo.ssi_addr, sadness_generator::SEGFAULT_ADDRESS as _);
+
+                                    Signal::Segv
+                                }
+                                SadnessFlavor::StackOverflow { .. } => Signal::Segv,
+                                SadnessFlavor::SmashedStack => {
+                                    // The fault is on the fetch of the garbage
+                                    // instruction pointer
+                                    assert_eq!(cc.siginfo.ssi_addr, sadness_generator::SMASHED_RETURN_ADDRESS as _);
+
                                     Signal::Segv
                                 }
-                                SadnessFlavor::Trap => Signal::Trap,
+                                SadnessFlavor::WriteReadOnly => {
+                                    // The address is mapped, we just don't have
+                                    // permission to write to it
+                                    assert_eq!(cc.siginfo.ssi_code, SEGV_ACCERR);
+                                    assert_eq!(cc.siginfo.ssi_addr, sadness_generator::read_only_address() as u64);
+
+                                    Signal::Segv
+                                }
+                                SadnessFlavor::Trap { .. } => Signal::Trap,
                             } as u32,
                         );
 
                         //assert_eq!(cc.tid, tid);
 
-                        // At least on linux these...aren't set. Which is weird
-                        //assert_eq!(cc.siginfo.ssi_pid, std::process::id());
-                        //assert_eq!(cc.siginfo.ssi_tid, tid as u32);
+                        // Note ssi_tid is only set for POSIX timers
                     } else if #[cfg(target_os = "macos")] {
                         use ch::ExceptionType;
 
@@ -45,10 +142,18 @@ pub fn handles_crash(flavor: SadnessFlavor) {
 
                                 ExceptionType::Software
                             }
-                            SadnessFlavor::Bus
-                            | SadnessFlavor::Segfault
+                            #[cfg(feature = "cpp")]
+                            SadnessFlavor::CppTerminate => {
+                                assert_eq!(exc.code, 0x10003); // EXC_SOFT_SIGNAL
+                                assert_eq!(exc.subcode.unwrap(), libc::SIGABRT as _);
+
+                                ExceptionType::Software
+                            }
+                            SadnessFlavor::Bus { .. }
+                            | SadnessFlavor::Segfault { .. }
+                            | SadnessFlavor::AtExitCrash
                             | SadnessFlavor::StackOverflow { .. } => {
-                                if flavor == SadnessFlavor::Segfault {
+                                if matches!(flavor, SadnessFlavor::Segfault { .. } | SadnessFlavor::AtExitCrash) {
                                     // For EXC_BAD_ACCESS exceptions, the subcode will be the
                                     // bad address we tried to access
                                     assert_eq!(cc.exception.unwrap().subcode.unwrap(), sadness_generator::SEGFAULT_ADDRESS as _);
@@ -56,10 +161,22 @@ pub fn handles_crash(flavor: SadnessFlavor) {
 
                                 ExceptionType::BadAccess
                             },
-                            SadnessFlavor::DivideByZero => ExceptionType::Arithmetic,
-                            SadnessFlavor::Illegal => ExceptionType::BadInstruction,
-                            SadnessFlavor::Trap => ExceptionType::Breakpoint,
+                            SadnessFlavor::SmashedStack => {
+                                assert_eq!(exc.subcode.unwrap(), sadness_generator::SMASHED_RETURN_ADDRESS as _);
+
+                                ExceptionType::BadAccess
+                            }
+                            SadnessFlavor::WriteReadOnly => {
+                                assert_eq!(exc.code, 2); // KERN_PROTECTION_FAILURE
+                                assert_eq!(exc.subcode.unwrap(), sadness_generator::read_only_address() as _);
+
+                                ExceptionType::BadAccess
+                            }
+                            SadnessFlavor::DivideByZero { .. } | SadnessFlavor::DivideOverflow => ExceptionType::Arithmetic,
+                            SadnessFlavor::Illegal { .. } => ExceptionType::BadInstruction,
+                            SadnessFlavor::Trap { .. } => ExceptionType::Breakpoint,
                             SadnessFlavor::Guard => ExceptionType::Guard,
+                            SadnessFlavor::ResourceCpu { .. } => ExceptionType::Resource,
                         };
 
                         assert_eq!(exc.kind, expected as _);
@@ -67,15 +184,19 @@ pub fn handles_crash(flavor: SadnessFlavor) {
                         use ch::ExceptionCode;
 
                         let ec = match flavor {
-                            SadnessFlavor::Abort => ExceptionCode::Abort,
-                            SadnessFlavor::DivideByZero => ExceptionCode::Fpe,
-                            SadnessFlavor::Illegal => ExceptionCode::Illegal,
+                            SadnessFlavor::Abort | SadnessFlavor::CrtAbort => ExceptionCode::Abort,
+                            #[cfg(feature = "cpp")]
+                            SadnessFlavor::CppTerminate => ExceptionCode::Abort,
+                            SadnessFlavor::DivideByZero { .. } => ExceptionCode::Fpe,
+                            SadnessFlavor::DivideOverflow => ExceptionCode::IntOverflow,
+                            SadnessFlavor::Illegal { .. } => ExceptionCode::Illegal,
                             SadnessFlavor::InvalidParameter => ExceptionCode::InvalidParameter,
                             SadnessFlavor::Purecall => ExceptionCode::Purecall,
-                            SadnessFlavor::Segfault => ExceptionCode::Segv,
+                            SadnessFlavor::Segfault { .. } | SadnessFlavor::AtExitCrash | SadnessFlavor::WriteReadOnly | SadnessFlavor::SmashedStack => ExceptionCode::Segv,
                             SadnessFlavor::StackOverflow { .. }=> ExceptionCode::StackOverflow,
-                            SadnessFlavor::Trap => ExceptionCode::Trap,
+                            SadnessFlavor::Trap { .. } | SadnessFlavor::DebugService { .. } => ExceptionCode::Trap,
                             SadnessFlavor::HeapCorruption => ExceptionCode::HeapCorruption,
+                            SadnessFlavor::FastFail => unreachable!("fastfail can't be caught"),
                         };
 
                         assert_eq!(
@@ -83,6 +204,19 @@ pub fn handles_crash(flavor: SadnessFlavor) {
                             "0x{:x} != 0x{:x}",
                             cc.exception_code, ec as i32
                         );
+
+                        let parameter = match flavor {
+                            SadnessFlavor::Trap { parameter, .. } => parameter,
+                            SadnessFlavor::DebugService { code } => Some(code as usize),
+                            _ => None,
+                        };
+
+                        if let Some(parameter) = parameter {
+                            let record = &*(*cc.exception_pointers).ExceptionRecord;
+
+                            assert!(record.NumberParameters >= 1);
+                            assert_eq!(record.ExceptionInformation[0], parameter);
+                        }
                     }
                 }
 
@@ -0,0 +1,126 @@
+#![allow(unsafe_code)]
+
+//! Helpers for recovering from crashes on arbitrary threads by jumping back to
+//! a point established on the crashing thread itself, rather than exiting the
+//! process like `shared::handles_crash` does
+
+use crash_handler as ch;
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub use sadness_generator::SadnessFlavor;
+
+thread_local! {
+    /// The point each thread jumps back to after crashing. This is allocated
+    /// the first time the thread sets it, so accessing it within the signal
+    /// handler doesn't need to allocate anything
+    static JUMP_POINT: ch::JumpPoint = ch::JumpPoint::new();
+    /// The tid of the thread, so the handler can check the crash context
+    /// actually describes the thread it was invoked on
+    static TID: Cell<i32> = const { Cell::new(0) };
+    /// Arbitrary thread local state that must survive the jump
+    static MARKER: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The number of crashes the handler has been invoked for
+static CRASHES: AtomicUsize = AtomicUsize::new(0);
+/// The number of crashes where the crash context didn't match the thread the
+/// handler was invoked on
+static MISMATCHES: AtomicUsize = AtomicUsize::new(0);
+
+/// Attaches a handler that jumps back to the crashing thread's jump point,
+/// resuming it with the signal number
+pub fn attach() -> ch::CrashHandler {
+    unsafe {
+        ch::CrashHandler::attach(ch::make_crash_event(|cc: &ch::CrashContext| {
+            CRASHES.fetch_add(1, Ordering::SeqCst);
+
+            // We can't panic in the signal handler, so just record the failure
+            if TID.with(Cell::get) != cc.tid {
+                MISMATCHES.fetch_add(1, Ordering::SeqCst);
+            }
+
+            JUMP_POINT
+                .with(|jp| jp.jump(cc.siginfo.ssi_signo as i32))
+                .unwrap_or(ch::CrashEventResult::Handled(false))
+        }))
+        .unwrap()
+    }
+}
+
+/// The number of crashes handled so far
+pub fn crashes() -> usize {
+    CRASHES.load(Ordering::SeqCst)
+}
+
+/// Asserts that the handler has always been invoked with the crash context for
+/// the thread it was running on
+pub fn assert_no_mismatches() {
+    assert_eq!(MISMATCHES.load(Ordering::SeqCst), 0);
+}
+
+/// Retrieves the action currently installed for the specified signal
+pub fn current_action(sig: ch::Signal) -> usize {
+    unsafe {
+        let mut sa: libc::sigaction = std::mem::zeroed();
+        assert_eq!(libc::sigaction(sig as i32, std::ptr::null(), &mut sa), 0);
+        sa.sa_sigaction
+    }
+}
+
+fn current_altstack() -> (usize, usize) {
+    unsafe {
+        let mut ss: libc::stack_t = std::mem::zeroed();
+        assert_eq!(libc::sigaltstack(std::ptr::null(), &mut ss), 0);
+        (ss.ss_sp as usize, ss.ss_size)
+    }
+}
+
+fn is_blocked(sig: ch::Signal) -> bool {
+    unsafe {
+        let mut set: libc::sigset_t = std::mem::zeroed();
+        assert_eq!(
+            libc::pthread_sigmask(libc::SIG_BLOCK, std::ptr::null(), &mut set),
+            0
+        );
+        libc::sigismember(&set, sig as i32) == 1
+    }
+}
+
+/// Crashes the current thread with the specified flavor, recovers from it via
+/// the jump point, then verifies the state of the thread is intact.
+///
+/// `before_crash` is invoked immediately before crashing, eg. to synchronize
+/// with other threads.
+pub fn crash_and_recover(flavor: SadnessFlavor, marker: u64, before_crash: impl FnOnce()) {
+    let expected = match flavor {
+        SadnessFlavor::Segfault { .. } => ch::Signal::Segv,
+        SadnessFlavor::DivideByZero { .. } => ch::Signal::Fpe,
+        _ => panic!("only segfaults and divide by zero are recovered from"),
+    };
+
+    TID.with(|tid| tid.set(unsafe { libc::syscall(libc::SYS_gettid) } as i32));
+    MARKER.with(|m| m.set(marker));
+
+    let altstack = current_altstack();
+
+    // The signal mask is restored when we jump back, otherwise the signals that
+    // were blocked during the signal handler would remain blocked and any
+    // subsequent crash on this thread would be fatal
+    let landing = JUMP_POINT.with(|jp| {
+        jp.set(|| {
+            before_crash();
+
+            unsafe {
+                flavor.make_sad();
+            }
+        })
+    });
+
+    assert_eq!(landing, ch::Landing::Resumed(expected as i32));
+    assert_eq!(MARKER.with(Cell::get), marker);
+    assert_eq!(current_altstack(), altstack);
+    assert!(!is_blocked(expected));
+}
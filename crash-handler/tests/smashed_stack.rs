@@ -0,0 +1,6 @@
+mod shared;
+
+#[test]
+fn handles_smashed_stack() {
+    shared::handles_crash(shared::SadnessFlavor::SmashedStack);
+}
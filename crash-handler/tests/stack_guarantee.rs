@@ -0,0 +1,42 @@
+//! Verifies that a worker thread that overflows its stack has enough stack
+//! left for a callback that needs more than the few pages Windows reserves by
+//! default, or that Rust's own stack overflow handler guarantees, once it has
+//! been registered via [`crash_handler::register_thread`]
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+
+#[test]
+fn handles_stack_overflow_with_guarantee() {
+    let _handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|cc: &ch::CrashContext| {
+            // Uses more stack than is available without the guarantee
+            let scratch = std::hint::black_box([0xa5u8; 40 * 1024]);
+            assert_eq!(scratch[scratch.len() - 1], 0xa5);
+
+            assert_eq!(cc.exception_code, ch::ExceptionCode::StackOverflow as i32);
+
+            #[allow(clippy::exit)]
+            std::process::exit(0);
+        })
+    })
+    .unwrap();
+
+    std::thread::Builder::new()
+        .name("overflowing-worker".to_owned())
+        .stack_size(256 * 1024)
+        .spawn(|| {
+            ch::register_thread().unwrap();
+
+            unsafe {
+                sadness_generator::raise_stack_overflow();
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    panic!("this should be impossible");
+}
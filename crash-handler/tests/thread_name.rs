@@ -0,0 +1,59 @@
+//! Verifies that the name and id of the crashing thread are captured
+
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static THREAD_ID: AtomicU64 = AtomicU64::new(0);
+
+#[test]
+fn captures_thread_name() {
+    let handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|cc: &ch::CrashContext| {
+            THREAD_ID.store(cc.crashing_thread_id(), Ordering::Relaxed);
+            (cc.thread_name.to_str().as_deref() == Some("render-worker-3")).into()
+        })
+    })
+    .unwrap();
+
+    let (handled, thread_id) = std::thread::scope(|s| {
+        std::thread::Builder::new()
+            .name("render-worker-3".to_owned())
+            .spawn_scoped(s, || {
+                cfg_if::cfg_if! {
+                    if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                        let handled = matches!(
+                            handler.simulate_signal(libc::SIGUSR2 as u32),
+                            ch::CrashEventResult::Handled(true)
+                        );
+                        let thread_id = unsafe { libc::syscall(libc::SYS_gettid) } as u64;
+                    } else if #[cfg(target_os = "windows")] {
+                        extern "system" {
+                            fn GetCurrentThreadId() -> u32;
+                        }
+
+                        let handled = matches!(
+                            handler.simulate_exception(None),
+                            ch::CrashEventResult::Handled(true)
+                        );
+                        let thread_id = u64::from(unsafe { GetCurrentThreadId() });
+                    } else if #[cfg(target_os = "macos")] {
+                        let handled = handler.simulate_exception(None);
+                        let mut thread_id = 0;
+                        unsafe {
+                            libc::pthread_threadid_np(0, &mut thread_id);
+                        }
+                    }
+                }
+
+                (handled, thread_id)
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    });
+
+    assert!(handled, "the thread name was not captured");
+    assert_eq!(THREAD_ID.load(Ordering::Relaxed), thread_id);
+}
@@ -0,0 +1,35 @@
+//! Verifies that the CPU state of the crashed thread is captured into the crash
+//! context when requested
+
+#![cfg(target_os = "macos")]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static CAPTURED: AtomicBool = AtomicBool::new(false);
+
+#[test]
+fn captures_thread_state() {
+    let handler = ch::CrashHandler::attach_with_options(
+        unsafe {
+            ch::make_crash_event(|cc: &ch::CrashContext| {
+                if let Some(ts) = &cc.thread_state {
+                    #[cfg(target_arch = "x86_64")]
+                    let pc = ts.general.__rip;
+                    #[cfg(target_arch = "aarch64")]
+                    let pc = ts.general.__pc;
+
+                    CAPTURED.store(pc != 0, Ordering::Relaxed);
+                }
+
+                true.into()
+            })
+        },
+        ch::AttachOptions::default().capture_thread_state(true),
+    )
+    .unwrap();
+
+    assert!(handler.simulate_exception(None));
+    assert!(CAPTURED.load(Ordering::Relaxed));
+}
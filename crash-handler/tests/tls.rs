@@ -0,0 +1,81 @@
+//! Verifies that the thread pointer of the crashing thread is captured, by
+//! comparing it with the one read by the thread itself before it crashed
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+struct JmpBuf(UnsafeCell<MaybeUninit<ch::jmp::JmpBuf>>);
+
+// SAFETY: only accessed by the test thread, and the signal handler running on it
+unsafe impl Sync for JmpBuf {}
+
+static JMP_BUF: JmpBuf = JmpBuf(UnsafeCell::new(MaybeUninit::uninit()));
+
+static CAPTURED: AtomicU64 = AtomicU64::new(0);
+
+/// Reads the thread pointer the same way `__builtin_thread_pointer()` does,
+/// or, on x86, via the TCB's pointer to itself, which is where the thread
+/// pointer points to on both glibc and musl
+fn thread_pointer() -> u64 {
+    let tp: usize;
+
+    unsafe {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "x86_64")] {
+                std::arch::asm!("mov {}, qword ptr fs:[0]", out(reg) tp, options(readonly, nostack, preserves_flags));
+            } else if #[cfg(target_arch = "x86")] {
+                std::arch::asm!("mov {}, dword ptr gs:[0]", out(reg) tp, options(readonly, nostack, preserves_flags));
+            } else if #[cfg(target_arch = "aarch64")] {
+                std::arch::asm!("mrs {}, tpidr_el0", out(reg) tp, options(nomem, nostack, preserves_flags));
+            } else if #[cfg(target_arch = "arm")] {
+                std::arch::asm!("mrc p15, 0, {}, c13, c0, 3", out(reg) tp, options(nomem, nostack, preserves_flags));
+            }
+        }
+    }
+
+    tp as u64
+}
+
+#[test]
+fn captures_thread_pointer() {
+    let _handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(|cc: &ch::CrashContext| {
+            CAPTURED.store(cc.tls.thread_pointer, Ordering::Relaxed);
+
+            ch::CrashEventResult::Jump {
+                jmp_buf: JMP_BUF.0.get().cast(),
+                value: 1,
+            }
+        })
+    })
+    .unwrap();
+
+    // Crash on a thread other than the main one, so that the thread pointer
+    // differs from the process' first one
+    let expected = std::thread::spawn(|| {
+        let expected = thread_pointer();
+
+        let value = unsafe { ch::jmp::sigsetjmp(JMP_BUF.0.get().cast(), 1) };
+
+        if value == 0 {
+            unsafe {
+                sadness_generator::raise_segfault();
+            }
+        }
+
+        assert_eq!(value, 1);
+        expected
+    })
+    .join()
+    .unwrap();
+
+    assert_ne!(expected, 0);
+    assert_eq!(CAPTURED.load(Ordering::Relaxed), expected);
+}
@@ -2,5 +2,9 @@ mod shared;
 
 #[test]
 fn handles_trap() {
-    shared::handles_crash(shared::SadnessFlavor::Trap);
+    shared::handles_crash(shared::SadnessFlavor::Trap {
+        native_thread: false,
+        #[cfg(windows)]
+        parameter: None,
+    });
 }
@@ -0,0 +1,11 @@
+#![cfg(windows)]
+
+mod shared;
+
+#[test]
+fn handles_trap_parameter() {
+    shared::handles_crash(shared::SadnessFlavor::Trap {
+        native_thread: false,
+        parameter: Some(0x5ad),
+    });
+}
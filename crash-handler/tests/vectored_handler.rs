@@ -0,0 +1,89 @@
+//! Verifies that the vectored exception handler is registered in the position
+//! requested relative to a competing vectored handler, or not at all, by
+//! raising a synthetic `STATUS_HEAP_CORRUPTION` that the competing handler
+//! continues execution from
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+type Handler = Option<unsafe extern "system" fn(*const std::ffi::c_void) -> i32>;
+
+extern "system" {
+    fn AddVectoredExceptionHandler(first_handler: u32, handler: Handler) -> *mut std::ffi::c_void;
+    fn RemoveVectoredExceptionHandler(handle: *mut std::ffi::c_void) -> u32;
+    fn SetUnhandledExceptionFilter(filter: Handler) -> Handler;
+    fn RaiseException(code: u32, flags: u32, num_args: u32, args: *const usize);
+}
+
+const STATUS_HEAP_CORRUPTION: u32 = 0xc0000374;
+const EXCEPTION_CONTINUE_EXECUTION: i32 = -1;
+
+/// Incremented by each handler that sees the exception, so the order they
+/// were invoked in can be checked
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+/// The position the crash event was invoked at, or 0 if it wasn't
+static EVENT_AT: AtomicUsize = AtomicUsize::new(0);
+/// The position the competing handler was invoked at, or 0 if it wasn't
+static COMPETING_AT: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "system" fn competing_handler(_exception_info: *const std::ffi::c_void) -> i32 {
+    COMPETING_AT.store(
+        SEQUENCE.fetch_add(1, Ordering::SeqCst) + 1,
+        Ordering::SeqCst,
+    );
+    EXCEPTION_CONTINUE_EXECUTION
+}
+
+/// Returns the positions the crash event and competing handler were invoked
+/// at for a heap corruption, with the crash handler attached with the
+/// specified position
+fn raise(position: ch::VectoredHandler) -> (usize, usize) {
+    SEQUENCE.store(0, Ordering::SeqCst);
+    EVENT_AT.store(0, Ordering::SeqCst);
+    COMPETING_AT.store(0, Ordering::SeqCst);
+
+    unsafe {
+        // Ensure the exception continues to the competing handler if the crash
+        // event sees it first and doesn't handle it
+        SetUnhandledExceptionFilter(None);
+
+        let competing = AddVectoredExceptionHandler(1, Some(competing_handler));
+        assert!(!competing.is_null());
+
+        let handler = ch::CrashHandler::attach_with_options(
+            ch::make_crash_event(|_cc: &ch::CrashContext| {
+                EVENT_AT.store(
+                    SEQUENCE.fetch_add(1, Ordering::SeqCst) + 1,
+                    Ordering::SeqCst,
+                );
+                false.into()
+            }),
+            ch::AttachOptions::default().vectored_handler(position),
+        )
+        .unwrap();
+
+        RaiseException(STATUS_HEAP_CORRUPTION, 0, 0, std::ptr::null());
+
+        handler.detach().unwrap();
+        RemoveVectoredExceptionHandler(competing);
+    }
+
+    (
+        EVENT_AT.load(Ordering::SeqCst),
+        COMPETING_AT.load(Ordering::SeqCst),
+    )
+}
+
+#[test]
+fn respects_vectored_handler_position() {
+    // Our handler is registered before the competing one, so it sees the
+    // exception first
+    assert_eq!(raise(ch::VectoredHandler::First), (1, 2));
+    // The competing handler continues execution before ours is reached
+    assert_eq!(raise(ch::VectoredHandler::Last), (0, 1));
+    // Our handler is never registered
+    assert_eq!(raise(ch::VectoredHandler::Disabled), (0, 1));
+}
@@ -0,0 +1,6 @@
+mod shared;
+
+#[test]
+fn handles_write_read_only() {
+    shared::handles_crash(shared::SadnessFlavor::WriteReadOnly);
+}
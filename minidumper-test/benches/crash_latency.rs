@@ -0,0 +1,41 @@
+//! Measures the wall clock time from a client raising a signal/exception until
+//! the server has finished writing the minidump for it, for each flavor.
+//!
+//! Each sample is printed to stdout as a single line of JSON, eg.
+//! `{"flavor":"segv","iteration":0,"latency_us":12345,"dump_size":67890}`, so
+//! that the results can be collected and compared over time.
+
+use minidumper_test::{time_minidump, Signal};
+
+fn main() {
+    // `cargo bench` passes `--bench`, otherwise we're being run by eg.
+    // `cargo test --benches`, and only need to check that the harness works
+    let iterations = if std::env::args().any(|arg| arg == "--bench") {
+        10
+    } else {
+        1
+    };
+
+    let signals = [
+        Signal::Abort,
+        #[cfg(unix)]
+        Signal::Bus,
+        Signal::Fpe,
+        Signal::Illegal,
+        Signal::Segv,
+        Signal::StackOverflow,
+        Signal::Trap,
+    ];
+
+    for signal in signals {
+        for iteration in 0..iterations {
+            let timing = time_minidump(&format!("{signal}-{iteration}-latency"), signal, false);
+
+            println!(
+                r#"{{"flavor":"{signal}","iteration":{iteration},"latency_us":{},"dump_size":{}}}"#,
+                timing.latency.as_micros(),
+                timing.dump_size,
+            );
+        }
+    }
+}
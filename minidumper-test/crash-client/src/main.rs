@@ -8,12 +8,50 @@ struct Command {
     /// that should be produced when this client crashes
     #[clap(long)]
     id: String,
+    /// Connects to the server at this socket path, rather than the name `--id`
+    /// is interpreted as, eg. to use the name from
+    /// `minidumper::SocketName::android_default`
+    #[clap(long)]
+    socket_path: Option<std::path::PathBuf>,
     /// The signal/exception to raise
+    #[clap(long, required_unless_present_any = ["messages", "pings", "message_rate"])]
+    signal: Option<Signal>,
+    /// Rather than crashing, sends the specified number of user messages to
+    /// the server, then exits normally
+    #[clap(long, conflicts_with = "signal")]
+    messages: Option<u32>,
+    /// Rather than crashing, pings the server the specified number of times,
+    /// then exits normally
+    #[clap(long, conflicts_with = "signal")]
+    pings: Option<u32>,
+    /// The time, in milliseconds, to wait between each ping
+    #[clap(long, requires = "pings")]
+    ping_interval_ms: Option<u64>,
+    /// Sends user messages, at the specified number per second, for
+    /// `--telemetry-ms` before raising the signal, or exiting normally
+    #[clap(long, requires = "telemetry_ms")]
+    message_rate: Option<u32>,
+    /// The time, in milliseconds, to send messages at `--message-rate`
+    #[clap(long, requires = "message_rate")]
+    telemetry_ms: Option<u64>,
+    /// The time, in milliseconds, to stay connected without sending anything
+    /// to the server, before exiting normally, or raising the signal
     #[clap(long)]
-    signal: Signal,
+    quiet_ms: Option<u64>,
     /// Raises the signal on a separate thread rather than the main thread
     #[clap(long)]
     use_thread: bool,
+    /// Writes the heap sentinel to a large heap allocation before crashing
+    #[clap(long)]
+    heap_sentinel: bool,
+    /// Makes the process non-dumpable before crashing, so that the server can
+    /// only ptrace it while the crash handler allows it to
+    #[clap(long)]
+    not_dumpable: bool,
+    /// Records `minidumper_test::BREADCRUMBS`, the last of them right before
+    /// raising the signal
+    #[clap(long)]
+    breadcrumbs: bool,
     /// Waits on a debugger to attach
     #[clap(long)]
     wait_on_debugger: bool,
@@ -42,15 +80,69 @@ fn real_main() -> anyhow::Result<()> {
         //     });
     }
 
-    let md_client = loop {
-        match minidumper::Client::with_name(&cmd.id) {
-            Ok(md_client) => break md_client,
-            Err(e) => {
-                if std::time::Instant::now() - start > connect_timeout {
-                    anyhow::bail!("timed out trying to connect to server process: {:#}", e);
-                }
+    let socket_name = match &cmd.socket_path {
+        Some(path) => minidumper::SocketName::Path(path),
+        None => minidumper::SocketName::from(&cmd.id),
+    };
+
+    let md_client = minidumper::Client::with_name_timeout(
+        socket_name,
+        connect_timeout.saturating_sub(start.elapsed()),
+        std::time::Duration::from_millis(10),
+    )
+    .map_err(|e| anyhow::anyhow!("timed out trying to connect to server process: {:#}", e))?;
+
+    if let (Some(rate), Some(duration)) = (cmd.message_rate, cmd.telemetry_ms) {
+        let interval = std::time::Duration::from_secs(1) / rate.max(1);
+        let end = std::time::Instant::now() + std::time::Duration::from_millis(duration);
+
+        let mut i = 0;
+        while std::time::Instant::now() < end {
+            md_client.send_message(
+                minidumper_test::TELEMETRY_KIND,
+                format!("telemetry {i} from {}", std::process::id()),
+            )?;
+            i += 1;
+
+            std::thread::sleep(interval);
+        }
+
+        println!("sent {i} telemetry messages");
+    }
+
+    let Some(signal) = cmd.signal else {
+        if let Some(messages) = cmd.messages {
+            for i in 0..messages {
+                md_client.send_message(i, format!("message {i} from {}", std::process::id()))?;
             }
+
+            // Messages are processed in order, so receiving the pong means the
+            // server has received all of our messages before we disconnect
+            md_client.ping()?;
+        }
+
+        for i in 0..cmd.pings.unwrap_or(0) {
+            if let Some(interval) = cmd.ping_interval_ms.filter(|_| i > 0) {
+                std::thread::sleep(std::time::Duration::from_millis(interval));
+            }
+
+            md_client.ping()?;
+        }
+
+        if let Some(quiet) = cmd.quiet_ms {
+            std::thread::sleep(std::time::Duration::from_millis(quiet));
         }
+
+        return Ok(());
+    };
+
+    let breadcrumbs = if cmd.breadcrumbs {
+        let breadcrumbs = md_client.create_breadcrumbs(16)?;
+        let (kind, data) = minidumper_test::BREADCRUMBS[0];
+        breadcrumbs.record(kind, data);
+        Some(breadcrumbs)
+    } else {
+        None
     };
 
     let _handler = crash_handler::CrashHandler::attach(unsafe {
@@ -60,9 +152,40 @@ fn real_main() -> anyhow::Result<()> {
         })
     });
 
-    let signal = cmd.signal;
+    if cmd.heap_sentinel {
+        // Large enough to be its own mapping, and far enough from any pointer
+        // on the stack that only a full memory dump includes the sentinel
+        let mut heap = vec![0u8; 1 << 20];
+        heap[1 << 19..][..minidumper_test::HEAP_SENTINEL.len()]
+            .copy_from_slice(minidumper_test::HEAP_SENTINEL);
+        std::mem::forget(heap);
+    }
+
+    if let Some(quiet) = cmd.quiet_ms {
+        std::thread::sleep(std::time::Duration::from_millis(quiet));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if cmd.not_dumpable {
+        // SAFETY: syscall
+        unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) };
+    }
 
     let raise_signal = move || {
+        // Lets the server measure how long it takes to handle the crash
+        println!(
+            "crash-time: {}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("system time is before the epoch")
+                .as_nanos()
+        );
+
+        if let Some(breadcrumbs) = &breadcrumbs {
+            let (kind, data) = minidumper_test::BREADCRUMBS[1];
+            breadcrumbs.record(kind, data);
+        }
+
         // SAFETY: we're about to intentionally crash ourselves via shenanigans,
         // none of this is safe
         unsafe {
@@ -70,6 +193,12 @@ fn real_main() -> anyhow::Result<()> {
                 Signal::Illegal => {
                     sadness_generator::raise_illegal_instruction();
                 }
+                Signal::IllegalCThread => {
+                    sadness_generator::SadnessFlavor::Illegal {
+                        native_thread: true,
+                    }
+                    .make_sad();
+                }
                 Signal::Trap => {
                     sadness_generator::raise_trap();
                 }
@@ -81,11 +210,49 @@ fn real_main() -> anyhow::Result<()> {
                     sadness_generator::raise_bus();
                 }
                 Signal::Fpe => {
+                    // Load a sentinel into a floating point register that is
+                    // otherwise unused, so that we can verify the floating
+                    // point state is captured in the minidump
+                    #[cfg(target_arch = "x86_64")]
+                    std::arch::asm!(
+                        "movq xmm15, {}",
+                        in(reg) minidumper_test::FPE_SENTINEL,
+                        out("xmm15") _,
+                    );
+                    #[cfg(target_arch = "aarch64")]
+                    std::arch::asm!(
+                        "fmov d31, {}",
+                        in(reg) minidumper_test::FPE_SENTINEL,
+                        out("v31") _,
+                    );
+
                     sadness_generator::raise_floating_point_exception();
                 }
+                Signal::DivideOverflow => {
+                    sadness_generator::raise_divide_overflow();
+                }
                 Signal::Segv => {
                     sadness_generator::raise_segfault();
                 }
+                Signal::SegvCThread => {
+                    sadness_generator::SadnessFlavor::Segfault {
+                        native_thread: true,
+                    }
+                    .make_sad();
+                }
+                Signal::WriteReadOnly => {
+                    sadness_generator::raise_write_read_only();
+                }
+                Signal::SmashedStack => {
+                    sadness_generator::raise_smashed_stack();
+                }
+                Signal::AtExit => {
+                    sadness_generator::raise_segfault_at_exit();
+                }
+                #[cfg(feature = "cpp")]
+                Signal::CppTerminate => {
+                    sadness_generator::raise_cpp_terminate();
+                }
                 Signal::StackOverflow => {
                     sadness_generator::raise_stack_overflow();
                 }
@@ -111,20 +278,50 @@ fn real_main() -> anyhow::Result<()> {
                 Signal::HeapCorruption => {
                     sadness_generator::raise_heap_corruption();
                 }
+                #[cfg(windows)]
+                Signal::TrapParameter => {
+                    sadness_generator::raise_trap_with_parameter(
+                        minidumper_test::BREAKPOINT_PARAMETER as usize,
+                    );
+                }
+                #[cfg(windows)]
+                Signal::DebugService => {
+                    sadness_generator::raise_debug_service(minidumper_test::BREAKPOINT_PARAMETER);
+                }
+                #[cfg(windows)]
+                Signal::CrtAbort => {
+                    sadness_generator::raise_crt_abort();
+                }
+                #[cfg(windows)]
+                Signal::FastFail => {
+                    sadness_generator::raise_fast_fail();
+                }
                 #[cfg(target_os = "macos")]
                 Signal::Guard => {
                     sadness_generator::raise_guard_exception();
                 }
+                #[cfg(target_os = "macos")]
+                Signal::ResourceFatal => {
+                    sadness_generator::raise_resource_cpu(true);
+                }
+                #[cfg(target_os = "macos")]
+                Signal::ResourceNonFatal => {
+                    sadness_generator::raise_resource_cpu(false);
+                }
             }
         }
     };
 
     let mut threads = Vec::new();
 
-    for _ in 0..10 {
-        threads.push(std::thread::spawn(move || {
-            std::thread::sleep(std::time::Duration::MAX);
-        }));
+    for i in 0..minidumper_test::SLEEPER_THREADS {
+        threads.push(
+            std::thread::Builder::new()
+                .name(format!("sleeper-{i}"))
+                .spawn(move || {
+                    std::thread::sleep(std::time::Duration::MAX);
+                })?,
+        );
     }
 
     if cmd.use_thread {
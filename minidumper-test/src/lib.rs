@@ -28,19 +28,39 @@ pub enum Signal {
     #[cfg(unix)]
     Bus,
     Fpe,
+    DivideOverflow,
     Illegal,
+    IllegalCThread,
     Segv,
+    SegvCThread,
     StackOverflow,
     StackOverflowCThread,
     Trap,
+    WriteReadOnly,
+    SmashedStack,
+    AtExit,
+    #[cfg(feature = "cpp")]
+    CppTerminate,
     #[cfg(windows)]
     Purecall,
     #[cfg(windows)]
     InvalidParameter,
     #[cfg(windows)]
     HeapCorruption,
+    #[cfg(windows)]
+    TrapParameter,
+    #[cfg(windows)]
+    DebugService,
+    #[cfg(windows)]
+    CrtAbort,
+    #[cfg(windows)]
+    FastFail,
     #[cfg(target_os = "macos")]
     Guard,
+    #[cfg(target_os = "macos")]
+    ResourceFatal,
+    #[cfg(target_os = "macos")]
+    ResourceNonFatal,
 }
 
 use std::fmt;
@@ -51,19 +71,39 @@ impl fmt::Display for Signal {
             #[cfg(unix)]
             Self::Bus => "bus",
             Self::Fpe => "fpe",
+            Self::DivideOverflow => "divide-overflow",
             Self::Illegal => "illegal",
+            Self::IllegalCThread => "illegal-c-thread",
             Self::Segv => "segv",
+            Self::SegvCThread => "segv-c-thread",
             Self::StackOverflow => "stack-overflow",
             Self::StackOverflowCThread => "stack-overflow-c-thread",
             Self::Trap => "trap",
+            Self::WriteReadOnly => "write-read-only",
+            Self::SmashedStack => "smashed-stack",
+            Self::AtExit => "at-exit",
+            #[cfg(feature = "cpp")]
+            Self::CppTerminate => "cpp-terminate",
             #[cfg(windows)]
             Self::Purecall => "purecall",
             #[cfg(windows)]
             Self::InvalidParameter => "invalid-parameter",
             #[cfg(windows)]
             Self::HeapCorruption => "heap-corruption",
+            #[cfg(windows)]
+            Self::TrapParameter => "trap-parameter",
+            #[cfg(windows)]
+            Self::DebugService => "debug-service",
+            #[cfg(windows)]
+            Self::CrtAbort => "crt-abort",
+            #[cfg(windows)]
+            Self::FastFail => "fast-fail",
             #[cfg(target_os = "macos")]
             Self::Guard => "guard",
+            #[cfg(target_os = "macos")]
+            Self::ResourceFatal => "resource-fatal",
+            #[cfg(target_os = "macos")]
+            Self::ResourceNonFatal => "resource-non-fatal",
         })
     }
 }
@@ -71,14 +111,34 @@ impl fmt::Display for Signal {
 use std::{
     path::PathBuf,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         mpsc, Arc, Mutex,
     },
 };
 
+/// Counts of the events the test server has observed
+#[derive(Default)]
+pub struct ServerStats {
+    pub connected: AtomicUsize,
+    pub disconnected: AtomicUsize,
+    pub messages: AtomicUsize,
+    /// The reason for every disconnect, in the order they occurred
+    pub disconnect_reasons: Mutex<Vec<minidumper::DisconnectReason>>,
+    /// The last time a minidump was written and closed
+    pub last_dump_written: Mutex<Option<std::time::SystemTime>>,
+    /// The in-memory contents of the last minidump written, if they were
+    /// provided, see [`minidumper::ContentsMode`]
+    pub last_dump_contents: Mutex<Option<Vec<u8>>>,
+    /// The details of the crash the last minidump was written for
+    pub last_dump_metadata: Mutex<Option<minidumper::DumpMetadata>>,
+    /// The warnings reported with the last minidump written
+    pub last_dump_warnings: Mutex<Vec<minidumper::DumpWarning>>,
+}
+
 pub struct Server {
     pub id: String,
     pub dump_rx: mpsc::Receiver<PathBuf>,
+    pub stats: Arc<ServerStats>,
     exit_run_loop: Arc<AtomicBool>,
     run_loop: Option<std::thread::JoinHandle<()>>,
 }
@@ -110,23 +170,133 @@ pub fn spinup_server(id: &str, dump_path: Option<PathBuf>) -> Server {
         }
     }
 
-    let mut server = minidumper::Server::with_name(id).expect("failed to start server");
+    spinup(
+        id,
+        Some(dump_path),
+        minidumper::DumpOptions::default(),
+        None,
+        None,
+    )
+}
+
+/// Spins up a server that writes minidumps with the specified options
+pub fn spinup_server_with_options(id: &str, dump_options: minidumper::DumpOptions) -> Server {
+    let dump_path = make_dump_path(id);
+    let _ = std::fs::remove_file(&dump_path);
+
+    spinup(id, Some(dump_path), dump_options, None, None)
+}
+
+/// Spins up a server that reaps client connections that haven't sent a
+/// message within the timeout
+pub fn spinup_server_with_stale_timeout(id: &str, stale_timeout: std::time::Duration) -> Server {
+    let dump_path = make_dump_path(id);
+    let _ = std::fs::remove_file(&dump_path);
+
+    spinup(
+        id,
+        Some(dump_path),
+        minidumper::DumpOptions::default(),
+        Some(stale_timeout),
+        None,
+    )
+}
+
+/// Spins up a server that waits for the delay before it starts writing each
+/// minidump, as if it were busy or slow
+pub fn spinup_server_with_delay(id: &str, delay: std::time::Duration) -> Server {
+    let dump_path = make_dump_path(id);
+    let _ = std::fs::remove_file(&dump_path);
+
+    spinup(
+        id,
+        Some(dump_path),
+        minidumper::DumpOptions::default(),
+        None,
+        Some(delay),
+    )
+}
+
+/// Spins up a server that can be connected to by multiple clients, each crash
+/// is written to its own dump file
+pub fn spinup_multi_client_server(id: &str) -> Server {
+    spinup(id, None, minidumper::DumpOptions::default(), None, None)
+}
+
+/// Spins up a server with the specified socket name, rather than the one `id`
+/// is interpreted as, its minidump is still named after `id`
+pub fn spinup_server_with_name(id: &str, name: &minidumper::OwnedSocketName) -> Server {
+    let dump_path = make_dump_path(id);
+    let _ = std::fs::remove_file(&dump_path);
+
+    spinup_named(
+        id,
+        name.as_socket_name(),
+        Some(dump_path),
+        minidumper::DumpOptions::default(),
+        None,
+        None,
+    )
+}
+
+fn spinup(
+    id: &str,
+    dump_path: Option<PathBuf>,
+    dump_options: minidumper::DumpOptions,
+    stale_timeout: Option<std::time::Duration>,
+    write_delay: Option<std::time::Duration>,
+) -> Server {
+    spinup_named(
+        id,
+        id.into(),
+        dump_path,
+        dump_options,
+        stale_timeout,
+        write_delay,
+    )
+}
+
+fn spinup_named(
+    id: &str,
+    name: minidumper::SocketName<'_>,
+    dump_path: Option<PathBuf>,
+    dump_options: minidumper::DumpOptions,
+    stale_timeout: Option<std::time::Duration>,
+    write_delay: Option<std::time::Duration>,
+) -> Server {
+    let mut server = minidumper::Server::with_name(name).expect("failed to start server");
 
     struct Inner {
-        _id: String,
+        id: String,
         dump_tx: Mutex<mpsc::Sender<PathBuf>>,
-        dump_path: PathBuf,
+        /// The path to write the dump to, if `None`, each dump is written to
+        /// a unique path
+        dump_path: Option<PathBuf>,
+        dump_count: AtomicUsize,
+        dump_options: minidumper::DumpOptions,
+        stats: Arc<ServerStats>,
+        /// How long to wait before each minidump is written
+        write_delay: Option<std::time::Duration>,
     }
 
     impl minidumper::ServerHandler for Inner {
         fn create_minidump_file(&self) -> Result<(std::fs::File, PathBuf), std::io::Error> {
-            if !self.dump_path.parent().unwrap().exists() {
-                let _ = std::fs::create_dir_all(self.dump_path.parent().unwrap());
+            if let Some(delay) = self.write_delay {
+                std::thread::sleep(delay);
             }
 
-            let file = std::fs::File::create(&self.dump_path)?;
+            let dump_path = self.dump_path.clone().unwrap_or_else(|| {
+                let count = self.dump_count.fetch_add(1, Ordering::Relaxed);
+                make_dump_path(&format!("{}-{count}", self.id))
+            });
+
+            if !dump_path.parent().unwrap().exists() {
+                let _ = std::fs::create_dir_all(dump_path.parent().unwrap());
+            }
 
-            Ok((file, self.dump_path.clone()))
+            let file = std::fs::File::create(&dump_path)?;
+
+            Ok((file, dump_path))
         }
 
         fn on_minidump_created(
@@ -138,6 +308,28 @@ pub fn spinup_server(id: &str, dump_path: Option<PathBuf>) -> Server {
                 .file
                 .sync_all()
                 .expect("failed to flush minidump file");
+            drop(md_bin.file);
+
+            *self
+                .stats
+                .last_dump_metadata
+                .lock()
+                .expect("unable to acquire lock") = Some(md_bin.metadata);
+            *self
+                .stats
+                .last_dump_contents
+                .lock()
+                .expect("unable to acquire lock") = md_bin.contents;
+            *self
+                .stats
+                .last_dump_warnings
+                .lock()
+                .expect("unable to acquire lock") = md_bin.dump_warnings;
+            *self
+                .stats
+                .last_dump_written
+                .lock()
+                .expect("unable to acquire lock") = Some(std::time::SystemTime::now());
 
             self.dump_tx
                 .lock()
@@ -148,17 +340,54 @@ pub fn spinup_server(id: &str, dump_path: Option<PathBuf>) -> Server {
             minidumper::LoopAction::Continue
         }
 
+        fn dump_options(
+            &self,
+            _crash_context: &crash_handler::CrashContext,
+        ) -> minidumper::DumpOptions {
+            self.dump_options.clone()
+        }
+
         fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
-            unreachable!("we only test crashes");
+            self.stats.messages.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn on_client_connected(
+            &self,
+            _client: minidumper::ClientId,
+            _peer: minidumper::PeerCredentials,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.stats.connected.fetch_add(1, Ordering::Relaxed);
+            minidumper::LoopAction::Continue
+        }
+
+        fn on_client_disconnected(
+            &self,
+            _client: minidumper::ClientId,
+            reason: minidumper::DisconnectReason,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.stats
+                .disconnect_reasons
+                .lock()
+                .expect("unable to acquire lock")
+                .push(reason);
+            self.stats.disconnected.fetch_add(1, Ordering::Relaxed);
+            minidumper::LoopAction::Continue
         }
     }
 
     let (tx, rx) = mpsc::channel();
+    let stats = Arc::new(ServerStats::default());
 
     let inner = Inner {
-        _id: id.to_owned(),
+        id: id.to_owned(),
         dump_tx: Mutex::new(tx),
         dump_path,
+        dump_count: AtomicUsize::new(0),
+        dump_options,
+        stats: stats.clone(),
+        write_delay,
     };
 
     let exit = Arc::new(AtomicBool::new(false));
@@ -166,24 +395,80 @@ pub fn spinup_server(id: &str, dump_path: Option<PathBuf>) -> Server {
 
     let run_loop = std::thread::spawn(move || {
         server
-            .run(Box::new(inner), &exit, None)
+            .run(Box::new(inner), &exit, stale_timeout)
             .expect("failed to run server loop");
     });
 
     Server {
         id: id.to_owned(),
         dump_rx: rx,
+        stats,
         exit_run_loop,
         run_loop: Some(run_loop),
     }
 }
 
-pub fn run_client(id: &str, signal: Signal, use_thread: bool) {
-    use std::env;
+pub fn run_client(id: &str, signal: Signal, use_thread: bool) -> std::process::Output {
+    run_client_with_args(id, signal, if use_thread { &["--use-thread"] } else { &[] })
+}
+
+/// Runs a client that crashes with the signal, passing it the extra arguments
+pub fn run_client_with_args(id: &str, signal: Signal, extra: &[&str]) -> std::process::Output {
+    let signal = signal.to_string();
+    let mut args = vec!["--signal", &signal];
+    args.extend_from_slice(extra);
+
+    let output = exec_client(id, &args);
+
+    // Ensure it was interrupted and did not exit properly
+    #[cfg(unix)]
+    assert!(output.status.code().is_none());
+    #[cfg(windows)]
+    {
+        // TODO: check that the status code matches the underlying error value
+        println!("client exited with {:?}", output.status.code());
+    }
+
+    output
+}
+
+/// Runs a client that pings the server `pings` times, waiting `ping_interval`
+/// between each, then stays connected without sending anything for `quiet`
+/// before exiting normally
+pub fn run_quiet_client(
+    id: &str,
+    pings: u32,
+    ping_interval: std::time::Duration,
+    quiet: std::time::Duration,
+) -> std::process::Output {
+    let output = exec_client(
+        id,
+        &[
+            "--pings",
+            &pings.to_string(),
+            "--ping-interval-ms",
+            &ping_interval.as_millis().to_string(),
+            "--quiet-ms",
+            &quiet.as_millis().to_string(),
+        ],
+    );
+
+    assert!(
+        output.status.success(),
+        "client exited with {:?}",
+        output.status
+    );
+
+    output
+}
 
+/// Creates the command for running a `crash-client` that connects to the
+/// server with the specified id, which is located next to the current
+/// executable
+pub fn crash_client_command(id: &str) -> std::process::Command {
     // Adapted from
     // https://github.com/rust-lang/cargo/blob/485670b3983b52289a2f353d589c57fae2f60f82/tests/testsuite/support/mod.rs#L507
-    let mut cmd_path = env::current_exe().expect("failed to get exe path");
+    let mut cmd_path = std::env::current_exe().expect("failed to get exe path");
     cmd_path.pop();
     if cmd_path.ends_with("deps") {
         cmd_path.pop();
@@ -196,14 +481,17 @@ pub fn run_client(id: &str, signal: Signal, use_thread: bool) {
 
     println!("running client: {}", cmd_path.display());
     let mut cmd = std::process::Command::new(&cmd_path);
+    cmd.args(["--id", id]);
+    cmd
+}
+
+fn exec_client(id: &str, args: &[&str]) -> std::process::Output {
+    let mut cmd = crash_client_command(id);
     cmd.stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped());
-    cmd.args(["--id", id, "--signal", &signal.to_string()]);
-    if use_thread {
-        cmd.arg("--use-thread");
-    }
+    cmd.args(args);
 
-    let wait_for_debugger = env::var("DEBUG").is_ok();
+    let wait_for_debugger = std::env::var("DEBUG").is_ok();
     if wait_for_debugger {
         cmd.arg("--wait-on-debugger");
     }
@@ -217,14 +505,7 @@ pub fn run_client(id: &str, signal: Signal, use_thread: bool) {
     println!("{}", stdout);
     eprintln!("{}", stderr);
 
-    // Ensure it was interrupted and did not exit properly
-    #[cfg(unix)]
-    assert!(output.status.code().is_none());
-    #[cfg(windows)]
-    {
-        // TODO: check that the status code matches the underlying error value
-        println!("client exited with {:?}", output.status.code());
-    }
+    output
 }
 
 #[inline]
@@ -264,6 +545,217 @@ pub fn generate_minidump(
     }
 }
 
+/// The time it took to handle a single crash
+pub struct CrashTiming {
+    /// The wall clock time from the client raising the signal/exception until
+    /// the server closed the minidump file
+    pub latency: std::time::Duration,
+    /// The size of the minidump, in bytes
+    pub dump_size: u64,
+}
+
+/// Crashes a client with the specified signal/exception, measuring how long
+/// it takes until the minidump has been written
+pub fn time_minidump(id: &str, signal: Signal, use_thread: bool) -> CrashTiming {
+    capture_output();
+
+    let server = spinup_server(id, None);
+    let output = run_client(id, signal, use_thread);
+
+    let dump_path = server
+        .dump_rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .expect("failed to receive dump path");
+
+    let written = server
+        .stats
+        .last_dump_written
+        .lock()
+        .expect("unable to acquire lock")
+        .expect("dump was received but not timestamped");
+
+    // The client prints the time immediately before it raises the signal/exception
+    let crashed = std::str::from_utf8(&output.stdout)
+        .expect("invalid stdout")
+        .lines()
+        .find_map(|line| line.strip_prefix("crash-time: "))
+        .and_then(|nanos| nanos.parse().ok())
+        .map(|nanos| std::time::UNIX_EPOCH + std::time::Duration::from_nanos(nanos))
+        .expect("client didn't report when it crashed");
+
+    CrashTiming {
+        latency: written
+            .duration_since(crashed)
+            .expect("minidump was written before the crash"),
+        dump_size: std::fs::metadata(&dump_path)
+            .expect("failed to read minidump metadata")
+            .len(),
+    }
+}
+
+/// Runs a client with a signal/exception that is not fatal, ensuring that the
+/// client exits normally and that no minidump is generated
+pub fn run_no_crash_test(signal: Signal, timeout: std::time::Duration) {
+    capture_output();
+
+    let id = format!("{signal}-0-no-crash");
+    let server = spinup_server(&id, None);
+    let output = exec_client(&id, &["--signal", &signal.to_string()]);
+
+    assert!(
+        output.status.success(),
+        "client exited with {:?}",
+        output.status
+    );
+    assert!(
+        server.dump_rx.recv_timeout(timeout).is_err(),
+        "a minidump was generated for a non-fatal exception"
+    );
+}
+
+/// Runs a client with a signal/exception that terminates it before it can
+/// request a minidump, ensuring that the client exits with the specified code
+/// and that no minidump is generated
+pub fn run_uncatchable_test(signal: Signal, exit_code: u32, timeout: std::time::Duration) {
+    capture_output();
+
+    let id = format!("{signal}-0-uncatchable");
+    let server = spinup_server(&id, None);
+    let output = exec_client(&id, &["--signal", &signal.to_string()]);
+
+    assert_eq!(
+        output.status.code(),
+        Some(exit_code as i32),
+        "client exited with {:?}",
+        output.status
+    );
+    assert!(
+        server.dump_rx.recv_timeout(timeout).is_err(),
+        "a minidump was generated for an uncatchable exception"
+    );
+}
+
+/// A client spawned as part of [`run_multi_client_test`]
+#[derive(Clone, Copy)]
+pub enum ClientKind {
+    /// Crashes with the specified signal/exception
+    Crash(Signal),
+    /// Sends the specified number of user messages, then exits normally
+    Messages(u32),
+    /// Pings the server the specified number of times, then exits normally
+    Ping(u32),
+}
+
+/// Spins up a single server, then runs all of the specified clients
+/// concurrently against it, ensuring that every crash produces its own
+/// minidump, and that the clients that don't crash are unaffected
+///
+/// Since the server has no way to know which client a crash came from, each
+/// crashing client must use a signal that produces a distinct crash reason so
+/// that every minidump can be attributed to exactly one client
+pub fn run_multi_client_test(id: &str, clients: &[ClientKind]) {
+    capture_output();
+
+    let server = spinup_multi_client_server(id);
+
+    std::thread::scope(|s| {
+        for client in clients {
+            s.spawn(move || match *client {
+                ClientKind::Crash(signal) => {
+                    run_client(id, signal, false);
+                }
+                ClientKind::Messages(count) => {
+                    let output = exec_client(id, &["--messages", &count.to_string()]);
+                    assert!(
+                        output.status.success(),
+                        "client exited with {:?}",
+                        output.status
+                    );
+                }
+                ClientKind::Ping(count) => {
+                    let output = exec_client(id, &["--pings", &count.to_string()]);
+                    assert!(
+                        output.status.success(),
+                        "client exited with {:?}",
+                        output.status
+                    );
+                }
+            });
+        }
+    });
+
+    let mut crashes: Vec<_> = clients
+        .iter()
+        .filter_map(|client| match client {
+            ClientKind::Crash(signal) => Some(*signal),
+            _ => None,
+        })
+        .collect();
+
+    for _ in 0..crashes.len() {
+        let dump_path = server
+            .dump_rx
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .expect("failed to receive dump path");
+
+        let md = std::fs::read(&dump_path).unwrap_or_else(|e| {
+            panic!(
+                "failed to read minidump from {}: {}",
+                dump_path.display(),
+                e
+            )
+        });
+
+        // Find the crashing client this minidump belongs to
+        let index = crashes
+            .iter()
+            .position(|signal| std::panic::catch_unwind(|| assert_minidump(&md, *signal)).is_ok())
+            .unwrap_or_else(|| {
+                panic!(
+                    "minidump {} did not match any crashing client",
+                    dump_path.display()
+                )
+            });
+
+        crashes.swap_remove(index);
+    }
+
+    assert!(
+        server.dump_rx.try_recv().is_err(),
+        "received more minidumps than there were crashing clients"
+    );
+
+    let expected_messages: usize = clients
+        .iter()
+        .map(|client| match client {
+            ClientKind::Messages(count) => *count as usize,
+            _ => 0,
+        })
+        .sum();
+
+    // All of the clients have exited, but the server may not have observed
+    // every disconnect yet
+    let start = std::time::Instant::now();
+    while server.stats.disconnected.load(Ordering::Relaxed) < clients.len()
+        && start.elapsed() < std::time::Duration::from_secs(5)
+    {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    assert_eq!(
+        server.stats.connected.load(Ordering::Relaxed),
+        clients.len()
+    );
+    assert_eq!(
+        server.stats.disconnected.load(Ordering::Relaxed),
+        clients.len()
+    );
+    assert_eq!(
+        server.stats.messages.load(Ordering::Relaxed),
+        expected_messages
+    );
+}
+
 pub use minidump::system_info::{Cpu, Os};
 
 #[inline]
@@ -327,6 +819,14 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                     _
                 ));
             }
+            // std::terminate calls abort
+            #[cfg(feature = "cpp")]
+            Signal::CppTerminate => {
+                verify!(CrashReason::LinuxGeneral(
+                    errors::ExceptionCodeLinux::SIGABRT,
+                    _
+                ));
+            }
             #[cfg(unix)]
             Signal::Bus => {
                 verify!(CrashReason::LinuxSigbus(
@@ -334,16 +834,46 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                 ));
             }
             Signal::Fpe => {
-                verify!(CrashReason::LinuxSigfpe(
-                    errors::ExceptionCodeLinuxSigfpeKind::FPE_INTDIV
-                ));
+                if sadness_generator::hardware_divide_by_zero_available() {
+                    // x86 traps on integer divide by zero, while aarch64 can
+                    // only trap on floating point divide by zero
+                    verify!(CrashReason::LinuxSigfpe(
+                        errors::ExceptionCodeLinuxSigfpeKind::FPE_INTDIV
+                            | errors::ExceptionCodeLinuxSigfpeKind::FPE_FLTDIV
+                    ));
+                } else {
+                    // The signal was explicitly raised, so the code will be
+                    // SI_TKILL rather than an actual arithmetic fault
+                    verify!(
+                        CrashReason::LinuxSigfpe(_)
+                            | CrashReason::LinuxGeneral(errors::ExceptionCodeLinux::SIGFPE, _)
+                    );
+                }
+            }
+            Signal::DivideOverflow => {
+                cfg_if::cfg_if! {
+                    if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+                        // The kernel reports every divide error as FPE_INTDIV,
+                        // it doesn't check if the divisor was actually 0
+                        verify!(CrashReason::LinuxSigfpe(
+                            errors::ExceptionCodeLinuxSigfpeKind::FPE_INTDIV
+                        ));
+                    } else {
+                        // ARM doesn't trap, the signal is queued with the code
+                        // the hardware would have reported
+                        verify!(CrashReason::LinuxSigfpe(
+                            errors::ExceptionCodeLinuxSigfpeKind::FPE_INTOVF
+                        ));
+                    }
+                }
             }
-            Signal::Illegal => {
+            Signal::Illegal | Signal::IllegalCThread => {
                 verify!(CrashReason::LinuxSigill(
                     errors::ExceptionCodeLinuxSigillKind::ILL_ILLOPN
                 ));
             }
-            Signal::Segv => {
+            // The crash is a plain segfault, just raised during teardown
+            Signal::Segv | Signal::SegvCThread | Signal::AtExit => {
                 verify!(CrashReason::LinuxSigsegv(
                     errors::ExceptionCodeLinuxSigsegvKind::SEGV_MAPERR
                 ));
@@ -365,12 +895,38 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                     _
                 ));
             }
+            Signal::WriteReadOnly => {
+                // Unlike a plain segfault, the address is mapped, we just don't
+                // have permission to write to it
+                verify!(CrashReason::LinuxSigsegv(
+                    errors::ExceptionCodeLinuxSigsegvKind::SEGV_ACCERR
+                ));
+            }
+            Signal::SmashedStack => {
+                // On 32-bit targets the garbage address is in the kernel's half
+                // of the address space, which is reported as either
+                verify!(CrashReason::LinuxSigsegv(
+                    errors::ExceptionCodeLinuxSigsegvKind::SEGV_MAPERR
+                        | errors::ExceptionCodeLinuxSigsegvKind::SEGV_ACCERR
+                ));
+
+                assert_eq!(
+                    crash_address,
+                    sadness_generator::SMASHED_RETURN_ADDRESS as _
+                );
+            }
             #[cfg(windows)]
-            Signal::Purecall | Signal::InvalidParameter | Signal::HeapCorruption => {
+            Signal::Purecall
+            | Signal::InvalidParameter
+            | Signal::HeapCorruption
+            | Signal::TrapParameter
+            | Signal::DebugService
+            | Signal::CrtAbort
+            | Signal::FastFail => {
                 unreachable!("windows only");
             }
             #[cfg(target_os = "macos")]
-            Signal::Guard => {
+            Signal::Guard | Signal::ResourceFatal | Signal::ResourceNonFatal => {
                 unreachable!("macos only");
             }
         },
@@ -378,17 +934,27 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
             Signal::Abort => {
                 assert_eq!(crash_reason, CrashReason::from_windows_code(0x40000015));
             }
+            #[cfg(feature = "cpp")]
+            Signal::CppTerminate => {
+                assert_eq!(crash_reason, CrashReason::from_windows_code(0x40000015));
+            }
             Signal::Fpe => {
                 verify!(CrashReason::WindowsGeneral(
                     errors::ExceptionCodeWindows::EXCEPTION_INT_DIVIDE_BY_ZERO
                 ));
             }
-            Signal::Illegal => {
+            // Unlike Linux, Windows checks the divisor of the faulting divide
+            Signal::DivideOverflow => {
+                verify!(CrashReason::WindowsGeneral(
+                    errors::ExceptionCodeWindows::EXCEPTION_INT_OVERFLOW
+                ));
+            }
+            Signal::Illegal | Signal::IllegalCThread => {
                 verify!(CrashReason::WindowsGeneral(
                     errors::ExceptionCodeWindows::EXCEPTION_ILLEGAL_INSTRUCTION
                 ));
             }
-            Signal::Segv => {
+            Signal::Segv | Signal::SegvCThread | Signal::AtExit => {
                 verify!(CrashReason::WindowsAccessViolation(
                     errors::ExceptionCodeWindowsAccessType::WRITE
                 ));
@@ -405,6 +971,27 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                     errors::ExceptionCodeWindows::EXCEPTION_BREAKPOINT
                 ));
             }
+            Signal::WriteReadOnly => {
+                verify!(CrashReason::WindowsAccessViolation(
+                    errors::ExceptionCodeWindowsAccessType::WRITE
+                ));
+
+                // The read-only static lives in the client's image, so we
+                // can't know its exact address, but it is definitely not
+                // the unmapped address used for plain segfaults
+                assert_ne!(crash_address, 0);
+                assert_ne!(crash_address, sadness_generator::SEGFAULT_ADDRESS as _);
+            }
+            Signal::SmashedStack => {
+                verify!(CrashReason::WindowsAccessViolation(
+                    errors::ExceptionCodeWindowsAccessType::EXEC
+                ));
+
+                assert_eq!(
+                    crash_address,
+                    sadness_generator::SMASHED_RETURN_ADDRESS as _
+                );
+            }
             #[cfg(windows)]
             Signal::Purecall => {
                 assert_eq!(crash_reason, CrashReason::from_windows_code(0xc0000025));
@@ -417,12 +1004,33 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
             Signal::HeapCorruption => {
                 assert_eq!(crash_reason, CrashReason::from_windows_error(0xc0000374));
             }
+            // The CRT's abort raises SIGABRT, which is caught by the abort
+            // signal handler, same as Signal::Abort
+            #[cfg(windows)]
+            Signal::CrtAbort => {
+                assert_eq!(crash_reason, CrashReason::from_windows_code(0x40000015));
+            }
+            #[cfg(windows)]
+            Signal::FastFail => {
+                unreachable!("fastfail terminates the process before a minidump can be requested");
+            }
+            #[cfg(windows)]
+            Signal::TrapParameter | Signal::DebugService => {
+                verify!(CrashReason::WindowsGeneral(
+                    errors::ExceptionCodeWindows::EXCEPTION_BREAKPOINT
+                ));
+
+                // The parameter distinguishes the breakpoint from a plain int3
+                let record = &exc.raw.exception_record;
+                assert!(record.number_parameters >= 1);
+                assert_eq!(record.exception_information[0], BREAKPOINT_PARAMETER as u64);
+            }
             #[cfg(unix)]
             Signal::Bus => {
                 unreachable!();
             }
             #[cfg(target_os = "macos")]
-            Signal::Guard => {
+            Signal::Guard | Signal::ResourceFatal | Signal::ResourceNonFatal => {
                 unreachable!("macos only");
             }
         },
@@ -434,6 +1042,13 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                     0x10003, // EXC_SOFT_SIGNAL
                 ));
             }
+            #[cfg(feature = "cpp")]
+            Signal::CppTerminate => {
+                verify!(CrashReason::MacGeneral(
+                    errors::ExceptionCodeMac::EXC_SOFTWARE,
+                    0x10003, // EXC_SOFT_SIGNAL
+                ));
+            }
             #[cfg(unix)]
             Signal::Bus => {
                 verify!(CrashReason::MacBadAccessKern(
@@ -456,7 +1071,23 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                     }
                 }
             }
-            Signal::Illegal => {
+            Signal::DivideOverflow => {
+                cfg_if::cfg_if! {
+                    if #[cfg(target_arch = "aarch64")] {
+                        unreachable!("the signal is raised explicitly, which isn't reported to the exception port");
+                    } else if #[cfg(target_arch = "x86_64")] {
+                        // The divide error is the same trap as for a divide by
+                        // 0, EXC_I386_INTO is only raised by the `into`
+                        // instruction, which doesn't exist in 64-bit mode
+                        verify!(CrashReason::MacArithmeticX86(
+                            errors::ExceptionCodeMacArithmeticX86Type::EXC_I386_DIV,
+                        ));
+                    } else {
+                        panic!("this target architecture is not supported on mac");
+                    }
+                }
+            }
+            Signal::Illegal | Signal::IllegalCThread => {
                 cfg_if::cfg_if! {
                     if #[cfg(target_arch = "aarch64")] {
                         verify!(CrashReason::MacBadInstructionArm(
@@ -471,7 +1102,7 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                     }
                 }
             }
-            Signal::Segv => {
+            Signal::Segv | Signal::SegvCThread | Signal::AtExit => {
                 verify!(CrashReason::MacBadAccessKern(
                     errors::ExceptionCodeMacBadAccessKernType::KERN_INVALID_ADDRESS,
                 ));
@@ -498,6 +1129,23 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                     }
                 }
             }
+            Signal::WriteReadOnly => {
+                verify!(CrashReason::MacBadAccessKern(
+                    errors::ExceptionCodeMacBadAccessKernType::KERN_PROTECTION_FAILURE
+                ));
+
+                assert_ne!(crash_address, sadness_generator::SEGFAULT_ADDRESS as _);
+            }
+            Signal::SmashedStack => {
+                verify!(CrashReason::MacBadAccessKern(
+                    errors::ExceptionCodeMacBadAccessKernType::KERN_INVALID_ADDRESS,
+                ));
+
+                assert_eq!(
+                    crash_address,
+                    sadness_generator::SMASHED_RETURN_ADDRESS as _
+                );
+            }
             #[cfg(target_os = "macos")]
             Signal::Guard => {
                 // Unfortunately the exception code which contains the details
@@ -522,13 +1170,327 @@ pub fn assert_minidump(md_buf: &[u8], signal: Signal) {
                     panic!("expected MacGuard crash, crash reason: {:?}", crash_reason);
                 }
             }
+            #[cfg(target_os = "macos")]
+            Signal::ResourceFatal => {
+                // Same as EXC_GUARD, the details of the EXC_RESOURCE exception
+                // are bit packed into the code
+                if let CrashReason::MacResource(kind, code, _subcode) = crash_reason {
+                    // We've exceeded the CPU usage limit
+                    assert_eq!(
+                        kind,
+                        errors::ExceptionCodeMacResourceType::RESOURCE_TYPE_CPU
+                    );
+
+                    // +-----------------+----------------+-----------------+
+                    // |[63:61] resource | [60:58] flavor | [57:0] details  |
+                    // +-----------------+----------------+-----------------+
+                    assert_eq!(
+                        errors::ExceptionCodeMacResourceType::RESOURCE_TYPE_CPU as u8,
+                        ((code >> 61) & 0x7) as u8
+                    );
+                    assert_eq!(
+                        2, /* FLAVOR_CPU_MONITOR_FATAL */
+                        ((code >> 58) & 0x7) as u8
+                    );
+                } else {
+                    panic!(
+                        "expected MacResource crash, crash reason: {:?}",
+                        crash_reason
+                    );
+                }
+            }
+            #[cfg(target_os = "macos")]
+            Signal::ResourceNonFatal => {
+                unreachable!("non-fatal resource exceptions don't produce a minidump");
+            }
             #[cfg(windows)]
-            Signal::Purecall | Signal::InvalidParameter | Signal::HeapCorruption => {
+            Signal::Purecall
+            | Signal::InvalidParameter
+            | Signal::HeapCorruption
+            | Signal::TrapParameter
+            | Signal::DebugService
+            | Signal::CrtAbort
+            | Signal::FastFail => {
                 unreachable!("windows only");
             }
         },
         _ => unreachable!("apparently we are targeting a new OS"),
     }
+
+    assert_crash_context(&md, &exc, signal, crash_address);
+    assert_threads(&md);
+}
+
+/// The number of threads, named `sleeper-<n>`, that the client spawns before
+/// raising its signal, so that the dump has more than the crashing thread
+pub const SLEEPER_THREADS: usize = 10;
+
+/// Asserts that the sleeper threads spawned by the client are in the dump
+fn assert_threads(md: &minidump::Minidump<'_, &[u8]>) {
+    let threads: minidump::MinidumpThreadList<'_> =
+        md.get_stream().expect("unable to find thread list stream");
+
+    // The main thread, plus the sleepers, plus any threads the OS or runtime
+    // started on its own, or that the client raised the signal on
+    assert!(
+        threads.threads.len() > SLEEPER_THREADS,
+        "expected more than {SLEEPER_THREADS} threads, found {}",
+        threads.threads.len()
+    );
+
+    // minidump-writer doesn't write thread names on every platform
+    let names = match md.get_stream::<minidump::MinidumpThreadNames>() {
+        Ok(names) => names,
+        Err(_) => return,
+    };
+
+    let mut sleepers: Vec<_> = threads
+        .threads
+        .iter()
+        .filter_map(|thread| {
+            let name = names.get_name(thread.raw.thread_id)?;
+            name.strip_prefix("sleeper-")?.parse::<usize>().ok()
+        })
+        .collect();
+    sleepers.sort_unstable();
+
+    assert_eq!(
+        sleepers,
+        (0..SLEEPER_THREADS).collect::<Vec<_>>(),
+        "the dump doesn't have the sleeper threads' names"
+    );
+}
+
+/// Asserts that the memory needed to make sense of a segfault was captured,
+/// ie. the code around the instruction pointer, and that the faulting address
+/// is recorded as mapped, or not, as it was in the crashed process
+fn assert_fault_memory(
+    md: &minidump::Minidump<'_, &[u8]>,
+    signal: Signal,
+    ip: u64,
+    crash_address: u64,
+) {
+    let memory: minidump::MinidumpMemoryList<'_> =
+        md.get_stream().expect("unable to find memory list stream");
+
+    // Nothing is mapped at the address, so there is nothing to capture
+    if !matches!(signal, Signal::WriteReadOnly) {
+        assert!(
+            memory
+                .memory_at_address(sadness_generator::SEGFAULT_ADDRESS as _)
+                .is_none(),
+            "the unmapped address 0x{:x} is in the memory list",
+            sadness_generator::SEGFAULT_ADDRESS
+        );
+    }
+
+    match get_native_os() {
+        Os::Linux => {
+            // minidump-writer captures the bytes around the instruction pointer
+            // of the crashing thread in addition to its stack
+            let code = memory.memory_at_address(ip).unwrap_or_else(|| {
+                panic!("instruction pointer 0x{ip:x} is not in the memory list")
+            });
+            assert_eq!(code.bytes.len() as u64, code.size);
+
+            let maps: minidump::MinidumpLinuxMaps<'_> =
+                md.get_stream().expect("unable to find linux maps stream");
+            let ip_map = maps
+                .memory_info_at_address(ip)
+                .unwrap_or_else(|| panic!("instruction pointer 0x{ip:x} is not mapped"));
+            assert!(ip_map.is_executable());
+
+            if matches!(signal, Signal::WriteReadOnly) {
+                let target = maps
+                    .memory_info_at_address(crash_address)
+                    .unwrap_or_else(|| panic!("crash address 0x{crash_address:x} is not mapped"));
+                assert!(
+                    target.is_readable() && !target.is_writable(),
+                    "crash address 0x{crash_address:x} is not read-only"
+                );
+            } else {
+                assert!(
+                    maps.memory_info_at_address(sadness_generator::SEGFAULT_ADDRESS as _)
+                        .is_none(),
+                    "0x{:x} was mapped in the crashed process",
+                    sadness_generator::SEGFAULT_ADDRESS
+                );
+            }
+        }
+        Os::Windows => {
+            // The code is only in the memory list if it is near the stack, as
+            // MiniDumpWriteDump leaves it to be read from the module instead,
+            // which assert_crash_context has already checked the ip is in.
+            // The memory info list is only written for full memory dumps
+            let infos = match md.get_stream::<minidump::MinidumpMemoryInfoList<'_>>() {
+                Ok(infos) => infos,
+                Err(_) => return,
+            };
+
+            let target = infos.memory_info_at_address(crash_address);
+            if matches!(signal, Signal::WriteReadOnly) {
+                let target = target.unwrap_or_else(|| {
+                    panic!("crash address 0x{crash_address:x} is not in the memory info list")
+                });
+                assert!(
+                    target.is_readable() && !target.is_writable(),
+                    "crash address 0x{crash_address:x} is not read-only"
+                );
+            } else if let Some(target) = target {
+                assert!(
+                    !target.is_readable(),
+                    "crash address 0x{crash_address:x} was accessible in the crashed process"
+                );
+            }
+        }
+        // minidump-writer doesn't write a memory info stream on Macos, and the
+        // code is read from the module like on Windows
+        _ => {}
+    }
+}
+
+/// The parameter of the breakpoint raised by the client for
+/// [`Signal::TrapParameter`], and the service code for [`Signal::DebugService`]
+pub const BREAKPOINT_PARAMETER: u32 = 0x5ad;
+
+/// The value loaded into a floating point register by the client before it
+/// raises [`Signal::Fpe`], to ensure the floating point state is captured
+pub const FPE_SENTINEL: u64 = 0x5ad5_5ad5_5ad5_5ad5;
+
+/// Written to a large heap allocation by the client before it crashes, when
+/// run with `--heap-sentinel`, so that the memory that is only written to full
+/// memory minidumps can be found in them
+pub const HEAP_SENTINEL: &[u8] = b"minidumper-test heap sentinel 5ad";
+
+/// The kind of the user messages sent by the client when run with
+/// `--message-rate`
+pub const TELEMETRY_KIND: u32 = 0x5ad;
+
+/// The breadcrumbs recorded by the client when run with `--breadcrumbs`, the
+/// last of them right before it raises the signal, as their kind and data
+pub const BREADCRUMBS: &[(u32, &str)] = &[(1, "client started"), (2, "raising signal")];
+
+/// Asserts the register state captured for the crashing thread is sane
+fn assert_crash_context(
+    md: &minidump::Minidump<'_, &[u8]>,
+    exc: &minidump::MinidumpException<'_>,
+    signal: Signal,
+    crash_address: u64,
+) {
+    use minidump::Module;
+
+    let system_info: minidump::MinidumpSystemInfo =
+        md.get_stream().expect("unable to find system info stream");
+    let misc_info = md.get_stream::<minidump::MinidumpMiscInfo>().ok();
+
+    let context = exc
+        .context(&system_info, misc_info.as_ref())
+        .expect("unable to find exception thread context");
+
+    let ip = context.get_instruction_pointer();
+    let sp = context.get_stack_pointer();
+
+    let modules: minidump::MinidumpModuleList =
+        md.get_stream().expect("unable to find module list stream");
+    let module = modules.module_at_address(ip);
+
+    // The return address was overwritten, so the crash happened on the fetch
+    // of the garbage instruction, which isn't in any module
+    if matches!(signal, Signal::SmashedStack) {
+        assert_eq!(
+            ip,
+            sadness_generator::SMASHED_RETURN_ADDRESS as u64,
+            "instruction pointer 0x{ip:x} is not the smashed return address"
+        );
+    } else {
+        assert!(
+            module.is_some(),
+            "instruction pointer 0x{ip:x} is not in a mapped module"
+        );
+    }
+
+    // These are raised by an instruction in sadness-generator, which is
+    // statically linked into the client, the rest are raised from within a
+    // system library or asynchronously
+    let raised_in_client = match signal {
+        Signal::Illegal
+        | Signal::IllegalCThread
+        | Signal::Segv
+        | Signal::SegvCThread
+        | Signal::AtExit
+        | Signal::StackOverflow
+        | Signal::StackOverflowCThread
+        | Signal::Trap
+        | Signal::WriteReadOnly => true,
+        #[cfg(unix)]
+        Signal::Bus => true,
+        Signal::Fpe => sadness_generator::hardware_divide_by_zero_available(),
+        Signal::DivideOverflow => cfg!(any(target_arch = "x86", target_arch = "x86_64")),
+        _ => false,
+    };
+
+    if raised_in_client {
+        let code_file = module.expect("the module was found above").code_file();
+        assert_eq!(
+            std::path::Path::new(code_file.as_ref())
+                .file_stem()
+                .and_then(|stem| stem.to_str()),
+            Some("crash-client"),
+            "instruction pointer 0x{ip:x} is not in the client binary"
+        );
+    }
+
+    if matches!(
+        signal,
+        Signal::Segv | Signal::SegvCThread | Signal::AtExit | Signal::WriteReadOnly
+    ) {
+        assert_fault_memory(md, signal, ip, crash_address);
+    }
+
+    // The stack pointer for a stack overflow is in the guard page, which won't
+    // be in the captured stack memory
+    if !matches!(signal, Signal::StackOverflow | Signal::StackOverflowCThread) {
+        let threads: minidump::MinidumpThreadList<'_> =
+            md.get_stream().expect("unable to find thread list stream");
+        let thread = threads
+            .get_thread(exc.get_crashing_thread_id())
+            .expect("unable to find crashing thread");
+        let stack = thread
+            .stack
+            .as_ref()
+            .expect("crashing thread has no stack memory");
+
+        assert!(
+            (stack.base_address..stack.base_address + stack.size).contains(&sp),
+            "stack pointer 0x{sp:x} is not in the stack memory 0x{:x} - 0x{:x}",
+            stack.base_address,
+            stack.base_address + stack.size
+        );
+    }
+
+    if matches!(signal, Signal::Fpe) {
+        match &context.raw {
+            minidump::MinidumpRawContext::Amd64(ctx) => {
+                // The legacy region of the XSAVE area is 160 bytes, followed
+                // by each 16 byte xmm register
+                const XMM15: usize = 160 + 15 * 16;
+
+                let xmm15 = u64::from_le_bytes(
+                    ctx.float_save[XMM15..XMM15 + 8]
+                        .try_into()
+                        .expect("xmm15 is 8 bytes"),
+                );
+                assert_eq!(xmm15, FPE_SENTINEL, "xmm15 was not captured");
+            }
+            minidump::MinidumpRawContext::Arm64(ctx) => {
+                assert_eq!(
+                    ctx.float_regs[31] as u64, FPE_SENTINEL,
+                    "d31 was not captured"
+                );
+            }
+            _ => {}
+        }
+    }
 }
 
 pub fn run_threaded_test(signal: Signal) {
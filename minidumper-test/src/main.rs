@@ -24,11 +24,201 @@ struct Command {
 
     #[clap(action, long)]
     dump: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    subcommand: Option<Subcommand>,
+}
+
+#[derive(clap::Subcommand)]
+enum Subcommand {
+    /// Runs a server that logs everything it observes, until killed
+    Serve {
+        /// The name of the socket to listen on
+        #[clap(long)]
+        socket: String,
+        /// The directory minidumps are written to
+        #[clap(long)]
+        dump_dir: PathBuf,
+        /// Disconnects clients that haven't sent a message in the specified
+        /// number of milliseconds
+        #[clap(long)]
+        stale_timeout_ms: Option<u64>,
+    },
+    /// Spawns several `crash-client`s that connect to a server, eg. one run
+    /// with `serve`, send messages to it, then crash
+    Swarm {
+        /// The name of the socket the server is listening on
+        #[clap(long)]
+        socket: String,
+        /// The number of clients to spawn
+        #[clap(long, default_value_t = 8)]
+        clients: u32,
+        /// The signal/exception each client raises
+        #[clap(long)]
+        signal: Signal,
+        /// The number of messages each client sends per second before it
+        /// crashes
+        #[clap(long, default_value_t = 10)]
+        message_rate: u32,
+        /// The time, in milliseconds, each client sends messages for before it
+        /// crashes
+        #[clap(long, default_value_t = 1000)]
+        crash_after_ms: u64,
+    },
+}
+
+/// Logs every event of the server loop
+struct LoggingHandler {
+    dump_dir: PathBuf,
+    dump_count: std::sync::atomic::AtomicUsize,
+}
+
+impl minidumper::ServerHandler for LoggingHandler {
+    fn create_minidump_file(&self) -> Result<(std::fs::File, PathBuf), std::io::Error> {
+        let count = self
+            .dump_count
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path = self.dump_dir.join(format!("{count}.dmp"));
+        println!("writing minidump to {}", path.display());
+
+        Ok((std::fs::File::create(&path)?, path))
+    }
+
+    fn on_minidump_created(
+        &self,
+        result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+    ) -> minidumper::LoopAction {
+        match result {
+            Ok(md_bin) => println!(
+                "wrote minidump {} for thread {}",
+                md_bin.path.display(),
+                md_bin.metadata.thread_id
+            ),
+            Err(err) => println!("failed to write minidump: {err:#}"),
+        }
+
+        minidumper::LoopAction::Continue
+    }
+
+    fn on_crash_request(
+        &self,
+        client: minidumper::ClientId,
+        _crash_context: &crash_handler::CrashContext,
+        pending_dumps: usize,
+    ) -> minidumper::CrashRequestAction {
+        println!("{client:?} crashed, {pending_dumps} dumps pending");
+        minidumper::CrashRequestAction::Dump
+    }
+
+    fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+        println!("message {kind}: {}", String::from_utf8_lossy(&buffer));
+    }
+
+    fn on_client_message(&self, client: minidumper::ClientId, kind: u32, buffer: Vec<u8>) {
+        println!(
+            "{client:?} sent message {kind}: {}",
+            String::from_utf8_lossy(&buffer)
+        );
+    }
+
+    fn on_client_connected(
+        &self,
+        client: minidumper::ClientId,
+        peer: minidumper::PeerCredentials,
+        num_clients: usize,
+    ) -> minidumper::LoopAction {
+        println!("{client:?} connected from {peer:?}, {num_clients} clients");
+        minidumper::LoopAction::Continue
+    }
+
+    fn on_client_disconnected(
+        &self,
+        client: minidumper::ClientId,
+        reason: minidumper::DisconnectReason,
+        num_clients: usize,
+    ) -> minidumper::LoopAction {
+        println!("{client:?} disconnected ({reason:?}), {num_clients} clients");
+        minidumper::LoopAction::Continue
+    }
+
+    fn on_protocol_error(
+        &self,
+        client: minidumper::ClientId,
+        violation: minidumper::ProtocolViolation,
+    ) {
+        println!("{client:?} violated the protocol: {violation:?}");
+    }
+}
+
+fn serve(socket: &str, dump_dir: PathBuf, stale_timeout: Option<std::time::Duration>) {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing_subscriber::filter::LevelFilter::TRACE)
+        .init();
+
+    std::fs::create_dir_all(&dump_dir).expect("failed to create dump directory");
+
+    let mut server = minidumper::Server::with_name(socket).expect("failed to start server");
+    println!("listening on {socket}");
+
+    let handler = LoggingHandler {
+        dump_dir,
+        dump_count: Default::default(),
+    };
+    let shutdown = std::sync::atomic::AtomicBool::new(false);
+
+    server
+        .run(Box::new(handler), &shutdown, stale_timeout)
+        .expect("failed to run server loop");
+}
+
+fn swarm(socket: &str, clients: u32, signal: Signal, message_rate: u32, crash_after_ms: u64) {
+    let children: Vec<_> = (0..clients)
+        .map(|_| {
+            crash_client_command(socket)
+                .args(["--signal", &signal.to_string()])
+                .args(["--message-rate", &message_rate.to_string()])
+                .args(["--telemetry-ms", &crash_after_ms.to_string()])
+                .spawn()
+                .expect("failed to spawn crash-client")
+        })
+        .collect();
+
+    for mut child in children {
+        let pid = child.id();
+        let status = child.wait().expect("failed to wait on crash-client");
+        println!("client {pid} exited with {status}");
+    }
 }
 
 fn main() {
     let cli = Command::parse();
 
+    match cli.subcommand {
+        Some(Subcommand::Serve {
+            socket,
+            dump_dir,
+            stale_timeout_ms,
+        }) => {
+            serve(
+                &socket,
+                dump_dir,
+                stale_timeout_ms.map(std::time::Duration::from_millis),
+            );
+            return;
+        }
+        Some(Subcommand::Swarm {
+            socket,
+            clients,
+            signal,
+            message_rate,
+            crash_after_ms,
+        }) => {
+            swarm(&socket, clients, signal, message_rate, crash_after_ms);
+            return;
+        }
+        None => {}
+    }
+
     if cli.list {
         for variant in Signal::value_variants() {
             println!("{variant}");
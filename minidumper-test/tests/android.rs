@@ -0,0 +1,43 @@
+//! Runs a crash and dump cycle with the socket name an Android app would use,
+//! which is an abstract name or a path depending on the API level of the
+//! device, run on emulators of both kinds by CI
+
+#![cfg(target_os = "android")]
+
+use minidumper_test::*;
+
+#[test]
+fn android_default_name() {
+    capture_output();
+
+    let name = minidumper::SocketName::android_default(&std::env::temp_dir())
+        .expect("failed to create the socket name");
+
+    let id = "android_default_name";
+    let server = spinup_server_with_name(id, &name);
+
+    // The client connects with the same name, like the app would after
+    // spawning its monitor
+    match &name {
+        minidumper::OwnedSocketName::Path(path) => {
+            run_client_with_args(
+                id,
+                Signal::Segv,
+                &["--socket-path", path.to_str().expect("non-utf8 path")],
+            );
+        }
+        minidumper::OwnedSocketName::Abstract(abstract_name) => {
+            let abstract_name = std::str::from_utf8(abstract_name).expect("non-utf8 name");
+            run_client_with_args(abstract_name, Signal::Segv, &[]);
+        }
+        _ => unreachable!("unknown socket name kind"),
+    }
+
+    let dump_path = server
+        .dump_rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .expect("failed to receive dump path");
+
+    let md = std::fs::read(&dump_path).expect("failed to read minidump");
+    assert_minidump(&md, Signal::Segv);
+}
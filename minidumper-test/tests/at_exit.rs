@@ -0,0 +1,14 @@
+//! The crash is raised from an `atexit` handler, while the client is exiting
+//! normally, rather than while it is running as usual
+
+use minidumper_test::*;
+
+#[test]
+fn at_exit_simple() {
+    run_test(Signal::AtExit, 0, false);
+}
+
+#[test]
+fn at_exit_threaded() {
+    run_threaded_test(Signal::AtExit);
+}
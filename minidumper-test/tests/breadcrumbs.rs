@@ -0,0 +1,45 @@
+//! Verifies that the breadcrumbs a client records right before it crashes are
+//! read by the server from their shared memory, and reported with the minidump
+
+use minidumper_test::*;
+
+#[test]
+fn breadcrumbs() {
+    capture_output();
+
+    let id = "breadcrumbs";
+    let server = spinup_server(id, None);
+    run_client_with_args(id, Signal::Segv, &["--breadcrumbs"]);
+
+    let dump_path = server
+        .dump_rx
+        .recv_timeout(std::time::Duration::from_secs(1))
+        .expect("failed to receive dump path");
+
+    let metadata = server
+        .stats
+        .last_dump_metadata
+        .lock()
+        .unwrap()
+        .take()
+        .expect("no minidump metadata");
+
+    let breadcrumbs: Vec<_> = metadata
+        .breadcrumbs
+        .iter()
+        .map(|crumb| (crumb.kind, std::str::from_utf8(&crumb.data).unwrap()))
+        .collect();
+    assert_eq!(breadcrumbs, BREADCRUMBS);
+    assert_eq!(metadata.breadcrumbs[1].seq, 2);
+
+    // The breadcrumbs are also embedded in the minidump itself
+    let md = std::fs::read(&dump_path).expect("failed to read minidump");
+    let md = minidump::Minidump::read(md.as_slice()).expect("failed to parse minidump");
+    let stream = md
+        .get_raw_stream(minidumper::BREADCRUMBS_STREAM_TYPE)
+        .expect("missing breadcrumbs stream");
+    assert_eq!(
+        stream,
+        minidumper::Breadcrumb::to_stream(&metadata.breadcrumbs)
+    );
+}
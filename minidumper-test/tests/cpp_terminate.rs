@@ -0,0 +1,13 @@
+#![cfg(feature = "cpp")]
+
+use minidumper_test::*;
+
+#[test]
+fn cpp_terminate_simple() {
+    run_test(Signal::CppTerminate, 0, false);
+}
+
+#[test]
+fn cpp_terminate_threaded() {
+    run_threaded_test(Signal::CppTerminate);
+}
@@ -0,0 +1,14 @@
+//! This is ignored on mac when targeting arm for the same reason as `fpe.rs`
+#![cfg(not(all(target_os = "macos", any(target_arch = "arm", target_arch = "aarch64"))))]
+
+use minidumper_test::*;
+
+#[test]
+fn divide_overflow_simple() {
+    run_test(Signal::DivideOverflow, 0, false);
+}
+
+#[test]
+fn divide_overflow_threaded() {
+    run_threaded_test(Signal::DivideOverflow);
+}
@@ -0,0 +1,58 @@
+//! Verifies that the warnings raised while writing a minidump are reported
+//! with it. This doesn't use `capture_output`, as the warnings can only be
+//! captured by installing `DumpWarningLogger` as the logger.
+
+use minidumper_test::*;
+
+/// Prints the errors logged by any crate
+struct Errors;
+
+impl log::Log for Errors {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::Level::Error
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        eprintln!("{}: {}", record.target(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn dump_warnings() {
+    minidumper::DumpWarningLogger::new(Box::new(Errors))
+        .install()
+        .expect("failed to install logger");
+
+    // Also exercises reading the mappings, which is only done on Linux
+    let options = minidumper::DumpOptions::default();
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let options = options.full_memory(16 * 1024 * 1024);
+
+    let id = "dump_warnings";
+    let server = spinup_server_with_options(id, options);
+    run_client_with_args(id, Signal::Segv, &["--breadcrumbs"]);
+
+    let dump_path = server
+        .dump_rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .expect("failed to receive dump path");
+    let md = std::fs::read(dump_path).expect("failed to read minidump");
+    assert_minidump(&md, Signal::Segv);
+
+    // minidump-writer may log about parts of the process it skipped, which a
+    // healthy client can have, but the minidump must have everything the
+    // server itself added
+    let warnings = server.stats.last_dump_warnings.lock().unwrap();
+    for warning in warnings.iter() {
+        println!("{:?}: {}", warning.kind, warning.message);
+    }
+
+    assert!(
+        warnings
+            .iter()
+            .all(|warning| warning.kind == minidumper::DumpWarningKind::Writer),
+        "{warnings:?}"
+    );
+}
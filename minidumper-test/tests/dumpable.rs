@@ -0,0 +1,24 @@
+//! Verifies that a client that isn't dumpable stays ptraceable for as long as
+//! the server takes to write its minidump, as the crash handler only allows it
+//! while the client waits on the server's ack
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+use minidumper_test::*;
+
+#[test]
+fn slow_server() {
+    capture_output();
+
+    let id = "dumpable-slow-server";
+    let server = spinup_server_with_delay(id, std::time::Duration::from_secs(1));
+    run_client_with_args(id, Signal::Segv, &["--not-dumpable"]);
+
+    let dump_path = server
+        .dump_rx
+        .recv_timeout(std::time::Duration::from_secs(10))
+        .expect("failed to receive dump path");
+
+    let md = std::fs::read(&dump_path).expect("failed to read minidump");
+    assert_minidump(&md, Signal::Segv);
+}
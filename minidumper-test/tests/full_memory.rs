@@ -0,0 +1,39 @@
+//! Verifies that full memory minidumps include the heap of the crashed
+//! process, which normal minidumps omit
+
+#![cfg(any(target_os = "linux", target_os = "android"))]
+
+use minidumper_test::*;
+
+fn dump_with(id: &str, dump_options: minidumper::DumpOptions) -> Vec<u8> {
+    capture_output();
+
+    let server = spinup_server_with_options(id, dump_options);
+    run_client_with_args(id, Signal::Segv, &["--heap-sentinel"]);
+
+    let dump_path = server
+        .dump_rx
+        .recv_timeout(std::time::Duration::from_secs(10))
+        .expect("failed to receive dump path");
+
+    let md = std::fs::read(&dump_path).expect("failed to read minidump");
+    assert_minidump(&md, Signal::Segv);
+    md
+}
+
+fn has_sentinel(md: &[u8]) -> bool {
+    md.windows(HEAP_SENTINEL.len()).any(|w| w == HEAP_SENTINEL)
+}
+
+#[test]
+fn full_memory() {
+    let normal = dump_with("full-memory-normal", minidumper::DumpOptions::default());
+    assert!(!has_sentinel(&normal), "a normal dump included the heap");
+
+    let full = dump_with(
+        "full-memory-full",
+        minidumper::DumpOptions::default().full_memory(512 * 1024 * 1024),
+    );
+    assert!(has_sentinel(&full), "a full dump didn't include the heap");
+    assert!(full.len() > normal.len());
+}
@@ -9,3 +9,13 @@ fn illegal_simple() {
 fn illegal_threaded() {
     run_threaded_test(Signal::Illegal);
 }
+
+#[test]
+fn illegal_c_thread() {
+    run_test(Signal::IllegalCThread, 0, false);
+}
+
+#[test]
+fn illegal_c_thread_threaded() {
+    run_threaded_test(Signal::IllegalCThread);
+}
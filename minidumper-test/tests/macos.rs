@@ -8,3 +8,15 @@ fn guard_simple() {
     // guard id, we don't run this threaded
     run_test(Signal::Guard, 0, false);
 }
+
+#[test]
+fn resource_fatal() {
+    // The CPU usage monitor applies to the entire process, so we don't run
+    // this threaded
+    run_test(Signal::ResourceFatal, 0, false);
+}
+
+#[test]
+fn resource_non_fatal() {
+    run_no_crash_test(Signal::ResourceNonFatal, std::time::Duration::from_secs(1));
+}
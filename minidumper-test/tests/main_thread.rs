@@ -0,0 +1,35 @@
+//! Verifies that the server is told whether the client crashed on its main
+//! thread, which is the thread the client attaches its crash handler from
+
+use minidumper_test::*;
+
+fn crash(id: &str, use_thread: bool) -> Option<bool> {
+    capture_output();
+
+    let server = spinup_server(id, None);
+    run_client(id, Signal::Segv, use_thread);
+
+    server
+        .dump_rx
+        .recv_timeout(std::time::Duration::from_secs(1))
+        .expect("failed to receive dump path");
+
+    let metadata = server
+        .stats
+        .last_dump_metadata
+        .lock()
+        .unwrap()
+        .take()
+        .expect("no minidump metadata");
+    metadata.is_main_thread
+}
+
+#[test]
+fn main_thread_simple() {
+    assert_eq!(crash("main-thread-simple", false), Some(true));
+}
+
+#[test]
+fn main_thread_threaded() {
+    assert_eq!(crash("main-thread-threaded", true), Some(false));
+}
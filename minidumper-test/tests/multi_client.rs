@@ -0,0 +1,20 @@
+use minidumper_test::*;
+
+#[test]
+fn multiple_clients() {
+    // Each crashing client uses a different signal so that every minidump
+    // can be attributed to the client that produced it
+    run_multi_client_test(
+        "multi-client",
+        &[
+            ClientKind::Crash(Signal::Segv),
+            ClientKind::Messages(10),
+            ClientKind::Ping(5),
+            ClientKind::Crash(Signal::Abort),
+            ClientKind::Messages(3),
+            ClientKind::Crash(Signal::Illegal),
+            ClientKind::Ping(1),
+            ClientKind::Crash(Signal::Trap),
+        ],
+    );
+}
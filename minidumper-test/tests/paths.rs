@@ -0,0 +1,55 @@
+use minidumper_test::*;
+use std::path::PathBuf;
+
+/// Runs a full client/server cycle with the specified socket name and dump
+/// path, ensuring the dump is written where we expect and is readable
+fn run_with_paths(id: &str, dump_path: PathBuf) {
+    let md = generate_minidump(id, Signal::Segv, false, Some(dump_path.clone()));
+    assert!(
+        dump_path.exists(),
+        "{} was not written",
+        dump_path.display()
+    );
+    assert_minidump(&md, Signal::Segv);
+}
+
+#[test]
+fn non_ascii() {
+    run_with_paths(
+        "sadness-悲しみ-😢",
+        PathBuf::from(".dumps/悲しみ/sadness-😢.dmp"),
+    );
+}
+
+#[test]
+fn spaces() {
+    run_with_paths(
+        "sadness with spaces",
+        PathBuf::from(".dumps/with spaces/sadness with spaces.dmp"),
+    );
+}
+
+/// The dump path is created by the server, so it's only restricted by
+/// `MAX_PATH`, unlike the socket name, which must fit in a `sockaddr_un`
+#[cfg(windows)]
+#[test]
+fn long_dump_path() {
+    use std::os::windows::ffi::OsStrExt;
+
+    const MAX_PATH: usize = 260;
+
+    let dir = std::env::current_dir()
+        .expect("failed to get current directory")
+        .join(".dumps")
+        .join("長い");
+    let prefix_len = dir.as_os_str().encode_wide().count() + "\\.dmp".len();
+
+    // Leave room for the null terminator, each character is a single UTF-16
+    // code unit
+    let file_len = (MAX_PATH - 1)
+        .checked_sub(prefix_len)
+        .expect("current directory is too long to test long dump paths");
+    let dump_path = dir.join(format!("{}.dmp", "悲".repeat(file_len)));
+
+    run_with_paths("sadness-long-path", dump_path);
+}
@@ -9,3 +9,13 @@ fn segfault_simple() {
 fn segfault_threaded() {
     run_threaded_test(Signal::Segv);
 }
+
+#[test]
+fn segfault_c_thread() {
+    run_test(Signal::SegvCThread, 0, false);
+}
+
+#[test]
+fn segfault_c_thread_threaded() {
+    run_threaded_test(Signal::SegvCThread);
+}
@@ -0,0 +1,11 @@
+use minidumper_test::*;
+
+#[test]
+fn smashed_stack_simple() {
+    run_test(Signal::SmashedStack, 0, false);
+}
+
+#[test]
+fn smashed_stack_threaded() {
+    run_threaded_test(Signal::SmashedStack);
+}
@@ -0,0 +1,119 @@
+//! Verifies how the stale timeout interacts with clients that ping, go quiet,
+//! and crash.
+//!
+//! The server only reaps stale connections between iterations of its loop,
+//! which happen every poll interval, so the quiet periods are either well
+//! under or well over the timeout to keep the tests deterministic
+
+use minidumper::DisconnectReason;
+use minidumper_test::*;
+use std::time::Duration;
+
+const STALE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Waits for the server to observe the specified number of disconnects, and
+/// returns their reasons
+fn disconnects(server: &Server, count: usize) -> Vec<DisconnectReason> {
+    let start = std::time::Instant::now();
+    while server
+        .stats
+        .disconnected
+        .load(std::sync::atomic::Ordering::Relaxed)
+        < count
+        && start.elapsed() < Duration::from_secs(5)
+    {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    server.stats.disconnect_reasons.lock().unwrap().clone()
+}
+
+/// A client that pings more often than the timeout is never reaped, even
+/// though it is connected for longer than it
+#[test]
+fn pinging_client_is_not_reaped() {
+    capture_output();
+
+    let server = spinup_server_with_stale_timeout("stale-pinging", STALE_TIMEOUT);
+    run_quiet_client(
+        "stale-pinging",
+        8,
+        STALE_TIMEOUT / 3,
+        Duration::from_millis(0),
+    );
+
+    assert_eq!(disconnects(&server, 1), [DisconnectReason::Closed]);
+}
+
+/// A client that stops pinging is reaped once it has been quiet for the
+/// timeout, while it is still connected
+#[test]
+fn quiet_client_is_reaped() {
+    capture_output();
+
+    let server = spinup_server_with_stale_timeout("stale-quiet", STALE_TIMEOUT);
+    run_quiet_client(
+        "stale-quiet",
+        1,
+        Duration::from_millis(0),
+        STALE_TIMEOUT * 4,
+    );
+
+    match disconnects(&server, 1).as_slice() {
+        [DisconnectReason::Stale(elapsed)] => {
+            assert!(*elapsed >= STALE_TIMEOUT);
+            assert!(
+                *elapsed < STALE_TIMEOUT + Duration::from_millis(500),
+                "reaped after {elapsed:?}"
+            );
+        }
+        reasons => panic!("expected a single stale disconnect, got {reasons:?}"),
+    }
+}
+
+/// A client that goes quiet for less than the timeout and then crashes still
+/// gets its minidump written
+#[test]
+fn quiet_client_crash_is_accepted() {
+    capture_output();
+
+    let server = spinup_server_with_stale_timeout("stale-crash-accepted", STALE_TIMEOUT);
+    run_client_with_args(
+        "stale-crash-accepted",
+        Signal::Segv,
+        &["--quiet-ms", &(STALE_TIMEOUT / 2).as_millis().to_string()],
+    );
+
+    let dump_path = server
+        .dump_rx
+        .recv_timeout(Duration::from_secs(1))
+        .expect("failed to receive dump path");
+    assert_minidump(&std::fs::read(dump_path).unwrap(), Signal::Segv);
+
+    assert_eq!(disconnects(&server, 1), [DisconnectReason::Crashed]);
+}
+
+/// A client that goes quiet for longer than the timeout has already been
+/// reaped when it crashes, so its crash can't be requested, and no minidump is
+/// written
+#[test]
+fn reaped_client_crash_is_rejected() {
+    capture_output();
+
+    let server = spinup_server_with_stale_timeout("stale-crash-rejected", STALE_TIMEOUT);
+    run_client_with_args(
+        "stale-crash-rejected",
+        Signal::Segv,
+        &["--quiet-ms", &(STALE_TIMEOUT * 3).as_millis().to_string()],
+    );
+
+    assert!(server
+        .dump_rx
+        .recv_timeout(Duration::from_millis(500))
+        .is_err());
+
+    assert!(matches!(
+        disconnects(&server, 1).as_slice(),
+        [DisconnectReason::Stale(_)]
+    ));
+}
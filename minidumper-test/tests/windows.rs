@@ -26,3 +26,62 @@ fn invalid_param_threaded() {
 fn heap_corruption() {
     run_threaded_test(Signal::HeapCorruption);
 }
+
+#[test]
+fn trap_parameter() {
+    run_test(Signal::TrapParameter, 0, false);
+}
+
+#[test]
+fn debug_service() {
+    run_test(Signal::DebugService, 0, false);
+}
+
+#[test]
+fn crt_abort() {
+    run_test(Signal::CrtAbort, 0, false);
+}
+
+/// `__fastfail` terminates the process without running any exception handlers,
+/// so the client never requests a minidump, the failure is only reported to
+/// Windows Error Reporting, if it is enabled
+#[test]
+fn fast_fail() {
+    run_uncatchable_test(
+        Signal::FastFail,
+        0xc0000409, // STATUS_STACK_BUFFER_OVERRUN
+        std::time::Duration::from_secs(1),
+    );
+}
+
+/// Minidumps are written directly to the file on Windows, so their contents
+/// are only provided if they are requested, in which case they must match the
+/// file, which must have been flushed before the handler is called
+#[test]
+fn dump_contents() {
+    capture_output();
+
+    let id = "dump-contents";
+    let server = spinup_server_with_options(
+        id,
+        minidumper::DumpOptions::default().contents(minidumper::ContentsMode::Always),
+    );
+    run_client(id, Signal::Segv, false);
+
+    let dump_path = server
+        .dump_rx
+        .recv_timeout(std::time::Duration::from_secs(10))
+        .expect("failed to receive dump path");
+
+    let contents = server
+        .stats
+        .last_dump_contents
+        .lock()
+        .unwrap()
+        .take()
+        .expect("the minidump contents were not provided");
+    assert_minidump(&contents, Signal::Segv);
+
+    let md = std::fs::read(&dump_path).expect("failed to read minidump");
+    assert_eq!(contents, md);
+}
@@ -0,0 +1,11 @@
+use minidumper_test::*;
+
+#[test]
+fn write_read_only_simple() {
+    run_test(Signal::WriteReadOnly, 0, false);
+}
+
+#[test]
+fn write_read_only_threaded() {
+    run_threaded_test(Signal::WriteReadOnly);
+}
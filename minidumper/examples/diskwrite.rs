@@ -66,26 +66,20 @@ fn main() {
         return;
     }
 
-    let mut server = None;
+    let exe = std::env::current_exe().expect("unable to find ourselves");
 
-    // Attempt to connect to the server
-    let (client, _server) = loop {
-        if let Ok(client) = Client::with_name(SOCKET_NAME) {
-            break (client, server.unwrap());
-        }
-
-        let exe = std::env::current_exe().expect("unable to find ourselves");
+    let mut server = std::process::Command::new(exe)
+        .arg("--server")
+        .spawn()
+        .expect("unable to spawn server process");
 
-        server = Some(
-            std::process::Command::new(exe)
-                .arg("--server")
-                .spawn()
-                .expect("unable to spawn server process"),
-        );
-
-        // Give it time to start
-        std::thread::sleep(std::time::Duration::from_millis(100));
-    };
+    // Attempt to connect to the server, giving it time to start
+    let client = Client::with_name_timeout(
+        SOCKET_NAME,
+        std::time::Duration::from_secs(5),
+        std::time::Duration::from_millis(10),
+    )
+    .expect("failed to connect to server");
 
     // Register our exception handler
     client.send_message(1, "mistakes will be made").unwrap();
@@ -112,7 +106,7 @@ fn main() {
     // process we are monitoring (this one) for crashes
     #[cfg(any(target_os = "linux", target_os = "android"))]
     {
-        handler.set_ptracer(Some(_server.id()));
+        handler.set_ptracer(Some(server.id()));
     }
 
     cfg_if::cfg_if! {
@@ -124,4 +118,7 @@ fn main() {
             handler.simulate_exception(None);
         }
     }
+
+    // The server exits once it has written the minidump
+    let _ = server.wait();
 }
@@ -0,0 +1,107 @@
+/* SPDX-License-Identifier: MIT OR Apache-2.0 */
+
+#ifndef MINIDUMPER_H
+#define MINIDUMPER_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call succeeded
+#define MINIDUMPER_OK 0
+
+// A pointer argument was null, the name was not valid UTF-8, or the message
+// kind was too large, ie. [`Error::InvalidMessageKind`]
+#define MINIDUMPER_ERR_INVALID_ARGUMENT -1
+
+// The socket name was invalid or unsupported on the current platform, ie.
+// [`Error::InvalidName`], [`Error::UnsupportedSocketName`], or, on Macos,
+// `Error::InvalidPortName`
+#define MINIDUMPER_ERR_INVALID_NAME -2
+
+// An I/O error occurred communicating with the server, ie. [`Error::Io`], or,
+// on Macos, `Error::PortError`
+#define MINIDUMPER_ERR_IO -3
+
+// The server sent an invalid response, ie. [`Error::ProtocolError`], or, on
+// Windows and Macos, `Error::Scroll`
+#define MINIDUMPER_ERR_PROTOCOL -4
+
+// Any other error, including a panic, which is caught rather than unwinding
+// into the caller
+#define MINIDUMPER_ERR_OTHER -5
+
+// The server failed to write the requested minidump, ie.
+// [`Error::ServerDumpFailed`]
+#define MINIDUMPER_ERR_DUMP_FAILED -6
+
+// The server was busy, and kept deferring the crash request until the client
+// ran out of retries, ie. [`Error::ServerBusy`]
+#define MINIDUMPER_ERR_SERVER_BUSY -7
+
+typedef struct MinidumperClient MinidumperClient;
+
+typedef struct MinidumperCrashContext MinidumperCrashContext;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Connects to the server with the specified name, see [`Client::with_name`].
+//
+// Returns null if the connection fails. The client must be destroyed with
+// [`minidumper_client_destroy`].
+//
+// # Safety
+//
+// `name` must be null, or a valid nul terminated string.
+MinidumperClient *minidumper_client_connect(const char *name);
+
+// Sends a message to the server, see [`Client::send_message`].
+//
+// Returns [`MINIDUMPER_OK`] on success, or one of the `MINIDUMPER_ERR_*`
+// codes.
+//
+// # Safety
+//
+// `client` must be null, or a client returned by [`minidumper_client_connect`]
+// that has not been destroyed. `buf` must be null, or valid for reads of
+// `len` bytes. `buf` may only be null if `len` is 0.
+int minidumper_client_send_message(const MinidumperClient *client,
+                                   uint32_t kind,
+                                   const uint8_t *buf,
+                                   size_t len);
+
+// Requests that the server write a minidump for the specified crash context,
+// see [`Client::request_dump`].
+//
+// Returns [`MINIDUMPER_OK`] once the server has finished writing the
+// minidump, or one of the `MINIDUMPER_ERR_*` codes.
+//
+// # Safety
+//
+// `client` must be null, or a client returned by [`minidumper_client_connect`]
+// that has not been destroyed. `crash_context` must be null, or point to a
+// valid [`crash_context::CrashContext`], eg. one received by a
+// `crash_handler::CrashEvent`.
+int minidumper_client_request_dump(const MinidumperClient *client,
+                                   const MinidumperCrashContext *crash_context);
+
+// Disconnects and destroys a client. Does nothing if `client` is null.
+//
+// # Safety
+//
+// `client` must be null, or a client returned by [`minidumper_client_connect`]
+// that has not already been destroyed, and that is not in use on any other
+// thread.
+void minidumper_client_destroy(MinidumperClient *client);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* MINIDUMPER_H */
@@ -0,0 +1,827 @@
+//! Breadcrumbs, ie. the last events recorded by a client, eg. "level loaded",
+//! kept in memory that is shared with the server, so that they can be read
+//! even if the client dies without being able to send anything, see
+//! [`crate::Client::create_breadcrumbs`]
+//!
+//! # Format
+//!
+//! The shared region is an array of `u64`s, in the native byte order, as the
+//! client and server always run on the same machine. It starts with a header
+//! of [`HEADER_WORDS`]:
+//!
+//! 0. [`MAGIC`] in the low 32 bits, and [`FORMAT_VERSION`] in the high 32 bits
+//! 1. The capacity in the low 32 bits, and [`RECORD_SIZE`] in the high 32 bits
+//! 2. The number of records that have been started
+//! 3. Reserved, 0
+//!
+//! followed by the capacity's worth of records, each of [`RECORD_WORDS`]:
+//!
+//! 0. The state of the record, 0 if it has never been written, `seq << 1 | 1`
+//!    while record `seq` is being written, and `seq << 1` once it has been,
+//!    where `seq` is the position of the record among all those written,
+//!    starting at 1
+//! 1. [`Breadcrumb::realtime_ns`]
+//! 2. [`Breadcrumb::kind`] in the low 32 bits, and the length of the data in
+//!    the high 32 bits
+//! 3. The data, 8 bytes per word, in little endian order, up to
+//!    [`MAX_BREADCRUMB_LEN`]
+//!
+//! Records are written round robin, and each is a seqlock, ie. a reader only
+//! keeps a record if its state is the same, and complete, both before and after
+//! reading it, so that a record that was being written when the client died, or
+//! that is being written while the server reads it, is skipped rather than torn.
+
+#![allow(unsafe_code)]
+
+use std::sync::{
+    atomic::{fence, AtomicU64, Ordering},
+    Arc,
+};
+
+/// The largest capacity that breadcrumbs can be created with
+pub const MAX_BREADCRUMBS: usize = 64 * 1024;
+/// The largest data of a single breadcrumb, longer data is truncated
+pub const MAX_BREADCRUMB_LEN: usize = (RECORD_WORDS - 3) * 8;
+/// The type of the stream the breadcrumbs are added to the minidump as, when
+/// there are any, see [`Breadcrumb::to_stream`]
+pub const BREADCRUMBS_STREAM_TYPE: u32 = 0x6d44_0001;
+
+/// "MDBC", in little endian order
+const MAGIC: u64 = 0x4342_444d;
+/// The version of the format of the shared region, which is increased for
+/// changes that older servers can't read
+const FORMAT_VERSION: u64 = 1;
+const HEADER_WORDS: usize = 4;
+const RECORD_WORDS: usize = 16;
+const RECORD_SIZE: usize = RECORD_WORDS * 8;
+
+/// A breadcrumb recorded by a client, see [`Breadcrumbs::record`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breadcrumb {
+    /// The position of the breadcrumb among all those recorded by the client,
+    /// starting at 1, so gaps are where breadcrumbs were overwritten, or
+    /// skipped as they were still being written
+    pub seq: u64,
+    /// When the breadcrumb was recorded, in nanoseconds since the Unix epoch
+    pub realtime_ns: u64,
+    /// The user defined kind of the breadcrumb
+    pub kind: u32,
+    /// The data of the breadcrumb, at most [`MAX_BREADCRUMB_LEN`] bytes
+    pub data: Vec<u8>,
+}
+
+impl Breadcrumb {
+    /// Encodes breadcrumbs as the [`BREADCRUMBS_STREAM_TYPE`] stream, ie. the
+    /// version of the format, currently 1, and the number of breadcrumbs, each as 4 bytes,
+    /// followed by each breadcrumb as its `seq` and `realtime_ns`, each as 8
+    /// bytes, its `kind` and the length of its data, each as 4 bytes, and its
+    /// data, padded to 8 bytes. All integers are little endian.
+    pub fn to_stream(breadcrumbs: &[Self]) -> Vec<u8> {
+        let mut stream = Vec::with_capacity(8 + breadcrumbs.len() * (24 + MAX_BREADCRUMB_LEN));
+        stream.extend_from_slice(&(FORMAT_VERSION as u32).to_le_bytes());
+        stream.extend_from_slice(&(breadcrumbs.len() as u32).to_le_bytes());
+
+        for crumb in breadcrumbs {
+            stream.extend_from_slice(&crumb.seq.to_le_bytes());
+            stream.extend_from_slice(&crumb.realtime_ns.to_le_bytes());
+            stream.extend_from_slice(&crumb.kind.to_le_bytes());
+            stream.extend_from_slice(&(crumb.data.len() as u32).to_le_bytes());
+            stream.extend_from_slice(&crumb.data);
+            stream.resize((stream.len() + 7) & !7, 0);
+        }
+
+        stream
+    }
+}
+
+/// The size of a region with the capacity
+#[inline]
+fn region_size(capacity: usize) -> usize {
+    (HEADER_WORDS + capacity * RECORD_WORDS) * 8
+}
+
+/// Writes the header of a new region
+fn init(words: &[AtomicU64], capacity: usize) {
+    words[0].store(MAGIC | FORMAT_VERSION << 32, Ordering::Relaxed);
+    words[1].store(
+        capacity as u64 | (RECORD_SIZE as u64) << 32,
+        Ordering::Relaxed,
+    );
+}
+
+/// Validates the header of a region, returning its capacity
+fn validate(words: &[AtomicU64]) -> Option<usize> {
+    let id = words.first()?.load(Ordering::Relaxed);
+    let layout = words.get(1)?.load(Ordering::Relaxed);
+    let capacity = (layout & 0xffff_ffff) as usize;
+
+    (id == MAGIC | FORMAT_VERSION << 32
+        && layout >> 32 == RECORD_SIZE as u64
+        && (1..=MAX_BREADCRUMBS).contains(&capacity)
+        && words.len() * 8 >= region_size(capacity))
+    .then_some(capacity)
+}
+
+/// Appends a record, overwriting the oldest one once the region is full
+fn append(words: &[AtomicU64], capacity: usize, kind: u32, data: &[u8]) {
+    let seq = words[2].fetch_add(1, Ordering::Relaxed) + 1;
+    let index = ((seq - 1) % capacity as u64) as usize;
+    let record = &words[HEADER_WORDS + index * RECORD_WORDS..][..RECORD_WORDS];
+
+    // Only one writer can own a record at a time, so if the writer of the
+    // record `capacity` records before this one hasn't finished yet, this one
+    // is dropped rather than torn
+    let state = record[0].load(Ordering::Relaxed);
+    if state & 1 != 0
+        || record[0]
+            .compare_exchange(state, seq << 1 | 1, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+    {
+        return;
+    }
+
+    fence(Ordering::Release);
+
+    let len = data.len().min(MAX_BREADCRUMB_LEN);
+    let realtime_ns = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64);
+
+    record[1].store(realtime_ns, Ordering::Relaxed);
+    record[2].store(u64::from(kind) | (len as u64) << 32, Ordering::Relaxed);
+
+    for (word, chunk) in record[3..].iter().zip(data[..len].chunks(8)) {
+        let mut bytes = [0; 8];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        word.store(u64::from_le_bytes(bytes), Ordering::Relaxed);
+    }
+
+    record[0].store(seq << 1, Ordering::Release);
+}
+
+/// Reads every complete record, oldest first
+fn snapshot(words: &[AtomicU64], capacity: usize) -> Vec<Breadcrumb> {
+    let mut breadcrumbs = Vec::with_capacity(capacity);
+
+    for record in words[HEADER_WORDS..]
+        .chunks_exact(RECORD_WORDS)
+        .take(capacity)
+    {
+        let state = record[0].load(Ordering::Acquire);
+        if state == 0 || state & 1 != 0 {
+            continue;
+        }
+
+        let realtime_ns = record[1].load(Ordering::Relaxed);
+        let kind_len = record[2].load(Ordering::Relaxed);
+
+        let mut data = [0u8; MAX_BREADCRUMB_LEN];
+        for (chunk, word) in data.chunks_mut(8).zip(&record[3..]) {
+            chunk.copy_from_slice(&word.load(Ordering::Relaxed).to_le_bytes());
+        }
+
+        fence(Ordering::Acquire);
+
+        // The record was overwritten while it was read
+        if record[0].load(Ordering::Relaxed) != state {
+            continue;
+        }
+
+        let len = ((kind_len >> 32) as usize).min(MAX_BREADCRUMB_LEN);
+        breadcrumbs.push(Breadcrumb {
+            seq: state >> 1,
+            realtime_ns,
+            kind: kind_len as u32,
+            data: data[..len].to_vec(),
+        });
+    }
+
+    breadcrumbs.sort_unstable_by_key(|crumb| crumb.seq);
+    breadcrumbs
+}
+
+/// Records breadcrumbs into memory that is shared with the server, see
+/// [`crate::Client::create_breadcrumbs`].
+///
+/// Recording is lock-free and doesn't allocate, so it can be done from any
+/// thread, at any time. Clones record into the same breadcrumbs.
+#[derive(Clone)]
+pub struct Breadcrumbs {
+    mapping: Arc<Mapping>,
+    capacity: usize,
+}
+
+impl Breadcrumbs {
+    /// Creates the shared memory for the capacity, returning what the server
+    /// needs to map it as well
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "windows",
+        target_os = "macos"
+    ))]
+    pub(crate) fn create(capacity: usize) -> std::io::Result<(Self, Share)> {
+        let (mapping, share) = Mapping::create(region_size(capacity))?;
+        init(mapping.words(), capacity);
+
+        Ok((
+            Self {
+                mapping: Arc::new(mapping),
+                capacity,
+            },
+            share,
+        ))
+    }
+
+    /// The payload of the message that tells the server to map the
+    /// breadcrumbs, ie. their size as 8 little endian bytes, followed, on
+    /// Windows and Macos, by the name of the shared memory
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "windows",
+        target_os = "macos"
+    ))]
+    pub(crate) fn payload(&self, _share: &Share) -> Vec<u8> {
+        #[allow(unused_mut)]
+        let mut payload = (self.mapping.len as u64).to_le_bytes().to_vec();
+        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        payload.extend_from_slice(_share.as_bytes());
+        payload
+    }
+
+    /// Records a breadcrumb, overwriting the oldest one once the capacity is
+    /// reached. Data longer than [`MAX_BREADCRUMB_LEN`] is truncated.
+    ///
+    /// If more breadcrumbs than the capacity are recorded while another
+    /// thread is still recording one, the breadcrumb that would overwrite it
+    /// is dropped instead.
+    #[inline]
+    pub fn record(&self, kind: u32, data: impl AsRef<[u8]>) {
+        append(self.mapping.words(), self.capacity, kind, data.as_ref());
+    }
+
+    /// The number of breadcrumbs that are kept
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Reads the breadcrumbs that have been recorded, oldest first
+    #[inline]
+    pub fn snapshot(&self) -> Vec<Breadcrumb> {
+        snapshot(self.mapping.words(), self.capacity)
+    }
+}
+
+impl std::fmt::Debug for Breadcrumbs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Breadcrumbs")
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The breadcrumbs of a client, mapped read-only by the server
+pub(crate) struct BreadcrumbReader {
+    mapping: Mapping,
+    capacity: usize,
+}
+
+impl BreadcrumbReader {
+    /// Maps the breadcrumbs described by the payload sent by the client, see
+    /// [`Breadcrumbs::payload`], which on Linux/Android are the memfd sent
+    /// along with it, and on Macos must be owned by the client's user
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "windows",
+        target_os = "macos"
+    ))]
+    pub(crate) fn open(
+        payload: &[u8],
+        #[cfg(any(target_os = "linux", target_os = "android"))] fd: Option<std::os::fd::OwnedFd>,
+        #[cfg(target_os = "macos")] owner: u32,
+    ) -> std::io::Result<Self> {
+        let invalid = |msg| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+
+        let size = payload
+            .get(..8)
+            .map(|size| u64::from_le_bytes(size.try_into().unwrap()))
+            .ok_or_else(|| invalid("the breadcrumbs message is truncated"))?;
+
+        let size = usize::try_from(size)
+            .ok()
+            .filter(|size| (region_size(1)..=region_size(MAX_BREADCRUMBS)).contains(size))
+            .ok_or_else(|| invalid("the breadcrumbs have an invalid size"))?;
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                let fd = fd.ok_or_else(|| invalid("the breadcrumbs were sent without their memfd"))?;
+                let mapping = Mapping::open(fd, size)?;
+            } else if #[cfg(target_os = "macos")] {
+                let name = std::str::from_utf8(&payload[8..])
+                    .map_err(|_err| invalid("the breadcrumbs name is not utf-8"))?;
+                let mapping = Mapping::open(name, size, owner)?;
+            } else {
+                let name = std::str::from_utf8(&payload[8..])
+                    .map_err(|_err| invalid("the breadcrumbs name is not utf-8"))?;
+                let mapping = Mapping::open(name, size)?;
+            }
+        }
+
+        let capacity = validate(mapping.words())
+            .ok_or_else(|| invalid("the breadcrumbs have an invalid header"))?;
+
+        Ok(Self { mapping, capacity })
+    }
+
+    /// Reads the breadcrumbs the client has recorded, oldest first
+    #[inline]
+    pub(crate) fn snapshot(&self) -> Vec<Breadcrumb> {
+        snapshot(self.mapping.words(), self.capacity)
+    }
+}
+
+/// What the server needs to map the breadcrumbs created by a client
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) type Share = std::os::fd::OwnedFd;
+/// What the server needs to map the breadcrumbs created by a client
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub(crate) type Share = String;
+
+/// Memory that is shared with another process, mapped into this one
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+    /// The file mapping, which is closed once the view is unmapped
+    #[cfg(target_os = "windows")]
+    handle: bindings::HANDLE,
+    /// The name of the shared memory object, which is unlinked once the
+    /// client is done with it, unless the server already has
+    #[cfg(target_os = "macos")]
+    name: Option<std::ffi::CString>,
+}
+
+// SAFETY: The memory is only accessed via atomics
+unsafe impl Send for Mapping {}
+// SAFETY: The memory is only accessed via atomics
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    #[inline]
+    fn words(&self) -> &[AtomicU64] {
+        // SAFETY: the mapping is page aligned, and lives as long as self
+        unsafe { std::slice::from_raw_parts(self.ptr.cast(), self.len / 8) }
+    }
+
+    /// Maps the file shared
+    #[cfg(unix)]
+    fn map(fd: std::os::fd::BorrowedFd<'_>, len: usize, writable: bool) -> std::io::Result<Self> {
+        use std::os::fd::AsRawFd;
+
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+
+        // SAFETY: syscall
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                prot,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            ptr: ptr.cast(),
+            len,
+            #[cfg(target_os = "macos")]
+            name: None,
+        })
+    }
+
+    /// Creates a memfd, sealed so that it can't be shrunk, which would fault
+    /// the server's reads of its mapping
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn create(len: usize) -> std::io::Result<(Self, Share)> {
+        use std::os::fd::{AsFd, AsRawFd, FromRawFd};
+
+        let name = std::ffi::CString::new("minidumper-breadcrumbs")?;
+
+        // SAFETY: syscalls, we own the fd once it is created
+        unsafe {
+            let fd = libc::syscall(
+                libc::SYS_memfd_create,
+                name.as_ptr(),
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            );
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            let fd = std::os::fd::OwnedFd::from_raw_fd(fd as i32);
+
+            if libc::ftruncate(fd.as_raw_fd(), len as libc::off_t) != 0
+                || libc::fcntl(
+                    fd.as_raw_fd(),
+                    libc::F_ADD_SEALS,
+                    libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_SEAL,
+                ) != 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            let mapping = Self::map(fd.as_fd(), len, true)?;
+            Ok((mapping, fd))
+        }
+    }
+
+    /// Maps the memfd sent by the client read-only, if it can't be shrunk
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn open(fd: std::os::fd::OwnedFd, len: usize) -> std::io::Result<Self> {
+        use std::os::fd::{AsFd, AsRawFd};
+
+        // SAFETY: syscall
+        let seals = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GET_SEALS) };
+        if seals < 0 || seals & libc::F_SEAL_SHRINK == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "the breadcrumbs memfd is not sealed",
+            ));
+        }
+
+        if std::fs::File::from(fd.try_clone()?).metadata()?.len() < len as u64 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "the breadcrumbs memfd is smaller than their size",
+            ));
+        }
+
+        Self::map(fd.as_fd(), len, false)
+    }
+
+    /// Creates a POSIX shared memory object, whose size, unlike that of a
+    /// file, can't be changed once it has been set, so it can't be shrunk to
+    /// fault the server's reads of its mapping
+    #[cfg(target_os = "macos")]
+    fn create(len: usize) -> std::io::Result<(Self, Share)> {
+        use std::os::fd::{AsFd, AsRawFd, FromRawFd};
+
+        static COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+        // Names are limited to `PSHMNAMLEN`, 31 bytes, on Macos
+        let name = format!(
+            "{SHM_PREFIX}{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        );
+        let cname = std::ffi::CString::new(name.clone())?;
+
+        // SAFETY: syscalls, we own the fd once it is created
+        unsafe {
+            let fd = libc::shm_open(
+                cname.as_ptr(),
+                libc::O_RDWR | libc::O_CREAT | libc::O_EXCL,
+                0o600 as libc::c_uint,
+            );
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            let fd = std::os::fd::OwnedFd::from_raw_fd(fd);
+
+            let mapping = if libc::ftruncate(fd.as_raw_fd(), len as libc::off_t) != 0 {
+                Err(std::io::Error::last_os_error())
+            } else {
+                Self::map(fd.as_fd(), len, true)
+            };
+
+            match mapping {
+                Ok(mut mapping) => {
+                    mapping.name = Some(cname);
+                    Ok((mapping, name))
+                }
+                Err(err) => {
+                    libc::shm_unlink(cname.as_ptr());
+                    Err(err)
+                }
+            }
+        }
+    }
+
+    /// Maps the shared memory object created by the client read-only, if it
+    /// is owned by the client's user, and unlinks it, so that it doesn't
+    /// outlive the client if it dies
+    #[cfg(target_os = "macos")]
+    fn open(name: &str, len: usize, owner: u32) -> std::io::Result<Self> {
+        use std::os::fd::{AsFd, AsRawFd, FromRawFd};
+
+        // Only objects created by clients are unlinked
+        if !name.starts_with(SHM_PREFIX) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the breadcrumbs name was not created by a client",
+            ));
+        }
+
+        let cname = std::ffi::CString::new(name)?;
+
+        // SAFETY: syscalls, we own the fd once it is opened
+        unsafe {
+            let fd = libc::shm_open(cname.as_ptr(), libc::O_RDONLY);
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            let fd = std::os::fd::OwnedFd::from_raw_fd(fd);
+
+            let mut stat = std::mem::zeroed::<libc::stat>();
+            if libc::fstat(fd.as_raw_fd(), &mut stat) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            if stat.st_uid != owner {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    "the breadcrumbs are not owned by the client's user",
+                ));
+            }
+
+            libc::shm_unlink(cname.as_ptr());
+
+            // The size is set once, by the client, before it sends the name
+            if stat.st_size < len as libc::off_t {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "the breadcrumbs object is smaller than their size",
+                ));
+            }
+
+            Self::map(fd.as_fd(), len, false)
+        }
+    }
+
+    /// Creates a named file mapping backed by the page file
+    #[cfg(target_os = "windows")]
+    fn create(len: usize) -> std::io::Result<(Self, Share)> {
+        static COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+        let name = format!(
+            "Local\\minidumper-breadcrumbs-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        );
+        let wide: Vec<u16> = name.encode_utf16().chain(Some(0)).collect();
+
+        // SAFETY: syscalls
+        unsafe {
+            let handle = bindings::CreateFileMappingW(
+                bindings::INVALID_HANDLE_VALUE,
+                std::ptr::null(),
+                bindings::PAGE_READWRITE,
+                (len as u64 >> 32) as u32,
+                len as u32,
+                wide.as_ptr(),
+            );
+            if handle == 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            // Another process has a mapping of the same name, which it could
+            // write to as well
+            if bindings::GetLastError() == bindings::ERROR_ALREADY_EXISTS {
+                bindings::CloseHandle(handle);
+                return Err(std::io::ErrorKind::AlreadyExists.into());
+            }
+
+            let mapping = Self::map(handle, len, bindings::FILE_MAP_WRITE)?;
+            Ok((mapping, name))
+        }
+    }
+
+    /// Opens the file mapping created by the client read-only
+    #[cfg(target_os = "windows")]
+    fn open(name: &str, len: usize) -> std::io::Result<Self> {
+        let wide: Vec<u16> = name.encode_utf16().chain(Some(0)).collect();
+
+        // SAFETY: syscall
+        let handle =
+            unsafe { bindings::OpenFileMappingW(bindings::FILE_MAP_READ, 0, wide.as_ptr()) };
+        if handle == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Self::map(handle, len, bindings::FILE_MAP_READ)
+    }
+
+    /// Maps a view of the file mapping, taking ownership of the handle
+    #[cfg(target_os = "windows")]
+    fn map(handle: bindings::HANDLE, len: usize, access: u32) -> std::io::Result<Self> {
+        // SAFETY: syscalls, we own the handle
+        unsafe {
+            let ptr = bindings::MapViewOfFile(handle, access, 0, 0, len);
+            if ptr.is_null() {
+                let err = std::io::Error::last_os_error();
+                bindings::CloseHandle(handle);
+                return Err(err);
+            }
+
+            Ok(Self {
+                ptr: ptr.cast(),
+                len,
+                handle,
+            })
+        }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                // SAFETY: syscall, we own the mapping
+                unsafe {
+                    libc::munmap(self.ptr.cast(), self.len);
+                }
+
+                #[cfg(target_os = "macos")]
+                if let Some(name) = &self.name {
+                    // SAFETY: syscall
+                    unsafe {
+                        libc::shm_unlink(name.as_ptr());
+                    }
+                }
+            } else if #[cfg(target_os = "windows")] {
+                // SAFETY: syscalls, we own the view and the handle
+                unsafe {
+                    bindings::UnmapViewOfFile(self.ptr.cast());
+                    bindings::CloseHandle(self.handle);
+                }
+            }
+        }
+    }
+}
+
+/// The prefix of the names of the shared memory objects backing breadcrumbs on
+/// Macos
+#[cfg(target_os = "macos")]
+const SHM_PREFIX: &str = "/mdbc-";
+
+#[cfg(target_os = "windows")]
+#[allow(non_snake_case, clippy::upper_case_acronyms)]
+mod bindings {
+    pub type BOOL = i32;
+    pub type HANDLE = isize;
+
+    pub const INVALID_HANDLE_VALUE: HANDLE = -1;
+    pub const PAGE_READWRITE: u32 = 0x04;
+    pub const FILE_MAP_WRITE: u32 = 0x0002;
+    pub const FILE_MAP_READ: u32 = 0x0004;
+    pub const ERROR_ALREADY_EXISTS: u32 = 183;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn CreateFileMappingW(
+            hFile: HANDLE,
+            lpFileMappingAttributes: *const std::ffi::c_void,
+            flProtect: u32,
+            dwMaximumSizeHigh: u32,
+            dwMaximumSizeLow: u32,
+            lpName: *const u16,
+        ) -> HANDLE;
+        pub fn OpenFileMappingW(
+            dwDesiredAccess: u32,
+            bInheritHandle: BOOL,
+            lpName: *const u16,
+        ) -> HANDLE;
+        pub fn MapViewOfFile(
+            hFileMappingObject: HANDLE,
+            dwDesiredAccess: u32,
+            dwFileOffsetHigh: u32,
+            dwFileOffsetLow: u32,
+            dwNumberOfBytesToMap: usize,
+        ) -> *mut std::ffi::c_void;
+        pub fn UnmapViewOfFile(lpBaseAddress: *const std::ffi::c_void) -> BOOL;
+        pub fn CloseHandle(hObject: HANDLE) -> BOOL;
+        pub fn GetLastError() -> u32;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn region(capacity: usize) -> Vec<AtomicU64> {
+        let words: Vec<_> = (0..region_size(capacity) / 8)
+            .map(|_| AtomicU64::new(0))
+            .collect();
+        init(&words, capacity);
+        words
+    }
+
+    #[test]
+    fn wraps_around() {
+        let words = region(4);
+        assert_eq!(validate(&words), Some(4));
+        assert!(snapshot(&words, 4).is_empty());
+
+        for i in 0..10u32 {
+            append(&words, 4, i, format!("event {i}").as_bytes());
+        }
+
+        let crumbs = snapshot(&words, 4);
+        assert_eq!(
+            crumbs.iter().map(|c| c.seq).collect::<Vec<_>>(),
+            [7, 8, 9, 10]
+        );
+        assert_eq!(crumbs[0].kind, 6);
+        assert_eq!(crumbs[3].data, b"event 9");
+        assert!(crumbs[3].realtime_ns > 0);
+
+        // Data that doesn't fit is truncated
+        append(&words, 4, 0, &[0xab; MAX_BREADCRUMB_LEN + 10][..]);
+        assert_eq!(
+            snapshot(&words, 4).last().unwrap().data,
+            [0xab; MAX_BREADCRUMB_LEN]
+        );
+    }
+
+    #[test]
+    fn skips_incomplete_records() {
+        let words = region(2);
+        append(&words, 2, 1, b"done");
+
+        // A writer that died in the middle of writing the second record
+        let record = &words[HEADER_WORDS + RECORD_WORDS..];
+        words[2].store(2, Ordering::Relaxed);
+        record[0].store(2 << 1 | 1, Ordering::Relaxed);
+        record[2].store(u64::MAX, Ordering::Relaxed);
+
+        let crumbs = snapshot(&words, 2);
+        assert_eq!(crumbs.len(), 1);
+        assert_eq!(crumbs[0].data, b"done");
+
+        // The record is never claimed by another writer, so it stays skipped,
+        // rather than becoming torn
+        append(&words, 2, 1, b"next");
+        append(&words, 2, 1, b"dropped");
+        assert_eq!(
+            snapshot(&words, 2)
+                .into_iter()
+                .map(|c| c.data)
+                .collect::<Vec<_>>(),
+            [b"next".to_vec()]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let words = region(2);
+        assert_eq!(validate(&words[..words.len() - 1]), None);
+
+        words[1].store(
+            (MAX_BREADCRUMBS as u64 + 1) | (RECORD_SIZE as u64) << 32,
+            Ordering::Relaxed,
+        );
+        assert_eq!(validate(&words), None);
+
+        let words = region(2);
+        words[0].store(MAGIC | (FORMAT_VERSION + 1) << 32, Ordering::Relaxed);
+        assert_eq!(validate(&words), None);
+    }
+
+    #[test]
+    fn encodes_stream() {
+        let stream = Breadcrumb::to_stream(&[
+            Breadcrumb {
+                seq: 3,
+                realtime_ns: 10,
+                kind: 2,
+                data: b"level".to_vec(),
+            },
+            Breadcrumb {
+                seq: 4,
+                realtime_ns: 11,
+                kind: 5,
+                data: Vec::new(),
+            },
+        ]);
+
+        assert_eq!(&stream[..8], &[1, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(&stream[8..16], &3u64.to_le_bytes());
+        assert_eq!(&stream[28..32], &5u32.to_le_bytes());
+        assert_eq!(&stream[32..37], b"level");
+        assert_eq!(&stream[40..48], &4u64.to_le_bytes());
+        assert_eq!(stream.len(), 64);
+    }
+}
@@ -0,0 +1,174 @@
+//! A C API for the [`Client`], so that code written in other languages can
+//! share the connection with the Rust code of the same process.
+//!
+//! The header for this API is `include/minidumper.h`, which is generated by
+//! running `cbindgen --config cbindgen.toml --crate minidumper --output include/minidumper.h`
+//! in the crate root.
+//!
+//! All of the functions can be called from any thread, including threads not
+//! created by Rust, and a [`Client`] can be used from several threads at once.
+
+#![allow(unsafe_code)]
+
+use crate::{Client, Error};
+use std::{
+    ffi::{c_char, c_int, CStr},
+    panic::{catch_unwind, AssertUnwindSafe},
+};
+
+/// The call succeeded
+pub const MINIDUMPER_OK: c_int = 0;
+/// A pointer argument was null, the name was not valid UTF-8, or the message
+/// kind was too large, ie. [`Error::InvalidMessageKind`]
+pub const MINIDUMPER_ERR_INVALID_ARGUMENT: c_int = -1;
+/// The socket name was invalid or unsupported on the current platform, ie.
+/// [`Error::InvalidName`], [`Error::UnsupportedSocketName`], or, on Macos,
+/// `Error::InvalidPortName`
+pub const MINIDUMPER_ERR_INVALID_NAME: c_int = -2;
+/// An I/O error occurred communicating with the server, ie. [`Error::Io`], or,
+/// on Linux/Android, `Error::ConnectDenied`, or, on Macos, `Error::PortError`
+pub const MINIDUMPER_ERR_IO: c_int = -3;
+/// The server sent an invalid response, ie. [`Error::ProtocolError`], or, on
+/// Windows and Macos, `Error::Scroll`
+pub const MINIDUMPER_ERR_PROTOCOL: c_int = -4;
+/// Any other error, including a panic, which is caught rather than unwinding
+/// into the caller
+pub const MINIDUMPER_ERR_OTHER: c_int = -5;
+/// The server failed to write the requested minidump, ie.
+/// [`Error::ServerDumpFailed`]
+pub const MINIDUMPER_ERR_DUMP_FAILED: c_int = -6;
+/// The server was busy, and kept deferring the crash request until the client
+/// ran out of retries, ie. [`Error::ServerBusy`]
+pub const MINIDUMPER_ERR_SERVER_BUSY: c_int = -7;
+
+impl Error {
+    /// Maps the error to one of the `MINIDUMPER_ERR_*` codes
+    fn code(&self) -> c_int {
+        match self {
+            Self::InvalidName | Self::UnsupportedSocketName => MINIDUMPER_ERR_INVALID_NAME,
+            Self::InvalidMessageKind(_) => MINIDUMPER_ERR_INVALID_ARGUMENT,
+            #[cfg(target_os = "macos")]
+            Self::InvalidPortName => MINIDUMPER_ERR_INVALID_NAME,
+            #[cfg(target_os = "macos")]
+            Self::PortError(_) => MINIDUMPER_ERR_IO,
+            Self::Io(_) => MINIDUMPER_ERR_IO,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Self::ConnectDenied { .. } => MINIDUMPER_ERR_IO,
+            Self::ProtocolError(_) => MINIDUMPER_ERR_PROTOCOL,
+            Self::ServerDumpFailed { .. } => MINIDUMPER_ERR_DUMP_FAILED,
+            Self::ServerBusy { .. } => MINIDUMPER_ERR_SERVER_BUSY,
+            #[cfg(any(target_os = "windows", target_os = "macos"))]
+            Self::Scroll(_) => MINIDUMPER_ERR_PROTOCOL,
+            _ => MINIDUMPER_ERR_OTHER,
+        }
+    }
+}
+
+/// Runs `f`, mapping its result, or a panic, to a code
+#[inline]
+fn call(f: impl FnOnce() -> Result<(), Error>) -> c_int {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => MINIDUMPER_OK,
+        Ok(Err(err)) => err.code(),
+        Err(_panic) => MINIDUMPER_ERR_OTHER,
+    }
+}
+
+/// Connects to the server with the specified name, see [`Client::with_name`].
+///
+/// Returns null if the connection fails. The client must be destroyed with
+/// [`minidumper_client_destroy`].
+///
+/// # Safety
+///
+/// `name` must be null, or a valid nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn minidumper_client_connect(name: *const c_char) -> *mut Client {
+    if name.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    let name = match CStr::from_ptr(name).to_str() {
+        Ok(name) => name,
+        Err(_err) => return std::ptr::null_mut(),
+    };
+
+    match catch_unwind(|| Client::with_name(name)) {
+        Ok(Ok(client)) => Box::into_raw(Box::new(client)),
+        Ok(Err(err)) => {
+            log::error!("failed to connect to minidumper server: {err}");
+            std::ptr::null_mut()
+        }
+        Err(_panic) => std::ptr::null_mut(),
+    }
+}
+
+/// Sends a message to the server, see [`Client::send_message`].
+///
+/// Returns [`MINIDUMPER_OK`] on success, or one of the `MINIDUMPER_ERR_*`
+/// codes.
+///
+/// # Safety
+///
+/// `client` must be null, or a client returned by [`minidumper_client_connect`]
+/// that has not been destroyed. `buf` must be null, or valid for reads of
+/// `len` bytes. `buf` may only be null if `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn minidumper_client_send_message(
+    client: *const Client,
+    kind: u32,
+    buf: *const u8,
+    len: usize,
+) -> c_int {
+    let client = match client.as_ref() {
+        Some(client) => client,
+        None => return MINIDUMPER_ERR_INVALID_ARGUMENT,
+    };
+
+    let buf = if len == 0 {
+        &[]
+    } else if buf.is_null() {
+        return MINIDUMPER_ERR_INVALID_ARGUMENT;
+    } else {
+        std::slice::from_raw_parts(buf, len)
+    };
+
+    call(|| client.send_message(kind, buf))
+}
+
+/// Requests that the server write a minidump for the specified crash context,
+/// see [`Client::request_dump`].
+///
+/// Returns [`MINIDUMPER_OK`] once the server has finished writing the
+/// minidump, or one of the `MINIDUMPER_ERR_*` codes.
+///
+/// # Safety
+///
+/// `client` must be null, or a client returned by [`minidumper_client_connect`]
+/// that has not been destroyed. `crash_context` must be null, or point to a
+/// valid [`crash_context::CrashContext`], eg. one received by a
+/// `crash_handler::CrashEvent`.
+#[no_mangle]
+pub unsafe extern "C" fn minidumper_client_request_dump(
+    client: *const Client,
+    crash_context: *const crash_context::CrashContext,
+) -> c_int {
+    match (client.as_ref(), crash_context.as_ref()) {
+        (Some(client), Some(crash_context)) => call(|| client.request_dump(crash_context)),
+        _ => MINIDUMPER_ERR_INVALID_ARGUMENT,
+    }
+}
+
+/// Disconnects and destroys a client. Does nothing if `client` is null.
+///
+/// # Safety
+///
+/// `client` must be null, or a client returned by [`minidumper_client_connect`]
+/// that has not already been destroyed, and that is not in use on any other
+/// thread.
+#[no_mangle]
+pub unsafe extern "C" fn minidumper_client_destroy(client: *mut Client) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
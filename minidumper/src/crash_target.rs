@@ -0,0 +1,142 @@
+//! The crash request sent to the server on Windows, which can also be sent on
+//! behalf of another process, see [`WindowsCrashTarget`]
+
+use crate::Error;
+
+/// The crash of a process that the server is asked to write a minidump of,
+/// which, unlike a [`crash_context::CrashContext`], can describe a process
+/// other than the one sending the request, see
+/// [`crate::Client::request_dump_for`].
+///
+/// This is how eg. a watchdog process that has detected that a third process
+/// is hung, and has attached to it via `DebugActiveProcess`, can have a
+/// minidump of it written by an existing server. The server only accepts such
+/// proxy requests if it allows them, see
+/// [`crate::ServerOptions::allow_proxy_dumps`].
+#[derive(Copy, Clone, Debug)]
+pub struct WindowsCrashTarget {
+    pub(crate) exception_pointers: usize,
+    pub(crate) process_id: u32,
+    pub(crate) thread_id: u32,
+    pub(crate) exception_code: i32,
+    pub(crate) timestamps: crash_context::CrashTimestamps,
+    pub(crate) thread_name: crash_context::ThreadName,
+    pub(crate) main_thread: crash_context::MainThread,
+}
+
+impl WindowsCrashTarget {
+    /// Creates a target for the specified thread of the process.
+    ///
+    /// `exception_pointers` is the address of an `EXCEPTION_POINTERS` in the
+    /// memory of the _target_ process, not the one calling this, which must
+    /// stay valid until the minidump has been written, as that is where
+    /// `MiniDumpWriteDump` reads the exception, and its context, from. For a
+    /// process that hasn't crashed, one can be written to its memory as
+    /// `write_hung_minidump` does.
+    ///
+    /// The timestamps default to the current time, the thread name to empty,
+    /// and whether the thread is the main thread to unknown.
+    ///
+    /// # Errors
+    ///
+    /// Either id is 0, or the address is null or not aligned for an
+    /// `EXCEPTION_POINTERS`, as [`Error::InvalidCrashTarget`]
+    pub fn new(
+        process_id: u32,
+        thread_id: u32,
+        exception_pointers: usize,
+        exception_code: i32,
+    ) -> Result<Self, Error> {
+        if process_id == 0 {
+            return Err(Error::InvalidCrashTarget("the process id is 0"));
+        }
+
+        if thread_id == 0 {
+            return Err(Error::InvalidCrashTarget("the thread id is 0"));
+        }
+
+        if exception_pointers == 0 {
+            return Err(Error::InvalidCrashTarget(
+                "the exception pointers address is null",
+            ));
+        }
+
+        if exception_pointers % std::mem::align_of::<crash_context::EXCEPTION_POINTERS>() != 0 {
+            return Err(Error::InvalidCrashTarget(
+                "the exception pointers address is misaligned",
+            ));
+        }
+
+        Ok(Self {
+            exception_pointers,
+            process_id,
+            thread_id,
+            exception_code,
+            timestamps: crash_context::CrashTimestamps::now(0),
+            thread_name: crash_context::ThreadName::default(),
+            main_thread: crash_context::MainThread::UNKNOWN,
+        })
+    }
+
+    /// Sets the timestamps of the crash, see [`crate::DumpMetadata::timestamps`]
+    #[inline]
+    pub fn timestamps(mut self, timestamps: crash_context::CrashTimestamps) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Sets the name of the thread, see [`crate::DumpMetadata::thread_name`]
+    #[inline]
+    pub fn thread_name(mut self, thread_name: crash_context::ThreadName) -> Self {
+        self.thread_name = thread_name;
+        self
+    }
+
+    /// Sets whether the thread is the main thread of the process, see
+    /// [`crate::DumpMetadata::is_main_thread`]
+    #[inline]
+    pub fn main_thread(mut self, is_main_thread: Option<bool>) -> Self {
+        self.main_thread = is_main_thread.into();
+        self
+    }
+
+    /// The id of the process the minidump is written of
+    #[inline]
+    pub fn process_id(&self) -> u32 {
+        self.process_id
+    }
+
+    /// The id of the thread that "crashed"
+    #[inline]
+    pub fn thread_id(&self) -> u32 {
+        self.thread_id
+    }
+
+    /// The address of the `EXCEPTION_POINTERS` in the target process
+    #[inline]
+    pub fn exception_pointers(&self) -> usize {
+        self.exception_pointers
+    }
+
+    /// The top level exception code
+    #[inline]
+    pub fn exception_code(&self) -> i32 {
+        self.exception_code
+    }
+}
+
+impl From<&crash_context::CrashContext> for WindowsCrashTarget {
+    /// The crash of the current process, as sent by
+    /// [`crate::Client::request_dump`]
+    fn from(cc: &crash_context::CrashContext) -> Self {
+        Self {
+            exception_pointers: cc.exception_pointers as usize,
+            process_id: cc.process_id,
+            thread_id: cc.thread_id,
+            exception_code: cc.exception_code,
+            timestamps: cc.timestamps,
+            thread_name: cc.thread_name,
+            main_thread: cc.main_thread,
+        }
+    }
+}
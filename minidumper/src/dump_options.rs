@@ -0,0 +1,202 @@
+//! Provides [`DumpOptions`], which control what is written to the minidump for
+//! a crash, see [`crate::ServerHandler::dump_options`]
+
+/// What memory of the crashed process is written to a minidump, see
+/// [`crate::DumpMetadata::mode`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DumpMode {
+    /// The memory `minidump-writer` writes by default, eg. the stacks of the
+    /// threads, and the memory around the instruction pointer of the crashing
+    /// thread
+    #[default]
+    Normal,
+    /// In addition to [`Self::Normal`], every readable private mapping of the
+    /// process, eg. the heap, except for file backed mappings that are read
+    /// only, as their contents are in the file, up to a total of `max_bytes`
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    Full {
+        /// The most bytes of mappings that are written, mappings past that
+        /// are omitted, in the order they appear in `/proc/<pid>/maps`
+        max_bytes: u64,
+    },
+}
+
+/// When [`crate::MinidumpBinary::contents`] is provided, see
+/// [`DumpOptions::contents`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ContentsMode {
+    /// The contents are provided if the minidump is written in memory before
+    /// being written to the file, ie. on every platform except Windows, where
+    /// `MiniDumpWriteDump` writes directly to the file
+    #[default]
+    IfAvailable,
+    /// The contents are always provided, on Windows by reading them back from
+    /// the file once the minidump has been written
+    Always,
+}
+
+/// Options for writing the minidump for a crash, returned by
+/// [`crate::ServerHandler::dump_options`]
+#[derive(Clone, Debug, Default)]
+pub struct DumpOptions {
+    pub(crate) mode: DumpMode,
+    pub(crate) contents: ContentsMode,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) size_limit: Option<u64>,
+}
+
+impl DumpOptions {
+    /// Writes every readable private mapping of the crashed process, up to a
+    /// total of `max_bytes`, see [`DumpMode::Full`].
+    ///
+    /// Note that these minidumps are huge, and that, as with every minidump,
+    /// its entire contents are held in memory while it is written, and
+    /// provided as [`crate::MinidumpBinary::contents`], so `max_bytes` bounds
+    /// the memory used by the server as much as the size of the minidump.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[inline]
+    pub fn full_memory(mut self, max_bytes: u64) -> Self {
+        self.mode = DumpMode::Full { max_bytes };
+        self
+    }
+
+    /// The size, in bytes, `minidump-writer` tries to keep the minidump
+    /// under, by truncating thread stacks, unlimited by default.
+    ///
+    /// The mappings written for [`Self::full_memory`] count against the
+    /// limit, ie. no more than the limit are written, regardless of their
+    /// `max_bytes`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[inline]
+    pub fn size_limit(mut self, limit: Option<u64>) -> Self {
+        self.size_limit = limit;
+        self
+    }
+
+    /// When the contents of the minidump are provided to
+    /// [`crate::ServerHandler::on_minidump_created`], see [`ContentsMode`]
+    #[inline]
+    pub fn contents(mut self, mode: ContentsMode) -> Self {
+        self.contents = mode;
+        self
+    }
+
+    /// The mappings to write in addition to the memory `minidump-writer`
+    /// writes itself, if any
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) fn app_memory(
+        &self,
+        pid: u32,
+    ) -> std::io::Result<Vec<minidump_writer::app_memory::AppMemory>> {
+        let max_bytes = match self.mode {
+            DumpMode::Normal => return Ok(Vec::new()),
+            DumpMode::Full { max_bytes } => max_bytes.min(self.size_limit.unwrap_or(u64::MAX)),
+        };
+
+        let maps = std::fs::read_to_string(format!("/proc/{pid}/maps"))?;
+
+        Ok(full_memory_regions(&maps, max_bytes)
+            .into_iter()
+            .map(|(ptr, length)| minidump_writer::app_memory::AppMemory { ptr, length })
+            .collect())
+    }
+}
+
+/// The regions of the mappings in `/proc/<pid>/maps` that are written for
+/// [`DumpMode::Full`], as `(address, length)`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn full_memory_regions(maps: &str, max_bytes: u64) -> Vec<(usize, usize)> {
+    let mut remaining = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+
+    maps.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (start, end) = fields.next()?.split_once('-')?;
+            let perms = fields.next()?.as_bytes();
+            let _offset = fields.next()?;
+            let _dev = fields.next()?;
+            let inode = fields.next()?;
+            let path = fields.next().unwrap_or_default();
+
+            let readable = perms.first() == Some(&b'r');
+            let writable = perms.get(1) == Some(&b'w');
+            let private = perms.get(3) == Some(&b'p');
+
+            // The contents of file backed mappings that have never been
+            // writable are already in the file, device mappings may not be
+            // readable like normal memory, and the vvar pages aren't readable
+            // via ptrace
+            if !readable
+                || !private
+                || (inode != "0" && !writable)
+                || path.starts_with("/dev/")
+                || path.starts_with("[vvar")
+                || path == "[vsyscall]"
+            {
+                return None;
+            }
+
+            let start = usize::from_str_radix(start, 16).ok()?;
+            let end = usize::from_str_radix(end, 16).ok()?;
+            Some((start, end.checked_sub(start)?))
+        })
+        .map_while(|(start, len)| {
+            let len = len.min(remaining);
+            remaining -= len;
+            (len > 0).then_some((start, len))
+        })
+        .collect()
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod test {
+    const MAPS: &str = "\
+55d0c0800000-55d0c0900000 r--p 00000000 fd:01 1234 /usr/bin/game
+55d0c0900000-55d0c0a00000 r-xp 00100000 fd:01 1234 /usr/bin/game
+55d0c0a00000-55d0c0a10000 rw-p 00200000 fd:01 1234 /usr/bin/game
+55d0c0a10000-55d0c0a31000 rw-p 00000000 00:00 0    [heap]
+7f0000000000-7f0000001000 rw-s 00000000 00:01 99   /memfd:shared (deleted)
+7f0000001000-7f0000002000 ---p 00000000 00:00 0
+7f0000002000-7f0000003000 rw-p 00000000 00:06 42   /dev/dri/renderD128
+7ffd00000000-7ffd00021000 rw-p 00000000 00:00 0    [stack]
+7ffd00100000-7ffd00104000 r--p 00000000 00:00 0    [vvar]
+7ffd00104000-7ffd00106000 r-xp 00000000 00:00 0    [vdso]
+ffffffffff600000-ffffffffff601000 --xp 00000000 00:00 0 [vsyscall]
+";
+
+    #[test]
+    fn selects_private_mappings() {
+        assert_eq!(
+            super::full_memory_regions(MAPS, u64::MAX),
+            [
+                (0x55d0_c0a0_0000, 0x1_0000),
+                (0x55d0_c0a1_0000, 0x2_1000),
+                (0x7ffd_0000_0000, 0x2_1000),
+                (0x7ffd_0010_4000, 0x2000),
+            ]
+        );
+    }
+
+    #[test]
+    fn caps_total_size() {
+        // The heap is truncated, and nothing after it is included
+        assert_eq!(
+            super::full_memory_regions(MAPS, 0x1_8000),
+            [(0x55d0_c0a0_0000, 0x1_0000), (0x55d0_c0a1_0000, 0x8000)]
+        );
+
+        assert!(super::full_memory_regions(MAPS, 0).is_empty());
+
+        // The size limit caps the mappings too
+        let options = crate::DumpOptions::default()
+            .full_memory(u64::MAX)
+            .size_limit(Some(0));
+        assert!(options.app_memory(std::process::id()).unwrap().is_empty());
+        assert!(crate::DumpOptions::default()
+            .app_memory(std::process::id())
+            .unwrap()
+            .is_empty());
+    }
+}
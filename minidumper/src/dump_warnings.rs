@@ -0,0 +1,237 @@
+//! Collects the problems that degraded a minidump that was still written, see
+//! [`DumpWarning`]
+
+use std::cell::RefCell;
+
+thread_local! {
+    /// The warnings collected while a minidump is written on this thread,
+    /// `None` when none is being written
+    static CAPTURE: RefCell<Option<Vec<DumpWarning>>> = const { RefCell::new(None) };
+}
+
+/// Something that went wrong while writing a minidump which, unlike an error,
+/// didn't prevent it from being written, but means that it is missing some of
+/// what it would normally contain, see `MinidumpBinary::dump_warnings`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DumpWarning {
+    /// What is missing from the minidump
+    pub kind: DumpWarningKind,
+    /// The description of what went wrong, as it was logged
+    pub message: String,
+}
+
+/// What a [`DumpWarning`] is about
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DumpWarningKind {
+    /// The mappings of the process couldn't be read, so only the memory
+    /// written in [`crate::DumpMode::Normal`] was, regardless of the mode the
+    /// handler requested
+    FullMemory,
+    /// The process info and breadcrumbs streams couldn't be added to the
+    /// minidump, but are still reported in the `DumpMetadata`
+    Streams,
+    /// The chain of exception records couldn't be read from the crashed
+    /// process, so `DumpMetadata::exception_record` is `None`
+    ExceptionRecord,
+    /// The minidump was written, but couldn't be flushed, or read back for
+    /// [`crate::ContentsMode::Always`]
+    File,
+    /// `minidump-writer` logged a warning, or error, eg. because it couldn't
+    /// read part of the process. These are only captured if
+    /// [`DumpWarningLogger`] is installed.
+    Writer,
+}
+
+/// A [`log::Log`] that forwards every record to another logger, while also
+/// capturing the warnings and errors that `minidump-writer` logs while a
+/// minidump is being written, as [`DumpWarningKind::Writer`] warnings.
+///
+/// `minidump-writer` only reports its problems via `log`, and the logger is
+/// process wide, so this must be installed in place of the application's own
+/// logger for them to be reported.
+pub struct DumpWarningLogger {
+    inner: Box<dyn log::Log>,
+}
+
+impl DumpWarningLogger {
+    /// Wraps the logger that the application would otherwise install
+    #[inline]
+    pub fn new(inner: Box<dyn log::Log>) -> Self {
+        Self { inner }
+    }
+
+    /// Installs this as the process wide logger, raising the maximum log
+    /// level to [`log::LevelFilter::Warn`] if it is lower, so that the
+    /// warnings are emitted at all. The inner logger still only receives the
+    /// records it has enabled.
+    ///
+    /// # Errors
+    ///
+    /// A logger has already been installed
+    pub fn install(self) -> Result<(), log::SetLoggerError> {
+        log::set_logger(Box::leak(Box::new(self)))?;
+
+        if log::max_level() < log::LevelFilter::Warn {
+            log::set_max_level(log::LevelFilter::Warn);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the record is one of `minidump-writer`'s warnings or errors
+    #[inline]
+    fn is_writer_warning(metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::Level::Warn && metadata.target().starts_with("minidump_writer")
+    }
+}
+
+impl log::Log for DumpWarningLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.inner.enabled(metadata) || (Self::is_writer_warning(metadata) && is_capturing())
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if Self::is_writer_warning(record.metadata()) {
+            push(DumpWarning {
+                kind: DumpWarningKind::Writer,
+                message: record.args().to_string(),
+            });
+        }
+
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Collects the warnings raised on the current thread until it is finished,
+/// or dropped
+#[cfg(feature = "minidump-writer")]
+pub(crate) struct Capture(());
+
+#[cfg(feature = "minidump-writer")]
+impl Capture {
+    pub(crate) fn start() -> Self {
+        CAPTURE.with(|capture| *capture.borrow_mut() = Some(Vec::new()));
+        Self(())
+    }
+
+    /// The warnings raised since the capture was started
+    pub(crate) fn finish(self) -> Vec<DumpWarning> {
+        let warnings = CAPTURE.with(|capture| capture.borrow_mut().take());
+        drop(self);
+        warnings.unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "minidump-writer")]
+impl Drop for Capture {
+    fn drop(&mut self) {
+        CAPTURE.with(|capture| *capture.borrow_mut() = None);
+    }
+}
+
+#[inline]
+fn is_capturing() -> bool {
+    CAPTURE.with(|capture| capture.borrow().is_some())
+}
+
+/// Adds the warning to the capture of the current thread, if there is one
+fn push(warning: DumpWarning) {
+    CAPTURE.with(|capture| {
+        if let Some(warnings) = capture.borrow_mut().as_mut() {
+            warnings.push(warning);
+        }
+    });
+}
+
+/// Logs the warning, and adds it to the capture of the current thread
+#[cfg(any(feature = "minidump-writer", target_os = "windows"))]
+pub(crate) fn warn(kind: DumpWarningKind, message: String) {
+    log::warn!("{message}");
+    push(DumpWarning { kind, message });
+}
+
+#[cfg(all(test, feature = "minidump-writer"))]
+mod test {
+    use super::*;
+    use log::Log;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Counts the errors it is asked to log
+    struct Counter(Arc<AtomicUsize>);
+
+    impl Log for Counter {
+        fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+            metadata.level() <= log::Level::Error
+        }
+
+        fn log(&self, _record: &log::Record<'_>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn record(logger: &DumpWarningLogger, level: log::Level, target: &str, message: &str) {
+        logger.log(
+            &log::Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("{message}"))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn captures_writer_warnings() {
+        let forwarded = Arc::new(AtomicUsize::new(0));
+        let logger = DumpWarningLogger::new(Box::new(Counter(forwarded.clone())));
+        let target = "minidump_writer::linux::ptrace_dumper";
+
+        // Nothing is captured outside of a capture
+        record(&logger, log::Level::Warn, target, "before");
+
+        let capture = Capture::start();
+        record(&logger, log::Level::Warn, target, "thread vanished");
+        record(&logger, log::Level::Error, target, "mapping vanished");
+        // Only warnings, and only from minidump-writer
+        record(&logger, log::Level::Info, target, "info");
+        record(&logger, log::Level::Warn, "minidumper::ipc", "ours");
+        warn(DumpWarningKind::Streams, "no streams".to_owned());
+
+        // Other threads aren't captured
+        std::thread::scope(|s| {
+            s.spawn(|| record(&logger, log::Level::Warn, target, "other thread"));
+        });
+
+        let warnings = capture.finish();
+        assert_eq!(
+            warnings,
+            [
+                (DumpWarningKind::Writer, "thread vanished"),
+                (DumpWarningKind::Writer, "mapping vanished"),
+                (DumpWarningKind::Streams, "no streams"),
+            ]
+            .map(|(kind, message)| DumpWarning {
+                kind,
+                message: message.to_owned()
+            })
+        );
+
+        assert!(!is_capturing());
+        record(&logger, log::Level::Warn, target, "after");
+        assert!(Capture::start().finish().is_empty());
+
+        // The inner logger still only gets the records it enabled
+        assert_eq!(forwarded.load(Ordering::Relaxed), 1);
+    }
+}
@@ -5,13 +5,38 @@ pub enum Error {
     /// The provided socket name or path was invalid
     #[error("the socket name is invalid")]
     InvalidName,
+    /// The provided socket name is of a kind that is not supported on the
+    /// current platform, eg. an abstract name on Windows
+    #[error("the socket name is not supported on this platform")]
+    UnsupportedSocketName,
+    /// The server's socket exists, but connecting to it was denied, rather than
+    /// refused, eg. by the permissions of a socket path, or by `SELinux`. On
+    /// Android, `SELinux` denies apps from connecting to many abstract sockets
+    /// from API level 28, see `SocketName::android_default` for a name that
+    /// the app is allowed to connect to.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[error(
+        "connecting to the {} socket was denied, eg. by SELinux or its permissions",
+        if *abstract_name { "abstract" } else { "path" }
+    )]
+    ConnectDenied {
+        /// Whether the socket name was an abstract name, rather than a path
+        abstract_name: bool,
+        /// The error returned by `connect`, ie. `EACCES` or `EPERM`
+        source: std::io::Error,
+    },
     #[cfg(target_os = "macos")]
     /// The provided socket name or path was invalid as a Mach port name
     #[error("the mach port name is invalid")]
     InvalidPortName,
+    /// Another server in this process already created a Mach port with the
+    /// same name, see [`crate::ServerSet`]
+    #[cfg(target_os = "macos")]
+    #[error("the mach port name is already in use by another server")]
+    PortNameInUse,
     /// An error occurred while creating or communicating with a Mach port
     #[cfg(target_os = "macos")]
-    #[error("the mach port name is invalid")]
+    #[error("mach port error: {0}")]
     PortError(#[from] crash_context::ipc::Error),
     /// An I/O or other syscall failed
     #[error(transparent)]
@@ -21,15 +46,18 @@ pub enum Error {
     #[error("client process requesting crash dump has an unknown or invalid pid")]
     UnknownClientPid,
     /// An error occurred during minidump generation
-    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[cfg(all(
+        feature = "minidump-writer",
+        any(target_os = "linux", target_os = "android")
+    ))]
     #[error(transparent)]
     Writer(Box<minidump_writer::errors::WriterError>),
     /// An error occurred during minidump generation
-    #[cfg(target_os = "windows")]
+    #[cfg(all(feature = "minidump-writer", target_os = "windows"))]
     #[error(transparent)]
     Writer(#[from] minidump_writer::errors::Error),
     /// An error occurred during minidump generation
-    #[cfg(target_os = "macos")]
+    #[cfg(all(feature = "minidump-writer", target_os = "macos"))]
     #[error(transparent)]
     Writer(#[from] minidump_writer::errors::WriterError),
     /// An error occurred reading or writing binary data
@@ -38,9 +66,111 @@ pub enum Error {
     Scroll(#[from] scroll::Error),
     #[error("protocol error occurred: {0}")]
     ProtocolError(&'static str),
+    /// The server failed to write the minidump requested by the client, so
+    /// no report exists for the crash
+    #[error("the server failed to write the minidump: {category:?} (os error {code:?})")]
+    ServerDumpFailed {
+        /// The step that failed
+        category: DumpFailureCategory,
+        /// The OS error code of the failure, eg. `ENOSPC`, if it was caused by
+        /// one
+        code: Option<i32>,
+    },
+    /// The server deferred the crash request more times than the client was
+    /// configured to retry it, see [`crate::Client::crash_retries`]
+    #[error("the server is busy and deferred the crash request, retry after {retry_after:?}")]
+    ServerBusy {
+        /// The delay the server last asked the client to wait before retrying
+        retry_after: std::time::Duration,
+    },
+    /// The kind of a user message is too large, as it would collide with the
+    /// kinds used internally by the protocol, see [`crate::MAX_MESSAGE_KIND`]
+    #[error("the message kind {0} is larger than the maximum of {max}", max = crate::MAX_MESSAGE_KIND)]
+    InvalidMessageKind(u32),
+    /// Breadcrumbs were created with a capacity of 0, or larger than
+    /// [`crate::MAX_BREADCRUMBS`], see [`crate::Client::create_breadcrumbs`]
+    #[error("the breadcrumb capacity {0} is not within 1..={max}", max = crate::MAX_BREADCRUMBS)]
+    InvalidBreadcrumbCapacity(usize),
+    /// The server is too old to support the feature
+    #[error("the server does not support {0}")]
+    UnsupportedByServer(&'static str),
+    /// A [`crate::WindowsCrashTarget`] was created with an invalid field
+    #[cfg(target_os = "windows")]
+    #[error("invalid crash target: {0}")]
+    InvalidCrashTarget(&'static str),
+    /// A value could not be encoded as a typed message, see
+    /// [`crate::Client::send_typed`]
+    #[cfg(feature = "serde")]
+    #[error("failed to encode typed message")]
+    Encode(#[source] bincode::Error),
+    /// There is no IPC on the target, eg. `wasm32-unknown-unknown`, so neither
+    /// a [`crate::Client`] nor a [`crate::Server`] can be created
+    #[error("the client and server are not supported on this target")]
+    Unsupported,
+}
+
+/// The step in which the server failed to write a minidump, see
+/// [`Error::ServerDumpFailed`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DumpFailureCategory {
+    /// [`crate::ServerHandler::create_minidump_file`] failed
+    CreateFile,
+    /// Writing the minidump to the file failed
+    Write,
+    /// The server wasn't allowed to `ptrace` the client, so it couldn't read
+    /// its state. The client must be dumpable, and allow the server as its
+    /// ptracer, until the minidump has been written, which the crash handler
+    /// only ensures for the duration of its callback, see
+    /// `crash_handler::DumpableGuard`. Only reported on Linux/Android.
+    PtraceDenied,
+    /// Any other failure, including ones unknown to this version of the client
+    Other,
+}
+
+impl DumpFailureCategory {
+    #[inline]
+    pub(crate) fn from_u8(category: u8) -> Self {
+        match category {
+            1 => Self::CreateFile,
+            2 => Self::Write,
+            3 => Self::PtraceDenied,
+            _ => Self::Other,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn as_u8(self) -> u8 {
+        match self {
+            Self::Other => 0,
+            Self::CreateFile => 1,
+            Self::Write => 2,
+            Self::PtraceDenied => 3,
+        }
+    }
+}
+
+/// Retrieves the OS error code of the first [`std::io::Error`] in the chain
+/// of sources of the error, if any
+#[cfg_attr(not(feature = "minidump-writer"), allow(dead_code))]
+pub(crate) fn os_error_code(err: &(dyn std::error::Error + 'static)) -> Option<i32> {
+    let mut err = Some(err);
+
+    while let Some(cur) = err {
+        if let Some(io) = cur.downcast_ref::<std::io::Error>() {
+            return io.raw_os_error();
+        }
+
+        err = cur.source();
+    }
+
+    None
 }
 
-#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg(all(
+    feature = "minidump-writer",
+    any(target_os = "linux", target_os = "android")
+))]
 impl From<minidump_writer::errors::WriterError> for Error {
     fn from(we: minidump_writer::errors::WriterError) -> Self {
         Self::Writer(Box::new(we))
@@ -0,0 +1,232 @@
+//! Writes minidumps of processes that haven't crashed, eg. because they are
+//! hung, and so will never send a crash request, see [`write_hung_minidump`]
+
+#![allow(unsafe_code)]
+
+use crate::Error;
+use std::{io, mem, ptr};
+
+#[allow(non_snake_case, clippy::upper_case_acronyms)]
+mod bindings {
+    pub type BOOL = i32;
+    pub type HANDLE = isize;
+
+    pub const PROCESS_VM_OPERATION: u32 = 0x0008;
+    pub const PROCESS_VM_WRITE: u32 = 0x0020;
+    pub const THREAD_SUSPEND_RESUME: u32 = 0x0002;
+    pub const THREAD_GET_CONTEXT: u32 = 0x0008;
+    pub const THREAD_QUERY_LIMITED_INFORMATION: u32 = 0x0800;
+    pub const MEM_COMMIT: u32 = 0x1000;
+    pub const MEM_RESERVE: u32 = 0x2000;
+    pub const MEM_RELEASE: u32 = 0x8000;
+    pub const PAGE_READWRITE: u32 = 0x04;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub fn OpenProcess(dwDesiredAccess: u32, bInheritHandle: BOOL, dwProcessId: u32) -> HANDLE;
+        pub fn OpenThread(dwDesiredAccess: u32, bInheritHandle: BOOL, dwThreadId: u32) -> HANDLE;
+        pub fn VirtualAllocEx(
+            hProcess: HANDLE,
+            lpAddress: *const std::ffi::c_void,
+            dwSize: usize,
+            flAllocationType: u32,
+            flProtect: u32,
+        ) -> *mut std::ffi::c_void;
+        pub fn VirtualFreeEx(
+            hProcess: HANDLE,
+            lpAddress: *mut std::ffi::c_void,
+            dwSize: usize,
+            dwFreeType: u32,
+        ) -> BOOL;
+        pub fn WriteProcessMemory(
+            hProcess: HANDLE,
+            lpBaseAddress: *mut std::ffi::c_void,
+            lpBuffer: *const std::ffi::c_void,
+            nSize: usize,
+            lpNumberOfBytesWritten: *mut usize,
+        ) -> BOOL;
+        pub fn CloseHandle(hObject: HANDLE) -> BOOL;
+    }
+}
+
+/// Closes the handle on drop
+struct Handle(bindings::HANDLE);
+
+impl Handle {
+    fn new(handle: bindings::HANDLE) -> io::Result<Self> {
+        if handle == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Self(handle))
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        // SAFETY: syscall, we own the handle
+        unsafe {
+            bindings::CloseHandle(self.0);
+        }
+    }
+}
+
+/// The exception information that `MiniDumpWriteDump` reads from the dumped
+/// process, which a hung process doesn't have, so it is synthesized, and
+/// written to memory allocated in the process
+#[repr(C)]
+struct SyntheticException {
+    pointers: crash_context::EXCEPTION_POINTERS,
+    record: crash_context::EXCEPTION_RECORD,
+    context: crash_context::CONTEXT,
+}
+
+/// Frees the memory allocated in the dumped process on drop
+struct RemoteAllocation<'process> {
+    process: &'process Handle,
+    addr: *mut std::ffi::c_void,
+}
+
+impl Drop for RemoteAllocation<'_> {
+    fn drop(&mut self) {
+        // SAFETY: syscall, we allocated the memory
+        unsafe {
+            bindings::VirtualFreeEx(self.process.0, self.addr, 0, bindings::MEM_RELEASE);
+        }
+    }
+}
+
+/// Writes a minidump of a process that hasn't crashed, eg. because it is
+/// hung, with the context of the specified thread, captured via
+/// [`crash_context::capture_thread_context`], as the exception context, so
+/// that the thread that is hung is the one that is shown as having "crashed".
+///
+/// The exception has the same code as the one used by
+/// [`crate::Client::request_on_demand_dump`], so the minidump can be told
+/// apart from one of a real crash.
+///
+/// Unlike a crash request, the process doesn't need to cooperate, but this
+/// process must be allowed to write to its memory, as that is where
+/// `MiniDumpWriteDump` reads the exception from.
+///
+/// # Errors
+///
+/// The process or thread can't be opened, or written to, eg. because they have
+/// exited, or this process lacks the rights to, or the minidump can't be
+/// written
+pub fn write_hung_minidump(
+    process_id: u32,
+    thread_id: u32,
+    file: &mut std::fs::File,
+) -> Result<(), Error> {
+    use bindings as b;
+
+    // SAFETY: syscalls
+    let (process, thread) = unsafe {
+        (
+            Handle::new(b::OpenProcess(
+                b::PROCESS_VM_OPERATION | b::PROCESS_VM_WRITE,
+                0,
+                process_id,
+            ))?,
+            Handle::new(b::OpenThread(
+                b::THREAD_SUSPEND_RESUME
+                    | b::THREAD_GET_CONTEXT
+                    | b::THREAD_QUERY_LIMITED_INFORMATION,
+                0,
+                thread_id,
+            ))?,
+        )
+    };
+
+    let context = crash_context::capture_thread_context(thread.0)?;
+
+    let size = mem::size_of::<SyntheticException>();
+
+    // SAFETY: syscall
+    let addr = unsafe {
+        b::VirtualAllocEx(
+            process.0,
+            ptr::null(),
+            size,
+            b::MEM_COMMIT | b::MEM_RESERVE,
+            b::PAGE_READWRITE,
+        )
+    };
+
+    if addr.is_null() {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    let remote = RemoteAllocation {
+        process: &process,
+        addr,
+    };
+
+    // SAFETY: the record is POD
+    let mut record: crash_context::EXCEPTION_RECORD = unsafe { mem::zeroed() };
+    record.ExceptionCode = crate::ipc::ON_DEMAND_EXCEPTION_CODE as i32;
+    record.ExceptionAddress = {
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "x86_64")] {
+                context.Rip
+            } else if #[cfg(target_arch = "x86")] {
+                context.Eip
+            } else if #[cfg(target_arch = "aarch64")] {
+                context.Pc
+            }
+        }
+    } as _;
+
+    let mut exception = SyntheticException {
+        pointers: crash_context::EXCEPTION_POINTERS {
+            ExceptionRecord: ptr::null_mut(),
+            ContextRecord: ptr::null_mut(),
+        },
+        record,
+        context,
+    };
+
+    // The pointers are to where the record and context are in the process,
+    // not in this one
+    let base = ptr::addr_of!(exception) as usize;
+    exception.pointers.ExceptionRecord =
+        (remote.addr as usize + (ptr::addr_of!(exception.record) as usize - base)) as _;
+    exception.pointers.ContextRecord =
+        (remote.addr as usize + (ptr::addr_of!(exception.context) as usize - base)) as _;
+
+    let mut written = 0;
+
+    // SAFETY: syscall, the local buffer is valid for its length
+    let res = unsafe {
+        b::WriteProcessMemory(
+            process.0,
+            remote.addr,
+            ptr::addr_of!(exception).cast(),
+            size,
+            &mut written,
+        )
+    };
+
+    if res == 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    let crash_context = crash_context::CrashContext {
+        exception_pointers: remote.addr.cast(),
+        exception_code: exception.record.ExceptionCode,
+        process_id,
+        thread_id,
+        timestamps: crash_context::CrashTimestamps::now(0),
+        thread_name: crash_context::ThreadName::default(),
+        main_thread: crash_context::MainThread::UNKNOWN,
+    };
+
+    minidump_writer::minidump_writer::MinidumpWriter::dump_crash_context(
+        crash_context,
+        None,
+        file,
+    )?;
+
+    Ok(())
+}
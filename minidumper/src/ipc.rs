@@ -7,7 +7,9 @@ cfg_if::cfg_if! {
 
         type Stream = uds::UnixSeqpacketConn;
 
-        struct Connection(uds::nonblocking::UnixSeqpacketConn);
+        /// A connection to a client, along with the last file descriptor it
+        /// sent, see [`Connection::take_fd`]
+        struct Connection(uds::nonblocking::UnixSeqpacketConn, std::cell::Cell<Option<std::os::fd::OwnedFd>>);
 
         impl polling::AsRawSource for Connection {
             fn raw(&self) -> RawFd {
@@ -36,12 +38,65 @@ cfg_if::cfg_if! {
 
             #[inline]
             fn recv(&self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+                self.1.set(None);
                 self.0.recv(buf)
             }
 
-            #[inline]
+            /// Receives a message, keeping the first file descriptor sent with
+            /// it, if any, until the next message is received, see
+            /// [`Self::take_fd`]
+            #[allow(unsafe_code)]
             fn recv_vectored(&self, buf: &mut [std::io::IoSliceMut<'_>]) -> Result<(usize, bool), std::io::Error> {
-                self.0.recv_vectored(buf)
+                use std::os::fd::FromRawFd;
+
+                self.1.set(None);
+
+                // Room for 2 fds, as the kernel can round a single one up, the
+                // u64s keep it aligned for cmsghdr
+                let mut control = [0u64; 4];
+
+                // SAFETY: syscalls, the iovecs and control buffer outlive the
+                // recvmsg, and we own any fds it received
+                unsafe {
+                    let mut msg: libc::msghdr = std::mem::zeroed();
+                    msg.msg_iov = buf.as_mut_ptr().cast();
+                    msg.msg_iovlen = buf.len() as _;
+                    msg.msg_control = control.as_mut_ptr().cast();
+                    msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+                    let read = libc::recvmsg(self.0.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC);
+                    if read < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+
+                    let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+                    while !cmsg.is_null() {
+                        if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                            let data = libc::CMSG_DATA(cmsg).cast::<RawFd>();
+                            let len = (*cmsg).cmsg_len as usize - (data as usize - cmsg as usize);
+
+                            for i in 0..len / std::mem::size_of::<RawFd>() {
+                                let fd = std::os::fd::OwnedFd::from_raw_fd(data.add(i).read_unaligned());
+
+                                // Any fds past the first are closed
+                                if i == 0 {
+                                    self.1.set(Some(fd));
+                                }
+                            }
+                        }
+
+                        cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+                    }
+
+                    Ok((read as usize, msg.msg_flags & libc::MSG_TRUNC != 0))
+                }
+            }
+
+            /// Takes the file descriptor sent with the last message received,
+            /// if any
+            #[inline]
+            fn take_fd(&self) -> Option<std::os::fd::OwnedFd> {
+                self.1.take()
             }
         }
 
@@ -68,7 +123,7 @@ cfg_if::cfg_if! {
 
         impl Listener {
             fn accept_unix_addr(&self) -> Result<(Connection, uds::UnixSocketAddr), std::io::Error> {
-                self.0.accept_unix_addr().map(|(conn, addr)| (Connection(conn), addr))
+                self.0.accept_unix_addr().map(|(conn, addr)| (Connection(conn, Default::default()), addr))
             }
         }
     } else if #[cfg(target_os = "windows")] {
@@ -99,6 +154,44 @@ cfg_if::cfg_if! {
             thread_id: u32,
             /// The top level exception code, also found in the `EXCEPTION_POINTERS.ExceptionRecord.ExceptionCode`
             exception_code: i32,
+            /// [`crash_context::CrashTimestamps::monotonic_ns`]
+            monotonic_ns: u64,
+            /// [`crash_context::CrashTimestamps::realtime_ns`]
+            realtime_ns: u64,
+            /// [`crash_context::CrashTimestamps::process_start_ns`]
+            process_start_ns: u64,
+            /// [`crash_context::CrashContext::thread_name`]
+            thread_name: [u8; 64],
+            /// [`crash_context::CrashContext::main_thread`]
+            main_thread: u8,
+        }
+
+        impl DumpRequest {
+            /// The maximum size of the encoded request, which is smaller on
+            /// 32-bit targets
+            const SIZE: usize = 112;
+
+            fn new(target: &crate::WindowsCrashTarget) -> Self {
+                Self {
+                    exception_pointers: target.exception_pointers as _,
+                    process_id: target.process_id,
+                    thread_id: target.thread_id,
+                    exception_code: target.exception_code,
+                    monotonic_ns: target.timestamps.monotonic_ns,
+                    realtime_ns: target.timestamps.realtime_ns,
+                    process_start_ns: target.timestamps.process_start_ns,
+                    thread_name: target.thread_name.0,
+                    main_thread: target.main_thread.0,
+                }
+            }
+
+            /// Writes the request to the start of the buffer, returning the
+            /// written part of it
+            fn encode(self, buf: &mut [u8; Self::SIZE]) -> Result<&[u8], crate::Error> {
+                use scroll::Pwrite;
+                let written = buf.pwrite(self, 0)?;
+                Ok(&buf[..written])
+            }
         }
     } else if #[cfg(target_os = "macos")] {
         mod mac;
@@ -131,18 +224,152 @@ cfg_if::cfg_if! {
     }
 }
 
-mod client;
-mod server;
+cfg_if::cfg_if! {
+    if #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "windows",
+        target_os = "macos"
+    ))] {
+        mod client;
+        mod server;
+
+        pub(crate) use client::is_retryable;
+        pub use client::{Client, DumpOutcome};
+        pub use server::{
+            ClientId, Server, ServerHandle, ServerOptions, ServerSet, ServerStats,
+        };
+    } else {
+        mod stub;
 
-pub use client::Client;
-pub use server::Server;
+        pub(crate) use stub::is_retryable;
+        pub use stub::{
+            Client, ClientId, DumpOutcome, Server, ServerHandle, ServerOptions, ServerSet,
+            ServerStats,
+        };
+    }
+}
 
 const CRASH: u32 = 0;
-#[cfg_attr(target_os = "macos", allow(dead_code))]
 const CRASH_ACK: u32 = 1;
 const PING: u32 = 2;
 const PONG: u32 = 3;
+/// The first kind of the messages sent by users, via [`Client::send_message`]
+/// and [`ServerHandle::send_to`], which are offset by this so that they can't
+/// collide with the kinds above. This can't change without breaking older
+/// peers, so newer internal messages reuse the kinds above instead, in the
+/// direction they were never sent in.
 const USER: u32 = 4;
+/// The largest kind a user message can have, see [`Client::send_message`].
+///
+/// Kinds are offset past the ones used internally by the protocol when they
+/// are sent, so the usable range is `0..=MAX_MESSAGE_KIND`, and larger kinds
+/// are rejected with [`crate::Error::InvalidMessageKind`].
+pub const MAX_MESSAGE_KIND: u32 = u32::MAX - USER - 1;
+
+/// Offsets a user message kind past the internal kinds, see [`USER`]
+#[inline]
+fn user_kind(kind: u32) -> Result<u32, crate::Error> {
+    if kind > MAX_MESSAGE_KIND {
+        Err(crate::Error::InvalidMessageKind(kind))
+    } else {
+        Ok(kind + USER)
+    }
+}
+/// Sent by clients as soon as they connect, with their [`PROTOCOL_VERSION`]
+/// as little endian bytes, followed, on Windows and Macos, by their command
+/// line and environment, see [`crate::ProcessInfo`]. This is the same kind as
+/// [`PONG`], which servers have always ignored when sent by a client, so older
+/// servers ignore it too
+const HELLO: u32 = PONG;
+/// Sent by clients that created breadcrumbs, see [`Client::create_breadcrumbs`],
+/// so that the server maps them too. This is the same kind as [`CRASH_ACK`],
+/// which is otherwise only sent by the server, so it is only sent to servers
+/// of [`PROTOCOL_VERSION`] 4 or later, as older ones disconnect clients that
+/// send it
+const BREADCRUMBS: u32 = CRASH_ACK;
+/// Sent by the server as soon as a crash request is received, before the
+/// minidump for it is written and [`CRASH_ACK`] is sent. This is the same kind
+/// as [`CRASH`], which is otherwise only sent by the client, so it is only sent
+/// to clients of [`PROTOCOL_VERSION`] 1 or later, as older ones treat any
+/// reply to a crash request other than [`CRASH_ACK`] as an error
+const CRASH_QUEUED: u32 = CRASH;
+/// Asks the server to shut down, if it accepts shutdown requests, see
+/// [`ServerOptions::accept_shutdown_requests`]. This is a [`HELLO`] without
+/// a payload, which older servers ignore, and which is only accepted from
+/// clients of [`PROTOCOL_VERSION`] 5 or later, as no older client sends one
+const SHUTDOWN: u32 = HELLO;
+
+/// The version of the protocol spoken by the client, so that the server only
+/// sends replies the client understands. Clients that don't send a [`HELLO`]
+/// are version 0.
+///
+/// 1. Understands the [`CRASH_QUEUED`] and [`ACK_CRASH_BUSY`] replies to a
+///    crash request
+/// 2. Sends its command line and environment in the [`HELLO`] on Windows and
+///    Macos, where Macos clients also started sending the [`HELLO`]
+/// 3. Understands the server's version in the [`PONG`] to its first [`PING`],
+///    after which both ends include a sequence number in every message they
+///    send, see [`Header::seq`]
+/// 4. Accepts [`BREADCRUMBS`]
+/// 5. Sends [`SHUTDOWN`]
+const PROTOCOL_VERSION: u32 = 5;
+
+/// The largest [`HELLO`] a client sends, ie. its version followed by its
+/// command line and environment
+const MAX_HELLO_SIZE: usize = 4 + crate::process_info::MAX_PAYLOAD_SIZE;
+
+/// The longest minidump path the server sends back to the client in the
+/// [`CRASH_ACK`], see [`DumpOutcome::path`]. Longer paths are omitted, as the
+/// client receives the ack into a fixed size buffer, since it can't allocate
+/// while handling a crash
+pub const MAX_DUMP_PATH_LEN: usize = 1024;
+
+/// The first byte of a [`CRASH_ACK`] payload, when the minidump was written,
+/// followed by its path. On Macos, this is the ack sent over the mach port
+/// instead, where older servers always send 1
+const ACK_DUMP_WRITTEN: u8 = 2;
+/// The first byte of a [`CRASH_ACK`] payload, when the minidump could not be
+/// written, followed by the [`crate::DumpFailureCategory`], whether there is
+/// an OS error code, and the code itself as 4 little endian bytes. On Macos,
+/// the category is sent in the second byte of the ack instead, without a code
+const ACK_DUMP_FAILED: u8 = 3;
+/// The first byte of a [`CRASH_ACK`] payload, ie. the `CRASH_BUSY` reply, when
+/// the server deferred the crash request, followed by how many milliseconds
+/// the client should wait before retrying it, as 4 little endian bytes. This
+/// is sent instead of [`CRASH_QUEUED`], and only to clients of
+/// [`PROTOCOL_VERSION`] 1 or later
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+const ACK_CRASH_BUSY: u8 = 4;
+/// The largest payload of a [`CRASH_ACK`], older servers send no payload
+const MAX_ACK_SIZE: usize = 1 + MAX_DUMP_PATH_LEN;
+
+/// The exception code of the crash context sent by
+/// [`Client::request_on_demand_dump`] on Windows. This is an informational
+/// code with the customer bit set, so it can't collide with a system exception
+#[cfg(target_os = "windows")]
+pub(crate) const ON_DEMAND_EXCEPTION_CODE: u32 = 0x6d44_4d50;
+
+/// Whether the crash context was sent by [`Client::request_on_demand_dump`],
+/// in which case the client keeps running after its minidump is written, so it
+/// stays connected to the server
+pub(crate) fn is_on_demand(crash_context: &crash_context::CrashContext) -> bool {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            // There is no signal 0
+            crash_context.siginfo.ssi_signo == 0
+        } else if #[cfg(target_os = "windows")] {
+            crash_context.exception_code as u32 == ON_DEMAND_EXCEPTION_CODE
+        } else if #[cfg(target_os = "macos")] {
+            // Exceptions simulated by the crash handler also lack one, and
+            // likewise don't terminate the process
+            crash_context.exception.is_none()
+        } else {
+            let _ = crash_context;
+            false
+        }
+    }
+}
 
 /// A socket name.
 ///
@@ -150,16 +377,155 @@ const USER: u32 = 4;
 ///
 /// Additionally, Linux can use a plain string that will be used as an abstract
 /// name. See [here](https://man7.org/linux/man-pages/man7/unix.7.html) for
-/// more details on abstract namespace sockets.
+/// more details on abstract namespace sockets. Abstract names are rejected at
+/// runtime with [`crate::Error::UnsupportedSocketName`] on other platforms, so
+/// that code that is generic over platforms doesn't need to be cfg'ed.
 ///
 /// Note that on Macos, this name is _also_ used as the name for a mach port.
 /// Apple doesn't have good/any documentation for mach port service names, but
 /// they are allowed to be longer than the path for a socket name. We also
 /// require that the path be utf-8.
+///
+/// On Windows, the path is encoded as utf-8, as that is the encoding that
+/// `AF_UNIX` sockets use on Windows.
+///
+/// A path that can't be represented, eg. because it is too long for the
+/// socket address, results in [`crate::Error::InvalidName`] on all platforms.
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
 pub enum SocketName<'scope> {
+    /// A path on the filesystem
     Path(&'scope std::path::Path),
-    #[cfg(any(target_os = "linux", target_os = "android"))]
-    Abstract(&'scope str),
+    /// A name in the Linux abstract socket namespace, without the leading nul
+    Abstract(&'scope [u8]),
+}
+
+impl<'scope> SocketName<'scope> {
+    /// Retrieves the path, for platforms that don't support abstract names
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn into_path(self) -> Result<&'scope std::path::Path, crate::Error> {
+        match self {
+            Self::Path(path) => Ok(path),
+            Self::Abstract(_) => Err(crate::Error::UnsupportedSocketName),
+        }
+    }
+}
+
+impl SocketName<'_> {
+    /// The socket name for a server run by an Android app, eg. in a monitor
+    /// process it spawned, see [`crate::MonitorConfig::android_default`], that
+    /// the `SELinux` policy of the device's API level allows the app to connect
+    /// to.
+    ///
+    /// Below API level 28 this is an abstract name. From API level 28, apps
+    /// run in per-app `SELinux` sandboxes, which deny connecting to many abstract
+    /// sockets, so it is instead a path in `context_dir`, which must be a
+    /// directory private to the app, eg. `Context.getFilesDir()`, as the
+    /// sandbox always allows the app to use the files in it. A path is also
+    /// used if the API level can't be read.
+    ///
+    /// Either way, the name contains the id of this process, so that each
+    /// process of the app that creates one gets its own, and must be passed
+    /// to the process that connects to, or creates, the server.
+    ///
+    /// # Errors
+    ///
+    /// `context_dir` is not absolute, or is too long for the path to fit in a
+    /// socket address, as [`crate::Error::InvalidName`]
+    #[cfg(target_os = "android")]
+    pub fn android_default(context_dir: &std::path::Path) -> Result<OwnedSocketName, crate::Error> {
+        android_socket_name(android_api_level(), context_dir, std::process::id())
+    }
+}
+
+/// The first API level at which [`SocketName::android_default`] uses a path,
+/// ie. Android 9, where apps were moved into per-app `SELinux` sandboxes
+#[cfg(any(target_os = "android", all(test, target_os = "linux")))]
+const ANDROID_PATH_SOCKET_API_LEVEL: u32 = 28;
+
+/// Chooses the name for [`SocketName::android_default`], split out so that
+/// the choice can be tested on any Linux
+#[cfg(any(target_os = "android", all(test, target_os = "linux")))]
+fn android_socket_name(
+    api_level: Option<u32>,
+    context_dir: &std::path::Path,
+    pid: u32,
+) -> Result<OwnedSocketName, crate::Error> {
+    // The size of `sockaddr_un::sun_path`, which includes the nul terminator
+    const MAX_PATH_LEN: usize = 108;
+
+    match api_level {
+        Some(level) if level < ANDROID_PATH_SOCKET_API_LEVEL => Ok(OwnedSocketName::Abstract(
+            format!("minidumper-{pid}").into_bytes(),
+        )),
+        _ => {
+            if !context_dir.is_absolute() {
+                return Err(crate::Error::InvalidName);
+            }
+
+            let path = context_dir.join(format!("minidumper-{pid}.sock"));
+            if path.as_os_str().len() >= MAX_PATH_LEN {
+                return Err(crate::Error::InvalidName);
+            }
+
+            Ok(OwnedSocketName::Path(path))
+        }
+    }
+}
+
+/// Reads the API level of the device, ie. the `ro.build.version.sdk` property
+#[cfg(target_os = "android")]
+#[allow(unsafe_code)]
+fn android_api_level() -> Option<u32> {
+    let name = std::ffi::CString::new("ro.build.version.sdk").ok()?;
+    let mut value = [0 as libc::c_char; libc::PROP_VALUE_MAX as usize];
+
+    // SAFETY: syscall, the buffer is as large as any property value
+    let len = unsafe { libc::__system_property_get(name.as_ptr(), value.as_mut_ptr()) };
+
+    let value: Vec<u8> = value[..usize::try_from(len).ok()?.min(value.len())]
+        .iter()
+        .map(|c| *c as u8)
+        .collect();
+    std::str::from_utf8(&value).ok()?.trim().parse().ok()
+}
+
+/// An owned [`SocketName`], eg. one that was built from a directory, see
+/// `SocketName::android_default` on Android
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OwnedSocketName {
+    /// A path on the filesystem
+    Path(std::path::PathBuf),
+    /// A name in the Linux abstract socket namespace, without the leading nul
+    Abstract(Vec<u8>),
+}
+
+impl OwnedSocketName {
+    /// Borrows the name, eg. to pass to [`Client::with_name`]
+    #[inline]
+    pub fn as_socket_name(&self) -> SocketName<'_> {
+        match self {
+            Self::Path(path) => SocketName::Path(path),
+            Self::Abstract(name) => SocketName::Abstract(name),
+        }
+    }
+}
+
+impl<'scope> From<&'scope OwnedSocketName> for SocketName<'scope> {
+    #[inline]
+    fn from(name: &'scope OwnedSocketName) -> Self {
+        name.as_socket_name()
+    }
+}
+
+impl From<SocketName<'_>> for OwnedSocketName {
+    fn from(name: SocketName<'_>) -> Self {
+        match name {
+            SocketName::Path(path) => Self::Path(path.to_owned()),
+            SocketName::Abstract(name) => Self::Abstract(name.to_owned()),
+        }
+    }
 }
 
 impl<'scope> From<&'scope std::path::Path> for SocketName<'scope> {
@@ -172,7 +538,7 @@ impl<'scope> From<&'scope str> for SocketName<'scope> {
     fn from(s: &'scope str) -> Self {
         cfg_if::cfg_if! {
             if #[cfg(any(target_os = "linux", target_os = "android"))] {
-                Self::Abstract(s)
+                Self::Abstract(s.as_bytes())
             } else {
                 Self::Path(std::path::Path::new(s))
             }
@@ -186,50 +552,242 @@ impl<'scope> From<&'scope String> for SocketName<'scope> {
     }
 }
 
+/// Uses the pathname, or on Linux, the abstract name, of the address.
+///
+/// An unnamed address is converted to an empty path, which is rejected with
+/// [`crate::Error::InvalidName`].
+#[cfg(unix)]
+impl<'scope> From<&'scope std::os::unix::net::SocketAddr> for SocketName<'scope> {
+    fn from(addr: &'scope std::os::unix::net::SocketAddr) -> Self {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+
+            if let Some(name) = addr.as_abstract_name() {
+                return Self::Abstract(name);
+            }
+        }
+
+        Self::Path(
+            addr.as_pathname()
+                .unwrap_or_else(|| std::path::Path::new("")),
+        )
+    }
+}
+
 #[derive(Copy, Clone)]
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 #[repr(C)]
 pub struct Header {
     kind: u32,
     size: u32,
+    /// The position of the message among those sent by one end of the
+    /// connection, starting at 0, so that the other end can detect messages
+    /// that were lost or reordered.
+    ///
+    /// This is only sent once both ends have agreed to, see
+    /// [`PROTOCOL_VERSION`] 3, as older peers expect headers without it, in
+    /// which case it is always 0
+    seq: u32,
 }
 
 impl Header {
-    fn as_bytes(&self) -> &[u8] {
+    /// The size of a header that includes its [`Self::seq`]
+    const SEQUENCED_SIZE: usize = std::mem::size_of::<Self>();
+
+    /// The size of a header, depending on whether the connection is sequenced
+    #[inline]
+    const fn wire_size(sequenced: bool) -> usize {
+        if sequenced {
+            Self::SEQUENCED_SIZE
+        } else {
+            Self::SEQUENCED_SIZE - std::mem::size_of::<u32>()
+        }
+    }
+
+    fn as_bytes(&self, sequenced: bool) -> &[u8] {
         #[allow(unsafe_code)]
         unsafe {
-            let size = std::mem::size_of::<Self>();
             let ptr = (self as *const Self).cast();
-            std::slice::from_raw_parts(ptr, size)
+            std::slice::from_raw_parts(ptr, Self::wire_size(sequenced))
         }
     }
 
-    fn from_bytes(buf: &[u8]) -> Option<Self> {
-        if buf.len() != std::mem::size_of::<Self>() {
+    fn from_bytes(buf: &[u8], sequenced: bool) -> Option<Self> {
+        if buf.len() != Self::wire_size(sequenced) {
             return None;
         }
 
+        let mut bytes = [0u8; Self::SEQUENCED_SIZE];
+        bytes[..buf.len()].copy_from_slice(buf);
+
+        // The buffer can come from anywhere, so we can't assume it is aligned
         #[allow(unsafe_code)]
         unsafe {
-            Some(*buf.as_ptr().cast::<Self>())
+            Some(bytes.as_ptr().cast::<Self>().read_unaligned())
         }
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Header;
+    use super::{user_kind, Header, MAX_MESSAGE_KIND, USER};
+    use crate::Error;
+
+    #[test]
+    fn user_kinds() {
+        // Every version of the protocol has offset user kinds by 4, so peers of
+        // different versions agree on them
+        assert_eq!(USER, 4);
+        assert_eq!(user_kind(0).unwrap(), USER);
+        assert_eq!(user_kind(MAX_MESSAGE_KIND).unwrap(), u32::MAX - 1);
+
+        for kind in [MAX_MESSAGE_KIND + 1, u32::MAX - USER, u32::MAX] {
+            assert!(
+                matches!(user_kind(kind), Err(Error::InvalidMessageKind(k)) if k == kind),
+                "kind {kind} was accepted"
+            );
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn android_socket_names() {
+        use super::{android_socket_name, OwnedSocketName};
+        use std::path::Path;
+
+        let dir = Path::new("/data/user/0/com.example.game/files");
+
+        assert_eq!(
+            android_socket_name(Some(26), dir, 42).unwrap(),
+            OwnedSocketName::Abstract(b"minidumper-42".to_vec())
+        );
+
+        // Newer, or unknown, API levels use a path in the app's directory
+        for level in [Some(28), Some(34), None] {
+            assert_eq!(
+                android_socket_name(level, dir, 42).unwrap(),
+                OwnedSocketName::Path(dir.join("minidumper-42.sock"))
+            );
+        }
+
+        let long = Path::new("/data").join("a".repeat(100));
+        for dir in [Path::new("files"), long.as_path()] {
+            assert!(matches!(
+                android_socket_name(Some(30), dir, 42),
+                Err(Error::InvalidName)
+            ));
+        }
+    }
 
     #[test]
     fn header_bytes() {
         let expected = Header {
             kind: 20,
             size: 8 * 1024,
+            seq: 7,
         };
-        let exp_bytes = expected.as_bytes();
+        let exp_bytes = expected.as_bytes(true);
 
-        let actual = Header::from_bytes(exp_bytes).unwrap();
+        let actual = Header::from_bytes(exp_bytes, true).unwrap();
 
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn header_bytes_unaligned() {
+        let expected = Header {
+            kind: 4,
+            size: 0xdead_beef,
+            seq: 0,
+        };
+
+        let mut buf = [0u8; std::mem::size_of::<Header>() + 1];
+        buf[1..].copy_from_slice(expected.as_bytes(true));
+
+        assert_eq!(Header::from_bytes(&buf[1..], true), Some(expected));
+        assert_eq!(Header::from_bytes(&buf[2..], true), None);
+        assert_eq!(Header::from_bytes(&[], true), None);
+    }
+
+    #[test]
+    fn header_bytes_unsequenced() {
+        let header = Header {
+            kind: 6,
+            size: 32,
+            seq: 9,
+        };
+
+        // Older peers only know of the kind and size, so the sequence number
+        // is neither sent nor received
+        let bytes = header.as_bytes(false);
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(&bytes[..4], &6u32.to_ne_bytes());
+        assert_eq!(&bytes[4..], &32u32.to_ne_bytes());
+
+        assert_eq!(
+            Header::from_bytes(bytes, false),
+            Some(Header { seq: 0, ..header })
+        );
+        assert_eq!(Header::from_bytes(bytes, true), None);
+        assert_eq!(Header::from_bytes(header.as_bytes(true), false), None);
+    }
+
+    #[test]
+    fn dump_outcome() {
+        use super::{server::CrashOutcome, DumpOutcome, MAX_DUMP_PATH_LEN};
+
+        let path = std::env::temp_dir().join("crash.dmp");
+        let written = DumpOutcome::from_payload(&CrashOutcome::Written(path.clone()).payload());
+        assert_eq!(written.written(), Some(true));
+        assert_eq!(written.path(), Some(path.as_path()));
+
+        let failed = DumpOutcome::from_payload(
+            &CrashOutcome::Failed {
+                category: crate::DumpFailureCategory::Write,
+                code: Some(28),
+            }
+            .payload(),
+        );
+        assert_eq!(failed.written(), Some(false));
+        assert_eq!(failed.path(), None);
+        assert!(matches!(
+            failed.error(),
+            Some(crate::Error::ServerDumpFailed {
+                category: crate::DumpFailureCategory::Write,
+                code: Some(28)
+            })
+        ));
+        assert!(written.error().is_none());
+
+        // Clients can tell a denied ptrace apart from other failures to write
+        let denied = DumpOutcome::from_payload(
+            &CrashOutcome::Failed {
+                category: crate::DumpFailureCategory::PtraceDenied,
+                code: Some(1),
+            }
+            .payload(),
+        );
+        assert!(matches!(
+            denied.error(),
+            Some(crate::Error::ServerDumpFailed {
+                category: crate::DumpFailureCategory::PtraceDenied,
+                code: Some(1)
+            })
+        ));
+
+        // Older servers send an empty ack
+        let unknown = DumpOutcome::from_payload(&CrashOutcome::Unknown.payload());
+        assert_eq!(unknown.written(), None);
+        assert_eq!(unknown.path(), None);
+
+        // Paths that don't fit are omitted, but the dump was still written
+        let long = "a".repeat(MAX_DUMP_PATH_LEN + 1);
+        let long = DumpOutcome::from_payload(&CrashOutcome::Written(long.into()).payload());
+        assert_eq!(long.written(), Some(true));
+        assert_eq!(long.path(), None);
+    }
 }
@@ -1,11 +1,72 @@
 use super::{Header, SocketName, Stream};
 use crate::Error;
-use std::io::IoSlice;
+use std::{
+    collections::VecDeque,
+    io::IoSlice,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+};
+
+/// The largest message the client will accept from the server
+const MAX_SERVER_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
 
 /// Client side of the connection, which runs in the process that may (or has)
 /// crashed to communicate with an external monitor process.
+///
+/// The underlying socket can be registered with an external event loop, via
+/// `AsRawFd`/`AsFd` or `AsRawSocket`/`AsSocket`, to be notified when the
+/// server has sent a message. Only readable events are meaningful, and a
+/// readable event without any data means the server has closed the connection.
+/// When notified, call [`Self::try_recv_server_message`] until it returns
+/// `Ok(None)`. The socket must not be read from, or have its blocking mode
+/// changed, other than via the methods on this type.
+///
+/// # Threads
+///
+/// A client can be shared between any number of threads, eg. application
+/// threads sending messages while the crash handler requests a dump from
+/// whichever thread crashed. Sends are serialized, so that each message is
+/// sent whole, and never interleaved with another, even on Windows and Macos,
+/// where the socket is a stream rather than one that preserves message
+/// boundaries.
+///
+/// The crash requests of [`Self::request_dump`] and friends, which don't use
+/// the socket at all on Macos, wait for a send, or a read, by another thread
+/// only for a bounded time, and not at all if it
+/// was this thread that was interrupted by the crash in the middle of one, as
+/// waiting would deadlock the crash handler. The request is then sent
+/// regardless, as the crash matters more than the interrupted message, which,
+/// on Windows, may mean that the server fails to decode the request.
 pub struct Client {
     socket: Stream,
+    /// Serializes reads of the socket, so that a reply can't be consumed by
+    /// another thread. This is a spinlock as it is also taken when requesting
+    /// a dump, which is usually done from a signal/exception handler, see
+    /// [`SpinLock::lock_for_crash`]
+    reading: SpinLock,
+    /// Messages pushed by the server that were received while waiting for the
+    /// reply to a ping. Only locked while `reading` is held, and never while
+    /// requesting a dump
+    pending: parking_lot::Mutex<VecDeque<(u32, Vec<u8>)>>,
+    /// Serializes sends to the socket, so that messages are sent whole, and in
+    /// the order of their sequence numbers
+    sending: SpinLock,
+    /// Whether both ends sequence their messages, which is negotiated by the
+    /// first [`Self::ping`], see [`super::PROTOCOL_VERSION`] 3
+    sequenced: AtomicBool,
+    /// The [`super::PROTOCOL_VERSION`] of the server, as sent in the reply to
+    /// the first [`Self::ping`], or 0 until then, or if the server is too old
+    /// to send it
+    server_version: AtomicU32,
+    /// The sequence number of the next message sent to the server
+    send_seq: AtomicU32,
+    /// The sequence number expected of the next message from the server. Only
+    /// accessed while `reading` is held
+    recv_seq: AtomicU32,
+    /// See [`Self::sequence_gaps`]
+    sequence_gaps: AtomicU64,
+    /// See [`Self::crash_retries`]
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    crash_retries: u32,
     /// On Macos we need this additional mach port based client to send crash
     /// contexts, as, unfortunately, it's the best (though hopefully not only?)
     /// way to get the real info needed by the minidump writer to write the
@@ -19,8 +80,8 @@ impl Client {
     ///
     /// # Errors
     ///
-    /// The specified socket name is invalid, or a connection cannot be made
-    /// with a server
+    /// The specified socket name is invalid or unsupported on the current
+    /// platform, or a connection cannot be made with a server
     pub fn with_name<'scope>(name: impl Into<SocketName<'scope>>) -> Result<Self, Error> {
         let sn = name.into();
 
@@ -35,13 +96,27 @@ impl Client {
                     }
                 };
 
-                let socket = Stream::connect_unix_addr(&socket_addr)?;
+                // A denied connect means the server is running, but this
+                // process can't reach it, which retrying won't change, unlike
+                // a refused one
+                let socket = Stream::connect_unix_addr(&socket_addr).map_err(|err| {
+                    if err.kind() == std::io::ErrorKind::PermissionDenied {
+                        Error::ConnectDenied {
+                            abstract_name: matches!(sn, SocketName::Abstract(_)),
+                            source: err,
+                        }
+                    } else {
+                        Error::Io(err)
+                    }
+                })?;
             } else if #[cfg(target_os = "windows")] {
-                let SocketName::Path(path) = sn;
-                let socket = Stream::connect(path)?;
+                let path = sn.into_path()?;
+                let socket_addr = super::windows::UnixSocketAddr::from_path(path).map_err(|_err| Error::InvalidName)?;
+                let socket = Stream::connect_unix_addr(&socket_addr)?;
             } else if #[cfg(target_os = "macos")] {
-                let SocketName::Path(path) = sn;
-                let socket = Stream::connect(path)?;
+                let path = sn.into_path()?;
+                let socket_addr = super::mac::UnixSocketAddr::new(path).map_err(|_err| Error::InvalidName)?;
+                let socket = Stream::connect_unix_addr(&socket_addr)?;
 
                 // Note that sun_path is limited to 108 characters including null,
                 // while a mach port name is limited to 128 including null, so
@@ -55,6 +130,15 @@ impl Client {
 
         let s = Self {
             socket,
+            reading: SpinLock::new(),
+            pending: parking_lot::Mutex::new(VecDeque::new()),
+            sending: SpinLock::new(),
+            sequenced: AtomicBool::new(false),
+            server_version: AtomicU32::new(0),
+            send_seq: AtomicU32::new(0),
+            recv_seq: AtomicU32::new(0),
+            sequence_gaps: AtomicU64::new(0),
+            crash_retries: 3,
             #[cfg(target_os = "macos")]
             port,
         };
@@ -70,12 +154,61 @@ impl Client {
             s.socket.recv(&mut ack)?;
         }
 
+        // Let the server know which replies we understand, and, where it can't
+        // read them from the process itself, our command line and environment
+        let hello = super::PROTOCOL_VERSION.to_le_bytes().to_vec();
+        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        let hello = [hello, crate::ProcessInfo::current_payload()].concat();
+        s.send_message_impl(super::HELLO, &hello)?;
+
         Ok(s)
     }
 
+    /// Creates a new client with the given name, retrying every `interval`
+    /// until `timeout` has elapsed if the server is not yet available.
+    ///
+    /// This is useful when the server is being started at the same time as
+    /// the client, as it might not have bound its socket yet.
+    ///
+    /// # Errors
+    ///
+    /// Errors that indicate the server is not yet available, ie. the socket
+    /// doesn't exist or refuses the connection, or, on Macos, the mach port
+    /// is not yet registered, are only returned once the timeout elapses.
+    /// Any other error, eg. [`Error::InvalidName`], or, on Linux/Android,
+    /// `Error::ConnectDenied`, is returned immediately.
+    pub fn with_name_timeout<'scope>(
+        name: impl Into<SocketName<'scope>>,
+        timeout: std::time::Duration,
+        interval: std::time::Duration,
+    ) -> Result<Self, Error> {
+        let sn = name.into();
+        let deadline = std::time::Instant::now() + timeout;
+
+        loop {
+            match Self::with_name(sn) {
+                Err(err) if is_retryable(&err) => {
+                    let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+                    if remaining.is_zero() {
+                        return Err(err);
+                    }
+
+                    std::thread::sleep(interval.min(remaining));
+                }
+                res => return res,
+            }
+        }
+    }
+
     /// Requests that the server generate a minidump for the specified crash
     /// context. This blocks until the server has finished writing the minidump.
     ///
+    /// This method does not allocate, nor take any locks other than a spinlock
+    /// to serialize reads from the socket, so it is safe to call from within a
+    /// signal or exception handler. As a consequence, any messages pushed by
+    /// the server (see [`Self::try_recv_server_message`]) that arrive before
+    /// the server acknowledges the crash request are discarded.
+    ///
     /// # Linux
     ///
     /// This uses a [`crash_context::CrashContext`] by reference as the size of
@@ -84,6 +217,12 @@ impl Client {
     /// and heap to avoid that complication, though you may of course generate
     /// one however you like.
     ///
+    /// The server `ptrace`s this process while it writes the minidump, which
+    /// the crash handler only allows for the duration of its callback, see
+    /// `crash_handler::DumpableGuard`. As this blocks until the server has
+    /// written the minidump, calling it from within the callback suffices,
+    /// otherwise the server reports [`crate::DumpFailureCategory::PtraceDenied`].
+    ///
     /// # Windows
     ///
     /// This uses a [`crash_context::CrashContext`] by reference, as
@@ -97,50 +236,323 @@ impl Client {
     /// [`thread_suspend`](https://developer.apple.com/documentation/kernel/1418833-thread_suspend)
     /// (apologies for the terrible documentation, blame Apple) before calling
     /// this method
+    ///
+    /// # Errors
+    ///
+    /// Communicating with the server fails, or the server reports that it
+    /// failed to write the minidump, as [`Error::ServerDumpFailed`], eg. so
+    /// that the caller can fall back to writing a minimal report itself
+    #[inline]
     pub fn request_dump(&self, crash_context: &crash_context::CrashContext) -> Result<(), Error> {
+        match self.request_dump_with_result(crash_context)?.error() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Requests that the server generate a minidump of a process other than
+    /// this one, eg. one this process is watching over and has found to be
+    /// hung, see [`crate::WindowsCrashTarget`]. This blocks until the server
+    /// has finished writing the minidump, as with [`Self::request_dump`].
+    ///
+    /// The server only accepts requests for a process other than the one that
+    /// sent them if it allows them, see
+    /// [`crate::ServerOptions::allow_proxy_dumps`], otherwise it disconnects
+    /// this client, and this fails with an I/O error.
+    ///
+    /// # Errors
+    ///
+    /// Communicating with the server fails, including if it denies the
+    /// request, or the server reports that it failed to write the minidump,
+    /// as [`Error::ServerDumpFailed`]
+    #[cfg(target_os = "windows")]
+    pub fn request_dump_for(&self, target: &crate::WindowsCrashTarget) -> Result<(), Error> {
+        let mut buf = [0u8; super::DumpRequest::SIZE];
+        let buffer = super::DumpRequest::new(target).encode(&mut buf)?;
+
+        let mut outcome = DumpOutcome::UNKNOWN;
+        self.send_crash_buffer(buffer, super::CRASH_ACK, &mut outcome)?;
+
+        match outcome.error() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets how many times a crash request is retried when the server defers
+    /// it, see [`crate::CrashRequestAction::Defer`], before giving up with
+    /// [`Error::ServerBusy`]. Between each attempt, the client sleeps for as
+    /// long as the server asked it to.
+    ///
+    /// Defaults to 3.
+    ///
+    /// # Macos
+    ///
+    /// Crash requests are never deferred, so this has no effect.
+    #[inline]
+    pub fn crash_retries(mut self, retries: u32) -> Self {
+        self.crash_retries = retries;
+        self
+    }
+
+    /// Requests that the server generate a minidump for the specified crash
+    /// context, as with [`Self::request_dump`], returning the outcome reported
+    /// by the server, eg. so that the path of the minidump can be recorded for
+    /// the next time the application is launched.
+    ///
+    /// This has the same guarantees as [`Self::request_dump`], ie. it does not
+    /// allocate, and is safe to call from within a signal or exception handler.
+    ///
+    /// Unlike [`Self::request_dump`], the server failing to write the minidump
+    /// is not an error, but is reported by [`DumpOutcome::error`].
+    #[inline]
+    pub fn request_dump_with_result(
+        &self,
+        crash_context: &crash_context::CrashContext,
+    ) -> Result<DumpOutcome, Error> {
+        let mut outcome = DumpOutcome::UNKNOWN;
+        self.send_crash_request(crash_context, super::CRASH_ACK, &mut outcome)?;
+        Ok(outcome)
+    }
+
+    /// Requests that the server generate a minidump for the specified crash
+    /// context, as with [`Self::request_dump`], but only blocks until the
+    /// server has queued the request, rather than until the minidump has been
+    /// written.
+    ///
+    /// The server writes minidumps one at a time, so when several processes
+    /// crash at nearly the same time, a request can otherwise be blocked for a
+    /// long time behind the requests of the other clients.
+    ///
+    /// Note however that the server reads the state of the crashed process
+    /// while writing its minidump, so the process must still be alive at that
+    /// point for the minidump to be complete, or even written at all. On Linux,
+    /// it must also still allow the server to `ptrace` it, see
+    /// [`Self::request_dump`].
+    ///
+    /// # Macos
+    ///
+    /// Crash requests are queued by the kernel, so this returns as soon as the
+    /// request has been sent, without waiting for the server.
+    #[inline]
+    pub fn queue_dump(&self, crash_context: &crash_context::CrashContext) -> Result<(), Error> {
+        let mut outcome = DumpOutcome::UNKNOWN;
+        self.send_crash_request(crash_context, super::CRASH_QUEUED, &mut outcome)
+    }
+
+    /// Requests that the server generate a minidump of the current process
+    /// at the point of this call, without the process having crashed, eg. to
+    /// capture the state of a hung or misbehaving application. This blocks
+    /// until the minidump has been written, after which the process continues
+    /// as normal.
+    ///
+    /// The crash context of the minidump is that of the calling thread, with
+    /// no exception, signal, or exception record, so that it can be told apart
+    /// from a real crash, see [`crate::DumpMetadata::on_demand`]. Unlike for a
+    /// crash request, the client stays connected to the server afterwards.
+    ///
+    /// Note that servers older than this one treat the request as a crash,
+    /// and disconnect the client once the minidump has been written.
+    ///
+    /// # Linux/Android
+    ///
+    /// As with a crash, the server must be allowed to `ptrace` this process,
+    /// eg. by holding a `crash_handler::DumpableGuard` for the duration of
+    /// this call, to write the minidump.
+    ///
+    /// # Errors
+    ///
+    /// Communicating with the server fails, or the server reports that it
+    /// failed to write the minidump, as [`Error::ServerDumpFailed`]
+    #[allow(unsafe_code)]
+    pub fn request_on_demand_dump(&self) -> Result<(), Error> {
+        let timestamps = crash_context::CrashTimestamps::now(0);
+        let thread_name = std::thread::current()
+            .name()
+            .map(|name| crash_context::ThreadName::new(name.as_bytes()))
+            .unwrap_or_default();
+
+        let mut outcome = DumpOutcome::UNKNOWN;
+
         cfg_if::cfg_if! {
             if #[cfg(any(target_os = "linux", target_os = "android"))] {
-                let crash_ctx_buffer = crash_context.as_bytes();
+                // SAFETY: the context is POD, and is filled in by getcontext,
+                // which only writes within it
+                let cc = unsafe {
+                    let mut cc: crash_context::CrashContext = std::mem::zeroed();
+                    crash_context::crash_context_getcontext(&mut cc.context);
+
+                    cfg_if::cfg_if! {
+                        if #[cfg(target_arch = "aarch64")] {
+                            let fp_ptr = cc.context.uc_mcontext.__reserved.as_ptr().cast::<crash_context::fpsimd_context>();
+
+                            if (*fp_ptr).head.magic == crash_context::FPSIMD_MAGIC {
+                                std::ptr::copy_nonoverlapping(fp_ptr, &mut cc.float_state, 1);
+                            }
+                        } else if #[cfg(not(target_arch = "arm"))] {
+                            if !cc.context.uc_mcontext.fpregs.is_null() {
+                                std::ptr::copy_nonoverlapping(cc.context.uc_mcontext.fpregs, ((&mut cc.float_state) as *mut crash_context::fpregset_t).cast(), 1);
+                            }
+                        }
+                    }
+
+                    cc.pid = std::process::id() as i32;
+                    cc.tid = libc::syscall(libc::SYS_gettid) as i32;
+                    // The signal number is left as 0, which marks the request
+                    // as on-demand, and the code as `SI_USER`, as if the process
+                    // had sent it to itself
+                    cc.siginfo.ssi_pid = cc.pid as u32;
+                    cc.timestamps = timestamps;
+                    cc.thread_name = thread_name;
+                    cc.tls = crash_context::TlsRegisters::current();
+                    cc
+                };
+
+                self.send_crash_request(&cc, super::CRASH_ACK, &mut outcome)?;
             } else if #[cfg(target_os = "windows")] {
-                use scroll::Pwrite;
-                let mut buf = [0u8; 24];
-                let written = buf.pwrite(
-                    super::DumpRequest {
-                        exception_pointers: crash_context.exception_pointers as _,
-                        process_id: crash_context.process_id,
-                        thread_id: crash_context.thread_id,
-                        exception_code: crash_context.exception_code,
-                    },
-                    0,
-                )?;
+                extern "system" {
+                    fn GetCurrentThreadId() -> u32;
+                }
+
+                // SAFETY: the record and context are POD, and only read by the
+                // server while this thread is blocked waiting for its ack
+                unsafe {
+                    let mut exception_record: crash_context::EXCEPTION_RECORD = std::mem::zeroed();
+                    exception_record.ExceptionCode = super::ON_DEMAND_EXCEPTION_CODE as i32;
+
+                    let mut exception_context = std::mem::MaybeUninit::zeroed();
+                    crash_context::capture_context(exception_context.as_mut_ptr());
+                    let mut exception_context = exception_context.assume_init();
+
+                    let exception_ptrs = crash_context::EXCEPTION_POINTERS {
+                        ExceptionRecord: &mut exception_record,
+                        ContextRecord: &mut exception_context,
+                    };
+
+                    let cc = crash_context::CrashContext {
+                        exception_pointers: &exception_ptrs,
+                        process_id: std::process::id(),
+                        thread_id: GetCurrentThreadId(),
+                        exception_code: exception_record.ExceptionCode,
+                        timestamps,
+                        thread_name,
+                        // Only the crash handler knows which thread is the main one
+                        main_thread: crash_context::MainThread::UNKNOWN,
+                    };
+
+                    self.send_crash_request(&cc, super::CRASH_ACK, &mut outcome)?;
+                }
+            } else if #[cfg(target_os = "macos")] {
+                extern "C" {
+                    fn mach_port_deallocate(task: u32, name: u32) -> i32;
+                }
+
+                // SAFETY: syscalls, the thread port is a new send right, which
+                // is released once the request has been sent
+                unsafe {
+                    let task = libc::mach_task_self();
+                    let thread = libc::mach_thread_self();
+
+                    let cc = crash_context::CrashContext {
+                        task,
+                        thread,
+                        handler_thread: thread,
+                        main_thread: Some(libc::pthread_main_np() != 0).into(),
+                        exception: None,
+                        timestamps,
+                        thread_name,
+                        thread_state: None,
+                    };
+
+                    let res = self.send_crash_request(&cc, super::CRASH_ACK, &mut outcome);
+                    mach_port_deallocate(task, thread);
+                    res?;
+                }
+            }
+        }
+
+        match outcome.error() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
 
-                let crash_ctx_buffer = &buf[..written];
+    /// Sends a crash request, then blocks until the server replies with a
+    /// message of the `reply` kind, whose payload is stored in `outcome`
+    fn send_crash_request(
+        &self,
+        crash_context: &crash_context::CrashContext,
+        reply: u32,
+        outcome: &mut DumpOutcome,
+    ) -> Result<(), Error> {
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                let crash_ctx_buffer = crash_context.as_bytes();
+            } else if #[cfg(target_os = "windows")] {
+                let mut buf = [0u8; super::DumpRequest::SIZE];
+                let crash_ctx_buffer = super::DumpRequest::new(&crate::WindowsCrashTarget::from(crash_context)).encode(&mut buf)?;
             } else if #[cfg(target_os = "macos")] {
-                self.port.send_crash_context(
+                let receive_timeout = if reply == super::CRASH_ACK {
+                    std::time::Duration::from_secs(5)
+                } else {
+                    std::time::Duration::ZERO
+                };
+
+                let ack = self.port.send_crash_context(
                     crash_context,
                     Some(std::time::Duration::from_secs(2)),
-                    Some(std::time::Duration::from_secs(5))
+                    Some(receive_timeout)
                 )?;
+
+                // The ack can't hold a path, so only the status, and the
+                // category of a failure, are reported
+                if let Some(ack) = ack {
+                    outcome.payload[..3].copy_from_slice(&[ack as u8, (ack >> 8) as u8, 0]);
+                    outcome.len = 3;
+                }
+
                 Ok(())
             }
         }
 
         #[cfg(not(target_os = "macos"))]
         {
-            self.send_message_impl(0, crash_ctx_buffer)?;
+            self.send_crash_buffer(crash_ctx_buffer, reply, outcome)
+        }
+    }
+
+    /// Sends an encoded crash request, retrying it for as long as the server
+    /// defers it, see [`Self::send_crash_request`]
+    #[cfg(not(target_os = "macos"))]
+    fn send_crash_buffer(
+        &self,
+        buffer: &[u8],
+        reply: u32,
+        outcome: &mut DumpOutcome,
+    ) -> Result<(), Error> {
+        let mut retries = 0;
 
-            // Wait for the server to send back an ack that it has finished
-            // with the crash context
-            let mut ack = [0u8; std::mem::size_of::<Header>()];
-            self.socket.recv(&mut ack)?;
+        loop {
+            {
+                let _sending = self.sending.lock_for_crash();
+                self.send_locked(super::CRASH, buffer)?;
+            }
+
+            // Wait for the server to send back an ack that it has queued, or
+            // finished with, the crash context, or that it is busy
+            outcome.len = self.recv_crash_reply(reply, &mut outcome.payload)?;
 
-            let header = Header::from_bytes(&ack);
+            let retry_after = match outcome.retry_after() {
+                Some(retry_after) => retry_after,
+                None => return Ok(()),
+            };
 
-            if header.filter(|hdr| hdr.kind == super::CRASH_ACK).is_none() {
-                return Err(Error::ProtocolError("received invalid response to crash"));
+            if retries == self.crash_retries {
+                return Err(Error::ServerBusy { retry_after });
             }
 
-            Ok(())
+            retries += 1;
+            sleep(retry_after);
         }
     }
 
@@ -159,14 +571,17 @@ impl Client {
     /// be split, but if you care about ordering you will need to handle that
     /// yourself.
     ///
+    /// The `kind` is user defined, and can be anything up to and including
+    /// [`super::MAX_MESSAGE_KIND`], as it is offset past the kinds used
+    /// internally by the protocol.
+    ///
     /// # Errors
     ///
-    /// The send to the server fails
+    /// The kind is larger than [`super::MAX_MESSAGE_KIND`], as
+    /// [`Error::InvalidMessageKind`], or the send to the server fails
     #[inline]
     pub fn send_message(&self, kind: u32, buf: impl AsRef<[u8]>) -> Result<(), Error> {
-        debug_assert!(kind < u32::MAX - super::USER);
-
-        self.send_message_impl(kind + super::USER, buf.as_ref())
+        self.send_message_impl(super::user_kind(kind)?, buf.as_ref())
 
         // TODO: should we have an ACK? IPC is a (relatively) reliable communication
         // method, and reserving receives from the server for the exclusive
@@ -176,37 +591,711 @@ impl Client {
         // self.socket.recv(&mut ack)?;
     }
 
+    /// Sends a value to the server as a typed message, to be decoded by a
+    /// [`crate::TypedHandler`], see [`crate::typed`] for the wire format.
+    ///
+    /// # Errors
+    ///
+    /// The value could not be encoded, or, as [`Self::send_message`], the
+    /// kind is invalid or the send to the server fails
+    #[cfg(feature = "serde")]
+    pub fn send_typed<T: serde::Serialize + ?Sized>(
+        &self,
+        kind: u32,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.send_message(kind, crate::typed::encode(value)?)
+    }
+
     /// Sends a ping to the server, to keep it from reaping connections that haven't
     /// sent a message within its keep alive window
     ///
+    /// The first ping also lets both ends start sequencing their messages, see
+    /// [`Self::sequence_gaps`], if the server supports it.
+    ///
     /// # Errors
     ///
     /// The send to the server fails
-    #[inline]
     pub fn ping(&self) -> Result<(), Error> {
-        self.send_message_impl(super::PING, &[])?;
+        const INVALID: Error = Error::ProtocolError("received invalid response to ping");
 
-        let mut pong = [0u8; std::mem::size_of::<Header>()];
-        self.socket.recv(&mut pong)?;
+        let _reading = self.reading.lock();
+        let mut pending = self.pending.lock();
 
-        let header = Header::from_bytes(&pong);
+        // Servers that understand sequence numbers reply to the first ping
+        // with their version, and expect every message after the ping to be
+        // sequenced, so nothing else can be sent until the reply is received
+        let negotiating = !self.sequenced.load(Ordering::Relaxed);
+        let sending = negotiating.then(|| self.sending.lock());
 
-        if header.filter(|hdr| hdr.kind == super::PONG).is_none() {
-            Err(Error::ProtocolError("received invalid response to ping"))
+        if sending.is_some() {
+            self.send_locked(super::PING, &[])?;
         } else {
-            Ok(())
+            self.send_message_impl(super::PING, &[])?;
+        }
+
+        let pong = loop {
+            match self.recv_message()? {
+                Some((super::PONG, buffer)) => break buffer,
+                Some((recvd, buffer)) if recvd >= super::USER => {
+                    pending.push_back((recvd - super::USER, buffer));
+                }
+                _ => return Err(INVALID),
+            }
+        };
+
+        if let (Some(version), true) = (pong.get(..4), negotiating) {
+            let version = u32::from_le_bytes(version.try_into().unwrap());
+            self.server_version.store(version, Ordering::Relaxed);
+
+            if version >= 3 {
+                self.sequenced.store(true, Ordering::Relaxed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates breadcrumbs that keep the last `capacity` events recorded via
+    /// [`crate::Breadcrumbs::record`], in memory that is shared with the
+    /// server, so that the server can still read them if this process dies
+    /// without being able to send anything, eg. because it was killed, or its
+    /// crash handler failed.
+    ///
+    /// The breadcrumbs are added to the [`crate::DumpMetadata`] of every
+    /// minidump the server writes for this client, and to the minidump itself,
+    /// and are otherwise passed to
+    /// [`crate::ServerHandler::on_client_breadcrumbs`] once the client
+    /// disconnects. Only the last breadcrumbs created by a client are read by
+    /// the server.
+    ///
+    /// The memory is a memfd sent over the socket on Linux/Android, a named
+    /// file mapping on Windows, and a POSIX shared memory object on Macos,
+    /// which the server unlinks once it has mapped it.
+    ///
+    /// # Errors
+    ///
+    /// The capacity is 0 or larger than [`crate::MAX_BREADCRUMBS`], as
+    /// [`Error::InvalidBreadcrumbCapacity`], the server is too old to read
+    /// breadcrumbs, as [`Error::UnsupportedByServer`], or creating the memory
+    /// or the send to the server fails
+    pub fn create_breadcrumbs(&self, capacity: usize) -> Result<crate::Breadcrumbs, Error> {
+        if !(1..=crate::MAX_BREADCRUMBS).contains(&capacity) {
+            return Err(Error::InvalidBreadcrumbCapacity(capacity));
+        }
+
+        // The server's version is only known once it has replied to a ping
+        if self.server_version.load(Ordering::Relaxed) < 4 {
+            self.ping()?;
+
+            if self.server_version.load(Ordering::Relaxed) < 4 {
+                return Err(Error::UnsupportedByServer("breadcrumbs"));
+            }
+        }
+
+        let (breadcrumbs, share) = crate::Breadcrumbs::create(capacity)?;
+        let payload = breadcrumbs.payload(&share);
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                use std::os::fd::AsRawFd;
+
+                let _sending = self.sending.lock();
+                let header = self.next_header(super::BREADCRUMBS, payload.len());
+                let msg = [header.as_bytes(self.sequenced.load(Ordering::Relaxed)), &payload].concat();
+
+                if self.socket.send_fds(&msg, &[share.as_raw_fd()])? != msg.len() {
+                    return Err(Error::Io(std::io::ErrorKind::WriteZero.into()));
+                }
+            } else {
+                self.send_message_impl(super::BREADCRUMBS, &payload)?;
+            }
+        }
+
+        Ok(breadcrumbs)
+    }
+
+    /// The number of messages received from the server whose sequence number
+    /// showed that messages before them were lost, or reordered.
+    ///
+    /// This is always 0 until the client has sent its first [`Self::ping`],
+    /// or when connected to a server that is too old to send sequence numbers.
+    #[inline]
+    pub fn sequence_gaps(&self) -> u64 {
+        self.sequence_gaps.load(Ordering::Relaxed)
+    }
+
+    /// Asks the server to shut down. This is ignored by the server unless it
+    /// was created with [`crate::ServerOptions::accept_shutdown_requests`].
+    ///
+    /// # Errors
+    ///
+    /// The send to the server fails
+    #[inline]
+    pub fn request_shutdown(&self) -> Result<(), Error> {
+        self.send_message_impl(super::SHUTDOWN, &[])
+    }
+
+    /// Retrieves the next message sent by the server, without blocking.
+    ///
+    /// Returns `Ok(None)` if there are no messages available, or another thread
+    /// is currently reading from the socket, eg. waiting for the reply to a
+    /// [`Self::ping`], otherwise the kind and payload of the message, as with
+    /// [`Self::send_message`] on the client side.
+    ///
+    /// # Errors
+    ///
+    /// The server has closed the connection, sent an invalid message, or the
+    /// read from the socket fails
+    pub fn try_recv_server_message(&self) -> Result<Option<(u32, Vec<u8>)>, Error> {
+        let Some(_reading) = self.reading.try_lock() else {
+            return Ok(None);
+        };
+        let mut pending = self.pending.lock();
+
+        if let Some(msg) = pending.pop_front() {
+            return Ok(Some(msg));
+        }
+
+        if !self.poll_readable()? {
+            return Ok(None);
+        }
+
+        match self.recv_message()? {
+            Some((kind, buffer)) if kind >= super::USER => Ok(Some((kind - super::USER, buffer))),
+            Some(_) => Err(Error::ProtocolError(
+                "received an unexpected message from the server",
+            )),
+            None => Err(Error::Io(std::io::ErrorKind::ConnectionAborted.into())),
+        }
+    }
+
+    /// Blocks until the server replies to a crash request with a message of
+    /// the `reply` kind, discarding any messages pushed by the server, as well
+    /// as the [`super::CRASH_QUEUED`] ack, in the meantime. A deferred request
+    /// is replied to with a [`super::CRASH_ACK`] instead, even if `reply` is
+    /// [`super::CRASH_QUEUED`].
+    ///
+    /// The payload of the reply is read into `payload`, returning its length.
+    ///
+    /// Unlike [`Self::ping`], this never allocates, as it is called from
+    /// within a crash handler
+    #[cfg(not(target_os = "macos"))]
+    fn recv_crash_reply(&self, reply: u32, payload: &mut [u8]) -> Result<usize, Error> {
+        use super::server::MessageSource;
+
+        const INVALID: Error = Error::ProtocolError("received invalid response to crash");
+
+        let _reading = self.reading.lock_for_crash();
+        let sequenced = self.sequenced.load(Ordering::Relaxed);
+        let header_size = Header::wire_size(sequenced);
+
+        loop {
+            let mut hdr_buf = [0u8; Header::SEQUENCED_SIZE];
+            let hdr_buf = &mut hdr_buf[..header_size];
+            let len = MessageSource::peek(&self.socket, hdr_buf)?;
+            let header = Header::from_bytes(&hdr_buf[..len], sequenced).ok_or(INVALID)?;
+            self.check_sequence(&header);
+
+            if header.kind == reply || header.kind == super::CRASH_ACK {
+                let size = header.size as usize;
+                let payload = payload.get_mut(..size).ok_or(INVALID)?;
+
+                cfg_if::cfg_if! {
+                    if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                        let (len, truncated) = MessageSource::recv_vectored(
+                            &self.socket,
+                            &mut [std::io::IoSliceMut::new(hdr_buf), std::io::IoSliceMut::new(payload)],
+                        )?;
+
+                        if truncated || len != header_size + size {
+                            return Err(INVALID);
+                        }
+                    } else {
+                        for buf in [hdr_buf, payload] {
+                            let mut read = 0;
+                            while read < buf.len() {
+                                match MessageSource::recv(&self.socket, &mut buf[read..])? {
+                                    0 => return Err(Error::Io(std::io::ErrorKind::ConnectionAborted.into())),
+                                    len => read += len,
+                                }
+                            }
+                        }
+                    }
+                }
+
+                return Ok(size);
+            } else if header.kind < super::USER && header.kind != super::CRASH_QUEUED {
+                return Err(INVALID);
+            }
+
+            cfg_if::cfg_if! {
+                if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                    // Seqpacket sockets discard the remainder of a message
+                    // that doesn't fit in the buffer
+                    MessageSource::recv(&self.socket, hdr_buf)?;
+                } else {
+                    let mut scratch = [0u8; 256];
+                    let mut remaining = header_size + header.size as usize;
+
+                    while remaining > 0 {
+                        let len = scratch.len().min(remaining);
+                        match MessageSource::recv(&self.socket, &mut scratch[..len])? {
+                            0 => return Err(Error::Io(std::io::ErrorKind::ConnectionAborted.into())),
+                            read => remaining -= read,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the next message from the server, blocking until it is available.
+    ///
+    /// Callers must hold the `reading` lock
+    #[inline]
+    fn recv_message(&self) -> Result<Option<(u32, Vec<u8>)>, Error> {
+        let Some((header, buffer)) = super::server::read_message_of(
+            &self.socket,
+            Vec::new,
+            MAX_SERVER_MESSAGE_SIZE,
+            self.sequenced.load(Ordering::Relaxed),
+            |kind| {
+                kind == super::CRASH_ACK
+                    || kind == super::CRASH_QUEUED
+                    || kind == super::PONG
+                    || kind >= super::USER
+            },
+        )?
+        else {
+            return Ok(None);
+        };
+
+        self.check_sequence(&header);
+        Ok(Some((header.kind, buffer)))
+    }
+
+    /// Counts a gap if the message is not the next one in the sequence, then
+    /// continues from its sequence number, so each gap is only counted once.
+    ///
+    /// Callers must hold the `reading` lock
+    #[inline]
+    fn check_sequence(&self, header: &Header) {
+        if !self.sequenced.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let expected = self
+            .recv_seq
+            .swap(header.seq.wrapping_add(1), Ordering::Relaxed);
+        if header.seq != expected {
+            self.sequence_gaps.fetch_add(1, Ordering::Relaxed);
         }
     }
 
+    /// Returns true if a read would not block
+    #[allow(unsafe_code)]
+    fn poll_readable(&self) -> Result<bool, Error> {
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                let mut pfd = libc::pollfd {
+                    fd: self.socket.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+
+                // SAFETY: syscall
+                match unsafe { libc::poll(&mut pfd, 1, 0) } {
+                    -1 => Err(std::io::Error::last_os_error().into()),
+                    ready => Ok(ready > 0 && pfd.revents != 0),
+                }
+            } else {
+                Ok(self.socket.poll_readable()?)
+            }
+        }
+    }
+
+    #[inline]
     fn send_message_impl(&self, kind: u32, buf: &[u8]) -> Result<(), Error> {
-        let header = Header {
+        let _sending = self.sending.lock();
+        self.send_locked(kind, buf)
+    }
+
+    /// The header of the next message sent, with the next sequence number.
+    ///
+    /// Callers must hold the `sending` lock
+    #[inline]
+    fn next_header(&self, kind: u32, size: usize) -> Header {
+        Header {
             kind,
-            size: buf.len() as u32,
-        };
+            size: size as u32,
+            seq: if self.sequenced.load(Ordering::Relaxed) {
+                self.send_seq.fetch_add(1, Ordering::Relaxed)
+            } else {
+                0
+            },
+        }
+    }
 
-        let io_bufs = [IoSlice::new(header.as_bytes()), IoSlice::new(buf)];
+    /// Sends a message with the next sequence number.
+    ///
+    /// Callers must hold the `sending` lock
+    fn send_locked(&self, kind: u32, buf: &[u8]) -> Result<(), Error> {
+        let header = self.next_header(kind, buf.len());
+        let header = header.as_bytes(self.sequenced.load(Ordering::Relaxed));
+        let mut sent = self
+            .socket
+            .send_vectored(&[IoSlice::new(header), IoSlice::new(buf)])?;
+
+        // Stream sockets can accept only part of the message, the rest of
+        // which must be sent before any other message is
+        for part in [header, buf] {
+            while sent < part.len() {
+                match self.socket.send(&part[sent..])? {
+                    0 => return Err(Error::Io(std::io::ErrorKind::WriteZero.into())),
+                    len => sent += len,
+                }
+            }
+
+            sent -= part.len();
+        }
 
-        self.socket.send_vectored(&io_bufs)?;
         Ok(())
     }
 }
+
+/// The outcome of a crash request, as reported by the server, see
+/// [`Client::request_dump_with_result`].
+///
+/// This holds the path of the minidump inline, rather than allocating it, as
+/// it is received from within a crash handler.
+#[derive(Clone)]
+pub struct DumpOutcome {
+    /// The payload of the [`super::CRASH_ACK`]
+    payload: [u8; super::MAX_ACK_SIZE],
+    len: usize,
+}
+
+impl DumpOutcome {
+    /// The outcome of a request the server didn't report an outcome for
+    const UNKNOWN: Self = Self {
+        payload: [0; super::MAX_ACK_SIZE],
+        len: 0,
+    };
+
+    /// Whether the server wrote the minidump, or `None` if the server didn't
+    /// report it, eg. because it is an older version, or, without the
+    /// `minidump-writer` feature, processes the crash context itself
+    #[inline]
+    pub fn written(&self) -> Option<bool> {
+        match self.payload[..self.len].first() {
+            Some(&super::ACK_DUMP_WRITTEN) => Some(true),
+            Some(&super::ACK_DUMP_FAILED) => Some(false),
+            _ => None,
+        }
+    }
+
+    /// The [`Error::ServerDumpFailed`] describing why the server failed to
+    /// write the minidump, if it did
+    #[inline]
+    pub fn error(&self) -> Option<Error> {
+        if self.written() != Some(false) {
+            return None;
+        }
+
+        let payload = &self.payload[..self.len];
+        let category = payload.get(1).copied().unwrap_or_default();
+        let code = match (payload.get(2), payload.get(3..7)) {
+            (Some(1), Some(code)) => Some(i32::from_le_bytes([code[0], code[1], code[2], code[3]])),
+            _ => None,
+        };
+
+        Some(Error::ServerDumpFailed {
+            category: crate::DumpFailureCategory::from_u8(category),
+            code,
+        })
+    }
+
+    /// The path the minidump was written to, as returned by
+    /// [`crate::ServerHandler::create_minidump_file`] in the server process,
+    /// as raw bytes.
+    ///
+    /// This is `None` if the minidump wasn't written, or its path is longer
+    /// than [`super::MAX_DUMP_PATH_LEN`], and is always `None` on Macos, as
+    /// the crash request is acknowledged over a mach port, which only carries
+    /// the status.
+    #[inline]
+    pub fn path_bytes(&self) -> Option<&[u8]> {
+        if self.written() == Some(true) && self.len > 1 {
+            Some(&self.payload[1..self.len])
+        } else {
+            None
+        }
+    }
+
+    /// The path the minidump was written to, see [`Self::path_bytes`]
+    #[inline]
+    pub fn path(&self) -> Option<&std::path::Path> {
+        let path = self.path_bytes()?;
+
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                use std::os::unix::ffi::OsStrExt;
+                Some(std::path::Path::new(std::ffi::OsStr::from_bytes(path)))
+            } else {
+                std::str::from_utf8(path).ok().map(std::path::Path::new)
+            }
+        }
+    }
+
+    /// How long the server asked the client to wait before retrying the crash
+    /// request, if it deferred it
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    pub(super) fn retry_after(&self) -> Option<std::time::Duration> {
+        match self.payload[..self.len] {
+            [super::ACK_CRASH_BUSY, a, b, c, d] => Some(std::time::Duration::from_millis(
+                u32::from_le_bytes([a, b, c, d]).into(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Creates the outcome from the payload of a [`super::CRASH_ACK`]
+    #[cfg(test)]
+    pub(super) fn from_payload(payload: &[u8]) -> Self {
+        let mut outcome = Self::UNKNOWN;
+        outcome.payload[..payload.len()].copy_from_slice(payload);
+        outcome.len = payload.len();
+        outcome
+    }
+}
+
+impl std::fmt::Debug for DumpOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DumpOutcome")
+            .field("written", &self.written())
+            .field("path", &self.path())
+            .finish()
+    }
+}
+
+/// Sleeps for the specified duration, without allocating, as this is called
+/// from within a crash handler
+#[cfg(not(target_os = "macos"))]
+#[allow(unsafe_code)]
+fn sleep(duration: std::time::Duration) {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            let mut remaining = libc::timespec {
+                tv_sec: duration.as_secs() as _,
+                tv_nsec: duration.subsec_nanos() as _,
+            };
+
+            loop {
+                let request = remaining;
+
+                // SAFETY: syscall
+                if unsafe { libc::nanosleep(&request, &mut remaining) } == 0
+                    || std::io::Error::last_os_error().raw_os_error() != Some(libc::EINTR)
+                {
+                    break;
+                }
+            }
+        } else {
+            // `Sleep` is just a syscall
+            std::thread::sleep(duration);
+        }
+    }
+}
+
+/// How long a crash request waits for another thread to release a lock, see
+/// [`SpinLock::lock_for_crash`]
+#[cfg(not(target_os = "macos"))]
+const CRASH_LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// A minimal spinlock, as unlike eg. [`parking_lot::Mutex`] it is safe to use
+/// within a signal handler
+struct SpinLock {
+    locked: AtomicBool,
+    /// The thread holding the lock, see [`current_thread`]
+    owner: AtomicUsize,
+}
+
+impl SpinLock {
+    #[inline]
+    fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            owner: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    fn try_lock(&self) -> Option<SpinLockGuard<'_>> {
+        if self
+            .locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+
+        self.owner.store(current_thread(), Ordering::Relaxed);
+        Some(SpinLockGuard(Some(self)))
+    }
+
+    #[inline]
+    fn lock(&self) -> SpinLockGuard<'_> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Takes the lock while requesting a dump, unless that would deadlock, in
+    /// which case the returned guard doesn't hold the lock.
+    ///
+    /// If the lock is held by the current thread, the crash interrupted it
+    /// while it held the lock, and it will never be released. If it is held
+    /// by another thread, it is waited on for at most [`CRASH_LOCK_TIMEOUT`],
+    /// as the thread may have been suspended, or crashed itself.
+    #[cfg(not(target_os = "macos"))]
+    fn lock_for_crash(&self) -> SpinLockGuard<'_> {
+        let start = std::time::Instant::now();
+
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+
+            // The owner is only stored after the lock is taken, so it can be
+            // 0 while the lock is held, but is never the current thread
+            // unless it is the one holding the lock
+            if self.owner.load(Ordering::Relaxed) == current_thread()
+                || start.elapsed() >= CRASH_LOCK_TIMEOUT
+            {
+                return SpinLockGuard(None);
+            }
+
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Releases the lock, if it is held, on drop
+struct SpinLockGuard<'a>(Option<&'a SpinLock>);
+
+impl Drop for SpinLockGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(lock) = self.0 {
+            lock.owner.store(0, Ordering::Relaxed);
+            lock.locked.store(false, Ordering::Release);
+        }
+    }
+}
+
+/// An identifier of the current thread that is unique among the threads that
+/// are alive, and never 0, which is safe to retrieve within a signal handler
+#[inline]
+fn current_thread() -> usize {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            // SAFETY: syscall
+            #[allow(unsafe_code)]
+            unsafe { libc::pthread_self() as usize }
+        } else if #[cfg(windows)] {
+            extern "system" {
+                fn GetCurrentThreadId() -> u32;
+            }
+
+            // SAFETY: syscall
+            #[allow(unsafe_code)]
+            unsafe { GetCurrentThreadId() as usize }
+        }
+    }
+}
+
+// Clients are meant to be shared between the threads sending messages and the
+// one that crashed, see the "Threads" section of the docs
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Client>();
+};
+
+/// Whether a connection failure might succeed if retried, as the server may
+/// not have finished starting up yet
+pub(crate) fn is_retryable(err: &Error) -> bool {
+    match err {
+        Error::Io(err) => matches!(
+            err.kind(),
+            std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+        ),
+        #[cfg(target_os = "macos")]
+        Error::PortError(err) => err.is_unknown_service(),
+        _ => false,
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(any(target_os = "linux", target_os = "android"))] {
+        impl super::server::MessageSource for Stream {
+            const STREAM: bool = false;
+
+            #[inline]
+            fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+                Stream::peek(self, buf)
+            }
+
+            #[inline]
+            fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+                Stream::recv(self, buf)
+            }
+
+            #[inline]
+            fn recv_vectored(&self, bufs: &mut [std::io::IoSliceMut<'_>]) -> std::io::Result<(usize, bool)> {
+                Stream::recv_vectored(self, bufs)
+            }
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(unix)] {
+        use std::os::fd::{AsRawFd, AsFd, BorrowedFd, RawFd};
+
+        impl AsRawFd for Client {
+            #[inline]
+            fn as_raw_fd(&self) -> RawFd {
+                self.socket.as_raw_fd()
+            }
+        }
+
+        impl AsFd for Client {
+            #[inline]
+            fn as_fd(&self) -> BorrowedFd<'_> {
+                #[allow(unsafe_code)]
+                unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+            }
+        }
+    } else if #[cfg(target_os = "windows")] {
+        use std::os::windows::io::{AsRawSocket, AsSocket, BorrowedSocket, RawSocket};
+
+        impl AsRawSocket for Client {
+            #[inline]
+            fn as_raw_socket(&self) -> RawSocket {
+                self.socket.as_raw_socket()
+            }
+        }
+
+        impl AsSocket for Client {
+            #[inline]
+            fn as_socket(&self) -> BorrowedSocket<'_> {
+                self.socket.as_socket()
+            }
+        }
+    }
+}
@@ -232,6 +232,11 @@ impl UnixListener {
         Ok(Self(Uds(listener.into_raw_fd())))
     }
 
+    /// Wraps an already bound and listening socket
+    pub(crate) fn from_fd(fd: std::os::fd::OwnedFd) -> Self {
+        Self(Uds(fd.into_raw_fd()))
+    }
+
     pub(crate) fn accept_unix_addr(&self) -> io::Result<(UnixStream, UnixSocketAddr)> {
         let mut sock_addr = std::mem::MaybeUninit::<libc::sockaddr_un>::uninit();
         let mut len = std::mem::size_of::<libc::sockaddr_un>() as _;
@@ -268,11 +273,10 @@ impl AsFd for UnixListener {
 pub(crate) struct UnixStream(Uds);
 
 impl UnixStream {
-    pub(crate) fn connect(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+    pub(crate) fn connect_unix_addr(addr: &UnixSocketAddr) -> io::Result<Self> {
         // SAFETY: syscalls
         unsafe {
             let inner = Uds::new()?;
-            let addr = UnixSocketAddr::new(path.as_ref())?;
 
             if libc::connect(
                 inner.0,
@@ -287,6 +291,19 @@ impl UnixStream {
         }
     }
 
+    /// The effective user id of the process at the other end
+    pub(crate) fn peer_uid(&self) -> io::Result<u32> {
+        let mut uid = 0;
+        let mut gid = 0;
+
+        // SAFETY: syscall
+        if unsafe { libc::getpeereid(self.0.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(uid)
+        }
+    }
+
     #[inline]
     pub(crate) fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
         self.0.recv_with_flags(buf, libc::MSG_PEEK)
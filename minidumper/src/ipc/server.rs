@@ -1,7 +1,15 @@
-use super::{Connection, Header, Listener, SocketName};
-use crate::{Error, LoopAction};
+use super::{Connection, Header, Listener, SocketName, Stream};
+use crate::{
+    Breadcrumb, CrashRequestAction, DisconnectReason, Error, LoopAction, PeerCredentials,
+    ProcessInfo, ProtocolViolation,
+};
 use polling::{Event, Poller};
-use std::io::ErrorKind;
+use std::collections::HashMap;
+use std::io::{ErrorKind, IoSliceMut};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
 use std::time::{Duration, Instant};
 
 /// Server side of the connection, which runs in the monitor process that is
@@ -10,13 +18,258 @@ pub struct Server {
     listener: Option<Listener>,
     #[cfg(target_os = "macos")]
     port: crash_context::ipc::Server,
+    #[cfg(target_os = "macos")]
+    _port_name: PortName,
     /// For abstract sockets, we don't have to worry about cleanup as it is
-    /// handled by the OS, but on Windows and `MacOS` we need to clean them up
-    /// manually. We basically rely on the crash monitor program this Server
-    /// is running in to exit cleanly, which should be mostly true, but we
-    /// may need to harden this code if people experience issues with socket
-    /// paths not being cleaned up reliably
+    /// handled by the OS, but socket paths need to be cleaned up manually,
+    /// see [`ServerOptions::cleanup_socket_on_drop`]. If the crash monitor
+    /// program this Server is running in doesn't exit cleanly, the path is
+    /// left behind, and is removed by the next server that binds it instead,
+    /// see [`bind_path`]
     socket_path: Option<std::path::PathBuf>,
+    /// State shared with every [`ServerHandle`]
+    shared: std::sync::Arc<Shared>,
+    options: ServerOptions,
+}
+
+/// The names of the mach ports created by the servers in this process
+#[cfg(target_os = "macos")]
+static PORT_NAMES: parking_lot::Mutex<Vec<std::ffi::CString>> =
+    parking_lot::const_mutex(Vec::new());
+
+/// Reserves the name of a server's mach port for as long as the server is
+/// alive, as a second server checking in the same service name wouldn't
+/// reliably fail, and could end up receiving the crash contexts meant for the
+/// first one
+#[cfg(target_os = "macos")]
+struct PortName(std::ffi::CString);
+
+#[cfg(target_os = "macos")]
+impl PortName {
+    fn claim(name: &std::ffi::CStr) -> Result<Self, Error> {
+        let mut names = PORT_NAMES.lock();
+        if names.iter().any(|claimed| claimed.as_c_str() == name) {
+            return Err(Error::PortNameInUse);
+        }
+
+        names.push(name.to_owned());
+        Ok(Self(name.to_owned()))
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for PortName {
+    fn drop(&mut self) {
+        PORT_NAMES.lock().retain(|claimed| *claimed != self.0);
+    }
+}
+
+/// Identifies a client connected to a [`Server`], see [`ServerHandle`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(pub(crate) usize);
+
+/// A message queued by a [`ServerHandle`], to be sent by the server loop,
+/// which assigns the sequence number of each client it is sent to
+enum Command {
+    SendTo(ClientId, u32, Vec<u8>),
+    Broadcast(u32, Vec<u8>),
+}
+
+#[derive(Default)]
+struct Shared {
+    /// Messages waiting to be sent by the server loop
+    commands: parking_lot::Mutex<Vec<Command>>,
+    /// The clients currently connected, as of the last server loop iteration
+    clients: parking_lot::Mutex<Vec<ClientId>>,
+    /// See [`ServerStats::rejected_connections`]
+    rejected_connections: std::sync::atomic::AtomicU64,
+    /// See [`ServerStats::aborted_connections`]
+    aborted_connections: std::sync::atomic::AtomicU64,
+    /// See [`ServerStats::sequence_gaps`]
+    sequence_gaps: std::sync::atomic::AtomicU64,
+    /// See [`ServerStats::dump_warnings`]
+    dump_warnings: std::sync::atomic::AtomicU64,
+}
+
+/// Statistics about a running [`Server`], see [`ServerHandle::stats`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ServerStats {
+    /// The number of connections that were closed as soon as they were
+    /// accepted, as the peer was not allowed to connect, see
+    /// [`ServerOptions::allowed_uids`], or the server already had
+    /// [`ServerOptions::max_clients`] connected
+    pub rejected_connections: u64,
+    /// The number of connections that the peer gave up on before they were
+    /// accepted, eg. because the listener backlog, see
+    /// [`ServerOptions::listen_backlog`], overflowed during a burst of
+    /// connections, and the peer timed out
+    pub aborted_connections: u64,
+    /// The number of messages whose sequence number showed that messages
+    /// before them were lost, or reordered, see
+    /// [`crate::ServerHandler::on_protocol_error`]
+    pub sequence_gaps: u64,
+    /// The number of warnings reported with the minidumps that were written,
+    /// each of which was degraded in some way, see
+    /// `MinidumpBinary::dump_warnings`
+    pub dump_warnings: u64,
+}
+
+/// A handle to a [`Server`] that can be used from other threads to push
+/// messages to connected clients while the server loop is running.
+///
+/// Messages are queued and sent by [`Server::run`] within a few milliseconds,
+/// and are received by clients via [`super::Client::try_recv_server_message`].
+/// Sends never block the server loop, so a message is dropped, and an error
+/// logged, if the client is not keeping up with reading them, or if the client
+/// disconnects before the message is sent, or if its kind is larger than
+/// [`super::MAX_MESSAGE_KIND`].
+#[derive(Clone)]
+pub struct ServerHandle {
+    shared: std::sync::Arc<Shared>,
+}
+
+impl ServerHandle {
+    /// Sends a message to the specified client.
+    ///
+    /// The `kind` is user defined, just as for [`super::Client::send_message`],
+    /// and can't collide with the kinds used internally by the protocol.
+    pub fn send_to(&self, client: ClientId, kind: u32, buf: &[u8]) {
+        if let Some(kind) = Self::user_kind(kind) {
+            self.shared
+                .commands
+                .lock()
+                .push(Command::SendTo(client, kind, buf.to_vec()));
+        }
+    }
+
+    /// Sends a message to every connected client, see [`Self::send_to`]
+    pub fn broadcast(&self, kind: u32, buf: &[u8]) {
+        if let Some(kind) = Self::user_kind(kind) {
+            self.shared
+                .commands
+                .lock()
+                .push(Command::Broadcast(kind, buf.to_vec()));
+        }
+    }
+
+    /// Offsets the kind of a message to be sent, or logs why the message is
+    /// dropped, as sends never fail
+    fn user_kind(kind: u32) -> Option<u32> {
+        match super::user_kind(kind) {
+            Ok(kind) => Some(kind),
+            Err(err) => {
+                log::error!("dropping message: {err}");
+                None
+            }
+        }
+    }
+
+    /// Sends a value to the specified client as a typed message, see
+    /// [`crate::typed`] for the wire format, and [`Self::send_to`].
+    ///
+    /// The client decodes it with [`crate::typed::decode`].
+    ///
+    /// # Errors
+    ///
+    /// The kind is larger than [`super::MAX_MESSAGE_KIND`], as
+    /// [`crate::Error::InvalidMessageKind`], or the value could not be encoded
+    #[cfg(feature = "serde")]
+    pub fn send_typed<T: serde::Serialize + ?Sized>(
+        &self,
+        client: ClientId,
+        kind: u32,
+        value: &T,
+    ) -> Result<(), crate::Error> {
+        let kind = super::user_kind(kind)?;
+        let buf = crate::typed::encode(value)?;
+
+        self.shared
+            .commands
+            .lock()
+            .push(Command::SendTo(client, kind, buf));
+        Ok(())
+    }
+
+    /// The clients that are currently connected to the server
+    pub fn clients(&self) -> Vec<ClientId> {
+        self.shared.clients.lock().clone()
+    }
+
+    /// Statistics about the server since it was created
+    pub fn stats(&self) -> ServerStats {
+        ServerStats {
+            rejected_connections: self.shared.rejected_connections.load(Ordering::Relaxed),
+            aborted_connections: self.shared.aborted_connections.load(Ordering::Relaxed),
+            sequence_gaps: self.shared.sequence_gaps.load(Ordering::Relaxed),
+            dump_warnings: self.shared.dump_warnings.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The sequence numbers of the messages sent to, and received from, a client
+#[derive(Default)]
+struct Sequence {
+    /// Whether the client understands sequence numbers, which is only the case
+    /// once it has been told the server does, see [`super::PROTOCOL_VERSION`] 3
+    sequenced: bool,
+    /// The sequence number of the next message sent to the client
+    send: u32,
+    /// The sequence number expected of the next message from the client
+    recv: u32,
+}
+
+impl Sequence {
+    /// Sequences every message sent and received from now on
+    #[inline]
+    fn start(&mut self) {
+        self.sequenced = true;
+    }
+
+    /// Creates the header for the next message sent to the client
+    #[inline]
+    fn header(&mut self, kind: u32, size: usize) -> Header {
+        let seq = if self.sequenced {
+            let seq = self.send;
+            self.send = seq.wrapping_add(1);
+            seq
+        } else {
+            0
+        };
+
+        Header {
+            kind,
+            size: size as u32,
+            seq,
+        }
+    }
+
+    /// Creates the next message sent to the client, ie. its header followed
+    /// by the payload
+    fn message(&mut self, kind: u32, payload: &[u8]) -> Vec<u8> {
+        let header = self.header(kind, payload.len());
+
+        let mut msg = Vec::with_capacity(Header::wire_size(self.sequenced) + payload.len());
+        msg.extend_from_slice(header.as_bytes(self.sequenced));
+        msg.extend_from_slice(payload);
+        msg
+    }
+
+    /// Checks the header of a message received from the client, continuing
+    /// from its sequence number even if it is not the expected one
+    fn check(&mut self, header: &Header) -> Option<ProtocolViolation> {
+        if !self.sequenced {
+            return None;
+        }
+
+        let expected = self.recv;
+        self.recv = header.seq.wrapping_add(1);
+
+        (header.seq != expected).then_some(ProtocolViolation::SequenceGap {
+            expected,
+            received: header.seq,
+        })
+    }
 }
 
 struct ClientConn {
@@ -30,434 +283,1558 @@ struct ClientConn {
     /// to drop when a crash is received on the mach port
     #[cfg(target_os = "macos")]
     pid: Option<u32>,
+    /// The pid of the client process, which its crash requests are validated
+    /// against, see [`ServerOptions::allow_proxy_dumps`]
+    #[cfg(target_os = "windows")]
+    peer_pid: Option<u32>,
+    /// The [`super::PROTOCOL_VERSION`] sent by the client
+    protocol_version: u32,
+    /// The sequence numbers of the messages exchanged with the client
+    sequence: Sequence,
+    /// The command line and environment sent by the client in its `HELLO`,
+    /// if they are captured
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    process: Option<ProcessInfo>,
+    /// The breadcrumbs created by the client, if any, see
+    /// [`super::Client::create_breadcrumbs`]
+    breadcrumbs: Option<crate::breadcrumbs::BreadcrumbReader>,
 }
 
 impl ClientConn {
-    fn recv(&mut self, handler: &dyn crate::ServerHandler) -> Option<(u32, Vec<u8>)> {
-        use std::io::IoSliceMut;
+    /// Whether the client can be sent messages. On Macos, the client waits
+    /// for a raw ack of its pid before it can receive messages
+    #[inline]
+    #[cfg_attr(not(target_os = "macos"), allow(clippy::unused_self))]
+    fn is_ready(&self) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(target_os = "macos")] {
+                self.pid.is_some()
+            } else {
+                true
+            }
+        }
+    }
 
-        let mut hdr_buf = [0u8; std::mem::size_of::<Header>()];
+    /// The command line and environment sent by the client, which on
+    /// Linux/Android is instead read from the process when it crashes
+    #[inline]
+    #[cfg_attr(
+        any(target_os = "linux", target_os = "android"),
+        allow(clippy::unused_self)
+    )]
+    fn process(&self) -> Option<ProcessInfo> {
         cfg_if::cfg_if! {
             if #[cfg(any(target_os = "linux", target_os = "android"))] {
-                let len = self.socket.0.peek(&mut hdr_buf).ok()?;
+                None
             } else {
-                let len = self.socket.peek(&mut hdr_buf).ok()?;
+                self.process.clone()
             }
         }
+    }
 
-        if len == 0 {
-            return None;
+    /// The breadcrumbs the client has recorded, for a crash of its own
+    /// process, as a proxy dump on Windows is of another one
+    fn breadcrumbs_for(&self, _crash_context: &crash_context::CrashContext) -> Vec<Breadcrumb> {
+        #[cfg(target_os = "windows")]
+        if self.peer_pid != Some(_crash_context.process_id) {
+            return Vec::new();
         }
 
-        let header = Header::from_bytes(&hdr_buf)?;
+        self.breadcrumbs
+            .as_ref()
+            .map_or_else(Vec::new, |breadcrumbs| breadcrumbs.snapshot())
+    }
 
-        if header.size == 0 {
-            self.socket.recv(&mut hdr_buf).ok()?;
-            Some((header.kind, Vec::new()))
-        } else {
-            let mut buffer = handler.message_alloc();
+    /// Reads the crash context from a crash request sent by the client,
+    /// failing if the request is malformed, or doesn't come from the client's
+    /// own process on Linux/Android
+    #[cfg(not(target_os = "macos"))]
+    fn crash_request(&self, buffer: &[u8]) -> Result<crash_context::CrashContext, Error> {
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                let peer_creds = self.socket.0.initial_peer_credentials()?;
 
-            buffer.resize(header.size as usize, 0);
+                let pid = peer_creds.pid().ok_or(Error::UnknownClientPid)?;
 
-            self.socket
-                .recv_vectored(&mut [IoSliceMut::new(&mut hdr_buf), IoSliceMut::new(&mut buffer)])
-                .ok()?;
+                let crash_ctx = crash_context::CrashContext::from_bytes(buffer).ok_or_else(|| {
+                    Error::from(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "client sent an incorrectly sized buffer",
+                    ))
+                })?;
 
-            Some((header.kind, buffer))
+                // Validate that the crash info and the socket agree on the pid
+                if pid.get() != crash_ctx.pid as u32 {
+                    return Err(Error::UnknownClientPid);
+                }
+            } else if #[cfg(target_os = "windows")] {
+                use scroll::Pread;
+                let dump_request: super::DumpRequest = buffer.pread(0)?;
+
+                // MiniDumpWriteDump primarily uses `EXCEPTION_POINTERS` for its crash
+                // context information, but inside that is an `EXCEPTION_RECORD`, which
+                // is an internally linked list, so rather than recurse and allocate until
+                // the end of that linked list, we just retrieve the actual pointer from
+                // the client process, and inform the dump writer that they are pointers
+                // to a different process, as MiniDumpWriteDump will internally read
+                // the processes memory as needed. The chain is only walked, via
+                // `read_exception_records`, to fill out the dump's metadata
+                let exception_pointers = dump_request.exception_pointers as *const crash_context::EXCEPTION_POINTERS;
+
+                let crash_ctx = crash_context::CrashContext {
+                    exception_pointers,
+                    process_id: dump_request.process_id,
+                    thread_id: dump_request.thread_id,
+                    exception_code: dump_request.exception_code,
+                    timestamps: crash_context::CrashTimestamps {
+                        monotonic_ns: dump_request.monotonic_ns,
+                        realtime_ns: dump_request.realtime_ns,
+                        process_start_ns: dump_request.process_start_ns,
+                    },
+                    thread_name: crash_context::ThreadName(dump_request.thread_name),
+                    main_thread: crash_context::MainThread(dump_request.main_thread),
+                };
+            }
         }
-    }
-}
 
-impl Server {
-    /// Creates a new server with the given name.
-    ///
-    /// Note that in the case of a path socket name, this method always attempts
-    /// to delete the specified path if it exists as both Windows and Macos have
-    /// issues around cleaning up these files if the process the server runs in
-    /// aborts abnormally.
-    ///
-    /// # Errors
-    ///
-    /// The provided socket name is invalid, or the listener socket was unable
-    /// to be bound to the specified socket name.
-    pub fn with_name<'scope>(name: impl Into<SocketName<'scope>>) -> Result<Self, Error> {
-        let sn = name.into();
-
-        #[allow(irrefutable_let_patterns)]
-        let socket_path = if let SocketName::Path(path) = &sn {
-            // There seems to be a bug, at least on Windows, where checking for
-            // the existence of the file path will actually fail even if the file
-            // is actually there, so we just unconditionally remove the path
-            let _res = std::fs::remove_file(path);
+        Ok(crash_ctx)
+    }
 
-            Some(std::path::PathBuf::from(path))
-        } else {
-            None
+    /// Receives the next message from the client, reporting any gap in its
+    /// sequence to the handler
+    fn recv(
+        &mut self,
+        handler: &dyn crate::ServerHandler,
+        max_message_size: Option<usize>,
+        shared: &Shared,
+    ) -> Result<Option<(u32, Vec<u8>)>, Error> {
+        let Some((header, buffer)) = read_message(
+            &self.socket,
+            || handler.message_alloc(),
+            max_message_size.unwrap_or_else(|| handler.max_message_size()),
+            self.sequence.sequenced,
+        )?
+        else {
+            return Ok(None);
         };
 
-        cfg_if::cfg_if! {
-            if #[cfg(any(target_os = "linux", target_os = "android"))] {
-                let socket_addr = match sn {
-                    SocketName::Path(path) => {
-                        uds::UnixSocketAddr::from_path(path).map_err(|_err| Error::InvalidName)?
-                    }
-                    SocketName::Abstract(name) => {
-                        uds::UnixSocketAddr::from_abstract(name).map_err(|_err| Error::InvalidName)?
-                    }
-                };
-
-                let listener = Listener(uds::nonblocking::UnixSeqpacketListener::bind_unix_addr(&socket_addr)?);
-            } else if #[cfg(target_os = "windows")] {
-                let SocketName::Path(path) = sn;
-                let listener = Listener::bind(path)?;
-                listener.set_nonblocking(true)?;
-            } else if #[cfg(target_os = "macos")] {
-                let SocketName::Path(path) = sn;
-                let listener = Listener::bind(path)?;
-                listener.set_nonblocking(true)?;
+        if let Some(violation) = self.sequence.check(&header) {
+            log::warn!("client {} violated the protocol: {violation:?}", self.key);
 
-                // Note that sun_path is limited to 108 characters including null,
-                // while a mach port name is limited to 128 including null, so
-                // the length is already effectively checked here
-                let port_name = std::ffi::CString::new(path.to_str().ok_or(Error::InvalidPortName)?).map_err(|_err| Error::InvalidPortName)?;
-                let port = crash_context::ipc::Server::create(&port_name)?;
-            } else {
-                compile_error!("unimplemented target platform");
-            }
+            shared.sequence_gaps.fetch_add(1, Ordering::Relaxed);
+            handler.on_protocol_error(ClientId(self.key), violation);
         }
 
-        Ok(Self {
-            listener: Some(listener),
-            #[cfg(target_os = "macos")]
-            port,
-            socket_path,
-        })
+        Ok(Some((header.kind, buffer)))
     }
 
-    /// Runs the server loop, accepting client connections and receiving IPC
-    /// messages.
-    ///
-    /// If `stale_timeout` is specified, client connections that have not sent
-    /// a message within that period will be shutdown and removed, to prevent
-    /// potential issues with the server process from indefinitely outlasting
-    /// the process(es) it was monitoring for crashes, in cases where the OS
-    /// (read, Windows) might take longer than one would want to properly reap
-    /// the client connections in the event of adrupt process termination.
-    /// Sending messages will prevent the connection from going stale, but if
-    /// messages are not guaranteed to be sent at a higher frequency than your
-    /// specified timeout, you can use [`crate::Client::ping`] to fill in any
-    /// message gaps to indicate the client is still alive.
-    ///
-    /// # Errors
-    ///
-    /// This method uses basic I/O event notification via [`polling`] which
-    /// can fail for a number of different reasons
-    #[allow(unsafe_code)]
-    pub fn run(
-        &mut self,
-        handler: Box<dyn crate::ServerHandler>,
-        shutdown: &std::sync::atomic::AtomicBool,
-        stale_timeout: Option<std::time::Duration>,
-    ) -> Result<(), Error> {
-        let mut events = polling::Events::new();
-        let listener = self.listener.take().unwrap();
+    /// Sends a message to the client, with the next sequence number
+    #[inline]
+    fn send(&mut self, kind: u32, payload: &[u8]) -> std::io::Result<usize> {
+        let msg = self.sequence.message(kind, payload);
+        self.socket.send(&msg)
+    }
+}
+
+/// A client that is disconnected once every event from the current poll has
+/// been processed
+struct Disconnect {
+    /// The key of the client
+    key: usize,
+    reason: DisconnectReason,
+    /// The crash request to queue once the client's socket has been
+    /// deregistered
+    #[cfg(not(target_os = "macos"))]
+    crash: Option<(crash_context::CrashContext, Option<ProcessInfo>)>,
+}
 
-        struct Poll {
-            listener: Listener,
-            clients: Vec<ClientConn>,
-            poll: Poller,
+impl Disconnect {
+    #[inline]
+    fn new(key: usize, reason: DisconnectReason) -> Self {
+        Self {
+            key,
+            reason,
+            #[cfg(not(target_os = "macos"))]
+            crash: None,
         }
+    }
+}
 
-        impl Poll {
-            fn new(listener: Listener) -> std::io::Result<Self> {
-                let s = Self {
-                    listener,
-                    poll: Poller::new()?,
-                    clients: Vec::new(),
-                };
+/// Retrieves the credentials of the process that connected
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "android")),
+    allow(clippy::unnecessary_wraps)
+)]
+fn peer_credentials(_conn: &Connection) -> std::io::Result<PeerCredentials> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            let creds = _conn.0.initial_peer_credentials()?;
+
+            Ok(PeerCredentials {
+                pid: creds.pid().map(|pid| pid.get()),
+                uid: Some(creds.euid()),
+                gid: creds.egid(),
+            })
+        } else if #[cfg(target_os = "windows")] {
+            // The connection isn't rejected if the pid can't be retrieved, as
+            // it is only needed to validate crash requests, which are then
+            // denied instead, see `ServerOptions::allow_proxy_dumps`
+            Ok(PeerCredentials {
+                pid: _conn
+                    .peer_pid()
+                    .map_err(|err| log::warn!("failed to retrieve peer pid: {err}"))
+                    .ok(),
+                ..Default::default()
+            })
+        } else {
+            Ok(PeerCredentials::default())
+        }
+    }
+}
 
-                // SAFETY: We ensure we delete the listener during drop
-                unsafe {
-                    s.poll.add(&s.listener, Event::readable(0))?;
-                }
+/// A crash request that has been received, but whose minidump has not been
+/// written yet
+struct PendingCrash {
+    crash_context: crash_context::CrashContext,
+    process: Option<ProcessInfo>,
+    breadcrumbs: Vec<Breadcrumb>,
+    ack: CrashAck,
+}
 
-                Ok(s)
-            }
+// SAFETY: The only part of the crash context that isn't `Send` is the pointer
+// to the exception pointers, which points into the memory of the client
+// process, and is never dereferenced in this one
+#[cfg(target_os = "windows")]
+#[allow(unsafe_code)]
+unsafe impl Send for PendingCrash {}
+
+/// Acknowledges a crash request once its minidump has been written
+enum CrashAck {
+    /// The connection of the crashed client, which has already been removed
+    /// from the server loop, along with its sequence numbers
+    #[cfg(not(target_os = "macos"))]
+    Socket(Connection, Sequence),
+    /// The client requested an on-demand dump, and is still connected, so the
+    /// ack is sent by the server loop, just as for a [`ServerHandle::send_to`]
+    #[cfg(not(target_os = "macos"))]
+    Connected(ClientId, Arc<Shared>),
+    #[cfg(target_os = "macos")]
+    Port(crash_context::ipc::Acknowledger),
+}
 
-            #[inline]
-            fn add(
-                &mut self,
-                src: impl polling::AsRawSource,
-                interest: Event,
-            ) -> std::io::Result<()> {
-                // SAFETY: We ensure we delete all sources we add before dropping the poll
-                unsafe { self.poll.add(src, interest) }
+impl CrashAck {
+    fn send(self, outcome: &CrashOutcome) {
+        match self {
+            #[cfg(not(target_os = "macos"))]
+            Self::Socket(socket, mut sequence) => {
+                if let Err(err) = socket.send(&outcome.ack(&mut sequence)) {
+                    log::error!("failed to send ack: {err}");
+                }
             }
-        }
-
-        impl Drop for Poll {
-            fn drop(&mut self) {
-                for client in std::mem::take(&mut self.clients) {
-                    if let Err(err) = self.poll.delete(client.socket) {
-                        log::error!("failed to deregister socket: {err}");
+            #[cfg(not(target_os = "macos"))]
+            Self::Connected(client, shared) => {
+                shared.commands.lock().push(Command::SendTo(
+                    client,
+                    super::CRASH_ACK,
+                    outcome.payload(),
+                ));
+            }
+            #[cfg(target_os = "macos")]
+            Self::Port(mut acker) => {
+                let status = match outcome {
+                    CrashOutcome::Unknown => 1,
+                    CrashOutcome::Written(_) => super::ACK_DUMP_WRITTEN.into(),
+                    CrashOutcome::Failed { category, .. } => {
+                        u32::from(super::ACK_DUMP_FAILED) | u32::from(category.as_u8()) << 8
                     }
-                }
+                };
 
-                if let Err(err) = self.poll.delete(&self.listener) {
-                    log::error!("failed to deregister listener: {err}");
+                if let Err(err) = acker.send_ack(status, Some(Duration::from_secs(2))) {
+                    log::error!("failed to send ack: {err}");
                 }
             }
         }
+    }
+}
 
-        let mut polling = Poll::new(listener)?;
-        let mut id = 1;
-
-        loop {
-            if shutdown.load(std::sync::atomic::Ordering::Relaxed) {
-                return Ok(());
-            }
+/// The outcome of a crash request, reported to the client in the
+/// [`super::CRASH_ACK`], see [`super::DumpOutcome`]
+pub(super) enum CrashOutcome {
+    /// The handler processed the crash context itself, so the outcome is not
+    /// known
+    #[cfg_attr(feature = "minidump-writer", allow(dead_code))]
+    Unknown,
+    /// The minidump was written to the path
+    #[cfg_attr(not(feature = "minidump-writer"), allow(dead_code))]
+    Written(std::path::PathBuf),
+    /// The minidump could not be written
+    Failed {
+        category: crate::DumpFailureCategory,
+        code: Option<i32>,
+    },
+    /// The request was deferred, and should be retried after the delay
+    #[cfg(not(target_os = "macos"))]
+    Busy(Duration),
+}
 
-            events.clear();
-            let timeout = Duration::from_millis(10);
-            let deadline = Instant::now() + timeout;
-            let mut remaining = Some(timeout);
-            while let Some(timeout) = remaining {
-                match polling.poll.wait(&mut events, Some(timeout)) {
-                    Ok(_) => {
-                        break;
+impl CrashOutcome {
+    /// The payload of the [`super::CRASH_ACK`], which is empty if the outcome
+    /// is unknown, as older servers never send a payload
+    pub(super) fn payload(&self) -> Vec<u8> {
+        match self {
+            Self::Unknown => Vec::new(),
+            Self::Written(path) => {
+                cfg_if::cfg_if! {
+                    if #[cfg(unix)] {
+                        use std::os::unix::ffi::OsStrExt;
+                        let path = Some(path.as_os_str().as_bytes());
+                    } else {
+                        let path = path.to_str().map(str::as_bytes);
                     }
-                    Err(e) => {
-                        if matches!(e.kind(), ErrorKind::Interrupted) {
-                            remaining = deadline.checked_duration_since(Instant::now());
-                        } else {
-                            return Err(e.into());
-                        }
+                }
+
+                let mut payload = vec![super::ACK_DUMP_WRITTEN];
+                match path {
+                    Some(path) if path.len() <= super::MAX_DUMP_PATH_LEN => {
+                        payload.extend_from_slice(path);
                     }
+                    _ => log::warn!("omitting minidump path from ack"),
                 }
+                payload
             }
-
-            #[cfg(target_os = "macos")]
-            if self.check_mach_port(&polling.poll, &mut polling.clients, handler.as_ref())?
-                == LoopAction::Exit
-            {
-                return Ok(());
+            Self::Failed { category, code } => {
+                let mut payload = vec![
+                    super::ACK_DUMP_FAILED,
+                    category.as_u8(),
+                    code.is_some().into(),
+                ];
+                payload.extend_from_slice(&code.unwrap_or_default().to_le_bytes());
+                payload
             }
+            #[cfg(not(target_os = "macos"))]
+            Self::Busy(retry_after) => {
+                let millis = u32::try_from(retry_after.as_millis()).unwrap_or(u32::MAX);
 
-            for event in events.iter() {
-                if event.key == 0 {
-                    match polling.listener.accept_unix_addr() {
-                        Ok((accepted, _addr)) => {
-                            let key = id;
-                            id += 1;
-
-                            polling.add(&accepted, Event::readable(key))?;
+                let mut payload = vec![super::ACK_CRASH_BUSY];
+                payload.extend_from_slice(&millis.to_le_bytes());
+                payload
+            }
+        }
+    }
 
-                            log::debug!("accepted connection {key}");
-                            polling.clients.push(ClientConn {
-                                socket: accepted,
-                                key,
-                                last_update: Instant::now(),
-                                #[cfg(target_os = "macos")]
-                                pid: None,
-                            });
+    /// The [`super::CRASH_ACK`] message, ie. its header followed by the
+    /// [`Self::payload`]
+    #[cfg(not(target_os = "macos"))]
+    fn ack(&self, sequence: &mut Sequence) -> Vec<u8> {
+        sequence.message(super::CRASH_ACK, &self.payload())
+    }
+}
 
-                            if handler.on_client_connected(polling.clients.len())
-                                == LoopAction::Exit
-                            {
-                                log::debug!("on_client_connected exited message loop");
-                                return Ok(());
-                            }
-                        }
-                        Err(err) => {
-                            log::error!("failed to accept socket connection: {err}");
-                        }
-                    }
+/// Writes the minidumps for crash requests on a dedicated thread, in the order
+/// they were received, so that the server loop can keep receiving crash
+/// requests from other clients while a minidump is being written
+struct DumpWriter {
+    queue: Option<std::sync::mpsc::Sender<PendingCrash>>,
+    /// Set once the handler returns [`LoopAction::Exit`] after a minidump has
+    /// been created
+    exit: Arc<AtomicBool>,
+    /// The number of requests that are queued, or being written
+    pending: Arc<AtomicUsize>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
 
-                    // We need to reregister insterest every time
-                    polling.poll.modify(&polling.listener, Event::readable(0))?;
-                } else if let Some(pos) = polling.clients.iter().position(|cc| cc.key == event.key)
+impl DumpWriter {
+    fn spawn(
+        handler: Arc<dyn crate::ServerHandler>,
+        shared: Arc<Shared>,
+        embed_process_info: bool,
+    ) -> std::io::Result<Self> {
+        let (tx, rx) = std::sync::mpsc::channel::<PendingCrash>();
+        let exit = Arc::new(AtomicBool::new(false));
+        let should_exit = exit.clone();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let written = pending.clone();
+
+        let thread = std::thread::Builder::new()
+            .name("minidump-writer".to_owned())
+            .spawn(move || {
+                for PendingCrash {
+                    crash_context,
+                    process,
+                    breadcrumbs,
+                    ack,
+                } in rx
                 {
-                    polling.clients[pos].last_update = Instant::now();
-
-                    let deregister = match polling.clients[pos].recv(handler.as_ref()) {
-                        Some((super::CRASH, buffer)) => {
-                            cfg_if::cfg_if! {
-                                if #[cfg(target_os = "macos")] {
-                                    use scroll::Pread;
-                                    let pid: u32 = buffer.pread(0)?;
-                                    polling.clients[pos].pid = Some(pid);
-
-                                    if let Err(err) = polling.clients[pos].socket.send(&[1]) {
-                                        log::error!("failed to send ack: {err}");
-                                    }
-
-                                    None
-                                } else {
-                                    let cc = polling.clients.swap_remove(pos);
-
-                                    cfg_if::cfg_if! {
-                                        if #[cfg(any(target_os = "linux", target_os = "android"))] {
-                                            let peer_creds = cc.socket.0.initial_peer_credentials()?;
-
-                                            let pid = peer_creds.pid().ok_or(Error::UnknownClientPid)?;
-
-                                            let crash_ctx = crash_context::CrashContext::from_bytes(&buffer).ok_or_else(|| {
-                                                Error::from(std::io::Error::new(
-                                                    std::io::ErrorKind::InvalidData,
-                                                    "client sent an incorrectly sized buffer",
-                                                ))
-                                            })?;
-
-                                            // Validate that the crash info and the socket agree on the pid
-                                            if pid.get() != crash_ctx.pid as u32 {
-                                                return Err(Error::UnknownClientPid);
-                                            }
-                                        } else if #[cfg(target_os = "windows")] {
-                                            use scroll::Pread;
-                                            let dump_request: super::DumpRequest = buffer.pread(0)?;
-
-                                            // MiniDumpWriteDump primarily uses `EXCEPTION_POINTERS` for its crash
-                                            // context information, but inside that is an `EXCEPTION_RECORD`, which
-                                            // is an internally linked list, so rather than recurse and allocate until
-                                            // the end of that linked list, we just retrieve the actual pointer from
-                                            // the client process, and inform the dump writer that they are pointers
-                                            // to a different process, as MiniDumpWriteDump will internally read
-                                            // the processes memory as needed
-                                            let exception_pointers = dump_request.exception_pointers as *const crash_context::EXCEPTION_POINTERS;
-
-                                            let crash_ctx = crash_context::CrashContext {
-                                                exception_pointers,
-                                                process_id: dump_request.process_id,
-                                                thread_id: dump_request.thread_id,
-                                                exception_code: dump_request.exception_code,
-                                            };
-                                        }
-                                    }
-
-                                    let action =
-                                        match Self::handle_crash_request(crash_ctx, handler.as_ref()) {
-                                            Err(err) => {
-                                                log::error!("failed to capture minidump: {err}");
-                                                LoopAction::Continue
-                                            }
-                                            Ok(action) => {
-                                                log::info!("captured minidump");
-                                                action
-                                            }
-                                        };
-
-                                    let ack = Header {
-                                        kind: super::CRASH_ACK,
-                                        size: 0,
-                                    };
-
-                                    if let Err(err) = cc.socket.send(ack.as_bytes()) {
-                                        log::error!("failed to send ack: {err}");
-                                    }
-
-                                    if action == LoopAction::Exit {
-                                        log::debug!("user handler requested exit after minidump creation");
-                                        return Ok(());
-                                    }
+                    let (action, outcome) = match Server::handle_crash_request(
+                        crash_context,
+                        process,
+                        breadcrumbs,
+                        embed_process_info,
+                        handler.as_ref(),
+                        &shared,
+                    ) {
+                        Err(err) => {
+                            log::error!("failed to capture minidump: {err}");
 
-                                    Some(cc.socket)
+                            // The only I/O error is from creating the file,
+                            // failures to write it are reported to the handler
+                            let (category, code) = match &err {
+                                Error::Io(io) => {
+                                    (crate::DumpFailureCategory::CreateFile, io.raw_os_error())
                                 }
-                            }
-                        }
-                        Some((super::PING, _buffer)) => {
-                            let pong = Header {
-                                kind: super::PONG,
-                                size: 0,
+                                _ => (crate::DumpFailureCategory::Other, None),
                             };
 
-                            if let Err(err) = polling.clients[pos].socket.send(pong.as_bytes()) {
-                                log::error!("failed to send PONG: {err}");
-
-                                let cc = polling.clients.swap_remove(pos);
-                                Some(cc.socket)
-                            } else {
-                                None
-                            }
-                        }
-                        Some((super::PONG, _buffer)) => None,
-                        Some((kind, buffer)) => {
-                            handler.on_message(
-                                kind - super::USER, /* give the user back the original code they specified */
-                                buffer,
-                            );
-
-                            // We only send acks for crash dump requests
-                            // if let Err(e) = clients[pos].socket.send(&[1]) {
-                            //     log::error!("failed to send ack: {}", e);
-                            // }
-
-                            None
+                            (
+                                LoopAction::Continue,
+                                CrashOutcome::Failed { category, code },
+                            )
                         }
-                        None => {
-                            log::debug!("client closed socket {pos}");
-                            let cc = polling.clients.swap_remove(pos);
-                            Some(cc.socket)
+                        Ok(handled) => {
+                            log::info!("captured minidump");
+                            handled
                         }
                     };
 
-                    if let Some(socket) = deregister {
-                        if let Err(err) = polling.poll.delete(&socket) {
-                            log::error!("failed to deregister socket: {err}");
-                        }
+                    written.fetch_sub(1, Ordering::Relaxed);
+                    ack.send(&outcome);
 
-                        if handler.on_client_disconnected(polling.clients.len()) == LoopAction::Exit
-                        {
-                            log::debug!("on_client_disconnected exited message loop");
-                            return Ok(());
-                        }
-                    } else {
-                        let conn = &polling.clients[pos];
-                        polling
-                            .poll
-                            .modify(&conn.socket, Event::readable(conn.key))?;
+                    if action == LoopAction::Exit {
+                        log::debug!("user handler requested exit after minidump creation");
+                        should_exit.store(true, Ordering::Relaxed);
+                        // Any requests that are still queued are dropped,
+                        // closing their connections
+                        break;
                     }
                 }
-            }
+            })?;
 
-            if let Some(st) = stale_timeout {
-                let before = polling.clients.len();
+        Ok(Self {
+            queue: Some(tx),
+            exit,
+            pending,
+            thread: Some(thread),
+        })
+    }
 
-                // Reap any connections that haven't sent a message in the period
-                // specified by the user
-                polling.clients.retain(|conn| {
-                    let keep = conn.last_update.elapsed() < st;
+    #[inline]
+    fn queue(&self, pending: PendingCrash) {
+        if let Some(queue) = &self.queue {
+            self.pending.fetch_add(1, Ordering::Relaxed);
 
-                    if !keep {
-                        log::debug!("dropping stale connection {:?}", conn.last_update.elapsed());
-                        if let Err(err) = polling.poll.delete(&conn.socket) {
-                            log::error!("failed to deregister timed-out socket: {err}");
-                        }
-                    }
+            if queue.send(pending).is_err() {
+                log::error!("dropping crash request as the minidump writer has exited");
+                self.pending.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
 
-                    keep
-                });
+    /// The number of requests that are queued, or being written
+    #[inline]
+    #[cfg_attr(target_os = "macos", allow(dead_code))]
+    fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
+    }
 
-                if before > polling.clients.len()
-                    && handler.on_client_disconnected(polling.clients.len()) == LoopAction::Exit
-                {
-                    log::debug!("on_client_disconnected exited message loop");
-                    return Ok(());
+    #[inline]
+    fn should_exit(&self) -> bool {
+        self.exit.load(Ordering::Relaxed)
+    }
+}
+
+impl DumpWriter {
+    /// Waits for the requests that are already queued to be written, and the
+    /// thread to exit
+    fn finish(&mut self) {
+        // Closing the queue lets the writer finish the requests that are
+        // already queued, and then exit
+        drop(self.queue.take());
+
+        if let Some(thread) = self.thread.take() {
+            if let Err(panic) = thread.join() {
+                // Propagate panics from the handler, as if it had been called
+                // on this thread
+                if !std::thread::panicking() {
+                    std::panic::resume_unwind(panic);
                 }
             }
         }
     }
+}
 
-    fn handle_crash_request(
-        crash_context: crash_context::CrashContext,
-        handler: &dyn crate::ServerHandler,
-    ) -> Result<LoopAction, Error> {
-        let (mut minidump_file, minidump_path) = handler.create_minidump_file()?;
+impl Drop for DumpWriter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// The socket operations needed to read a message from a client, split out so
+/// that message parsing can be tested without an actual socket, and shared
+/// with the [`super::Client`]
+pub(super) trait MessageSource {
+    /// Whether this is a stream socket, which has no message boundaries, so
+    /// a message may only arrive over several reads, rather than the
+    /// seqpacket sockets used on Linux, where a message is read in one go
+    const STREAM: bool;
+
+    fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+    fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize>;
+    /// Returns the number of bytes read, and whether the message was truncated
+    /// because the buffers were not large enough to hold all of it
+    fn recv_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<(usize, bool)>;
+}
+
+impl MessageSource for Connection {
+    const STREAM: bool = cfg!(not(any(target_os = "linux", target_os = "android")));
 
+    #[inline]
+    fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
         cfg_if::cfg_if! {
             if #[cfg(any(target_os = "linux", target_os = "android"))] {
-                let mut writer =
-                    minidump_writer::minidump_writer::MinidumpWriter::new(crash_context.pid, crash_context.tid);
-                writer.set_crash_context(minidump_writer::crash_context::CrashContext { inner: crash_context });
+                self.0.peek(buf)
+            } else {
+                Connection::peek(self, buf)
+            }
+        }
+    }
+
+    #[inline]
+    fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Connection::recv(self, buf)
+    }
+
+    #[inline]
+    fn recv_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<(usize, bool)> {
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                Connection::recv_vectored(self, bufs)
+            } else {
+                // Stream sockets don't have message boundaries, so any bytes
+                // past the end of our buffers are simply left for the next read
+                Connection::recv_vectored(self, bufs).map(|len| (len, false))
+            }
+        }
+    }
+}
+
+/// Verifies that a socket passed to [`Server::from_listener`] is a listening
+/// `AF_UNIX` socket of the expected type
+#[cfg(unix)]
+#[allow(unsafe_code)]
+fn check_listener(fd: std::os::fd::BorrowedFd<'_>, expected_type: i32) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    fn get(fd: std::os::fd::BorrowedFd<'_>, level: i32, name: i32) -> std::io::Result<i32> {
+        let mut value = 0i32;
+        let mut len = std::mem::size_of::<i32>() as libc::socklen_t;
+
+        // SAFETY: syscall
+        if unsafe {
+            libc::getsockopt(
+                fd.as_raw_fd(),
+                level,
+                name,
+                (&mut value as *mut i32).cast(),
+                &mut len,
+            )
+        } != 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(value)
+    }
+
+    // SAFETY: syscall, the storage is large enough for any address family
+    let family = unsafe {
+        let mut addr: libc::sockaddr_storage = std::mem::zeroed();
+        let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+
+        if libc::getsockname(
+            fd.as_raw_fd(),
+            (&mut addr as *mut libc::sockaddr_storage).cast(),
+            &mut len,
+        ) != 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        i32::from(addr.ss_family)
+    };
+
+    let invalid = |msg| Err(std::io::Error::new(ErrorKind::InvalidInput, msg));
+
+    if family != libc::AF_UNIX {
+        invalid("the listener is not a Unix domain socket")
+    } else if get(fd, libc::SOL_SOCKET, libc::SO_TYPE)? != expected_type {
+        invalid("the listener is not of the expected socket type")
+    } else if get(fd, libc::SOL_SOCKET, libc::SO_ACCEPTCONN)? == 0 {
+        invalid("the socket is not listening")
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether this process is denied from `ptrace`ing the process, which is
+/// checked by opening its memory, as that requires the same permission
+#[cfg(all(
+    feature = "minidump-writer",
+    any(target_os = "linux", target_os = "android")
+))]
+fn is_ptrace_denied(pid: libc::pid_t) -> bool {
+    matches!(
+        std::fs::File::open(format!("/proc/{pid}/mem")),
+        Err(err) if err.kind() == ErrorKind::PermissionDenied
+    )
+}
+
+/// Reads the next message from a client.
+///
+/// Returns `Ok(None)` if the client has closed the connection. The payload is
+/// allocated with `alloc`, but only once the header has been validated, so a
+/// client can never make the server allocate more than `max_size` bytes for a
+/// single message.
+///
+/// # Errors
+///
+/// Any malformed, truncated, or oversized message results in an
+/// [`Error::ProtocolError`], after which the stream can't be trusted, and
+/// failures from the underlying socket are returned as is.
+fn read_message(
+    source: &impl MessageSource,
+    alloc: impl FnOnce() -> Vec<u8>,
+    max_size: usize,
+    sequenced: bool,
+) -> Result<Option<(Header, Vec<u8>)>, Error> {
+    // Only user messages, and the internal kinds that are sent by clients,
+    // are accepted, eg. acks are only ever sent from the server to the client
+    read_message_of(source, alloc, max_size, sequenced, |kind| {
+        (super::USER..=super::USER + super::MAX_MESSAGE_KIND).contains(&kind)
+            || matches!(
+                kind,
+                super::CRASH | super::PING | super::HELLO | super::BREADCRUMBS
+            )
+    })
+}
+
+/// Reads the next message, as [`read_message`], but only accepting messages
+/// whose kind passes `is_valid_kind`, so that it can be used by either end of
+/// the connection
+pub(super) fn read_message_of<S: MessageSource>(
+    source: &S,
+    alloc: impl FnOnce() -> Vec<u8>,
+    max_size: usize,
+    sequenced: bool,
+    is_valid_kind: impl FnOnce(u32) -> bool,
+) -> Result<Option<(Header, Vec<u8>)>, Error> {
+    let header_size = Header::wire_size(sequenced);
+
+    let mut hdr_buf = [0u8; Header::SEQUENCED_SIZE];
+    let hdr_buf = &mut hdr_buf[..header_size];
+    // The header can only be peeked on a seqpacket socket, as the whole
+    // message must be received at once, but on a stream socket it is just
+    // consumed, the rest of the message following it in later reads
+    let len = if S::STREAM {
+        recv_exact(source, hdr_buf)?
+    } else {
+        source.peek(hdr_buf)?
+    };
+
+    if len == 0 {
+        return Ok(None);
+    }
+
+    let header = Header::from_bytes(&hdr_buf[..len], sequenced)
+        .ok_or(Error::ProtocolError("received a truncated message header"))?;
+
+    if !is_valid_kind(header.kind) {
+        return Err(Error::ProtocolError("received an invalid message kind"));
+    }
+
+    // The client can't know the limit when it sends its `HELLO`, so it is
+    // always accepted, up to the size that clients limit it to
+    let max_size = if header.kind == super::HELLO {
+        max_size.max(super::MAX_HELLO_SIZE)
+    } else {
+        max_size
+    };
+
+    let size = header.size as usize;
+    if size > max_size {
+        return Err(Error::ProtocolError(
+            "received a message larger than the maximum message size",
+        ));
+    }
+
+    if S::STREAM {
+        if size == 0 {
+            return Ok(Some((header, Vec::new())));
+        }
+
+        let mut buffer = alloc();
+        buffer.resize(size, 0);
+
+        if recv_exact(source, &mut buffer)? != size {
+            return Err(Error::ProtocolError("received a truncated message"));
+        }
+
+        return Ok(Some((header, buffer)));
+    }
+
+    if size == 0 {
+        if source.recv(hdr_buf)? != header_size {
+            return Err(Error::ProtocolError("received a truncated message header"));
+        }
+
+        return Ok(Some((header, Vec::new())));
+    }
+
+    let mut buffer = alloc();
+    buffer.resize(size, 0);
+
+    let (read, truncated) =
+        source.recv_vectored(&mut [IoSliceMut::new(hdr_buf), IoSliceMut::new(&mut buffer)])?;
+
+    if truncated {
+        return Err(Error::ProtocolError(
+            "received a message larger than its header specified",
+        ));
+    } else if read != header_size + size {
+        return Err(Error::ProtocolError("received a truncated message"));
+    }
+
+    Ok(Some((header, buffer)))
+}
+
+/// How long to wait for the rest of a message that has only partially arrived
+/// on a stream socket, before giving up on the client
+const PARTIAL_MESSAGE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Reads from a stream socket until `buf` is full, returning the number of
+/// bytes read, which is only less than the length of `buf` if the connection
+/// was closed.
+///
+/// The server's sockets are nonblocking, so this waits, for at most
+/// [`PARTIAL_MESSAGE_TIMEOUT`], for the rest of a message that hasn't fully
+/// arrived yet.
+fn recv_exact(source: &impl MessageSource, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut read = 0;
+    let mut deadline = None;
+    while read < buf.len() {
+        match source.recv(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(len) => read += len,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                let deadline =
+                    *deadline.get_or_insert_with(|| Instant::now() + PARTIAL_MESSAGE_TIMEOUT);
+                if Instant::now() >= deadline {
+                    return Err(Error::ProtocolError(
+                        "timed out waiting for the rest of a message",
+                    ));
+                }
+
+                std::thread::yield_now();
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(read)
+}
+
+/// Options for [`Server::with_name_and_options`] and [`Server::run_with_options`]
+#[derive(Clone, Debug)]
+pub struct ServerOptions {
+    #[cfg(unix)]
+    pub(crate) socket_mode: Option<u32>,
+    #[cfg(windows)]
+    pub(crate) owner_only: bool,
+    #[cfg(windows)]
+    pub(crate) allow_proxy_dumps: bool,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) allowed_uids: UidPolicy,
+    pub(crate) accept_shutdown_requests: bool,
+    pub(crate) stale_timeout: Option<Duration>,
+    pub(crate) cleanup_socket_on_drop: bool,
+    pub(crate) listen_backlog: u32,
+    pub(crate) poll_interval: Duration,
+    pub(crate) max_clients: Option<usize>,
+    pub(crate) max_message_size: Option<usize>,
+    pub(crate) capture_process_info: bool,
+    pub(crate) allowed_env_vars: Vec<String>,
+    #[cfg(feature = "minidump-writer")]
+    pub(crate) embed_process_info: bool,
+}
+
+/// The users whose processes are allowed to connect to the [`Server`]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Clone, Debug)]
+pub(crate) enum UidPolicy {
+    SameUser,
+    Any,
+    Only(Vec<u32>),
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            #[cfg(unix)]
+            socket_mode: Some(0o600),
+            #[cfg(windows)]
+            owner_only: true,
+            #[cfg(windows)]
+            allow_proxy_dumps: false,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            allowed_uids: UidPolicy::SameUser,
+            accept_shutdown_requests: false,
+            stale_timeout: None,
+            cleanup_socket_on_drop: true,
+            listen_backlog: 128,
+            poll_interval: Duration::from_millis(10),
+            max_clients: None,
+            max_message_size: None,
+            capture_process_info: true,
+            allowed_env_vars: Vec::new(),
+            #[cfg(feature = "minidump-writer")]
+            embed_process_info: false,
+        }
+    }
+}
+
+impl ServerOptions {
+    /// The permissions the socket path is set to immediately after it is
+    /// bound, `0o600` by default, so that only processes running as the same
+    /// user can connect to the server.
+    ///
+    /// If `None`, the permissions are left as determined by the umask.
+    ///
+    /// This has no effect on abstract socket names, which have no permissions.
+    #[cfg(unix)]
+    #[inline]
+    pub fn socket_mode(mut self, mode: Option<u32>) -> Self {
+        self.socket_mode = mode;
+        self
+    }
+
+    /// If true, the default, the DACL of the socket path is replaced
+    /// immediately after it is bound with one that only grants access to the
+    /// user the server is running as, so that only processes running as the
+    /// same user can connect to the server.
+    ///
+    /// If false, the DACL is inherited from the parent directory as usual.
+    #[cfg(windows)]
+    #[inline]
+    pub fn owner_only(mut self, owner_only: bool) -> Self {
+        self.owner_only = owner_only;
+        self
+    }
+
+    /// If true, clients are allowed to request minidumps of processes other
+    /// than their own, see [`crate::Client::request_dump_for`]. False by
+    /// default, in which case a client whose crash request is for a process
+    /// other than its own is disconnected without a minidump being written,
+    /// with [`crate::DisconnectReason::Errored`] with
+    /// [`std::io::ErrorKind::PermissionDenied`].
+    ///
+    /// The process a request is for is compared to the process id of the
+    /// client, as reported by the OS when it connected, so if that couldn't be
+    /// retrieved, every request from the client is considered to be for
+    /// another process.
+    #[cfg(windows)]
+    #[inline]
+    pub fn allow_proxy_dumps(mut self, allow: bool) -> Self {
+        self.allow_proxy_dumps = allow;
+        self
+    }
+
+    /// The effective user ids of the processes that are allowed to connect to
+    /// the server. By default, only processes running as the same effective
+    /// user as the server are allowed, and note that this replaces that
+    /// default rather than adding to it.
+    ///
+    /// Connections from processes running as any other user are closed as soon
+    /// as they are accepted, without calling
+    /// [`crate::ServerHandler::on_client_connected`], and are counted in
+    /// [`ServerStats::rejected_connections`].
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[inline]
+    pub fn allowed_uids(mut self, uids: impl IntoIterator<Item = u32>) -> Self {
+        self.allowed_uids = UidPolicy::Only(uids.into_iter().collect());
+        self
+    }
+
+    /// Allows processes running as any user to connect to the server, see
+    /// [`Self::allowed_uids`].
+    ///
+    /// Note that the permissions of the socket path, see [`Self::socket_mode`],
+    /// still apply.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[inline]
+    pub fn allow_any_uid(mut self) -> Self {
+        self.allowed_uids = UidPolicy::Any;
+        self
+    }
+
+    /// If true, a shutdown request sent by a client, eg. via
+    /// [`crate::MonitorHandle::shutdown`], sets the `shutdown` flag passed to
+    /// [`Server::run`], so that the loop exits as if it had been set by the
+    /// caller. By default, shutdown requests are ignored.
+    #[inline]
+    pub fn accept_shutdown_requests(mut self, accept: bool) -> Self {
+        self.accept_shutdown_requests = accept;
+        self
+    }
+
+    /// If specified, client connections that have not sent a message within
+    /// that period are shutdown and removed, to prevent potential issues with
+    /// the server process from indefinitely outlasting the process(es) it was
+    /// monitoring for crashes, in cases where the OS (read, Windows) might take
+    /// longer than one would want to properly reap the client connections in
+    /// the event of adrupt process termination. By default, connections never
+    /// go stale.
+    ///
+    /// Sending messages will prevent the connection from going stale, but if
+    /// messages are not guaranteed to be sent at a higher frequency than your
+    /// specified timeout, you can use [`crate::Client::ping`] to fill in any
+    /// message gaps to indicate the client is still alive.
+    ///
+    /// Connections are only reaped between iterations of the server loop, see
+    /// [`Self::poll_interval`], so a crash request from a client that has been
+    /// quiet for longer than the timeout is still accepted if its connection
+    /// hasn't been reaped yet. Once it has been, the client's
+    /// [`crate::Client::request_dump`] fails and no minidump is written, so
+    /// clients should ping at comfortably less than the timeout.
+    #[inline]
+    pub fn stale_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.stale_timeout = timeout;
+        self
+    }
+
+    /// The maximum number of connections waiting to be accepted by the server
+    /// loop, 128 by default. Connections beyond that are refused, or, on
+    /// Linux/Android, wait for room in the backlog, so servers that many
+    /// clients connect to at once, eg. when a fleet of processes is started,
+    /// may want a larger one. Note that the OS may cap it, eg. to
+    /// `/proc/sys/net/core/somaxconn` on Linux.
+    ///
+    /// This is only used when the server binds the socket, see
+    /// [`Server::with_name_and_options`].
+    #[inline]
+    pub fn listen_backlog(mut self, backlog: u32) -> Self {
+        self.listen_backlog = backlog;
+        self
+    }
+
+    /// If true, the default, the socket path is removed when the [`Server`] is
+    /// dropped.
+    ///
+    /// If false, the path is left as is, for users that manage it themselves,
+    /// eg. because it is bound again by another server after this one exits.
+    /// Note that a path left behind is still removed if it is stale when a
+    /// server is created with it, see [`Server::with_name`].
+    ///
+    /// This has no effect on abstract socket names, which are removed by the
+    /// OS when the socket is closed.
+    #[inline]
+    pub fn cleanup_socket_on_drop(mut self, cleanup: bool) -> Self {
+        self.cleanup_socket_on_drop = cleanup;
+        self
+    }
+
+    /// The longest the server loop waits for socket events before checking
+    /// the `shutdown` flag, reaping stale connections, and sending the
+    /// messages queued by [`ServerHandle`]s, 10ms by default.
+    ///
+    /// Longer intervals reduce wakeups of an idle server, at the cost of
+    /// reacting to those more slowly.
+    #[inline]
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// The maximum number of clients that can be connected at the same time.
+    /// Further connections are closed as soon as they are accepted, without
+    /// calling [`crate::ServerHandler::on_client_connected`], and are counted
+    /// in [`ServerStats::rejected_connections`]. Unlimited by default.
+    #[inline]
+    pub fn max_clients(mut self, max: Option<usize>) -> Self {
+        self.max_clients = max;
+        self
+    }
+
+    /// The maximum size, in bytes, of the payload of a single message the
+    /// server will accept from a client, overriding
+    /// [`crate::ServerHandler::max_message_size`], which is used by default.
+    #[inline]
+    pub fn max_message_size(mut self, max: Option<usize>) -> Self {
+        self.max_message_size = max;
+        self
+    }
+
+    /// If true, the default, the command line of a crashed client, and the
+    /// environment variables in [`Self::allowed_env_vars`], are captured
+    /// when its crash request is received, and provided as
+    /// [`crate::DumpMetadata::process`].
+    ///
+    /// If false, neither is captured, and on Windows and Macos, where they
+    /// are sent by the client when it connects, they are discarded as soon as
+    /// they are received.
+    #[inline]
+    pub fn capture_process_info(mut self, capture: bool) -> Self {
+        self.capture_process_info = capture;
+        self
+    }
+
+    /// The names of the environment variables of a crashed client that are
+    /// captured along with its command line, see
+    /// [`Self::capture_process_info`]. By default, no environment variables
+    /// are captured, as they often contain secrets.
+    ///
+    /// Names are case insensitive on Windows.
+    #[inline]
+    pub fn allowed_env_vars<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.allowed_env_vars = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// If true, the captured command line and environment, see
+    /// [`Self::capture_process_info`], are also added to the minidump as the
+    /// [`crate::CMDLINE_STREAM_TYPE`] and [`crate::ENVIRON_STREAM_TYPE`]
+    /// streams. False by default.
+    ///
+    /// # Linux/Android
+    ///
+    /// `minidump-writer` always writes streams of those types, with the
+    /// entire environment of the client, which are replaced, and zeroed, if
+    /// this is enabled, so that only the allowed environment variables are
+    /// in the minidump.
+    ///
+    /// # Windows
+    ///
+    /// The minidump is read back from the file returned by
+    /// [`crate::ServerHandler::create_minidump_file`], which must therefore
+    /// be opened for reading as well as writing.
+    #[cfg(feature = "minidump-writer")]
+    #[inline]
+    pub fn embed_process_info(mut self, embed: bool) -> Self {
+        self.embed_process_info = embed;
+        self
+    }
+
+    /// The command line and environment of the client process, if they are
+    /// to be captured, either read from the process, or, on Windows and
+    /// Macos, as received from the client when it connected
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "android")),
+        allow(clippy::unused_self)
+    )]
+    fn process_info(
+        &self,
+        _crash_context: &crash_context::CrashContext,
+        _received: Option<ProcessInfo>,
+    ) -> Option<ProcessInfo> {
+        if !self.capture_process_info {
+            return None;
+        }
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                let pid = _crash_context.pid as u32;
+
+                match ProcessInfo::read(pid, &self.allowed_env_vars) {
+                    Ok(info) => Some(info),
+                    Err(err) => {
+                        log::warn!("failed to read the command line and environment of {pid}: {err}");
+                        None
+                    }
+                }
+            } else {
+                _received
+            }
+        }
+    }
+
+    /// Whether the peer is allowed to connect
+    #[cfg_attr(
+        not(any(target_os = "linux", target_os = "android")),
+        allow(clippy::unused_self)
+    )]
+    fn allows(&self, _peer: &PeerCredentials) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                match &self.allowed_uids {
+                    UidPolicy::Any => true,
+                    // SAFETY: syscall
+                    #[allow(unsafe_code)]
+                    UidPolicy::SameUser => _peer.uid == Some(unsafe { libc::geteuid() }),
+                    UidPolicy::Only(uids) => matches!(_peer.uid, Some(uid) if uids.contains(&uid)),
+                }
+            } else {
+                true
+            }
+        }
+    }
+
+    /// Whether a crash request from a client is allowed, ie. whether it is for
+    /// the client's own process, or proxy requests are allowed. Requests that
+    /// can't be decoded are allowed, so that they are reported as such
+    #[cfg(target_os = "windows")]
+    fn allows_crash_request(&self, peer_pid: Option<u32>, buffer: &[u8]) -> bool {
+        use scroll::Pread;
+
+        if self.allow_proxy_dumps {
+            return true;
+        }
+
+        match buffer.pread::<super::DumpRequest>(0) {
+            Ok(request) => peer_pid == Some(request.process_id),
+            Err(_err) => true,
+        }
+    }
+
+    /// Applies the permissions to the socket path
+    fn restrict(&self, path: &std::path::Path) -> Result<(), Error> {
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                use std::os::unix::fs::PermissionsExt;
+
+                if let Some(mode) = self.socket_mode {
+                    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+                }
+            } else if #[cfg(windows)] {
+                if self.owner_only {
+                    super::windows::restrict_to_current_user(path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Server {
+    /// Creates a new server with the given name, and the default
+    /// [`ServerOptions`].
+    ///
+    /// In the case of a path socket name, the path may have been left behind by
+    /// a previous server that exited abnormally, eg. if it was killed, which
+    /// would cause the bind to fail as the address is in use. If so, the path
+    /// is probed by connecting to it, and is removed and bound again if no
+    /// server is listening on it. A path that a live server is listening on is
+    /// never removed.
+    ///
+    /// # Errors
+    ///
+    /// The provided socket name is invalid or unsupported on the current
+    /// platform, or the listener socket was unable to be bound to the specified
+    /// socket name, including if another server is already listening on it, in
+    /// which case the error is an [`std::io::ErrorKind::AddrInUse`] I/O error.
+    #[inline]
+    pub fn with_name<'scope>(name: impl Into<SocketName<'scope>>) -> Result<Self, Error> {
+        Self::with_name_and_options(name, ServerOptions::default())
+    }
+
+    /// Creates a new server with the given name and options.
+    ///
+    /// See [`Self::with_name`] for details.
+    ///
+    /// # Errors
+    ///
+    /// As [`Self::with_name`], or the permissions could not be applied to the
+    /// socket path.
+    pub fn with_name_and_options<'scope>(
+        name: impl Into<SocketName<'scope>>,
+        options: ServerOptions,
+    ) -> Result<Self, Error> {
+        let sn = name.into();
+
+        let socket_path = if let SocketName::Path(path) = &sn {
+            Some(std::path::PathBuf::from(path))
+        } else {
+            None
+        };
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                let socket_addr = match sn {
+                    SocketName::Path(path) => {
+                        uds::UnixSocketAddr::from_path(path).map_err(|_err| Error::InvalidName)?
+                    }
+                    SocketName::Abstract(name) => {
+                        uds::UnixSocketAddr::from_abstract(name).map_err(|_err| Error::InvalidName)?
+                    }
+                };
+
+                let listener = bind_path(
+                    socket_path.as_deref(),
+                    || uds::nonblocking::UnixSeqpacketListener::bind_unix_addr(&socket_addr).map(Listener),
+                    || Stream::connect_unix_addr(&socket_addr),
+                )?;
+                set_backlog(&listener, options.listen_backlog)?;
+            } else if #[cfg(target_os = "windows")] {
+                let path = sn.into_path()?;
+                let socket_addr = super::windows::UnixSocketAddr::from_path(path).map_err(|_err| Error::InvalidName)?;
+                let listener = bind_path(
+                    socket_path.as_deref(),
+                    || Listener::bind_unix_addr(&socket_addr, options.listen_backlog),
+                    || Stream::connect_unix_addr(&socket_addr),
+                )?;
+                listener.set_nonblocking(true)?;
+            } else if #[cfg(target_os = "macos")] {
+                let path = sn.into_path()?;
+                // Validate the path up front so that an invalid path is reported
+                // as such rather than as an I/O error from the bind
+                let socket_addr = super::mac::UnixSocketAddr::new(path).map_err(|_err| Error::InvalidName)?;
+                let listener = bind_path(
+                    socket_path.as_deref(),
+                    || Listener::bind(path),
+                    || Stream::connect_unix_addr(&socket_addr),
+                )?;
+                set_backlog(&listener, options.listen_backlog)?;
+                listener.set_nonblocking(true)?;
+
+                // Note that sun_path is limited to 108 characters including null,
+                // while a mach port name is limited to 128 including null, so
+                // the length is already effectively checked here
+                let port_name = std::ffi::CString::new(path.to_str().ok_or(Error::InvalidPortName)?).map_err(|_err| Error::InvalidPortName)?;
+                let port_name = PortName::claim(&port_name)?;
+                let port = crash_context::ipc::Server::create(&port_name.0)?;
+            } else {
+                compile_error!("unimplemented target platform");
+            }
+        }
+
+        // Construct the server first, so that the socket path is removed if
+        // the permissions can't be applied
+        let server = Self {
+            listener: Some(listener),
+            #[cfg(target_os = "macos")]
+            port,
+            #[cfg(target_os = "macos")]
+            _port_name: port_name,
+            socket_path,
+            shared: Default::default(),
+            options,
+        };
+
+        if let Some(path) = &server.socket_path {
+            server.options.restrict(path)?;
+        }
+
+        Ok(server)
+    }
+
+    /// Creates a new server from a socket that has already been bound and is
+    /// listening, eg. one passed by systemd socket activation, or created and
+    /// handed down by a parent process.
+    ///
+    /// * Linux/Android - `listener` must be an `AF_UNIX` `SOCK_SEQPACKET` socket
+    /// * Windows - `listener` must be an `AF_UNIX` `SOCK_STREAM` socket
+    /// * Macos - `listener` must be an `AF_UNIX` `SOCK_STREAM` socket, and
+    ///   `port_name` is the name of the mach port that is created to receive
+    ///   crash requests, which must be the same as the name clients connect
+    ///   with, ie. the socket path
+    ///
+    /// The listener is switched to non-blocking mode, but otherwise used as is.
+    /// Unlike [`Self::with_name`], no path is removed before the server starts,
+    /// and the permissions of the socket are left as is.
+    ///
+    /// If `socket_path` is `Some`, the server takes ownership of the path and
+    /// removes it when dropped, just as it does for the path it binds in
+    /// [`Self::with_name`]. If `None`, whoever bound the socket is responsible
+    /// for cleaning up its path, if any.
+    ///
+    /// # Errors
+    ///
+    /// The listener is not a listening socket of the expected type, or, on
+    /// Macos, the mach port could not be created.
+    #[allow(unsafe_code)]
+    pub fn from_listener(
+        #[cfg(unix)] listener: std::os::fd::OwnedFd,
+        #[cfg(windows)] listener: std::os::windows::io::OwnedSocket,
+        socket_path: Option<std::path::PathBuf>,
+        #[cfg(target_os = "macos")] port_name: &std::ffi::CStr,
+    ) -> Result<Self, Error> {
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                use std::os::fd::{AsFd, FromRawFd, IntoRawFd};
+
+                check_listener(listener.as_fd(), libc::SOCK_SEQPACKET)?;
+
+                // SAFETY: we have ownership of the file descriptor
+                let listener = unsafe {
+                    let blocking = uds::UnixSeqpacketListener::from_raw_fd(listener.into_raw_fd());
+                    blocking.set_nonblocking(true)?;
+                    Listener(uds::nonblocking::UnixSeqpacketListener::from_raw_fd(blocking.into_raw_fd()))
+                };
+            } else if #[cfg(target_os = "windows")] {
+                let listener = Listener::from_socket(listener);
+                listener.set_nonblocking(true)?;
+            } else if #[cfg(target_os = "macos")] {
+                use std::os::fd::AsFd;
+
+                check_listener(listener.as_fd(), libc::SOCK_STREAM)?;
+
+                let listener = Listener::from_fd(listener);
+                listener.set_nonblocking(true)?;
+
+                let port_name = PortName::claim(port_name)?;
+                let port = crash_context::ipc::Server::create(&port_name.0)?;
+            } else {
+                compile_error!("unimplemented target platform");
+            }
+        }
+
+        Ok(Self {
+            listener: Some(listener),
+            #[cfg(target_os = "macos")]
+            port,
+            #[cfg(target_os = "macos")]
+            _port_name: port_name,
+            socket_path,
+            shared: Default::default(),
+            options: ServerOptions::default(),
+        })
+    }
+
+    /// Retrieves a handle that can be used to send messages to clients from
+    /// other threads while [`Self::run`] is running
+    #[inline]
+    pub fn handle(&self) -> ServerHandle {
+        ServerHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Runs the server loop, accepting client connections and receiving IPC
+    /// messages, with the options the server was created with.
+    ///
+    /// If `stale_timeout` is specified, client connections that have not sent
+    /// a message within that period will be shutdown and removed, see
+    /// [`ServerOptions::stale_timeout`].
+    ///
+    /// See [`Self::run_with_options`] for details.
+    ///
+    /// # Errors
+    ///
+    /// This method uses basic I/O event notification via [`polling`] which
+    /// can fail for a number of different reasons
+    #[inline]
+    pub fn run(
+        &mut self,
+        handler: Box<dyn crate::ServerHandler>,
+        shutdown: &std::sync::atomic::AtomicBool,
+        stale_timeout: Option<std::time::Duration>,
+    ) -> Result<(), Error> {
+        let options = self.options.clone().stale_timeout(stale_timeout);
+        self.run_with_options(handler, shutdown, options)
+    }
+
+    /// Runs the server loop, accepting client connections and receiving IPC
+    /// messages.
+    ///
+    /// The `options` replace the ones the server was created with, though the
+    /// ones that only apply when the socket is bound, eg.
+    /// [`ServerOptions::socket_mode`], have no effect at this point.
+    ///
+    /// Crash requests are queued as soon as they are received, and their
+    /// minidumps written one at a time, in order, on a separate thread, so
+    /// that the loop can keep handling other clients, including other clients
+    /// that have crashed, in the meantime. This means that
+    /// [`crate::ServerHandler::create_minidump_file`] and
+    /// [`crate::ServerHandler::on_minidump_created`] are called concurrently
+    /// with the handler's other methods. When shutdown, this method only
+    /// returns once the minidumps for the requests already queued have been
+    /// written.
+    ///
+    /// To run several servers from a single loop, see [`ServerSet`].
+    ///
+    /// # Errors
+    ///
+    /// This method uses basic I/O event notification via [`polling`] which
+    /// can fail for a number of different reasons
+    pub fn run_with_options(
+        &mut self,
+        handler: Box<dyn crate::ServerHandler>,
+        shutdown: &std::sync::atomic::AtomicBool,
+        options: ServerOptions,
+    ) -> Result<(), Error> {
+        self.options = options;
+
+        let poll = Poller::new()?;
+        let mut events = polling::Events::new();
+        let poll_interval = self.options.poll_interval;
+
+        let mut running = Running::start(self, handler, &poll, Slot::SINGLE)?;
+
+        loop {
+            if shutdown.load(std::sync::atomic::Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            if running.writer.should_exit() {
+                return Ok(());
+            }
+
+            let ready = wait(&poll, &mut events, poll_interval)?;
+
+            if running.process(&ready, shutdown)? == LoopAction::Exit {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Sends the messages queued by [`ServerHandle`]s, and publishes the
+    /// current set of clients to them
+    fn send_queued(&self, clients: &mut HashMap<usize, ClientConn>) {
+        {
+            // Published in the order the clients connected in, rather than
+            // the map's
+            let mut ready: Vec<_> = clients
+                .values()
+                .filter(|cc| cc.is_ready())
+                .map(|cc| ClientId(cc.key))
+                .collect();
+            ready.sort_unstable();
+
+            let mut published = self.shared.clients.lock();
+            if *published != ready {
+                *published = ready;
+            }
+        }
+
+        let commands = std::mem::take(&mut *self.shared.commands.lock());
+
+        let send = |cc: &mut ClientConn, kind: u32, payload: &[u8]| {
+            let msg = cc.sequence.message(kind, payload);
+            match cc.socket.send(&msg) {
+                Ok(sent) if sent == msg.len() => {}
+                Ok(_) => log::error!("failed to send complete message to client {}", cc.key),
+                Err(err) => log::error!("failed to send message to client {}: {err}", cc.key),
+            }
+        };
+
+        for cmd in commands {
+            match cmd {
+                Command::SendTo(id, kind, payload) => {
+                    if let Some(cc) = clients.get_mut(&id.0).filter(|cc| cc.is_ready()) {
+                        send(cc, kind, &payload);
+                    } else {
+                        log::debug!("dropping message to unknown client {}", id.0);
+                    }
+                }
+                Command::Broadcast(kind, payload) => {
+                    for cc in clients.values_mut().filter(|cc| cc.is_ready()) {
+                        send(cc, kind, &payload);
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_crash_request(
+        crash_context: crash_context::CrashContext,
+        process: Option<ProcessInfo>,
+        breadcrumbs: Vec<Breadcrumb>,
+        _embed_process_info: bool,
+        handler: &dyn crate::ServerHandler,
+        _shared: &Shared,
+    ) -> Result<(LoopAction, CrashOutcome), Error> {
+        // Started before the metadata, as reading it from the process can also
+        // fail in ways that only degrade the minidump
+        #[cfg(feature = "minidump-writer")]
+        let capture = crate::dump_warnings::Capture::start();
+
+        let metadata = crate::DumpMetadata {
+            timestamps: crash_context.timestamps,
+            thread_id: crash_context.crashing_thread_id(),
+            thread_name: crash_context.thread_name,
+            is_main_thread: crash_context.is_main_thread(),
+            on_demand: super::is_on_demand(&crash_context),
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            tls: crash_context.tls,
+            process,
+            breadcrumbs,
+            // Set once the dump options have been retrieved from the handler
+            #[cfg(feature = "minidump-writer")]
+            mode: crate::DumpMode::Normal,
+            // The crashing process is still alive, waiting for the dump to be
+            // written, so the chain can be read out of its memory
+            #[cfg(target_os = "windows")]
+            exception_record: match crash_context
+                .read_exception_records(crash_context::MAX_EXCEPTION_RECORD_DEPTH)
+            {
+                Ok(mut records) => records.pop(),
+                Err(err) => {
+                    crate::dump_warnings::warn(
+                        crate::DumpWarningKind::ExceptionRecord,
+                        format!("failed to read exception records: {err}"),
+                    );
+                    None
+                }
+            },
+        };
+
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "minidump-writer")] {
+                Self::write_minidump(
+                    crash_context,
+                    metadata,
+                    _embed_process_info,
+                    handler,
+                    capture,
+                    _shared,
+                )
+            } else {
+                Ok((
+                    handler.on_crash_context(&crash_context, &metadata),
+                    CrashOutcome::Unknown,
+                ))
+            }
+        }
+    }
+
+    #[cfg(feature = "minidump-writer")]
+    fn write_minidump(
+        crash_context: crash_context::CrashContext,
+        mut metadata: crate::DumpMetadata,
+        embed_process_info: bool,
+        handler: &dyn crate::ServerHandler,
+        capture: crate::dump_warnings::Capture,
+        shared: &Shared,
+    ) -> Result<(LoopAction, CrashOutcome), Error> {
+        let options = handler.dump_options(&crash_context);
+        metadata.mode = options.mode;
+
+        let (mut minidump_file, minidump_path) = handler.create_minidump_file()?;
+
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                let pid = crash_context.pid;
+                let mut writer =
+                    minidump_writer::minidump_writer::MinidumpWriter::new(pid, crash_context.tid);
+
+                if let Some(limit) = options.size_limit {
+                    writer.set_minidump_size_limit(limit);
+                }
+
+                // The minidump is still useful without the extra mappings
+                match options.app_memory(crash_context.pid as u32) {
+                    Ok(app_memory) => {
+                        writer.set_app_memory(app_memory);
+                    }
+                    Err(err) => {
+                        crate::dump_warnings::warn(
+                            crate::DumpWarningKind::FullMemory,
+                            format!("failed to read the mappings to write to the minidump: {err}"),
+                        );
+                        metadata.mode = crate::DumpMode::Normal;
+                    }
+                }
+
+                writer.set_crash_context(minidump_writer::crash_context::CrashContext { inner: crash_context });
             } else if #[cfg(target_os = "windows")] {
                 // SAFETY: Unfortunately this is a bit dangerous since we are relying on the crashing process
                 // to still be alive and still have the interior pointers in the crash context still at the
@@ -470,80 +1847,1372 @@ impl Server {
                 let mut writer = minidump_writer::minidump_writer::MinidumpWriter::with_crash_context(crash_context);
             }
         }
-
-        #[cfg(not(target_os = "windows"))]
-        let result = writer.dump(&mut minidump_file);
-
-        // Notify the user handler about the minidump, even if we failed to write it
-        Ok(handler.on_minidump_created(
-            result
-                .map(|_contents| crate::MinidumpBinary {
-                    file: minidump_file,
-                    path: minidump_path,
-                    #[cfg(target_os = "windows")]
-                    contents: None,
-                    #[cfg(not(target_os = "windows"))]
-                    contents: Some(_contents),
-                })
-                .map_err(crate::Error::from),
-        ))
+
+        cfg_if::cfg_if! {
+            if #[cfg(target_os = "windows")] {
+                // MiniDumpWriteDump writes directly to the file, so the
+                // contents are only in memory if they are read back
+                let mut result = result.map(|_| {
+                    if options.contents != crate::ContentsMode::Always {
+                        return None;
+                    }
+
+                    match read_contents(&mut minidump_file) {
+                        Ok(contents) => Some(contents),
+                        Err(err) => {
+                            crate::dump_warnings::warn(
+                                crate::DumpWarningKind::File,
+                                format!("failed to read back the minidump: {err}"),
+                            );
+                            None
+                        }
+                    }
+                });
+            } else {
+                let mut result = writer.dump(&mut minidump_file).map(Some);
+            }
+        }
+
+        if let Ok(contents) = &mut result {
+            let mut streams = Vec::new();
+
+            if let (Some(process), true) = (&metadata.process, embed_process_info) {
+                streams.extend(process.streams());
+            }
+
+            if !metadata.breadcrumbs.is_empty() {
+                streams.push((
+                    crate::BREADCRUMBS_STREAM_TYPE,
+                    crate::Breadcrumb::to_stream(&metadata.breadcrumbs),
+                ));
+            }
+
+            // The minidump is still usable without them, so this isn't
+            // reported as a failure to write it
+            if !streams.is_empty() {
+                if let Err(err) = crate::process_info::embed_streams(
+                    &mut minidump_file,
+                    contents.as_mut(),
+                    &streams,
+                ) {
+                    crate::dump_warnings::warn(
+                        crate::DumpWarningKind::Streams,
+                        format!(
+                            "failed to add the process info and breadcrumbs to the minidump: {err}"
+                        ),
+                    );
+                }
+            }
+        }
+
+        // The writes to the file may still be buffered by the OS, which
+        // handlers that eg. map the file, rather than using the handle, have
+        // observed as a truncated minidump
+        #[cfg(target_os = "windows")]
+        if result.is_ok() {
+            use std::io::Seek;
+
+            // `sync_all` is `FlushFileBuffers` on Windows
+            if let Err(err) = minidump_file
+                .sync_all()
+                .and_then(|()| minidump_file.rewind())
+            {
+                crate::dump_warnings::warn(
+                    crate::DumpWarningKind::File,
+                    format!("failed to flush the minidump file: {err}"),
+                );
+            }
+        }
+
+        let dump_warnings = capture.finish();
+        if result.is_ok() {
+            shared
+                .dump_warnings
+                .fetch_add(dump_warnings.len() as u64, Ordering::Relaxed);
+        }
+
+        let outcome = match &result {
+            Ok(_) => CrashOutcome::Written(minidump_path.clone()),
+            // The writer's errors don't carry the errno, so the cause is
+            // checked separately, as it is a misconfiguration of the client
+            // rather than a transient failure
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Err(_err) if is_ptrace_denied(pid) => {
+                log::error!(
+                    "not allowed to ptrace client process {pid}, it must be dumpable, and allow \
+                    this process as its ptracer, until the minidump has been written"
+                );
+
+                CrashOutcome::Failed {
+                    category: crate::DumpFailureCategory::PtraceDenied,
+                    code: Some(libc::EPERM),
+                }
+            }
+            Err(err) => CrashOutcome::Failed {
+                category: crate::DumpFailureCategory::Write,
+                code: crate::errors::os_error_code(err),
+            },
+        };
+
+        // Notify the user handler about the minidump, even if we failed to write it
+        let action = handler.on_minidump_created(
+            result
+                .map(|contents| crate::MinidumpBinary {
+                    file: minidump_file,
+                    path: minidump_path,
+                    contents,
+                    metadata,
+                    dump_warnings,
+                })
+                .map_err(crate::Error::from),
+        );
+
+        Ok((action, outcome))
+    }
+
+    /// Receives every crash context sent to the mach port, queueing each of
+    /// them to have a minidump written
+    #[cfg(target_os = "macos")]
+    fn check_mach_port(
+        &mut self,
+        poll: &Poller,
+        clients: &mut HashMap<usize, ClientConn>,
+        writer: &DumpWriter,
+    ) -> Result<(), Error> {
+        // We use a really short timeout for receiving on the mach port since we check it
+        // frequently rather than spawning a separate thread and blocking. The
+        // port is drained, as several clients may have crashed at once, and
+        // the kernel only queues a handful of messages per port
+        while let Some(rcc) = self
+            .port
+            .try_recv_crash_context(Some(Duration::from_millis(1)))?
+        {
+            // Try to find a client connection that matches the port sender
+            let Some(key) = clients
+                .values()
+                .find(|cc| cc.pid == Some(rcc.pid))
+                .map(|cc| cc.key)
+            else {
+                // The process may have crashed before it sent its pid
+                log::error!(
+                    "ignoring crash context from process {} which isn't a client",
+                    rcc.pid
+                );
+                continue;
+            };
+
+            let process = self
+                .options
+                .process_info(&rcc.crash_context, clients[&key].process());
+            let breadcrumbs = clients[&key].breadcrumbs_for(&rcc.crash_context);
+
+            // The client keeps running after an on-demand dump, so it stays
+            // connected
+            if !super::is_on_demand(&rcc.crash_context) {
+                let cc = clients.remove(&key).unwrap();
+
+                if let Err(err) = poll.delete(&cc.socket) {
+                    log::error!("failed to deregister socket: {err}");
+                }
+            }
+
+            writer.queue(PendingCrash {
+                crash_context: rcc.crash_context,
+                process,
+                breadcrumbs,
+                ack: CrashAck::Port(rcc.acker),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Waits for events on the poller, returning the keys that are ready.
+///
+/// A client may have several events in the same batch, but is only read from
+/// once per iteration, which also means that a client being disconnected
+/// can't cause a later event for it to be dropped, or attributed to another
+/// client
+fn wait(
+    poll: &Poller,
+    events: &mut polling::Events,
+    timeout: Duration,
+) -> Result<Vec<usize>, Error> {
+    events.clear();
+    let deadline = Instant::now() + timeout;
+    let mut remaining = Some(timeout);
+    while let Some(timeout) = remaining {
+        match poll.wait(events, Some(timeout)) {
+            Ok(_) => {
+                break;
+            }
+            Err(e) => {
+                if matches!(e.kind(), ErrorKind::Interrupted) {
+                    remaining = deadline.checked_duration_since(Instant::now());
+                } else {
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    let mut ready: Vec<usize> = events.iter().map(|event| event.key).collect();
+    ready.sort_unstable();
+    ready.dedup();
+    Ok(ready)
+}
+
+/// The keys of one of the servers sharing a [`Poller`], which are interleaved
+/// with those of the other servers so that an event is never attributed to
+/// the wrong one
+#[derive(Copy, Clone)]
+struct Slot {
+    index: usize,
+    count: usize,
+}
+
+impl Slot {
+    /// The slot of a server that has the poller to itself, whose keys are
+    /// registered as is
+    const SINGLE: Self = Self { index: 0, count: 1 };
+
+    /// The key a source of the server is registered with
+    #[inline]
+    fn key(self, key: usize) -> usize {
+        key * self.count + self.index
+    }
+
+    /// The key after `key` that the server can use for a client, wrapping
+    /// around rather than registering a key that would overflow
+    #[inline]
+    fn next_key(self, key: usize) -> usize {
+        key.wrapping_add(1) % (usize::MAX / self.count)
+    }
+}
+
+/// The state of a [`Server`] while its loop is running, either by itself, see
+/// [`Server::run_with_options`], or alongside other servers, see
+/// [`ServerSet::run`]
+struct Running<'s> {
+    server: &'s mut Server,
+    poll: &'s Poller,
+    slot: Slot,
+    /// Returned to the server once the loop exits, so that the socket path
+    /// isn't left behind without anything listening on it, which would let
+    /// another server remove it as stale while this one still owns it
+    listener: Option<Listener>,
+    /// The connected clients, by their key, before it is mapped to the slot
+    clients: HashMap<usize, ClientConn>,
+    /// The last key handed out by [`Self::next_key`]
+    last_key: usize,
+    handler: Arc<dyn crate::ServerHandler>,
+    writer: DumpWriter,
+}
+
+impl<'s> Running<'s> {
+    #[allow(unsafe_code)]
+    fn start(
+        server: &'s mut Server,
+        handler: Box<dyn crate::ServerHandler>,
+        poll: &'s Poller,
+        slot: Slot,
+    ) -> Result<Self, Error> {
+        let handler: Arc<dyn crate::ServerHandler> = handler.into();
+        let writer = DumpWriter::spawn(handler.clone(), server.shared.clone(), {
+            cfg_if::cfg_if! {
+                if #[cfg(feature = "minidump-writer")] {
+                    server.options.embed_process_info
+                } else {
+                    false
+                }
+            }
+        })?;
+
+        let listener = server.listener.take();
+        let running = Self {
+            server,
+            poll,
+            slot,
+            listener,
+            clients: HashMap::new(),
+            last_key: 0,
+            handler,
+            writer,
+        };
+
+        // SAFETY: We ensure we delete the listener during drop
+        unsafe {
+            running
+                .poll
+                .add(running.listener(), Event::readable(slot.key(0)))?;
+        }
+
+        Ok(running)
+    }
+
+    #[inline]
+    fn listener(&self) -> &Listener {
+        self.listener
+            .as_ref()
+            .expect("the listener is only returned to the server on drop")
+    }
+
+    /// Reports the breadcrumbs of a client that is disconnected without having
+    /// crashed, if it created any
+    fn report_breadcrumbs(&self, cc: &ClientConn, reason: DisconnectReason) {
+        if let Some(breadcrumbs) = &cc.breadcrumbs {
+            self.handler
+                .on_client_breadcrumbs(ClientId(cc.key), reason, breadcrumbs.snapshot());
+        }
+    }
+
+    #[inline]
+    #[allow(unsafe_code)]
+    fn add(&self, src: impl polling::AsRawSource, key: usize) -> std::io::Result<()> {
+        // SAFETY: We ensure we delete all sources we add before dropping the poll
+        unsafe { self.poll.add(src, Event::readable(self.slot.key(key))) }
+    }
+
+    /// Allocates the key for a new client, skipping the listener's key,
+    /// as well as the keys of clients that are still connected should
+    /// the keys ever wrap around
+    fn next_key(&mut self) -> usize {
+        loop {
+            self.last_key = self.slot.next_key(self.last_key);
+
+            if self.last_key != 0 && !self.clients.contains_key(&self.last_key) {
+                return self.last_key;
+            }
+        }
     }
 
-    #[cfg(target_os = "macos")]
-    fn check_mach_port(
+    /// Handles the events for the server's `ready` keys, and then sends the
+    /// messages queued by its handles, and reaps its stale clients
+    fn process(
         &mut self,
-        poll: &Poller,
-        clients: &mut Vec<ClientConn>,
-        handler: &dyn crate::ServerHandler,
+        ready: &[usize],
+        shutdown: &std::sync::atomic::AtomicBool,
     ) -> Result<LoopAction, Error> {
-        // We use a really short timeout for receiving on the mach port since we check it
-        // frequently rather than spawning a separate thread and blocking
-        if let Some(mut rcc) = self
-            .port
-            .try_recv_crash_context(Some(Duration::from_millis(1)))?
-        {
-            // Try to find a client connection that matches the port sender
-            let pos = clients
-                .iter()
-                .position(|cc| cc.pid == Some(rcc.pid))
-                .ok_or(Error::UnknownClientPid)?;
-            let cc = clients.swap_remove(pos);
+        #[cfg(target_os = "macos")]
+        self.server
+            .check_mach_port(self.poll, &mut self.clients, &self.writer)?;
+
+        // Clients are only removed once every event has been processed
+        let mut disconnects = Vec::new();
+
+        for &key in ready {
+            if key == 0 {
+                // Accept every connection that is waiting, rather than one
+                // per iteration, so that a burst of connections doesn't
+                // wait on the poll interval
+                loop {
+                    let accepted = match self.listener().accept_unix_addr() {
+                        Ok((accepted, _addr)) => accepted,
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                        // The peer gave up before the connection was
+                        // accepted, which doesn't affect the others
+                        Err(err) if err.kind() == ErrorKind::ConnectionAborted => {
+                            log::debug!("connection aborted before it was accepted");
+                            self.server
+                                .shared
+                                .aborted_connections
+                                .fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        Err(err) => {
+                            log::error!("failed to accept socket connection: {err}");
+                            break;
+                        }
+                    };
 
-            let action = match Self::handle_crash_request(rcc.crash_context, handler) {
-                Err(err) => {
-                    log::error!("failed to capture minidump: {err}");
-                    LoopAction::Continue
+                    let peer = match peer_credentials(&accepted) {
+                        Ok(peer) if self.server.options.allows(&peer) => peer,
+                        res => {
+                            match res {
+                                Ok(peer) => log::warn!("rejected connection from {peer:?}"),
+                                Err(err) => log::warn!(
+                                    "rejected connection with unknown credentials: {err}"
+                                ),
+                            }
+
+                            self.server
+                                .shared
+                                .rejected_connections
+                                .fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                    };
+
+                    if matches!(self.server.options.max_clients, Some(max) if self.clients.len() >= max)
+                    {
+                        log::warn!("rejected connection as the server is full");
+
+                        self.server
+                            .shared
+                            .rejected_connections
+                            .fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+
+                    let key = self.next_key();
+
+                    if let Err(err) = self.add(&accepted, key) {
+                        log::error!("failed to register connection {key}: {err}");
+                        continue;
+                    }
+
+                    log::debug!("accepted connection {key}");
+                    self.clients.insert(
+                        key,
+                        ClientConn {
+                            socket: accepted,
+                            key,
+                            last_update: Instant::now(),
+                            #[cfg(target_os = "macos")]
+                            pid: None,
+                            #[cfg(target_os = "windows")]
+                            peer_pid: peer.pid,
+                            protocol_version: 0,
+                            sequence: Sequence::default(),
+                            #[cfg(any(target_os = "windows", target_os = "macos"))]
+                            process: None,
+                            breadcrumbs: None,
+                        },
+                    );
+
+                    if self
+                        .handler
+                        .on_client_connected(ClientId(key), peer, self.clients.len())
+                        == LoopAction::Exit
+                    {
+                        log::debug!("on_client_connected exited message loop");
+                        return Ok(LoopAction::Exit);
+                    }
                 }
-                Ok(action) => {
-                    log::info!("captured minidump");
-                    action
+
+                // We need to reregister insterest every time
+                self.poll
+                    .modify(self.listener(), Event::readable(self.slot.key(0)))?;
+            } else if let Some(cc) = self.clients.get_mut(&key) {
+                cc.last_update = Instant::now();
+                let client = ClientId(key);
+
+                let disconnect = match cc.recv(
+                    self.handler.as_ref(),
+                    self.server.options.max_message_size,
+                    &self.server.shared,
+                ) {
+                    #[cfg(target_os = "windows")]
+                    Ok(Some((super::CRASH, buffer)))
+                        if !self
+                            .server
+                            .options
+                            .allows_crash_request(cc.peer_pid, &buffer) =>
+                    {
+                        log::warn!("client {key} requested a minidump of another process");
+                        Some(Disconnect::new(
+                            key,
+                            DisconnectReason::Errored(ErrorKind::PermissionDenied),
+                        ))
+                    }
+                    Ok(Some((super::CRASH, buffer))) => {
+                        cfg_if::cfg_if! {
+                            if #[cfg(target_os = "macos")] {
+                                use scroll::Pread;
+                                let pid: u32 = match buffer.pread(0) {
+                                    Ok(pid) => pid,
+                                    Err(err) => {
+                                        log::error!("client {key} sent an invalid crash request: {err}");
+                                        disconnects.push(Disconnect::new(
+                                            key,
+                                            DisconnectReason::Errored(ErrorKind::InvalidData),
+                                        ));
+                                        continue;
+                                    }
+                                };
+                                cc.pid = Some(pid);
+
+                                if let Err(err) = cc.socket.send(&[1]) {
+                                    log::error!("failed to send ack: {err}");
+                                }
+
+                                None
+                            } else {
+                                let crash_ctx = match cc.crash_request(&buffer) {
+                                    Ok(crash_ctx) => crash_ctx,
+                                    Err(err) => {
+                                        log::error!("client {key} sent an invalid crash request: {err}");
+                                        disconnects.push(Disconnect::new(
+                                            key,
+                                            DisconnectReason::Errored(ErrorKind::InvalidData),
+                                        ));
+                                        continue;
+                                    }
+                                };
+
+                                let retry_after = match self.handler.on_crash_request(client, &crash_ctx, self.writer.pending()) {
+                                    CrashRequestAction::Dump => None,
+                                    CrashRequestAction::Defer { retry_after } => {
+                                        if cc.protocol_version >= 1 {
+                                            Some(retry_after)
+                                        } else {
+                                            log::warn!("client {key} is too old to defer its crash request");
+                                            None
+                                        }
+                                    }
+                                };
+
+                                if let Some(retry_after) = retry_after {
+                                    log::debug!("deferring crash request from client {key}");
+
+                                    // The client stays connected, and
+                                    // retries the request after the delay
+                                    let busy = CrashOutcome::Busy(retry_after).ack(&mut cc.sequence);
+                                    if let Err(err) = cc.socket.send(&busy) {
+                                        log::error!("failed to send busy ack: {err}");
+                                        Some(Disconnect::new(key, DisconnectReason::Errored(err.kind())))
+                                    } else {
+                                        None
+                                    }
+                                } else if super::is_on_demand(&crash_ctx) {
+                                    log::debug!("client {key} requested an on-demand dump");
+
+                                    // The client keeps running, so it stays
+                                    // connected, and is acked by the loop
+                                    if cc.protocol_version >= 1 {
+                                        if let Err(err) = cc.send(super::CRASH_QUEUED, &[]) {
+                                            log::error!("failed to send queued ack: {err}");
+                                        }
+                                    }
+
+                                    self.writer.queue(PendingCrash {
+                                        process: self.server.options.process_info(&crash_ctx, cc.process()),
+                                        breadcrumbs: cc.breadcrumbs_for(&crash_ctx),
+                                        crash_context: crash_ctx,
+                                        ack: CrashAck::Connected(client, self.server.shared.clone()),
+                                    });
+
+                                    None
+                                } else {
+                                    let process = self.server.options.process_info(&crash_ctx, cc.process());
+
+                                    // The request is queued once the socket
+                                    // has been deregistered
+                                    Some(Disconnect {
+                                        key,
+                                        reason: DisconnectReason::Crashed,
+                                        crash: Some((crash_ctx, process)),
+                                    })
+                                }
+                            }
+                        }
+                    }
+                    Ok(Some((super::PING, _buffer))) => {
+                        // The first ping from a client that understands
+                        // sequence numbers is replied to with our version,
+                        // after which both ends sequence their messages
+                        let res = if cc.protocol_version >= 3 && !cc.sequence.sequenced {
+                            let res = cc.send(super::PONG, &super::PROTOCOL_VERSION.to_le_bytes());
+                            cc.sequence.start();
+                            res
+                        } else {
+                            cc.send(super::PONG, &[])
+                        };
+
+                        if let Err(err) = res {
+                            log::error!("failed to send PONG: {err}");
+                            Some(Disconnect::new(key, DisconnectReason::Errored(err.kind())))
+                        } else {
+                            None
+                        }
+                    }
+                    // Only clients that have already sent their `HELLO` send
+                    // one without a payload
+                    Ok(Some((super::SHUTDOWN, buffer)))
+                        if buffer.is_empty() && cc.protocol_version >= 5 =>
+                    {
+                        if self.server.options.accept_shutdown_requests {
+                            log::debug!("client {key} requested shutdown");
+                            shutdown.store(true, std::sync::atomic::Ordering::Relaxed);
+                        } else {
+                            log::warn!("ignoring shutdown request from client {key}");
+                        }
+
+                        None
+                    }
+                    Ok(Some((super::HELLO, buffer))) => {
+                        if let Some(version) = buffer.get(..4) {
+                            cc.protocol_version = u32::from_le_bytes(version.try_into().unwrap());
+                        }
+
+                        // Clients of protocol version 2 or later follow
+                        // the version with their command line and environment
+                        #[cfg(any(target_os = "windows", target_os = "macos"))]
+                        if self.server.options.capture_process_info {
+                            cc.process = buffer.get(4..).and_then(|payload| {
+                                ProcessInfo::from_payload(
+                                    payload,
+                                    &self.server.options.allowed_env_vars,
+                                )
+                            });
+                        }
+
+                        None
+                    }
+                    Ok(Some((super::BREADCRUMBS, buffer))) => {
+                        cfg_if::cfg_if! {
+                            if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                                let reader = crate::breadcrumbs::BreadcrumbReader::open(&buffer, cc.socket.take_fd());
+                            } else if #[cfg(target_os = "macos")] {
+                                let reader = cc.socket.peer_uid().and_then(|uid| {
+                                    crate::breadcrumbs::BreadcrumbReader::open(&buffer, uid)
+                                });
+                            } else {
+                                let reader = crate::breadcrumbs::BreadcrumbReader::open(&buffer);
+                            }
+                        }
+
+                        // The client can still crash, or send messages, the
+                        // breadcrumbs just aren't reported
+                        match reader {
+                            Ok(reader) => cc.breadcrumbs = Some(reader),
+                            Err(err) => {
+                                log::warn!("failed to map the breadcrumbs of client {key}: {err}");
+                            }
+                        }
+
+                        None
+                    }
+                    Ok(Some((kind, buffer))) if kind >= super::USER => {
+                        self.handler.on_client_message(
+                            client,
+                            kind - super::USER, /* give the user back the original code they specified */
+                            buffer,
+                        );
+
+                        // We only send acks for crash dump requests
+                        // if let Err(e) = clients[pos].socket.send(&[1]) {
+                        //     log::error!("failed to send ack: {}", e);
+                        // }
+
+                        None
+                    }
+                    Ok(Some((kind, _buffer))) => {
+                        log::error!("client {key} sent a message of internal kind {kind}");
+                        Some(Disconnect::new(
+                            key,
+                            DisconnectReason::Errored(ErrorKind::InvalidData),
+                        ))
+                    }
+                    Ok(None) => {
+                        log::debug!("client closed socket {key}");
+                        Some(Disconnect::new(key, DisconnectReason::Closed))
+                    }
+                    Err(err) => {
+                        log::error!("failed to receive message from client {key}: {err}");
+
+                        let kind = match &err {
+                            Error::Io(err) => err.kind(),
+                            // Protocol violations
+                            _ => ErrorKind::InvalidData,
+                        };
+
+                        Some(Disconnect::new(key, DisconnectReason::Errored(kind)))
+                    }
+                };
+
+                if let Some(disconnect) = disconnect {
+                    disconnects.push(disconnect);
+                } else {
+                    if let Err(err) = self
+                        .poll
+                        .modify(&cc.socket, Event::readable(self.slot.key(key)))
+                    {
+                        log::error!("failed to reregister client {key}: {err}");
+                        disconnects
+                            .push(Disconnect::new(key, DisconnectReason::Errored(err.kind())));
+                    }
                 }
+            } else {
+                log::debug!("ignoring event for unknown client {key}");
+            }
+        }
+
+        for disconnect in disconnects {
+            let cc = match self.clients.remove(&disconnect.key) {
+                Some(cc) => cc,
+                None => continue,
             };
 
-            if let Err(err) = rcc.acker.send_ack(1, Some(Duration::from_secs(2))) {
-                log::error!("failed to send ack: {err}");
+            if let Err(err) = self.poll.delete(&cc.socket) {
+                log::error!("failed to deregister socket: {err}");
+            }
+
+            if disconnect.reason != DisconnectReason::Crashed {
+                self.report_breadcrumbs(&cc, disconnect.reason);
+            }
+
+            #[cfg(not(target_os = "macos"))]
+            if let Some((crash_context, process)) = disconnect.crash {
+                let breadcrumbs = cc.breadcrumbs_for(&crash_context);
+                let mut sequence = cc.sequence;
+                if cc.protocol_version >= 1 {
+                    if let Err(err) = cc.socket.send(&sequence.message(super::CRASH_QUEUED, &[])) {
+                        log::error!("failed to send queued ack: {err}");
+                    }
+                }
+
+                self.writer.queue(PendingCrash {
+                    crash_context,
+                    breadcrumbs,
+                    process,
+                    ack: CrashAck::Socket(cc.socket, sequence),
+                });
+            }
+
+            if self.handler.on_client_disconnected(
+                ClientId(disconnect.key),
+                disconnect.reason,
+                self.clients.len(),
+            ) == LoopAction::Exit
+            {
+                log::debug!("on_client_disconnected exited message loop");
+                return Ok(LoopAction::Exit);
+            }
+        }
+
+        self.server.send_queued(&mut self.clients);
+
+        if let Some(st) = self.server.options.stale_timeout {
+            // Reap any connections that haven't sent a message in the period
+            // specified by the user
+            let mut stale: Vec<_> = self
+                .clients
+                .values()
+                .filter_map(|cc| {
+                    let elapsed = cc.last_update.elapsed();
+                    (elapsed >= st).then_some((cc.key, elapsed))
+                })
+                .collect();
+            stale.sort_unstable();
+
+            for (key, elapsed) in stale {
+                log::debug!("dropping stale connection {elapsed:?}");
+                let cc = self.clients.remove(&key).unwrap();
+                if let Err(err) = self.poll.delete(&cc.socket) {
+                    log::error!("failed to deregister timed-out socket: {err}");
+                }
+
+                self.report_breadcrumbs(&cc, DisconnectReason::Stale(elapsed));
+
+                if self.handler.on_client_disconnected(
+                    ClientId(cc.key),
+                    DisconnectReason::Stale(elapsed),
+                    self.clients.len(),
+                ) == LoopAction::Exit
+                {
+                    log::debug!("on_client_disconnected exited message loop");
+                    return Ok(LoopAction::Exit);
+                }
             }
+        }
+
+        Ok(LoopAction::Continue)
+    }
+}
 
-            if let Err(err) = poll.delete(&cc.socket) {
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        // The minidumps that are already queued are written before their
+        // clients are disconnected
+        self.writer.finish();
+
+        // Ensures handles don't report clients once the loop has exited
+        self.server.shared.clients.lock().clear();
+
+        for client in std::mem::take(&mut self.clients).into_values() {
+            if let Err(err) = self.poll.delete(client.socket) {
                 log::error!("failed to deregister socket: {err}");
             }
+        }
+
+        if let Some(listener) = self.listener.take() {
+            if let Err(err) = self.poll.delete(&listener) {
+                log::error!("failed to deregister listener: {err}");
+            }
 
-            Ok(action)
-        } else {
-            Ok(LoopAction::Continue)
+            self.server.listener = Some(listener);
+        }
+    }
+}
+
+/// Runs several [`Server`]s from a single loop, eg. in a monitor process that
+/// writes the minidumps of several applications, each with its own socket
+/// name and handler.
+///
+/// The servers share a single [`Poller`], and are woken up at the shortest
+/// [`ServerOptions::poll_interval`] of any of them, rather than each needing
+/// its own thread and loop. Otherwise each server behaves just as if it was
+/// run by [`Server::run_with_options`] with the options it was created with,
+/// including having its own thread to write its minidumps on.
+///
+/// On macOS, each server still creates its own mach port, the names of which
+/// must be unique within the process, see `Error::PortNameInUse`.
+#[derive(Default)]
+pub struct ServerSet {
+    servers: Vec<Server>,
+    handlers: Vec<Box<dyn crate::ServerHandler>>,
+}
+
+impl ServerSet {
+    /// Creates an empty set
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a server, and the handler for it, to the set. Use
+    /// [`Server::handle`] beforehand to get a handle for it.
+    #[inline]
+    pub fn add(&mut self, server: Server, handler: Box<dyn crate::ServerHandler>) -> &mut Self {
+        self.servers.push(server);
+        self.handlers.push(handler);
+        self
+    }
+
+    /// The number of servers in the set
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    /// Whether the set has no servers
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// Runs the loop for every server in the set, until `shutdown` is set, or
+    /// until every server has stopped.
+    ///
+    /// A server stops when its handler returns [`LoopAction::Exit`], after
+    /// which its clients are disconnected, but the remaining servers keep
+    /// running. A shutdown request from a client of a server that accepts
+    /// them, see [`ServerOptions::accept_shutdown_requests`], stops all of
+    /// them. The servers, and their socket paths, are dropped once this
+    /// returns.
+    ///
+    /// # Errors
+    ///
+    /// This method uses basic I/O event notification via [`polling`] which
+    /// can fail for a number of different reasons, and fails as soon as any
+    /// of the servers fails
+    pub fn run(self, shutdown: &std::sync::atomic::AtomicBool) -> Result<(), Error> {
+        let Self {
+            mut servers,
+            handlers,
+        } = self;
+
+        let poll_interval = match servers.iter().map(|s| s.options.poll_interval).min() {
+            Some(interval) => interval,
+            None => return Ok(()),
+        };
+
+        let poll = Poller::new()?;
+        let mut events = polling::Events::new();
+        let count = servers.len();
+
+        let mut running = servers
+            .iter_mut()
+            .zip(handlers)
+            .enumerate()
+            .map(|(index, (server, handler))| {
+                Running::start(server, handler, &poll, Slot { index, count }).map(Some)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        loop {
+            if shutdown.load(std::sync::atomic::Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            for server in &mut running {
+                if matches!(server, Some(s) if s.writer.should_exit()) {
+                    *server = None;
+                }
+            }
+
+            if running.iter().all(Option::is_none) {
+                return Ok(());
+            }
+
+            let mut ready = vec![Vec::new(); count];
+            for key in wait(&poll, &mut events, poll_interval)? {
+                ready[key % count].push(key / count);
+            }
+
+            for (server, ready) in running.iter_mut().zip(&ready) {
+                if let Some(s) = server {
+                    if s.process(ready, shutdown)? == LoopAction::Exit {
+                        *server = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Reads back the minidump written to the file
+#[cfg(all(target_os = "windows", feature = "minidump-writer"))]
+fn read_contents(file: &mut std::fs::File) -> std::io::Result<Vec<u8>> {
+    use std::io::{Read, Seek};
+
+    let mut contents = Vec::new();
+    file.rewind()?;
+    // There is only the handle, not a path that could be read instead
+    #[allow(clippy::verbose_file_reads)]
+    file.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+/// Sets the backlog of a listener that was bound by `uds`, or std, both of
+/// which use their own, by listening again, which only updates the backlog of
+/// a socket that is already listening
+#[cfg(unix)]
+#[allow(unsafe_code)]
+fn set_backlog(listener: &impl std::os::fd::AsRawFd, backlog: u32) -> std::io::Result<()> {
+    // SAFETY: syscall
+    if unsafe { libc::listen(listener.as_raw_fd(), backlog.min(i32::MAX as u32) as i32) } != 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Binds a listener, removing the socket path, if any, and binding again if
+/// the bind fails because the path was left behind by a server that is no
+/// longer running.
+///
+/// Whether the path is stale is determined by connecting to it, rather than
+/// by checking whether it exists, as that appears to fail on Windows even if
+/// it does. A path that can't be removed, or that a live server, or anything
+/// else we can't connect to, is listening on, is left as is, and the original
+/// error is returned.
+fn bind_path<L, S>(
+    path: Option<&std::path::Path>,
+    bind: impl Fn() -> std::io::Result<L>,
+    probe: impl FnOnce() -> std::io::Result<S>,
+) -> std::io::Result<L> {
+    let err = match bind() {
+        Err(err) if err.kind() == ErrorKind::AddrInUse => err,
+        res => return res,
+    };
+
+    let path = if let Some(path) = path {
+        path
+    } else {
+        return Err(err);
+    };
+
+    match probe() {
+        Err(probe_err)
+            if matches!(
+                probe_err.kind(),
+                ErrorKind::ConnectionRefused | ErrorKind::NotFound
+            ) => {}
+        _ => return Err(err),
+    }
+
+    log::debug!("removing stale socket path '{}'", path.display());
+    match std::fs::remove_file(path) {
+        Err(rm_err) if rm_err.kind() != ErrorKind::NotFound => {
+            log::warn!(
+                "failed to remove stale socket path '{}': {rm_err}",
+                path.display()
+            );
+            Err(err)
         }
+        _ => bind(),
     }
 }
 
 impl Drop for Server {
     fn drop(&mut self) {
+        // The path is removed while the listener is still open, otherwise
+        // another server could consider it stale in the meantime, and bind a
+        // new socket to it, which we would then remove
+        if let Some(path) = self.socket_path.take() {
+            if self.options.cleanup_socket_on_drop {
+                // Note we don't check for the existence of the path since there
+                // appears to be a bug on MacOS and Windows, or at least an oversight
+                // in std, where checking the existence of the path always fails
+                let _res = std::fs::remove_file(path);
+            }
+        }
+
         let _ = self.listener.take();
+    }
+}
 
-        if let Some(path) = self.socket_path.take() {
-            // Note we don't check for the existence of the path since there
-            // appears to be a bug on MacOS and Windows, or at least an oversight
-            // in std, where checking the existence of the path always fails
-            let _res = std::fs::remove_file(path);
+#[cfg(test)]
+mod test {
+    use super::{read_message, Header, IoSliceMut, MessageSource, Sequence};
+    use crate::{
+        ipc::{BREADCRUMBS, CRASH, PING, USER},
+        Error, ProtocolViolation,
+    };
+    use proptest::prelude::*;
+    use std::cell::Cell;
+
+    /// An in-memory connection, where each read stops at the next split point,
+    /// emulating data arriving in pieces. By default it behaves like a stream
+    /// socket, or like a seqpacket socket if created with [`Self::seqpacket`],
+    /// in which case each piece is a message of its own
+    struct MockConnection<const STREAM: bool = true> {
+        data: Vec<u8>,
+        splits: Vec<usize>,
+        pos: Cell<usize>,
+        /// The largest buffer the payload was ever read into
+        largest_read: Cell<usize>,
+    }
+
+    impl MockConnection {
+        fn new(data: Vec<u8>, splits: Vec<usize>) -> Self {
+            Self::with_splits(data, splits)
+        }
+    }
+
+    impl MockConnection<false> {
+        fn seqpacket(data: Vec<u8>, splits: Vec<usize>) -> Self {
+            Self::with_splits(data, splits)
+        }
+    }
+
+    impl<const STREAM: bool> MockConnection<STREAM> {
+        fn with_splits(data: Vec<u8>, mut splits: Vec<usize>) -> Self {
+            splits.retain(|&split| split > 0 && split < data.len());
+            splits.push(data.len());
+            splits.sort_unstable();
+
+            Self {
+                data,
+                splits,
+                pos: Cell::new(0),
+                largest_read: Cell::new(0),
+            }
+        }
+
+        /// The bytes available to a single read
+        fn available(&self) -> &[u8] {
+            let pos = self.pos.get();
+            let end = self
+                .splits
+                .iter()
+                .copied()
+                .find(|&split| split > pos)
+                .unwrap_or(pos);
+            &self.data[pos..end]
+        }
+    }
+
+    impl<const STREAM: bool> MessageSource for MockConnection<STREAM> {
+        const STREAM: bool = STREAM;
+
+        fn peek(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let available = self.available();
+            let len = available.len().min(buf.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            Ok(len)
+        }
+
+        fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.largest_read
+                .set(self.largest_read.get().max(buf.len()));
+
+            let len = self.peek(buf)?;
+            self.pos.set(self.pos.get() + len);
+            Ok(len)
+        }
+
+        fn recv_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> std::io::Result<(usize, bool)> {
+            let available = self.available();
+
+            let mut total = 0;
+            for buf in bufs.iter_mut() {
+                self.largest_read
+                    .set(self.largest_read.get().max(buf.len()));
+
+                let len = (available.len() - total).min(buf.len());
+                buf[..len].copy_from_slice(&available[total..total + len]);
+                total += len;
+            }
+
+            self.pos.set(self.pos.get() + total);
+            Ok((total, false))
+        }
+    }
+
+    fn message(kind: u32, payload: &[u8]) -> Vec<u8> {
+        Sequence::default().message(kind, payload)
+    }
+
+    /// Creates a message with the specified sequence number
+    fn sequenced_message(kind: u32, seq: u32, payload: &[u8]) -> Vec<u8> {
+        let mut sequence = Sequence {
+            send: seq,
+            ..Default::default()
+        };
+        sequence.start();
+        sequence.message(kind, payload)
+    }
+
+    /// Checks the invariants that must hold for any input, returning the result
+    /// of the read for further checks
+    fn check_read<const STREAM: bool>(
+        conn: &MockConnection<STREAM>,
+        max_size: usize,
+        sequenced: bool,
+    ) -> Result<Option<(u32, Vec<u8>)>, TestCaseError> {
+        let header_size = Header::wire_size(sequenced);
+        let result = read_message(conn, Vec::new, max_size, sequenced);
+
+        prop_assert!(conn.largest_read.get() <= header_size.max(max_size));
+
+        match result {
+            Ok(None) => {
+                prop_assert!(conn.data.is_empty());
+                Ok(None)
+            }
+            Ok(Some((read, payload))) => {
+                let header = Header::from_bytes(&conn.data[..header_size], sequenced).unwrap();
+                prop_assert_eq!(read, header);
+                prop_assert_eq!(payload.len(), header.size as usize);
+                prop_assert!(payload.len() <= max_size);
+                prop_assert_eq!(
+                    &payload[..],
+                    &conn.data[header_size..header_size + payload.len()]
+                );
+                prop_assert_eq!(conn.pos.get(), header_size + payload.len());
+                Ok(Some((header.kind, payload)))
+            }
+            Err(Error::ProtocolError(_)) => Ok(None),
+            Err(err) => Err(TestCaseError::fail(format!("unexpected error: {err}"))),
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_bytes(
+            data in proptest::collection::vec(any::<u8>(), 0..64),
+            splits in proptest::collection::vec(0usize..64, 0..8),
+            max_size in 0usize..64,
+            sequenced in any::<bool>(),
+        ) {
+            let conn = MockConnection::new(data, splits);
+            check_read(&conn, max_size, sequenced)?;
+        }
+
+        #[test]
+        fn arbitrary_headers(
+            kind in any::<u32>(),
+            size in any::<u32>(),
+            seq in any::<u32>(),
+            tail in proptest::collection::vec(any::<u8>(), 0..256),
+            splits in proptest::collection::vec(0usize..268, 0..8),
+            max_size in 0usize..1024,
+            sequenced in any::<bool>(),
+        ) {
+            let mut data = Header { kind, size, seq }.as_bytes(sequenced).to_vec();
+            data.extend(tail);
+
+            let conn = MockConnection::new(data, splits);
+            check_read(&conn, max_size, sequenced)?;
+        }
+
+        #[test]
+        fn well_formed(
+            kind in prop_oneof![Just(CRASH), Just(PING), USER..u32::MAX],
+            payload in proptest::collection::vec(any::<u8>(), 0..1024),
+            trailing in proptest::collection::vec(any::<u8>(), 0..64),
+        ) {
+            let mut data = message(kind, &payload);
+            let len = data.len();
+            data.extend(trailing);
+
+            // Since the message arrives in one piece, it must be parsed as is,
+            // without touching the bytes that follow it
+            let conn = MockConnection::new(data.clone(), vec![len]);
+            let read = check_read(&conn, payload.len(), false)?;
+            prop_assert_eq!(read.as_ref(), Some(&(kind, payload.clone())));
+
+            let conn = MockConnection::seqpacket(data, vec![len]);
+            let read = check_read(&conn, payload.len(), false)?;
+            prop_assert_eq!(read, Some((kind, payload)));
+        }
+
+        #[test]
+        fn well_formed_sequenced(
+            kind in prop_oneof![Just(CRASH), Just(PING), USER..u32::MAX],
+            seq in any::<u32>(),
+            payload in proptest::collection::vec(any::<u8>(), 0..1024),
+        ) {
+            let data = sequenced_message(kind, seq, &payload);
+            let len = data.len();
+
+            let conn = MockConnection::new(data, vec![len]);
+            let read = check_read(&conn, payload.len(), true)?;
+            prop_assert_eq!(read, Some((kind, payload)));
+        }
+    }
+
+    #[test]
+    fn closed() {
+        let conn = MockConnection::new(Vec::new(), Vec::new());
+        assert!(matches!(read_message(&conn, Vec::new, 0, false), Ok(None)));
+    }
+
+    /// Reads a message with each of the sequence numbers from a connection,
+    /// returning the violation, if any, detected for each of them
+    fn check_sequence(seqs: &[u32]) -> Vec<Option<ProtocolViolation>> {
+        let data = seqs
+            .iter()
+            .flat_map(|&seq| sequenced_message(USER, seq, b"payload"))
+            .collect();
+        let conn = MockConnection::new(data, Vec::new());
+
+        let mut sequence = Sequence::default();
+        sequence.start();
+
+        let mut violations = Vec::new();
+        while let Some((header, _payload)) = read_message(&conn, Vec::new, 32, true).unwrap() {
+            violations.push(sequence.check(&header));
+        }
+        violations
+    }
+
+    #[test]
+    fn sequence_gap() {
+        // The gap is only reported once, as the sequence continues from the
+        // number that was received
+        assert_eq!(
+            check_sequence(&[0, 1, 3, 4]),
+            [
+                None,
+                None,
+                Some(ProtocolViolation::SequenceGap {
+                    expected: 2,
+                    received: 3
+                }),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn sequence_reordered() {
+        assert_eq!(
+            check_sequence(&[0, 2, 1]),
+            [
+                None,
+                Some(ProtocolViolation::SequenceGap {
+                    expected: 1,
+                    received: 2
+                }),
+                Some(ProtocolViolation::SequenceGap {
+                    expected: 3,
+                    received: 1
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn unsequenced_is_unchecked() {
+        let mut sequence = Sequence::default();
+
+        for seq in [5, 0, 9] {
+            let header = Header {
+                kind: USER,
+                size: 0,
+                seq,
+            };
+            assert_eq!(sequence.check(&header), None);
+        }
+
+        // Nothing is assigned until sequencing starts
+        assert_eq!(sequence.header(USER, 0).seq, 0);
+        sequence.start();
+        assert_eq!(sequence.header(USER, 0).seq, 0);
+        assert_eq!(sequence.header(USER, 0).seq, 1);
+    }
+
+    #[test]
+    fn oversized() {
+        let conn = MockConnection::new(message(USER, &[0; 33]), Vec::new());
+        assert!(matches!(
+            read_message(
+                &conn,
+                || unreachable!("the buffer should never be allocated"),
+                32,
+                false
+            ),
+            Err(Error::ProtocolError(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_kind() {
+        // Past the largest user kind, see `MAX_MESSAGE_KIND`
+        let conn = MockConnection::new(message(u32::MAX, &[]), Vec::new());
+        assert!(matches!(
+            read_message(&conn, Vec::new, 32, false),
+            Err(Error::ProtocolError(_))
+        ));
+
+        // `CRASH_ACK` is only sent by the server, but is also `BREADCRUMBS`
+        let conn = MockConnection::new(message(BREADCRUMBS, &[0; 8]), Vec::new());
+        assert!(matches!(
+            read_message(&conn, Vec::new, 32, false),
+            Ok(Some((header, _))) if header.kind == BREADCRUMBS
+        ));
+
+        // The boundaries of the user kinds, see `MAX_MESSAGE_KIND`
+        for kind in [USER, u32::MAX - 1] {
+            let conn = MockConnection::new(message(kind, &[]), Vec::new());
+            assert!(matches!(
+                read_message(&conn, Vec::new, 32, false),
+                Ok(Some((header, _))) if header.kind == kind
+            ));
+        }
+    }
+
+    #[test]
+    fn split_message() {
+        let msg = message(USER + 1, b"a message");
+
+        for split in 1..msg.len() {
+            // A stream socket has no message boundaries, so the rest of the
+            // message is simply read once it arrives
+            let conn = MockConnection::new(msg.clone(), vec![split]);
+            assert!(
+                matches!(
+                    read_message(&conn, Vec::new, 32, false),
+                    Ok(Some((header, payload))) if header.kind == USER + 1 && payload == b"a message"
+                ),
+                "split at {split} was not read whole"
+            );
+
+            // Whereas on a seqpacket socket, each piece is a message of its own
+            let conn = MockConnection::seqpacket(msg.clone(), vec![split]);
+            assert!(
+                matches!(
+                    read_message(&conn, Vec::new, 32, false),
+                    Err(Error::ProtocolError(_))
+                ),
+                "split at {split} was not detected"
+            );
         }
     }
 }
@@ -0,0 +1,366 @@
+//! The client and server for targets without IPC, eg. `wasm32-unknown-unknown`,
+//! so that code using them compiles unchanged, but neither can be created, as
+//! creating either always fails with [`Error::Unsupported`].
+//!
+//! Types that can only be obtained from a [`Client`] or [`Server`] hold an
+//! [`Infallible`], so their methods can never actually be called.
+
+use super::SocketName;
+use crate::Error;
+use std::{convert::Infallible, time::Duration};
+
+/// Client side of the connection, which can't be created on this target
+pub struct Client {
+    never: Infallible,
+}
+
+impl Client {
+    /// Always fails with [`Error::Unsupported`]
+    pub fn with_name<'scope>(_name: impl Into<SocketName<'scope>>) -> Result<Self, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// Always fails with [`Error::Unsupported`], without waiting
+    pub fn with_name_timeout<'scope>(
+        name: impl Into<SocketName<'scope>>,
+        _timeout: Duration,
+        _interval: Duration,
+    ) -> Result<Self, Error> {
+        Self::with_name(name)
+    }
+
+    /// Requests that the server generate a minidump for the crash context
+    #[inline]
+    pub fn request_dump(&self, _crash_context: &crash_context::CrashContext) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// Sets how many times a deferred crash request is retried
+    #[inline]
+    pub fn crash_retries(self, _retries: u32) -> Self {
+        match self.never {}
+    }
+
+    /// Requests that the server generate a minidump for the crash context,
+    /// returning the outcome reported by the server
+    #[inline]
+    pub fn request_dump_with_result(
+        &self,
+        _crash_context: &crash_context::CrashContext,
+    ) -> Result<DumpOutcome, Error> {
+        match self.never {}
+    }
+
+    /// Requests that the server generate a minidump for the crash context,
+    /// only blocking until the server has queued the request
+    #[inline]
+    pub fn queue_dump(&self, _crash_context: &crash_context::CrashContext) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// Requests that the server generate a minidump of the current process
+    #[inline]
+    pub fn request_on_demand_dump(&self) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// Sends a message to the server
+    #[inline]
+    pub fn send_message(&self, _kind: u32, _buf: impl AsRef<[u8]>) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// Sends a value to the server as a typed message
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub fn send_typed<T: serde::Serialize + ?Sized>(
+        &self,
+        _kind: u32,
+        _value: &T,
+    ) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// Sends a ping to the server
+    #[inline]
+    pub fn ping(&self) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// Creates breadcrumbs shared with the server
+    #[inline]
+    pub fn create_breadcrumbs(&self, _capacity: usize) -> Result<crate::Breadcrumbs, Error> {
+        match self.never {}
+    }
+
+    /// The number of messages received from the server out of sequence
+    #[inline]
+    pub fn sequence_gaps(&self) -> u64 {
+        match self.never {}
+    }
+
+    /// Asks the server to shut down
+    #[inline]
+    pub fn request_shutdown(&self) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// Retrieves the next message sent by the server, without blocking
+    #[inline]
+    pub fn try_recv_server_message(&self) -> Result<Option<(u32, Vec<u8>)>, Error> {
+        match self.never {}
+    }
+}
+
+/// The outcome of a crash request, see [`Client::request_dump_with_result`]
+#[derive(Clone, Debug)]
+pub struct DumpOutcome {
+    never: Infallible,
+}
+
+impl DumpOutcome {
+    /// Whether the server wrote the minidump
+    #[inline]
+    pub fn written(&self) -> Option<bool> {
+        match self.never {}
+    }
+
+    /// Why the server failed to write the minidump, if it did
+    #[inline]
+    pub fn error(&self) -> Option<Error> {
+        match self.never {}
+    }
+
+    /// The path the minidump was written to, as raw bytes
+    #[inline]
+    pub fn path_bytes(&self) -> Option<&[u8]> {
+        match self.never {}
+    }
+
+    /// The path the minidump was written to
+    #[inline]
+    pub fn path(&self) -> Option<&std::path::Path> {
+        match self.never {}
+    }
+}
+
+/// Whether a connection failure might succeed if retried, which it never will
+pub(crate) fn is_retryable(_err: &Error) -> bool {
+    false
+}
+
+/// Identifies a client connected to a [`Server`], see [`ServerHandle`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientId(pub(crate) usize);
+
+/// Statistics about a running [`Server`], see [`ServerHandle::stats`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ServerStats {
+    /// The number of connections that were closed as soon as they were
+    /// accepted
+    pub rejected_connections: u64,
+    /// The number of connections that were aborted before they were accepted
+    pub aborted_connections: u64,
+    /// The number of messages received out of sequence
+    pub sequence_gaps: u64,
+    /// The number of warnings reported with the minidumps that were written
+    pub dump_warnings: u64,
+}
+
+/// A handle to a [`Server`], which can't be created on this target
+#[derive(Clone)]
+pub struct ServerHandle {
+    never: Infallible,
+}
+
+impl ServerHandle {
+    /// Sends a message to the specified client
+    pub fn send_to(&self, _client: ClientId, _kind: u32, _buf: &[u8]) {
+        match self.never {}
+    }
+
+    /// Sends a message to every connected client
+    pub fn broadcast(&self, _kind: u32, _buf: &[u8]) {
+        match self.never {}
+    }
+
+    /// Sends a value to the specified client as a typed message
+    #[cfg(feature = "serde")]
+    pub fn send_typed<T: serde::Serialize + ?Sized>(
+        &self,
+        _client: ClientId,
+        _kind: u32,
+        _value: &T,
+    ) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// The clients that are currently connected to the server
+    pub fn clients(&self) -> Vec<ClientId> {
+        match self.never {}
+    }
+
+    /// Statistics about the server since it was created
+    pub fn stats(&self) -> ServerStats {
+        match self.never {}
+    }
+}
+
+/// Options for a [`Server`], which are all ignored on this target
+#[derive(Clone, Debug, Default)]
+pub struct ServerOptions {
+    _private: (),
+}
+
+impl ServerOptions {
+    /// Whether shutdown requests sent by clients are accepted
+    #[inline]
+    pub fn accept_shutdown_requests(self, _accept: bool) -> Self {
+        self
+    }
+
+    /// How long client connections can go without sending a message
+    #[inline]
+    pub fn stale_timeout(self, _timeout: Option<Duration>) -> Self {
+        self
+    }
+
+    /// The maximum number of connections waiting to be accepted
+    #[inline]
+    pub fn listen_backlog(self, _backlog: u32) -> Self {
+        self
+    }
+
+    /// Whether the socket path is removed when the server is dropped
+    #[inline]
+    pub fn cleanup_socket_on_drop(self, _cleanup: bool) -> Self {
+        self
+    }
+
+    /// The longest the server loop waits for socket events
+    #[inline]
+    pub fn poll_interval(self, _interval: Duration) -> Self {
+        self
+    }
+
+    /// The maximum number of clients that can be connected at the same time
+    #[inline]
+    pub fn max_clients(self, _max: Option<usize>) -> Self {
+        self
+    }
+
+    /// The maximum size of the payload of a single message from a client
+    #[inline]
+    pub fn max_message_size(self, _max: Option<usize>) -> Self {
+        self
+    }
+
+    /// Whether the command line and environment of crashed clients are
+    /// captured
+    #[inline]
+    pub fn capture_process_info(self, _capture: bool) -> Self {
+        self
+    }
+
+    /// The environment variables captured along with the command line
+    #[inline]
+    pub fn allowed_env_vars<S: Into<String>>(self, _names: impl IntoIterator<Item = S>) -> Self {
+        self
+    }
+
+    /// Whether the captured command line and environment are embedded in the
+    /// minidump
+    #[cfg(feature = "minidump-writer")]
+    #[inline]
+    pub fn embed_process_info(self, _embed: bool) -> Self {
+        self
+    }
+}
+
+/// Server side of the connection, which can't be created on this target
+pub struct Server {
+    never: Infallible,
+}
+
+impl Server {
+    /// Always fails with [`Error::Unsupported`]
+    #[inline]
+    pub fn with_name<'scope>(name: impl Into<SocketName<'scope>>) -> Result<Self, Error> {
+        Self::with_name_and_options(name, ServerOptions::default())
+    }
+
+    /// Always fails with [`Error::Unsupported`]
+    pub fn with_name_and_options<'scope>(
+        _name: impl Into<SocketName<'scope>>,
+        _options: ServerOptions,
+    ) -> Result<Self, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// Retrieves a handle that can be used to send messages to clients
+    #[inline]
+    pub fn handle(&self) -> ServerHandle {
+        match self.never {}
+    }
+
+    /// Runs the server loop with the options the server was created with
+    #[inline]
+    pub fn run(
+        &mut self,
+        _handler: Box<dyn crate::ServerHandler>,
+        _shutdown: &std::sync::atomic::AtomicBool,
+        _stale_timeout: Option<Duration>,
+    ) -> Result<(), Error> {
+        match self.never {}
+    }
+
+    /// Runs the server loop
+    pub fn run_with_options(
+        &mut self,
+        _handler: Box<dyn crate::ServerHandler>,
+        _shutdown: &std::sync::atomic::AtomicBool,
+        _options: ServerOptions,
+    ) -> Result<(), Error> {
+        match self.never {}
+    }
+}
+
+/// Several servers run by a single loop, which is always empty on this target
+/// as no [`Server`] can be created
+#[derive(Default)]
+pub struct ServerSet {
+    _private: (),
+}
+
+impl ServerSet {
+    /// Creates an empty set
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a server to the set
+    #[inline]
+    pub fn add(&mut self, server: Server, _handler: Box<dyn crate::ServerHandler>) -> &mut Self {
+        match server.never {}
+    }
+
+    /// The number of servers in the set, which is always 0
+    #[inline]
+    pub fn len(&self) -> usize {
+        0
+    }
+
+    /// Whether the set has no servers, which is always true
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        true
+    }
+
+    /// Returns immediately, as the set has no servers
+    #[inline]
+    pub fn run(self, _shutdown: &std::sync::atomic::AtomicBool) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -74,12 +74,103 @@ mod bindings {
         ),
     >;
 
+    #[repr(C)]
+    pub struct WSAPOLLFD {
+        pub fd: SOCKET,
+        pub events: i16,
+        pub revents: i16,
+    }
+
+    pub const POLLRDNORM: i16 = 0x100;
+
+    /// `_WSAIOR(IOC_VENDOR, 256)` from `afunix.h`
+    pub const SIO_AF_UNIX_GETPEERPID: u32 = 0x5800_0100;
+
     pub type WSA_ERROR = i32;
     pub const WSAESHUTDOWN: WSA_ERROR = 10058;
 
+    pub type PSID = *mut std::ffi::c_void;
+
+    #[repr(C)]
+    pub struct SID_AND_ATTRIBUTES {
+        pub Sid: PSID,
+        pub Attributes: u32,
+    }
+
+    #[repr(C)]
+    pub struct TOKEN_USER {
+        pub User: SID_AND_ATTRIBUTES,
+    }
+
+    #[repr(C)]
+    pub struct ACL {
+        pub AclRevision: u8,
+        pub Sbz1: u8,
+        pub AclSize: u16,
+        pub AceCount: u16,
+        pub Sbz2: u16,
+    }
+
+    #[repr(C)]
+    pub struct ACE_HEADER {
+        pub AceType: u8,
+        pub AceFlags: u8,
+        pub AceSize: u16,
+    }
+
+    #[repr(C)]
+    pub struct ACCESS_ALLOWED_ACE {
+        pub Header: ACE_HEADER,
+        pub Mask: u32,
+        pub SidStart: u32,
+    }
+
+    pub const TOKEN_QUERY: u32 = 0x0008;
+    pub const TOKEN_USER_CLASS: i32 = 1;
+    pub const ACL_REVISION: u32 = 2;
+    pub const GENERIC_ALL: u32 = 0x1000_0000;
+    pub const SE_FILE_OBJECT: i32 = 1;
+    pub const DACL_SECURITY_INFORMATION: u32 = 0x0000_0004;
+    pub const PROTECTED_DACL_SECURITY_INFORMATION: u32 = 0x8000_0000;
+
     #[link(name = "kernel32")]
     extern "system" {
         pub fn SetHandleInformation(hObject: HANDLE, dwMask: u32, dwFlags: HANDLE_FLAGS) -> BOOL;
+        pub fn GetCurrentProcess() -> HANDLE;
+        pub fn CloseHandle(hObject: HANDLE) -> BOOL;
+    }
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        pub fn OpenProcessToken(
+            ProcessHandle: HANDLE,
+            DesiredAccess: u32,
+            TokenHandle: *mut HANDLE,
+        ) -> BOOL;
+        pub fn GetTokenInformation(
+            TokenHandle: HANDLE,
+            TokenInformationClass: i32,
+            TokenInformation: *mut std::ffi::c_void,
+            TokenInformationLength: u32,
+            ReturnLength: *mut u32,
+        ) -> BOOL;
+        pub fn GetLengthSid(pSid: PSID) -> u32;
+        pub fn InitializeAcl(pAcl: *mut ACL, nAclLength: u32, dwAclRevision: u32) -> BOOL;
+        pub fn AddAccessAllowedAce(
+            pAcl: *mut ACL,
+            dwAceRevision: u32,
+            AccessMask: u32,
+            pSid: PSID,
+        ) -> BOOL;
+        pub fn SetNamedSecurityInfoW(
+            pObjectName: *const u16,
+            ObjectType: i32,
+            SecurityInfo: u32,
+            psidOwner: PSID,
+            psidGroup: PSID,
+            pDacl: *const ACL,
+            pSacl: *const ACL,
+        ) -> u32;
     }
 
     #[link(name = "ws2_32")]
@@ -112,6 +203,18 @@ mod bindings {
         pub fn bind(s: SOCKET, name: *const SOCKADDR, namelen: i32) -> i32;
         pub fn listen(s: SOCKET, backlog: i32) -> i32;
         pub fn connect(s: SOCKET, name: *const SOCKADDR, namelen: i32) -> i32;
+        pub fn WSAPoll(fdArray: *mut WSAPOLLFD, fds: u32, timeout: i32) -> i32;
+        pub fn WSAIoctl(
+            s: SOCKET,
+            dwIoControlCode: u32,
+            lpvInBuffer: *const std::ffi::c_void,
+            cbInBuffer: u32,
+            lpvOutBuffer: *mut std::ffi::c_void,
+            cbOutBuffer: u32,
+            lpcbBytesReturned: *mut u32,
+            lpOverlapped: *mut OVERLAPPED,
+            lpCompletionRoutine: LPWSAOVERLAPPED_COMPLETION_ROUTINE,
+        ) -> i32;
     }
 }
 
@@ -138,6 +241,90 @@ fn last_socket_error() -> io::Error {
     io::Error::from_raw_os_error(unsafe { bindings::WSAGetLastError() })
 }
 
+/// Replaces the DACL of the file at `path`, eg. a socket path, with one that
+/// only grants access to the user the current process is running as, the
+/// equivalent of a `0600` mode on unix
+pub(crate) fn restrict_to_current_user(path: &std::path::Path) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+
+    // SAFETY: syscalls, the buffers are sized as the calls require, and the
+    // token handle is closed before returning
+    unsafe {
+        let mut token = 0;
+        if bindings::OpenProcessToken(
+            bindings::GetCurrentProcess(),
+            bindings::TOKEN_QUERY,
+            &mut token,
+        ) == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        // The SID is stored after the TOKEN_USER in the same buffer, so query
+        // the needed size first. The buffer is u64s to satisfy the alignment
+        // of the TOKEN_USER
+        let mut len = 0;
+        bindings::GetTokenInformation(
+            token,
+            bindings::TOKEN_USER_CLASS,
+            std::ptr::null_mut(),
+            0,
+            &mut len,
+        );
+        let mut user = vec![0u64; (len as usize + 7) / 8];
+        let queried = bindings::GetTokenInformation(
+            token,
+            bindings::TOKEN_USER_CLASS,
+            user.as_mut_ptr().cast(),
+            len,
+            &mut len,
+        );
+        let err = io::Error::last_os_error();
+        bindings::CloseHandle(token);
+
+        if queried == 0 {
+            return Err(err);
+        }
+
+        let sid = (*user.as_ptr().cast::<bindings::TOKEN_USER>()).User.Sid;
+
+        let acl_len = std::mem::size_of::<bindings::ACL>()
+            + std::mem::size_of::<bindings::ACCESS_ALLOWED_ACE>()
+            - std::mem::size_of::<u32>()
+            + bindings::GetLengthSid(sid) as usize;
+        let mut acl_buf = vec![0u64; (acl_len + 7) / 8];
+        let acl = acl_buf.as_mut_ptr().cast::<bindings::ACL>();
+
+        if bindings::InitializeAcl(acl, acl_len as u32, bindings::ACL_REVISION) == 0
+            || bindings::AddAccessAllowedAce(
+                acl,
+                bindings::ACL_REVISION,
+                bindings::GENERIC_ALL,
+                sid,
+            ) == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+
+        // The DACL is protected so that it doesn't inherit any other entries
+        // from the parent directory
+        match bindings::SetNamedSecurityInfoW(
+            wide_path.as_ptr(),
+            bindings::SE_FILE_OBJECT,
+            bindings::DACL_SECURITY_INFORMATION | bindings::PROTECTED_DACL_SECURITY_INFORMATION,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            acl,
+            std::ptr::null(),
+        ) {
+            0 => Ok(()),
+            err => Err(io::Error::from_raw_os_error(err as i32)),
+        }
+    }
+}
+
 pub(crate) struct UnixSocketAddr {
     addr: sockaddr_un,
     len: i32,
@@ -368,11 +555,10 @@ impl Drop for Socket {
 pub(crate) struct UnixListener(Socket);
 
 impl UnixListener {
-    pub(crate) fn bind(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+    pub(crate) fn bind_unix_addr(addr: &UnixSocketAddr, backlog: u32) -> io::Result<Self> {
         init();
 
         let inner = Socket::new()?;
-        let addr = UnixSocketAddr::from_path(path.as_ref())?;
 
         // SAFETY: syscall
         if unsafe {
@@ -388,7 +574,10 @@ impl UnixListener {
 
         // SAFETY: syscall
         if unsafe {
-            bindings::listen(inner.as_raw_socket() as _, 128 /* backlog */)
+            bindings::listen(
+                inner.as_raw_socket() as _,
+                backlog.min(i32::MAX as u32) as i32,
+            )
         } != 0
         {
             Err(last_socket_error())
@@ -397,6 +586,14 @@ impl UnixListener {
         }
     }
 
+    /// Wraps an already bound and listening socket
+    pub(crate) fn from_socket(sock: std::os::windows::io::OwnedSocket) -> Self {
+        init();
+
+        // SAFETY: we have ownership of the socket
+        Self(unsafe { Socket::from_raw_socket(sock.into_raw_socket()) })
+    }
+
     pub(crate) fn accept_unix_addr(&self) -> io::Result<(UnixStream, UnixSocketAddr)> {
         let mut sock_addr = std::mem::MaybeUninit::<sockaddr_un>::uninit();
         let mut len = std::mem::size_of::<sockaddr_un>() as i32;
@@ -444,11 +641,10 @@ impl IntoRawSocket for UnixListener {
 pub(crate) struct UnixStream(Socket);
 
 impl UnixStream {
-    pub(crate) fn connect(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+    pub(crate) fn connect_unix_addr(addr: &UnixSocketAddr) -> io::Result<Self> {
         init();
 
         let inner = Socket::new()?;
-        let addr = UnixSocketAddr::from_path(path)?;
 
         // SAFETY: syscall
         if unsafe {
@@ -480,6 +676,48 @@ impl UnixStream {
         self.0.recv_vectored(bufs)
     }
 
+    /// Returns true if a read would not block, ie. there is data available,
+    /// or the connection has been closed
+    pub(crate) fn poll_readable(&self) -> io::Result<bool> {
+        let mut pfd = bindings::WSAPOLLFD {
+            fd: self.0 .0,
+            events: bindings::POLLRDNORM,
+            revents: 0,
+        };
+
+        // SAFETY: syscall
+        match unsafe { bindings::WSAPoll(&mut pfd, 1, 0) } {
+            bindings::SOCKET_ERROR => Err(last_socket_error()),
+            ready => Ok(ready > 0 && pfd.revents != 0),
+        }
+    }
+
+    /// Retrieves the process id of the peer, as of when it connected
+    pub(crate) fn peer_pid(&self) -> io::Result<u32> {
+        let mut pid = 0u32;
+        let mut len = 0;
+
+        // SAFETY: syscall, the output buffer is valid for its length
+        if unsafe {
+            bindings::WSAIoctl(
+                self.0 .0,
+                bindings::SIO_AF_UNIX_GETPEERPID,
+                std::ptr::null(),
+                0,
+                (&mut pid as *mut u32).cast(),
+                std::mem::size_of::<u32>() as u32,
+                &mut len,
+                std::ptr::null_mut(),
+                None,
+            )
+        } != 0
+        {
+            Err(last_socket_error())
+        } else {
+            Ok(pid)
+        }
+    }
+
     #[inline]
     pub(crate) fn send(&self, buf: &[u8]) -> io::Result<usize> {
         self.send_vectored(&[io::IoSlice::new(buf)])
@@ -1,21 +1,130 @@
 #![doc = include_str!("../README.md")]
+// Without a client and server, most of the crate is only there so that code
+// using it compiles, see `ipc/stub.rs`
+#![cfg_attr(
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "windows",
+        target_os = "macos"
+    )),
+    allow(dead_code)
+)]
 
+#[cfg(feature = "capi")]
+pub mod capi;
 mod errors;
 
-pub use errors::Error;
+pub use errors::{DumpFailureCategory, Error};
+#[cfg(feature = "minidump-writer")]
 use std::{fs::File, path::PathBuf};
 
 mod ipc;
-pub use ipc::{Client, Server};
+pub use ipc::{
+    Client, ClientId, DumpOutcome, OwnedSocketName, Server, ServerHandle, ServerOptions, ServerSet,
+    ServerStats, SocketName, MAX_DUMP_PATH_LEN, MAX_MESSAGE_KIND,
+};
+
+#[cfg(target_os = "windows")]
+mod crash_target;
+#[cfg(target_os = "windows")]
+pub use crash_target::WindowsCrashTarget;
+
+#[cfg(all(target_os = "windows", feature = "minidump-writer"))]
+mod hung;
+#[cfg(all(target_os = "windows", feature = "minidump-writer"))]
+pub use hung::write_hung_minidump;
+
+mod memory;
+pub use memory::RemoteMemory;
+
+#[cfg(feature = "minidump-writer")]
+mod dump_options;
+#[cfg(feature = "minidump-writer")]
+pub use dump_options::{ContentsMode, DumpMode, DumpOptions};
+
+mod panic_hook;
+pub use panic_hook::{
+    install_panic_hook, last_panic_message, PanicAction, PanicPolicy, MAX_PANIC_MESSAGE_LEN,
+};
+
+mod breadcrumbs;
+pub use breadcrumbs::{
+    Breadcrumb, Breadcrumbs, BREADCRUMBS_STREAM_TYPE, MAX_BREADCRUMBS, MAX_BREADCRUMB_LEN,
+};
+
+mod dump_warnings;
+pub use dump_warnings::{DumpWarning, DumpWarningKind, DumpWarningLogger};
+
+mod process_info;
+pub use process_info::{ProcessInfo, CMDLINE_STREAM_TYPE, ENVIRON_STREAM_TYPE};
+
+mod monitor;
+pub use monitor::{
+    monitor_socket_name, spawn_monitor, MonitorConfig, MonitorHandle, MONITOR_SOCKET_ENV,
+    MONITOR_SOCKET_PATH_ENV,
+};
+
+#[cfg(feature = "serde")]
+pub mod typed;
+#[cfg(feature = "serde")]
+pub use typed::TypedHandler;
 
 /// The result of a successful minidump generation.
+#[cfg(feature = "minidump-writer")]
 pub struct MinidumpBinary {
     /// The file the minidump was written to, as provided by [`ServerHandler::create_minidump_file`]
     pub file: File,
     /// The path to the file as provided by [`ServerHandler::create_minidump_file`].
     pub path: PathBuf,
-    /// The in-memory contents of the minidump, if available
+    /// The in-memory contents of the minidump, if available, see
+    /// [`ContentsMode`]
     pub contents: Option<Vec<u8>>,
+    /// Details about the crash the minidump was written for
+    pub metadata: DumpMetadata,
+    /// What went wrong while writing the minidump, without preventing it from
+    /// being written, in the order it happened. Empty for a minidump that is
+    /// as complete as requested, see [`DumpWarningKind`].
+    pub dump_warnings: Vec<DumpWarning>,
+}
+
+/// Details about a crash, as reported by the crashing client, rather than
+/// observed by the [`Server`], which may only receive the crash some time
+/// after it occurred
+#[derive(Clone, Debug, Default)]
+pub struct DumpMetadata {
+    /// The clock readings taken by the client when the crash was caught, eg.
+    /// the time of the crash, and how long the process had been running
+    pub timestamps: crash_context::CrashTimestamps,
+    /// The OS id of the thread that crashed
+    pub thread_id: u64,
+    /// The name of the thread that crashed, if it had one
+    pub thread_name: crash_context::ThreadName,
+    /// Whether the thread that crashed is the main thread of the process, if
+    /// the client knew which thread that is, see
+    /// `CrashContext::is_main_thread`
+    pub is_main_thread: Option<bool>,
+    /// Whether the dump was requested via [`Client::request_on_demand_dump`],
+    /// ie. the process didn't crash, and is still running
+    pub on_demand: bool,
+    /// The thread local storage registers of the thread that crashed
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub tls: crash_context::TlsRegisters,
+    /// The command line and allowed environment variables of the process that
+    /// crashed, if they were captured, see [`ServerOptions::capture_process_info`]
+    pub process: Option<ProcessInfo>,
+    /// The breadcrumbs the client had recorded when the crash was caught, oldest
+    /// first, if it created any, see [`Client::create_breadcrumbs`]. They are
+    /// also added to the minidump as the [`BREADCRUMBS_STREAM_TYPE`] stream.
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// What memory of the process was written to the minidump, as requested
+    /// by [`ServerHandler::dump_options`]
+    #[cfg(feature = "minidump-writer")]
+    pub mode: DumpMode,
+    /// The innermost record in the chain of nested exception records, ie. the
+    /// original exception, read from the crashed process
+    #[cfg(target_os = "windows")]
+    pub exception_record: Option<crash_context::OwnedExceptionRecord>,
 }
 
 /// Actions for the [`Server`] message loop to take after a [`ServerHandler`]
@@ -29,34 +138,211 @@ pub enum LoopAction {
     Continue,
 }
 
+/// What the [`Server`] does with a crash request, see
+/// [`ServerHandler::on_crash_request`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CrashRequestAction {
+    /// Queues the request to have its minidump written, as normal
+    Dump,
+    /// Tells the client that the server is busy, eg. because it is already
+    /// writing a large minidump, and that it should retry the request after
+    /// the delay, see [`Client::crash_retries`]. The client stays connected.
+    ///
+    /// The delay is sent in milliseconds, saturating at [`u32::MAX`].
+    Defer {
+        /// How long the client should wait before retrying the request
+        retry_after: std::time::Duration,
+    },
+}
+
+/// The credentials of a process that connected to the [`Server`], as of when
+/// it connected, see [`ServerHandler::on_client_connected`].
+///
+/// These are only available on Linux/Android, except for the process id, which
+/// is also available on Windows, and are `None` on other platforms.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PeerCredentials {
+    /// The process id
+    pub pid: Option<u32>,
+    /// The effective user id
+    pub uid: Option<u32>,
+    /// The effective group id
+    pub gid: Option<u32>,
+}
+
+/// Why a client was disconnected from the [`Server`], see
+/// [`ServerHandler::on_client_disconnected`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DisconnectReason {
+    /// The client closed its connection
+    Closed,
+    /// The client requested a minidump, after which its connection is closed
+    /// by the server once the minidump has been written
+    Crashed,
+    /// Receiving from, or sending to, the client failed. Clients that violate
+    /// the protocol, eg. by sending a message larger than
+    /// [`ServerHandler::max_message_size`], are reported as
+    /// [`std::io::ErrorKind::InvalidData`]
+    Errored(std::io::ErrorKind),
+    /// The client hadn't sent a message in longer than the
+    /// [`ServerOptions::stale_timeout`], with how long it had been. As the OS closes
+    /// the connection when a process exits, this usually means the client
+    /// exited abruptly without the connection being closed, or has hung
+    Stale(std::time::Duration),
+}
+
+/// A violation of the protocol by a client that doesn't warrant disconnecting
+/// it, see [`ServerHandler::on_protocol_error`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProtocolViolation {
+    /// The sequence number of a message was not the one following that of
+    /// the previous message from the client, meaning messages were lost, or
+    /// arrived out of order. The server continues from the received sequence
+    /// number, so each gap is only reported once.
+    SequenceGap {
+        /// The sequence number the server expected
+        expected: u32,
+        /// The sequence number of the message that was received
+        received: u32,
+    },
+}
+
 /// Allows user code to hook into the server to avoid hardcoding too many details
 pub trait ServerHandler: Send + Sync {
     /// Called when a crash request has been received and a backing file needs
     /// to be created to store it.
+    #[cfg(feature = "minidump-writer")]
     fn create_minidump_file(&self) -> Result<(File, PathBuf), std::io::Error>;
     /// Called when a crash has been fully written as a minidump to the provided
     /// file. Also returns the full heap buffer as well.
     ///
     /// A return value of true indicates that the message loop should exit and
     /// stop processing messages.
+    #[cfg(feature = "minidump-writer")]
     fn on_minidump_created(&self, result: Result<MinidumpBinary, Error>) -> LoopAction;
+    /// Called before the minidump for a crash is written, to determine what
+    /// is written to it, eg. [`DumpOptions::full_memory`] for a crash that
+    /// needs more than the stacks to investigate.
+    ///
+    /// Defaults to [`DumpOptions::default`].
+    #[cfg(feature = "minidump-writer")]
+    fn dump_options(&self, _crash_context: &crash_context::CrashContext) -> DumpOptions {
+        DumpOptions::default()
+    }
+    /// Called when a crash request has been received, with the crash context
+    /// sent by the client, when the `minidump-writer` feature is disabled and
+    /// the server therefore doesn't write minidumps itself.
+    ///
+    /// The client is blocked until this returns, so the crashed process, eg.
+    /// the pid on Linux, the task on Macos, or the exception pointers on
+    /// Windows, can be inspected to produce a dump in whatever way the handler
+    /// sees fit.
+    #[cfg(not(feature = "minidump-writer"))]
+    fn on_crash_context(
+        &self,
+        crash_context: &crash_context::CrashContext,
+        metadata: &DumpMetadata,
+    ) -> LoopAction;
+    /// Called when a crash request has been received, before it is queued to
+    /// have its minidump written, with the number of crash requests that are
+    /// already queued, or being written.
+    ///
+    /// Returning [`CrashRequestAction::Defer`] tells the client to retry the
+    /// request later, rather than blocking it until every queued minidump has
+    /// been written. Clients older than this version of the crate don't
+    /// understand that reply, so their requests are always queued.
+    ///
+    /// Defaults to [`CrashRequestAction::Dump`].
+    ///
+    /// # Macos
+    ///
+    /// This is not called, as crash contexts are received over a mach port,
+    /// whose ack can't ask the client to retry.
+    fn on_crash_request(
+        &self,
+        _client: ClientId,
+        _crash_context: &crash_context::CrashContext,
+        _pending_dumps: usize,
+    ) -> CrashRequestAction {
+        CrashRequestAction::Dump
+    }
     /// Called when the client sends a user message sent from the client with
     /// `send_message`
     fn on_message(&self, kind: u32, buffer: Vec<u8>);
+    /// Called when a client sends a user message, identifying the client so
+    /// that the server can reply via [`ServerHandle::send_to`].
+    ///
+    /// Defaults to calling [`Self::on_message`].
+    fn on_client_message(&self, _client: ClientId, kind: u32, buffer: Vec<u8>) {
+        self.on_message(kind, buffer);
+    }
     /// Optional allocation function for the buffer used to store a message.
     ///
     /// Defaults to creating a new vec.
     fn message_alloc(&self) -> Vec<u8> {
         Vec::new()
     }
+    /// The maximum size, in bytes, of the payload of a single message the
+    /// server will accept from a client. Clients that send a message larger
+    /// than this are treated as having violated the protocol and disconnected,
+    /// before any buffer for the message is allocated.
+    ///
+    /// Defaults to 16MiB, and is ignored if [`ServerOptions::max_message_size`]
+    /// is set.
+    fn max_message_size(&self) -> usize {
+        16 * 1024 * 1024
+    }
     /// Called when a new client connection has been established with the Server,
-    /// with the number of currently active client connections.
-    fn on_client_connected(&self, _num_clients: usize) -> LoopAction {
+    /// with the credentials of the client process, and the number of currently
+    /// active client connections.
+    ///
+    /// Returning [`LoopAction::Exit`] exits the message loop, so rejecting
+    /// individual clients based on their credentials is instead done via
+    /// [`ServerOptions::allowed_uids`].
+    fn on_client_connected(
+        &self,
+        _client: ClientId,
+        _peer: PeerCredentials,
+        _num_clients: usize,
+    ) -> LoopAction {
         LoopAction::Continue
     }
-    /// Called when a client has disconnected from the Server, with the number
-    /// of currently active client connections.
-    fn on_client_disconnected(&self, _num_clients: usize) -> LoopAction {
+    /// Called when a client has disconnected from the Server, or been
+    /// disconnected by it, with the reason why, and the number of currently
+    /// active client connections.
+    fn on_client_disconnected(
+        &self,
+        _client: ClientId,
+        _reason: DisconnectReason,
+        _num_clients: usize,
+    ) -> LoopAction {
         LoopAction::Continue
     }
+    /// Called when a client that created breadcrumbs, see
+    /// [`Client::create_breadcrumbs`], disconnects for any reason other than
+    /// crashing, just before [`Self::on_client_disconnected`], with the
+    /// breadcrumbs it had recorded, oldest first. This is how the last events
+    /// of a client that died without being able to request a minidump, eg.
+    /// because it was killed, can still be reported.
+    ///
+    /// The breadcrumbs of a client that crashed are instead in the
+    /// [`DumpMetadata`] of its minidump.
+    fn on_client_breadcrumbs(
+        &self,
+        _client: ClientId,
+        _reason: DisconnectReason,
+        _breadcrumbs: Vec<Breadcrumb>,
+    ) {
+    }
+    /// Called when a client violates the protocol in a way that the server
+    /// can recover from, eg. when messages from it were lost. Such
+    /// violations are also counted in [`ServerStats::sequence_gaps`].
+    ///
+    /// The message that revealed the violation is still processed as normal
+    /// after this returns.
+    fn on_protocol_error(&self, _client: ClientId, _violation: ProtocolViolation) {}
 }
@@ -0,0 +1,369 @@
+//! Provides [`RemoteMemory`] for reading the memory of a client process from
+//! the monitor process
+
+#![allow(unsafe_code)]
+
+use std::io;
+
+/// Reads memory from another process, typically a client that has crashed, or
+/// that has sent the address of some state it wants the server to retrieve in a
+/// user message.
+///
+/// # Linux
+///
+/// Reads use [`process_vm_readv`](https://man7.org/linux/man-pages/man2/process_vm_readv.2.html),
+/// falling back to `/proc/<pid>/mem` if the syscall is unavailable, eg. due to
+/// a seccomp filter. Both require ptrace access to the process, which the
+/// `crash-handler` grants to the monitor (or any process) for the duration of
+/// the crash callback via `PR_SET_DUMPABLE` and `PR_SET_PTRACER`, see
+/// `CrashHandler::set_ptracer`, and for as long as the client holds a
+/// `DumpableGuard`. Otherwise, reads will only succeed if the monitor is
+/// otherwise allowed to ptrace the client, eg. if it is the parent of the
+/// client and `/proc/sys/kernel/yama/ptrace_scope` is 1 or less.
+///
+/// # Windows
+///
+/// Reads use [`ReadProcessMemory`](https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-readprocessmemory)
+/// on a handle opened with `PROCESS_VM_READ`.
+///
+/// # Macos
+///
+/// Reads use `mach_vm_read_overwrite` on the task port for the process, which
+/// can't be retrieved from just a pid, so the [`RemoteMemory`] must be created
+/// from the task sent along with the [`crash_context::CrashContext`].
+///
+/// # Other targets
+///
+/// There are no other processes to read the memory of, so creating a
+/// [`RemoteMemory`] always fails with [`io::ErrorKind::Unsupported`].
+pub struct RemoteMemory {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pid: libc::pid_t,
+    #[cfg(target_os = "windows")]
+    process: isize,
+    #[cfg(target_os = "macos")]
+    task: u32,
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(any(target_os = "linux", target_os = "android"))] {
+        impl RemoteMemory {
+            /// Creates a [`RemoteMemory`] for the specified process.
+            ///
+            /// Note that this does not check if the process exists or that
+            /// its memory can be read, errors will only occur when reading.
+            #[inline]
+            pub fn new(pid: u32) -> io::Result<Self> {
+                Ok(Self { pid: pid as _ })
+            }
+
+            /// Creates a [`RemoteMemory`] for the process that crashed
+            #[inline]
+            pub fn from_crash_context(cc: &crash_context::CrashContext) -> io::Result<Self> {
+                Self::new(cc.pid as u32)
+            }
+
+            /// Reads memory from the process at the specified address into
+            /// the buffer, returning the number of bytes read.
+            ///
+            /// This can be less than the length of the buffer if the range
+            /// crosses into memory that is not mapped in the process.
+            ///
+            /// # Errors
+            ///
+            /// The memory could not be read, eg. because the process is not
+            /// allowed to be ptraced by this process, or the address is invalid
+            pub fn read(&self, addr: usize, buf: &mut [u8]) -> io::Result<usize> {
+                if buf.is_empty() {
+                    return Ok(0);
+                }
+
+                let local = libc::iovec {
+                    iov_base: buf.as_mut_ptr().cast(),
+                    iov_len: buf.len(),
+                };
+                let remote = libc::iovec {
+                    iov_base: addr as *mut _,
+                    iov_len: buf.len(),
+                };
+
+                // SAFETY: syscall, the local iovec covers exactly the buffer
+                let read = unsafe { libc::process_vm_readv(self.pid, &local, 1, &remote, 1, 0) };
+
+                if read >= 0 {
+                    return Ok(read as usize);
+                }
+
+                let err = io::Error::last_os_error();
+
+                // The syscall can be unavailable on older kernels, or be
+                // blocked by seccomp filters, in which case the proc file is
+                // the only other option that doesn't involve ptrace attaching
+                if matches!(err.raw_os_error(), Some(libc::ENOSYS | libc::EPERM)) {
+                    self.read_proc_mem(addr, buf)
+                } else {
+                    Err(err)
+                }
+            }
+
+            fn read_proc_mem(&self, addr: usize, buf: &mut [u8]) -> io::Result<usize> {
+                use std::os::unix::fs::FileExt;
+
+                let mem = std::fs::File::open(format!("/proc/{}/mem", self.pid))?;
+
+                let mut total = 0;
+                while total < buf.len() {
+                    match mem.read_at(&mut buf[total..], (addr + total) as u64) {
+                        Ok(0) => break,
+                        Ok(read) => total += read,
+                        Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                        // Like process_vm_readv, only fail if nothing could be read
+                        Err(_err) if total > 0 => break,
+                        Err(err) => return Err(err),
+                    }
+                }
+
+                Ok(total)
+            }
+        }
+    } else if #[cfg(target_os = "windows")] {
+        #[allow(non_snake_case, clippy::upper_case_acronyms)]
+        mod bindings {
+            pub type BOOL = i32;
+            pub type HANDLE = isize;
+
+            pub const PROCESS_VM_READ: u32 = 0x0010;
+            pub const ERROR_PARTIAL_COPY: i32 = 299;
+
+            #[link(name = "kernel32")]
+            extern "system" {
+                pub fn OpenProcess(dwDesiredAccess: u32, bInheritHandle: BOOL, dwProcessId: u32) -> HANDLE;
+                pub fn ReadProcessMemory(
+                    hProcess: HANDLE,
+                    lpBaseAddress: *const std::ffi::c_void,
+                    lpBuffer: *mut std::ffi::c_void,
+                    nSize: usize,
+                    lpNumberOfBytesRead: *mut usize,
+                ) -> BOOL;
+                pub fn CloseHandle(hObject: HANDLE) -> BOOL;
+            }
+        }
+
+        impl RemoteMemory {
+            /// Opens the specified process for reading.
+            ///
+            /// # Errors
+            ///
+            /// The process doesn't exist, or this process doesn't have the
+            /// rights to read its memory
+            pub fn new(pid: u32) -> io::Result<Self> {
+                // SAFETY: syscall
+                let process = unsafe { bindings::OpenProcess(bindings::PROCESS_VM_READ, 0, pid) };
+
+                if process == 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(Self { process })
+                }
+            }
+
+            /// Opens the process that crashed for reading
+            #[inline]
+            pub fn from_crash_context(cc: &crash_context::CrashContext) -> io::Result<Self> {
+                Self::new(cc.process_id)
+            }
+
+            /// Reads memory from the process at the specified address into
+            /// the buffer, returning the number of bytes read.
+            ///
+            /// This can be less than the length of the buffer if the range
+            /// crosses into memory that is not accessible in the process.
+            ///
+            /// # Errors
+            ///
+            /// The memory could not be read, eg. the address is invalid
+            pub fn read(&self, addr: usize, buf: &mut [u8]) -> io::Result<usize> {
+                if buf.is_empty() {
+                    return Ok(0);
+                }
+
+                let mut read = 0;
+
+                // SAFETY: syscall, the local buffer is valid for its length
+                let res = unsafe {
+                    bindings::ReadProcessMemory(
+                        self.process,
+                        addr as *const _,
+                        buf.as_mut_ptr().cast(),
+                        buf.len(),
+                        &mut read,
+                    )
+                };
+
+                if res != 0 {
+                    return Ok(read);
+                }
+
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(bindings::ERROR_PARTIAL_COPY) && read > 0 {
+                    Ok(read)
+                } else {
+                    Err(err)
+                }
+            }
+        }
+
+        impl Drop for RemoteMemory {
+            fn drop(&mut self) {
+                // SAFETY: syscall, we own the handle
+                unsafe {
+                    bindings::CloseHandle(self.process);
+                }
+            }
+        }
+    } else if #[cfg(target_os = "macos")] {
+        extern "C" {
+            fn mach_vm_read_overwrite(
+                target_task: u32,
+                address: u64,
+                size: u64,
+                data: u64,
+                outsize: *mut u64,
+            ) -> i32;
+        }
+
+        impl RemoteMemory {
+            /// Creates a [`RemoteMemory`] for the specified task port.
+            ///
+            /// The port is not owned by the [`RemoteMemory`], and must remain
+            /// valid for as long as it is used.
+            #[inline]
+            pub fn from_task(task: u32) -> Self {
+                Self { task }
+            }
+
+            /// Creates a [`RemoteMemory`] for the task that crashed
+            #[inline]
+            pub fn from_crash_context(cc: &crash_context::CrashContext) -> io::Result<Self> {
+                Ok(Self::from_task(cc.task))
+            }
+
+            /// Reads memory from the task at the specified address into the
+            /// buffer, returning the number of bytes read.
+            ///
+            /// # Errors
+            ///
+            /// The memory could not be read, eg. the address, or part of the
+            /// range, is invalid
+            pub fn read(&self, addr: usize, buf: &mut [u8]) -> io::Result<usize> {
+                if buf.is_empty() {
+                    return Ok(0);
+                }
+
+                let mut read = 0;
+
+                // SAFETY: syscall, the local buffer is valid for its length
+                let kr = unsafe {
+                    mach_vm_read_overwrite(
+                        self.task,
+                        addr as u64,
+                        buf.len() as u64,
+                        buf.as_mut_ptr() as u64,
+                        &mut read,
+                    )
+                };
+
+                if kr == 0 {
+                    Ok(read as usize)
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("mach_vm_read_overwrite failed: {kr}"),
+                    ))
+                }
+            }
+        }
+    } else {
+        #[allow(clippy::unused_self)]
+        impl RemoteMemory {
+            /// Always fails with [`io::ErrorKind::Unsupported`]
+            #[inline]
+            pub fn from_crash_context(_cc: &crash_context::CrashContext) -> io::Result<Self> {
+                Err(io::ErrorKind::Unsupported.into())
+            }
+
+            /// Always fails with [`io::ErrorKind::Unsupported`]
+            #[inline]
+            pub fn read(&self, _addr: usize, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::Unsupported.into())
+            }
+        }
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod test {
+    use super::RemoteMemory;
+
+    const PATTERN: &[u8] = b"remote memory sentinel";
+
+    #[test]
+    fn reads_memory() {
+        let mem = RemoteMemory::new(std::process::id()).unwrap();
+
+        let mut buf = [0u8; PATTERN.len()];
+        assert_eq!(
+            mem.read(PATTERN.as_ptr() as usize, &mut buf).unwrap(),
+            buf.len()
+        );
+        assert_eq!(buf, PATTERN);
+
+        // Use the fallback directly, as we can't block the syscall
+        let mut buf = [0u8; PATTERN.len()];
+        assert_eq!(
+            mem.read_proc_mem(PATTERN.as_ptr() as usize, &mut buf)
+                .unwrap(),
+            buf.len()
+        );
+        assert_eq!(buf, PATTERN);
+
+        assert!(mem.read(0, &mut buf).is_err());
+        assert!(mem.read_proc_mem(0, &mut buf).is_err());
+    }
+
+    #[test]
+    fn reads_partial() {
+        // SAFETY: syscalls
+        unsafe {
+            let page_size = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+
+            let map = libc::mmap(
+                std::ptr::null_mut(),
+                page_size * 2,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            );
+            assert_ne!(map, libc::MAP_FAILED);
+            assert_eq!(
+                libc::munmap(map.cast::<u8>().add(page_size).cast(), page_size),
+                0
+            );
+
+            let map = std::slice::from_raw_parts_mut(map.cast::<u8>(), page_size);
+            map.fill(0xaa);
+
+            let mem = RemoteMemory::new(std::process::id()).unwrap();
+
+            // Reads that cross into the unmapped page only read up until it
+            let addr = map.as_ptr() as usize + page_size - 16;
+            let mut buf = [0u8; 32];
+            assert_eq!(mem.read(addr, &mut buf).unwrap(), 16);
+            assert_eq!(mem.read_proc_mem(addr, &mut buf).unwrap(), 16);
+            assert!(buf[..16].iter().all(|b| *b == 0xaa));
+
+            libc::munmap(map.as_mut_ptr().cast(), page_size);
+        }
+    }
+}
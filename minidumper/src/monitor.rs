@@ -0,0 +1,248 @@
+//! Spawns a helper process to run the [`crate::Server`] in, see [`spawn_monitor`]
+
+use crate::{Client, Error, OwnedSocketName, SocketName};
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    process::{Child, Command, ExitStatus},
+    time::{Duration, Instant},
+};
+
+/// The environment variable set in the monitor process to the socket name
+/// that its [`crate::Server`] should be created with, see
+/// [`monitor_socket_name`]
+pub const MONITOR_SOCKET_ENV: &str = "MINIDUMPER_MONITOR_SOCKET";
+/// The environment variable set in the monitor process, instead of
+/// [`MONITOR_SOCKET_ENV`], on Linux/Android, when the socket name is a path,
+/// see [`monitor_socket_name`]
+pub const MONITOR_SOCKET_PATH_ENV: &str = "MINIDUMPER_MONITOR_SOCKET_PATH";
+
+/// Retrieves the socket name passed to the monitor by [`spawn_monitor`], for
+/// the monitor to create its [`crate::Server`] with, or `None` if this process
+/// was not launched as a monitor
+pub fn monitor_socket_name() -> Option<OwnedSocketName> {
+    if let Some(path) = std::env::var_os(MONITOR_SOCKET_PATH_ENV) {
+        return Some(OwnedSocketName::Path(path.into()));
+    }
+
+    let name = std::env::var(MONITOR_SOCKET_ENV).ok()?;
+    Some(SocketName::from(name.as_str()).into())
+}
+
+/// Configures how [`spawn_monitor`] launches the monitor process
+#[derive(Clone, Debug)]
+pub struct MonitorConfig {
+    pub(crate) program: PathBuf,
+    pub(crate) args: Vec<OsString>,
+    pub(crate) socket_name: OwnedSocketName,
+    pub(crate) connect_timeout: Duration,
+}
+
+impl MonitorConfig {
+    /// Launches the specified executable as the monitor, which is expected to
+    /// run a [`crate::Server`] with the name returned by
+    /// [`monitor_socket_name`].
+    ///
+    /// The socket name is interpreted in the same way as a `&str` passed to
+    /// [`crate::Server::with_name`], ie. it is an abstract name on
+    /// Linux/Android, and a path on other platforms.
+    pub fn new(program: impl Into<PathBuf>, socket_name: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            socket_name: SocketName::from(socket_name.into().as_str()).into(),
+            connect_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Launches the specified executable as the monitor of an Android app,
+    /// with the socket name that the app is allowed to connect to, see
+    /// [`SocketName::android_default`]
+    ///
+    /// # Errors
+    ///
+    /// The socket name can't be created in `context_dir`
+    #[cfg(target_os = "android")]
+    pub fn android_default(
+        program: impl Into<PathBuf>,
+        context_dir: &std::path::Path,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            program: program.into(),
+            args: Vec::new(),
+            socket_name: SocketName::android_default(context_dir)?,
+            connect_timeout: Duration::from_secs(5),
+        })
+    }
+
+    /// Launches the current executable as the monitor, with `marker` as its
+    /// first argument, so that it can detect that it is the monitor, eg. at
+    /// the start of `main`, and run the [`crate::Server`] instead of its usual
+    /// code.
+    ///
+    /// # Errors
+    ///
+    /// The path of the current executable can't be retrieved
+    pub fn reexec(
+        marker: impl Into<OsString>,
+        socket_name: impl Into<String>,
+    ) -> Result<Self, Error> {
+        Ok(Self::new(std::env::current_exe()?, socket_name).arg(marker))
+    }
+
+    /// Replaces the socket name, eg. with a path on Linux/Android, where the
+    /// name passed to [`Self::new`] is always an abstract name
+    #[inline]
+    pub fn socket_name(mut self, socket_name: impl Into<OwnedSocketName>) -> Self {
+        self.socket_name = socket_name.into();
+        self
+    }
+
+    /// Adds an argument to pass to the monitor
+    #[inline]
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds arguments to pass to the monitor
+    #[inline]
+    pub fn args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// How long to wait for the monitor's server to start accepting
+    /// connections, 5 seconds by default
+    #[inline]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+}
+
+/// A monitor process launched by [`spawn_monitor`]
+pub struct MonitorHandle {
+    client: Client,
+    child: Child,
+}
+
+impl MonitorHandle {
+    /// The client connected to the monitor's server
+    #[inline]
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The process id of the monitor
+    #[inline]
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Asks the monitor's server to shut down, then waits for the monitor to
+    /// exit.
+    ///
+    /// The server must have been created with
+    /// [`crate::ServerOptions::accept_shutdown_requests`], otherwise the
+    /// request is ignored and this will wait until the monitor exits of its
+    /// own accord.
+    ///
+    /// # Errors
+    ///
+    /// The request could not be sent, or waiting for the monitor failed
+    pub fn shutdown(mut self) -> Result<ExitStatus, Error> {
+        self.client.request_shutdown()?;
+        Ok(self.child.wait()?)
+    }
+}
+
+/// Launches a monitor process, as configured by `config`, and connects to the
+/// [`crate::Server`] it runs, once it is accepting connections.
+///
+/// This gives a one call out of process setup, leaving the choice of
+/// which executable to launch, and how it detects that it is the monitor, to
+/// the caller. The monitor is expected to create its server with the socket
+/// name passed to it via the environment, see [`monitor_socket_name`].
+///
+/// # Errors
+///
+/// The monitor could not be launched, it exited before its server accepted a
+/// connection, in which case an [`std::io::ErrorKind::Other`] error is returned,
+/// or the server did not accept a connection before the
+/// [`MonitorConfig::connect_timeout`] elapsed, in which case the monitor is
+/// killed and an [`std::io::ErrorKind::TimedOut`] error is returned
+pub fn spawn_monitor(config: MonitorConfig) -> Result<MonitorHandle, Error> {
+    let (env, socket_name) = socket_env(&config.socket_name)?;
+    let mut child = Command::new(&config.program)
+        .args(&config.args)
+        .env(env, socket_name)
+        .spawn()?;
+
+    let deadline = Instant::now() + config.connect_timeout;
+
+    loop {
+        let err = match Client::with_name(&config.socket_name) {
+            Ok(client) => return Ok(MonitorHandle { client, child }),
+            Err(err) if crate::ipc::is_retryable(&err) => err,
+            Err(err) => {
+                kill(&mut child);
+                return Err(err);
+            }
+        };
+
+        if let Some(status) = child.try_wait()? {
+            return Err(std::io::Error::other(format!(
+                "the monitor exited before accepting connections: {status}"
+            ))
+            .into());
+        }
+
+        if Instant::now() >= deadline {
+            log::error!("timed out connecting to the monitor: {err}");
+            kill(&mut child);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "timed out waiting for the monitor to accept connections",
+            )
+            .into());
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// The environment variable, and its value, that passes the socket name to
+/// the monitor, see [`monitor_socket_name`]
+fn socket_env(socket_name: &OwnedSocketName) -> Result<(&'static str, OsString), Error> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            use std::os::unix::ffi::OsStrExt;
+
+            Ok(match socket_name {
+                OwnedSocketName::Abstract(name) => {
+                    (MONITOR_SOCKET_ENV, std::ffi::OsStr::from_bytes(name).to_owned())
+                }
+                OwnedSocketName::Path(path) => (MONITOR_SOCKET_PATH_ENV, path.clone().into()),
+            })
+        } else {
+            match socket_name {
+                OwnedSocketName::Path(path) => Ok((MONITOR_SOCKET_ENV, path.clone().into())),
+                OwnedSocketName::Abstract(_) => Err(Error::UnsupportedSocketName),
+            }
+        }
+    }
+}
+
+/// Kills and reaps the monitor after a failure to connect to it
+fn kill(child: &mut Child) {
+    if let Err(err) = child.kill() {
+        log::error!("failed to kill the monitor: {err}");
+    }
+
+    let _status = child.wait();
+}
@@ -0,0 +1,162 @@
+//! Reports panics to the server from a process wide panic hook, see
+//! [`install_panic_hook`]
+
+use crate::Client;
+use std::{fmt::Write, sync::Arc, time::Duration};
+
+/// The maximum length of the panic message recorded by the hook, longer
+/// messages are truncated
+pub const MAX_PANIC_MESSAGE_LEN: usize = 4096;
+
+/// How long the hook waits for another thread that is recording its own panic
+/// before giving up on recording this one
+const RECORD_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The last panic recorded by the hook, which is allocated up front so that
+/// recording it doesn't allocate
+static LAST_PANIC: parking_lot::Mutex<PanicMessage> = parking_lot::const_mutex(PanicMessage {
+    buf: [0; MAX_PANIC_MESSAGE_LEN],
+    len: 0,
+});
+
+struct PanicMessage {
+    buf: [u8; MAX_PANIC_MESSAGE_LEN],
+    len: usize,
+}
+
+impl PanicMessage {
+    #[inline]
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl Write for PanicMessage {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let mut len = s.len().min(self.buf.len() - self.len);
+
+        // Truncate on a char boundary, so the message stays valid utf-8
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+
+        if len < s.len() {
+            Err(std::fmt::Error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// What the hook installed by [`install_panic_hook`] does once it has
+/// recorded the panic, and run the previous hook
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PanicAction {
+    /// Aborts the process, which the crash handler, if one is installed,
+    /// handles as any other crash, so the minidump is requested as usual
+    Abort,
+    /// Requests an on-demand dump, see [`Client::request_on_demand_dump`],
+    /// after which the panic continues as normal, ie. it either unwinds, or
+    /// aborts if the panic strategy is `abort`
+    Dump,
+    /// Only records the panic, and sends it to the server if configured to,
+    /// after which the panic continues as normal
+    Continue,
+}
+
+/// Configures the hook installed by [`install_panic_hook`]
+#[derive(Copy, Clone, Debug)]
+pub struct PanicPolicy {
+    pub(crate) action: PanicAction,
+    pub(crate) message_kind: Option<u32>,
+}
+
+impl PanicPolicy {
+    /// Performs the specified action after each panic, without sending the
+    /// panic message to the server
+    #[inline]
+    pub fn new(action: PanicAction) -> Self {
+        Self {
+            action,
+            message_kind: None,
+        }
+    }
+
+    /// Sends the panic message to the server as a user message of the
+    /// specified kind, see [`Client::send_message`], before the action is
+    /// performed. The message is the panic's location and payload, formatted
+    /// as the default hook prints them, truncated to
+    /// [`MAX_PANIC_MESSAGE_LEN`].
+    #[inline]
+    pub fn send_message(mut self, kind: u32) -> Self {
+        self.message_kind = Some(kind);
+        self
+    }
+}
+
+/// Installs a panic hook that records each panic, sends it to the server,
+/// and then either aborts, or requests an on-demand dump, as configured by
+/// the `policy`.
+///
+/// The hook that was previously installed, eg. the default one that prints
+/// the panic to `stderr`, is called after the panic has been recorded, and
+/// before the action is performed.
+///
+/// The panic is recorded into a buffer that is allocated up front, and
+/// retrieved via [`last_panic_message`], eg. to attach it to the report for
+/// the crash. The buffer is only ever waited on for a short time, so a panic
+/// while the hook is running, eg. in the previous hook on versions of Rust
+/// that call the hook again rather than aborting immediately, can't deadlock
+/// it, though it is then not recorded. A panic in a destructor while another
+/// panic unwinds is handled as any other, so with [`PanicAction::Dump`] it
+/// requests a second dump, before the process aborts.
+///
+/// If `client` is `None`, nothing is sent to the server, and
+/// [`PanicAction::Dump`] behaves as [`PanicAction::Continue`].
+///
+/// # Linux/Android
+///
+/// [`PanicAction::Dump`] requires that the server is allowed to `ptrace` this
+/// process for the duration of the hook, eg. by holding a
+/// `crash_handler::DumpableGuard` for as long as the hook is installed
+pub fn install_panic_hook(client: Option<Arc<Client>>, policy: PanicPolicy) {
+    let previous = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(mut message) = LAST_PANIC.try_lock_for(RECORD_TIMEOUT) {
+            message.len = 0;
+            // A truncated message is still recorded
+            let _res = write!(message, "{info}");
+
+            if let (Some(client), Some(kind)) = (&client, policy.message_kind) {
+                if let Err(err) = client.send_message(kind, message.as_bytes()) {
+                    log::error!("failed to send panic message: {err}");
+                }
+            }
+        }
+
+        previous(info);
+
+        match policy.action {
+            PanicAction::Abort => std::process::abort(),
+            PanicAction::Dump => {
+                if let Some(client) = &client {
+                    if let Err(err) = client.request_on_demand_dump() {
+                        log::error!("failed to request dump for panic: {err}");
+                    }
+                }
+            }
+            PanicAction::Continue => {}
+        }
+    }));
+}
+
+/// Retrieves the message of the last panic recorded by the hook installed via
+/// [`install_panic_hook`], if any
+pub fn last_panic_message() -> Option<String> {
+    let message = LAST_PANIC.lock();
+    (message.len > 0).then(|| String::from_utf8_lossy(message.as_bytes()).into_owned())
+}
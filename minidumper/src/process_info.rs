@@ -0,0 +1,392 @@
+//! Provides [`ProcessInfo`], the command line and environment of a crashed
+//! client, so that dumps can be triaged without correlating them with other
+//! logs
+
+#[cfg(feature = "minidump-writer")]
+use std::{fs::File, io};
+
+/// The breakpad stream type for the command line, as the nul separated
+/// arguments, ie. the same format as `/proc/<pid>/cmdline`
+pub const CMDLINE_STREAM_TYPE: u32 = 0x4767_0003;
+/// The breakpad stream type for the environment, as the nul separated
+/// `KEY=VALUE` pairs, ie. the same format as `/proc/<pid>/environ`
+pub const ENVIRON_STREAM_TYPE: u32 = 0x4767_0004;
+
+/// The largest payload sent by a client, see [`ProcessInfo::current_payload`].
+/// Servers accept a `HELLO` of this size regardless of their maximum message
+/// size, as the client can't know it
+pub(crate) const MAX_PAYLOAD_SIZE: usize = 64 * 1024;
+
+/// The command line and environment of a crashed client, captured by the
+/// [`crate::Server`] when the crash request is received, see
+/// [`crate::ServerOptions::capture_process_info`]
+///
+/// # Linux/Android
+///
+/// Read from `/proc/<pid>/cmdline` and `/proc/<pid>/environ`, so reflect any
+/// changes the process made to them.
+///
+/// # Windows/Macos
+///
+/// Sent by the client when it connects, from [`std::env::args_os`] and
+/// [`std::env::vars_os`], so don't reflect changes made to the environment
+/// after that. Strings that aren't valid UTF-8 are converted lossily.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// The command line, ie. the executable followed by its arguments
+    pub cmdline: Vec<String>,
+    /// The environment variables that are in the
+    /// [`crate::ServerOptions::allowed_env_vars`], in the order they appear in
+    /// the environment
+    pub environment: Vec<(String, String)>,
+}
+
+impl ProcessInfo {
+    /// Parses the nul separated command line and environment, only keeping
+    /// the environment variables that are allowed
+    pub(crate) fn parse(cmdline: &[u8], environ: &[u8], allowed: &[String]) -> Self {
+        let split = |buf: &[u8]| {
+            buf.split(|b| *b == 0)
+                .filter(|s| !s.is_empty())
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let environment = split(environ)
+            .into_iter()
+            .filter_map(|var| {
+                let (key, value) = var.split_once('=')?;
+
+                // Names are case insensitive on Windows
+                let is_allowed = allowed.iter().any(|name| {
+                    if cfg!(target_os = "windows") {
+                        name.eq_ignore_ascii_case(key)
+                    } else {
+                        name == key
+                    }
+                });
+
+                is_allowed.then(|| (key.to_owned(), value.to_owned()))
+            })
+            .collect();
+
+        Self {
+            cmdline: split(cmdline),
+            environment,
+        }
+    }
+
+    /// Reads the command line and environment of the process
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) fn read(pid: u32, allowed: &[String]) -> std::io::Result<Self> {
+        let cmdline = std::fs::read(format!("/proc/{pid}/cmdline"))?;
+
+        // Don't even read the environment if none of it would be kept
+        let environ = if allowed.is_empty() {
+            Vec::new()
+        } else {
+            std::fs::read(format!("/proc/{pid}/environ"))?
+        };
+
+        Ok(Self::parse(&cmdline, &environ, allowed))
+    }
+
+    /// The command line and environment of the current process, as sent by
+    /// the client after its protocol version in the `HELLO`, ie. the length of
+    /// the nul separated command line as 4 little endian bytes, followed by it,
+    /// followed by the nul separated environment.
+    ///
+    /// Arguments and variables that don't fit in [`MAX_PAYLOAD_SIZE`] are
+    /// omitted.
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    pub(crate) fn current_payload() -> Vec<u8> {
+        let mut payload = vec![0; 4];
+
+        let push = |payload: &mut Vec<u8>, entry: String| {
+            if payload.len() + entry.len() < MAX_PAYLOAD_SIZE {
+                payload.extend_from_slice(entry.as_bytes());
+                payload.push(0);
+            }
+        };
+
+        for arg in std::env::args_os() {
+            push(&mut payload, arg.to_string_lossy().into_owned());
+        }
+
+        let cmdline_len = (payload.len() - 4) as u32;
+        payload[..4].copy_from_slice(&cmdline_len.to_le_bytes());
+
+        for (key, value) in std::env::vars_os() {
+            push(
+                &mut payload,
+                format!("{}={}", key.to_string_lossy(), value.to_string_lossy()),
+            );
+        }
+
+        payload
+    }
+
+    /// Parses the payload sent by the client, see [`Self::current_payload`]
+    #[cfg(any(target_os = "windows", target_os = "macos"))]
+    pub(crate) fn from_payload(payload: &[u8], allowed: &[String]) -> Option<Self> {
+        let len = u32::from_le_bytes(payload.get(..4)?.try_into().ok()?) as usize;
+        let cmdline = payload.get(4..4 + len)?;
+        let environ = &payload[4 + len..];
+
+        Some(Self::parse(cmdline, environ, allowed))
+    }
+
+    /// The contents of the [`CMDLINE_STREAM_TYPE`] stream
+    #[cfg(feature = "minidump-writer")]
+    fn cmdline_stream(&self) -> Vec<u8> {
+        let mut stream = Vec::new();
+        for arg in &self.cmdline {
+            stream.extend_from_slice(arg.as_bytes());
+            stream.push(0);
+        }
+        stream
+    }
+
+    /// The contents of the [`ENVIRON_STREAM_TYPE`] stream
+    #[cfg(feature = "minidump-writer")]
+    fn environ_stream(&self) -> Vec<u8> {
+        let mut stream = Vec::new();
+        for (key, value) in &self.environment {
+            stream.extend_from_slice(key.as_bytes());
+            stream.push(b'=');
+            stream.extend_from_slice(value.as_bytes());
+            stream.push(0);
+        }
+        stream
+    }
+
+    /// The command line and environment, as the [`CMDLINE_STREAM_TYPE`] and
+    /// [`ENVIRON_STREAM_TYPE`] streams to add to a minidump, see
+    /// [`embed_streams`]
+    #[cfg(feature = "minidump-writer")]
+    pub(crate) fn streams(&self) -> [(u32, Vec<u8>); 2] {
+        [
+            (CMDLINE_STREAM_TYPE, self.cmdline_stream()),
+            (ENVIRON_STREAM_TYPE, self.environ_stream()),
+        ]
+    }
+}
+
+/// Adds streams to a minidump that has been written, eg. the command line and
+/// environment, see [`ProcessInfo::streams`].
+///
+/// Streams of the same types that are already in the minidump, eg. the
+/// environment written on Linux, which contains every variable, are replaced,
+/// and their contents zeroed, so that only the allowed environment variables
+/// remain in the minidump.
+///
+/// If the contents of the minidump are not provided, they are read back from
+/// the file, which must therefore be readable.
+#[cfg(feature = "minidump-writer")]
+pub(crate) fn embed_streams(
+    file: &mut File,
+    contents: Option<&mut Vec<u8>>,
+    streams: &[(u32, Vec<u8>)],
+) -> io::Result<()> {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    let mut read = Vec::new();
+    let contents = if let Some(contents) = contents {
+        contents
+    } else {
+        file.seek(SeekFrom::Start(0))?;
+        // There is only the handle, not a path that could be read instead
+        #[allow(clippy::verbose_file_reads)]
+        file.read_to_end(&mut read)?;
+        &mut read
+    };
+
+    let original_len = contents.len();
+    let patches = add_streams(contents, streams)?;
+
+    for (offset, len) in patches {
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.write_all(&contents[offset..offset + len])?;
+    }
+
+    file.seek(SeekFrom::Start(original_len as u64))?;
+    file.write_all(&contents[original_len..])?;
+    file.flush()
+}
+
+/// Appends the streams to the minidump, along with a new stream directory
+/// that replaces the old one, returning the ranges of the original minidump
+/// that were modified
+#[cfg(feature = "minidump-writer")]
+fn add_streams(dump: &mut Vec<u8>, streams: &[(u32, Vec<u8>)]) -> io::Result<Vec<(usize, usize)>> {
+    const SIGNATURE: u32 = 0x504d_444d; // MDMP
+    const HEADER_SIZE: usize = 32;
+    const DIRECTORY_ENTRY_SIZE: usize = 12;
+
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid minidump");
+    let read_u32 = |dump: &[u8], offset: usize| {
+        dump.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+            .ok_or_else(invalid)
+    };
+
+    if dump.len() < HEADER_SIZE || read_u32(dump, 0)? != SIGNATURE {
+        return Err(invalid());
+    }
+
+    let count = read_u32(dump, 8)? as usize;
+    let directory = read_u32(dump, 12)? as usize;
+
+    let mut entries = Vec::with_capacity(count + streams.len());
+    let mut patches = Vec::new();
+
+    for i in 0..count {
+        let offset = directory + i * DIRECTORY_ENTRY_SIZE;
+        let kind = read_u32(dump, offset)?;
+        let size = read_u32(dump, offset + 4)? as usize;
+        let rva = read_u32(dump, offset + 8)? as usize;
+
+        if streams.iter().any(|(replaced, _)| *replaced == kind) {
+            dump.get_mut(rva..rva + size).ok_or_else(invalid)?.fill(0);
+            patches.push((rva, size));
+        } else {
+            entries.push((kind, size as u32, rva as u32));
+        }
+    }
+
+    let to_rva = |len: usize| u32::try_from(len).map_err(|_err| invalid());
+
+    for (kind, stream) in streams {
+        // Streams are 4 byte aligned
+        dump.resize((dump.len() + 3) & !3, 0);
+        entries.push((*kind, stream.len() as u32, to_rva(dump.len())?));
+        dump.extend_from_slice(stream);
+    }
+
+    dump.resize((dump.len() + 3) & !3, 0);
+    let directory = to_rva(dump.len())?;
+
+    for (kind, size, rva) in &entries {
+        dump.extend_from_slice(&kind.to_le_bytes());
+        dump.extend_from_slice(&size.to_le_bytes());
+        dump.extend_from_slice(&rva.to_le_bytes());
+    }
+
+    dump[8..12].copy_from_slice(&(entries.len() as u32).to_le_bytes());
+    dump[12..16].copy_from_slice(&directory.to_le_bytes());
+    patches.push((8, 8));
+
+    Ok(patches)
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProcessInfo;
+
+    #[test]
+    fn filters_environment() {
+        let info = ProcessInfo::parse(
+            b"/usr/bin/game\0--level\0forest\0",
+            b"HOME=/home/user\0DEPLOYMENT=staging\0SECRET=hunter2\0NOT_A_VAR\0",
+            &["DEPLOYMENT".to_owned(), "MISSING".to_owned()],
+        );
+
+        assert_eq!(info.cmdline, ["/usr/bin/game", "--level", "forest"]);
+        assert_eq!(
+            info.environment,
+            [("DEPLOYMENT".to_owned(), "staging".to_owned())]
+        );
+
+        let none = ProcessInfo::parse(b"game", b"DEPLOYMENT=staging\0", &[]);
+        assert_eq!(none.cmdline, ["game"]);
+        assert!(none.environment.is_empty());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn reads_own_process() {
+        let allowed = ["CARGO_PKG_NAME".to_owned()];
+        let info = ProcessInfo::read(std::process::id(), &allowed).unwrap();
+
+        assert_eq!(info.cmdline, std::env::args().collect::<Vec<_>>());
+        assert_eq!(
+            info.environment,
+            std::env::var("CARGO_PKG_NAME")
+                .map(|name| vec![(allowed[0].clone(), name)])
+                .unwrap_or_default()
+        );
+
+        // Nothing is read for processes that don't exist
+        assert!(ProcessInfo::read(u32::MAX, &allowed).is_err());
+    }
+
+    #[cfg(feature = "minidump-writer")]
+    #[test]
+    fn replaces_streams() {
+        let read_u32 = |dump: &[u8], offset: usize| {
+            u32::from_le_bytes(dump[offset..offset + 4].try_into().unwrap()) as usize
+        };
+
+        // A header, the data of 2 streams, one of which is replaced, and the
+        // directory
+        let mut dump = Vec::new();
+        dump.extend_from_slice(&0x504d_444du32.to_le_bytes());
+        dump.extend_from_slice(&0xa793u32.to_le_bytes());
+        dump.extend_from_slice(&2u32.to_le_bytes());
+        dump.extend_from_slice(&40u32.to_le_bytes());
+        dump.resize(32, 0);
+        dump.extend_from_slice(b"KEEP");
+        dump.extend_from_slice(b"GONE");
+        for (kind, rva) in [(3u32, 32u32), (super::ENVIRON_STREAM_TYPE, 36)] {
+            dump.extend_from_slice(&kind.to_le_bytes());
+            dump.extend_from_slice(&4u32.to_le_bytes());
+            dump.extend_from_slice(&rva.to_le_bytes());
+        }
+
+        let original = dump.clone();
+
+        let info = ProcessInfo {
+            cmdline: vec!["game".to_owned(), "-x".to_owned()],
+            environment: vec![("DEPLOYMENT".to_owned(), "prod".to_owned())],
+        };
+
+        let patches = super::add_streams(
+            &mut dump,
+            &[
+                (super::CMDLINE_STREAM_TYPE, info.cmdline_stream()),
+                (super::ENVIRON_STREAM_TYPE, info.environ_stream()),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(patches, [(36, 4), (8, 8)]);
+        assert_eq!(&dump[36..40], &[0; 4]);
+
+        let count = read_u32(&dump, 8);
+        let directory = read_u32(&dump, 12);
+        assert_eq!(count, 3);
+        assert!(directory >= original.len());
+
+        let streams: Vec<_> = (0..count)
+            .map(|i| {
+                let entry = directory + i * 12;
+                let rva = read_u32(&dump, entry + 8);
+                (
+                    read_u32(&dump, entry) as u32,
+                    &dump[rva..rva + read_u32(&dump, entry + 4)],
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            streams,
+            [
+                (3, &b"KEEP"[..]),
+                (super::CMDLINE_STREAM_TYPE, &b"game\0-x\0"[..]),
+                (super::ENVIRON_STREAM_TYPE, &b"DEPLOYMENT=prod\0"[..]),
+            ]
+        );
+
+        assert!(super::add_streams(&mut vec![0; 32], &[]).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! Typed messages, sent with [`crate::Client::send_typed`] and received via a
+//! [`TypedHandler`], for when the raw bytes of [`crate::Client::send_message`]
+//! would otherwise need to be (de)serialized by hand.
+//!
+//! # Wire format
+//!
+//! A typed message is an ordinary user message, whose payload is a single
+//! byte containing [`FORMAT_VERSION`], followed by the value encoded with
+//! [bincode](https://docs.rs/bincode/1) 1.x, configured with
+//!
+//! - fixed width integers, rather than bincode's default variable length ones
+//! - little endian byte order
+//! - trailing bytes after the value rejected
+//! - no size limit, other than [`crate::ServerOptions::max_message_size`]
+//!
+//! The version is bumped whenever any of the above changes, and a payload with
+//! a version other than the one this crate was built with fails to decode
+//! with [`DecodeError::UnsupportedVersion`], so that a client and server built
+//! against different versions of this crate fail loudly rather than decoding
+//! garbage.
+//!
+//! The version only covers the encoding, not the type being encoded, so
+//! changing the type sent for a `kind` in an incompatible way should be done
+//! by sending it with a new `kind` instead.
+
+use crate::ClientId;
+use bincode::Options as _;
+
+/// The version of the wire format prepended to every typed message
+pub const FORMAT_VERSION: u8 = 1;
+
+/// The reason a typed message could not be decoded
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    /// The message was empty, so was not sent as a typed message
+    #[error("typed message is empty")]
+    Empty,
+    /// The message was encoded with a different version of the wire format
+    #[error("typed message has format version {0}, expected {FORMAT_VERSION}")]
+    UnsupportedVersion(u8),
+    /// The message body could not be decoded as the expected type
+    #[error("failed to decode typed message")]
+    Invalid(#[source] bincode::Error),
+}
+
+#[inline]
+fn options() -> impl bincode::Options + Copy {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_little_endian()
+        .reject_trailing_bytes()
+}
+
+/// Encodes a value as a typed message payload
+pub(crate) fn encode<T: serde::Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, crate::Error> {
+    let options = options();
+    let size = options
+        .serialized_size(value)
+        .map_err(crate::Error::Encode)?;
+
+    let mut buf = Vec::with_capacity(size as usize + 1);
+    buf.push(FORMAT_VERSION);
+    options
+        .serialize_into(&mut buf, value)
+        .map_err(crate::Error::Encode)?;
+
+    Ok(buf)
+}
+
+/// Decodes a typed message payload, as sent by [`crate::Client::send_typed`]
+/// or [`crate::ServerHandle::send_typed`]
+///
+/// # Errors
+///
+/// The payload is not a typed message of the current [`FORMAT_VERSION`], or
+/// does not contain exactly one `T`
+pub fn decode<T: serde::de::DeserializeOwned>(buffer: &[u8]) -> Result<T, DecodeError> {
+    let (version, body) = buffer.split_first().ok_or(DecodeError::Empty)?;
+
+    if *version != FORMAT_VERSION {
+        return Err(DecodeError::UnsupportedVersion(*version));
+    }
+
+    options().deserialize(body).map_err(DecodeError::Invalid)
+}
+
+/// Decodes user messages as a caller chosen type, typically an enum of every
+/// message the client can send, and reports messages that can't be decoded
+/// rather than panicking.
+///
+/// Implementors call [`Self::dispatch`] from
+/// [`crate::ServerHandler::on_client_message`].
+pub trait TypedHandler<T: serde::de::DeserializeOwned> {
+    /// Called with each user message that was successfully decoded
+    fn on_typed_message(&self, client: ClientId, kind: u32, message: T);
+    /// Called with each user message that could not be decoded, eg. because
+    /// the client sent a different type, or was built against a different
+    /// version of this crate
+    fn on_decode_error(&self, client: ClientId, kind: u32, error: DecodeError);
+
+    /// Decodes the message, and calls [`Self::on_typed_message`] or
+    /// [`Self::on_decode_error`] depending on the result
+    fn dispatch(&self, client: ClientId, kind: u32, buffer: &[u8]) {
+        match decode(buffer) {
+            Ok(message) => self.on_typed_message(client, kind, message),
+            Err(err) => self.on_decode_error(client, kind, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Message {
+        Progress { done: u32, total: u32 },
+        Log(String),
+        Quit,
+    }
+
+    #[test]
+    fn roundtrips() {
+        for msg in [
+            Message::Progress { done: 3, total: 9 },
+            Message::Log("hello".to_owned()),
+            Message::Quit,
+        ] {
+            let buf = encode(&msg).unwrap();
+            assert_eq!(buf[0], FORMAT_VERSION);
+            assert_eq!(decode::<Message>(&buf).unwrap(), msg);
+        }
+    }
+
+    /// The encoding is part of the wire format, so must not change without
+    /// bumping [`FORMAT_VERSION`]
+    #[test]
+    fn fixed_encoding() {
+        assert_eq!(
+            encode(&Message::Progress { done: 3, total: 9 }).unwrap(),
+            [FORMAT_VERSION, 0, 0, 0, 0, 3, 0, 0, 0, 9, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut buf = encode(&Message::Quit).unwrap();
+        buf[0] = FORMAT_VERSION + 1;
+
+        assert!(matches!(
+            decode::<Message>(&buf),
+            Err(DecodeError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+        assert!(matches!(decode::<Message>(&[]), Err(DecodeError::Empty)));
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        let mut buf = encode(&Message::Quit).unwrap();
+        buf.push(0);
+        assert!(matches!(
+            decode::<Message>(&buf),
+            Err(DecodeError::Invalid(_))
+        ));
+
+        let buf = encode(&Message::Log("truncated".to_owned())).unwrap();
+        assert!(matches!(
+            decode::<Message>(&buf[..buf.len() - 1]),
+            Err(DecodeError::Invalid(_))
+        ));
+
+        assert!(matches!(
+            decode::<Message>(&[FORMAT_VERSION, 9, 0, 0, 0]),
+            Err(DecodeError::Invalid(_))
+        ));
+    }
+
+    struct Handler {
+        decoded: std::cell::RefCell<Vec<Message>>,
+        errors: std::cell::Cell<usize>,
+    }
+
+    impl TypedHandler<Message> for Handler {
+        fn on_typed_message(&self, _client: ClientId, _kind: u32, message: Message) {
+            self.decoded.borrow_mut().push(message);
+        }
+
+        fn on_decode_error(&self, _client: ClientId, _kind: u32, _error: DecodeError) {
+            self.errors.set(self.errors.get() + 1);
+        }
+    }
+
+    #[test]
+    fn dispatches() {
+        let handler = Handler {
+            decoded: Default::default(),
+            errors: Default::default(),
+        };
+        let client = ClientId(1);
+
+        handler.dispatch(client, 0, &encode(&Message::Quit).unwrap());
+        handler.dispatch(client, 0, &encode(&"not a message").unwrap());
+        handler.dispatch(client, 0, b"raw bytes");
+
+        assert_eq!(*handler.decoded.borrow(), [Message::Quit]);
+        assert_eq!(handler.errors.get(), 2);
+    }
+}
@@ -0,0 +1,118 @@
+//! Tests the C API for the client
+
+#![allow(unsafe_code)]
+
+use minidumper::capi;
+use std::sync::{atomic, Arc};
+
+#[test]
+fn capi_messages() {
+    let name = "capi_messages";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    type Messages = Arc<parking_lot::Mutex<Vec<(u32, Vec<u8>)>>>;
+
+    struct Server {
+        messages: Messages,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+            self.messages.lock().push((kind, buffer));
+        }
+    }
+
+    let messages = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+    let server_handler = Server {
+        messages: messages.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    unsafe {
+        assert!(capi::minidumper_client_connect(std::ptr::null()).is_null());
+        assert!(capi::minidumper_client_connect(c"capi_nope".as_ptr()).is_null());
+
+        let c_name = std::ffi::CString::new(name).unwrap();
+        let client = capi::minidumper_client_connect(c_name.as_ptr());
+        assert!(!client.is_null());
+
+        assert_eq!(
+            capi::minidumper_client_send_message(std::ptr::null(), 0, std::ptr::null(), 0),
+            capi::MINIDUMPER_ERR_INVALID_ARGUMENT
+        );
+        assert_eq!(
+            capi::minidumper_client_send_message(client, 0, std::ptr::null(), 1),
+            capi::MINIDUMPER_ERR_INVALID_ARGUMENT
+        );
+        assert_eq!(
+            capi::minidumper_client_request_dump(client, std::ptr::null()),
+            capi::MINIDUMPER_ERR_INVALID_ARGUMENT
+        );
+
+        // Send from several threads at once, as a C caller might
+        let client_addr = client as usize;
+        let senders: Vec<_> = (0..4u32)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    let msg = format!("msg #{i}");
+                    capi::minidumper_client_send_message(
+                        client_addr as *const minidumper::Client,
+                        i,
+                        msg.as_ptr(),
+                        msg.len(),
+                    )
+                })
+            })
+            .collect();
+
+        for sender in senders {
+            assert_eq!(sender.join().unwrap(), capi::MINIDUMPER_OK);
+        }
+
+        assert_eq!(
+            capi::minidumper_client_send_message(client, 4, std::ptr::null(), 0),
+            capi::MINIDUMPER_OK
+        );
+
+        capi::minidumper_client_destroy(client);
+        capi::minidumper_client_destroy(std::ptr::null_mut());
+    }
+
+    // Wait for the server to receive the messages before shutting it down
+    let start = std::time::Instant::now();
+    while messages.lock().len() < 5 {
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    let mut messages = messages.lock();
+    messages.sort_by_key(|(kind, _)| *kind);
+    assert_eq!(messages.len(), 5);
+    for (i, (kind, msg)) in (0..4).zip(messages.iter()) {
+        assert_eq!(i, *kind);
+        assert_eq!(format!("msg #{i}").as_bytes(), msg.as_slice());
+    }
+    assert!(messages[4].1.is_empty());
+}
@@ -0,0 +1,100 @@
+//! Verifies that crash contexts are passed to the handler as is when the
+//! `minidump-writer` feature is disabled
+
+#![cfg(not(feature = "minidump-writer"))]
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
+
+#[test]
+fn forwards_crash_context() {
+    let name = "forwards_crash_context";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        thread_id: Arc<AtomicU64>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn on_crash_context(
+            &self,
+            crash_context: &crash_context::CrashContext,
+            metadata: &minidumper::DumpMetadata,
+        ) -> minidumper::LoopAction {
+            cfg_if::cfg_if! {
+                if #[cfg(any(target_os = "linux", target_os = "android"))] {
+                    assert_eq!(crash_context.pid as u32, std::process::id());
+                } else if #[cfg(target_os = "windows")] {
+                    assert_eq!(crash_context.process_id, std::process::id());
+                }
+            }
+
+            assert_eq!(metadata.thread_id, crash_context.crashing_thread_id());
+            assert_eq!(metadata.is_main_thread, crash_context.is_main_thread());
+            assert_eq!(
+                metadata
+                    .breadcrumbs
+                    .iter()
+                    .map(|crumb| (crumb.kind, crumb.data.as_slice()))
+                    .collect::<Vec<_>>(),
+                [(1, &b"started"[..]), (2, &b"about to crash"[..])]
+            );
+            self.thread_id.store(metadata.thread_id, Ordering::Relaxed);
+
+            minidumper::LoopAction::Exit
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+    }
+
+    let thread_id = Arc::new(AtomicU64::new(0));
+
+    let server_handler = Server {
+        thread_id: thread_id.clone(),
+    };
+
+    let shutdown = AtomicBool::new(false);
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &shutdown, None));
+
+    let client = minidumper::Client::with_name(name).unwrap();
+
+    let breadcrumbs = client.create_breadcrumbs(8).unwrap();
+    breadcrumbs.record(1, "started");
+
+    static DUMPED: AtomicBool = AtomicBool::new(false);
+
+    let handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(move |cc: &ch::CrashContext| {
+            let dumped = client.request_dump(cc).is_ok();
+            DUMPED.store(dumped, Ordering::Relaxed);
+            ch::CrashEventResult::Handled(dumped)
+        })
+    })
+    .unwrap();
+
+    breadcrumbs.record(2, "about to crash");
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            handler.simulate_signal(libc::SIGUSR2 as u32);
+        } else {
+            handler.simulate_exception(None);
+        }
+    }
+
+    handler.detach().unwrap();
+
+    // The handler exits the loop once it has the crash context
+    server_loop.join().unwrap().unwrap();
+
+    assert!(DUMPED.load(Ordering::Relaxed), "the dump request failed");
+    assert_ne!(thread_id.load(Ordering::Relaxed), 0);
+}
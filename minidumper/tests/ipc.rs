@@ -105,7 +105,14 @@ fn inactive_reap() {
             });
         }
 
-        fn on_client_disconnected(&self, num_clients: usize) -> minidumper::LoopAction {
+        fn on_client_disconnected(
+            &self,
+            _client: minidumper::ClientId,
+            reason: minidumper::DisconnectReason,
+            num_clients: usize,
+        ) -> minidumper::LoopAction {
+            assert!(matches!(reason, minidumper::DisconnectReason::Stale(_)));
+
             self.messages.lock().push(Message {
                 msg: format!("num_clients = {num_clients}"),
             });
@@ -190,7 +197,12 @@ fn ping() {
             panic!("should not be called");
         }
 
-        fn on_client_disconnected(&self, num_clients: usize) -> minidumper::LoopAction {
+        fn on_client_disconnected(
+            &self,
+            _client: minidumper::ClientId,
+            _reason: minidumper::DisconnectReason,
+            num_clients: usize,
+        ) -> minidumper::LoopAction {
             if num_clients == 0 {
                 minidumper::LoopAction::Exit
             } else {
@@ -231,3 +243,2160 @@ fn ping() {
 
     assert!(client.ping().is_err(), "server should be gone");
 }
+
+/// Tests that a client of the original protocol, which sends no `HELLO` and
+/// offsets its user kinds by 4, still has its messages delivered with the kinds
+/// it sent, rather than having them read as newer internal messages, eg. as a
+/// shutdown request
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn baseline_client() {
+    let name = format!("baseline_client_{}", std::process::id());
+
+    let mut server = minidumper::Server::with_name_and_options(
+        name.as_str(),
+        minidumper::ServerOptions::default().accept_shutdown_requests(true),
+    )
+    .unwrap();
+
+    struct Server {
+        messages: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+            let msg = String::from_utf8(buffer).unwrap();
+            self.messages.lock().unwrap().push(format!("{kind}: {msg}"));
+        }
+    }
+
+    let messages = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let server_handler = Server {
+        messages: messages.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let exit = shutdown.clone();
+    let server_loop = std::thread::spawn(move || server.run(Box::new(server_handler), &exit, None));
+
+    let addr = uds::UnixSocketAddr::from_abstract(name.as_bytes()).unwrap();
+    let conn = uds::UnixSeqpacketConn::connect_unix_addr(&addr).unwrap();
+
+    // A header is the kind followed by the payload size
+    let message = |kind: u32, payload: &[u8]| {
+        [
+            &kind.to_ne_bytes()[..],
+            &(payload.len() as u32).to_ne_bytes(),
+            payload,
+        ]
+        .concat()
+    };
+
+    for kind in 0..4u32 {
+        conn.send(&message(kind + 4, format!("kind {kind}").as_bytes()))
+            .unwrap();
+    }
+
+    // Messages are handled in order, so once the ping is answered, so are they
+    conn.send(&message(2, &[])).unwrap();
+    let mut pong = [0u8; 16];
+    assert_eq!(conn.recv(&mut pong).unwrap(), 8);
+    assert_eq!(&pong[..8], message(3, &[]));
+
+    assert_eq!(
+        *messages.lock().unwrap(),
+        (0..4u32)
+            .map(|kind| format!("{kind}: kind {kind}"))
+            .collect::<Vec<_>>()
+    );
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+}
+
+/// Tests that a malformed crash request, or one for another process, only
+/// disconnects the client that sent it, rather than stopping the server
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn invalid_crash_request() {
+    let name = format!("invalid_crash_request_{}", std::process::id());
+
+    let mut server = minidumper::Server::with_name(name.as_str()).unwrap();
+
+    struct Server {
+        disconnects: Arc<parking_lot::Mutex<Vec<minidumper::DisconnectReason>>>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_client_disconnected(
+            &self,
+            _client: minidumper::ClientId,
+            reason: minidumper::DisconnectReason,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.disconnects.lock().push(reason);
+            minidumper::LoopAction::Continue
+        }
+    }
+
+    let disconnects = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    let server_handler = Server {
+        disconnects: disconnects.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let exit = shutdown.clone();
+    let server_loop = std::thread::spawn(move || server.run(Box::new(server_handler), &exit, None));
+
+    let addr = uds::UnixSocketAddr::from_abstract(name.as_bytes()).unwrap();
+
+    // A header is the kind followed by the payload size
+    let crash = |payload: &[u8]| {
+        [
+            &0u32.to_ne_bytes()[..],
+            &(payload.len() as u32).to_ne_bytes(),
+            payload,
+        ]
+        .concat()
+    };
+
+    // A truncated crash context, and one for pid 0 rather than this process
+    for payload in [
+        vec![0u8; 3],
+        vec![0u8; std::mem::size_of::<crash_context::CrashContext>()],
+    ] {
+        let conn = uds::UnixSeqpacketConn::connect_unix_addr(&addr).unwrap();
+        conn.send(&crash(&payload)).unwrap();
+
+        // The server closes the connection
+        let mut buf = [0u8; 16];
+        assert_eq!(conn.recv(&mut buf).unwrap(), 0);
+    }
+
+    // The server is still running
+    let client = minidumper::Client::with_name(name.as_str()).unwrap();
+    client.ping().unwrap();
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    assert_eq!(
+        *disconnects.lock(),
+        [minidumper::DisconnectReason::Errored(std::io::ErrorKind::InvalidData); 2]
+    );
+}
+
+/// Tests that the server reports why each client was disconnected, including
+/// each client that is reaped for being stale
+#[test]
+fn disconnect_reasons() {
+    let name = "disconnect_reasons";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        disconnects:
+            Arc<parking_lot::Mutex<Vec<(minidumper::ClientId, minidumper::DisconnectReason)>>>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_client_disconnected(
+            &self,
+            client: minidumper::ClientId,
+            reason: minidumper::DisconnectReason,
+            num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.disconnects.lock().push((client, reason));
+
+            if num_clients == 0 {
+                minidumper::LoopAction::Exit
+            } else {
+                minidumper::LoopAction::Continue
+            }
+        }
+    }
+
+    let disconnects = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    let handle = server.handle();
+
+    let server_handler = Server {
+        disconnects: disconnects.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let server_loop = std::thread::spawn(move || {
+        server.run(
+            Box::new(server_handler),
+            &shutdown,
+            Some(std::time::Duration::from_millis(200)),
+        )
+    });
+
+    let clients: Vec<_> = (0..3)
+        .map(|_| minidumper::Client::with_name(name).unwrap())
+        .collect();
+
+    let start = std::time::Instant::now();
+    let mut ids = loop {
+        let ids = handle.clients();
+        if ids.len() == clients.len() {
+            break ids;
+        }
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    };
+
+    // Close one client, and let the others go stale
+    let mut clients = clients.into_iter();
+    drop(clients.next());
+
+    server_loop.join().unwrap().unwrap();
+
+    let mut disconnects = disconnects.lock().clone();
+    assert_eq!(disconnects.len(), 3);
+    assert_eq!(disconnects[0].1, minidumper::DisconnectReason::Closed);
+    assert!(disconnects[1..]
+        .iter()
+        .all(|(_, reason)| matches!(reason, minidumper::DisconnectReason::Stale(elapsed) if *elapsed >= std::time::Duration::from_millis(200))));
+
+    // Each client is reported exactly once
+    disconnects.sort_by_key(|(id, _)| *id);
+    ids.sort();
+    assert!(disconnects.iter().map(|(id, _)| *id).eq(ids));
+}
+
+/// Tests that the options passed to `run_with_options` are applied by the
+/// server loop, rather than the ones the server was created with
+#[test]
+fn run_options() {
+    struct Server {
+        messages: Arc<atomic::AtomicUsize>,
+        disconnects: Arc<parking_lot::Mutex<Vec<minidumper::DisconnectReason>>>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            self.messages.fetch_add(1, atomic::Ordering::Relaxed);
+        }
+
+        fn on_client_disconnected(
+            &self,
+            _client: minidumper::ClientId,
+            reason: minidumper::DisconnectReason,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.disconnects.lock().push(reason);
+            minidumper::LoopAction::Continue
+        }
+    }
+
+    struct Running {
+        handle: minidumper::ServerHandle,
+        messages: Arc<atomic::AtomicUsize>,
+        disconnects: Arc<parking_lot::Mutex<Vec<minidumper::DisconnectReason>>>,
+        shutdown: Arc<atomic::AtomicBool>,
+        server_loop: std::thread::JoinHandle<Result<(), minidumper::Error>>,
+    }
+
+    impl Running {
+        fn stop(self) -> std::time::Duration {
+            self.shutdown.store(true, atomic::Ordering::Relaxed);
+            let start = std::time::Instant::now();
+            self.server_loop.join().unwrap().unwrap();
+            start.elapsed()
+        }
+    }
+
+    let run = |name: &str, options: minidumper::ServerOptions| {
+        let mut server = minidumper::Server::with_name(name).unwrap();
+        let handle = server.handle();
+
+        let messages = Arc::new(atomic::AtomicUsize::new(0));
+        let disconnects = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let server_handler = Server {
+            messages: messages.clone(),
+            disconnects: disconnects.clone(),
+        };
+
+        let shutdown = Arc::new(atomic::AtomicBool::new(false));
+        let is_shutdown = shutdown.clone();
+        let server_loop = std::thread::spawn(move || {
+            server.run_with_options(Box::new(server_handler), &is_shutdown, options)
+        });
+
+        Running {
+            handle,
+            messages,
+            disconnects,
+            shutdown,
+            server_loop,
+        }
+    };
+
+    let wait_for = |what: &dyn Fn() -> bool| {
+        let start = std::time::Instant::now();
+        while !what() {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    };
+
+    // Connections past the maximum are closed as soon as they are accepted
+    {
+        let running = run(
+            "run_options_max_clients",
+            minidumper::ServerOptions::default().max_clients(Some(2)),
+        );
+
+        let clients: Vec<_> = (0..3)
+            .map(|_| minidumper::Client::with_name("run_options_max_clients").unwrap())
+            .collect();
+
+        wait_for(&|| running.handle.stats().rejected_connections == 1);
+        assert_eq!(running.handle.clients().len(), 2);
+        assert_eq!(
+            clients
+                .iter()
+                .filter(|client| client.ping().is_ok())
+                .count(),
+            2
+        );
+
+        running.stop();
+    }
+
+    // The maximum message size overrides the one provided by the handler
+    {
+        let running = run(
+            "run_options_max_message_size",
+            minidumper::ServerOptions::default().max_message_size(Some(16)),
+        );
+
+        let client = minidumper::Client::with_name("run_options_max_message_size").unwrap();
+        client.send_message(1, [0u8; 16]).unwrap();
+        wait_for(&|| running.messages.load(atomic::Ordering::Relaxed) == 1);
+
+        // Depending on timing, the send may or may not fail, but either way
+        // the server must disconnect the client without receiving the message
+        let _res = client.send_message(1, [0u8; 17]);
+        wait_for(&|| !running.disconnects.lock().is_empty());
+
+        assert_eq!(running.messages.load(atomic::Ordering::Relaxed), 1);
+        assert_eq!(
+            running.disconnects.lock()[0],
+            minidumper::DisconnectReason::Errored(std::io::ErrorKind::InvalidData)
+        );
+
+        running.stop();
+    }
+
+    // Connections go stale with the timeout passed to the run, not the
+    // server's own options, which don't have one
+    {
+        let running = run(
+            "run_options_stale_timeout",
+            minidumper::ServerOptions::default()
+                .stale_timeout(Some(std::time::Duration::from_millis(50))),
+        );
+
+        let _client = minidumper::Client::with_name("run_options_stale_timeout").unwrap();
+        wait_for(&|| !running.disconnects.lock().is_empty());
+
+        assert!(matches!(
+            running.disconnects.lock()[0],
+            minidumper::DisconnectReason::Stale(elapsed) if elapsed >= std::time::Duration::from_millis(50)
+        ));
+
+        running.stop();
+    }
+
+    // The shutdown flag is only checked once per poll interval
+    {
+        let running = run(
+            "run_options_poll_interval",
+            minidumper::ServerOptions::default()
+                .poll_interval(std::time::Duration::from_millis(500)),
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(running.stop() >= std::time::Duration::from_millis(200));
+    }
+}
+
+/// Tests that socket paths that can't be used are reported as an invalid name
+/// rather than as an opaque I/O error
+#[test]
+fn invalid_socket_path() {
+    let long_path = std::path::PathBuf::from("a".repeat(200));
+
+    assert!(matches!(
+        minidumper::Server::with_name(long_path.as_path()),
+        Err(minidumper::Error::InvalidName)
+    ));
+    assert!(matches!(
+        minidumper::Client::with_name(long_path.as_path()),
+        Err(minidumper::Error::InvalidName)
+    ));
+}
+
+/// Tests that a server can be created from a socket that was bound by someone
+/// else, eg. systemd socket activation
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn from_listener() {
+    use std::os::fd::{FromRawFd, IntoRawFd, OwnedFd};
+
+    let name = "from_listener";
+
+    let listener = uds::UnixSeqpacketListener::bind_unix_addr(
+        &uds::UnixSocketAddr::from_abstract(name).unwrap(),
+    )
+    .unwrap();
+    #[allow(unsafe_code)]
+    let listener = unsafe { OwnedFd::from_raw_fd(listener.into_raw_fd()) };
+
+    let mut server = minidumper::Server::from_listener(listener, None).unwrap();
+
+    struct Server {
+        received: Arc<atomic::AtomicBool>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, buffer: Vec<u8>) {
+            assert_eq!(buffer, b"inherited");
+            self.received.store(true, atomic::Ordering::Relaxed);
+        }
+    }
+
+    let received = Arc::new(atomic::AtomicBool::new(false));
+    let server_handler = Server {
+        received: received.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    let client = minidumper::Client::with_name(name).unwrap();
+    client.send_message(1, "inherited").unwrap();
+    client.ping().unwrap();
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    assert!(received.load(atomic::Ordering::Relaxed));
+
+    // Sockets that can't be served are rejected rather than failing later
+    let path = std::env::temp_dir().join(format!("from_listener_{}", std::process::id()));
+    let stream = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let err = minidumper::Server::from_listener(stream.into(), Some(path.clone()));
+    assert!(
+        matches!(err, Err(minidumper::Error::Io(ref err)) if err.kind() == std::io::ErrorKind::InvalidInput)
+    );
+    // The path is only owned by the server if it was actually created
+    assert!(path.exists());
+    std::fs::remove_file(path).unwrap();
+}
+
+/// Tests that a socket path left behind by a server that didn't clean it up,
+/// eg. because it was killed, doesn't prevent the next server from starting,
+/// while the path of a live server is left alone
+#[test]
+fn stale_socket_path() {
+    let path = std::env::temp_dir().join(format!("stale_socket_path_{}", std::process::id()));
+
+    drop(
+        minidumper::Server::with_name_and_options(
+            path.as_path(),
+            minidumper::ServerOptions::default().cleanup_socket_on_drop(false),
+        )
+        .unwrap(),
+    );
+    #[cfg(unix)]
+    assert!(path.exists());
+
+    let server = minidumper::Server::with_name(path.as_path()).unwrap();
+    minidumper::Client::with_name(path.as_path()).unwrap();
+
+    assert!(matches!(
+        minidumper::Server::with_name(path.as_path()),
+        Err(minidumper::Error::Io(ref err)) if err.kind() == std::io::ErrorKind::AddrInUse
+    ));
+    // The live server's path wasn't removed by the failed attempt
+    minidumper::Client::with_name(path.as_path()).unwrap();
+
+    drop(server);
+    #[cfg(unix)]
+    assert!(!path.exists());
+}
+
+/// Tests that std socket addresses can be used as names, and that abstract
+/// names are rejected at runtime on platforms that don't support them
+#[test]
+fn std_socket_names() {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(b"std_socket_names").unwrap();
+
+            let _server = minidumper::Server::with_name(&addr).unwrap();
+            let _client = minidumper::Client::with_name(&addr).unwrap();
+        } else {
+            let name = minidumper::SocketName::Abstract(b"std_socket_names");
+
+            assert!(matches!(
+                minidumper::Server::with_name(name),
+                Err(minidumper::Error::UnsupportedSocketName)
+            ));
+            assert!(matches!(
+                minidumper::Client::with_name(name),
+                Err(minidumper::Error::UnsupportedSocketName)
+            ));
+        }
+    }
+}
+
+/// Tests that clients can wait for a server that is not yet available, but
+/// give up immediately on names that can never be connected to
+#[test]
+fn connect_timeout() {
+    use std::time::{Duration, Instant};
+
+    let name = "connect_timeout";
+    let interval = Duration::from_millis(10);
+
+    let start = Instant::now();
+    assert!(matches!(
+        minidumper::Client::with_name_timeout(name, Duration::from_millis(100), interval),
+        Err(minidumper::Error::Io(_))
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    let long_path = std::path::PathBuf::from("a".repeat(200));
+    let start = Instant::now();
+    assert!(matches!(
+        minidumper::Client::with_name_timeout(
+            long_path.as_path(),
+            Duration::from_secs(60),
+            interval
+        ),
+        Err(minidumper::Error::InvalidName)
+    ));
+    assert!(start.elapsed() < Duration::from_secs(60));
+
+    struct Server;
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {}
+    }
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+
+        let mut server = minidumper::Server::with_name(name).unwrap();
+        server.run(Box::new(Server), &is_shutdown, None)
+    });
+
+    let client =
+        minidumper::Client::with_name_timeout(name, Duration::from_secs(10), interval).unwrap();
+    client.ping().unwrap();
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+}
+
+/// Tests that the client socket can be polled for messages from the server
+/// without interfering with replies to the client's own requests
+#[test]
+fn client_socket() {
+    let name = "client_socket";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server;
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {}
+    }
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop = std::thread::spawn(move || server.run(Box::new(Server), &is_shutdown, None));
+
+    let client = minidumper::Client::with_name(name).unwrap();
+
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::os::fd::AsRawFd;
+            assert!(client.as_raw_fd() >= 0);
+        } else if #[cfg(windows)] {
+            use std::os::windows::io::AsRawSocket;
+            assert_ne!(client.as_raw_socket(), !0);
+        }
+    }
+
+    // The pong is consumed by the ping, not left for the poll
+    client.ping().unwrap();
+    assert!(client.try_recv_server_message().unwrap().is_none());
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    // The server closing the connection is reported as an error
+    let start = std::time::Instant::now();
+    loop {
+        match client.try_recv_server_message() {
+            Ok(None) if start.elapsed() < std::time::Duration::from_secs(5) => {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            res => {
+                assert!(res.is_err());
+                break;
+            }
+        }
+    }
+}
+
+/// Tests that the server can push messages to clients, both in reply to a
+/// message and unprompted, without breaking the client's own requests
+#[test]
+fn server_messages() {
+    let name = "server_messages";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        handle: minidumper::ServerHandle,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_client_message(&self, client: minidumper::ClientId, kind: u32, buffer: Vec<u8>) {
+            // Echo the message back to the client that sent it
+            self.handle.send_to(client, kind + 1, &buffer);
+        }
+    }
+
+    let handle = server.handle();
+    let server_handler = Server {
+        handle: handle.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    let client = minidumper::Client::with_name(name).unwrap();
+
+    let recv = || {
+        let start = std::time::Instant::now();
+        loop {
+            if let Some(msg) = client.try_recv_server_message().unwrap() {
+                return msg;
+            }
+
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    };
+
+    client.send_message(1, "echo").unwrap();
+    assert_eq!(recv(), (2, b"echo".to_vec()));
+
+    while handle.clients().is_empty() {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    // Messages that arrive while waiting for a pong are kept for later
+    handle.broadcast(20, b"everyone");
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    client.ping().unwrap();
+    assert_eq!(recv(), (20, b"everyone".to_vec()));
+    assert!(client.try_recv_server_message().unwrap().is_none());
+
+    // Both ends sequence their messages once the client has pinged
+    for i in 0..10 {
+        client.send_message(i, "echo").unwrap();
+        assert_eq!(recv(), (i + 1, b"echo".to_vec()));
+    }
+    client.ping().unwrap();
+
+    assert_eq!(client.sequence_gaps(), 0);
+    assert_eq!(handle.stats().sequence_gaps, 0);
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    assert!(handle.clients().is_empty());
+}
+
+/// Tests that a burst of short-lived clients, more than fit in the listener
+/// backlog at once, are accepted promptly
+#[test]
+fn accept_storm() {
+    use std::time::{Duration, Instant};
+
+    const THREADS: usize = 16;
+    const CLIENTS: usize = 25;
+
+    let name = "accept_storm";
+
+    let mut server = minidumper::Server::with_name_and_options(
+        name,
+        minidumper::ServerOptions::default().listen_backlog(8),
+    )
+    .unwrap();
+
+    struct Server;
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+    }
+
+    let handle = server.handle();
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop = std::thread::spawn(move || server.run(Box::new(Server), &is_shutdown, None));
+
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            std::thread::spawn(move || {
+                (0..CLIENTS)
+                    .map(|_| {
+                        let start = Instant::now();
+                        // The connection can be refused while the backlog is
+                        // full, which counts towards the latency
+                        let client = minidumper::Client::with_name_timeout(
+                            name,
+                            Duration::from_secs(10),
+                            Duration::from_millis(1),
+                        )
+                        .unwrap();
+                        client.ping().unwrap();
+                        start.elapsed()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    let mut latencies: Vec<_> = threads
+        .into_iter()
+        .flat_map(|thread| thread.join().unwrap())
+        .collect();
+    latencies.sort();
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    let p99 = latencies[latencies.len() * 99 / 100];
+    assert!(
+        p99 < Duration::from_millis(250),
+        "p99 connect latency was {p99:?}"
+    );
+    assert_eq!(handle.stats().rejected_connections, 0);
+}
+
+/// Tests that messages from many clients that rapidly connect, send messages,
+/// and disconnect, are neither lost nor attributed to the wrong client
+#[test]
+fn connection_churn() {
+    let name = "connection_churn";
+
+    const THREADS: u32 = 8;
+    const CYCLES: u32 = 25;
+    const MESSAGES: u32 = 5;
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    #[derive(Debug)]
+    enum Event {
+        Connected,
+        Message(u32, String),
+        Disconnected(minidumper::DisconnectReason),
+    }
+
+    struct Server {
+        events: Arc<parking_lot::Mutex<Vec<(minidumper::ClientId, Event)>>>,
+        disconnected: Arc<atomic::AtomicU32>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_client_message(&self, client: minidumper::ClientId, kind: u32, buffer: Vec<u8>) {
+            self.events.lock().push((
+                client,
+                Event::Message(kind, String::from_utf8(buffer).unwrap()),
+            ));
+        }
+
+        fn on_client_connected(
+            &self,
+            client: minidumper::ClientId,
+            _peer: minidumper::PeerCredentials,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.events.lock().push((client, Event::Connected));
+            minidumper::LoopAction::Continue
+        }
+
+        fn on_client_disconnected(
+            &self,
+            client: minidumper::ClientId,
+            reason: minidumper::DisconnectReason,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.events
+                .lock()
+                .push((client, Event::Disconnected(reason)));
+            self.disconnected.fetch_add(1, atomic::Ordering::Relaxed);
+            minidumper::LoopAction::Continue
+        }
+    }
+
+    let events = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    let disconnected = Arc::new(atomic::AtomicU32::new(0));
+
+    let server_handler = Server {
+        events: events.clone(),
+        disconnected: disconnected.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    let threads: Vec<_> = (0..THREADS)
+        .map(|thread| {
+            std::thread::spawn(move || {
+                for cycle in 0..CYCLES {
+                    let client = minidumper::Client::with_name(name).unwrap();
+
+                    for i in 0..MESSAGES {
+                        client.send_message(thread, format!("{cycle}-{i}")).unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let start = std::time::Instant::now();
+    while disconnected.load(atomic::Ordering::Relaxed) < THREADS * CYCLES {
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    // Group the events by client, in the order they were received
+    let mut clients = std::collections::BTreeMap::<_, Vec<_>>::new();
+    for (client, event) in events.lock().drain(..) {
+        clients.entry(client).or_default().push(event);
+    }
+
+    assert_eq!(clients.len(), (THREADS * CYCLES) as usize);
+
+    let mut seen = std::collections::BTreeSet::new();
+    for (client, events) in clients {
+        let messages = match events.as_slice() {
+            [Event::Connected, messages @ .., Event::Disconnected(minidumper::DisconnectReason::Closed)] => {
+                messages
+            }
+            other => panic!("unexpected events for {client:?}: {other:?}"),
+        };
+        assert_eq!(messages.len(), MESSAGES as usize, "{client:?}");
+
+        // Every message came from the same client connection
+        let (thread, cycle) = match &messages[0] {
+            Event::Message(thread, msg) => (*thread, msg.split_once('-').unwrap().0.to_owned()),
+            other => panic!("unexpected event for {client:?}: {other:?}"),
+        };
+        for (i, msg) in messages.iter().enumerate() {
+            match msg {
+                Event::Message(kind, msg) => {
+                    assert_eq!(*kind, thread, "{client:?}");
+                    assert_eq!(*msg, format!("{cycle}-{i}"), "{client:?}");
+                }
+                other => panic!("unexpected event for {client:?}: {other:?}"),
+            }
+        }
+
+        assert!(seen.insert((thread, cycle)), "{client:?}");
+    }
+}
+
+/// Tests that the server queues the crash requests of several clients that
+/// crash at nearly the same time, writing a minidump for each of them
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn concurrent_crashes() {
+    const CLIENTS: usize = 8;
+
+    let name = "concurrent_crashes";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        /// Blocks the writing of minidumps until set
+        release: Arc<atomic::AtomicBool>,
+        dumps: Arc<atomic::AtomicUsize>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            while !self.release.load(atomic::Ordering::Relaxed) {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+
+            let path = std::env::temp_dir().join(format!("{}.dmp", uuid::Uuid::new_v4()));
+            Ok((std::fs::File::create(&path)?, path))
+        }
+
+        fn on_minidump_created(
+            &self,
+            result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            // The dump itself may fail, as a process can't ptrace its own
+            // threads, but the request is still handled
+            if let Ok(md_bin) = result {
+                let _ = std::fs::remove_file(md_bin.path);
+            }
+
+            self.dumps.fetch_add(1, atomic::Ordering::Relaxed);
+            minidumper::LoopAction::Continue
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+    }
+
+    let release = Arc::new(atomic::AtomicBool::new(false));
+    let dumps = Arc::new(atomic::AtomicUsize::new(0));
+
+    let server_handler = Server {
+        release: release.clone(),
+        dumps: dumps.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    /// A crash context for the current process, which is all the server
+    /// validates before writing the minidump
+    #[allow(unsafe_code)]
+    fn crash_context() -> crash_context::CrashContext {
+        let mut crash_context: crash_context::CrashContext = unsafe { std::mem::zeroed() };
+        crash_context.pid = std::process::id() as _;
+        crash_context.tid = crash_context.pid;
+        crash_context
+    }
+
+    let barrier = std::sync::Barrier::new(CLIENTS);
+
+    std::thread::scope(|s| {
+        let (mut queued, mut waiting) = (Vec::new(), Vec::new());
+
+        for i in 0..CLIENTS {
+            let client = minidumper::Client::with_name(name).unwrap();
+            let barrier = &barrier;
+
+            // Half of the clients only wait for their request to be queued,
+            // which must not be blocked behind the minidumps of other clients
+            if i % 2 == 0 {
+                queued.push(s.spawn(move || {
+                    barrier.wait();
+                    client.queue_dump(&crash_context())
+                }));
+            } else {
+                waiting.push(s.spawn(move || {
+                    barrier.wait();
+                    client.request_dump(&crash_context())
+                }));
+            }
+        }
+
+        for request in queued {
+            request.join().unwrap().unwrap();
+        }
+
+        assert_eq!(dumps.load(atomic::Ordering::Relaxed), 0);
+        release.store(true, atomic::Ordering::Relaxed);
+
+        for request in waiting {
+            // As above, the dump itself may fail, which the server reports
+            match request.join().unwrap() {
+                Ok(()) | Err(minidumper::Error::ServerDumpFailed { .. }) => {}
+                Err(err) => panic!("failed to request dump: {err}"),
+            }
+        }
+    });
+
+    let start = std::time::Instant::now();
+    while dumps.load(atomic::Ordering::Relaxed) < CLIENTS {
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    assert_eq!(dumps.load(atomic::Ordering::Relaxed), CLIENTS);
+}
+
+/// Tests that several servers run by one loop each handle their own clients,
+/// including the crash requests of clients of both that crash at once
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn server_set() {
+    const SERVERS: usize = 2;
+
+    struct Server {
+        index: usize,
+        /// The messages received by each server, by the client's server
+        messages: Arc<parking_lot::Mutex<Vec<Vec<usize>>>>,
+        dumps: Arc<[atomic::AtomicUsize; SERVERS]>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            let path = std::env::temp_dir().join(format!("{}.dmp", uuid::Uuid::new_v4()));
+            Ok((std::fs::File::create(&path)?, path))
+        }
+
+        fn on_minidump_created(
+            &self,
+            result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            // The dump itself may fail, as a process can't ptrace its own
+            // threads, but the request is still handled
+            if let Ok(md_bin) = result {
+                let _ = std::fs::remove_file(md_bin.path);
+            }
+
+            self.dumps[self.index].fetch_add(1, atomic::Ordering::Relaxed);
+            minidumper::LoopAction::Continue
+        }
+
+        fn on_message(&self, _kind: u32, buffer: Vec<u8>) {
+            self.messages.lock()[self.index].push(buffer[0] as usize);
+        }
+    }
+
+    let messages = Arc::new(parking_lot::Mutex::new(vec![Vec::new(); SERVERS]));
+    let dumps = Arc::new([atomic::AtomicUsize::new(0), atomic::AtomicUsize::new(0)]);
+
+    let names: Vec<_> = (0..SERVERS).map(|i| format!("server_set_{i}")).collect();
+
+    let mut set = minidumper::ServerSet::new();
+    for (index, name) in names.iter().enumerate() {
+        set.add(
+            minidumper::Server::with_name(name.as_str()).unwrap(),
+            Box::new(Server {
+                index,
+                messages: messages.clone(),
+                dumps: dumps.clone(),
+            }),
+        );
+    }
+    assert_eq!(set.len(), SERVERS);
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop = std::thread::spawn(move || set.run(&is_shutdown));
+
+    #[allow(unsafe_code)]
+    fn crash_context() -> crash_context::CrashContext {
+        let mut crash_context: crash_context::CrashContext = unsafe { std::mem::zeroed() };
+        crash_context.pid = std::process::id() as _;
+        crash_context.tid = crash_context.pid;
+        crash_context
+    }
+
+    let barrier = std::sync::Barrier::new(SERVERS);
+
+    std::thread::scope(|s| {
+        let requests: Vec<_> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let client = minidumper::Client::with_name(name.as_str()).unwrap();
+                let barrier = &barrier;
+
+                s.spawn(move || {
+                    client.send_message(1, vec![index as u8]).unwrap();
+                    client.ping().unwrap();
+
+                    barrier.wait();
+                    client.request_dump(&crash_context())
+                })
+            })
+            .collect();
+
+        for request in requests {
+            match request.join().unwrap() {
+                Ok(()) | Err(minidumper::Error::ServerDumpFailed { .. }) => {}
+                Err(err) => panic!("failed to request dump: {err}"),
+            }
+        }
+    });
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    // Each server only saw its own client
+    assert_eq!(*messages.lock(), vec![vec![0], vec![1]]);
+    assert!(dumps
+        .iter()
+        .all(|dumps| dumps.load(atomic::Ordering::Relaxed) == 1));
+
+    // The socket paths are removed once the set has been run
+    for name in &names {
+        assert!(minidumper::Client::with_name(name.as_str()).is_err());
+    }
+}
+
+/// Tests that the client is told where the server wrote the minidump
+#[test]
+fn dump_outcome() {
+    let name = "dump_outcome";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        path: Arc<parking_lot::Mutex<Option<std::path::PathBuf>>>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            let path = std::env::temp_dir().join(format!("{}.dmp", uuid::Uuid::new_v4()));
+            *self.path.lock() = Some(path.clone());
+            Ok((std::fs::File::create(&path)?, path))
+        }
+
+        fn on_minidump_created(
+            &self,
+            result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            if let Ok(md_bin) = result {
+                let _ = std::fs::remove_file(md_bin.path);
+            }
+
+            minidumper::LoopAction::Continue
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+    }
+
+    let path = Arc::new(parking_lot::Mutex::new(None));
+
+    let server_handler = Server { path: path.clone() };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    #[allow(unsafe_code)]
+    let mut crash_context: crash_context::CrashContext = unsafe { std::mem::zeroed() };
+    crash_context.pid = std::process::id() as _;
+    crash_context.tid = crash_context.pid;
+
+    let client = minidumper::Client::with_name(name).unwrap();
+    let outcome = client.request_dump_with_result(&crash_context).unwrap();
+
+    // The dump itself may fail, as a process can't ptrace its own threads,
+    // but the server always reports the outcome
+    match outcome.written() {
+        Some(true) => assert_eq!(outcome.path(), path.lock().as_deref()),
+        Some(false) => assert_eq!(outcome.path(), None),
+        None => panic!("the server didn't report the outcome"),
+    }
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+}
+
+/// Tests that the client is told when the server fails to write the minidump
+#[test]
+fn dump_failure() {
+    let name = "dump_failure";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server;
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            // Simulate eg. the disk being full
+            Err(std::io::Error::from_raw_os_error(28))
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+    }
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop = std::thread::spawn(move || server.run(Box::new(Server), &is_shutdown, None));
+
+    #[allow(unsafe_code)]
+    let mut crash_context: crash_context::CrashContext = unsafe { std::mem::zeroed() };
+    crash_context.pid = std::process::id() as _;
+    crash_context.tid = crash_context.pid;
+
+    let client = minidumper::Client::with_name(name).unwrap();
+
+    // Macos can only report the category of the failure
+    let expected_code = if cfg!(target_os = "macos") {
+        None
+    } else {
+        Some(28)
+    };
+
+    match client.request_dump(&crash_context) {
+        Err(minidumper::Error::ServerDumpFailed { category, code }) => {
+            assert_eq!(category, minidumper::DumpFailureCategory::CreateFile);
+            assert_eq!(code, expected_code);
+        }
+        res => panic!("expected the dump to fail, got {res:?}"),
+    }
+
+    // The connection is closed after a crash request
+    let client = minidumper::Client::with_name(name).unwrap();
+    let outcome = client.request_dump_with_result(&crash_context).unwrap();
+    assert_eq!(outcome.written(), Some(false));
+    assert!(matches!(
+        outcome.error(),
+        Some(minidumper::Error::ServerDumpFailed {
+            category: minidumper::DumpFailureCategory::CreateFile,
+            ..
+        })
+    ));
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+}
+
+/// Tests that crash requests deferred by the server are retried by the client
+/// until they succeed, or the client runs out of retries
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn deferred_crash() {
+    let name = "deferred_crash";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        deferrals: Arc<atomic::AtomicUsize>,
+        requests: Arc<atomic::AtomicUsize>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            // Fail quickly, as only reaching the writer matters
+            Err(std::io::Error::from_raw_os_error(28))
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_crash_request(
+            &self,
+            _client: minidumper::ClientId,
+            _crash_context: &crash_context::CrashContext,
+            pending_dumps: usize,
+        ) -> minidumper::CrashRequestAction {
+            assert_eq!(pending_dumps, 0);
+            self.requests.fetch_add(1, atomic::Ordering::Relaxed);
+
+            let deferrals = self.deferrals.load(atomic::Ordering::Relaxed);
+            if deferrals > 0 {
+                self.deferrals
+                    .store(deferrals - 1, atomic::Ordering::Relaxed);
+                minidumper::CrashRequestAction::Defer {
+                    retry_after: std::time::Duration::from_millis(20),
+                }
+            } else {
+                minidumper::CrashRequestAction::Dump
+            }
+        }
+    }
+
+    let deferrals = Arc::new(atomic::AtomicUsize::new(2));
+    let requests = Arc::new(atomic::AtomicUsize::new(0));
+
+    let server_handler = Server {
+        deferrals: deferrals.clone(),
+        requests: requests.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    #[allow(unsafe_code)]
+    let mut crash_context: crash_context::CrashContext = unsafe { std::mem::zeroed() };
+    crash_context.pid = std::process::id() as _;
+    crash_context.tid = crash_context.pid;
+
+    // The request is deferred twice, then dumped on the last retry
+    let client = minidumper::Client::with_name(name)
+        .unwrap()
+        .crash_retries(2);
+
+    let start = std::time::Instant::now();
+    match client.request_dump(&crash_context) {
+        Err(minidumper::Error::ServerDumpFailed { .. }) => {}
+        res => panic!("expected the request to reach the writer, got {res:?}"),
+    }
+
+    assert!(start.elapsed() >= std::time::Duration::from_millis(40));
+    assert_eq!(requests.load(atomic::Ordering::Relaxed), 3);
+
+    // The request is deferred more times than the client retries it
+    deferrals.store(5, atomic::Ordering::Relaxed);
+    requests.store(0, atomic::Ordering::Relaxed);
+
+    let client = minidumper::Client::with_name(name)
+        .unwrap()
+        .crash_retries(1);
+
+    match client.request_dump(&crash_context) {
+        Err(minidumper::Error::ServerBusy { retry_after }) => {
+            assert_eq!(retry_after, std::time::Duration::from_millis(20));
+        }
+        res => panic!("expected the server to be busy, got {res:?}"),
+    }
+
+    assert_eq!(requests.load(atomic::Ordering::Relaxed), 2);
+
+    // Deferred clients stay connected
+    client.ping().unwrap();
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+}
+
+/// Tests that on-demand dumps are written without the client being treated as
+/// crashed, so that it can keep using the connection afterwards
+#[test]
+fn on_demand_dump() {
+    let name = "on_demand_dump";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        requests: Arc<atomic::AtomicUsize>,
+        disconnects: Arc<atomic::AtomicUsize>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            self.requests.fetch_add(1, atomic::Ordering::Relaxed);
+
+            // Fail quickly, as only reaching the writer matters
+            Err(std::io::Error::from_raw_os_error(28))
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_client_disconnected(
+            &self,
+            _client: minidumper::ClientId,
+            _reason: minidumper::DisconnectReason,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.disconnects.fetch_add(1, atomic::Ordering::Relaxed);
+            minidumper::LoopAction::Continue
+        }
+    }
+
+    let requests = Arc::new(atomic::AtomicUsize::new(0));
+    let disconnects = Arc::new(atomic::AtomicUsize::new(0));
+    let handle = server.handle();
+
+    let server_handler = Server {
+        requests: requests.clone(),
+        disconnects: disconnects.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    let client = minidumper::Client::with_name(name).unwrap();
+
+    for i in 1..=2 {
+        match client.request_on_demand_dump() {
+            Ok(()) | Err(minidumper::Error::ServerDumpFailed { .. }) => {}
+            Err(err) => panic!("on-demand dump {i} failed: {err}"),
+        }
+
+        assert_eq!(requests.load(atomic::Ordering::Relaxed), i);
+    }
+
+    // The client is still connected, and usable
+    client.ping().unwrap();
+    assert_eq!(handle.clients().len(), 1);
+    assert_eq!(disconnects.load(atomic::Ordering::Relaxed), 0);
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+}
+
+/// Tests that the breadcrumbs recorded by a client are read by the server when
+/// the client disconnects, even though nothing is sent when recording them
+#[test]
+fn breadcrumbs() {
+    let name = "breadcrumbs";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        tx: std::sync::mpsc::Sender<(minidumper::DisconnectReason, Vec<minidumper::Breadcrumb>)>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_client_breadcrumbs(
+            &self,
+            _client: minidumper::ClientId,
+            reason: minidumper::DisconnectReason,
+            breadcrumbs: Vec<minidumper::Breadcrumb>,
+        ) {
+            self.tx.send((reason, breadcrumbs)).unwrap();
+        }
+
+        fn on_client_disconnected(
+            &self,
+            _client: minidumper::ClientId,
+            _reason: minidumper::DisconnectReason,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            minidumper::LoopAction::Exit
+        }
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let shutdown = atomic::AtomicBool::new(false);
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(Server { tx }), &shutdown, None));
+
+    let client = minidumper::Client::with_name(name).unwrap();
+
+    assert!(matches!(
+        client.create_breadcrumbs(0),
+        Err(minidumper::Error::InvalidBreadcrumbCapacity(0))
+    ));
+
+    let breadcrumbs = client.create_breadcrumbs(4).unwrap();
+    assert_eq!(breadcrumbs.capacity(), 4);
+
+    for i in 0..6u32 {
+        breadcrumbs.record(i, format!("event {i}"));
+    }
+
+    // Makes sure the server has mapped the breadcrumbs before disconnecting
+    client.ping().unwrap();
+    drop(client);
+
+    let (reason, received) = rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .expect("the breadcrumbs were not reported");
+    assert_eq!(reason, minidumper::DisconnectReason::Closed);
+    assert_eq!(received, breadcrumbs.snapshot());
+    assert_eq!(
+        received.iter().map(|crumb| crumb.seq).collect::<Vec<_>>(),
+        [3, 4, 5, 6]
+    );
+    assert_eq!(received[3].kind, 5);
+    assert_eq!(received[3].data, b"event 5");
+
+    server_loop.join().unwrap().unwrap();
+}
+
+/// Tests that messages sent by many threads sharing a client are received
+/// intact, even while another thread requests dumps via the same client
+#[test]
+fn concurrent_sends() {
+    const THREADS: u8 = 8;
+    const MESSAGES: u32 = 200;
+
+    /// Each message is tagged with the thread and index that sent it, and
+    /// padded to a size that varies between messages with a byte pattern
+    /// derived from both
+    fn message(thread: u8, index: u32) -> Vec<u8> {
+        let mut msg = vec![thread];
+        msg.extend_from_slice(&index.to_le_bytes());
+        let len = (index as usize * 37 + thread as usize * 101) % 4096;
+        msg.extend((0..len).map(|i| (i as u8) ^ thread ^ (index as u8)));
+        msg
+    }
+
+    let name = "concurrent_sends";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        received: Arc<parking_lot::Mutex<Vec<Vec<u32>>>>,
+        dumps: Arc<atomic::AtomicUsize>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            self.dumps.fetch_add(1, atomic::Ordering::Relaxed);
+
+            // Fail quickly, as only the crash request reaching the server
+            // matters
+            Err(std::io::Error::from_raw_os_error(28))
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+            assert_eq!(kind, 1);
+
+            let thread = buffer[0];
+            let index = u32::from_le_bytes(buffer[1..5].try_into().unwrap());
+            assert!(
+                buffer == message(thread, index),
+                "message {index} from thread {thread} was corrupted"
+            );
+
+            self.received.lock()[thread as usize].push(index);
+        }
+    }
+
+    let received = Arc::new(parking_lot::Mutex::new(vec![Vec::new(); THREADS as usize]));
+    let dumps = Arc::new(atomic::AtomicUsize::new(0));
+
+    let server_handler = Server {
+        received: received.clone(),
+        dumps: dumps.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+    let client = minidumper::Client::with_name(name).unwrap();
+
+    std::thread::scope(|s| {
+        for thread in 0..THREADS {
+            let client = &client;
+            s.spawn(move || {
+                for index in 0..MESSAGES {
+                    client.send_message(1, message(thread, index)).unwrap();
+                }
+            });
+        }
+
+        // The on-demand dump goes through the same path as a crash, without
+        // the server disconnecting the client afterwards
+        s.spawn(|| {
+            for _ in 0..3 {
+                match client.request_on_demand_dump() {
+                    Ok(()) | Err(minidumper::Error::ServerDumpFailed { .. }) => {}
+                    Err(err) => panic!("on-demand dump failed: {err}"),
+                }
+            }
+        });
+    });
+
+    // The server replies once it has handled every message sent before
+    client.ping().unwrap();
+
+    shutdown.store(true, atomic::Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+
+    // Each thread's messages were received in the order they were sent
+    let expected: Vec<_> = (0..MESSAGES).collect();
+    assert!(received.lock().iter().all(|indices| *indices == expected));
+    assert_eq!(dumps.load(atomic::Ordering::Relaxed), 3);
+}
+
+/// Tests that the socket path is only accessible by the user the server is
+/// running as
+#[cfg(unix)]
+#[test]
+fn socket_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join("minidumper-socket-permissions.sock");
+
+    let server = minidumper::Server::with_name(path.as_path()).unwrap();
+    let mode = || std::fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(), 0o600);
+
+    // The same user can connect
+    minidumper::Client::with_name(path.as_path()).unwrap();
+
+    // Connecting as a different user can only be simulated as root, which
+    // otherwise bypasses the permissions
+    #[cfg(target_os = "linux")]
+    #[allow(unsafe_code)]
+    unsafe {
+        if libc::geteuid() == 0 {
+            use std::os::unix::ffi::OsStrExt;
+
+            let mut addr: libc::sockaddr_un = std::mem::zeroed();
+            addr.sun_family = libc::AF_UNIX as _;
+            for (dst, src) in addr.sun_path.iter_mut().zip(path.as_os_str().as_bytes()) {
+                *dst = *src as _;
+            }
+
+            // Only async signal safe functions are called in the child, as
+            // the test process is multithreaded
+            match libc::fork() {
+                0 => {
+                    /// The user id of `nobody`
+                    const NOBODY: libc::uid_t = 65534;
+
+                    let code = if libc::setuid(NOBODY) != 0 {
+                        2
+                    } else {
+                        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET, 0);
+                        if libc::connect(
+                            fd,
+                            (&addr as *const libc::sockaddr_un).cast(),
+                            std::mem::size_of::<libc::sockaddr_un>() as _,
+                        ) == 0
+                        {
+                            0
+                        } else if *libc::__errno_location() == libc::EACCES {
+                            1
+                        } else {
+                            3
+                        }
+                    };
+
+                    libc::_exit(code);
+                }
+                -1 => panic!("failed to fork: {}", std::io::Error::last_os_error()),
+                child => {
+                    let mut status = 0;
+                    assert_eq!(libc::waitpid(child, &mut status, 0), child);
+                    assert!(libc::WIFEXITED(status));
+                    assert_eq!(
+                        libc::WEXITSTATUS(status),
+                        1,
+                        "a different user was not denied access to the socket"
+                    );
+                }
+            }
+        }
+    }
+
+    drop(server);
+
+    let _server = minidumper::Server::with_name_and_options(
+        path.as_path(),
+        minidumper::ServerOptions::default().socket_mode(Some(0o660)),
+    )
+    .unwrap();
+    assert_eq!(mode(), 0o660);
+}
+
+/// Tests that crash targets are validated, and that a request for a minidump of
+/// another process is denied unless the server allows it
+#[cfg(target_os = "windows")]
+#[test]
+fn proxy_dumps() {
+    use minidumper::WindowsCrashTarget as Target;
+
+    for invalid in [
+        Target::new(0, 1, 0x1000, 0),
+        Target::new(1, 0, 0x1000, 0),
+        Target::new(1, 1, 0, 0),
+        Target::new(1, 1, 0x1001, 0),
+    ] {
+        assert!(matches!(
+            invalid,
+            Err(minidumper::Error::InvalidCrashTarget(_))
+        ));
+    }
+
+    let name = "proxy_dumps";
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server {
+        reason: Arc<parking_lot::Mutex<Option<minidumper::DisconnectReason>>>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_client_disconnected(
+            &self,
+            _client: minidumper::ClientId,
+            reason: minidumper::DisconnectReason,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            *self.reason.lock() = Some(reason);
+            minidumper::LoopAction::Exit
+        }
+    }
+
+    let reason = Arc::new(parking_lot::Mutex::new(None));
+    let server_handler = Server {
+        reason: reason.clone(),
+    };
+
+    let shutdown = Arc::new(atomic::AtomicBool::new(false));
+    let server_loop =
+        std::thread::spawn(move || server.run(Box::new(server_handler), &shutdown, None));
+
+    // The System process, which is never the client
+    let target = Target::new(4, 1, 0x1000, 0).unwrap();
+    let client = minidumper::Client::with_name(name).unwrap();
+    assert!(matches!(
+        client.request_dump_for(&target),
+        Err(minidumper::Error::Io(_))
+    ));
+
+    server_loop.join().unwrap().unwrap();
+    assert_eq!(
+        *reason.lock(),
+        Some(minidumper::DisconnectReason::Errored(
+            std::io::ErrorKind::PermissionDenied
+        ))
+    );
+}
+
+/// Tests that the credentials of clients are reported, and that clients running
+/// as users that aren't allowed are rejected
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn peer_credentials() {
+    struct Server {
+        peers: Arc<parking_lot::Mutex<Vec<minidumper::PeerCredentials>>>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+
+        fn on_client_connected(
+            &self,
+            _client: minidumper::ClientId,
+            peer: minidumper::PeerCredentials,
+            _num_clients: usize,
+        ) -> minidumper::LoopAction {
+            self.peers.lock().push(peer);
+            minidumper::LoopAction::Continue
+        }
+    }
+
+    #[allow(unsafe_code)]
+    let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+
+    let run = |name: &str, options: minidumper::ServerOptions| {
+        let mut server = minidumper::Server::with_name_and_options(name, options).unwrap();
+        let handle = server.handle();
+
+        let peers = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let server_handler = Server {
+            peers: peers.clone(),
+        };
+
+        let shutdown = Arc::new(atomic::AtomicBool::new(false));
+        let is_shutdown = shutdown.clone();
+        let server_loop =
+            std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+        let client = minidumper::Client::with_name(name).unwrap();
+        let pinged = client.ping().is_ok();
+
+        shutdown.store(true, atomic::Ordering::Relaxed);
+        server_loop.join().unwrap().unwrap();
+
+        let peers = peers.lock().clone();
+        (pinged, peers, handle.stats())
+    };
+
+    let (pinged, peers, stats) = run("peer_credentials", Default::default());
+    assert!(pinged);
+    assert_eq!(stats.rejected_connections, 0);
+    assert_eq!(peers.len(), 1);
+    assert_eq!(peers[0].pid, Some(std::process::id()));
+    assert_eq!(peers[0].uid, Some(uid));
+    assert_eq!(peers[0].gid, Some(gid));
+
+    let (pinged, peers, stats) = run(
+        "peer_credentials_rejected",
+        minidumper::ServerOptions::default().allowed_uids([uid.wrapping_add(1)]),
+    );
+    assert!(!pinged, "the client should have been disconnected");
+    assert!(peers.is_empty());
+    assert_eq!(stats.rejected_connections, 1);
+
+    let (pinged, peers, _stats) = run(
+        "peer_credentials_any",
+        minidumper::ServerOptions::default().allow_any_uid(),
+    );
+    assert!(pinged);
+    assert_eq!(peers.len(), 1);
+}
+
+/// The server run by [`spawn_monitor`] in a re-exec of this test binary, which
+/// does nothing when run as part of the normal test suite
+#[test]
+fn monitor_process() {
+    let Some(name) = minidumper::monitor_socket_name() else {
+        return;
+    };
+
+    struct Server {
+        messages: Arc<atomic::AtomicUsize>,
+    }
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            panic!("should not be called");
+        }
+
+        fn on_minidump_created(
+            &self,
+            _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            panic!("should not be called");
+        }
+
+        fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+            assert_eq!(kind, 1);
+            assert_eq!(buffer, b"monitored");
+            self.messages.fetch_add(1, atomic::Ordering::Relaxed);
+        }
+    }
+
+    let mut server = minidumper::Server::with_name_and_options(
+        &name,
+        minidumper::ServerOptions::default().accept_shutdown_requests(true),
+    )
+    .unwrap();
+
+    let messages = Arc::new(atomic::AtomicUsize::new(0));
+    let server_handler = Server {
+        messages: messages.clone(),
+    };
+
+    // Only the shutdown request stops the server
+    let shutdown = atomic::AtomicBool::new(false);
+    server
+        .run(Box::new(server_handler), &shutdown, None)
+        .unwrap();
+
+    assert!(shutdown.load(atomic::Ordering::Relaxed));
+    assert_eq!(messages.load(atomic::Ordering::Relaxed), 1);
+}
+
+/// Tests that a monitor process can be spawned and shut down
+#[test]
+fn spawn_monitor() {
+    let name = format!("spawn_monitor_{}", std::process::id());
+
+    // Re-exec this test binary, running only the monitor_process test
+    let config = minidumper::MonitorConfig::reexec("monitor_process", name.as_str())
+        .unwrap()
+        .args(["--exact", "--nocapture"]);
+
+    let monitor = minidumper::spawn_monitor(config).unwrap();
+    assert_ne!(monitor.pid(), std::process::id());
+
+    monitor.client().send_message(1, "monitored").unwrap();
+    monitor.client().ping().unwrap();
+
+    let status = monitor.shutdown().unwrap();
+    assert!(status.success(), "{status}");
+
+    // A monitor that exits without starting its server is reported
+    let config = minidumper::MonitorConfig::reexec("monitor_process", name.as_str())
+        .unwrap()
+        .args(["--exact", "--list"]);
+
+    let err = minidumper::spawn_monitor(config).err().unwrap();
+    assert!(
+        matches!(&err, minidumper::Error::Io(err) if err.kind() == std::io::ErrorKind::Other),
+        "{err:?}"
+    );
+}
+
+/// Tests that a monitor can be spawned with a path name, which is passed in a
+/// separate variable on Linux/Android, where plain names are abstract
+#[test]
+fn spawn_monitor_path() {
+    let path = std::env::temp_dir().join(format!("spawn_monitor_path_{}", std::process::id()));
+
+    let config = minidumper::MonitorConfig::reexec("monitor_process", "unused")
+        .unwrap()
+        .socket_name(minidumper::OwnedSocketName::Path(path.clone()))
+        .args(["--exact", "--nocapture"]);
+
+    let monitor = minidumper::spawn_monitor(config).unwrap();
+
+    monitor.client().send_message(1, "monitored").unwrap();
+
+    let status = monitor.shutdown().unwrap();
+    assert!(status.success(), "{status}");
+    assert!(!path.exists());
+}
@@ -0,0 +1,136 @@
+//! Verifies that requesting a dump from within a crash handler doesn't allocate.
+//!
+//! This is in its own test binary as it replaces the global allocator
+
+#![allow(unsafe_code)]
+
+use crash_handler as ch;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Counts the allocations made on threads that are currently tracking them
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static TRACKING: Cell<bool> = const { Cell::new(false) };
+}
+
+#[inline]
+fn count() {
+    if TRACKING.with(Cell::get) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn request_dump_does_not_allocate() {
+    let name = "request_dump_does_not_allocate";
+
+    let mut server = minidumper::Server::with_name(name).unwrap();
+
+    struct Server;
+
+    impl minidumper::ServerHandler for Server {
+        fn create_minidump_file(
+            &self,
+        ) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+            let path = std::env::temp_dir().join("request_dump_does_not_allocate.dmp");
+            Ok((std::fs::File::create(&path)?, path))
+        }
+
+        fn on_minidump_created(
+            &self,
+            result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+        ) -> minidumper::LoopAction {
+            // The dump itself may fail, eg. as a process can't ptrace its own
+            // threads, but the client is acked regardless
+            if let Ok(md_bin) = result {
+                let _ = std::fs::remove_file(md_bin.path);
+            }
+
+            minidumper::LoopAction::Continue
+        }
+
+        fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {
+            panic!("should not be called");
+        }
+    }
+
+    let handle = server.handle();
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let is_shutdown = shutdown.clone();
+    let server_loop = std::thread::spawn(move || server.run(Box::new(Server), &is_shutdown, None));
+
+    let client = Arc::new(minidumper::Client::with_name(name).unwrap());
+
+    // Push a message to the client that it will need to discard while waiting
+    // for the crash ack
+    while handle.clients().is_empty() {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    handle.broadcast(1, &[0xcc; 1024]);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    static DUMPED: AtomicBool = AtomicBool::new(false);
+
+    let crash_client = client.clone();
+    let handler = ch::CrashHandler::attach(unsafe {
+        ch::make_crash_event(move |cc: &ch::CrashContext| {
+            TRACKING.with(|tracking| tracking.set(true));
+            // The server reports the dump failing, which is still a response
+            let dumped = matches!(
+                crash_client.request_dump(cc),
+                Ok(()) | Err(minidumper::Error::ServerDumpFailed { .. })
+            );
+            TRACKING.with(|tracking| tracking.set(false));
+
+            DUMPED.store(dumped, Ordering::Relaxed);
+            ch::CrashEventResult::Handled(dumped)
+        })
+    })
+    .unwrap();
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            handler.simulate_signal(libc::SIGUSR2 as u32);
+        } else {
+            handler.simulate_exception(None);
+        }
+    }
+
+    handler.detach().unwrap();
+
+    assert!(DUMPED.load(Ordering::Relaxed), "the dump request failed");
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), 0);
+
+    shutdown.store(true, Ordering::Relaxed);
+    server_loop.join().unwrap().unwrap();
+}
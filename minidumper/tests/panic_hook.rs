@@ -0,0 +1,225 @@
+//! Verifies that the panic hook reports panics to the server, both ones that
+//! unwind, and ones that abort the process. The tests of the latter run
+//! themselves as a child process that does the actual panicking, since the
+//! test itself needs to check how the child exited
+
+use std::sync::{atomic, Arc};
+
+const CHILD_ENV: &str = "MINIDUMPER_PANIC_HOOK_CHILD";
+
+struct Server {
+    messages: Arc<parking_lot::Mutex<Vec<String>>>,
+    dumps: Arc<atomic::AtomicUsize>,
+}
+
+impl minidumper::ServerHandler for Server {
+    fn create_minidump_file(&self) -> Result<(std::fs::File, std::path::PathBuf), std::io::Error> {
+        self.dumps.fetch_add(1, atomic::Ordering::Relaxed);
+
+        // Fail quickly, as only the dump request reaching the server matters
+        Err(std::io::Error::from_raw_os_error(28))
+    }
+
+    fn on_minidump_created(
+        &self,
+        _result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+    ) -> minidumper::LoopAction {
+        panic!("should not be called");
+    }
+
+    fn on_message(&self, kind: u32, buffer: Vec<u8>) {
+        assert_eq!(kind, 1);
+        self.messages
+            .lock()
+            .push(String::from_utf8(buffer).expect("panic message is not utf-8"));
+    }
+}
+
+/// Runs a server for a test, until it is dropped
+struct TestServer {
+    messages: Arc<parking_lot::Mutex<Vec<String>>>,
+    dumps: Arc<atomic::AtomicUsize>,
+    shutdown: Arc<atomic::AtomicBool>,
+    server_loop: Option<std::thread::JoinHandle<Result<(), minidumper::Error>>>,
+}
+
+impl TestServer {
+    fn new(name: &str) -> Self {
+        let mut server = minidumper::Server::with_name(name).unwrap();
+
+        let messages = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let dumps = Arc::new(atomic::AtomicUsize::new(0));
+
+        let server_handler = Server {
+            messages: messages.clone(),
+            dumps: dumps.clone(),
+        };
+
+        let shutdown = Arc::new(atomic::AtomicBool::new(false));
+        let is_shutdown = shutdown.clone();
+        let server_loop =
+            std::thread::spawn(move || server.run(Box::new(server_handler), &is_shutdown, None));
+
+        Self {
+            messages,
+            dumps,
+            shutdown,
+            server_loop: Some(server_loop),
+        }
+    }
+
+    /// Waits for the server to have received the specified number of messages
+    fn wait_for_messages(&self, count: usize) -> Vec<String> {
+        let start = std::time::Instant::now();
+        while self.messages.lock().len() < count {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        self.messages.lock().clone()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, atomic::Ordering::Relaxed);
+
+        if let Some(server_loop) = self.server_loop.take() {
+            let res = server_loop.join();
+            if !std::thread::panicking() {
+                res.unwrap().unwrap();
+            }
+        }
+    }
+}
+
+/// Runs the test as a child process connected to the server, returning how
+/// it exited, and its stderr
+fn run_child(test: &str, name: &str) -> (std::process::ExitStatus, String) {
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test, "--nocapture"])
+        .env(CHILD_ENV, name)
+        .output()
+        .expect("failed to run child");
+
+    (
+        output.status,
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+/// Installs the hook in the child, returning true if this is the child
+fn install_in_child(policy: minidumper::PanicPolicy) -> bool {
+    let name = match std::env::var(CHILD_ENV) {
+        Ok(name) => name,
+        Err(_) => return false,
+    };
+
+    let client = minidumper::Client::with_name(name.as_str()).unwrap();
+    minidumper::install_panic_hook(Some(Arc::new(client)), policy);
+    true
+}
+
+fn assert_aborted(status: std::process::ExitStatus, stderr: &str) {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(status.signal(), Some(libc::SIGABRT), "{stderr}");
+        } else if #[cfg(target_os = "windows")] {
+            // STATUS_STACK_BUFFER_OVERRUN, as abort uses __fastfail
+            assert_eq!(status.code(), Some(0xc0000409_u32 as i32), "{stderr}");
+        }
+    }
+}
+
+#[test]
+fn unwind() {
+    if std::env::var_os(CHILD_ENV).is_some() {
+        return;
+    }
+
+    let name = "panic_hook_unwind";
+    let server = TestServer::new(name);
+    let client = Arc::new(minidumper::Client::with_name(name).unwrap());
+
+    let previous = Arc::new(atomic::AtomicUsize::new(0));
+    {
+        let previous = previous.clone();
+        std::panic::set_hook(Box::new(move |_info| {
+            previous.fetch_add(1, atomic::Ordering::Relaxed);
+        }));
+    }
+
+    minidumper::install_panic_hook(
+        Some(client.clone()),
+        minidumper::PanicPolicy::new(minidumper::PanicAction::Dump).send_message(1),
+    );
+
+    let res = std::panic::catch_unwind(|| panic!("unwound {}", 1));
+    drop(std::panic::take_hook());
+    assert!(res.is_err());
+
+    // The previous hook is chained, and the panic still unwinds once the
+    // dump has been requested
+    assert_eq!(previous.load(atomic::Ordering::Relaxed), 1);
+    assert_eq!(server.dumps.load(atomic::Ordering::Relaxed), 1);
+
+    let messages = server.wait_for_messages(1);
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].contains("unwound 1"), "{}", messages[0]);
+    assert!(messages[0].contains("panic_hook.rs"), "{}", messages[0]);
+
+    assert_eq!(
+        minidumper::last_panic_message().as_ref(),
+        Some(&messages[0])
+    );
+}
+
+#[test]
+fn abort() {
+    if install_in_child(
+        minidumper::PanicPolicy::new(minidumper::PanicAction::Abort).send_message(1),
+    ) {
+        panic!("aborted");
+    }
+
+    let name = "panic_hook_abort";
+    let server = TestServer::new(name);
+    let (status, stderr) = run_child("abort", name);
+
+    // The default hook still prints the panic, before the process aborts
+    assert!(stderr.contains("aborted"), "{stderr}");
+    assert_aborted(status, &stderr);
+
+    let messages = server.wait_for_messages(1);
+    assert!(messages[0].contains("aborted"), "{}", messages[0]);
+}
+
+#[test]
+fn nested() {
+    struct PanicOnDrop;
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            panic!("nested");
+        }
+    }
+
+    if install_in_child(
+        minidumper::PanicPolicy::new(minidumper::PanicAction::Continue).send_message(1),
+    ) {
+        let _guard = PanicOnDrop;
+        panic!("outer");
+    }
+
+    let name = "panic_hook_nested";
+    let server = TestServer::new(name);
+    let (status, stderr) = run_child("nested", name);
+
+    // A panic while unwinding aborts the process, but both are reported
+    assert_aborted(status, &stderr);
+
+    let messages = server.wait_for_messages(2);
+    assert!(messages[0].contains("outer"), "{}", messages[0]);
+    assert!(messages[1].contains("nested"), "{}", messages[1]);
+}
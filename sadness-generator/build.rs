@@ -0,0 +1,38 @@
+fn main() {
+    let mut sources = Vec::new();
+
+    if cfg!(feature = "cpp") {
+        sources.push("src/sadness.cpp");
+    }
+
+    // The purecall handler is only invoked by the MSVC CRT, on windows-gnu
+    // libstdc++ instead calls `std::terminate` for pure virtual calls
+    let is_msvc = std::env::var("CARGO_CFG_TARGET_ENV").is_ok_and(|env| env == "msvc");
+    if is_msvc && !cfg!(feature = "purecall-direct") {
+        sources.push("src/purecall.cpp");
+    }
+
+    if sources.is_empty() {
+        return;
+    }
+
+    for src in &sources {
+        println!("cargo:rerun-if-changed={src}");
+    }
+
+    compile(&sources);
+}
+
+/// `cc` is only a build dependency when building on an MSVC host, or with the
+/// `cpp` feature, see Cargo.toml
+#[cfg(any(feature = "cpp", target_env = "msvc"))]
+fn compile(sources: &[&str]) {
+    cc::Build::new().cpp(true).files(sources).compile("sadness");
+}
+
+#[cfg(not(any(feature = "cpp", target_env = "msvc")))]
+fn compile(_sources: &[&str]) {
+    panic!(
+        "the purecall shim can only be compiled when cross compiling to MSVC if the `cpp` feature is enabled, otherwise enable the `purecall-direct` feature"
+    );
+}
@@ -0,0 +1,32 @@
+//! Raises every [`sadness_generator::SadnessFlavor`] available on the current
+//! platform, each in its own process, and reports whether each one terminated
+//! the process as expected, exiting with a non-zero code if any didn't.
+//!
+//! `cargo run -p sadness-generator --example make_sad_all`
+
+fn main() {
+    let mut total = 0;
+    let mut failures = Vec::new();
+
+    sadness_generator::run_all(|flavor, outcome| {
+        println!("{flavor:?}: {outcome}");
+
+        total += 1;
+        if !outcome.is_expected() {
+            failures.push(flavor);
+        }
+    });
+
+    println!(
+        "{} of {total} flavors terminated as expected",
+        total - failures.len()
+    );
+
+    if !failures.is_empty() {
+        for flavor in failures {
+            println!("  {flavor:?}");
+        }
+
+        std::process::exit(1);
+    }
+}
@@ -18,33 +18,97 @@ pub enum SadnessFlavor {
     /// * `SIGSEGV` on Linux
     /// * `EXCEPTION_ACCESS_VIOLATION` on Windows
     /// * `EXC_BAD_ACCESS` on Macos
-    Segfault,
+    Segfault {
+        /// Raises the signal/exception from a non-[`std::thread::Thread`]
+        native_thread: bool,
+    },
+    /// * `SIGSEGV` (`SEGV_ACCERR`) on Linux
+    /// * `EXCEPTION_ACCESS_VIOLATION` (write) on Windows
+    /// * `EXC_BAD_ACCESS` (`KERN_PROTECTION_FAILURE`) on Macos
+    ///
+    /// Unlike [`Self::Segfault`], the address being written to is mapped, just
+    /// not writable
+    WriteReadOnly,
     /// * `SIGFPE` on Linux
     /// * `EXCEPTION_INT_DIVIDE_BY_ZERO` on Windows
     /// * `EXC_ARITHMETIC` on Macos
-    DivideByZero,
+    DivideByZero {
+        /// Raises the signal/exception from a non-[`std::thread::Thread`]
+        native_thread: bool,
+    },
+    /// Divides the smallest signed integer by -1, whose quotient overflows
+    ///
+    /// * `SIGFPE` on Linux, with `FPE_INTDIV` on x86, as the kernel reports
+    ///   every divide error as such, and `FPE_INTOVF` on ARM
+    /// * `EXCEPTION_INT_OVERFLOW` on Windows
+    /// * `EXC_ARITHMETIC` on Macos
+    ///
+    /// See [`raise_divide_overflow`] for how this is raised on ARM, whose
+    /// divide instructions don't trap
+    DivideOverflow,
     /// * `SIGILL` on Linux
     /// * `EXCEPTION_ILLEGAL_INSTRUCTION` on Windows
     /// * `EXC_BAD_INSTRUCTION` on Macos
-    Illegal,
+    Illegal {
+        /// Raises the signal/exception from a non-[`std::thread::Thread`]
+        native_thread: bool,
+    },
     /// * `SIGBUS` on Linux
     /// * `EXC_BAD_ACCESS` on Macos
     #[cfg(unix)]
-    Bus,
+    Bus {
+        /// Raises the signal/exception from a non-[`std::thread::Thread`]
+        native_thread: bool,
+    },
     /// * `SIGTRAP` on Linux
     /// * `EXCEPTION_BREAKPOINT` on Windows
     /// * `EXC_BREAKPOINT` on Macos
-    Trap,
+    Trap {
+        /// Raises the signal/exception from a non-[`std::thread::Thread`]
+        native_thread: bool,
+        /// Raises the exception via `RaiseException` with this as its only
+        /// parameter, rather than via a breakpoint instruction, as is done by
+        /// eg. anti-tamper code to identify its breakpoints
+        #[cfg(windows)]
+        parameter: Option<usize>,
+    },
+    /// Raises an `EXCEPTION_BREAKPOINT` via `int 2dh`, the debug service
+    /// interrupt used by eg. `DbgPrint`, with the specified service code. The
+    /// code is the first parameter of the exception record.
+    ///
+    /// On architectures other than x86 and x86_64, the exception is raised via
+    /// `RaiseException` instead, with the code as its only parameter
+    #[cfg(windows)]
+    DebugService {
+        /// The service code, placed in `eax`
+        code: u32,
+    },
     /// * `SIGSEGV` on Linux
     /// * `EXCEPTION_STACK_OVERFLOW` on Windows
     /// * `EXC_BAD_ACCESS` on Macos
     StackOverflow {
-        /// Raises the signal/exception from a non-[`std::thread::Thread`]
+        /// Raises the signal/exception from a non-[`std::thread::Thread`]. On
+        /// Windows, it is still raised on the calling thread, as we can't
+        /// control the stack of native threads
         non_rust_thread: bool,
         /// If using a native thread and there is a signal handler that longjumps,
         /// we can't wait on the thread as we would normally as it would deadlock
         long_jumps: bool,
     },
+    /// Overwrites the saved return address of a frame via a stack buffer
+    /// overflow, then returns to it, jumping to [`SMASHED_RETURN_ADDRESS`]
+    ///
+    /// * `SIGSEGV` on Linux
+    /// * `EXCEPTION_ACCESS_VIOLATION` (execute) on Windows
+    /// * `EXC_BAD_ACCESS` on Macos
+    ///
+    /// The crashing instruction pointer is the garbage address, which is not
+    /// in any module, so the stack can't be walked from it
+    SmashedStack,
+    /// [`Self::Segfault`], but raised from an `atexit` handler while the
+    /// process is exiting normally, after `main` has returned, eg. after
+    /// thread local destructors have run on the exiting thread
+    AtExitCrash,
     /// Raises a [purecall](https://docs.microsoft.com/en-us/cpp/c-runtime-library/reference/purecall?view=msvc-170)
     /// exception
     #[cfg(windows)]
@@ -56,10 +120,40 @@ pub enum SadnessFlavor {
     /// Raises a `STATUS_HEAP_CORRUPTION` exception by freeing an invalid pointer
     #[cfg(windows)]
     HeapCorruption,
+    /// Calls the CRT's `abort`, which raises `SIGABRT` for the abort signal
+    /// handler installed by eg. `crash-handler` to catch, before terminating
+    /// the process with `STATUS_FATAL_APP_EXIT`.
+    ///
+    /// This is the same as [`Self::Abort`], but is explicit about which of the
+    /// Windows abort paths is taken, see [`Self::FastFail`]
+    #[cfg(windows)]
+    CrtAbort,
+    /// Calls [`std::process::abort`], which on Windows uses the [fastfail](https://docs.microsoft.com/en-us/cpp/intrinsics/fastfail?view=msvc-170)
+    /// intrinsic. This terminates the process with `STATUS_STACK_BUFFER_OVERRUN`
+    /// without invoking any exception handlers in the process, so it can't be
+    /// caught, it is only reported to Windows Error Reporting, if enabled
+    #[cfg(windows)]
+    FastFail,
+    /// Throws a C++ exception that is never caught, resulting in
+    /// `std::terminate` being called, which in turn aborts the process
+    ///
+    /// * `SIGABRT` on Linux and Macos
+    /// * The abort handler on Windows
+    #[cfg(feature = "cpp")]
+    CppTerminate,
     /// Raises an `EXC_GUARD` exception on Macos by placing a guard on a
     /// file descriptor then attempting to perform the operation that was guarded
     #[cfg(target_os = "macos")]
     Guard,
+    /// Raises an `EXC_RESOURCE` exception on Macos by enabling the CPU usage
+    /// monitor with a very low limit, then spinning until it is exceeded
+    #[cfg(target_os = "macos")]
+    ResourceCpu {
+        /// If true, the CPU usage monitor is configured to make the exception
+        /// fatal. If false, the exception is not fatal, and the process exits
+        /// normally after spinning for [`RESOURCE_CPU_DURATION`]
+        fatal: bool,
+    },
 }
 
 impl SadnessFlavor {
@@ -71,42 +165,192 @@ impl SadnessFlavor {
     pub unsafe fn make_sad(self) -> ! {
         match self {
             Self::Abort => raise_abort(),
-            Self::Segfault => raise_segfault(),
-            Self::DivideByZero => raise_floating_point_exception(),
-            Self::Illegal => raise_illegal_instruction(),
+            Self::Segfault { native_thread } => {
+                if !native_thread {
+                    raise_segfault()
+                } else {
+                    raise_in_native_thread(self, false)
+                }
+            }
+            Self::WriteReadOnly => raise_write_read_only(),
+            Self::DivideByZero { native_thread } => {
+                if !native_thread {
+                    raise_floating_point_exception()
+                } else {
+                    raise_in_native_thread(self, false)
+                }
+            }
+            Self::DivideOverflow => raise_divide_overflow(),
+            Self::Illegal { native_thread } => {
+                if !native_thread {
+                    raise_illegal_instruction()
+                } else {
+                    raise_in_native_thread(self, false)
+                }
+            }
             #[cfg(unix)]
-            Self::Bus => raise_bus(),
-            Self::Trap => raise_trap(),
-            #[allow(unused_variables)]
+            Self::Bus { native_thread } => {
+                if !native_thread {
+                    raise_bus()
+                } else {
+                    raise_in_native_thread(self, false)
+                }
+            }
+            Self::Trap {
+                native_thread,
+                #[cfg(windows)]
+                parameter,
+            } => {
+                if native_thread {
+                    raise_in_native_thread(self, false)
+                } else {
+                    #[cfg(windows)]
+                    if let Some(parameter) = parameter {
+                        raise_trap_with_parameter(parameter)
+                    }
+
+                    raise_trap()
+                }
+            }
+            #[cfg(windows)]
+            Self::DebugService { code } => raise_debug_service(code),
             Self::StackOverflow {
                 non_rust_thread,
                 long_jumps,
             } => {
-                if !non_rust_thread {
+                if !non_rust_thread || cfg!(windows) {
                     raise_stack_overflow()
                 } else {
-                    #[cfg(unix)]
-                    {
-                        raise_stack_overflow_in_non_rust_thread(long_jumps)
-                    }
-                    #[cfg(windows)]
-                    {
-                        raise_stack_overflow()
-                    }
+                    raise_in_native_thread(self, long_jumps)
                 }
             }
+            Self::SmashedStack => raise_smashed_stack(),
+            Self::AtExitCrash => raise_segfault_at_exit(),
             #[cfg(windows)]
             Self::Purecall => raise_purecall(),
             #[cfg(windows)]
             Self::InvalidParameter => raise_invalid_parameter(),
             #[cfg(windows)]
             Self::HeapCorruption => raise_heap_corruption(),
+            #[cfg(windows)]
+            Self::CrtAbort => raise_crt_abort(),
+            #[cfg(windows)]
+            Self::FastFail => raise_fast_fail(),
+            #[cfg(feature = "cpp")]
+            Self::CppTerminate => raise_cpp_terminate(),
             #[cfg(target_os = "macos")]
             Self::Guard => raise_guard_exception(),
+            #[cfg(target_os = "macos")]
+            Self::ResourceCpu { fatal } => raise_resource_cpu(fatal),
+        }
+    }
+
+    /// Every flavor available on the current platform, both on the calling
+    /// thread and on a native thread for the flavors that support it, see
+    /// [`run_all`]. On Windows, a stack overflow is only listed once, as it is
+    /// raised on the calling thread regardless of `non_rust_thread`
+    pub fn all() -> impl Iterator<Item = Self> {
+        let mut flavors = vec![Self::Abort];
+
+        for native_thread in [false, true] {
+            flavors.extend([
+                Self::Segfault { native_thread },
+                Self::DivideByZero { native_thread },
+                Self::Illegal { native_thread },
+                #[cfg(unix)]
+                Self::Bus { native_thread },
+                Self::Trap {
+                    native_thread,
+                    #[cfg(windows)]
+                    parameter: None,
+                },
+            ]);
+        }
+
+        flavors.extend([
+            Self::WriteReadOnly,
+            Self::DivideOverflow,
+            #[cfg(windows)]
+            Self::Trap {
+                native_thread: false,
+                parameter: Some(0x5ad),
+            },
+            #[cfg(windows)]
+            Self::DebugService { code: 0x5ad },
+            Self::StackOverflow {
+                non_rust_thread: false,
+                long_jumps: false,
+            },
+            // The same as the above on Windows, see `non_rust_thread`
+            #[cfg(unix)]
+            Self::StackOverflow {
+                non_rust_thread: true,
+                long_jumps: false,
+            },
+            Self::SmashedStack,
+            Self::AtExitCrash,
+            #[cfg(windows)]
+            Self::Purecall,
+            #[cfg(windows)]
+            Self::InvalidParameter,
+            #[cfg(windows)]
+            Self::HeapCorruption,
+            #[cfg(windows)]
+            Self::CrtAbort,
+            #[cfg(windows)]
+            Self::FastFail,
+            #[cfg(feature = "cpp")]
+            Self::CppTerminate,
+            #[cfg(target_os = "macos")]
+            Self::Guard,
+            #[cfg(target_os = "macos")]
+            Self::ResourceCpu { fatal: true },
+            #[cfg(target_os = "macos")]
+            Self::ResourceCpu { fatal: false },
+        ]);
+
+        flavors.into_iter()
+    }
+
+    /// Retrieves the same flavor, but with any request to raise it from a
+    /// native thread removed, so that it is raised on the calling thread
+    #[inline]
+    fn on_current_thread(self) -> Self {
+        match self {
+            Self::Segfault { .. } => Self::Segfault {
+                native_thread: false,
+            },
+            Self::DivideByZero { .. } => Self::DivideByZero {
+                native_thread: false,
+            },
+            Self::Illegal { .. } => Self::Illegal {
+                native_thread: false,
+            },
+            #[cfg(unix)]
+            Self::Bus { .. } => Self::Bus {
+                native_thread: false,
+            },
+            Self::Trap {
+                #[cfg(windows)]
+                parameter,
+                ..
+            } => Self::Trap {
+                native_thread: false,
+                #[cfg(windows)]
+                parameter,
+            },
+            Self::StackOverflow { long_jumps, .. } => Self::StackOverflow {
+                non_rust_thread: false,
+                long_jumps,
+            },
+            other => other,
         }
     }
 }
 
+mod run;
+pub use run::{run_all, Outcome, Termination, FLAVOR_ENV};
+
 /// [`SadnessFlavor::Abort`]
 ///
 /// # Safety
@@ -138,6 +382,53 @@ pub unsafe fn raise_segfault() -> ! {
     std::process::abort()
 }
 
+/// [`SadnessFlavor::AtExitCrash`]
+///
+/// Registers an `atexit` handler that calls [`raise_segfault`], then exits
+/// the process normally via `exit`, rather than [`std::process::exit`], as
+/// the latter doesn't run `atexit` handlers on Windows.
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes.
+pub unsafe fn raise_segfault_at_exit() -> ! {
+    extern "C" fn crash_at_exit() {
+        // SAFETY: intentionally crashing
+        unsafe { raise_segfault() }
+    }
+
+    if libc::atexit(crash_at_exit) != 0 {
+        panic!("failed to register atexit handler");
+    }
+
+    libc::exit(0)
+}
+
+/// A value placed in a read-only section of the binary, which
+/// [`raise_write_read_only`] attempts to write to
+static READ_ONLY: u32 = 0x5ad;
+
+/// The address that [`raise_write_read_only`] attempts to write to. Unlike
+/// [`SEGFAULT_ADDRESS`], this address is mapped, but only readable.
+#[inline]
+pub fn read_only_address() -> usize {
+    std::ptr::addr_of!(READ_ONLY) as usize
+}
+
+/// [`SadnessFlavor::WriteReadOnly`]
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes.
+pub unsafe fn raise_write_read_only() -> ! {
+    let ro_ptr = std::ptr::addr_of!(READ_ONLY).cast_mut();
+    std::ptr::write_volatile(ro_ptr, 1);
+
+    // If we actually get here that means the static was placed in a writable
+    // section, which is...unexpected
+    std::process::abort()
+}
+
 /// [`SadnessFlavor::DivideByZero`]
 ///
 /// # Safety
@@ -159,8 +450,27 @@ pub unsafe fn raise_floating_point_exception() -> ! {
         }
         #[cfg(any(target_arch = "arm", target_arch = "aarch64"))]
         {
-            // Unfortunately ARM by default will not raise SIGFPE on divide
-            // by 0 and just return 0, so we just explicitly raise here for now
+            // Unfortunately ARM will not raise SIGFPE on an integer divide by
+            // 0 and just returns 0, but some implementations support trapping
+            // on floating point divide by 0 if it is enabled
+            #[cfg(all(
+                target_arch = "aarch64",
+                any(target_os = "linux", target_os = "android")
+            ))]
+            if fpcr::enable_divide_by_zero_trap() {
+                let zero = std::ptr::read_volatile(&0.0f64);
+                let quotient: f64;
+                asm!(
+                    "fdiv {quotient:d}, {one:d}, {zero:d}",
+                    quotient = out(vreg) quotient,
+                    one = in(vreg) 1.0f64,
+                    zero = in(vreg) zero,
+                );
+
+                println!("floating point divide by zero did not trap: {quotient}");
+            }
+
+            // Trapping is unavailable, so just explicitly raise
             libc::raise(libc::SIGFPE);
             0
         }
@@ -171,6 +481,145 @@ pub unsafe fn raise_floating_point_exception() -> ! {
     std::process::abort()
 }
 
+/// [`SadnessFlavor::DivideOverflow`]
+///
+/// On x86 and `x86_64` this is an `idiv` of `i32::MIN` and `i64::MIN`
+/// respectively by -1, which raises a divide error.
+///
+/// ARM divide instructions saturate rather than trap, so the exception is
+/// raised explicitly instead: `SIGFPE` with a code of `FPE_INTOVF`, as if
+/// raised by the hardware, on Linux, `EXCEPTION_INT_OVERFLOW` on Windows, and
+/// a plain `SIGFPE` on Macos.
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes.
+pub unsafe fn raise_divide_overflow() -> ! {
+    #[cfg(target_arch = "x86_64")]
+    asm!(
+        "cqo",
+        "idiv {divisor}",
+        divisor = in(reg) -1i64,
+        inout("rax") i64::MIN => _,
+        out("rdx") _,
+    );
+    #[cfg(target_arch = "x86")]
+    asm!(
+        "cdq",
+        "idiv {divisor}",
+        divisor = in(reg) -1i32,
+        inout("eax") i32::MIN => _,
+        out("edx") _,
+    );
+    #[cfg(all(
+        any(target_arch = "arm", target_arch = "aarch64"),
+        any(target_os = "linux", target_os = "android")
+    ))]
+    {
+        /// From `<asm-generic/siginfo.h>`, not exposed by libc
+        const FPE_INTOVF: i32 = 2;
+
+        // Unlike `raise`, which always uses `SI_TKILL`, a signal queued to
+        // the calling process can have a code that is otherwise reserved for
+        // signals generated by the kernel
+        let mut info: libc::siginfo_t = std::mem::zeroed();
+        info.si_signo = libc::SIGFPE;
+        info.si_code = FPE_INTOVF;
+
+        libc::syscall(
+            libc::SYS_rt_tgsigqueueinfo,
+            libc::getpid(),
+            libc::syscall(libc::SYS_gettid),
+            libc::SIGFPE,
+            &info,
+        );
+    }
+    #[cfg(all(
+        any(target_arch = "arm", target_arch = "aarch64"),
+        target_os = "windows"
+    ))]
+    {
+        /// `EXCEPTION_INT_OVERFLOW`
+        const EXCEPTION_INT_OVERFLOW: u32 = 0xc0000095;
+
+        win_bindings::raise_exception(EXCEPTION_INT_OVERFLOW, 0, 0, std::ptr::null());
+    }
+    #[cfg(all(any(target_arch = "arm", target_arch = "aarch64"), target_os = "macos"))]
+    libc::raise(libc::SIGFPE);
+
+    std::process::abort()
+}
+
+/// Whether [`raise_floating_point_exception`] will cause a genuine hardware
+/// divide by zero exception (`FPE_INTDIV` or `FPE_FLTDIV`), or will instead
+/// fall back to `raise(SIGFPE)`
+///
+/// This is always true on x86. On aarch64 Linux this checks if trapping of
+/// floating point divide by zero can be enabled, which is optional and not
+/// supported by many implementations, and is always false on other ARM targets.
+pub fn hardware_divide_by_zero_available() -> bool {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        true
+    }
+    #[cfg(all(
+        target_arch = "aarch64",
+        any(target_os = "linux", target_os = "android")
+    ))]
+    {
+        // SAFETY: we restore the floating point control register after
+        // checking if trapping can be enabled
+        unsafe {
+            let prev = fpcr::read();
+            let available = fpcr::enable_divide_by_zero_trap();
+            fpcr::write(prev);
+            available
+        }
+    }
+    #[cfg(any(
+        target_arch = "arm",
+        all(
+            target_arch = "aarch64",
+            not(any(target_os = "linux", target_os = "android"))
+        )
+    ))]
+    {
+        false
+    }
+}
+
+/// Access to the aarch64 floating point control register
+#[cfg(all(
+    target_arch = "aarch64",
+    any(target_os = "linux", target_os = "android")
+))]
+mod fpcr {
+    use std::arch::asm;
+
+    /// Divide by zero floating point exception trap enable
+    const DZE: u64 = 1 << 9;
+
+    #[inline]
+    pub(crate) unsafe fn read() -> u64 {
+        let fpcr: u64;
+        asm!("mrs {}, fpcr", out(reg) fpcr);
+        fpcr
+    }
+
+    #[inline]
+    pub(crate) unsafe fn write(fpcr: u64) {
+        asm!("msr fpcr, {}", in(reg) fpcr);
+    }
+
+    /// Attempts to enable trapping on floating point divide by zero. The trap
+    /// enable bits are RAZ/WI on implementations that don't support trapping,
+    /// so we read back the register to determine if it was actually enabled.
+    pub(crate) unsafe fn enable_divide_by_zero_trap() -> bool {
+        write(read() | DZE);
+        read() & DZE != 0
+    }
+}
+
 /// [`SadnessFlavor::Illegal`]
 ///
 /// # Safety
@@ -244,6 +693,45 @@ pub unsafe fn raise_trap() -> ! {
     std::process::abort()
 }
 
+/// [`SadnessFlavor::Trap`] with a parameter
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes.
+#[cfg(target_os = "windows")]
+pub unsafe fn raise_trap_with_parameter(parameter: usize) -> ! {
+    /// `EXCEPTION_BREAKPOINT`
+    const EXCEPTION_BREAKPOINT: u32 = 0x80000003;
+
+    win_bindings::raise_exception(EXCEPTION_BREAKPOINT, 0, 1, &parameter);
+    std::process::abort()
+}
+
+/// [`SadnessFlavor::DebugService`]
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes.
+#[cfg(target_os = "windows")]
+pub unsafe fn raise_debug_service(code: u32) -> ! {
+    // The instruction after `int 2dh` is skipped when execution continues, so
+    // it is followed by an `int3`, same as `DbgBreakPointWithStatus`
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        asm!(
+            "int 0x2d",
+            "int3",
+            in("eax") code,
+            in("ecx") 0,
+            in("edx") 0,
+        );
+
+        std::process::abort()
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    raise_trap_with_parameter(code as usize);
+}
+
 /// [`SadnessFlavor::StackOverflow`]
 ///
 /// # Safety
@@ -271,66 +759,198 @@ pub unsafe fn raise_stack_overflow() -> ! {
     std::process::abort()
 }
 
-/// [`SadnessFlavor::StackOverflow`]
+/// The garbage address that [`raise_smashed_stack`] overwrites the return
+/// address with. It is aligned so that the jump to it faults on the fetch,
+/// rather than on the alignment, and canonical, so that on `x86_64` the `ret`
+/// itself doesn't fault
+#[cfg(target_pointer_width = "64")]
+pub const SMASHED_RETURN_ADDRESS: u64 = 0x0bad_c0de_0000;
+#[cfg(target_pointer_width = "32")]
+pub const SMASHED_RETURN_ADDRESS: u32 = 0xdead_bee0;
+
+/// [`SadnessFlavor::SmashedStack`]
 ///
-/// This is raised inside of a non-Rust `std::thread::Thread` to ensure that
-/// alternate stacks apply to all threads, even ones not created from Rust
+/// The frame is created entirely in assembly, as the layout of a frame created
+/// by the compiler, and thus the offset of the saved return address from a
+/// local buffer, isn't known. A call pushes, or saves, the return address,
+/// then the callee reserves a 2 word buffer below it, writes 3 words to the
+/// buffer, the last of which lands on the saved return address, and returns.
 ///
 /// # Safety
 ///
 /// This is not safe. It intentionally crashes.
-#[cfg(unix)]
-pub unsafe fn raise_stack_overflow_in_non_rust_thread(uses_longjmp: bool) -> ! {
-    let mut native: libc::pthread_t = std::mem::zeroed();
-    let mut attr: libc::pthread_attr_t = std::mem::zeroed();
-
-    assert_eq!(
-        libc::pthread_attr_setstacksize(&mut attr, 2 * 1024 * 1024),
-        0,
-        "failed to set thread stack size",
+pub unsafe fn raise_smashed_stack() -> ! {
+    #[cfg(target_arch = "x86_64")]
+    asm!(
+        "call 2f",
+        "ud2",
+        "2:",
+        "sub rsp, 16",
+        "mov qword ptr [rsp], 0",
+        "mov qword ptr [rsp + 8], 0",
+        "mov qword ptr [rsp + 16], rax",
+        "add rsp, 16",
+        "ret",
+        in("rax") SMASHED_RETURN_ADDRESS,
+        options(noreturn),
+    );
+    #[cfg(target_arch = "x86")]
+    asm!(
+        "call 2f",
+        "ud2",
+        "2:",
+        "sub esp, 8",
+        "mov dword ptr [esp], 0",
+        "mov dword ptr [esp + 4], 0",
+        "mov dword ptr [esp + 8], eax",
+        "add esp, 8",
+        "ret",
+        in("eax") SMASHED_RETURN_ADDRESS,
+        options(noreturn),
+    );
+    // The return address is passed in the link register, so the callee saves
+    // it above the buffer, same as a non-leaf function does
+    #[cfg(target_arch = "aarch64")]
+    asm!(
+        "bl 2f",
+        "udf #0",
+        "2:",
+        "sub sp, sp, #32",
+        "stp x29, x30, [sp, #16]",
+        "stp xzr, xzr, [sp]",
+        "str x0, [sp, #24]",
+        "ldp x29, x30, [sp, #16]",
+        "add sp, sp, #32",
+        "ret",
+        in("x0") SMASHED_RETURN_ADDRESS,
+        options(noreturn),
+    );
+    #[cfg(target_arch = "arm")]
+    asm!(
+        "bl 2f",
+        "udf #0",
+        "2:",
+        "push {{r11, lr}}",
+        "sub sp, sp, #8",
+        "str r1, [sp]",
+        "str r1, [sp, #4]",
+        "str r0, [sp, #12]",
+        "add sp, sp, #8",
+        "pop {{r11, pc}}",
+        in("r0") SMASHED_RETURN_ADDRESS,
+        in("r1") 0,
+        options(noreturn),
     );
+}
 
+/// Raises the specified flavor inside of a non-Rust [`std::thread::Thread`] to
+/// ensure that crash handling applies to all threads, even ones not created
+/// from Rust, eg. alternate stacks being installed for threads created via
+/// `pthread_create` rather than [`std::thread::spawn`]
+///
+/// If a signal handler will longjump rather than terminate the process, we
+/// can't wait on the thread as we would normally as it would deadlock, in which
+/// case `long_jumps` must be true and the calling code is responsible for
+/// cleaning up the thread.
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes.
+pub unsafe fn raise_in_native_thread(flavor: SadnessFlavor, long_jumps: bool) -> ! {
     use std::sync;
 
-    let pair = sync::Arc::new((sync::Mutex::new(false), sync::Condvar::new()));
-    let tpair = pair.clone();
+    type Started = sync::Arc<(sync::Mutex<bool>, sync::Condvar)>;
+
+    struct ThreadParams {
+        flavor: SadnessFlavor,
+        started: Started,
+    }
+
+    /// Notifies the spawning thread that we've started, then makes ourselves
+    /// sad
+    unsafe fn start(params: *mut std::ffi::c_void) -> ! {
+        let params = Box::from_raw(params.cast::<ThreadParams>());
 
-    extern "C" fn thread_start(arg: *mut libc::c_void) -> *mut libc::c_void {
         {
-            let tpair =
-                unsafe { sync::Arc::from_raw(arg as *const (sync::Mutex<bool>, sync::Condvar)) };
-            let (lock, cvar) = &*tpair;
+            let (lock, cvar) = &*params.started;
             let mut started = lock.lock().unwrap();
             *started = true;
             cvar.notify_one();
         }
 
-        unsafe { raise_stack_overflow() };
+        params.flavor.make_sad()
     }
 
-    let ret = libc::pthread_create(
-        &mut native,
-        &attr,
-        thread_start,
-        sync::Arc::into_raw(tpair) as *mut _,
-    );
+    let pair: Started = sync::Arc::new((sync::Mutex::new(false), sync::Condvar::new()));
 
-    // We might not get here, but that's ok
-    assert_eq!(
-        libc::pthread_attr_destroy(&mut attr),
-        0,
-        "failed to destroy thread attributes"
-    );
-    assert_eq!(ret, 0, "pthread_create failed");
+    let params = Box::into_raw(Box::new(ThreadParams {
+        flavor: flavor.on_current_thread(),
+        started: pair.clone(),
+    }));
+
+    #[cfg(unix)]
+    {
+        extern "C" fn thread_start(arg: *mut libc::c_void) -> *mut libc::c_void {
+            unsafe { start(arg.cast()) }
+        }
+
+        let mut native: libc::pthread_t = std::mem::zeroed();
+        let mut attr: libc::pthread_attr_t = std::mem::zeroed();
 
-    // Note if we're doing longjmp shenanigans, we can't do thread join, that
-    // has to be handled by the calling code
-    if !uses_longjmp {
         assert_eq!(
-            libc::pthread_join(native, std::ptr::null_mut()),
+            libc::pthread_attr_setstacksize(&mut attr, 2 * 1024 * 1024),
             0,
-            "failed to join"
+            "failed to set thread stack size",
         );
+
+        let ret = libc::pthread_create(&mut native, &attr, thread_start, params.cast());
+
+        // We might not get here, but that's ok
+        assert_eq!(
+            libc::pthread_attr_destroy(&mut attr),
+            0,
+            "failed to destroy thread attributes"
+        );
+        assert_eq!(ret, 0, "pthread_create failed");
+
+        // Note if we're doing longjmp shenanigans, we can't do thread join, that
+        // has to be handled by the calling code
+        if !long_jumps {
+            assert_eq!(
+                libc::pthread_join(native, std::ptr::null_mut()),
+                0,
+                "failed to join"
+            );
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use win_bindings::*;
+
+        unsafe extern "system" fn thread_start(arg: *mut std::ffi::c_void) -> u32 {
+            start(arg)
+        }
+
+        let handle = create_thread(
+            std::ptr::null(),
+            2 * 1024 * 1024,
+            Some(thread_start),
+            params.cast(),
+            0,
+            std::ptr::null_mut(),
+        );
+        assert!(handle != 0, "CreateThread failed");
+
+        // Same as above, we can't wait on the thread if it's going to be
+        // longjmp'ed out of
+        if !long_jumps {
+            assert_eq!(
+                wait_for_single_object(handle, INFINITE),
+                0,
+                "failed to wait on thread"
+            );
+        }
     }
 
     let (lock, cvar) = &*pair;
@@ -345,6 +965,25 @@ pub unsafe fn raise_stack_overflow_in_non_rust_thread(uses_longjmp: bool) -> ! {
     loop {}
 }
 
+/// [`SadnessFlavor::StackOverflow`]
+///
+/// This is raised inside of a non-Rust `std::thread::Thread` to ensure that
+/// alternate stacks apply to all threads, even ones not created from Rust
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes.
+#[cfg(unix)]
+pub unsafe fn raise_stack_overflow_in_non_rust_thread(uses_longjmp: bool) -> ! {
+    raise_in_native_thread(
+        SadnessFlavor::StackOverflow {
+            non_rust_thread: false,
+            long_jumps: uses_longjmp,
+        },
+        uses_longjmp,
+    )
+}
+
 /// [`SadnessFlavor::StackOverflow`]
 ///
 /// # Safety
@@ -367,18 +1006,50 @@ pub unsafe fn raise_stack_overflow_in_non_rust_thread_longjmp() -> ! {
     raise_stack_overflow_in_non_rust_thread(true)
 }
 
+/// [`SadnessFlavor::CppTerminate`]
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes.
+#[cfg(feature = "cpp")]
+pub unsafe fn raise_cpp_terminate() -> ! {
+    extern "C" {
+        fn sadness_throw_uncaught();
+    }
+
+    sadness_throw_uncaught();
+
+    std::process::abort()
+}
+
 /// [`SadnessFlavor::Purecall`]
 ///
+/// On MSVC this calls a virtual method from a base class constructor, the same
+/// way pure virtual calls occur in real code. With the `purecall-direct`
+/// feature, or when targeting windows-gnu, the CRT `_purecall` is called directly.
+///
 /// # Safety
 ///
 /// This is not safe. It intentionally crashes.
 #[cfg(target_os = "windows")]
 pub unsafe fn raise_purecall() -> ! {
-    extern "C" {
-        fn _purecall() -> i32;
+    #[cfg(all(target_env = "msvc", not(feature = "purecall-direct")))]
+    {
+        extern "C" {
+            fn sadness_pure_virtual_call();
+        }
+
+        sadness_pure_virtual_call();
     }
 
-    _purecall();
+    #[cfg(any(not(target_env = "msvc"), feature = "purecall-direct"))]
+    {
+        extern "C" {
+            fn _purecall() -> i32;
+        }
+
+        _purecall();
+    }
 
     std::process::abort()
 }
@@ -428,6 +1099,26 @@ pub unsafe fn raise_heap_corruption() -> ! {
     std::process::abort()
 }
 
+/// [`SadnessFlavor::CrtAbort`]
+///
+/// # Safety
+///
+/// This is not safe. It intentionally emits `SIGABRT`.
+#[cfg(target_os = "windows")]
+pub unsafe fn raise_crt_abort() -> ! {
+    libc::abort()
+}
+
+/// [`SadnessFlavor::FastFail`]
+///
+/// # Safety
+///
+/// This is not safe. It intentionally terminates the process.
+#[cfg(target_os = "windows")]
+pub unsafe fn raise_fast_fail() -> ! {
+    std::process::abort()
+}
+
 /// The identifier used when guarding the file resource when raising
 /// an `EXC_GUARD` exception via [`raise_guard_exception`]
 #[cfg(target_os = "macos")]
@@ -483,3 +1174,57 @@ pub unsafe fn raise_guard_exception() -> ! {
 
     std::process::abort()
 }
+
+/// The amount of time [`raise_resource_cpu`] spins, which is far longer than
+/// is needed to trip the CPU usage monitor
+#[cfg(target_os = "macos")]
+pub const RESOURCE_CPU_DURATION: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// [`SadnessFlavor::ResourceCpu`]
+///
+/// # Safety
+///
+/// This is not safe. It intentionally crashes if `fatal` is true.
+#[cfg(target_os = "macos")]
+pub unsafe fn raise_resource_cpu(fatal: bool) -> ! {
+    /// Signature of `proc_set_cpumon_params` and `proc_set_cpumon_params_fatal`
+    /// from `libproc_internal.h`
+    type SetCpumonParams =
+        unsafe extern "C" fn(pid: libc::pid_t, percentage: i32, interval: i32) -> i32;
+
+    // The SDK doesn't have these functions to link against, so we need to
+    // look them up by name before invoking them
+    let name: &[u8] = if fatal {
+        b"proc_set_cpumon_params_fatal\0"
+    } else {
+        b"proc_set_cpumon_params\0"
+    };
+
+    let set_cpumon_params = libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr().cast());
+    assert!(
+        !set_cpumon_params.is_null(),
+        "failed to find CPU usage monitor function, unable to crash"
+    );
+    let set_cpumon_params: SetCpumonParams = std::mem::transmute(set_cpumon_params);
+
+    // Trip the monitor if we use more than 1% of the CPU over a 1 second interval
+    assert_eq!(
+        set_cpumon_params(libc::getpid(), 1, 1),
+        0,
+        "failed to configure CPU usage monitor"
+    );
+
+    let start = std::time::Instant::now();
+    let mut spins = 0u64;
+    while start.elapsed() < RESOURCE_CPU_DURATION {
+        spins = std::hint::black_box(spins.wrapping_add(1));
+    }
+
+    if !fatal {
+        println!("CPU usage monitor was not fatal after {spins} spins");
+        std::process::exit(0);
+    }
+
+    // If we get here the monitor either didn't trip, or wasn't actually fatal
+    std::process::abort()
+}
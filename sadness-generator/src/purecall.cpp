@@ -0,0 +1,27 @@
+// Reproduces a pure virtual call the way they occur in the wild, by calling a
+// virtual method from a base class constructor, at which point the vtable slot
+// for the method points to the CRT's `_purecall`
+
+struct Base;
+
+// Calling the pure method from a separate, non-inlined function prevents the
+// compiler from diagnosing or devirtualizing the call
+__declspec(noinline) static void call_pure(Base* base);
+
+struct Base {
+    Base() { call_pure(this); }
+    virtual ~Base() {}
+    virtual void pure() = 0;
+};
+
+struct Derived : Base {
+    void pure() override {}
+};
+
+__declspec(noinline) static void call_pure(Base* base) {
+    base->pure();
+}
+
+extern "C" void sadness_pure_virtual_call() {
+    Derived derived;
+}
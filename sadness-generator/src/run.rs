@@ -0,0 +1,250 @@
+use crate::SadnessFlavor;
+use std::{
+    process::{Command, ExitStatus, Stdio},
+    time::{Duration, Instant},
+};
+
+/// The environment variable used by [`run_all`] to select the flavor a child
+/// process raises, by its index in [`SadnessFlavor::all`]
+pub const FLAVOR_ENV: &str = "SADNESS_GENERATOR_FLAVOR";
+
+/// The maximum amount of time a child process is given to terminate before it
+/// is killed, it is generous as eg. [`SadnessFlavor::ResourceCpu`] needs to
+/// spin for a while before the exception is raised
+const CHILD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How a child process terminated
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Termination {
+    /// The process was terminated by the specified signal
+    #[cfg(unix)]
+    Signal(i32),
+    /// The process exited with the specified code. On Windows, this is the
+    /// `NTSTATUS` of the exception that terminated the process, if any
+    Exit(i32),
+}
+
+impl Termination {
+    fn from_status(status: ExitStatus) -> Option<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            if let Some(signal) = status.signal() {
+                return Some(Self::Signal(signal));
+            }
+        }
+
+        status.code().map(Self::Exit)
+    }
+}
+
+impl std::fmt::Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(unix)]
+            Self::Signal(signal) => write!(f, "signal {signal}"),
+            // NTSTATUS values are far more recognizable in hex
+            #[cfg(windows)]
+            Self::Exit(code) => write!(f, "exit code {:#010x}", *code as u32),
+            #[cfg(not(windows))]
+            Self::Exit(code) => write!(f, "exit code {code}"),
+        }
+    }
+}
+
+/// The outcome of raising a single [`SadnessFlavor`] in a child process
+#[derive(Debug)]
+pub enum Outcome {
+    /// The child terminated the way the flavor is expected to
+    Expected(Termination),
+    /// The child terminated, but not in a way the flavor is expected to
+    Unexpected(Termination),
+    /// The child didn't terminate in time, and was killed
+    TimedOut,
+    /// The child couldn't be spawned or waited on
+    Error(std::io::Error),
+}
+
+impl Outcome {
+    /// True if the child terminated the way the flavor is expected to
+    #[inline]
+    pub fn is_expected(&self) -> bool {
+        matches!(self, Self::Expected(_))
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Expected(term) => write!(f, "expected {term}"),
+            Self::Unexpected(term) => write!(f, "unexpected {term}"),
+            Self::TimedOut => f.write_str("timed out"),
+            Self::Error(err) => write!(f, "error: {err}"),
+        }
+    }
+}
+
+impl SadnessFlavor {
+    /// The ways the process may terminate after raising this flavor, when no
+    /// crash handler is installed
+    fn expected_terminations(self) -> &'static [Termination] {
+        #[cfg(unix)]
+        {
+            use libc::{SIGABRT, SIGBUS, SIGFPE, SIGILL, SIGSEGV, SIGTRAP};
+            use Termination::Signal;
+
+            // Macos reports some invalid accesses as `SIGBUS` rather than
+            // `SIGSEGV`, depending on the kind of mapping being accessed
+            #[cfg(target_os = "macos")]
+            const SEGV: &[Termination] = &[Signal(SIGSEGV), Signal(SIGBUS)];
+            #[cfg(not(target_os = "macos"))]
+            const SEGV: &[Termination] = &[Signal(SIGSEGV)];
+
+            match self {
+                Self::Abort => &[Signal(SIGABRT)],
+                Self::Segfault { .. } | Self::AtExitCrash => &[Signal(SIGSEGV)],
+                Self::WriteReadOnly | Self::SmashedStack => SEGV,
+                Self::DivideByZero { .. } | Self::DivideOverflow => &[Signal(SIGFPE)],
+                Self::Illegal { .. } => &[Signal(SIGILL)],
+                Self::Bus { .. } => &[Signal(SIGBUS)],
+                Self::Trap { .. } => &[Signal(SIGTRAP)],
+                // Rust threads have a guard page handler installed by std that
+                // reports the overflow then aborts, native threads don't
+                Self::StackOverflow {
+                    non_rust_thread: false,
+                    ..
+                } => &[Signal(SIGABRT)],
+                Self::StackOverflow { .. } => SEGV,
+                #[cfg(feature = "cpp")]
+                Self::CppTerminate => &[Signal(SIGABRT)],
+                #[cfg(target_os = "macos")]
+                Self::Guard | Self::ResourceCpu { fatal: true } => &[Signal(libc::SIGKILL)],
+                #[cfg(target_os = "macos")]
+                Self::ResourceCpu { fatal: false } => &[Termination::Exit(0)],
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            use Termination::Exit;
+
+            const ACCESS_VIOLATION: i32 = 0xc0000005_u32 as i32;
+            const BREAKPOINT: i32 = 0x80000003_u32 as i32;
+            const STACK_BUFFER_OVERRUN: i32 = 0xc0000409_u32 as i32;
+
+            // The CRT's `abort` exits with 3, unless it is configured to
+            // report the abort to WER, in which case it fastfails
+            const ABORT: &[Termination] = &[Exit(3), Exit(0x40000015), Exit(STACK_BUFFER_OVERRUN)];
+
+            match self {
+                Self::Abort | Self::CrtAbort | Self::Purecall => ABORT,
+                #[cfg(feature = "cpp")]
+                Self::CppTerminate => ABORT,
+                Self::Segfault { .. }
+                | Self::WriteReadOnly
+                | Self::SmashedStack
+                | Self::AtExitCrash => &[Exit(ACCESS_VIOLATION)],
+                // ARM doesn't trap on division by zero, so `SIGFPE` is raised
+                // via the CRT instead, whose default action exits with 3
+                Self::DivideByZero { .. } => &[Exit(0xc0000094_u32 as i32), Exit(3)],
+                Self::DivideOverflow => &[Exit(0xc0000095_u32 as i32)],
+                Self::Illegal { .. } => &[Exit(0xc000001d_u32 as i32)],
+                Self::Trap { .. } | Self::DebugService { .. } => &[Exit(BREAKPOINT)],
+                Self::StackOverflow { .. } => &[Exit(0xc00000fd_u32 as i32)],
+                Self::InvalidParameter | Self::FastFail => &[Exit(STACK_BUFFER_OVERRUN)],
+                Self::HeapCorruption => &[Exit(0xc0000374_u32 as i32)],
+            }
+        }
+    }
+}
+
+/// Raises every flavor in [`SadnessFlavor::all`], each in its own child process,
+/// calling `report` with the outcome of each one in turn.
+///
+/// The children are spawned by re-executing the current executable, with the
+/// same arguments, and [`FLAVOR_ENV`] set to the flavor to raise. This
+/// function must therefore be called before the calling program does anything
+/// else, as when [`FLAVOR_ENV`] is set, it raises the selected flavor and never
+/// returns.
+///
+/// ```no_run
+/// let mut failures = 0;
+/// sadness_generator::run_all(|flavor, outcome| {
+///     println!("{flavor:?}: {outcome}");
+///     failures += usize::from(!outcome.is_expected());
+/// });
+/// std::process::exit(failures.min(1) as i32);
+/// ```
+pub fn run_all(mut report: impl FnMut(SadnessFlavor, Outcome)) {
+    if let Some(index) = std::env::var_os(FLAVOR_ENV) {
+        let flavor = index
+            .to_str()
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| SadnessFlavor::all().nth(index))
+            .unwrap_or_else(|| panic!("invalid {FLAVOR_ENV} {index:?}"));
+
+        // SAFETY: this is the whole point
+        unsafe { flavor.make_sad() }
+    }
+
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(err) => {
+            for flavor in SadnessFlavor::all() {
+                report(
+                    flavor,
+                    Outcome::Error(std::io::Error::new(err.kind(), err.to_string())),
+                );
+            }
+            return;
+        }
+    };
+
+    for (index, flavor) in SadnessFlavor::all().enumerate() {
+        let outcome = match run_one(&exe, index) {
+            Ok(Some(term)) => {
+                if flavor.expected_terminations().contains(&term) {
+                    Outcome::Expected(term)
+                } else {
+                    Outcome::Unexpected(term)
+                }
+            }
+            Ok(None) => Outcome::TimedOut,
+            Err(err) => Outcome::Error(err),
+        };
+
+        report(flavor, outcome);
+    }
+}
+
+/// Runs a single child process, returning `None` if it had to be killed
+fn run_one(exe: &std::path::Path, index: usize) -> std::io::Result<Option<Termination>> {
+    let mut child = Command::new(exe)
+        .args(std::env::args_os().skip(1))
+        .env(FLAVOR_ENV, index.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Termination::from_status(status).map(Some).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!("unable to determine how the child terminated: {status}"),
+                )
+            });
+        }
+
+        if start.elapsed() > CHILD_TIMEOUT {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,12 @@
+#include <stdexcept>
+
+[[noreturn]] static void throw_uncaught() {
+    throw std::runtime_error("this exception is never caught");
+}
+
+// The exception is thrown through a `noexcept` boundary, which guarantees that
+// no handler is found and `std::terminate` is called, regardless of what frames
+// are further up the stack
+extern "C" void sadness_throw_uncaught() noexcept {
+    throw_uncaught();
+}
@@ -7,6 +7,15 @@
 )]
 #[link(name = "kernel32", kind = "raw-dylib")]
 extern "system" {
+    #[link_name = "CreateThread"]
+    pub fn create_thread(
+        thread_attributes: *const SecurityAttributes,
+        stack_size: usize,
+        start_address: LpthreadStartRoutine,
+        parameter: *const ::core::ffi::c_void,
+        creation_flags: ThreadCreationFlags::Enum,
+        thread_id: *mut u32,
+    ) -> Handle;
     #[link_name = "GetProcAddress"]
     pub fn get_proc_address(module: Hmodule, proc_name: Pcstr) -> Farproc;
     #[link_name = "GetProcessHeap"]
@@ -19,12 +28,38 @@ extern "system" {
     ) -> Bool;
     #[link_name = "LoadLibraryA"]
     pub fn load_library_a(lib_file_name: Pcstr) -> Hmodule;
+    #[link_name = "RaiseException"]
+    pub fn raise_exception(
+        exception_code: u32,
+        exception_flags: u32,
+        number_of_arguments: u32,
+        arguments: *const usize,
+    );
+    #[link_name = "WaitForSingleObject"]
+    pub fn wait_for_single_object(handle: Handle, milliseconds: u32) -> WaitEvent::Enum;
 }
+pub const INFINITE: u32 = 4294967295;
 pub type Bool = i32;
 pub type Farproc = ::core::option::Option<unsafe extern "system" fn() -> isize>;
+pub type Handle = isize;
 pub mod HeapFlags {
     pub type Enum = u32;
 }
 pub type HeapHandle = isize;
 pub type Hmodule = isize;
+pub type LpthreadStartRoutine = ::core::option::Option<
+    unsafe extern "system" fn(thread_parameter: *mut ::core::ffi::c_void) -> u32,
+>;
 pub type Pcstr = *const u8;
+#[repr(C)]
+pub struct SecurityAttributes {
+    pub length: u32,
+    pub security_descriptor: *mut ::core::ffi::c_void,
+    pub inherit_handle: Bool,
+}
+pub mod ThreadCreationFlags {
+    pub type Enum = u32;
+}
+pub mod WaitEvent {
+    pub type Enum = u32;
+}